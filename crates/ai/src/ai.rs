@@ -6,3 +6,4 @@ pub mod prompts;
 pub mod providers;
 #[cfg(any(test, feature = "test-support"))]
 pub mod test;
+pub mod tokenizer;
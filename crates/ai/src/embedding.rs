@@ -71,6 +71,11 @@ impl Embedding {
 #[async_trait]
 pub trait EmbeddingProvider: CredentialProvider {
     fn base_model(&self) -> Box<dyn LanguageModel>;
+    /// Identifies the embeddings produced by this provider. Embeddings can only be compared
+    /// with embeddings having the same identifier.
+    fn embedding_model_id(&self) -> String {
+        self.base_model().name()
+    }
     async fn embed_batch(&self, spans: Vec<String>) -> Result<Vec<Embedding>>;
     fn max_tokens_per_batch(&self) -> usize;
     fn rate_limit_expiration(&self) -> Option<Instant>;
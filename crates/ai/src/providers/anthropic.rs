@@ -0,0 +1,447 @@
+use anyhow::{anyhow, Result};
+use futures::{
+    future::BoxFuture, io::BufReader, stream::BoxStream, AsyncBufReadExt, AsyncReadExt, FutureExt,
+    Stream, StreamExt,
+};
+use gpui::{AppContext, BackgroundExecutor};
+use isahc::{http::StatusCode, Request, RequestExt};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{env, io, sync::Arc};
+use util::ResultExt;
+
+use crate::{
+    auth::{CredentialProvider, ProviderCredential},
+    completion::{CompletionProvider, CompletionRequest},
+    models::{LanguageModel, TruncationDirection},
+    providers::open_ai::{OpenAiRequest, Role},
+    tokenizer::Tokenizer,
+};
+
+pub const ANTHROPIC_API_URL: &'static str = "https://api.anthropic.com/v1";
+const ANTHROPIC_API_VERSION: &'static str = "2023-06-01";
+/// The maximum number of tokens Anthropic's models can generate in a single response.
+const MAX_OUTPUT_TOKENS: usize = 4096;
+
+/// A model served by Anthropic's messages API.
+///
+/// Anthropic doesn't publish a tokenizer for its current models, so token counts
+/// are estimated from the length of the text.
+#[derive(Clone)]
+pub struct AnthropicLanguageModel {
+    name: String,
+    tokenizer: Tokenizer,
+}
+
+impl AnthropicLanguageModel {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            tokenizer: Tokenizer::anthropic(),
+        }
+    }
+}
+
+impl LanguageModel for AnthropicLanguageModel {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn count_tokens(&self, content: &str) -> Result<usize> {
+        Ok(self.tokenizer.count_tokens(content))
+    }
+
+    fn truncate(
+        &self,
+        content: &str,
+        length: usize,
+        direction: TruncationDirection,
+    ) -> Result<String> {
+        self.tokenizer.truncate(content, length, direction)
+    }
+
+    fn capacity(&self) -> Result<usize> {
+        if self.name.starts_with("claude-2.0") || self.name.starts_with("claude-instant") {
+            Ok(100_000)
+        } else {
+            Ok(200_000)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct AnthropicRequest {
+    model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    max_tokens: usize,
+    stream: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+    temperature: f32,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct AnthropicMessage {
+    role: &'static str,
+    content: String,
+}
+
+impl AnthropicRequest {
+    /// Converts a chat request into the shape expected by the messages API, where the system
+    /// prompt is passed separately and user and assistant messages have to alternate,
+    /// starting with the user. Fails if no user message remains, as the API would reject it.
+    fn from_open_ai(request: OpenAiRequest) -> Result<Self> {
+        let mut system = Vec::new();
+        let mut messages: Vec<AnthropicMessage> = Vec::new();
+        for message in request.messages {
+            let role = match message.role {
+                Role::System => {
+                    system.push(message.content);
+                    continue;
+                }
+                Role::User => "user",
+                Role::Assistant => "assistant",
+            };
+            if message.content.trim().is_empty() {
+                continue;
+            }
+            match messages.last_mut() {
+                Some(last) if last.role == role => {
+                    last.content.push_str("\n\n");
+                    last.content.push_str(&message.content);
+                }
+                None if role == "assistant" => {}
+                _ => messages.push(AnthropicMessage {
+                    role,
+                    content: message.content,
+                }),
+            }
+        }
+
+        if messages.is_empty() {
+            return Err(anyhow!("the request doesn't contain a user message"));
+        }
+
+        Ok(Self {
+            model: request.model,
+            system: (!system.is_empty()).then(|| system.join("\n\n")),
+            messages,
+            max_tokens: MAX_OUTPUT_TOKENS,
+            stream: true,
+            stop_sequences: request.stop,
+            temperature: request.temperature.clamp(0., 1.),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicStreamEvent {
+    ContentBlockDelta {
+        delta: AnthropicDelta,
+    },
+    MessageStop,
+    Error {
+        error: AnthropicError,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicDelta {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicError {
+    message: String,
+}
+
+pub async fn stream_completion(
+    credential: ProviderCredential,
+    executor: BackgroundExecutor,
+    request: Box<dyn CompletionRequest>,
+) -> Result<impl Stream<Item = Result<String>>> {
+    let api_key = match credential {
+        ProviderCredential::Credentials { api_key } => api_key,
+        _ => {
+            return Err(anyhow!("no credentials provider for completion"));
+        }
+    };
+
+    // Requests are built in OpenAI's chat format, which is translated here.
+    let request = AnthropicRequest::from_open_ai(serde_json::from_str(&request.data()?)?)?;
+    let mut response = Request::post(format!("{ANTHROPIC_API_URL}/messages"))
+        .header("Content-Type", "application/json")
+        .header("X-Api-Key", api_key)
+        .header("Anthropic-Version", ANTHROPIC_API_VERSION)
+        .body(serde_json::to_string(&request)?)?
+        .send_async()
+        .await?;
+
+    if response.status() == StatusCode::OK {
+        let (tx, rx) = futures::channel::mpsc::unbounded::<Result<String>>();
+        executor
+            .spawn(async move {
+                let mut lines = BufReader::new(response.body_mut()).lines();
+
+                fn parse_line(
+                    line: Result<String, io::Error>,
+                ) -> Result<Option<AnthropicStreamEvent>> {
+                    if let Some(data) = line?.strip_prefix("data: ") {
+                        Ok(Some(serde_json::from_str(data)?))
+                    } else {
+                        Ok(None)
+                    }
+                }
+
+                while let Some(line) = lines.next().await {
+                    let text = match parse_line(line) {
+                        Ok(Some(AnthropicStreamEvent::ContentBlockDelta { delta })) => {
+                            Ok(delta.text)
+                        }
+                        Ok(Some(AnthropicStreamEvent::MessageStop)) => break,
+                        Ok(Some(AnthropicStreamEvent::Error { error })) => {
+                            Err(anyhow!("Anthropic API error: {}", error.message))
+                        }
+                        Ok(Some(AnthropicStreamEvent::Other) | None) => continue,
+                        Err(error) => Err(error),
+                    };
+                    let is_error = text.is_err();
+                    if tx.unbounded_send(text).is_err() || is_error {
+                        break;
+                    }
+                }
+
+                anyhow::Ok(())
+            })
+            .detach();
+
+        Ok(rx)
+    } else {
+        let mut body = String::new();
+        response.body_mut().read_to_string(&mut body).await?;
+
+        #[derive(Deserialize)]
+        struct AnthropicErrorResponse {
+            error: AnthropicError,
+        }
+
+        match serde_json::from_str::<AnthropicErrorResponse>(&body) {
+            Ok(response) if !response.error.message.is_empty() => Err(anyhow!(
+                "Failed to connect to Anthropic API: {}",
+                response.error.message,
+            )),
+            _ => Err(anyhow!(
+                "Failed to connect to Anthropic API: {} {}",
+                response.status(),
+                body,
+            )),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AnthropicCompletionProvider {
+    model: AnthropicLanguageModel,
+    credential: Arc<RwLock<ProviderCredential>>,
+    executor: BackgroundExecutor,
+}
+
+impl AnthropicCompletionProvider {
+    pub fn new(model_name: String, executor: BackgroundExecutor) -> Self {
+        Self {
+            model: AnthropicLanguageModel::new(model_name),
+            credential: Arc::new(RwLock::new(ProviderCredential::NoCredentials)),
+            executor,
+        }
+    }
+}
+
+impl CredentialProvider for AnthropicCompletionProvider {
+    fn has_credentials(&self) -> bool {
+        match *self.credential.read() {
+            ProviderCredential::Credentials { .. } => true,
+            _ => false,
+        }
+    }
+
+    fn retrieve_credentials(&self, cx: &mut AppContext) -> BoxFuture<ProviderCredential> {
+        let existing_credential = self.credential.read().clone();
+        let retrieved_credential = match existing_credential {
+            ProviderCredential::Credentials { .. } => {
+                return async move { existing_credential }.boxed()
+            }
+            _ => {
+                if let Some(api_key) = env::var("ANTHROPIC_API_KEY").log_err() {
+                    async move { ProviderCredential::Credentials { api_key } }.boxed()
+                } else {
+                    let credentials = cx.read_credentials(ANTHROPIC_API_URL);
+                    async move {
+                        if let Some(Some((_, api_key))) = credentials.await.log_err() {
+                            if let Some(api_key) = String::from_utf8(api_key).log_err() {
+                                ProviderCredential::Credentials { api_key }
+                            } else {
+                                ProviderCredential::NoCredentials
+                            }
+                        } else {
+                            ProviderCredential::NoCredentials
+                        }
+                    }
+                    .boxed()
+                }
+            }
+        };
+
+        async move {
+            let retrieved_credential = retrieved_credential.await;
+            *self.credential.write() = retrieved_credential.clone();
+            retrieved_credential
+        }
+        .boxed()
+    }
+
+    fn save_credentials(
+        &self,
+        cx: &mut AppContext,
+        credential: ProviderCredential,
+    ) -> BoxFuture<()> {
+        *self.credential.write() = credential.clone();
+        let write_credentials = match credential {
+            ProviderCredential::Credentials { api_key } => {
+                Some(cx.write_credentials(ANTHROPIC_API_URL, "X-Api-Key", api_key.as_bytes()))
+            }
+            _ => None,
+        };
+
+        async move {
+            if let Some(write_credentials) = write_credentials {
+                write_credentials.await.log_err();
+            }
+        }
+        .boxed()
+    }
+
+    fn delete_credentials(&self, cx: &mut AppContext) -> BoxFuture<()> {
+        *self.credential.write() = ProviderCredential::NoCredentials;
+        let delete_credentials = cx.delete_credentials(ANTHROPIC_API_URL);
+        async move {
+            delete_credentials.await.log_err();
+        }
+        .boxed()
+    }
+}
+
+impl CompletionProvider for AnthropicCompletionProvider {
+    fn base_model(&self) -> Box<dyn LanguageModel> {
+        Box::new(self.model.clone())
+    }
+
+    fn complete(
+        &self,
+        prompt: Box<dyn CompletionRequest>,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+        let credential = self.credential.read().clone();
+        let request = stream_completion(credential, self.executor.clone(), prompt);
+        async move { Ok(request.await?.boxed()) }.boxed()
+    }
+
+    fn box_clone(&self) -> Box<dyn CompletionProvider> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::open_ai::RequestMessage;
+
+    #[test]
+    fn test_request_from_open_ai() {
+        let request = AnthropicRequest::from_open_ai(OpenAiRequest {
+            model: "claude-2.1".into(),
+            messages: vec![
+                RequestMessage {
+                    role: Role::Assistant,
+                    content: "Hi, how can I help?".into(),
+                },
+                RequestMessage {
+                    role: Role::System,
+                    content: "You are a helpful assistant.".into(),
+                },
+                RequestMessage {
+                    role: Role::User,
+                    content: "Explain this code.".into(),
+                },
+                RequestMessage {
+                    role: Role::User,
+                    content: "fn main() {}".into(),
+                },
+                RequestMessage {
+                    role: Role::Assistant,
+                    content: " ".into(),
+                },
+                RequestMessage {
+                    role: Role::Assistant,
+                    content: "It does nothing.".into(),
+                },
+            ],
+            stream: true,
+            stop: vec!["|END|>".into()],
+            temperature: 1.5,
+        })
+        .unwrap();
+
+        assert_eq!(
+            request,
+            AnthropicRequest {
+                model: "claude-2.1".into(),
+                system: Some("You are a helpful assistant.".into()),
+                messages: vec![
+                    AnthropicMessage {
+                        role: "user",
+                        content: "Explain this code.\n\nfn main() {}".into(),
+                    },
+                    AnthropicMessage {
+                        role: "assistant",
+                        content: "It does nothing.".into(),
+                    },
+                ],
+                max_tokens: MAX_OUTPUT_TOKENS,
+                stream: true,
+                stop_sequences: vec!["|END|>".into()],
+                temperature: 1.,
+            }
+        );
+    }
+
+    #[test]
+    fn test_request_without_user_message() {
+        let request = AnthropicRequest::from_open_ai(OpenAiRequest {
+            model: "claude-2.1".into(),
+            messages: vec![
+                RequestMessage {
+                    role: Role::System,
+                    content: "You are a helpful assistant.".into(),
+                },
+                RequestMessage {
+                    role: Role::Assistant,
+                    content: "Hi, how can I help?".into(),
+                },
+                RequestMessage {
+                    role: Role::User,
+                    content: "  ".into(),
+                },
+            ],
+            stream: true,
+            stop: Vec::new(),
+            temperature: 1.,
+        });
+
+        assert!(request.is_err());
+    }
+}
@@ -0,0 +1,376 @@
+use anyhow::{anyhow, Context as _, Result};
+use async_trait::async_trait;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use futures::{future::BoxFuture, lock::Mutex, AsyncReadExt, AsyncWriteExt, FutureExt};
+use gpui::AppContext;
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+use tokenizers::{PaddingParams, PaddingStrategy, TruncationParams};
+use util::{
+    http::{AsyncBody, HttpClient},
+    paths::EMBEDDINGS_DIR,
+};
+
+use crate::{
+    auth::{CredentialProvider, ProviderCredential},
+    embedding::{Embedding, EmbeddingProvider},
+    models::LanguageModel,
+    providers::open_ai_compatible::OpenAiCompatibleLanguageModel,
+};
+
+/// The model used for local embeddings, when none is configured.
+pub const DEFAULT_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+
+const HUGGING_FACE_URL: &str = "https://huggingface.co";
+/// The models that can be run locally, each pinned to a revision whose files are
+/// checked against their SHA-256 digests after being downloaded.
+const PINNED_MODELS: &[PinnedModel] = &[PinnedModel {
+    name: DEFAULT_MODEL,
+    revision: "c9745ed1d9f207416be6d2e6f8de32d1f16199bf",
+    files: [
+        (
+            "config.json",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        ),
+        (
+            "tokenizer.json",
+            "0000000000000000000000000000000000000000000000000000000000000000",
+        ),
+        (
+            "model.safetensors",
+            "53aa51172d142c89d9012cce15ae4d6cc0ca6895895114379cacb4fab128d9db",
+        ),
+    ],
+}];
+/// The number of word pieces the model attends to. Longer spans are truncated.
+const CONTEXT_SIZE: usize = 256;
+const MAX_TOKENS_PER_BATCH: usize = 8192;
+
+/// Embeds spans on this machine with a sentence-transformers model, which needs
+/// neither a network connection nor an API key once its weights are downloaded.
+///
+/// The model's files are fetched from Hugging Face the first time a span is embedded
+/// and kept in the embeddings directory.
+#[derive(Clone)]
+pub struct LocalEmbeddingProvider {
+    model_name: String,
+    pinned_model: Option<&'static PinnedModel>,
+    model_dir: PathBuf,
+    client: Arc<dyn HttpClient>,
+    model: Arc<Mutex<Option<Arc<LocalModel>>>>,
+}
+
+struct PinnedModel {
+    name: &'static str,
+    revision: &'static str,
+    /// The name of each file the model needs, with its SHA-256 digest.
+    files: [(&'static str, &'static str); 3],
+}
+
+struct LocalModel {
+    bert: BertModel,
+    tokenizer: tokenizers::Tokenizer,
+    device: Device,
+}
+
+impl LocalEmbeddingProvider {
+    pub fn new(model_name: String, client: Arc<dyn HttpClient>) -> Self {
+        let pinned_model = PINNED_MODELS.iter().find(|model| model.name == model_name);
+        let mut model_dir = EMBEDDINGS_DIR
+            .join("models")
+            .join(model_name.replace('/', "--"));
+        if let Some(pinned_model) = pinned_model {
+            model_dir.push(pinned_model.revision);
+        }
+        Self {
+            model_name,
+            pinned_model,
+            model_dir,
+            client,
+            model: Default::default(),
+        }
+    }
+
+    /// Returns the loaded model, downloading its files first if needed.
+    async fn model(&self) -> Result<Arc<LocalModel>> {
+        let mut model = self.model.lock().await;
+        if let Some(model) = model.as_ref() {
+            return Ok(model.clone());
+        }
+
+        let pinned_model = self.pinned_model.ok_or_else(|| {
+            anyhow!(
+                "{} isn't supported as a local embedding model",
+                self.model_name
+            )
+        })?;
+        for (file_name, sha256) in pinned_model.files {
+            let path = self.model_dir.join(file_name);
+            if smol::fs::metadata(&path).await.is_err() {
+                self.download(pinned_model, file_name, sha256, &path)
+                    .await
+                    .with_context(|| format!("downloading {file_name} of {}", self.model_name))?;
+            }
+        }
+        let model_dir = self.model_dir.clone();
+        let loaded = Arc::new(smol::unblock(move || LocalModel::load(&model_dir)).await?);
+        *model = Some(loaded.clone());
+        Ok(loaded)
+    }
+
+    /// Streams a file of the model's pinned revision to `path`, failing if its contents
+    /// don't match the expected SHA-256 digest.
+    async fn download(
+        &self,
+        pinned_model: &PinnedModel,
+        file_name: &str,
+        sha256: &str,
+        path: &Path,
+    ) -> Result<()> {
+        let url = format!(
+            "{HUGGING_FACE_URL}/{}/resolve/{}/{file_name}",
+            pinned_model.name, pinned_model.revision
+        );
+        let mut response = self.client.get(&url, AsyncBody::empty(), true).await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("request to {url} failed: {}", response.status()));
+        }
+
+        // Write to a temporary file first, so that an interrupted or corrupted download
+        // isn't mistaken for a complete one.
+        smol::fs::create_dir_all(&self.model_dir).await?;
+        let temp_path = path.with_extension("download");
+        let mut file = smol::fs::File::create(&temp_path).await?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let len = response.body_mut().read(&mut buffer).await?;
+            if len == 0 {
+                break;
+            }
+            hasher.update(&buffer[..len]);
+            file.write_all(&buffer[..len]).await?;
+        }
+        file.sync_all().await?;
+        drop(file);
+
+        let digest = format!("{:x}", hasher.finalize());
+        if digest != sha256 {
+            smol::fs::remove_file(&temp_path).await.ok();
+            return Err(anyhow!(
+                "checksum mismatch for {url}: expected {sha256}, got {digest}"
+            ));
+        }
+        smol::fs::rename(&temp_path, path).await?;
+        Ok(())
+    }
+}
+
+impl LocalModel {
+    fn load(model_dir: &Path) -> Result<Self> {
+        let device = Device::Cpu;
+        let config: Config =
+            serde_json::from_str(&std::fs::read_to_string(model_dir.join("config.json"))?)?;
+        let mut tokenizer = tokenizers::Tokenizer::from_file(model_dir.join("tokenizer.json"))
+            .map_err(|error| anyhow!("failed to load tokenizer: {error}"))?;
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::BatchLongest,
+            ..Default::default()
+        }));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: CONTEXT_SIZE,
+                ..Default::default()
+            }))
+            .map_err(|error| anyhow!("failed to configure tokenizer: {error}"))?;
+        let weights = VarBuilder::from_buffered_safetensors(
+            std::fs::read(model_dir.join("model.safetensors"))?,
+            DTYPE,
+            &device,
+        )?;
+        let bert = BertModel::load(weights, &config)?;
+        Ok(Self {
+            bert,
+            tokenizer,
+            device,
+        })
+    }
+
+    fn embed(&self, spans: Vec<String>) -> Result<Vec<Embedding>> {
+        if spans.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(spans, true)
+            .map_err(|error| anyhow!("failed to tokenize spans: {error}"))?;
+        let token_ids = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_ids(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let attention_mask = encodings
+            .iter()
+            .map(|encoding| Tensor::new(encoding.get_attention_mask(), &self.device))
+            .collect::<candle_core::Result<Vec<_>>>()?;
+        let token_ids = Tensor::stack(&token_ids, 0)?;
+        let attention_mask = Tensor::stack(&attention_mask, 0)?;
+        let token_type_ids = token_ids.zeros_like()?;
+
+        let output = self
+            .bert
+            .forward(&token_ids, &token_type_ids, Some(&attention_mask))?;
+        let embeddings = normalize(&mean_pool(&output, &attention_mask)?)?;
+        Ok(embeddings
+            .to_vec2::<f32>()?
+            .into_iter()
+            .map(Embedding)
+            .collect())
+    }
+}
+
+/// Averages the embeddings of each span's tokens, ignoring padding.
+fn mean_pool(output: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+    let mask = attention_mask.to_dtype(DType::F32)?.unsqueeze(2)?;
+    let sum = output.broadcast_mul(&mask)?.sum(1)?;
+    let count = mask.sum(1)?.clamp(1e-9, f32::MAX)?;
+    Ok(sum.broadcast_div(&count)?)
+}
+
+/// Scales each row to unit length, so that similarities are cosine similarities.
+fn normalize(embeddings: &Tensor) -> Result<Tensor> {
+    let norm = embeddings.sqr()?.sum_keepdim(1)?.sqrt()?;
+    Ok(embeddings.broadcast_div(&norm.clamp(1e-12, f32::MAX)?)?)
+}
+
+impl CredentialProvider for LocalEmbeddingProvider {
+    fn has_credentials(&self) -> bool {
+        true
+    }
+
+    fn retrieve_credentials(&self, _cx: &mut AppContext) -> BoxFuture<ProviderCredential> {
+        async { ProviderCredential::NotNeeded }.boxed()
+    }
+
+    fn save_credentials(
+        &self,
+        _cx: &mut AppContext,
+        _credential: ProviderCredential,
+    ) -> BoxFuture<()> {
+        async {}.boxed()
+    }
+
+    fn delete_credentials(&self, _cx: &mut AppContext) -> BoxFuture<()> {
+        async {}.boxed()
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbeddingProvider {
+    fn base_model(&self) -> Box<dyn LanguageModel> {
+        // Spans are only budgeted with tokens here. The model's own tokenizer truncates
+        // them to its context when they're embedded.
+        Box::new(OpenAiCompatibleLanguageModel::new(
+            self.model_name.clone(),
+            CONTEXT_SIZE,
+        ))
+    }
+
+    fn embedding_model_id(&self) -> String {
+        format!("local:{}", self.model_name)
+    }
+
+    fn max_tokens_per_batch(&self) -> usize {
+        MAX_TOKENS_PER_BATCH
+    }
+
+    fn rate_limit_expiration(&self) -> Option<Instant> {
+        None
+    }
+
+    async fn embed_batch(&self, spans: Vec<String>) -> Result<Vec<Embedding>> {
+        // Running the model keeps a thread busy for a while, so it mustn't run on the
+        // executor that polls this future.
+        let model = self.model().await?;
+        smol::unblock(move || model.embed(spans)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use util::http::{FakeHttpClient, Response};
+
+    #[test]
+    fn test_download_verifies_digest() {
+        // The SHA-256 digest of "hello world".
+        const DIGEST: &str = "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9";
+        let pinned_model = &PINNED_MODELS[0];
+        let client = FakeHttpClient::create(|request| async move {
+            assert_eq!(
+                request.uri().to_string(),
+                "https://huggingface.co/sentence-transformers/all-MiniLM-L6-v2/resolve/\
+                 c9745ed1d9f207416be6d2e6f8de32d1f16199bf/config.json"
+            );
+            Ok(Response::builder()
+                .status(200)
+                .body("hello world".into())
+                .unwrap())
+        });
+        let model_dir = tempfile::tempdir().unwrap();
+        let mut provider = LocalEmbeddingProvider::new(DEFAULT_MODEL.into(), client);
+        provider.model_dir = model_dir.path().to_path_buf();
+        let path = model_dir.path().join("config.json");
+        let temp_path = path.with_extension("download");
+
+        smol::block_on(async {
+            // A download that doesn't match its digest is discarded.
+            let wrong_digest = DIGEST.replace('b', "c");
+            let error = provider
+                .download(pinned_model, "config.json", &wrong_digest, &path)
+                .await
+                .unwrap_err();
+            assert!(error.to_string().contains("checksum mismatch"));
+            assert!(!path.exists());
+            assert!(!temp_path.exists());
+
+            provider
+                .download(pinned_model, "config.json", DIGEST, &path)
+                .await
+                .unwrap();
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello world");
+            assert!(!temp_path.exists());
+        });
+    }
+
+    #[test]
+    fn test_mean_pool_ignores_padding() {
+        let device = Device::Cpu;
+        // Two spans of two-dimensional token embeddings, the second padded to three tokens.
+        let output = Tensor::new(
+            &[
+                [[1f32, 2.], [3., 4.], [5., 6.]],
+                [[3., 0.], [100., 100.], [100., 100.]],
+            ],
+            &device,
+        )
+        .unwrap();
+        let attention_mask = Tensor::new(&[[1u32, 1, 1], [1, 0, 0]], &device).unwrap();
+
+        let pooled = mean_pool(&output, &attention_mask).unwrap();
+        assert_eq!(
+            pooled.to_vec2::<f32>().unwrap(),
+            vec![vec![3., 4.], vec![3., 0.]]
+        );
+        assert_eq!(
+            normalize(&pooled).unwrap().to_vec2::<f32>().unwrap(),
+            vec![vec![0.6, 0.8], vec![1., 0.]]
+        );
+    }
+}
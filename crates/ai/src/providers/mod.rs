@@ -1 +1,175 @@
+pub mod anthropic;
+pub mod local;
 pub mod open_ai;
+pub mod open_ai_compatible;
+
+use gpui::BackgroundExecutor;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use util::http::HttpClient;
+
+use crate::{completion::CompletionProvider, embedding::EmbeddingProvider};
+use anthropic::AnthropicCompletionProvider;
+use local::LocalEmbeddingProvider;
+use open_ai::{OpenAiCompletionProvider, OpenAiEmbeddingProvider};
+use open_ai_compatible::{
+    OpenAiCompatibleCompletionProvider, OpenAiCompatibleEmbeddingProvider,
+    OpenAiCompatibleLanguageModel,
+};
+
+/// The service that completes prompts for a feature, and the model it uses.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "name")]
+pub enum CompletionProviderSettings {
+    /// OpenAI's hosted API.
+    #[serde(rename = "openai")]
+    OpenAi {
+        /// The model to use. When omitted, the assistant's `default_open_ai_model` is used.
+        #[serde(default)]
+        model: Option<String>,
+    },
+    /// A server implementing OpenAI's chat completions API, such as llama.cpp's server
+    /// or Ollama.
+    #[serde(rename = "openai_compatible")]
+    OpenAiCompatible {
+        /// The endpoint's base URL, e.g. "http://localhost:11434/v1".
+        api_url: String,
+        /// The name of the model to request from the server.
+        model: String,
+        /// The number of tokens fitting into the model's context window.
+        #[serde(default = "default_context_size")]
+        context_size: usize,
+    },
+    /// Anthropic's messages API.
+    #[serde(rename = "anthropic")]
+    Anthropic {
+        /// The model to use.
+        #[serde(default = "default_anthropic_model")]
+        model: String,
+    },
+}
+
+/// The service that embeds code for semantic search, and the model it uses.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "name")]
+pub enum EmbeddingProviderSettings {
+    /// OpenAI's hosted API, using `text-embedding-ada-002`.
+    #[serde(rename = "openai")]
+    OpenAi,
+    /// A server implementing OpenAI's embeddings API, such as llama.cpp's server.
+    #[serde(rename = "openai_compatible")]
+    OpenAiCompatible {
+        /// The endpoint's base URL, e.g. "http://localhost:8080/v1".
+        api_url: String,
+        /// The name of the embedding model to request from the server.
+        model: String,
+        /// The maximum number of tokens a single span may contain.
+        #[serde(default = "default_context_size")]
+        context_size: usize,
+        /// The maximum number of tokens sent in a single request.
+        #[serde(default = "default_max_tokens_per_batch")]
+        max_tokens_per_batch: usize,
+        /// The number of dimensions to request, for models supporting shortened embeddings.
+        #[serde(default)]
+        dimensions: Option<usize>,
+    },
+    /// A sentence-transformers model running on this machine, which needs neither an
+    /// API key nor, once its weights are downloaded, a network connection.
+    #[serde(rename = "local")]
+    Local {
+        /// The model's repository on Hugging Face.
+        #[serde(default = "default_local_model")]
+        model: String,
+    },
+}
+
+fn default_context_size() -> usize {
+    open_ai_compatible::DEFAULT_CONTEXT_SIZE
+}
+
+fn default_local_model() -> String {
+    local::DEFAULT_MODEL.into()
+}
+
+fn default_anthropic_model() -> String {
+    "claude-2.1".into()
+}
+
+fn default_max_tokens_per_batch() -> usize {
+    8192
+}
+
+impl Default for CompletionProviderSettings {
+    fn default() -> Self {
+        Self::OpenAi { model: None }
+    }
+}
+
+impl Default for EmbeddingProviderSettings {
+    fn default() -> Self {
+        Self::OpenAi
+    }
+}
+
+impl CompletionProviderSettings {
+    /// The model configured for this provider, if any.
+    pub fn model(&self) -> Option<&str> {
+        match self {
+            Self::OpenAi { model } => model.as_deref(),
+            Self::OpenAiCompatible { model, .. } | Self::Anthropic { model } => Some(model),
+        }
+    }
+
+    /// Whether this is OpenAI's hosted API, whose models can be picked per conversation.
+    pub fn is_open_ai(&self) -> bool {
+        matches!(self, Self::OpenAi { .. })
+    }
+
+    /// Creates a provider completing prompts with the given model.
+    pub async fn build(
+        &self,
+        model: String,
+        executor: BackgroundExecutor,
+    ) -> Arc<dyn CompletionProvider> {
+        match self {
+            Self::OpenAi { .. } => Arc::new(OpenAiCompletionProvider::new(model, executor).await),
+            Self::OpenAiCompatible {
+                api_url,
+                context_size,
+                ..
+            } => Arc::new(OpenAiCompatibleCompletionProvider::new(
+                api_url.clone(),
+                OpenAiCompatibleLanguageModel::new(model, *context_size),
+                executor,
+            )),
+            Self::Anthropic { .. } => Arc::new(AnthropicCompletionProvider::new(model, executor)),
+        }
+    }
+}
+
+impl EmbeddingProviderSettings {
+    pub async fn build(
+        &self,
+        client: Arc<dyn HttpClient>,
+        executor: BackgroundExecutor,
+    ) -> Arc<dyn EmbeddingProvider> {
+        match self {
+            Self::OpenAi => Arc::new(OpenAiEmbeddingProvider::new(client, executor).await),
+            Self::OpenAiCompatible {
+                api_url,
+                model,
+                context_size,
+                max_tokens_per_batch,
+                dimensions,
+            } => Arc::new(OpenAiCompatibleEmbeddingProvider::new(
+                api_url.clone(),
+                OpenAiCompatibleLanguageModel::new(model.clone(), *context_size),
+                *max_tokens_per_batch,
+                *dimensions,
+                client,
+            )),
+            Self::Local { model } => Arc::new(LocalEmbeddingProvider::new(model.clone(), client)),
+        }
+    }
+}
@@ -57,7 +57,7 @@ pub struct RequestMessage {
     pub content: String,
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct OpenAiRequest {
     pub model: String,
     pub messages: Vec<RequestMessage>,
@@ -102,14 +102,18 @@ pub struct OpenAiResponseStreamEvent {
     pub usage: Option<OpenAiUsage>,
 }
 
+/// Streams a chat completion from `api_url`, which can be OpenAI's API or any server
+/// implementing the same protocol.
 pub async fn stream_completion(
+    api_url: &str,
     credential: ProviderCredential,
     executor: BackgroundExecutor,
     request: Box<dyn CompletionRequest>,
 ) -> Result<impl Stream<Item = Result<OpenAiResponseStreamEvent>>> {
     let api_key = match credential {
-        ProviderCredential::Credentials { api_key } => api_key,
-        _ => {
+        ProviderCredential::Credentials { api_key } => Some(api_key),
+        ProviderCredential::NotNeeded => None,
+        ProviderCredential::NoCredentials => {
             return Err(anyhow!("no credentials provider for completion"));
         }
     };
@@ -117,12 +121,12 @@ pub async fn stream_completion(
     let (tx, rx) = futures::channel::mpsc::unbounded::<Result<OpenAiResponseStreamEvent>>();
 
     let json_data = request.data()?;
-    let mut response = Request::post(format!("{OPEN_AI_API_URL}/chat/completions"))
-        .header("Content-Type", "application/json")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .body(json_data)?
-        .send_async()
-        .await?;
+    let mut request = Request::post(format!("{api_url}/chat/completions"))
+        .header("Content-Type", "application/json");
+    if let Some(api_key) = api_key {
+        request = request.header("Authorization", format!("Bearer {}", api_key));
+    }
+    let mut response = request.body(json_data)?.send_async().await?;
 
     let status = response.status();
     if status == StatusCode::OK {
@@ -134,6 +138,10 @@ pub async fn stream_completion(
                     line: Result<String, io::Error>,
                 ) -> Result<Option<OpenAiResponseStreamEvent>> {
                     if let Some(data) = line?.strip_prefix("data: ") {
+                        // Some servers terminate the stream with a sentinel instead of a finish reason.
+                        if data == "[DONE]" {
+                            return Ok(None);
+                        }
                         let event = serde_json::from_str(data)?;
                         Ok(Some(event))
                     } else {
@@ -303,7 +311,7 @@ impl CompletionProvider for OpenAiCompletionProvider {
         // which is currently model based, due to the language model.
         // At some point in the future we should rectify this.
         let credential = self.credential.read().clone();
-        let request = stream_completion(credential, self.executor.clone(), prompt);
+        let request = stream_completion(OPEN_AI_API_URL, credential, self.executor.clone(), prompt);
         async move {
             let response = request.await?;
             let stream = response
@@ -8,7 +8,6 @@ use gpui::BackgroundExecutor;
 use isahc::http::StatusCode;
 use isahc::prelude::Configurable;
 use isahc::{AsyncBody, Response};
-use lazy_static::lazy_static;
 use parking_lot::{Mutex, RwLock};
 use parse_duration::parse;
 use postage::watch;
@@ -18,7 +17,6 @@ use std::env;
 use std::ops::Add;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tiktoken_rs::{cl100k_base, CoreBPE};
 use util::http::{HttpClient, Request};
 use util::ResultExt;
 
@@ -29,10 +27,6 @@ use crate::providers::open_ai::OpenAiLanguageModel;
 
 use crate::providers::open_ai::OPEN_AI_API_URL;
 
-lazy_static! {
-    pub(crate) static ref OPEN_AI_BPE_TOKENIZER: CoreBPE = cl100k_base().unwrap();
-}
-
 #[derive(Clone)]
 pub struct OpenAiEmbeddingProvider {
     model: OpenAiLanguageModel,
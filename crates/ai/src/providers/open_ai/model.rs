@@ -1,23 +1,19 @@
-use anyhow::anyhow;
-use tiktoken_rs::CoreBPE;
-
-use crate::models::{LanguageModel, TruncationDirection};
-
-use super::OPEN_AI_BPE_TOKENIZER;
+use crate::{
+    models::{LanguageModel, TruncationDirection},
+    tokenizer::Tokenizer,
+};
 
 #[derive(Clone)]
 pub struct OpenAiLanguageModel {
     name: String,
-    bpe: Option<CoreBPE>,
+    tokenizer: Tokenizer,
 }
 
 impl OpenAiLanguageModel {
     pub fn load(model_name: &str) -> Self {
-        let bpe =
-            tiktoken_rs::get_bpe_from_model(model_name).unwrap_or(OPEN_AI_BPE_TOKENIZER.to_owned());
         OpenAiLanguageModel {
             name: model_name.to_string(),
-            bpe: Some(bpe),
+            tokenizer: Tokenizer::open_ai(model_name),
         }
     }
 }
@@ -27,11 +23,7 @@ impl LanguageModel for OpenAiLanguageModel {
         self.name.clone()
     }
     fn count_tokens(&self, content: &str) -> anyhow::Result<usize> {
-        if let Some(bpe) = &self.bpe {
-            anyhow::Ok(bpe.encode_with_special_tokens(content).len())
-        } else {
-            Err(anyhow!("bpe for open ai model was not retrieved"))
-        }
+        anyhow::Ok(self.tokenizer.count_tokens(content))
     }
     fn truncate(
         &self,
@@ -39,19 +31,7 @@ impl LanguageModel for OpenAiLanguageModel {
         length: usize,
         direction: TruncationDirection,
     ) -> anyhow::Result<String> {
-        if let Some(bpe) = &self.bpe {
-            let tokens = bpe.encode_with_special_tokens(content);
-            if tokens.len() > length {
-                match direction {
-                    TruncationDirection::End => bpe.decode(tokens[..length].to_vec()),
-                    TruncationDirection::Start => bpe.decode(tokens[length..].to_vec()),
-                }
-            } else {
-                bpe.decode(tokens)
-            }
-        } else {
-            Err(anyhow!("bpe for open ai model was not retrieved"))
-        }
+        self.tokenizer.truncate(content, length, direction)
     }
     fn capacity(&self) -> anyhow::Result<usize> {
         anyhow::Ok(tiktoken_rs::model::get_context_size(&self.name))
@@ -0,0 +1,351 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures::{future::BoxFuture, stream::BoxStream, AsyncReadExt, FutureExt, StreamExt};
+use gpui::{AppContext, BackgroundExecutor};
+use isahc::{http::StatusCode, prelude::Configurable};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use util::{
+    http::{HttpClient, Request},
+    ResultExt,
+};
+
+use crate::{
+    auth::{CredentialProvider, ProviderCredential},
+    completion::{CompletionProvider, CompletionRequest},
+    embedding::{Embedding, EmbeddingProvider},
+    models::{LanguageModel, TruncationDirection},
+    providers::open_ai::stream_completion,
+    tokenizer::Tokenizer,
+};
+
+/// The context size assumed for models served by an OpenAI-compatible endpoint,
+/// when none is configured.
+pub const DEFAULT_CONTEXT_SIZE: usize = 4096;
+
+/// A model served by a self-hosted endpoint, such as llama.cpp's server or Ollama.
+///
+/// Tokens are counted with tiktoken's encoding for models it knows, and estimated
+/// for any other model, whose tokenizer isn't available locally.
+#[derive(Clone)]
+pub struct OpenAiCompatibleLanguageModel {
+    name: String,
+    context_size: usize,
+    tokenizer: Tokenizer,
+}
+
+impl OpenAiCompatibleLanguageModel {
+    pub fn new(name: String, context_size: usize) -> Self {
+        let tokenizer = Tokenizer::open_ai_compatible(&name);
+        Self {
+            name,
+            context_size,
+            tokenizer,
+        }
+    }
+}
+
+impl LanguageModel for OpenAiCompatibleLanguageModel {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn count_tokens(&self, content: &str) -> Result<usize> {
+        Ok(self.tokenizer.count_tokens(content))
+    }
+
+    fn truncate(
+        &self,
+        content: &str,
+        length: usize,
+        direction: TruncationDirection,
+    ) -> Result<String> {
+        self.tokenizer.truncate(content, length, direction)
+    }
+
+    fn capacity(&self) -> Result<usize> {
+        Ok(self.context_size)
+    }
+}
+
+/// Credentials for a self-hosted endpoint. Most local servers don't require an API key,
+/// so one is only used if it was saved for the endpoint's URL.
+#[derive(Clone)]
+struct EndpointCredential {
+    api_url: String,
+    credential: Arc<RwLock<ProviderCredential>>,
+}
+
+impl EndpointCredential {
+    fn new(api_url: String) -> Self {
+        Self {
+            api_url,
+            credential: Arc::new(RwLock::new(ProviderCredential::NotNeeded)),
+        }
+    }
+
+    fn get(&self) -> ProviderCredential {
+        self.credential.read().clone()
+    }
+
+    fn retrieve(&self, cx: &mut AppContext) -> BoxFuture<ProviderCredential> {
+        let credentials = cx.read_credentials(&self.api_url);
+        async move {
+            let credential = match credentials.await.log_err() {
+                Some(Some((_, api_key))) => match String::from_utf8(api_key).log_err() {
+                    Some(api_key) => ProviderCredential::Credentials { api_key },
+                    None => ProviderCredential::NotNeeded,
+                },
+                _ => ProviderCredential::NotNeeded,
+            };
+            *self.credential.write() = credential.clone();
+            credential
+        }
+        .boxed()
+    }
+
+    fn save(&self, cx: &mut AppContext, credential: ProviderCredential) -> BoxFuture<()> {
+        *self.credential.write() = credential.clone();
+        let write_credentials = match credential {
+            ProviderCredential::Credentials { api_key } => {
+                Some(cx.write_credentials(&self.api_url, "Bearer", api_key.as_bytes()))
+            }
+            _ => None,
+        };
+        async move {
+            if let Some(write_credentials) = write_credentials {
+                write_credentials.await.log_err();
+            }
+        }
+        .boxed()
+    }
+
+    fn delete(&self, cx: &mut AppContext) -> BoxFuture<()> {
+        *self.credential.write() = ProviderCredential::NotNeeded;
+        let delete_credentials = cx.delete_credentials(&self.api_url);
+        async move {
+            delete_credentials.await.log_err();
+        }
+        .boxed()
+    }
+}
+
+/// Completes prompts using a server that implements OpenAI's chat completions API,
+/// such as llama.cpp's server (`http://localhost:8080/v1`) or Ollama (`http://localhost:11434/v1`).
+#[derive(Clone)]
+pub struct OpenAiCompatibleCompletionProvider {
+    api_url: String,
+    model: OpenAiCompatibleLanguageModel,
+    credential: EndpointCredential,
+    executor: BackgroundExecutor,
+}
+
+impl OpenAiCompatibleCompletionProvider {
+    pub fn new(
+        api_url: String,
+        model: OpenAiCompatibleLanguageModel,
+        executor: BackgroundExecutor,
+    ) -> Self {
+        let api_url = api_url.trim_end_matches('/').to_string();
+        Self {
+            credential: EndpointCredential::new(api_url.clone()),
+            api_url,
+            model,
+            executor,
+        }
+    }
+}
+
+impl CredentialProvider for OpenAiCompatibleCompletionProvider {
+    fn has_credentials(&self) -> bool {
+        true
+    }
+
+    fn retrieve_credentials(&self, cx: &mut AppContext) -> BoxFuture<ProviderCredential> {
+        self.credential.retrieve(cx)
+    }
+
+    fn save_credentials(
+        &self,
+        cx: &mut AppContext,
+        credential: ProviderCredential,
+    ) -> BoxFuture<()> {
+        self.credential.save(cx, credential)
+    }
+
+    fn delete_credentials(&self, cx: &mut AppContext) -> BoxFuture<()> {
+        self.credential.delete(cx)
+    }
+}
+
+impl CompletionProvider for OpenAiCompatibleCompletionProvider {
+    fn base_model(&self) -> Box<dyn LanguageModel> {
+        Box::new(self.model.clone())
+    }
+
+    fn complete(
+        &self,
+        prompt: Box<dyn CompletionRequest>,
+    ) -> BoxFuture<'static, Result<BoxStream<'static, Result<String>>>> {
+        let api_url = self.api_url.clone();
+        let credential = self.credential.get();
+        let executor = self.executor.clone();
+        async move {
+            let response = stream_completion(&api_url, credential, executor, prompt).await?;
+            let stream = response
+                .filter_map(|response| async move {
+                    match response {
+                        Ok(mut response) => Some(Ok(response.choices.pop()?.delta.content?)),
+                        Err(error) => Some(Err(error)),
+                    }
+                })
+                .boxed();
+            Ok(stream)
+        }
+        .boxed()
+    }
+
+    fn box_clone(&self) -> Box<dyn CompletionProvider> {
+        Box::new(self.clone())
+    }
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+/// Embeds spans using a server that implements OpenAI's embeddings API.
+#[derive(Clone)]
+pub struct OpenAiCompatibleEmbeddingProvider {
+    api_url: String,
+    model: OpenAiCompatibleLanguageModel,
+    max_tokens_per_batch: usize,
+    dimensions: Option<usize>,
+    credential: EndpointCredential,
+    client: Arc<dyn HttpClient>,
+}
+
+impl OpenAiCompatibleEmbeddingProvider {
+    pub fn new(
+        api_url: String,
+        model: OpenAiCompatibleLanguageModel,
+        max_tokens_per_batch: usize,
+        dimensions: Option<usize>,
+        client: Arc<dyn HttpClient>,
+    ) -> Self {
+        let api_url = api_url.trim_end_matches('/').to_string();
+        Self {
+            credential: EndpointCredential::new(api_url.clone()),
+            api_url,
+            model,
+            max_tokens_per_batch,
+            dimensions,
+            client,
+        }
+    }
+}
+
+impl CredentialProvider for OpenAiCompatibleEmbeddingProvider {
+    fn has_credentials(&self) -> bool {
+        true
+    }
+
+    fn retrieve_credentials(&self, cx: &mut AppContext) -> BoxFuture<ProviderCredential> {
+        self.credential.retrieve(cx)
+    }
+
+    fn save_credentials(
+        &self,
+        cx: &mut AppContext,
+        credential: ProviderCredential,
+    ) -> BoxFuture<()> {
+        self.credential.save(cx, credential)
+    }
+
+    fn delete_credentials(&self, cx: &mut AppContext) -> BoxFuture<()> {
+        self.credential.delete(cx)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiCompatibleEmbeddingProvider {
+    fn base_model(&self) -> Box<dyn LanguageModel> {
+        Box::new(self.model.clone())
+    }
+
+    fn embedding_model_id(&self) -> String {
+        match self.dimensions {
+            Some(dimensions) => format!("{}:{}:{}", self.api_url, self.model.name, dimensions),
+            None => format!("{}:{}", self.api_url, self.model.name),
+        }
+    }
+
+    fn max_tokens_per_batch(&self) -> usize {
+        self.max_tokens_per_batch
+    }
+
+    fn rate_limit_expiration(&self) -> Option<Instant> {
+        None
+    }
+
+    async fn embed_batch(&self, spans: Vec<String>) -> Result<Vec<Embedding>> {
+        let body = serde_json::to_string(&EmbeddingRequest {
+            model: &self.model.name,
+            input: spans.iter().map(|span| span.as_str()).collect(),
+            dimensions: self.dimensions,
+        })?;
+        let mut request = Request::post(format!("{}/embeddings", self.api_url))
+            .redirect_policy(isahc::config::RedirectPolicy::Follow)
+            .timeout(Duration::from_secs(60))
+            .header("Content-Type", "application/json");
+        if let ProviderCredential::Credentials { api_key } = self.credential.get() {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let mut response = self.client.send(request.body(body.into())?).await?;
+        let mut body = String::new();
+        response.body_mut().read_to_string(&mut body).await?;
+        if response.status() != StatusCode::OK {
+            return Err(anyhow!(
+                "embedding request to {} failed: {} {}",
+                self.api_url,
+                response.status(),
+                body
+            ));
+        }
+
+        let mut response: EmbeddingResponse = serde_json::from_str(&body)?;
+        if response.data.len() != spans.len() {
+            return Err(anyhow!(
+                "expected {} embeddings, got {}",
+                spans.len(),
+                response.data.len()
+            ));
+        }
+        response.data.sort_by_key(|embedding| embedding.index);
+        Ok(response
+            .data
+            .into_iter()
+            .map(|embedding| Embedding::from(embedding.embedding))
+            .collect())
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Result;
+use lazy_static::lazy_static;
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+use tiktoken_rs::{tokenizer::Tokenizer as Encoding, CoreBPE};
+
+use crate::models::TruncationDirection;
+
+/// The average number of characters per token assumed for models whose tokenizer
+/// isn't available locally. It errs on the low side, so that prompts are budgeted
+/// conservatively.
+pub const DEFAULT_CHARS_PER_TOKEN: f32 = 3.5;
+
+lazy_static! {
+    /// The byte-pair encodings built so far. Building one takes a while and a lot of
+    /// memory, so every model using the same encoding shares it.
+    static ref ENCODINGS: Mutex<HashMap<Encoding, Arc<CoreBPE>>> = Default::default();
+}
+
+/// How a provider's models split text into tokens, used to budget prompts.
+#[derive(Clone)]
+pub enum Tokenizer {
+    /// A byte-pair encoding matching the model's own tokenizer.
+    Bpe(Arc<CoreBPE>),
+    /// An estimate based on the length of the text, for models whose tokenizer
+    /// isn't published or can't be run locally.
+    Estimate { chars_per_token: f32 },
+}
+
+impl Tokenizer {
+    /// The tokenizer of an OpenAI model, falling back to `cl100k_base` for models
+    /// unknown to tiktoken.
+    pub fn open_ai(model_name: &str) -> Self {
+        let encoding =
+            tiktoken_rs::tokenizer::get_tokenizer(model_name).unwrap_or(Encoding::Cl100kBase);
+        Self::Bpe(shared_bpe(encoding))
+    }
+
+    /// The tokenizer of a model served under an OpenAI-compatible API. Models known to
+    /// tiktoken use their encoding, while the token count of any other model is estimated.
+    pub fn open_ai_compatible(model_name: &str) -> Self {
+        match tiktoken_rs::tokenizer::get_tokenizer(model_name) {
+            Some(encoding) => Self::Bpe(shared_bpe(encoding)),
+            None => Self::estimate(),
+        }
+    }
+
+    /// The tokenizer of an Anthropic model. Anthropic doesn't publish a tokenizer for
+    /// its current models, so token counts are estimated.
+    pub fn anthropic() -> Self {
+        Self::estimate()
+    }
+
+    fn estimate() -> Self {
+        Self::Estimate {
+            chars_per_token: DEFAULT_CHARS_PER_TOKEN,
+        }
+    }
+
+    pub fn count_tokens(&self, content: &str) -> usize {
+        match self {
+            Self::Bpe(bpe) => bpe.encode_with_special_tokens(content).len(),
+            Self::Estimate { chars_per_token } => {
+                (content.chars().count() as f32 / chars_per_token).ceil() as usize
+            }
+        }
+    }
+
+    /// Truncates the content to at most `length` tokens, dropping text from the
+    /// given direction.
+    pub fn truncate(
+        &self,
+        content: &str,
+        length: usize,
+        direction: TruncationDirection,
+    ) -> Result<String> {
+        match self {
+            Self::Bpe(bpe) => {
+                let tokens = bpe.encode_with_special_tokens(content);
+                if tokens.len() <= length {
+                    return Ok(content.to_string());
+                }
+                match direction {
+                    TruncationDirection::End => bpe.decode(tokens[..length].to_vec()),
+                    TruncationDirection::Start => {
+                        bpe.decode(tokens[tokens.len() - length..].to_vec())
+                    }
+                }
+            }
+            Self::Estimate { chars_per_token } => {
+                let max_chars = (length as f32 * chars_per_token).floor() as usize;
+                let char_count = content.chars().count();
+                if char_count <= max_chars {
+                    return Ok(content.to_string());
+                }
+                Ok(match direction {
+                    TruncationDirection::End => content.chars().take(max_chars).collect(),
+                    TruncationDirection::Start => {
+                        content.chars().skip(char_count - max_chars).collect()
+                    }
+                })
+            }
+        }
+    }
+}
+
+fn shared_bpe(encoding: Encoding) -> Arc<CoreBPE> {
+    ENCODINGS
+        .lock()
+        .entry(encoding)
+        .or_insert_with(|| {
+            // The encodings are bundled with tiktoken, so building them can't fail.
+            Arc::new(tiktoken_rs::get_bpe_from_tokenizer(encoding).unwrap())
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_bpe() {
+        let tokenizer = Tokenizer::open_ai("gpt-4");
+        let content = "one two three four five six";
+        assert_eq!(tokenizer.count_tokens(content), 6);
+        assert_eq!(
+            tokenizer
+                .truncate(content, 2, TruncationDirection::End)
+                .unwrap(),
+            "one two"
+        );
+        assert_eq!(
+            tokenizer
+                .truncate(content, 2, TruncationDirection::Start)
+                .unwrap(),
+            " five six"
+        );
+        assert_eq!(
+            tokenizer
+                .truncate(content, 10, TruncationDirection::End)
+                .unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn test_truncate_estimate() {
+        let tokenizer = Tokenizer::Estimate {
+            chars_per_token: 2.,
+        };
+        let content = "αβγδεζη";
+        assert_eq!(tokenizer.count_tokens(content), 4);
+        assert_eq!(
+            tokenizer
+                .truncate(content, 2, TruncationDirection::End)
+                .unwrap(),
+            "αβγδ"
+        );
+        assert_eq!(
+            tokenizer
+                .truncate(content, 2, TruncationDirection::Start)
+                .unwrap(),
+            "δεζη"
+        );
+        assert_eq!(
+            tokenizer
+                .truncate(content, 4, TruncationDirection::Start)
+                .unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn test_provider_tokenizers() {
+        assert!(matches!(
+            Tokenizer::open_ai_compatible("gpt-3.5-turbo"),
+            Tokenizer::Bpe(_)
+        ));
+        assert!(matches!(
+            Tokenizer::open_ai_compatible("llama-2-7b"),
+            Tokenizer::Estimate { .. }
+        ));
+        assert!(matches!(Tokenizer::anthropic(), Tokenizer::Estimate { .. }));
+
+        // Models using the same encoding share it.
+        let (Tokenizer::Bpe(gpt_4), Tokenizer::Bpe(gpt_3_5)) = (
+            Tokenizer::open_ai("gpt-4"),
+            Tokenizer::open_ai_compatible("gpt-3.5-turbo"),
+        ) else {
+            panic!("expected byte-pair encodings");
+        };
+        assert!(Arc::ptr_eq(&gpt_4, &gpt_3_5));
+    }
+}
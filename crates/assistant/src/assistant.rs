@@ -1,21 +1,30 @@
+pub mod assistant_completion_provider;
 pub mod assistant_panel;
 mod assistant_settings;
+mod attachments;
 mod codegen;
+mod conversation_history;
+mod edit_proposals;
+mod edit_review;
+mod prompt_templates;
 mod prompts;
+mod slash_commands;
 mod streaming_diff;
+mod tools;
 
 use ai::providers::open_ai::Role;
 use anyhow::Result;
+pub use assistant_completion_provider::{AssistantCompletionProvider, InlineCompletionModel};
 pub use assistant_panel::AssistantPanel;
-use assistant_settings::OpenAiModel;
+use attachments::ContextAttachment;
 use chrono::{DateTime, Local};
 use collections::HashMap;
+use conversation_history::{index_terms, ConversationIndex};
 use fs::Fs;
-use futures::StreamExt;
 use gpui::{actions, AppContext, SharedString};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{cmp::Reverse, ffi::OsStr, path::PathBuf, sync::Arc};
+use std::{path::PathBuf, sync::Arc, time::SystemTime};
 use util::paths::CONVERSATIONS_DIR;
 
 actions!(
@@ -31,6 +40,15 @@ actions!(
         InlineAssist,
         ToggleIncludeConversation,
         ToggleRetrieveContext,
+        AttachSelection,
+        AttachFile,
+        AttachDiagnostics,
+        AttachGitDiff,
+        AttachTerminalOutput,
+        ReviewEdits,
+        TogglePinned,
+        EditTags,
+        ContinueInNewConversation,
     ]
 );
 
@@ -68,51 +86,86 @@ struct SavedConversation {
     messages: Vec<SavedMessage>,
     message_metadata: HashMap<MessageId, MessageMetadata>,
     summary: String,
-    model: OpenAiModel,
+    model: String,
+    #[serde(default)]
+    attachments: Vec<ContextAttachment>,
+    #[serde(default)]
+    pinned: bool,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 
 impl SavedConversation {
     const VERSION: &'static str = "0.1.0";
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct SavedConversationMetadata {
     title: String,
     path: PathBuf,
-    mtime: chrono::DateTime<chrono::Local>,
+    /// When the last message was sent, or the modification time for
+    /// conversations without messages.
+    last_message_at: DateTime<Local>,
+    model: String,
+    pinned: bool,
+    tags: Vec<String>,
+    /// The distinct lowercased words of the title and messages, sorted for
+    /// searching the history by prefix.
+    terms: Vec<String>,
 }
 
 impl SavedConversationMetadata {
+    /// Lists the saved conversations from their index, after re-indexing the
+    /// ones that changed since it was last updated.
     pub async fn list(fs: Arc<dyn Fs>) -> Result<Vec<Self>> {
         fs.create_dir(&CONVERSATIONS_DIR).await?;
 
-        let mut paths = fs.read_dir(&CONVERSATIONS_DIR).await?;
-        let mut conversations = Vec::<SavedConversationMetadata>::new();
-        while let Some(path) = paths.next().await {
-            let path = path?;
-            if path.extension() != Some(OsStr::new("json")) {
-                continue;
-            }
-
-            let pattern = r" - \d+.zed.json$";
-            let re = Regex::new(pattern).unwrap();
-
-            let metadata = fs.metadata(&path).await?;
-            if let Some((file_name, metadata)) = path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .zip(metadata)
-            {
-                let title = re.replace(file_name, "");
-                conversations.push(Self {
-                    title: title.into_owned(),
-                    path,
-                    mtime: metadata.mtime.into(),
-                });
-            }
+        let mut index = ConversationIndex::load(fs.as_ref()).await;
+        if index.refresh(fs.as_ref()).await? {
+            index.save(fs.as_ref()).await?;
         }
-        conversations.sort_unstable_by_key(|conversation| Reverse(conversation.mtime));
+        Ok(index.conversations())
+    }
 
-        Ok(conversations)
+    /// Describes the conversation saved at `path`, which couldn't be read if
+    /// `saved_conversation` is `None`.
+    fn new(
+        path: PathBuf,
+        mtime: SystemTime,
+        saved_conversation: Option<&SavedConversation>,
+    ) -> Self {
+        let pattern = r" - \d+.zed.json$";
+        let re = Regex::new(pattern).unwrap();
+        let title = path
+            .file_name()
+            .map(|name| re.replace(&name.to_string_lossy(), "").into_owned())
+            .unwrap_or_default();
+        let mtime: DateTime<Local> = mtime.into();
+        match saved_conversation {
+            Some(saved_conversation) => Self {
+                last_message_at: saved_conversation
+                    .message_metadata
+                    .values()
+                    .map(|metadata| metadata.sent_at)
+                    .max()
+                    .unwrap_or(mtime),
+                terms: index_terms(&format!("{title}\n{}", saved_conversation.text)),
+                title,
+                path,
+                model: saved_conversation.model.clone(),
+                pinned: saved_conversation.pinned,
+                tags: saved_conversation.tags.clone(),
+            },
+            None => Self {
+                terms: index_terms(&title),
+                title,
+                path,
+                last_message_at: mtime,
+                model: String::new(),
+                pinned: false,
+                tags: Vec::new(),
+            },
+        }
     }
 }
 
@@ -0,0 +1,364 @@
+use crate::assistant_settings::AssistantSettings;
+use ai::{
+    completion::CompletionProvider,
+    providers::{
+        open_ai::{OpenAiRequest, RequestMessage, Role},
+        CompletionProviderSettings,
+    },
+};
+use editor::{Direction, InlineCompletionProvider};
+use futures::StreamExt;
+use gpui::{AppContext, EntityId, Model, ModelContext, Subscription, Task};
+use language::{language_settings::all_language_settings, Anchor, Bias, Buffer, ToOffset};
+use settings::{Settings, SettingsStore};
+use std::{fmt::Write, sync::Arc, time::Duration};
+use util::ResultExt;
+
+/// How long to wait after the last keystroke before asking the model for a
+/// completion. Requests are more expensive than Copilot's, so we wait longer.
+pub const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(300);
+const MAX_PREFIX_LEN: usize = 6000;
+const MAX_SUFFIX_LEN: usize = 2000;
+const CURSOR_MARKER: &str = "<|CURSOR|>";
+
+/// The completion provider that inline completions are requested from. It is
+/// shared by every editor and rebuilt when the assistant's settings change.
+pub struct InlineCompletionModel {
+    provider_settings: Option<CompletionProviderSettings>,
+    completion_provider: Option<(Arc<dyn CompletionProvider>, String)>,
+    build_provider: Task<Option<()>>,
+    _settings_subscription: Subscription,
+}
+
+impl InlineCompletionModel {
+    pub fn new(cx: &mut ModelContext<Self>) -> Self {
+        let mut this = Self {
+            provider_settings: None,
+            completion_provider: None,
+            build_provider: Task::ready(None),
+            _settings_subscription: cx
+                .observe_global::<SettingsStore>(|this, cx| this.update_provider(cx)),
+        };
+        this.update_provider(cx);
+        this
+    }
+
+    /// The provider to request completions from and the model to use, once the
+    /// provider has been built and its credentials loaded.
+    pub fn completion_provider(&self) -> Option<(Arc<dyn CompletionProvider>, String)> {
+        self.completion_provider.clone()
+    }
+
+    fn update_provider(&mut self, cx: &mut ModelContext<Self>) {
+        let settings = AssistantSettings::get_global(cx);
+        let provider_settings = settings.inline_provider().clone();
+        if self.provider_settings.as_ref() == Some(&provider_settings) {
+            return;
+        }
+
+        let model = settings.default_model(&provider_settings);
+        self.provider_settings = Some(provider_settings.clone());
+        let executor = cx.background_executor().clone();
+        self.build_provider = cx.spawn(|this, mut cx| async move {
+            let completion_provider = provider_settings.build(model.clone(), executor).await;
+            cx.update(|cx| completion_provider.retrieve_credentials(cx))
+                .log_err()?
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.completion_provider = Some((completion_provider, model));
+                cx.notify();
+            })
+            .log_err()
+        });
+    }
+}
+
+/// Supplies inline completions by asking the assistant's completion model to
+/// fill in the text at the cursor.
+pub struct AssistantCompletionProvider {
+    completion_provider: Arc<dyn CompletionProvider>,
+    model: String,
+    completion: Option<CurrentCompletion>,
+    pending_refresh: Task<Option<()>>,
+}
+
+struct CurrentCompletion {
+    buffer_id: EntityId,
+    position: Anchor,
+    text: String,
+}
+
+impl AssistantCompletionProvider {
+    pub fn new(completion_provider: Arc<dyn CompletionProvider>, model: String) -> Self {
+        Self {
+            completion_provider,
+            model,
+            completion: None,
+            pending_refresh: Task::ready(None),
+        }
+    }
+}
+
+impl InlineCompletionProvider for AssistantCompletionProvider {
+    fn is_enabled(&self, buffer: &Model<Buffer>, cursor_position: Anchor, cx: &AppContext) -> bool {
+        if !self.completion_provider.has_credentials() {
+            return false;
+        }
+
+        let buffer = buffer.read(cx);
+        let file = buffer.file();
+        let language = buffer.language_at(cursor_position);
+        let settings = all_language_settings(file, cx);
+        settings.inline_completions_enabled(language.as_ref(), file.map(|f| f.path().as_ref()))
+    }
+
+    fn refresh(
+        &mut self,
+        buffer: Model<Buffer>,
+        cursor_position: Anchor,
+        debounce: bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let completion_provider = self.completion_provider.clone();
+        let model = self.model.clone();
+        // Replacing the pending task drops it, cancelling the previous request.
+        self.pending_refresh = cx.spawn(|this, mut cx| async move {
+            if debounce {
+                cx.background_executor().timer(DEBOUNCE_TIMEOUT).await;
+            }
+
+            let (snapshot, language_name) = buffer
+                .update(&mut cx, |buffer, _| {
+                    let language_name = buffer.language_at(cursor_position).map(|l| l.name());
+                    (buffer.snapshot(), language_name)
+                })
+                .log_err()?;
+            let offset = cursor_position.to_offset(&snapshot);
+            let prefix_start =
+                snapshot.clip_offset(offset.saturating_sub(MAX_PREFIX_LEN), Bias::Right);
+            let suffix_end =
+                snapshot.clip_offset((offset + MAX_SUFFIX_LEN).min(snapshot.len()), Bias::Left);
+            let prefix = snapshot
+                .text_for_range(prefix_start..offset)
+                .collect::<String>();
+            let suffix = snapshot
+                .text_for_range(offset..suffix_end)
+                .collect::<String>();
+
+            let prompt = fill_in_the_middle_prompt(language_name.as_deref(), &prefix, &suffix);
+            let request = Box::new(OpenAiRequest {
+                model,
+                messages: vec![RequestMessage {
+                    role: Role::User,
+                    content: prompt,
+                }],
+                stream: true,
+                stop: Vec::new(),
+                temperature: 0.,
+            });
+            let response = async {
+                let mut chunks = completion_provider.complete(request).await?;
+                let mut response = String::new();
+                while let Some(chunk) = chunks.next().await {
+                    response.push_str(&chunk?);
+                }
+                anyhow::Ok(response)
+            }
+            .await
+            .log_err()?;
+
+            let text = completion_text(&response).to_string();
+            this.update(&mut cx, |this, cx| {
+                this.completion = (!text.is_empty()).then(|| CurrentCompletion {
+                    buffer_id: buffer.entity_id(),
+                    position: snapshot.anchor_before(offset),
+                    text,
+                });
+                cx.notify();
+            })
+            .log_err()
+        });
+    }
+
+    fn cycle(
+        &mut self,
+        _buffer: Model<Buffer>,
+        _cursor_position: Anchor,
+        _direction: Direction,
+        _cx: &mut ModelContext<Self>,
+    ) {
+        // The model is asked for a single completion, so there is nothing to cycle through.
+    }
+
+    fn accept(&mut self, _cx: &mut ModelContext<Self>) {
+        self.pending_refresh = Task::ready(None);
+    }
+
+    fn discard(&mut self, _cx: &mut ModelContext<Self>) {
+        self.pending_refresh = Task::ready(None);
+    }
+
+    fn active_completion_text<'a>(
+        &'a self,
+        buffer: &Model<Buffer>,
+        cursor_position: Anchor,
+        cx: &'a AppContext,
+    ) -> Option<&'a str> {
+        let completion = self.completion.as_ref()?;
+        if completion.buffer_id != buffer.entity_id() {
+            return None;
+        }
+
+        // Keep showing the completion while the user types the text it starts with.
+        let buffer = buffer.read(cx);
+        if !completion.position.is_valid(buffer) {
+            return None;
+        }
+        let start = completion.position.to_offset(buffer);
+        let cursor = cursor_position.to_offset(buffer);
+        if cursor < start {
+            return None;
+        }
+        let typed = buffer.text_for_range(start..cursor).collect::<String>();
+        let text = completion.text.strip_prefix(typed.as_str())?;
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+/// Builds a prompt asking the model for the text that belongs at the cursor,
+/// between `prefix` and `suffix`.
+pub fn fill_in_the_middle_prompt(
+    language_name: Option<&str>,
+    prefix: &str,
+    suffix: &str,
+) -> String {
+    let mut prompt = String::new();
+    match language_name {
+        Some(language_name) => writeln!(
+            prompt,
+            "Complete the {language_name} code at {CURSOR_MARKER} in the following file."
+        ),
+        None => writeln!(
+            prompt,
+            "Complete the text at {CURSOR_MARKER} in the following file."
+        ),
+    }
+    .unwrap();
+    writeln!(
+        prompt,
+        "Reply with only the text to insert at the cursor. Don't repeat the text around it, \
+        don't explain the completion and don't wrap it in a code block. \
+        Reply with nothing if there is nothing to insert."
+    )
+    .unwrap();
+    writeln!(prompt).unwrap();
+    write!(prompt, "```\n{prefix}{CURSOR_MARKER}{suffix}\n```").unwrap();
+    prompt
+}
+
+/// Extracts the text to insert from the model's response, which may have
+/// wrapped it in a code block despite being asked not to.
+fn completion_text(response: &str) -> &str {
+    let Some(code) = response.trim().strip_prefix("```") else {
+        return response.trim_end();
+    };
+    let code = code.split_once('\n').map_or("", |(_, code)| code);
+    code.strip_suffix("```")
+        .unwrap_or(code)
+        .trim_end_matches('\n')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai::test::FakeCompletionProvider;
+    use gpui::{Context, TestAppContext};
+    use indoc::indoc;
+    use language::{BufferId, Point};
+
+    #[test]
+    fn test_fill_in_the_middle_prompt() {
+        assert_eq!(
+            fill_in_the_middle_prompt(Some("Rust"), "fn main() {\n    ", "\n}"),
+            indoc! {"
+                Complete the Rust code at <|CURSOR|> in the following file.
+                Reply with only the text to insert at the cursor. Don't repeat the text around it, don't explain the completion and don't wrap it in a code block. Reply with nothing if there is nothing to insert.
+
+                ```
+                fn main() {
+                    <|CURSOR|>
+                }
+                ```"}
+        );
+    }
+
+    #[test]
+    fn test_completion_text() {
+        assert_eq!(completion_text("let x = 1;\n"), "let x = 1;");
+        assert_eq!(completion_text("  x + 1"), "  x + 1");
+        assert_eq!(completion_text("```rust\nlet x = 1;\n```\n"), "let x = 1;");
+        assert_eq!(completion_text("```\n```"), "");
+    }
+
+    #[gpui::test]
+    async fn test_assistant_completion_provider(cx: &mut TestAppContext) {
+        let buffer =
+            cx.new_model(|_| Buffer::new(0, BufferId::new(1).unwrap(), "fn main() {\n    \n}\n"));
+        let fake_provider = Arc::new(FakeCompletionProvider::new());
+        let provider = cx
+            .new_model(|_| AssistantCompletionProvider::new(fake_provider.clone(), "model".into()));
+        let anchor_at = |point: Point, cx: &mut TestAppContext| {
+            buffer.read_with(cx, |buffer, _| buffer.anchor_after(point))
+        };
+        let active_completion_text = |point: Point, cx: &mut TestAppContext| {
+            let cursor = anchor_at(point, cx);
+            provider.read_with(cx, |provider, cx| {
+                provider
+                    .active_completion_text(&buffer, cursor, cx)
+                    .map(str::to_string)
+            })
+        };
+
+        let cursor = anchor_at(Point::new(1, 4), cx);
+        provider.update(cx, |provider, cx| {
+            provider.refresh(buffer.clone(), cursor, false, cx)
+        });
+        cx.background_executor.run_until_parked();
+        fake_provider.send_completion("println!(\"Hello\");");
+        cx.background_executor.run_until_parked();
+        assert_eq!(active_completion_text(Point::new(1, 4), cx), None);
+
+        fake_provider.finish_completion();
+        cx.background_executor.run_until_parked();
+        assert_eq!(
+            active_completion_text(Point::new(1, 4), cx).as_deref(),
+            Some("println!(\"Hello\");")
+        );
+
+        // The completion follows the text typed at the cursor, as long as it matches.
+        buffer.update(cx, |buffer, cx| buffer.edit([(16..16, "print")], None, cx));
+        assert_eq!(
+            active_completion_text(Point::new(1, 9), cx).as_deref(),
+            Some("ln!(\"Hello\");")
+        );
+        buffer.update(cx, |buffer, cx| buffer.edit([(21..21, "x")], None, cx));
+        assert_eq!(active_completion_text(Point::new(1, 10), cx), None);
+
+        // A new request replaces the pending one, which is never completed.
+        let cursor = anchor_at(Point::new(0, 0), cx);
+        provider.update(cx, |provider, cx| {
+            provider.refresh(buffer.clone(), cursor, true, cx)
+        });
+        provider.update(cx, |provider, cx| {
+            provider.refresh(buffer.clone(), cursor, false, cx)
+        });
+        cx.background_executor.run_until_parked();
+        fake_provider.send_completion("// A program\n");
+        fake_provider.finish_completion();
+        cx.background_executor.run_until_parked();
+        assert_eq!(
+            active_completion_text(Point::new(0, 0), cx).as_deref(),
+            Some("// A program")
+        );
+    }
+}
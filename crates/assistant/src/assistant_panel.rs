@@ -1,16 +1,36 @@
+mod attachments;
+mod edit_proposals;
+mod slash_commands;
+mod tools;
+
+use self::{
+    attachments::{Attachment, AttachmentId},
+    tools::PendingToolCall,
+};
 use crate::{
     assistant_settings::{AssistantDockPosition, AssistantSettings, OpenAiModel},
+    attachments::attachments_prompt,
     codegen::{self, Codegen, CodegenKind},
+    conversation_history::{
+        fitting_suffix_start, parse_tags, search_conversations, ConversationIndex, HistoryQuery,
+    },
+    edit_proposals::EDIT_FORMAT_PROMPT,
     prompts::generate_content_prompt,
-    Assist, CycleMessageRole, InlineAssist, MessageId, MessageMetadata, MessageStatus,
-    NewConversation, QuoteSelection, ResetKey, Role, SavedConversation, SavedConversationMetadata,
-    SavedMessage, Split, ToggleFocus, ToggleIncludeConversation, ToggleRetrieveContext,
+    slash_commands::SlashCommandCompletionProvider,
+    tools::tools_prompt,
+    Assist, ContinueInNewConversation, CycleMessageRole, EditTags, InlineAssist, MessageId,
+    MessageMetadata, MessageStatus, NewConversation, QuoteSelection, ResetKey, ReviewEdits, Role,
+    SavedConversation, SavedConversationMetadata, SavedMessage, Split, ToggleFocus,
+    ToggleIncludeConversation, TogglePinned, ToggleRetrieveContext,
 };
 use ai::prompts::repository_context::PromptCodeSnippet;
 use ai::{
     auth::ProviderCredential,
     completion::{CompletionProvider, CompletionRequest},
-    providers::open_ai::{OpenAiCompletionProvider, OpenAiRequest, RequestMessage},
+    providers::{
+        open_ai::{OpenAiRequest, RequestMessage},
+        CompletionProviderSettings,
+    },
 };
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Local};
@@ -35,11 +55,13 @@ use gpui::{
     StatefulInteractiveElement, Styled, Subscription, Task, TextStyle, UniformListScrollHandle,
     View, ViewContext, VisualContext, WeakModel, WeakView, WhiteSpace, WindowContext,
 };
-use language::{language_settings::SoftWrap, Buffer, BufferId, LanguageRegistry, ToOffset as _};
+use language::{
+    language_settings::SoftWrap, Buffer, BufferId, LanguageRegistry, Point, ToOffset as _,
+};
 use project::Project;
 use search::{buffer_search::DivRegistrar, BufferSearchBar};
 use semantic_index::{SemanticIndex, SemanticIndexStatus};
-use settings::Settings;
+use settings::{Settings, SettingsStore};
 use std::{
     cell::Cell,
     cmp,
@@ -75,7 +97,12 @@ pub fn init(cx: &mut AppContext) {
                 })
                 .register_action(AssistantPanel::inline_assist)
                 .register_action(AssistantPanel::cancel_last_inline_assist)
-                .register_action(ConversationEditor::quote_selection);
+                .register_action(ConversationEditor::quote_selection)
+                .register_action(ConversationEditor::attach_selection)
+                .register_action(ConversationEditor::attach_file)
+                .register_action(ConversationEditor::attach_diagnostics)
+                .register_action(ConversationEditor::attach_git_diff)
+                .register_action(ConversationEditor::attach_terminal_output);
         },
     )
     .detach();
@@ -90,10 +117,17 @@ pub struct AssistantPanel {
     editors: Vec<View<ConversationEditor>>,
     saved_conversations: Vec<SavedConversationMetadata>,
     saved_conversations_scroll_handle: UniformListScrollHandle,
+    history_query_editor: View<Editor>,
+    /// Indices into `saved_conversations` of the conversations that match the
+    /// history query.
+    matching_conversations: Vec<usize>,
     zoomed: bool,
     focus_handle: FocusHandle,
     toolbar: View<Toolbar>,
     completion_provider: Arc<dyn CompletionProvider>,
+    inline_completion_provider: Arc<dyn CompletionProvider>,
+    provider_settings: CompletionProviderSettings,
+    inline_provider_settings: CompletionProviderSettings,
     api_key_editor: Option<View<Editor>>,
     languages: Arc<LanguageRegistry>,
     fs: Arc<dyn Fs>,
@@ -121,10 +155,20 @@ impl AssistantPanel {
                 .await
                 .log_err()
                 .unwrap_or_default();
-            // Defaulting currently to GPT4, allow for this to be set via config.
-            let completion_provider =
-                OpenAiCompletionProvider::new("gpt-4".into(), cx.background_executor().clone())
-                    .await;
+            let (provider_settings, inline_provider_settings, model, inline_model) =
+                cx.update(|cx| {
+                    let settings = AssistantSettings::get_global(cx);
+                    (
+                        settings.provider.clone(),
+                        settings.inline_provider().clone(),
+                        settings.default_model(&settings.provider),
+                        settings.default_model(settings.inline_provider()),
+                    )
+                })?;
+            let executor = cx.background_executor().clone();
+            let completion_provider = provider_settings.build(model, executor.clone()).await;
+            let inline_completion_provider =
+                inline_provider_settings.build(inline_model, executor).await;
 
             // TODO: deserialize state.
             let workspace_handle = workspace.clone();
@@ -142,7 +186,7 @@ impl AssistantPanel {
                                 .unwrap_or_default();
                             this.update(&mut cx, |this, cx| {
                                 this.saved_conversations = saved_conversations;
-                                cx.notify();
+                                this.update_matching_conversations(cx);
                             })
                             .ok();
                         }
@@ -163,6 +207,28 @@ impl AssistantPanel {
                     cx.on_focus_in(&focus_handle, Self::focus_in).detach();
                     cx.on_focus_out(&focus_handle, Self::focus_out).detach();
 
+                    let history_query_editor = cx.new_view(|cx| {
+                        let mut editor = Editor::single_line(cx);
+                        editor.set_placeholder_text(
+                            "Search conversations (model:, tag:, after:, before:, is:pinned)",
+                            cx,
+                        );
+                        editor
+                    });
+                    let matching_conversations =
+                        search_conversations(&saved_conversations, &HistoryQuery::default());
+
+                    let subscriptions = vec![
+                        cx.observe_global::<SettingsStore>(|this, cx| {
+                            this.update_completion_providers(cx)
+                        }),
+                        cx.subscribe(&history_query_editor, |this, _, event, cx| {
+                            if let EditorEvent::BufferEdited = event {
+                                this.update_matching_conversations(cx);
+                            }
+                        }),
+                    ];
+
                     Self {
                         workspace: workspace_handle,
                         active_editor_index: Default::default(),
@@ -170,16 +236,21 @@ impl AssistantPanel {
                         editors: Default::default(),
                         saved_conversations,
                         saved_conversations_scroll_handle: Default::default(),
+                        history_query_editor,
+                        matching_conversations,
                         zoomed: false,
                         focus_handle,
                         toolbar,
-                        completion_provider: Arc::new(completion_provider),
+                        completion_provider,
+                        inline_completion_provider,
+                        provider_settings,
+                        inline_provider_settings,
                         api_key_editor: None,
                         languages: workspace.app_state().languages.clone(),
                         fs: workspace.app_state().fs.clone(),
                         width: None,
                         height: None,
-                        subscriptions: Default::default(),
+                        subscriptions,
                         next_inline_assist_id: 0,
                         pending_inline_assists: Default::default(),
                         pending_inline_assist_ids_by_editor: Default::default(),
@@ -194,6 +265,53 @@ impl AssistantPanel {
         })
     }
 
+    fn update_completion_providers(&mut self, cx: &mut ViewContext<Self>) {
+        let settings = AssistantSettings::get_global(cx);
+        let provider_settings = settings.provider.clone();
+        let inline_provider_settings = settings.inline_provider().clone();
+        if provider_settings == self.provider_settings
+            && inline_provider_settings == self.inline_provider_settings
+        {
+            return;
+        }
+
+        let model = settings.default_model(&provider_settings);
+        let inline_model = settings.default_model(&inline_provider_settings);
+        self.provider_settings = provider_settings.clone();
+        self.inline_provider_settings = inline_provider_settings.clone();
+        let executor = cx.background_executor().clone();
+        cx.spawn(|this, mut cx| async move {
+            let completion_provider = provider_settings.build(model, executor.clone()).await;
+            let inline_completion_provider =
+                inline_provider_settings.build(inline_model, executor).await;
+            let load_credentials = this.update(&mut cx, |this, cx| {
+                // The settings may have changed again while the providers were built.
+                if this.provider_settings != provider_settings
+                    || this.inline_provider_settings != inline_provider_settings
+                {
+                    return None;
+                }
+
+                this.completion_provider = completion_provider;
+                this.inline_completion_provider = inline_completion_provider;
+                this.api_key_editor = None;
+                Some(this.load_credentials(cx))
+            })?;
+
+            if let Some(load_credentials) = load_credentials {
+                load_credentials.await;
+                this.update(&mut cx, |this, cx| {
+                    if !this.has_credentials() {
+                        this.build_api_key_editor(cx);
+                    }
+                    cx.notify();
+                })?;
+            }
+            anyhow::Ok(())
+        })
+        .detach_and_log_err(cx);
+    }
+
     fn focus_in(&mut self, cx: &mut ViewContext<Self>) {
         self.toolbar
             .update(cx, |toolbar, cx| toolbar.focus_changed(true, cx));
@@ -296,7 +414,7 @@ impl AssistantPanel {
         };
 
         let inline_assist_id = post_inc(&mut self.next_inline_assist_id);
-        let provider = self.completion_provider.clone();
+        let provider = self.inline_completion_provider.clone();
 
         let codegen = cx.new_model(|cx| {
             Codegen::new(editor.read(cx).buffer().clone(), codegen_kind, provider, cx)
@@ -680,10 +798,10 @@ impl AssistantPanel {
             Task::ready(Ok(Vec::new()))
         };
 
-        let mut model = AssistantSettings::get_global(cx)
-            .default_open_ai_model
-            .clone();
-        let model_name = model.full_name();
+        let settings = AssistantSettings::get_global(cx);
+        let mut model = settings.default_model(settings.inline_provider());
+        let uses_panel_provider = settings.inline_provider.is_none();
+        let base_model = Arc::from(self.inline_completion_provider.base_model());
 
         let prompt = cx.background_executor().spawn(async move {
             let snippets = snippets.await?;
@@ -695,7 +813,7 @@ impl AssistantPanel {
                 buffer,
                 range,
                 snippets,
-                model_name,
+                base_model,
                 project_name,
             )
         });
@@ -709,7 +827,9 @@ impl AssistantPanel {
                     .messages(cx)
                     .map(|message| message.to_open_ai_message(buffer)),
             );
-            model = conversation.model.clone();
+            if uses_panel_provider {
+                model = conversation.model.clone();
+            }
         }
 
         cx.spawn(|_, mut cx| async move {
@@ -722,7 +842,7 @@ impl AssistantPanel {
             });
 
             let request = Box::new(OpenAiRequest {
-                model: model.full_name().into(),
+                model,
                 messages,
                 stream: true,
                 stop: vec!["|END|>".to_string()],
@@ -854,13 +974,17 @@ impl AssistantPanel {
                     api_key: api_key.clone(),
                 };
 
-                let completion_provider = self.completion_provider.clone();
+                let completion_provider = self.provider_for_api_key().0.clone();
                 cx.spawn(|this, mut cx| async move {
                     cx.update(|cx| completion_provider.save_credentials(cx, credential))?
                         .await;
 
                     this.update(&mut cx, |this, cx| {
                         this.api_key_editor.take();
+                        // Ask for the inline assistant's key too, if it uses another provider.
+                        if !this.has_credentials() {
+                            this.build_api_key_editor(cx);
+                        }
                         this.focus_handle.focus(cx);
                         cx.notify();
                     })
@@ -874,9 +998,12 @@ impl AssistantPanel {
 
     fn reset_credentials(&mut self, _: &ResetKey, cx: &mut ViewContext<Self>) {
         let completion_provider = self.completion_provider.clone();
+        let inline_completion_provider = self.inline_completion_provider.clone();
         cx.spawn(|this, mut cx| async move {
             cx.update(|cx| completion_provider.delete_credentials(cx))?
                 .await;
+            cx.update(|cx| inline_completion_provider.delete_credentials(cx))?
+                .await;
             this.update(&mut cx, |this, cx| {
                 this.build_api_key_editor(cx);
                 this.focus_handle.focus(cx);
@@ -947,32 +1074,7 @@ impl AssistantPanel {
         editor: &View<Editor>,
         cx: &mut ViewContext<Self>,
     ) -> impl IntoElement {
-        let settings = ThemeSettings::get_global(cx);
-        let text_style = TextStyle {
-            color: if editor.read(cx).read_only(cx) {
-                cx.theme().colors().text_disabled
-            } else {
-                cx.theme().colors().text
-            },
-            font_family: settings.ui_font.family.clone(),
-            font_features: settings.ui_font.features,
-            font_size: rems(0.875).into(),
-            font_weight: FontWeight::NORMAL,
-            font_style: FontStyle::Normal,
-            line_height: relative(1.3).into(),
-            background_color: None,
-            underline: None,
-            white_space: WhiteSpace::Normal,
-        };
-        EditorElement::new(
-            &editor,
-            EditorStyle {
-                background: cx.theme().colors().editor_background,
-                local_player: cx.theme().players().local(),
-                text: text_style,
-                ..Default::default()
-            },
-        )
+        single_line_editor_element(editor, cx)
     }
 
     fn render_hamburger_button(cx: &mut ViewContext<Self>) -> impl IntoElement {
@@ -992,6 +1094,7 @@ impl AssistantPanel {
             vec![
                 Self::render_split_button(cx).into_any_element(),
                 Self::render_quote_button(cx).into_any_element(),
+                Self::render_review_edits_button(cx).into_any_element(),
                 Self::render_assist_button(cx).into_any_element(),
             ]
         } else {
@@ -1021,6 +1124,19 @@ impl AssistantPanel {
             .tooltip(|cx| Tooltip::for_action("Assist", &Assist, cx))
     }
 
+    fn render_review_edits_button(cx: &mut ViewContext<Self>) -> impl IntoElement {
+        IconButton::new("review_edits_button", IconName::Replace)
+            .on_click(cx.listener(|this, _event, cx| {
+                if let Some(active_editor) = this.active_editor() {
+                    active_editor.update(cx, |editor, cx| {
+                        editor.review_edits(&Default::default(), cx)
+                    });
+                }
+            }))
+            .icon_size(IconSize::Small)
+            .tooltip(|cx| Tooltip::for_action("Review Proposed Edits", &ReviewEdits, cx))
+    }
+
     fn render_quote_button(cx: &mut ViewContext<Self>) -> impl IntoElement {
         IconButton::new("quote_button", IconName::Quote)
             .on_click(cx.listener(|this, _event, cx| {
@@ -1059,18 +1175,47 @@ impl AssistantPanel {
             })
     }
 
+    fn update_matching_conversations(&mut self, cx: &mut ViewContext<Self>) {
+        let query = HistoryQuery::parse(&self.history_query_editor.read(cx).text(cx));
+        self.matching_conversations = search_conversations(&self.saved_conversations, &query);
+        cx.notify();
+    }
+
+    fn render_history_query_editor(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        h_flex()
+            .gap_2()
+            .px_3()
+            .py_2()
+            .border_b_1()
+            .border_color(cx.theme().colors().border_variant)
+            .child(
+                Icon::new(IconName::MagnifyingGlass)
+                    .size(IconSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .child(single_line_editor_element(&self.history_query_editor, cx)),
+            )
+    }
+
     fn render_saved_conversation(
         &mut self,
         index: usize,
         cx: &mut ViewContext<Self>,
     ) -> impl IntoElement {
-        let conversation = &self.saved_conversations[index];
+        let conversation = &self.saved_conversations[self.matching_conversations[index]];
         let path = conversation.path.clone();
+        let pinned = conversation.pinned;
 
         ButtonLike::new(index)
-            .on_click(cx.listener(move |this, _, cx| {
-                this.open_conversation(path.clone(), cx)
-                    .detach_and_log_err(cx)
+            .on_click(cx.listener({
+                let path = path.clone();
+                move |this, _, cx| {
+                    this.open_conversation(path.clone(), cx)
+                        .detach_and_log_err(cx)
+                }
             }))
             .full_width()
             .child(
@@ -1078,15 +1223,108 @@ impl AssistantPanel {
                     .flex()
                     .w_full()
                     .gap_2()
+                    .items_center()
                     .child(
-                        Label::new(conversation.mtime.format("%F %I:%M%p").to_string())
-                            .color(Color::Muted)
-                            .size(LabelSize::Small),
+                        IconButton::new(("pin_saved_conversation", index), IconName::Pin)
+                            .icon_size(IconSize::XSmall)
+                            .selected(pinned)
+                            .tooltip(move |cx| {
+                                Tooltip::text(if pinned { "Unpin" } else { "Pin" }, cx)
+                            })
+                            .on_click(cx.listener(move |this, _, cx| {
+                                this.set_saved_conversation_pinned(path.clone(), !pinned, cx)
+                                    .detach_and_log_err(cx)
+                            })),
+                    )
+                    .child(
+                        Label::new(
+                            conversation
+                                .last_message_at
+                                .format("%F %I:%M%p")
+                                .to_string(),
+                        )
+                        .color(Color::Muted)
+                        .size(LabelSize::Small),
                     )
-                    .child(Label::new(conversation.title.clone()).size(LabelSize::Small)),
+                    .child(
+                        div()
+                            .flex_1()
+                            .child(Label::new(conversation.title.clone()).size(LabelSize::Small)),
+                    )
+                    .children(conversation.tags.iter().map(|tag| {
+                        Label::new(format!("#{tag}"))
+                            .color(Color::Muted)
+                            .size(LabelSize::XSmall)
+                    }))
+                    .child(
+                        Label::new(conversation.model.clone())
+                            .color(Color::Muted)
+                            .size(LabelSize::XSmall),
+                    ),
             )
     }
 
+    /// Pins or unpins a saved conversation, updating it in place if it is open.
+    fn set_saved_conversation_pinned(
+        &mut self,
+        path: PathBuf,
+        pinned: bool,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        if let Some(ix) = self.editor_index_for_path(&path, cx) {
+            let conversation = self.editors[ix].read(cx).conversation.clone();
+            conversation.update(cx, |conversation, cx| conversation.set_pinned(pinned, cx));
+            return Task::ready(Ok(()));
+        }
+
+        let fs = self.fs.clone();
+        cx.background_executor().spawn(async move {
+            let mut saved_conversation: SavedConversation =
+                serde_json::from_str(&fs.load(&path).await?)?;
+            saved_conversation.pinned = pinned;
+            fs.atomic_write(path.clone(), serde_json::to_string(&saved_conversation)?)
+                .await?;
+            ConversationIndex::update(fs.as_ref(), path, &saved_conversation).await
+        })
+    }
+
+    fn continue_in_new_conversation(
+        &mut self,
+        _: &ContinueInNewConversation,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(conversation) = self
+            .active_editor()
+            .map(|editor| editor.read(cx).conversation.clone())
+        else {
+            return;
+        };
+
+        let messages = conversation.read(cx).continuation_messages(cx);
+        let languages = self.languages.clone();
+        cx.spawn(|this, mut cx| async move {
+            let messages = messages.await?;
+            this.update(&mut cx, |this, cx| {
+                let continuation = conversation.update(cx, |conversation, cx| {
+                    conversation.continuation(messages, languages, cx)
+                });
+                continuation.update(cx, |continuation, cx| {
+                    continuation.save(None, this.fs.clone(), cx)
+                });
+                let editor = cx.new_view(|cx| {
+                    ConversationEditor::for_conversation(
+                        continuation,
+                        this.fs.clone(),
+                        this.workspace.clone(),
+                        cx,
+                    )
+                });
+                this.add_conversation(editor, cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
     fn open_conversation(&mut self, path: PathBuf, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
         cx.focus(&self.focus_handle);
 
@@ -1128,17 +1366,44 @@ impl AssistantPanel {
     }
 
     fn has_credentials(&mut self) -> bool {
-        self.completion_provider.has_credentials()
+        self.provider_missing_credentials().is_none()
+    }
+
+    /// The first provider that still needs an API key, along with its settings.
+    fn provider_missing_credentials(
+        &self,
+    ) -> Option<(&Arc<dyn CompletionProvider>, &CompletionProviderSettings)> {
+        if !self.completion_provider.has_credentials() {
+            Some((&self.completion_provider, &self.provider_settings))
+        } else if !self.inline_completion_provider.has_credentials() {
+            Some((
+                &self.inline_completion_provider,
+                &self.inline_provider_settings,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// The provider whose API key is requested when the API key editor is shown.
+    fn provider_for_api_key(&self) -> (&Arc<dyn CompletionProvider>, &CompletionProviderSettings) {
+        self.provider_missing_credentials()
+            .unwrap_or((&self.completion_provider, &self.provider_settings))
     }
 
     fn load_credentials(&mut self, cx: &mut ViewContext<Self>) -> Task<()> {
-        let completion_provider = self.completion_provider.clone();
+        let completion_providers = [
+            self.completion_provider.clone(),
+            self.inline_completion_provider.clone(),
+        ];
         cx.spawn(|_, mut cx| async move {
-            if let Some(retrieve_credentials) = cx
-                .update(|cx| completion_provider.retrieve_credentials(cx))
-                .log_err()
-            {
-                retrieve_credentials.await;
+            for completion_provider in completion_providers {
+                if let Some(retrieve_credentials) = cx
+                    .update(|cx| completion_provider.retrieve_credentials(cx))
+                    .log_err()
+                {
+                    retrieve_credentials.await;
+                }
             }
         })
     }
@@ -1155,7 +1420,7 @@ fn build_api_key_editor(cx: &mut WindowContext) -> View<Editor> {
 impl Render for AssistantPanel {
     fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
         if let Some(api_key_editor) = self.api_key_editor.clone() {
-            const INSTRUCTIONS: [&'static str; 6] = [
+            const OPEN_AI_INSTRUCTIONS: [&'static str; 6] = [
                 "To use the assistant panel or inline assistant, you need to add your OpenAI API key.",
                 " - You can create an API key at: platform.openai.com/api-keys",
                 " - Make sure your OpenAI account has credits",
@@ -1163,6 +1428,25 @@ impl Render for AssistantPanel {
                 " ",
                 "Paste your OpenAI API key and press Enter to use the assistant:"
             ];
+            const ANTHROPIC_INSTRUCTIONS: [&'static str; 6] = [
+                "To use the assistant panel or inline assistant, you need to add your Anthropic API key.",
+                " - You can create an API key at: console.anthropic.com/settings/keys",
+                " - Make sure your Anthropic account has credits",
+                " - Having a subscription for another service like GitHub Copilot won't work.",
+                " ",
+                "Paste your Anthropic API key and press Enter to use the assistant:"
+            ];
+            const ENDPOINT_INSTRUCTIONS: [&'static str; 4] = [
+                "The assistant is configured to use an OpenAI-compatible endpoint.",
+                " - Local servers like Ollama or llama.cpp's server usually don't need an API key",
+                " ",
+                "Paste the endpoint's API key and press Enter to use the assistant:",
+            ];
+            let instructions: &[&str] = match self.provider_for_api_key().1 {
+                CompletionProviderSettings::OpenAi { .. } => &OPEN_AI_INSTRUCTIONS,
+                CompletionProviderSettings::OpenAiCompatible { .. } => &ENDPOINT_INSTRUCTIONS,
+                CompletionProviderSettings::Anthropic { .. } => &ANTHROPIC_INSTRUCTIONS,
+            };
 
             v_flex()
                 .p_4()
@@ -1170,7 +1454,9 @@ impl Render for AssistantPanel {
                 .on_action(cx.listener(AssistantPanel::save_credentials))
                 .track_focus(&self.focus_handle)
                 .children(
-                    INSTRUCTIONS.map(|instruction| Label::new(instruction).size(LabelSize::Small)),
+                    instructions
+                        .iter()
+                        .map(|instruction| Label::new(*instruction).size(LabelSize::Small)),
                 )
                 .child(
                     h_flex()
@@ -1247,6 +1533,7 @@ impl Render for AssistantPanel {
                 .on_action(cx.listener(AssistantPanel::select_next_match))
                 .on_action(cx.listener(AssistantPanel::select_prev_match))
                 .on_action(cx.listener(AssistantPanel::handle_editor_cancel))
+                .on_action(cx.listener(AssistantPanel::continue_in_new_conversation))
                 .track_focus(&self.focus_handle)
                 .child(header)
                 .children(if self.toolbar.read(cx).hidden() {
@@ -1262,8 +1549,8 @@ impl Render for AssistantPanel {
                         } else {
                             let view = cx.view().clone();
                             let scroll_handle = self.saved_conversations_scroll_handle.clone();
-                            let conversation_count = self.saved_conversations.len();
-                            canvas(move |bounds, cx| {
+                            let conversation_count = self.matching_conversations.len();
+                            let saved_conversations = canvas(move |bounds, cx| {
                                 uniform_list(
                                     view,
                                     "saved_conversations",
@@ -1282,8 +1569,12 @@ impl Render for AssistantPanel {
                                     cx,
                                 );
                             })
-                            .size_full()
-                            .into_any_element()
+                            .size_full();
+                            v_flex()
+                                .size_full()
+                                .child(self.render_history_query_editor(cx))
+                                .child(div().flex_1().child(saved_conversations))
+                                .into_any_element()
                         }),
                 )
         }
@@ -1385,8 +1676,11 @@ impl FocusableView for AssistantPanel {
 
 enum ConversationEvent {
     MessagesEdited,
+    AttachmentsChanged,
+    LabelsChanged,
     SummaryChanged,
     StreamedCompletion,
+    ToolCallRequested,
 }
 
 #[derive(Default)]
@@ -1401,11 +1695,16 @@ struct Conversation {
     message_anchors: Vec<MessageAnchor>,
     messages_metadata: HashMap<MessageId, MessageMetadata>,
     next_message_id: MessageId,
+    attachments: Vec<Attachment>,
+    next_attachment_id: AttachmentId,
+    pending_attachment_token_count: Task<Option<()>>,
+    pinned: bool,
+    tags: Vec<String>,
     summary: Option<Summary>,
     pending_summary: Task<Option<()>>,
     completion_count: usize,
     pending_completions: Vec<PendingCompletion>,
-    model: OpenAiModel,
+    model: String,
     token_count: Option<usize>,
     max_token_count: usize,
     pending_token_count: Task<Option<()>>,
@@ -1413,6 +1712,12 @@ struct Conversation {
     path: Option<PathBuf>,
     _subscriptions: Vec<Subscription>,
     completion_provider: Arc<dyn CompletionProvider>,
+    provider_settings: CompletionProviderSettings,
+    pending_completion_provider: Task<Option<()>>,
+    pending_tool_call: Option<PendingToolCall>,
+    /// Dropping it cancels the tool call, killing the command it runs.
+    pending_tool_output: Option<Task<Option<()>>>,
+    tool_calls_in_turn: usize,
 }
 
 impl EventEmitter<ConversationEvent> for Conversation {}
@@ -1439,26 +1744,44 @@ impl Conversation {
         });
 
         let settings = AssistantSettings::get_global(cx);
-        let model = settings.default_open_ai_model.clone();
+        let model = settings.conversation_model(None);
+        let provider_settings = settings.provider.clone();
 
         let mut this = Self {
             id: Some(Uuid::new_v4().to_string()),
             message_anchors: Default::default(),
             messages_metadata: Default::default(),
             next_message_id: Default::default(),
+            attachments: Default::default(),
+            next_attachment_id: Default::default(),
+            pending_attachment_token_count: Task::ready(None),
+            pinned: false,
+            tags: Vec::new(),
             summary: None,
             pending_summary: Task::ready(None),
             completion_count: Default::default(),
             pending_completions: Default::default(),
             token_count: None,
-            max_token_count: tiktoken_rs::model::get_context_size(&model.full_name()),
+            max_token_count: completion_provider
+                .base_model()
+                .capacity()
+                .log_err()
+                .unwrap_or_default(),
             pending_token_count: Task::ready(None),
-            model: model.clone(),
-            _subscriptions: vec![cx.subscribe(&buffer, Self::handle_buffer_event)],
+            model,
+            _subscriptions: vec![
+                cx.subscribe(&buffer, Self::handle_buffer_event),
+                cx.observe_global::<SettingsStore>(Self::handle_settings_changed),
+            ],
             pending_save: Task::ready(Ok(())),
             path: None,
             buffer,
             completion_provider,
+            provider_settings,
+            pending_completion_provider: Task::ready(None),
+            pending_tool_call: None,
+            pending_tool_output: None,
+            tool_calls_in_turn: 0,
         };
         let message = MessageAnchor {
             id: MessageId(post_inc(&mut this.next_message_id.0)),
@@ -1498,6 +1821,13 @@ impl Conversation {
                 .map(|summary| summary.text.clone())
                 .unwrap_or_default(),
             model: self.model.clone(),
+            attachments: self
+                .attachments
+                .iter()
+                .map(|attachment| attachment.context.clone())
+                .collect(),
+            pinned: self.pinned,
+            tags: self.tags.clone(),
         }
     }
 
@@ -1511,14 +1841,16 @@ impl Conversation {
             Some(id) => Some(id),
             None => Some(Uuid::new_v4().to_string()),
         };
-        let model = saved_conversation.model;
-        let completion_provider: Arc<dyn CompletionProvider> = Arc::new(
-            OpenAiCompletionProvider::new(
-                model.full_name().into(),
-                cx.background_executor().clone(),
+        let (model, provider_settings) = cx.update(|cx| {
+            let settings = AssistantSettings::get_global(cx);
+            (
+                settings.conversation_model(Some(&saved_conversation.model)),
+                settings.provider.clone(),
             )
-            .await,
-        );
+        })?;
+        let completion_provider = provider_settings
+            .build(model.clone(), cx.background_executor().clone())
+            .await;
         cx.update(|cx| completion_provider.retrieve_credentials(cx))?
             .await;
 
@@ -1550,12 +1882,29 @@ impl Conversation {
             buffer
         })?;
 
+        let attachments = saved_conversation
+            .attachments
+            .into_iter()
+            .enumerate()
+            .map(|(ix, context)| Attachment {
+                id: AttachmentId(ix),
+                context,
+                token_count: None,
+            })
+            .collect::<Vec<_>>();
+        let next_attachment_id = AttachmentId(attachments.len());
+
         cx.new_model(|cx| {
             let mut this = Self {
                 id,
                 message_anchors,
                 messages_metadata: saved_conversation.message_metadata,
                 next_message_id,
+                attachments,
+                next_attachment_id,
+                pending_attachment_token_count: Task::ready(None),
+                pinned: saved_conversation.pinned,
+                tags: saved_conversation.tags,
                 summary: Some(Summary {
                     text: saved_conversation.summary,
                     done: true,
@@ -1564,15 +1913,28 @@ impl Conversation {
                 completion_count: Default::default(),
                 pending_completions: Default::default(),
                 token_count: None,
-                max_token_count: tiktoken_rs::model::get_context_size(&model.full_name()),
+                max_token_count: completion_provider
+                    .base_model()
+                    .capacity()
+                    .log_err()
+                    .unwrap_or_default(),
                 pending_token_count: Task::ready(None),
                 model,
-                _subscriptions: vec![cx.subscribe(&buffer, Self::handle_buffer_event)],
+                _subscriptions: vec![
+                    cx.subscribe(&buffer, Self::handle_buffer_event),
+                    cx.observe_global::<SettingsStore>(Self::handle_settings_changed),
+                ],
                 pending_save: Task::ready(Ok(())),
                 path: Some(path),
                 buffer,
                 completion_provider,
+                provider_settings,
+                pending_completion_provider: Task::ready(None),
+                pending_tool_call: None,
+                pending_tool_output: None,
+                tool_calls_in_turn: 0,
             };
+            this.count_attachment_tokens(cx);
             this.count_remaining_tokens(cx);
             this
         })
@@ -1593,44 +1955,48 @@ impl Conversation {
         }
     }
 
+    fn handle_settings_changed(&mut self, cx: &mut ModelContext<Self>) {
+        let settings = AssistantSettings::get_global(cx);
+        if settings.provider != self.provider_settings {
+            self.provider_settings = settings.provider.clone();
+            let model = settings.conversation_model(Some(&self.model));
+            self.set_model(model, cx);
+        }
+    }
+
     fn count_remaining_tokens(&mut self, cx: &mut ModelContext<Self>) {
-        let messages = self
+        // Messages are framed by a few tokens for their role and separators.
+        const TOKENS_PER_MESSAGE: usize = 4;
+
+        let mut messages = self
             .messages(cx)
-            .into_iter()
-            .filter_map(|message| {
-                Some(tiktoken_rs::ChatCompletionRequestMessage {
-                    role: match message.role {
-                        Role::User => "user".into(),
-                        Role::Assistant => "assistant".into(),
-                        Role::System => "system".into(),
-                    },
-                    content: Some(
-                        self.buffer
-                            .read(cx)
-                            .text_for_range(message.offset_range)
-                            .collect(),
-                    ),
-                    name: None,
-                    function_call: None,
-                })
+            .map(|message| {
+                self.buffer
+                    .read(cx)
+                    .text_for_range(message.offset_range)
+                    .collect::<String>()
             })
             .collect::<Vec<_>>();
-        let model = self.model.clone();
+        messages.push(self.system_prompt(cx));
+        let model = self.completion_provider.base_model();
         self.pending_token_count = cx.spawn(|this, mut cx| {
             async move {
                 cx.background_executor()
                     .timer(Duration::from_millis(200))
                     .await;
-                let token_count = cx
+                let (token_count, max_token_count) = cx
                     .background_executor()
                     .spawn(async move {
-                        tiktoken_rs::num_tokens_from_messages(&model.full_name(), &messages)
+                        let mut token_count = 0;
+                        for message in &messages {
+                            token_count += model.count_tokens(message)? + TOKENS_PER_MESSAGE;
+                        }
+                        anyhow::Ok((token_count, model.capacity()?))
                     })
                     .await?;
 
                 this.update(&mut cx, |this, cx| {
-                    this.max_token_count =
-                        tiktoken_rs::model::get_context_size(&this.model.full_name());
+                    this.max_token_count = max_token_count;
                     this.token_count = Some(token_count);
                     cx.notify()
                 })?;
@@ -1644,9 +2010,220 @@ impl Conversation {
         Some(self.max_token_count as isize - self.token_count? as isize)
     }
 
-    fn set_model(&mut self, model: OpenAiModel, cx: &mut ModelContext<Self>) {
-        self.model = model;
-        self.count_remaining_tokens(cx);
+    /// The system message that precedes the conversation, explaining how to
+    /// propose edits and use tools, and presenting any attachments to the model.
+    fn system_prompt(&self, cx: &AppContext) -> String {
+        let mut prompt = EDIT_FORMAT_PROMPT.to_string();
+        let settings = AssistantSettings::get_global(cx);
+        if settings.enable_tools {
+            prompt.push_str("\n\n");
+            prompt.push_str(&tools_prompt(&settings.tool_commands));
+        }
+        if let Some(attachments) = attachments_prompt(
+            self.attachments
+                .iter()
+                .map(|attachment| &attachment.context),
+        ) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&attachments);
+        }
+        prompt
+    }
+
+    /// Switches to the given model of the assistant's provider, rebuilding the
+    /// completion provider so that tokens are counted for the new model. The
+    /// conversation keeps using the current model until the new provider is ready.
+    fn set_model(&mut self, model: String, cx: &mut ModelContext<Self>) {
+        let provider_settings = self.provider_settings.clone();
+        let executor = cx.background_executor().clone();
+        self.pending_completion_provider = cx.spawn(|this, mut cx| {
+            async move {
+                let completion_provider = provider_settings.build(model.clone(), executor).await;
+                cx.update(|cx| completion_provider.retrieve_credentials(cx))?
+                    .await;
+                this.update(&mut cx, |this, cx| {
+                    this.model = model;
+                    this.completion_provider = completion_provider;
+                    for attachment in &mut this.attachments {
+                        attachment.token_count = None;
+                    }
+                    this.count_attachment_tokens(cx);
+                    this.count_remaining_tokens(cx);
+                    cx.notify();
+                })
+            }
+            .log_err()
+        });
+    }
+
+    fn set_pinned(&mut self, pinned: bool, cx: &mut ModelContext<Self>) {
+        self.pinned = pinned;
+        cx.emit(ConversationEvent::LabelsChanged);
+        cx.notify();
+    }
+
+    fn set_tags(&mut self, tags: Vec<String>, cx: &mut ModelContext<Self>) {
+        self.tags = tags;
+        cx.emit(ConversationEvent::LabelsChanged);
+        cx.notify();
+    }
+
+    /// Computes the messages of a conversation that continues this one in a
+    /// fresh context. The most recent messages are kept verbatim, and the ones
+    /// that don't fit alongside them are replaced by a summary.
+    fn continuation_messages(&self, cx: &AppContext) -> Task<Result<Vec<(Role, String)>>> {
+        const SUMMARY_PROMPT: &str = "Summarize the conversation so far, so that it can be \
+            continued without it. Keep every detail, code snippet and decision that is needed \
+            to carry on.";
+
+        let buffer = self.buffer.read(cx);
+        let messages = self
+            .messages(cx)
+            .filter(|message| matches!(message.status, MessageStatus::Done))
+            .map(|message| {
+                let text = buffer
+                    .text_for_range(message.offset_range)
+                    .collect::<String>();
+                (message.role, text.trim_end().to_string())
+            })
+            .filter(|(_, text)| !text.is_empty())
+            .collect::<Vec<_>>();
+        let system_prompt = self.system_prompt(cx);
+        let model = self.model.clone();
+        let completion_provider = self.completion_provider.clone();
+        let executor = cx.background_executor().clone();
+        cx.spawn(|_| async move {
+            let (token_counts, capacity) = executor
+                .spawn({
+                    let base_model = completion_provider.base_model();
+                    let texts = messages
+                        .iter()
+                        .map(|(_, text)| text.clone())
+                        .collect::<Vec<_>>();
+                    async move {
+                        let token_counts = texts
+                            .iter()
+                            .map(|text| base_model.count_tokens(text))
+                            .collect::<Result<Vec<_>>>()?;
+                        let capacity = base_model
+                            .capacity()?
+                            .saturating_sub(base_model.count_tokens(&system_prompt)?);
+                        anyhow::Ok((token_counts, capacity))
+                    }
+                })
+                .await?;
+
+            // Keep as many recent messages as fit in half of the context, to
+            // leave room for the conversation to continue.
+            let kept_start = fitting_suffix_start(&token_counts, capacity / 2);
+            if kept_start == 0 {
+                return Ok(messages);
+            }
+
+            // Summarize the remaining messages, leaving out the oldest ones if
+            // they don't fit in a single request either.
+            let summarized_start =
+                fitting_suffix_start(&token_counts[..kept_start], capacity * 3 / 4);
+            let request: Box<dyn CompletionRequest> = Box::new(OpenAiRequest {
+                model,
+                messages: messages[summarized_start..kept_start]
+                    .iter()
+                    .map(|(role, content)| RequestMessage {
+                        role: *role,
+                        content: content.clone(),
+                    })
+                    .chain(Some(RequestMessage {
+                        role: Role::User,
+                        content: SUMMARY_PROMPT.into(),
+                    }))
+                    .collect(),
+                stream: true,
+                stop: vec![],
+                temperature: 1.0,
+            });
+            let mut chunks = completion_provider.complete(request).await?;
+            let mut summary = String::new();
+            while let Some(chunk) = chunks.next().await {
+                summary.push_str(&chunk?);
+            }
+
+            Ok(iter::once((
+                Role::System,
+                format!("Summary of the earlier conversation:\n\n{}", summary.trim()),
+            ))
+            .chain(messages.into_iter().skip(kept_start))
+            .collect())
+        })
+    }
+
+    /// Creates a conversation that continues this one with the given messages,
+    /// keeping its model, attachments and tags.
+    fn continuation(
+        &self,
+        messages: Vec<(Role, String)>,
+        language_registry: Arc<LanguageRegistry>,
+        cx: &mut ModelContext<Self>,
+    ) -> Model<Self> {
+        let title = self
+            .summary
+            .as_ref()
+            .map_or("New Conversation", |summary| summary.text.trim());
+        let summary = Summary {
+            text: format!("{title} (continued)"),
+            done: true,
+        };
+        cx.new_model(|cx| {
+            let mut continuation =
+                Self::new(language_registry, cx, self.completion_provider.clone());
+            continuation.model = self.model.clone();
+            continuation.provider_settings = self.provider_settings.clone();
+            continuation.tags = self.tags.clone();
+            continuation.summary = Some(summary);
+            for attachment in &self.attachments {
+                continuation.attach(attachment.context.clone(), cx);
+            }
+            continuation.set_messages(messages, cx);
+            continuation
+        })
+    }
+
+    /// Replaces the messages of the conversation, leaving an empty user
+    /// message at the end for the next reply.
+    fn set_messages(&mut self, messages: Vec<(Role, String)>, cx: &mut ModelContext<Self>) {
+        let mut text = String::new();
+        let mut message_starts = Vec::new();
+        for (role, content) in messages
+            .into_iter()
+            .chain(Some((Role::User, String::new())))
+        {
+            if !message_starts.is_empty() {
+                text.push('\n');
+            }
+            message_starts.push((role, text.len()));
+            text.push_str(&content);
+        }
+
+        self.buffer
+            .update(cx, |buffer, cx| buffer.set_text(text, cx));
+        self.message_anchors.clear();
+        self.messages_metadata.clear();
+        let buffer = self.buffer.read(cx);
+        for (role, start) in message_starts {
+            let message = MessageAnchor {
+                id: MessageId(post_inc(&mut self.next_message_id.0)),
+                start: buffer.anchor_before(start),
+            };
+            self.messages_metadata.insert(
+                message.id,
+                MessageMetadata {
+                    role,
+                    sent_at: Local::now(),
+                    status: MessageStatus::Done,
+                },
+            );
+            self.message_anchors.push(message);
+        }
+        cx.emit(ConversationEvent::MessagesEdited);
         cx.notify();
     }
 
@@ -1698,12 +2275,18 @@ impl Conversation {
                 return Default::default();
             }
 
+            let system_message = RequestMessage {
+                role: Role::System,
+                content: self.system_prompt(cx),
+            };
             let request: Box<dyn CompletionRequest> = Box::new(OpenAiRequest {
-                model: self.model.full_name().to_string(),
-                messages: self
-                    .messages(cx)
-                    .filter(|message| matches!(message.status, MessageStatus::Done))
-                    .map(|message| message.to_open_ai_message(self.buffer.read(cx)))
+                model: self.model.clone(),
+                messages: iter::once(system_message)
+                    .chain(
+                        self.messages(cx)
+                            .filter(|message| matches!(message.status, MessageStatus::Done))
+                            .map(|message| message.to_open_ai_message(self.buffer.read(cx))),
+                    )
                     .collect(),
                 stream: true,
                 stop: vec![],
@@ -1719,6 +2302,7 @@ impl Conversation {
             let user_message = self
                 .insert_message_after(assistant_message.id, Role::User, MessageStatus::Done, cx)
                 .unwrap();
+            let reply_message_id = user_message.id;
             user_messages.push(user_message);
 
             let task = cx.spawn({
@@ -1761,6 +2345,7 @@ impl Conversation {
                     };
 
                     let result = stream_completion.await;
+                    let succeeded = result.is_ok();
 
                     this.update(&mut cx, |this, cx| {
                         if let Some(metadata) =
@@ -1778,6 +2363,10 @@ impl Conversation {
                             }
                             cx.notify();
                         }
+
+                        if succeeded {
+                            this.request_tool_call(assistant_message_id, reply_message_id, cx);
+                        }
                     })
                     .ok();
                 }
@@ -1793,7 +2382,7 @@ impl Conversation {
     }
 
     fn cancel_last_assist(&mut self) -> bool {
-        self.pending_completions.pop().is_some()
+        self.pending_tool_output.take().is_some() || self.pending_completions.pop().is_some()
     }
 
     fn cycle_message_roles(&mut self, ids: HashSet<MessageId>, cx: &mut ModelContext<Self>) {
@@ -1982,7 +2571,7 @@ impl Conversation {
                         .into(),
                 }));
             let request: Box<dyn CompletionRequest> = Box::new(OpenAiRequest {
-                model: self.model.full_name().to_string(),
+                model: self.model.clone(),
                 messages: messages.collect(),
                 stream: true,
                 stop: vec![],
@@ -2141,6 +2730,9 @@ impl Conversation {
                 fs.create_dir(CONVERSATIONS_DIR.as_ref()).await?;
                 fs.atomic_write(path.clone(), serde_json::to_string(&conversation).unwrap())
                     .await?;
+                ConversationIndex::update(fs.as_ref(), path.clone(), &conversation)
+                    .await
+                    .log_err();
                 this.update(&mut cx, |this, _| this.path = Some(path))?;
             }
 
@@ -2171,6 +2763,8 @@ struct ConversationEditor {
     editor: View<Editor>,
     blocks: HashSet<BlockId>,
     scroll_position: Option<ScrollPosition>,
+    tag_editor: Option<View<Editor>>,
+    pending_slash_commands: Option<Task<()>>,
     _subscriptions: Vec<Subscription>,
 }
 
@@ -2193,11 +2787,20 @@ impl ConversationEditor {
         workspace: WeakView<Workspace>,
         cx: &mut ViewContext<Self>,
     ) -> Self {
+        conversation.update(cx, |conversation, cx| {
+            conversation.buffer.update(cx, |buffer, cx| {
+                buffer.set_completion_triggers(vec!["/".into()], cx)
+            })
+        });
         let editor = cx.new_view(|cx| {
             let mut editor = Editor::for_buffer(conversation.read(cx).buffer.clone(), None, cx);
             editor.set_soft_wrap_mode(SoftWrap::EditorWidth, cx);
             editor.set_show_gutter(false, cx);
             editor.set_show_wrap_guides(false, cx);
+            editor.set_completion_provider(Box::new(SlashCommandCompletionProvider::new(
+                workspace.clone(),
+                fs.clone(),
+            )));
             editor
         });
 
@@ -2212,6 +2815,8 @@ impl ConversationEditor {
             editor,
             blocks: Default::default(),
             scroll_position: None,
+            tag_editor: None,
+            pending_slash_commands: None,
             fs,
             workspace,
             _subscriptions,
@@ -2221,6 +2826,19 @@ impl ConversationEditor {
     }
 
     fn assist(&mut self, _: &Assist, cx: &mut ViewContext<Self>) {
+        if self.pending_slash_commands.is_some() {
+            return;
+        }
+
+        let commands = self.slash_commands_in_selected_messages(cx);
+        if commands.is_empty() {
+            self.send_selected_messages(cx);
+        } else {
+            self.run_slash_commands(commands, cx);
+        }
+    }
+
+    fn send_selected_messages(&mut self, cx: &mut ViewContext<Self>) {
         report_assistant_event(
             self.workspace.clone(),
             self.conversation.read(cx).id.clone(),
@@ -2236,6 +2854,7 @@ impl ConversationEditor {
                 .into_iter()
                 .map(|message| message.id)
                 .collect();
+            conversation.tool_calls_in_turn = 0;
             conversation.assist(selected_messages, cx)
         });
         let new_selections = user_messages
@@ -2269,6 +2888,47 @@ impl ConversationEditor {
         }
     }
 
+    fn toggle_pinned(&mut self, _: &TogglePinned, cx: &mut ViewContext<Self>) {
+        self.conversation.update(cx, |conversation, cx| {
+            conversation.set_pinned(!conversation.pinned, cx)
+        });
+    }
+
+    fn edit_tags(&mut self, _: &EditTags, cx: &mut ViewContext<Self>) {
+        let tags = self.conversation.read(cx).tags.join(", ");
+        let tag_editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("Tags, separated by commas", cx);
+            editor.set_text(tags, cx);
+            editor.move_to_end(&editor::actions::MoveToEnd, cx);
+            editor
+        });
+        cx.focus_view(&tag_editor);
+        self.tag_editor = Some(tag_editor);
+        cx.notify();
+    }
+
+    fn confirm_tags(&mut self, _: &menu::Confirm, cx: &mut ViewContext<Self>) {
+        let Some(tag_editor) = self.tag_editor.take() else {
+            cx.propagate();
+            return;
+        };
+        let tags = parse_tags(&tag_editor.read(cx).text(cx));
+        self.conversation
+            .update(cx, |conversation, cx| conversation.set_tags(tags, cx));
+        cx.focus_view(&self.editor);
+        cx.notify();
+    }
+
+    fn cancel_tags(&mut self, _: &editor::actions::Cancel, cx: &mut ViewContext<Self>) {
+        if self.tag_editor.take().is_some() {
+            cx.focus_view(&self.editor);
+            cx.notify();
+        } else {
+            cx.propagate();
+        }
+    }
+
     fn cycle_message_role(&mut self, _: &CycleMessageRole, cx: &mut ViewContext<Self>) {
         let cursors = self.cursors(cx);
         self.conversation.update(cx, |conversation, cx| {
@@ -2302,12 +2962,18 @@ impl ConversationEditor {
                     conversation.save(Some(Duration::from_millis(500)), self.fs.clone(), cx);
                 });
             }
+            ConversationEvent::AttachmentsChanged | ConversationEvent::LabelsChanged => {
+                self.conversation.update(cx, |conversation, cx| {
+                    conversation.save(Some(Duration::from_millis(500)), self.fs.clone(), cx);
+                });
+            }
             ConversationEvent::SummaryChanged => {
                 cx.emit(ConversationEditorEvent::TabContentChanged);
                 self.conversation.update(cx, |conversation, cx| {
                     conversation.save(None, self.fs.clone(), cx);
                 });
             }
+            ConversationEvent::ToolCallRequested => self.request_tool_call_approval(cx),
             ConversationEvent::StreamedCompletion => {
                 self.editor.update(cx, |editor, cx| {
                     if let Some(scroll_position) = self.scroll_position {
@@ -2573,8 +3239,13 @@ impl ConversationEditor {
 
     fn cycle_model(&mut self, cx: &mut ViewContext<Self>) {
         self.conversation.update(cx, |conversation, cx| {
-            let new_model = conversation.model.cycle();
-            conversation.set_model(new_model, cx);
+            // Only OpenAI's models are known; other providers use the configured model.
+            if !conversation.provider_settings.is_open_ai() {
+                return;
+            }
+            let new_model = OpenAiModel::from_full_name(&conversation.model)
+                .map_or(OpenAiModel::Four, |model| model.cycle());
+            conversation.set_model(new_model.full_name().into(), cx);
         });
     }
 
@@ -2588,13 +3259,75 @@ impl ConversationEditor {
     }
 
     fn render_current_model(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        Button::new(
-            "current_model",
-            self.conversation.read(cx).model.short_name(),
+        let model = &self.conversation.read(cx).model;
+        let model_name = OpenAiModel::from_full_name(model)
+            .map_or_else(|| model.clone(), |model| model.short_name().to_string());
+        Button::new("current_model", model_name)
+            .style(ButtonStyle::Filled)
+            .tooltip(move |cx| Tooltip::text("Change Model", cx))
+            .on_click(cx.listener(|this, _, cx| this.cycle_model(cx)))
+    }
+
+    fn render_pin_button(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let pinned = self.conversation.read(cx).pinned;
+        IconButton::new("pin_conversation", IconName::Pin)
+            .icon_size(IconSize::Small)
+            .selected(pinned)
+            .tooltip(move |cx| {
+                Tooltip::for_action(if pinned { "Unpin" } else { "Pin" }, &TogglePinned, cx)
+            })
+            .on_click(cx.listener(|this, _, cx| this.toggle_pinned(&TogglePinned, cx)))
+    }
+
+    fn render_tags(&self, cx: &mut ViewContext<Self>) -> Option<AnyElement> {
+        let container = h_flex()
+            .gap_1()
+            .px_4()
+            .py_2()
+            .border_t_1()
+            .border_color(cx.theme().colors().border_variant)
+            .bg(cx.theme().colors().editor_background)
+            .child(
+                Icon::new(IconName::Hash)
+                    .size(IconSize::Small)
+                    .color(Color::Muted),
+            );
+
+        if let Some(tag_editor) = self.tag_editor.as_ref() {
+            return Some(
+                container
+                    .on_action(cx.listener(Self::confirm_tags))
+                    .on_action(cx.listener(Self::cancel_tags))
+                    .child(
+                        div()
+                            .flex_1()
+                            .child(single_line_editor_element(tag_editor, cx)),
+                    )
+                    .into_any_element(),
+            );
+        }
+
+        let tags = &self.conversation.read(cx).tags;
+        if tags.is_empty() {
+            return None;
+        }
+
+        Some(
+            container
+                .id("tags")
+                .flex_wrap()
+                .cursor_pointer()
+                .tooltip(|cx| Tooltip::for_action("Edit Tags", &EditTags, cx))
+                .on_click(cx.listener(|this, _, cx| this.edit_tags(&EditTags, cx)))
+                .children(tags.iter().map(|tag| {
+                    div()
+                        .px_1()
+                        .rounded_md()
+                        .bg(cx.theme().colors().element_background)
+                        .child(Label::new(tag.clone()).size(LabelSize::Small))
+                }))
+                .into_any_element(),
         )
-        .style(ButtonStyle::Filled)
-        .tooltip(move |cx| Tooltip::text("Change Model", cx))
-        .on_click(cx.listener(|this, _, cx| this.cycle_model(cx)))
     }
 
     fn render_remaining_tokens(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
@@ -2606,7 +3339,27 @@ impl ConversationEditor {
         } else {
             Color::Default
         };
-        Some(Label::new(remaining_tokens.to_string()).color(remaining_tokens_color))
+        Some(
+            h_flex()
+                .gap_1()
+                .when(remaining_tokens <= 500, |this| {
+                    this.child(
+                        IconButton::new("continue_in_new_conversation", IconName::ArrowUpRight)
+                            .icon_size(IconSize::Small)
+                            .tooltip(|cx| {
+                                Tooltip::for_action(
+                                    "Continue in New Conversation",
+                                    &ContinueInNewConversation,
+                                    cx,
+                                )
+                            })
+                            .on_click(|_, cx| {
+                                cx.dispatch_action(Box::new(ContinueInNewConversation))
+                            }),
+                    )
+                })
+                .child(Label::new(remaining_tokens.to_string()).color(remaining_tokens_color)),
+        )
     }
 }
 
@@ -2622,14 +3375,24 @@ impl Render for ConversationEditor {
             .capture_action(cx.listener(ConversationEditor::cycle_message_role))
             .on_action(cx.listener(ConversationEditor::assist))
             .on_action(cx.listener(ConversationEditor::split))
+            .on_action(cx.listener(ConversationEditor::review_edits))
+            .on_action(cx.listener(ConversationEditor::toggle_pinned))
+            .on_action(cx.listener(ConversationEditor::edit_tags))
             .size_full()
             .relative()
             .child(
-                div()
+                v_flex()
                     .size_full()
-                    .pl_4()
-                    .bg(cx.theme().colors().editor_background)
-                    .child(self.editor.clone()),
+                    .child(
+                        div()
+                            .flex_1()
+                            .w_full()
+                            .pl_4()
+                            .bg(cx.theme().colors().editor_background)
+                            .child(self.editor.clone()),
+                    )
+                    .children(self.render_tags(cx))
+                    .children(self.render_attachments(cx)),
             )
             .child(
                 h_flex()
@@ -2637,6 +3400,7 @@ impl Render for ConversationEditor {
                     .gap_1()
                     .top_3()
                     .right_5()
+                    .child(self.render_pin_button(cx))
                     .child(self.render_current_model(cx))
                     .children(self.render_remaining_tokens(cx)),
             )
@@ -2649,6 +3413,37 @@ impl FocusableView for ConversationEditor {
     }
 }
 
+/// Renders a single-line editor, such as a prompt or a search query, in the
+/// UI font.
+fn single_line_editor_element(editor: &View<Editor>, cx: &WindowContext) -> EditorElement {
+    let settings = ThemeSettings::get_global(cx);
+    let text_style = TextStyle {
+        color: if editor.read(cx).read_only(cx) {
+            cx.theme().colors().text_disabled
+        } else {
+            cx.theme().colors().text
+        },
+        font_family: settings.ui_font.family.clone(),
+        font_features: settings.ui_font.features,
+        font_size: rems(0.875).into(),
+        font_weight: FontWeight::NORMAL,
+        font_style: FontStyle::Normal,
+        line_height: relative(1.3).into(),
+        background_color: None,
+        underline: None,
+        white_space: WhiteSpace::Normal,
+    };
+    EditorElement::new(
+        editor,
+        EditorStyle {
+            background: cx.theme().colors().editor_background,
+            local_player: cx.theme().players().local(),
+            text: text_style,
+            ..Default::default()
+        },
+    )
+}
+
 #[derive(Clone, Debug)]
 struct MessageAnchor {
     id: MessageId,
@@ -3151,32 +3946,7 @@ impl InlineAssistant {
     }
 
     fn render_prompt_editor(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
-        let settings = ThemeSettings::get_global(cx);
-        let text_style = TextStyle {
-            color: if self.prompt_editor.read(cx).read_only(cx) {
-                cx.theme().colors().text_disabled
-            } else {
-                cx.theme().colors().text
-            },
-            font_family: settings.ui_font.family.clone(),
-            font_features: settings.ui_font.features,
-            font_size: rems(0.875).into(),
-            font_weight: FontWeight::NORMAL,
-            font_style: FontStyle::Normal,
-            line_height: relative(1.3).into(),
-            background_color: None,
-            underline: None,
-            white_space: WhiteSpace::Normal,
-        };
-        EditorElement::new(
-            &self.prompt_editor,
-            EditorStyle {
-                background: cx.theme().colors().editor_background,
-                local_player: cx.theme().players().local(),
-                text: text_style,
-                ..Default::default()
-            },
-        )
+        single_line_editor_element(&self.prompt_editor, cx)
     }
 }
 
@@ -3220,9 +3990,15 @@ fn merge_ranges(ranges: &mut Vec<Range<Anchor>>, buffer: &MultiBufferSnapshot) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::MessageId;
+    use crate::{
+        attachments::{AttachmentKind, ContextAttachment},
+        tools::ToolCall,
+        MessageId,
+    };
     use ai::test::FakeCompletionProvider;
+    use fs::FakeFs;
     use gpui::{AppContext, TestAppContext};
+    use serde_json::json;
     use settings::SettingsStore;
 
     #[gpui::test]
@@ -3605,6 +4381,252 @@ mod tests {
         );
     }
 
+    #[gpui::test]
+    async fn test_attachments(cx: &mut TestAppContext) {
+        let settings_store = cx.update(SettingsStore::test);
+        cx.set_global(settings_store);
+        cx.update(init);
+        let registry = Arc::new(LanguageRegistry::test());
+        let completion_provider = Arc::new(FakeCompletionProvider::new());
+        let conversation =
+            cx.new_model(|cx| Conversation::new(registry.clone(), cx, completion_provider));
+
+        let selection = ContextAttachment {
+            kind: AttachmentKind::Selection,
+            source: "src/lib.rs:1".into(),
+            language: Some("rust".into()),
+            text: "fn a() {}".into(),
+        };
+        let terminal_output = ContextAttachment {
+            kind: AttachmentKind::TerminalOutput,
+            source: "cargo check".into(),
+            language: None,
+            text: "error[E0425]".into(),
+        };
+        conversation.update(cx, |conversation, cx| {
+            conversation.attach(selection, cx);
+            conversation.attach(terminal_output.clone(), cx);
+        });
+        cx.run_until_parked();
+        assert_eq!(
+            attachments(&conversation, cx),
+            [(0, Some(9)), (1, Some(12))]
+        );
+
+        // Attachments are counted as part of the conversation.
+        cx.executor().advance_clock(Duration::from_secs(1));
+        cx.run_until_parked();
+        let prompt_tokens = conversation.read_with(cx, |conversation, cx| {
+            conversation.system_prompt(cx).chars().count()
+        });
+        assert_eq!(
+            conversation.read_with(cx, |conversation, _| conversation.token_count),
+            Some(prompt_tokens + 4 * 2)
+        );
+
+        conversation.update(cx, |conversation, cx| {
+            conversation.remove_attachment(AttachmentId(0), cx)
+        });
+        assert_eq!(attachments(&conversation, cx), [(1, Some(12))]);
+
+        let deserialized_conversation = Conversation::deserialize(
+            conversation.read_with(cx, |conversation, cx| conversation.serialize(cx)),
+            Default::default(),
+            registry.clone(),
+            &mut cx.to_async(),
+        )
+        .await
+        .unwrap();
+        cx.run_until_parked();
+        assert_eq!(attachments(&deserialized_conversation, cx), [(0, Some(12))]);
+        let deserialized_attachment = deserialized_conversation.read_with(cx, |conversation, _| {
+            conversation.attachments[0].context.clone()
+        });
+        assert_eq!(deserialized_attachment, terminal_output);
+
+        fn attachments(
+            conversation: &Model<Conversation>,
+            cx: &TestAppContext,
+        ) -> Vec<(usize, Option<usize>)> {
+            conversation.read_with(cx, |conversation, _| {
+                conversation
+                    .attachments
+                    .iter()
+                    .map(|attachment| (attachment.id.0, attachment.token_count))
+                    .collect()
+            })
+        }
+    }
+
+    #[gpui::test]
+    async fn test_continue_in_new_conversation(cx: &mut TestAppContext) {
+        let settings_store = cx.update(SettingsStore::test);
+        cx.set_global(settings_store);
+        cx.update(init);
+        let registry = Arc::new(LanguageRegistry::test());
+        let completion_provider = Arc::new(FakeCompletionProvider::new());
+        let conversation =
+            cx.new_model(|cx| Conversation::new(registry.clone(), cx, completion_provider.clone()));
+        conversation.update(cx, |conversation, cx| {
+            conversation.set_tags(vec!["rust".into()], cx);
+            conversation.set_messages(
+                vec![
+                    (Role::User, "a".repeat(2000)),
+                    (Role::Assistant, "b".repeat(2000)),
+                    (Role::User, "c".repeat(2000)),
+                ],
+                cx,
+            );
+        });
+
+        // Only the latest message fits in half of the context, so the ones
+        // before it are summarized.
+        let continued_messages = cx.update(|cx| conversation.read(cx).continuation_messages(cx));
+        cx.run_until_parked();
+        completion_provider.send_completion("The user sent a and b.");
+        completion_provider.finish_completion();
+        let continued_messages = continued_messages.await.unwrap();
+        assert_eq!(
+            continued_messages,
+            [
+                (
+                    Role::System,
+                    "Summary of the earlier conversation:\n\nThe user sent a and b.".into()
+                ),
+                (Role::User, "c".repeat(2000)),
+            ]
+        );
+
+        let continuation = conversation.update(cx, |conversation, cx| {
+            conversation.continuation(continued_messages, registry.clone(), cx)
+        });
+        continuation.read_with(cx, |continuation, cx| {
+            let buffer = continuation.buffer.read(cx);
+            assert_eq!(
+                continuation
+                    .messages(cx)
+                    .map(|message| (
+                        message.role,
+                        buffer
+                            .text_for_range(message.offset_range)
+                            .collect::<String>()
+                    ))
+                    .collect::<Vec<_>>(),
+                [
+                    (
+                        Role::System,
+                        "Summary of the earlier conversation:\n\nThe user sent a and b.\n".into()
+                    ),
+                    (Role::User, format!("{}\n", "c".repeat(2000))),
+                    (Role::User, String::new()),
+                ]
+            );
+            assert_eq!(continuation.tags, ["rust"]);
+            assert!(!continuation.pinned);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_tool_calls(cx: &mut TestAppContext) {
+        let settings_store = cx.update(SettingsStore::test);
+        cx.set_global(settings_store);
+        cx.update(|cx| {
+            language::init(cx);
+            Project::init_settings(cx);
+            init(cx);
+        });
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree("/root", json!({ "a.rs": "fn a() {}\nfn b() {}\n" }))
+            .await;
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+
+        let registry = Arc::new(LanguageRegistry::test());
+        let completion_provider = Arc::new(FakeCompletionProvider::new());
+        let conversation =
+            cx.new_model(|cx| Conversation::new(registry, cx, completion_provider.clone()));
+        conversation.update(cx, |conversation, cx| {
+            conversation.summary = Some(Summary {
+                text: "Tools".into(),
+                done: true,
+            });
+            conversation.buffer.update(cx, |buffer, cx| {
+                buffer.edit([(0..0, "What's in a.rs?")], None, cx)
+            });
+            let message_id = conversation.message_anchors[0].id;
+            conversation.assist(iter::once(message_id).collect(), cx);
+        });
+        cx.run_until_parked();
+        completion_provider.send_completion(
+            "Let me read it.\n```tool\n{\"tool\": \"read_file\", \"path\": \"a.rs\", \"end_line\": 1}\n```",
+        );
+        completion_provider.finish_completion();
+        cx.run_until_parked();
+        conversation.read_with(cx, |conversation, _| {
+            assert_eq!(
+                conversation.pending_tool_call.as_ref().unwrap().call,
+                ToolCall::ReadFile {
+                    path: "a.rs".into(),
+                    start_line: None,
+                    end_line: Some(1),
+                }
+            );
+        });
+
+        // Once approved, the output is sent back to the assistant as the
+        // user's reply.
+        conversation.update(cx, |conversation, cx| {
+            conversation.resolve_tool_call(true, project.clone(), cx)
+        });
+        cx.run_until_parked();
+        conversation.read_with(cx, |conversation, cx| {
+            let messages = conversation.messages(cx).collect::<Vec<_>>();
+            assert_eq!(
+                messages
+                    .iter()
+                    .map(|message| message.role)
+                    .collect::<Vec<_>>(),
+                [
+                    Role::User,
+                    Role::Assistant,
+                    Role::User,
+                    Role::Assistant,
+                    Role::User
+                ]
+            );
+            assert_eq!(
+                conversation
+                    .buffer
+                    .read(cx)
+                    .text_for_range(messages[2].offset_range.clone())
+                    .collect::<String>(),
+                "<tool_result tool=\"read_file\">\n1: fn a() {}\n</tool_result>\n"
+            );
+            assert!(matches!(messages[3].status, MessageStatus::Pending));
+        });
+
+        // A declined call is recorded without asking the assistant to continue.
+        completion_provider.send_completion("```tool\n{\"tool\": \"grep\", \"query\": \"b\"}\n```");
+        completion_provider.finish_completion();
+        cx.run_until_parked();
+        conversation.update(cx, |conversation, cx| {
+            assert!(conversation.pending_tool_call.is_some());
+            conversation.resolve_tool_call(false, project.clone(), cx)
+        });
+        cx.run_until_parked();
+        conversation.read_with(cx, |conversation, cx| {
+            let messages = conversation.messages(cx).collect::<Vec<_>>();
+            assert_eq!(messages.len(), 5);
+            assert_eq!(
+                conversation
+                    .buffer
+                    .read(cx)
+                    .text_for_range(messages[4].offset_range.clone())
+                    .collect::<String>(),
+                "<tool_result tool=\"grep\" error=\"true\">\nThe user declined to run this tool\n</tool_result>"
+            );
+        });
+    }
+
     fn messages(
         conversation: &Model<Conversation>,
         cx: &AppContext,
@@ -3630,9 +4652,8 @@ fn report_assistant_event(
     let client = workspace.read(cx).project().read(cx).client();
     let telemetry = client.telemetry();
 
-    let model = AssistantSettings::get_global(cx)
-        .default_open_ai_model
-        .clone();
+    let settings = AssistantSettings::get_global(cx);
+    let model = settings.default_model(&settings.provider);
 
-    telemetry.report_assistant_event(conversation_id, assistant_kind, model.full_name())
+    telemetry.report_assistant_event(conversation_id, assistant_kind, &model)
 }
@@ -0,0 +1,309 @@
+use super::{AssistantPanel, Conversation, ConversationEditor, ConversationEvent};
+use crate::{
+    attachments::{describe_location, severity_label, AttachmentKind, ContextAttachment},
+    AttachDiagnostics, AttachFile, AttachGitDiff, AttachSelection, AttachTerminalOutput,
+};
+use anyhow::Result;
+use editor::Editor;
+use gpui::{FocusableView, ModelContext};
+use language::Point;
+use std::fmt::Write;
+use terminal_view::{terminal_panel::TerminalPanel, TerminalView};
+use ui::{prelude::*, Tooltip};
+use util::{post_inc, TryFutureExt};
+use workspace::{Toast, Workspace};
+
+pub(super) const ATTACHMENT_TOAST_ID: usize = usize::MAX;
+const MAX_ATTACHED_TERMINAL_LINES: usize = 500;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(super) struct AttachmentId(pub(super) usize);
+
+pub(super) struct Attachment {
+    pub(super) id: AttachmentId,
+    pub(super) context: ContextAttachment,
+    /// The number of tokens in the attachment for the conversation's current model.
+    pub(super) token_count: Option<usize>,
+}
+
+impl Conversation {
+    pub(super) fn attach(&mut self, context: ContextAttachment, cx: &mut ModelContext<Self>) {
+        self.attachments.push(Attachment {
+            id: AttachmentId(post_inc(&mut self.next_attachment_id.0)),
+            context,
+            token_count: None,
+        });
+        self.count_attachment_tokens(cx);
+        self.count_remaining_tokens(cx);
+        cx.emit(ConversationEvent::AttachmentsChanged);
+        cx.notify();
+    }
+
+    pub(super) fn remove_attachment(&mut self, id: AttachmentId, cx: &mut ModelContext<Self>) {
+        self.attachments.retain(|attachment| attachment.id != id);
+        self.count_remaining_tokens(cx);
+        cx.emit(ConversationEvent::AttachmentsChanged);
+        cx.notify();
+    }
+
+    /// Counts the tokens of every attachment whose count isn't known for the
+    /// current model yet.
+    pub(super) fn count_attachment_tokens(&mut self, cx: &mut ModelContext<Self>) {
+        let uncounted = self
+            .attachments
+            .iter()
+            .filter(|attachment| attachment.token_count.is_none())
+            .map(|attachment| (attachment.id, attachment.context.text.clone()))
+            .collect::<Vec<_>>();
+        if uncounted.is_empty() {
+            return;
+        }
+
+        let model = self.completion_provider.base_model();
+        self.pending_attachment_token_count = cx.spawn(|this, mut cx| {
+            async move {
+                let token_counts = cx
+                    .background_executor()
+                    .spawn(async move {
+                        uncounted
+                            .into_iter()
+                            .map(|(id, text)| anyhow::Ok((id, model.count_tokens(&text)?)))
+                            .collect::<Result<Vec<_>>>()
+                    })
+                    .await?;
+
+                this.update(&mut cx, |this, cx| {
+                    for (id, token_count) in token_counts {
+                        if let Some(attachment) = this
+                            .attachments
+                            .iter_mut()
+                            .find(|attachment| attachment.id == id)
+                        {
+                            attachment.token_count = Some(token_count);
+                        }
+                    }
+                    cx.notify();
+                })
+            }
+            .log_err()
+        });
+    }
+}
+
+impl ConversationEditor {
+    pub(super) fn attach_selection(
+        workspace: &mut Workspace,
+        _: &AttachSelection,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let Some(attachment) = ContextAttachment::for_selection(workspace, cx) else {
+            workspace.show_toast(Toast::new(ATTACHMENT_TOAST_ID, "Nothing is selected"), cx);
+            return;
+        };
+        Self::attach_to_conversation(workspace, attachment, cx);
+    }
+
+    pub(super) fn attach_file(
+        workspace: &mut Workspace,
+        _: &AttachFile,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        if let Some(attachment) = ContextAttachment::for_active_file(workspace, cx) {
+            Self::attach_to_conversation(workspace, attachment, cx);
+        }
+    }
+
+    pub(super) fn attach_diagnostics(
+        workspace: &mut Workspace,
+        _: &AttachDiagnostics,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let Some(editor) = workspace
+            .active_item(cx)
+            .and_then(|item| item.act_as::<Editor>(cx))
+        else {
+            return;
+        };
+
+        let editor = editor.read(cx);
+        let cursor = editor.selections.newest::<usize>(cx).head();
+        let buffer = editor.buffer().read(cx).snapshot(cx);
+        let mut text = String::new();
+        for entry in buffer.diagnostics_in_range::<_, usize>(cursor..cursor, false) {
+            let severity = severity_label(entry.diagnostic.severity);
+            let start = buffer.offset_to_point(entry.range.start);
+            let end = buffer.offset_to_point(entry.range.end);
+            let lines = Point::new(start.row, 0)..Point::new(end.row, buffer.line_len(end.row));
+            writeln!(
+                text,
+                "{severity} at {}: {}",
+                describe_location(&buffer, entry.range.clone()),
+                entry.diagnostic.message
+            )
+            .unwrap();
+            text.extend(buffer.text_for_range(lines));
+            text.push_str("\n\n");
+        }
+
+        if text.is_empty() {
+            workspace.show_toast(
+                Toast::new(
+                    ATTACHMENT_TOAST_ID,
+                    "There are no diagnostics at the cursor",
+                ),
+                cx,
+            );
+            return;
+        }
+
+        let attachment = ContextAttachment {
+            kind: AttachmentKind::Diagnostics,
+            source: describe_location(&buffer, cursor..cursor),
+            language: None,
+            text: text.trim_end().to_string(),
+        };
+        Self::attach_to_conversation(workspace, attachment, cx);
+    }
+
+    pub(super) fn attach_git_diff(
+        workspace: &mut Workspace,
+        _: &AttachGitDiff,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let diff = ContextAttachment::for_git_diff(workspace.project().read(cx), cx);
+        cx.spawn(|workspace, mut cx| async move {
+            let attachment = diff.await?;
+            workspace.update(&mut cx, |workspace, cx| {
+                let Some(attachment) = attachment else {
+                    workspace.show_toast(
+                        Toast::new(ATTACHMENT_TOAST_ID, "There are no uncommitted changes"),
+                        cx,
+                    );
+                    return;
+                };
+                Self::attach_to_conversation(workspace, attachment, cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub(super) fn attach_terminal_output(
+        workspace: &mut Workspace,
+        _: &AttachTerminalOutput,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        // Prefer a terminal in the center over the one in the terminal panel.
+        let terminal_view = workspace
+            .active_item(cx)
+            .and_then(|item| item.downcast::<TerminalView>())
+            .or_else(|| {
+                workspace
+                    .panel::<TerminalPanel>(cx)?
+                    .read(cx)
+                    .active_terminal(cx)
+            });
+        let Some(terminal_view) = terminal_view else {
+            workspace.show_toast(
+                Toast::new(ATTACHMENT_TOAST_ID, "There is no open terminal"),
+                cx,
+            );
+            return;
+        };
+
+        let terminal = terminal_view.read(cx).terminal().read(cx);
+        let source = terminal.title(true);
+        let output = terminal.text_snapshot(MAX_ATTACHED_TERMINAL_LINES, cx);
+        cx.spawn(|workspace, mut cx| async move {
+            let text = output.await;
+            workspace.update(&mut cx, |workspace, cx| {
+                let attachment = ContextAttachment {
+                    kind: AttachmentKind::TerminalOutput,
+                    source,
+                    language: None,
+                    text,
+                };
+                Self::attach_to_conversation(workspace, attachment, cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    /// Attaches context to the active conversation, starting a new one if needed.
+    fn attach_to_conversation(
+        workspace: &mut Workspace,
+        attachment: ContextAttachment,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let Some(panel) = workspace.panel::<AssistantPanel>(cx) else {
+            return;
+        };
+
+        // Activate the panel
+        if !panel.focus_handle(cx).contains_focused(cx) {
+            workspace.toggle_panel_focus::<AssistantPanel>(cx);
+        }
+
+        panel.update(cx, |panel, cx| {
+            let conversation = panel
+                .active_editor()
+                .cloned()
+                .unwrap_or_else(|| panel.new_conversation(cx));
+            conversation.update(cx, |conversation, cx| {
+                conversation
+                    .conversation
+                    .update(cx, |conversation, cx| conversation.attach(attachment, cx))
+            });
+        });
+    }
+
+    pub(super) fn render_attachments(
+        &self,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<impl IntoElement> {
+        let attachments = &self.conversation.read(cx).attachments;
+        if attachments.is_empty() {
+            return None;
+        }
+
+        Some(
+            h_flex()
+                .flex_wrap()
+                .gap_1()
+                .px_4()
+                .py_2()
+                .border_t_1()
+                .border_color(cx.theme().colors().border_variant)
+                .bg(cx.theme().colors().editor_background)
+                .children(attachments.iter().map(|attachment| {
+                    let id = attachment.id;
+                    h_flex()
+                        .id(("attachment", id.0))
+                        .gap_1()
+                        .pl_1()
+                        .rounded_md()
+                        .bg(cx.theme().colors().element_background)
+                        .child(
+                            Icon::new(attachment.context.kind.icon())
+                                .size(IconSize::Small)
+                                .color(Color::Muted),
+                        )
+                        .child(Label::new(attachment.context.source.clone()).size(LabelSize::Small))
+                        .children(attachment.token_count.map(|token_count| {
+                            Label::new(format!("{token_count} tokens"))
+                                .size(LabelSize::XSmall)
+                                .color(Color::Muted)
+                        }))
+                        .child(
+                            IconButton::new(("remove_attachment", id.0), IconName::Close)
+                                .icon_size(IconSize::XSmall)
+                                .tooltip(|cx| Tooltip::text("Remove Attachment", cx))
+                                .on_click(cx.listener(move |this, _, cx| {
+                                    this.conversation.update(cx, |conversation, cx| {
+                                        conversation.remove_attachment(id, cx)
+                                    })
+                                })),
+                        )
+                })),
+        )
+    }
+}
@@ -0,0 +1,77 @@
+use super::ConversationEditor;
+use crate::{
+    edit_proposals::parse_edit_proposals,
+    edit_review::{resolve_edit_proposals, EditReview},
+    ReviewEdits, Role,
+};
+use gpui::{ViewContext, VisualContext};
+use workspace::Toast;
+
+const REVIEW_EDITS_TOAST_ID: usize = usize::MAX - 1;
+
+impl ConversationEditor {
+    /// Opens the edits proposed in the assistant's message at the cursor, or in
+    /// its latest message, for review.
+    pub(super) fn review_edits(&mut self, _: &ReviewEdits, cx: &mut ViewContext<Self>) {
+        let cursor = self.editor.read(cx).selections.newest::<usize>(cx).head();
+        let conversation = self.conversation.read(cx);
+        let message = conversation
+            .messages_for_offsets([cursor], cx)
+            .into_iter()
+            .find(|message| message.role == Role::Assistant)
+            .or_else(|| {
+                conversation
+                    .messages(cx)
+                    .filter(|message| message.role == Role::Assistant)
+                    .last()
+            });
+        let proposals = message.map_or_else(Vec::new, |message| {
+            let text = conversation
+                .buffer
+                .read(cx)
+                .text_for_range(message.offset_range)
+                .collect::<String>();
+            parse_edit_proposals(&text)
+        });
+
+        let workspace = self.workspace.clone();
+        if proposals.is_empty() {
+            workspace
+                .update(cx, |workspace, cx| {
+                    workspace.show_toast(
+                        Toast::new(
+                            REVIEW_EDITS_TOAST_ID,
+                            "The assistant's message doesn't propose any edits",
+                        ),
+                        cx,
+                    )
+                })
+                .ok();
+            return;
+        }
+
+        let Some(project) = workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return;
+        };
+        cx.spawn(|_, mut cx| async move {
+            let resolved = resolve_edit_proposals(project.clone(), proposals, &mut cx).await?;
+            workspace.update(&mut cx, |workspace, cx| {
+                if resolved.is_empty() {
+                    let message = format!(
+                        "None of the proposed edits could be located:\n{}",
+                        resolved.unresolved().join("\n")
+                    );
+                    workspace.show_toast(Toast::new(REVIEW_EDITS_TOAST_ID, message), cx);
+                    return;
+                }
+
+                let review = cx.new_view(|cx| EditReview::new(project, resolved, cx));
+                workspace.add_item(Box::new(review), cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+}
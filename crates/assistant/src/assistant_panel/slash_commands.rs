@@ -0,0 +1,136 @@
+use super::{attachments::ATTACHMENT_TOAST_ID, ConversationEditor};
+use crate::{
+    prompt_templates::UserPrompt,
+    slash_commands::{run_slash_command, slash_commands_in, Expansion, SlashCommand},
+    Role,
+};
+use ai::models::LanguageModel;
+use gpui::{AppContext, ViewContext};
+use language::OffsetRangeExt as _;
+use std::{ops::Range, sync::Arc};
+use util::ResultExt;
+use workspace::Toast;
+
+/// Tokens left for the assistant's reply when expanding a user prompt.
+const PROMPT_RESPONSE_TOKENS: usize = 1024;
+
+impl ConversationEditor {
+    /// Finds the slash commands in the user messages that are about to be sent.
+    pub(super) fn slash_commands_in_selected_messages(
+        &self,
+        cx: &AppContext,
+    ) -> Vec<(Range<language::Anchor>, SlashCommand)> {
+        let cursors = self.cursors(cx);
+        let conversation = self.conversation.read(cx);
+        let buffer = conversation.buffer.read(cx);
+        let mut commands = Vec::new();
+        for message in conversation.messages_for_offsets(cursors, cx) {
+            if message.role != Role::User {
+                continue;
+            }
+
+            let text = buffer
+                .text_for_range(message.offset_range.clone())
+                .collect::<String>();
+            let message_start = message.offset_range.start;
+            for (range, command) in slash_commands_in(&text) {
+                let start = buffer.anchor_after(message_start + range.start);
+                let end = buffer.anchor_before(message_start + range.end);
+                commands.push((start..end, command));
+            }
+        }
+        commands
+    }
+
+    /// Replaces slash commands with the context they refer to, then sends the
+    /// messages they were typed in. If any of them fails, nothing is sent.
+    pub(super) fn run_slash_commands(
+        &mut self,
+        commands: Vec<(Range<language::Anchor>, SlashCommand)>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let conversation = self.conversation.read(cx);
+        let model: Arc<dyn LanguageModel> = conversation.completion_provider.base_model().into();
+        let reserved_tokens = conversation.token_count.unwrap_or(0) + PROMPT_RESPONSE_TOKENS;
+        let workspace = self.workspace.clone();
+        let fs = self.fs.clone();
+        self.pending_slash_commands = Some(cx.spawn(|this, mut cx| async move {
+            let expansions = async {
+                let prompts = UserPrompt::list(fs).await.log_err().unwrap_or_default();
+                let mut expansions = Vec::new();
+                for (range, command) in commands {
+                    let expansion = workspace
+                        .update(&mut cx, |workspace, cx| {
+                            run_slash_command(
+                                command,
+                                &prompts,
+                                model.clone(),
+                                reserved_tokens,
+                                workspace,
+                                cx,
+                            )
+                        })?
+                        .await?;
+                    expansions.extend(expansion.map(|expansion| (range, expansion)));
+                }
+                anyhow::Ok(expansions)
+            }
+            .await;
+
+            this.update(&mut cx, |this, cx| {
+                this.pending_slash_commands = None;
+                match expansions {
+                    Ok(expansions) => {
+                        this.apply_slash_command_expansions(expansions, cx);
+                        this.send_selected_messages(cx);
+                    }
+                    Err(error) => {
+                        this.workspace
+                            .update(cx, |workspace, cx| {
+                                workspace.show_toast(
+                                    Toast::new(ATTACHMENT_TOAST_ID, error.to_string()),
+                                    cx,
+                                )
+                            })
+                            .ok();
+                    }
+                }
+            })
+            .ok();
+        }));
+    }
+
+    fn apply_slash_command_expansions(
+        &mut self,
+        expansions: Vec<(Range<language::Anchor>, Expansion)>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.conversation.update(cx, |conversation, cx| {
+            let buffer = conversation.buffer.read(cx);
+            let mut edits = Vec::new();
+            let mut attachments = Vec::new();
+            for (range, expansion) in expansions {
+                let range = range.to_offset(buffer);
+                match expansion {
+                    Expansion::Attachment(attachment) => {
+                        attachments.push(attachment);
+                        edits.push((range, String::new()));
+                    }
+                    Expansion::Text(mut text) => {
+                        if buffer.reversed_chars_for_range(range.clone()).next() == Some('\n') {
+                            text.push('\n');
+                        }
+                        edits.push((range, text));
+                    }
+                }
+            }
+
+            conversation
+                .buffer
+                .update(cx, |buffer, cx| buffer.edit(edits, None, cx));
+            for attachment in attachments {
+                conversation.attach(attachment, cx);
+            }
+        });
+    }
+}
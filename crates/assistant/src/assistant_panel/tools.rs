@@ -0,0 +1,134 @@
+use super::{Conversation, ConversationEditor, ConversationEvent};
+use crate::{
+    assistant_settings::AssistantSettings,
+    tools::{parse_tool_call, tool_result, ToolCall},
+    MessageId,
+};
+use anyhow::anyhow;
+use gpui::{Model, ModelContext, PromptLevel, Task, ViewContext};
+use project::Project;
+use settings::Settings;
+use std::iter;
+
+/// How many tools the assistant can call before the user sends another message.
+const MAX_TOOL_CALLS_PER_TURN: usize = 10;
+
+/// A tool call that is waiting for the user's approval.
+pub(super) struct PendingToolCall {
+    pub(super) call: ToolCall,
+    /// The user message that the call's output is inserted into.
+    reply_message_id: MessageId,
+}
+
+impl Conversation {
+    /// Asks the user to approve the tool call that ends the given assistant
+    /// message, if there is one.
+    pub(super) fn request_tool_call(
+        &mut self,
+        message_id: MessageId,
+        reply_message_id: MessageId,
+        cx: &mut ModelContext<Self>,
+    ) {
+        if !AssistantSettings::get_global(cx).enable_tools
+            || self.tool_calls_in_turn >= MAX_TOOL_CALLS_PER_TURN
+        {
+            return;
+        }
+
+        let Some(message) = self.messages(cx).find(|message| message.id == message_id) else {
+            return;
+        };
+        let text = self
+            .buffer
+            .read(cx)
+            .text_for_range(message.offset_range)
+            .collect::<String>();
+        if let Some(call) = parse_tool_call(&text) {
+            self.pending_tool_call = Some(PendingToolCall {
+                call,
+                reply_message_id,
+            });
+            cx.emit(ConversationEvent::ToolCallRequested);
+        }
+    }
+
+    /// Runs the pending tool call if the user approved it, inserting its output
+    /// into the user's reply, which is then sent to the assistant. A declined
+    /// call is recorded in the reply without sending it.
+    pub(super) fn resolve_tool_call(
+        &mut self,
+        approved: bool,
+        project: Model<Project>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let Some(PendingToolCall {
+            call,
+            reply_message_id,
+        }) = self.pending_tool_call.take()
+        else {
+            return;
+        };
+
+        let output = if approved {
+            let commands = AssistantSettings::get_global(cx).tool_commands.clone();
+            call.run(&commands, project, cx)
+        } else {
+            Task::ready(Err(anyhow!("The user declined to run this tool")))
+        };
+        self.pending_tool_output = Some(cx.spawn(|this, mut cx| async move {
+            let output = output.await;
+            this.update(&mut cx, |this, cx| {
+                this.pending_tool_output = None;
+                let reply = this
+                    .messages(cx)
+                    .find(|message| message.id == reply_message_id)?;
+                let mut result = tool_result(&call, &output);
+                if !reply.offset_range.is_empty() {
+                    result.push('\n');
+                }
+                this.buffer.update(cx, |buffer, cx| {
+                    let start = reply.offset_range.start;
+                    buffer.edit([(start..start, result)], None, cx)
+                });
+
+                if approved {
+                    this.tool_calls_in_turn += 1;
+                    this.assist(iter::once(reply_message_id).collect(), cx);
+                }
+                Some(())
+            })
+            .ok()?
+        }));
+    }
+}
+
+impl ConversationEditor {
+    pub(super) fn request_tool_call_approval(&mut self, cx: &mut ViewContext<Self>) {
+        let Some(project) = self
+            .workspace
+            .upgrade()
+            .map(|workspace| workspace.read(cx).project().clone())
+        else {
+            return;
+        };
+        let Some(pending_call) = self.conversation.read(cx).pending_tool_call.as_ref() else {
+            return;
+        };
+
+        let commands = &AssistantSettings::get_global(cx).tool_commands;
+        let message = format!(
+            "The assistant wants to {}.",
+            pending_call.call.description(commands)
+        );
+        let answer = cx.prompt(PromptLevel::Info, &message, None, &["Allow", "Deny"]);
+        cx.spawn(|this, mut cx| async move {
+            let approved = answer.await? == 0;
+            this.update(&mut cx, |this, cx| {
+                this.conversation.update(cx, |conversation, cx| {
+                    conversation.resolve_tool_call(approved, project, cx)
+                })
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+}
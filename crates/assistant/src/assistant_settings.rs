@@ -1,4 +1,6 @@
+use ai::providers::CompletionProviderSettings;
 use anyhow;
+use collections::HashMap;
 use gpui::Pixels;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -15,6 +17,12 @@ pub enum OpenAiModel {
 }
 
 impl OpenAiModel {
+    pub fn from_full_name(full_name: &str) -> Option<Self> {
+        [Self::ThreePointFiveTurbo, Self::Four, Self::FourTurbo]
+            .into_iter()
+            .find(|model| model.full_name() == full_name)
+    }
+
     pub fn full_name(&self) -> &'static str {
         match self {
             OpenAiModel::ThreePointFiveTurbo => "gpt-3.5-turbo-0613",
@@ -55,6 +63,40 @@ pub struct AssistantSettings {
     pub default_width: Pixels,
     pub default_height: Pixels,
     pub default_open_ai_model: OpenAiModel,
+    pub provider: CompletionProviderSettings,
+    pub inline_provider: Option<CompletionProviderSettings>,
+    pub enable_tools: bool,
+    pub tool_commands: HashMap<String, String>,
+}
+
+impl AssistantSettings {
+    /// The provider used by the inline assistant.
+    pub fn inline_provider(&self) -> &CompletionProviderSettings {
+        self.inline_provider.as_ref().unwrap_or(&self.provider)
+    }
+
+    /// The model used when starting to work with the given provider.
+    pub fn default_model(&self, provider: &CompletionProviderSettings) -> String {
+        provider
+            .model()
+            .unwrap_or(self.default_open_ai_model.full_name())
+            .to_string()
+    }
+
+    /// The model a conversation should use with the assistant panel's provider. A
+    /// conversation's own model is only kept if the provider serves it.
+    pub fn conversation_model(&self, model: Option<&str>) -> String {
+        match model {
+            Some(model)
+                if self.provider.model() == Some(model)
+                    || (self.provider.is_open_ai()
+                        && OpenAiModel::from_full_name(model).is_some()) =>
+            {
+                model.to_string()
+            }
+            _ => self.default_model(&self.provider),
+        }
+    }
 }
 
 /// Assistant panel settings
@@ -80,6 +122,27 @@ pub struct AssistantSettingsContent {
     ///
     /// Default: gpt-4-1106-preview
     pub default_open_ai_model: Option<OpenAiModel>,
+    /// The service that completes prompts in the assistant panel, e.g.
+    /// `{ "name": "openai_compatible", "api_url": "http://localhost:11434/v1", "model": "codellama" }`.
+    ///
+    /// Default: { "name": "openai" }
+    pub provider: Option<CompletionProviderSettings>,
+    /// The service that completes prompts for the inline assistant. When unset, the
+    /// assistant panel's provider is used.
+    ///
+    /// Default: null
+    pub inline_provider: Option<CompletionProviderSettings>,
+    /// Whether the assistant panel can ask to read the project's files, search
+    /// it, list its symbols and run the commands in `tool_commands`. Each call
+    /// has to be approved.
+    ///
+    /// Default: true
+    pub enable_tools: Option<bool>,
+    /// Commands that the assistant panel can ask to run in the project's root,
+    /// keyed by name, e.g. `{ "test": "cargo test" }`.
+    ///
+    /// Default: {}
+    pub tool_commands: Option<HashMap<String, String>>,
 }
 
 impl Settings for AssistantSettings {
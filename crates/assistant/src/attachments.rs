@@ -0,0 +1,248 @@
+use anyhow::Result;
+use editor::{Editor, MultiBufferSnapshot};
+use gpui::{AppContext, Task};
+use language::{Buffer, DiagnosticSeverity};
+use project::Project;
+use serde::{Deserialize, Serialize};
+use std::{fmt::Write, ops::Range};
+use ui::IconName;
+use workspace::Workspace;
+
+/// Where the content of a [`ContextAttachment`] came from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    Selection,
+    File,
+    Diagnostics,
+    GitDiff,
+    TerminalOutput,
+    Symbol,
+    SearchResults,
+}
+
+impl AttachmentKind {
+    pub fn icon(&self) -> IconName {
+        match self {
+            AttachmentKind::Selection => IconName::Quote,
+            AttachmentKind::File => IconName::File,
+            AttachmentKind::Diagnostics => IconName::ExclamationTriangle,
+            AttachmentKind::GitDiff => IconName::FileGit,
+            AttachmentKind::TerminalOutput => IconName::Terminal,
+            AttachmentKind::Symbol => IconName::Hash,
+            AttachmentKind::SearchResults => IconName::MagnifyingGlass,
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            AttachmentKind::Selection => "selection",
+            AttachmentKind::File => "file",
+            AttachmentKind::Diagnostics => "diagnostics",
+            AttachmentKind::GitDiff => "git_diff",
+            AttachmentKind::TerminalOutput => "terminal_output",
+            AttachmentKind::Symbol => "symbol",
+            AttachmentKind::SearchResults => "search_results",
+        }
+    }
+}
+
+/// A piece of context that the user explicitly attached to a conversation.
+///
+/// The content is captured when the attachment is created, so that what the
+/// user sees in the conversation is exactly what gets sent to the model.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContextAttachment {
+    pub kind: AttachmentKind,
+    /// A short description of where the content came from, e.g. a path and a
+    /// line range, shown on the attachment's chip.
+    pub source: String,
+    /// The language used to fence the content, if any.
+    pub language: Option<String>,
+    pub text: String,
+}
+
+impl ContextAttachment {
+    /// Formats the attachment as a delimited section of the prompt.
+    pub fn to_prompt_section(&self) -> String {
+        let tag = self.kind.tag();
+        let language = self.language.as_deref().unwrap_or("");
+        let mut section = String::new();
+        writeln!(section, "<{tag} source=\"{}\">", self.source).unwrap();
+        writeln!(section, "```{language}").unwrap();
+        section.push_str(&self.text);
+        if !self.text.ends_with('\n') {
+            section.push('\n');
+        }
+        writeln!(section, "```").unwrap();
+        write!(section, "</{tag}>").unwrap();
+        section
+    }
+
+    /// Captures the newest selection in the active editor, if it isn't empty.
+    pub(crate) fn for_selection(workspace: &Workspace, cx: &AppContext) -> Option<Self> {
+        let editor = workspace
+            .active_item(cx)
+            .and_then(|item| item.act_as::<Editor>(cx))?;
+        let editor = editor.read(cx);
+        let range = editor.selections.newest::<usize>(cx).range();
+        if range.is_empty() {
+            return None;
+        }
+
+        let buffer = editor.buffer().read(cx).snapshot(cx);
+        Some(Self {
+            kind: AttachmentKind::Selection,
+            source: describe_location(&buffer, range.clone()),
+            language: buffer
+                .language_at(range.start)
+                .map(|language| language.name().to_lowercase()),
+            text: buffer.text_for_range(range).collect(),
+        })
+    }
+
+    /// Captures the whole file open in the active editor.
+    pub(crate) fn for_active_file(workspace: &Workspace, cx: &AppContext) -> Option<Self> {
+        let buffer = workspace
+            .active_item(cx)
+            .and_then(|item| item.act_as::<Editor>(cx))
+            .and_then(|editor| editor.read(cx).buffer().read(cx).as_singleton())?;
+        Some(Self::for_buffer(buffer.read(cx)))
+    }
+
+    pub(crate) fn for_buffer(buffer: &Buffer) -> Self {
+        Self {
+            kind: AttachmentKind::File,
+            source: buffer.file().map_or_else(
+                || "untitled".into(),
+                |file| file.path().to_string_lossy().into_owned(),
+            ),
+            language: buffer
+                .language()
+                .map(|language| language.name().to_lowercase()),
+            text: buffer.text(),
+        }
+    }
+
+    /// Captures the uncommitted changes in the project's repositories, if there
+    /// are any.
+    pub(crate) fn for_git_diff(project: &Project, cx: &AppContext) -> Task<Result<Option<Self>>> {
+        let fs = project.fs().clone();
+        let roots = project
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path())
+            .collect::<Vec<_>>();
+        cx.background_executor().spawn(async move {
+            let mut diff = String::new();
+            for root in roots {
+                if let Some(repo) = fs.open_repo(&root.join(".git")) {
+                    diff.push_str(&repo.lock().working_tree_diff()?);
+                }
+            }
+
+            if diff.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some(Self {
+                kind: AttachmentKind::GitDiff,
+                source: "uncommitted changes".into(),
+                language: Some("diff".into()),
+                text: diff,
+            }))
+        })
+    }
+}
+
+/// Describes where `range` is in the editor's buffers, e.g. `src/main.rs:3-5`.
+pub(crate) fn describe_location(buffer: &MultiBufferSnapshot, range: Range<usize>) -> String {
+    let Some((start_buffer, start)) = buffer.point_to_buffer_offset(range.start) else {
+        return "untitled".into();
+    };
+    let end = buffer
+        .point_to_buffer_offset(range.end)
+        .filter(|(end_buffer, _)| end_buffer.remote_id() == start_buffer.remote_id())
+        .map_or(start, |(_, end)| end);
+
+    let path = start_buffer.file().map_or_else(
+        || "untitled".into(),
+        |file| file.path().to_string_lossy().into_owned(),
+    );
+    let start_row = start_buffer.offset_to_point(start).row + 1;
+    let end_row = start_buffer.offset_to_point(end).row + 1;
+    if start_row == end_row {
+        format!("{path}:{start_row}")
+    } else {
+        format!("{path}:{start_row}-{end_row}")
+    }
+}
+
+pub(crate) fn severity_label(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::ERROR => "error",
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::INFORMATION => "info",
+        _ => "hint",
+    }
+}
+
+/// Builds the system message that precedes a conversation with attachments.
+pub fn attachments_prompt<'a>(
+    attachments: impl IntoIterator<Item = &'a ContextAttachment>,
+) -> Option<String> {
+    let mut attachments = attachments.into_iter().peekable();
+    attachments.peek()?;
+
+    let mut prompt = String::from(
+        "The user attached the following context to this conversation. \
+        Use it to inform your answers.",
+    );
+    for attachment in attachments {
+        prompt.push_str("\n\n");
+        prompt.push_str(&attachment.to_prompt_section());
+    }
+    Some(prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_attachments_prompt() {
+        assert_eq!(attachments_prompt(&Vec::<ContextAttachment>::new()), None);
+
+        let attachments = [
+            ContextAttachment {
+                kind: AttachmentKind::Selection,
+                source: "src/main.rs:2-3".into(),
+                language: Some("rust".into()),
+                text: "let a = 1;\nlet b = 2;".into(),
+            },
+            ContextAttachment {
+                kind: AttachmentKind::TerminalOutput,
+                source: "cargo test".into(),
+                language: None,
+                text: "test result: ok\n".into(),
+            },
+        ];
+        assert_eq!(
+            attachments_prompt(&attachments).unwrap(),
+            indoc! {r#"
+                The user attached the following context to this conversation. Use it to inform your answers.
+
+                <selection source="src/main.rs:2-3">
+                ```rust
+                let a = 1;
+                let b = 2;
+                ```
+                </selection>
+
+                <terminal_output source="cargo test">
+                ```
+                test result: ok
+                ```
+                </terminal_output>"#}
+        );
+    }
+}
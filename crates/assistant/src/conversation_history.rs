@@ -0,0 +1,439 @@
+use crate::{SavedConversation, SavedConversationMetadata};
+use anyhow::Result;
+use chrono::NaiveDate;
+use collections::{BTreeMap, BTreeSet, HashSet};
+use fs::Fs;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, ffi::OsStr, path::PathBuf, time::SystemTime};
+use util::paths::CONVERSATIONS_DIR;
+
+/// The file in [`CONVERSATIONS_DIR`] that indexes the saved conversations.
+const INDEX_FILE_NAME: &str = "conversations.index";
+
+/// The metadata and search terms of the saved conversations, persisted next to
+/// them so that the history can be listed and searched without reading every
+/// conversation.
+///
+/// Each entry records the modification time of the file it was read from, so
+/// that conversations changed outside of the editor, or saved while another
+/// instance was updating the index, are re-indexed on the next refresh.
+#[derive(Serialize, Deserialize)]
+pub struct ConversationIndex {
+    version: u32,
+    entries: BTreeMap<PathBuf, IndexEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    mtime: SystemTime,
+    metadata: SavedConversationMetadata,
+}
+
+impl ConversationIndex {
+    const VERSION: u32 = 1;
+
+    fn path() -> PathBuf {
+        CONVERSATIONS_DIR.join(INDEX_FILE_NAME)
+    }
+
+    /// Loads the index, starting over if it's missing or was written by another
+    /// version.
+    pub async fn load(fs: &dyn Fs) -> Self {
+        fs.load(&Self::path())
+            .await
+            .ok()
+            .and_then(|json| serde_json::from_str::<Self>(&json).ok())
+            .filter(|index| index.version == Self::VERSION)
+            .unwrap_or_else(|| Self {
+                version: Self::VERSION,
+                entries: BTreeMap::default(),
+            })
+    }
+
+    pub async fn save(&self, fs: &dyn Fs) -> Result<()> {
+        fs.atomic_write(Self::path(), serde_json::to_string(self)?)
+            .await
+    }
+
+    /// Reads the conversations that changed since they were indexed and drops
+    /// the ones that were deleted, returning whether the index changed.
+    pub async fn refresh(&mut self, fs: &dyn Fs) -> Result<bool> {
+        let mut paths = fs.read_dir(&CONVERSATIONS_DIR).await?;
+        let mut existing_paths = HashSet::default();
+        let mut changed = false;
+        while let Some(path) = paths.next().await {
+            let path = path?;
+            if path.extension() != Some(OsStr::new("json")) {
+                continue;
+            }
+            let Some(metadata) = fs.metadata(&path).await? else {
+                continue;
+            };
+
+            existing_paths.insert(path.clone());
+            if self
+                .entries
+                .get(&path)
+                .map_or(false, |entry| entry.mtime == metadata.mtime)
+            {
+                continue;
+            }
+            let saved_conversation = fs
+                .load(&path)
+                .await
+                .ok()
+                .and_then(|json| serde_json::from_str::<SavedConversation>(&json).ok());
+            self.insert(path, metadata.mtime, saved_conversation.as_ref());
+            changed = true;
+        }
+
+        let entry_count = self.entries.len();
+        self.entries.retain(|path, _| existing_paths.contains(path));
+        Ok(changed || self.entries.len() != entry_count)
+    }
+
+    fn insert(
+        &mut self,
+        path: PathBuf,
+        mtime: SystemTime,
+        saved_conversation: Option<&SavedConversation>,
+    ) {
+        let metadata = SavedConversationMetadata::new(path.clone(), mtime, saved_conversation);
+        self.entries.insert(path, IndexEntry { mtime, metadata });
+    }
+
+    /// The indexed conversations, the most recent first.
+    pub fn conversations(&self) -> Vec<SavedConversationMetadata> {
+        let mut conversations = self
+            .entries
+            .values()
+            .map(|entry| entry.metadata.clone())
+            .collect::<Vec<_>>();
+        conversations.sort_unstable_by_key(|conversation| Reverse(conversation.last_message_at));
+        conversations
+    }
+
+    /// Indexes a conversation that was just written to `path`.
+    pub async fn update(
+        fs: &dyn Fs,
+        path: PathBuf,
+        saved_conversation: &SavedConversation,
+    ) -> Result<()> {
+        let Some(metadata) = fs.metadata(&path).await? else {
+            return Ok(());
+        };
+        let mut index = Self::load(fs).await;
+        index.insert(path, metadata.mtime, Some(saved_conversation));
+        index.save(fs).await
+    }
+}
+
+/// Splits text into the lowercased words that the history is searched by.
+fn search_terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+}
+
+/// The distinct search terms of a conversation's text, sorted so that they can
+/// be matched by prefix.
+pub fn index_terms(text: &str) -> Vec<String> {
+    search_terms(text)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// A search over the saved conversations, parsed from the text typed into the
+/// history's search bar.
+///
+/// Words are matched against the beginnings of the words in the title and the
+/// messages of each conversation, and the following filters narrow the results
+/// down further:
+///
+/// - `model:<name>` keeps conversations whose model contains `name`.
+/// - `tag:<tag>` keeps conversations tagged with `tag`.
+/// - `after:<yyyy-mm-dd>` and `before:<yyyy-mm-dd>` keep conversations whose
+///   last message was sent on or after, or before, the given date.
+/// - `is:pinned` keeps pinned conversations.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HistoryQuery {
+    pub terms: Vec<String>,
+    pub model: Option<String>,
+    pub tags: Vec<String>,
+    pub after: Option<NaiveDate>,
+    pub before: Option<NaiveDate>,
+    pub pinned: bool,
+}
+
+impl HistoryQuery {
+    pub fn parse(query: &str) -> Self {
+        let mut this = Self::default();
+        for word in query.split_whitespace() {
+            let word = word.to_lowercase();
+            let date = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+            if let Some(model) = word.strip_prefix("model:") {
+                this.model = Some(model.to_string());
+            } else if let Some(tag) = word.strip_prefix("tag:") {
+                this.tags.push(tag.to_string());
+            } else if let Some(after) = word.strip_prefix("after:").and_then(date) {
+                this.after = Some(after);
+            } else if let Some(before) = word.strip_prefix("before:").and_then(date) {
+                this.before = Some(before);
+            } else if word == "is:pinned" {
+                this.pinned = true;
+            } else {
+                this.terms.push(word);
+            }
+        }
+        this
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn matches(&self, conversation: &SavedConversationMetadata) -> bool {
+        if self.pinned && !conversation.pinned {
+            return false;
+        }
+
+        if let Some(model) = self.model.as_ref() {
+            if !conversation.model.to_lowercase().contains(model) {
+                return false;
+            }
+        }
+
+        if !self.tags.iter().all(|tag| {
+            conversation
+                .tags
+                .iter()
+                .any(|candidate| candidate.to_lowercase() == *tag)
+        }) {
+            return false;
+        }
+
+        let date = conversation.last_message_at.date_naive();
+        if self.after.map_or(false, |after| date < after)
+            || self.before.map_or(false, |before| date >= before)
+        {
+            return false;
+        }
+
+        self.terms.iter().all(|term| {
+            search_terms(term).all(|term| {
+                let ix = conversation
+                    .terms
+                    .partition_point(|candidate| *candidate < term);
+                conversation
+                    .terms
+                    .get(ix)
+                    .map_or(false, |candidate| candidate.starts_with(&term))
+            })
+        })
+    }
+}
+
+/// Returns the indices of the conversations matching the query, with pinned
+/// conversations first and each group in its original order.
+pub fn search_conversations(
+    conversations: &[SavedConversationMetadata],
+    query: &HistoryQuery,
+) -> Vec<usize> {
+    let mut matches = conversations
+        .iter()
+        .enumerate()
+        .filter(|(_, conversation)| query.matches(conversation))
+        .map(|(ix, _)| ix)
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|ix| !conversations[*ix].pinned);
+    matches
+}
+
+/// Parses the tags typed by the user, which are separated by commas or spaces.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags = Vec::<String>::new();
+    for tag in text.split(|c: char| c == ',' || c.is_whitespace()) {
+        let tag = tag.trim_start_matches('#');
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// Returns the index of the first message in the longest suffix of messages
+/// whose token counts fit within `budget`.
+pub fn fitting_suffix_start(token_counts: &[usize], budget: usize) -> usize {
+    let mut total = 0;
+    for (ix, token_count) in token_counts.iter().enumerate().rev() {
+        total += token_count;
+        if total > budget {
+            return ix + 1;
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Local, TimeZone};
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_parse_history_query() {
+        assert!(HistoryQuery::parse("  ").is_empty());
+        assert_eq!(
+            HistoryQuery::parse("Borrow checker model:GPT-4 tag:rust after:2024-01-15 is:pinned"),
+            HistoryQuery {
+                terms: vec!["borrow".into(), "checker".into()],
+                model: Some("gpt-4".into()),
+                tags: vec!["rust".into()],
+                after: NaiveDate::from_ymd_opt(2024, 1, 15),
+                before: None,
+                pinned: true,
+            }
+        );
+
+        // Invalid dates are searched for as text.
+        assert_eq!(
+            HistoryQuery::parse("before:yesterday").terms,
+            ["before:yesterday"]
+        );
+    }
+
+    #[test]
+    fn test_search_conversations() {
+        let conversations = [
+            conversation("Lifetimes", "gpt-4", &["rust"], false, "2024-01-20"),
+            conversation("Flaky test", "gpt-3.5-turbo", &["ci"], false, "2024-01-10"),
+            conversation("Commit prompt", "claude-3", &["rust"], true, "2023-12-01"),
+        ];
+        let search =
+            |query: &str| search_conversations(&conversations, &HistoryQuery::parse(query));
+
+        assert_eq!(search(""), [2, 0, 1]);
+        assert_eq!(search("lifetimes"), [0]);
+        assert_eq!(search("MESSAGES ABOUT"), [2, 0, 1]);
+        assert_eq!(search("model:gpt"), [0, 1]);
+        assert_eq!(search("tag:rust"), [2, 0]);
+        assert_eq!(search("tag:rust tag:ci"), Vec::<usize>::new());
+        assert_eq!(search("after:2024-01-10"), [0, 1]);
+        assert_eq!(search("before:2024-01-10"), [2]);
+        assert_eq!(search("is:pinned"), [2]);
+
+        fn conversation(
+            title: &str,
+            model: &str,
+            tags: &[&str],
+            pinned: bool,
+            date: &str,
+        ) -> SavedConversationMetadata {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+            let last_message_at: DateTime<Local> = Local
+                .from_local_datetime(&date.and_hms_opt(12, 0, 0).unwrap())
+                .unwrap();
+            SavedConversationMetadata {
+                title: title.into(),
+                path: PathBuf::from(format!("{title} - 1.zed.json")),
+                last_message_at,
+                model: model.into(),
+                pinned,
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                terms: index_terms(&format!("{title}\nmessages about {title}")),
+            }
+        }
+    }
+
+    #[gpui::test]
+    async fn test_conversation_index(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        let saved_conversation = |text: &str| {
+            serde_json::to_string(&SavedConversation {
+                id: None,
+                zed: "conversation".into(),
+                version: SavedConversation::VERSION.into(),
+                text: text.into(),
+                messages: Vec::new(),
+                message_metadata: Default::default(),
+                summary: String::new(),
+                model: "gpt-4".into(),
+                attachments: Vec::new(),
+                pinned: false,
+                tags: Vec::new(),
+            })
+            .unwrap()
+        };
+        fs.insert_tree(
+            &*CONVERSATIONS_DIR,
+            json!({
+                "Lifetimes - 1.zed.json": saved_conversation("Why does the borrow checker complain?"),
+                "Flaky test - 1.zed.json": saved_conversation("The test times out on CI."),
+            }),
+        )
+        .await;
+
+        let conversations = SavedConversationMetadata::list(fs.clone()).await.unwrap();
+        let mut titles = conversations
+            .iter()
+            .map(|conversation| conversation.title.as_str())
+            .collect::<Vec<_>>();
+        titles.sort();
+        assert_eq!(titles, ["Flaky test", "Lifetimes"]);
+        let query = HistoryQuery::parse("borr");
+        assert_eq!(
+            search_conversations(&conversations, &query)
+                .into_iter()
+                .map(|ix| conversations[ix].title.as_str())
+                .collect::<Vec<_>>(),
+            ["Lifetimes"]
+        );
+
+        // The index is up to date, so refreshing it doesn't read any conversation.
+        let mut index = ConversationIndex::load(fs.as_ref()).await;
+        assert!(!index.refresh(fs.as_ref()).await.unwrap());
+
+        fs.remove_file(
+            &CONVERSATIONS_DIR.join("Flaky test - 1.zed.json"),
+            Default::default(),
+        )
+        .await
+        .unwrap();
+        fs.atomic_write(
+            CONVERSATIONS_DIR.join("Lifetimes - 1.zed.json"),
+            saved_conversation("What does 'static mean?"),
+        )
+        .await
+        .unwrap();
+        assert!(index.refresh(fs.as_ref()).await.unwrap());
+        let conversations = index.conversations();
+        assert_eq!(conversations.len(), 1);
+        assert_eq!(
+            conversations[0].terms,
+            ["does", "lifetimes", "mean", "static", "what"]
+        );
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(parse_tags(""), Vec::<String>::new());
+        assert_eq!(
+            parse_tags("rust, #ci  prompts,rust"),
+            ["rust", "ci", "prompts"]
+        );
+    }
+
+    #[test]
+    fn test_fitting_suffix_start() {
+        assert_eq!(fitting_suffix_start(&[], 10), 0);
+        assert_eq!(fitting_suffix_start(&[3, 3, 3], 10), 0);
+        assert_eq!(fitting_suffix_start(&[3, 3, 3], 6), 1);
+        assert_eq!(fitting_suffix_start(&[3, 3, 3], 5), 2);
+        assert_eq!(fitting_suffix_start(&[3, 3, 20], 5), 3);
+    }
+}
@@ -0,0 +1,161 @@
+use std::{ops::Range, path::PathBuf};
+
+/// Instructs the model to describe changes to files as edit blocks that
+/// [`parse_edit_proposals`] understands.
+pub const EDIT_FORMAT_PROMPT: &str = r#"When you propose changes to files in the project, describe each change as an edit block:
+
+<edit path="path/to/file.rs">
+<old>
+the lines to replace, copied verbatim from the file
+</old>
+<new>
+the lines to replace them with
+</new>
+</edit>
+
+Include enough lines in <old> for them to be unique within the file, and leave it empty to append to the end of the file. Paths are relative to the root of the project."#;
+
+/// A change to a file that the assistant proposed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EditProposal {
+    pub path: PathBuf,
+    pub old_text: String,
+    pub new_text: String,
+}
+
+/// Extracts the edit blocks described by [`EDIT_FORMAT_PROMPT`] from a message,
+/// ignoring any that are incomplete.
+pub fn parse_edit_proposals(text: &str) -> Vec<EditProposal> {
+    const EDIT_START: &str = "<edit path=\"";
+    const EDIT_END: &str = "</edit>";
+
+    let mut proposals = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(EDIT_START) {
+        rest = &rest[start + EDIT_START.len()..];
+        let Some(path_end) = rest.find("\">") else {
+            break;
+        };
+        let path = &rest[..path_end];
+        rest = &rest[path_end + 2..];
+        let Some(body_end) = rest.find(EDIT_END) else {
+            break;
+        };
+        let body = &rest[..body_end];
+        rest = &rest[body_end + EDIT_END.len()..];
+
+        if let Some((old_text, new_text)) = tag_contents(body, "old").zip(tag_contents(body, "new"))
+        {
+            proposals.push(EditProposal {
+                path: PathBuf::from(path.trim()),
+                old_text: old_text.to_string(),
+                new_text: new_text.to_string(),
+            });
+        }
+    }
+    proposals
+}
+
+/// Returns the text between `<tag>` and `</tag>`, without the newline that
+/// follows the opening tag.
+fn tag_contents<'a>(text: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let start = text.find(&open)? + open.len();
+    let end = start + text[start..].find(&close)?;
+    let contents = &text[start..end];
+    Some(contents.strip_prefix('\n').unwrap_or(contents))
+}
+
+/// Narrows the replacement of `old_text` with `new_text` to the lines that
+/// differ, returning the byte ranges of those lines in each text.
+pub fn changed_lines(old_text: &str, new_text: &str) -> (Range<usize>, Range<usize>) {
+    let old_lines = old_text.split_inclusive('\n').collect::<Vec<_>>();
+    let new_lines = new_text.split_inclusive('\n').collect::<Vec<_>>();
+    let prefix = old_lines
+        .iter()
+        .zip(&new_lines)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old_lines[prefix..]
+        .iter()
+        .rev()
+        .zip(new_lines[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+
+    let len = |lines: &[&str]| lines.iter().map(|line| line.len()).sum::<usize>();
+    let old_range =
+        len(&old_lines[..prefix])..old_text.len() - len(&old_lines[old_lines.len() - suffix..]);
+    let new_range =
+        len(&new_lines[..prefix])..new_text.len() - len(&new_lines[new_lines.len() - suffix..]);
+    (old_range, new_range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_parse_edit_proposals() {
+        let message = indoc! {r#"
+            Rename the method in the trait and its implementation:
+
+            <edit path="src/shape.rs">
+            <old>
+                fn area(&self) -> f64;
+            </old>
+            <new>
+                fn surface_area(&self) -> f64;
+            </new>
+            </edit>
+
+            <edit path="src/circle.rs">
+            <old>
+            </old>
+            <new>
+            impl Circle {}
+            </new>
+            </edit>
+
+            <edit path="src/square.rs">
+            <old>
+            fn area
+        "#};
+
+        assert_eq!(
+            parse_edit_proposals(message),
+            [
+                EditProposal {
+                    path: "src/shape.rs".into(),
+                    old_text: "    fn area(&self) -> f64;\n".into(),
+                    new_text: "    fn surface_area(&self) -> f64;\n".into(),
+                },
+                EditProposal {
+                    path: "src/circle.rs".into(),
+                    old_text: "".into(),
+                    new_text: "impl Circle {}\n".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let old_text = "a\nb\nc\nd\n";
+        let new_text = "a\nB\nC\nd\n";
+        let (old_range, new_range) = changed_lines(old_text, new_text);
+        assert_eq!(&old_text[old_range], "b\nc\n");
+        assert_eq!(&new_text[new_range], "B\nC\n");
+
+        let (old_range, new_range) = changed_lines("a\nb\n", "a\nx\nb\n");
+        assert_eq!((old_range, new_range), (2..2, 2..4));
+
+        let (old_range, new_range) = changed_lines("a\nb", "a\nb");
+        assert_eq!((old_range, new_range), (3..3, 3..3));
+
+        let (old_range, new_range) = changed_lines("", "a\n");
+        assert_eq!((old_range, new_range), (0..0, 0..2));
+    }
+}
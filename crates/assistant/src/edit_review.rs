@@ -0,0 +1,745 @@
+use crate::edit_proposals::{changed_lines, EditProposal};
+use anyhow::Result;
+use editor::{
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle},
+    Anchor, Editor, EditorEvent, MultiBuffer, ToPoint,
+};
+use gpui::{
+    AnyElement, AnyView, AppContext, AsyncAppContext, ClickEvent, EventEmitter, FocusHandle,
+    FocusableView, HighlightStyle, Model, ParentElement, Render, SharedString, StyledText, Task,
+    View, ViewContext, VisualContext, WeakView,
+};
+use language::{Buffer, Point};
+use project::{Project, ProjectPath};
+use std::{
+    any::{Any, TypeId},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use ui::{prelude::*, Tooltip};
+use workspace::{
+    item::{Item, ItemEvent, ItemHandle},
+    ItemNavHistory,
+};
+
+const CONTEXT_LINE_COUNT: u32 = 2;
+
+/// Proposed edits located in the buffers they apply to.
+pub struct ResolvedEdits {
+    edits: Vec<(Model<Buffer>, Vec<(Range<language::Anchor>, String)>)>,
+    /// Descriptions of the proposals that couldn't be located.
+    unresolved: Vec<String>,
+}
+
+impl ResolvedEdits {
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    pub fn unresolved(&self) -> &[String] {
+        &self.unresolved
+    }
+}
+
+/// Opens the buffers that the proposals apply to and finds the text that each
+/// of them replaces, narrowed down to the lines that change.
+pub async fn resolve_edit_proposals(
+    project: Model<Project>,
+    proposals: Vec<EditProposal>,
+    cx: &mut AsyncAppContext,
+) -> Result<ResolvedEdits> {
+    let mut edits = Vec::<(Model<Buffer>, Vec<(Range<usize>, String)>)>::new();
+    let mut unresolved = Vec::new();
+    for proposal in proposals {
+        let path = proposal.path.display().to_string();
+        let Some(project_path) = project.read_with(cx, |project, cx| {
+            find_project_path(project, &proposal.path, cx)
+        })?
+        else {
+            unresolved.push(format!("{path}: no such file in the project"));
+            continue;
+        };
+        let buffer = match project
+            .update(cx, |project, cx| project.open_buffer(project_path, cx))?
+            .await
+        {
+            Ok(buffer) => buffer,
+            Err(error) => {
+                unresolved.push(format!("{path}: {error}"));
+                continue;
+            }
+        };
+
+        let (range, new_text) =
+            match buffer.read_with(cx, |buffer, _| locate_edit(&buffer.text(), &proposal))? {
+                Ok(edit) => edit,
+                Err(reason) => {
+                    unresolved.push(format!("{path}: {reason}"));
+                    continue;
+                }
+            };
+        if range.is_empty() && new_text.is_empty() {
+            continue;
+        }
+
+        let buffer_edits = match edits.iter_mut().find(|(existing, _)| *existing == buffer) {
+            Some((_, buffer_edits)) => buffer_edits,
+            None => {
+                edits.push((buffer, Vec::new()));
+                &mut edits.last_mut().unwrap().1
+            }
+        };
+        let overlaps = buffer_edits.iter().any(|(existing, _)| {
+            (existing.start < range.end && range.start < existing.end) || *existing == range
+        });
+        if overlaps {
+            unresolved.push(format!("{path}: overlaps another edit"));
+        } else {
+            buffer_edits.push((range, new_text));
+        }
+    }
+
+    let edits = edits
+        .into_iter()
+        .map(|(buffer, mut buffer_edits)| {
+            buffer_edits.sort_by_key(|(range, _)| range.start);
+            let buffer_edits = buffer.read_with(cx, |buffer, _| {
+                buffer_edits
+                    .into_iter()
+                    .map(|(range, new_text)| {
+                        let range =
+                            buffer.anchor_before(range.start)..buffer.anchor_after(range.end);
+                        (range, new_text)
+                    })
+                    .collect()
+            })?;
+            anyhow::Ok((buffer, buffer_edits))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(ResolvedEdits { edits, unresolved })
+}
+
+/// Finds the range of the text that a proposal replaces, narrowed down to the
+/// lines that change, along with the text to replace it with. The text to
+/// replace must occur exactly once, so that the edit can't land in the wrong
+/// place.
+fn locate_edit(text: &str, proposal: &EditProposal) -> Result<(Range<usize>, String), String> {
+    if proposal.old_text.is_empty() {
+        let mut new_text = proposal.new_text.clone();
+        if !text.is_empty() && !text.ends_with('\n') {
+            new_text.insert(0, '\n');
+        }
+        return Ok((text.len()..text.len(), new_text));
+    }
+
+    let mut matches = text.match_indices(proposal.old_text.as_str());
+    let start = match (matches.next(), matches.count()) {
+        (Some((start, _)), 0) => start,
+        (None, _) => return Err("couldn't find the text to replace".into()),
+        (Some(_), other_count) => {
+            return Err(format!(
+                "the text to replace occurs {} times",
+                other_count + 1
+            ))
+        }
+    };
+    let (old_lines, new_lines) = changed_lines(&proposal.old_text, &proposal.new_text);
+    Ok((
+        start + old_lines.start..start + old_lines.end,
+        proposal.new_text[new_lines].to_string(),
+    ))
+}
+
+/// Finds a file in the project by its path, which may be relative to a
+/// worktree, start with the worktree's name, or be absolute.
+fn find_project_path(project: &Project, path: &Path, cx: &AppContext) -> Option<ProjectPath> {
+    project.visible_worktrees(cx).find_map(|worktree| {
+        let worktree = worktree.read(cx);
+        let candidates = [
+            Some(path),
+            path.strip_prefix(worktree.root_name()).ok(),
+            path.strip_prefix(worktree.abs_path()).ok(),
+        ];
+        candidates.into_iter().flatten().find_map(|candidate| {
+            worktree
+                .entry_for_path(candidate)
+                .filter(|entry| entry.is_file())
+                .map(|entry| ProjectPath {
+                    worktree_id: worktree.id(),
+                    path: entry.path.clone(),
+                })
+        })
+    })
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum HunkStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+struct Hunk {
+    /// The text that the hunk replaces, in the review's multibuffer.
+    range: Range<Anchor>,
+    new_text: SharedString,
+    status: HunkStatus,
+}
+
+/// Presents edits that the assistant proposed across several files, so they
+/// can be accepted or rejected hunk by hunk before being applied together.
+pub struct EditReview {
+    multibuffer: Model<MultiBuffer>,
+    editor: View<Editor>,
+    hunks: Vec<Hunk>,
+    unresolved: Vec<String>,
+    blocks: Vec<BlockId>,
+    applied_count: Option<usize>,
+}
+
+impl EditReview {
+    pub fn new(
+        project: Model<Project>,
+        resolved: ResolvedEdits,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let multibuffer = cx.new_model(|cx| {
+            MultiBuffer::new(project.read(cx).replica_id(), project.read(cx).capability())
+        });
+        let mut hunks = Vec::new();
+        multibuffer.update(cx, |multibuffer, cx| {
+            for (buffer, edits) in resolved.edits {
+                let ranges = edits.iter().map(|(range, _)| range.clone()).collect();
+                let ranges = multibuffer.push_excerpts_with_context_lines(
+                    buffer,
+                    ranges,
+                    CONTEXT_LINE_COUNT,
+                    cx,
+                );
+                hunks.extend(
+                    ranges
+                        .into_iter()
+                        .zip(edits)
+                        .map(|(range, (_, new_text))| Hunk {
+                            range,
+                            new_text: new_text.into(),
+                            status: HunkStatus::Pending,
+                        }),
+                );
+            }
+        });
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::for_multibuffer(multibuffer.clone(), Some(project), cx);
+            editor.set_vertical_scroll_margin(5, cx);
+            editor
+        });
+        cx.subscribe(&editor, |_, _, event: &EditorEvent, cx| {
+            cx.emit(event.clone())
+        })
+        .detach();
+
+        let mut this = Self {
+            multibuffer,
+            editor,
+            hunks,
+            unresolved: resolved.unresolved,
+            blocks: Vec::new(),
+            applied_count: None,
+        };
+        this.update_hunks(cx);
+        this
+    }
+
+    fn set_hunk_status(&mut self, ix: usize, status: HunkStatus, cx: &mut ViewContext<Self>) {
+        if let Some(hunk) = self.hunks.get_mut(ix) {
+            hunk.status = status;
+            self.update_hunks(cx);
+        }
+    }
+
+    fn set_all_hunk_statuses(&mut self, status: HunkStatus, cx: &mut ViewContext<Self>) {
+        for hunk in &mut self.hunks {
+            hunk.status = status;
+        }
+        self.update_hunks(cx);
+    }
+
+    /// Applies the accepted hunks as a single transaction, which can be undone
+    /// as a whole from the review's editor.
+    fn apply(&mut self, cx: &mut ViewContext<Self>) {
+        let edits = self
+            .hunks
+            .iter()
+            .filter(|hunk| hunk.status == HunkStatus::Accepted)
+            .map(|hunk| (hunk.range.clone(), hunk.new_text.to_string()))
+            .collect::<Vec<_>>();
+        if edits.is_empty() {
+            return;
+        }
+
+        self.applied_count = Some(edits.len());
+        self.editor.update(cx, |editor, cx| {
+            editor.transact(cx, |editor, cx| editor.edit(edits, cx));
+        });
+        self.hunks.clear();
+        self.update_hunks(cx);
+    }
+
+    /// Shows the text that each hunk removes and inserts.
+    fn update_hunks(&mut self, cx: &mut ViewContext<Self>) {
+        let review = cx.view().downgrade();
+        let deleted_background = cx.theme().status().deleted_background;
+        self.editor.update(cx, |editor, cx| {
+            let snapshot = editor.buffer().read(cx).snapshot(cx);
+            let removed_ranges = self
+                .hunks
+                .iter()
+                .filter(|hunk| hunk.status != HunkStatus::Rejected)
+                .map(|hunk| hunk.range.clone())
+                .collect::<Vec<_>>();
+            if removed_ranges.is_empty() {
+                editor.clear_highlights::<Self>(cx);
+            } else {
+                editor.highlight_text::<Self>(
+                    removed_ranges,
+                    HighlightStyle {
+                        background_color: Some(deleted_background),
+                        fade_out: Some(0.4),
+                        ..Default::default()
+                    },
+                    cx,
+                );
+            }
+
+            let blocks = self.hunks.iter().enumerate().map(|(ix, hunk)| {
+                // Show the new text after the lines that it replaces.
+                let start = hunk.range.start.to_point(&snapshot);
+                let end = hunk.range.end.to_point(&snapshot);
+                let (position, disposition) = if end.column == 0 && end.row > start.row {
+                    (
+                        snapshot.anchor_after(Point::new(end.row - 1, 0)),
+                        BlockDisposition::Below,
+                    )
+                } else if start == end && start.column == 0 {
+                    (hunk.range.start, BlockDisposition::Above)
+                } else {
+                    (hunk.range.end, BlockDisposition::Below)
+                };
+
+                let new_lines = if hunk.status == HunkStatus::Rejected {
+                    Vec::new()
+                } else {
+                    hunk.new_text
+                        .lines()
+                        .map(|line| SharedString::from(line.to_string()))
+                        .collect::<Vec<_>>()
+                };
+                BlockProperties {
+                    position,
+                    height: (new_lines.len() + 1).min(u8::MAX as usize) as u8,
+                    style: BlockStyle::Flex,
+                    render: Arc::new({
+                        let review = review.clone();
+                        let status = hunk.status;
+                        move |cx: &mut BlockContext| {
+                            render_hunk(ix, status, &new_lines, review.clone(), cx)
+                        }
+                    }),
+                    disposition,
+                }
+            });
+            let blocks = blocks.collect::<Vec<_>>();
+            editor.remove_blocks(self.blocks.drain(..).collect(), None, cx);
+            self.blocks = editor.insert_blocks(blocks, None, cx);
+        });
+        cx.notify();
+    }
+
+    fn render_header(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let accepted_count = self
+            .hunks
+            .iter()
+            .filter(|hunk| hunk.status == HunkStatus::Accepted)
+            .count();
+        let summary = if let Some(applied_count) = self.applied_count {
+            format!("Applied {applied_count} of the proposed edits")
+        } else {
+            format!("{accepted_count} of {} edits accepted", self.hunks.len())
+        };
+
+        let header = h_flex()
+            .justify_between()
+            .gap_2()
+            .child(Label::new(summary))
+            .when(self.applied_count.is_none(), |this| {
+                this.child(
+                    h_flex()
+                        .gap_1()
+                        .child(
+                            Button::new("accept_all", "Accept All").on_click(cx.listener(
+                                |this, _, cx| this.set_all_hunk_statuses(HunkStatus::Accepted, cx),
+                            )),
+                        )
+                        .child(
+                            Button::new("reject_all", "Reject All").on_click(cx.listener(
+                                |this, _, cx| this.set_all_hunk_statuses(HunkStatus::Rejected, cx),
+                            )),
+                        )
+                        .child(
+                            Button::new("apply", "Apply Accepted")
+                                .style(ButtonStyle::Filled)
+                                .disabled(accepted_count == 0)
+                                .on_click(cx.listener(|this, _, cx| this.apply(cx))),
+                        ),
+                )
+            });
+
+        // Edits that couldn't be located, for example because the text they
+        // replace occurs more than once, are listed so that they can be made by hand.
+        v_flex()
+            .gap_1()
+            .px_2()
+            .py_1()
+            .border_b_1()
+            .border_color(cx.theme().colors().border_variant)
+            .child(header)
+            .children(self.unresolved.iter().map(|description| {
+                h_flex()
+                    .gap_1()
+                    .child(
+                        Icon::new(IconName::ExclamationTriangle)
+                            .size(IconSize::Small)
+                            .color(Color::Warning),
+                    )
+                    .child(
+                        Label::new(format!("Skipped {description}"))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted),
+                    )
+            }))
+    }
+}
+
+fn render_hunk(
+    ix: usize,
+    status: HunkStatus,
+    new_lines: &[SharedString],
+    review: WeakView<EditReview>,
+    cx: &mut BlockContext,
+) -> AnyElement {
+    let set_status = move |status| {
+        let review = review.clone();
+        move |_: &ClickEvent, cx: &mut WindowContext| {
+            review
+                .update(cx, |review, cx| review.set_hunk_status(ix, status, cx))
+                .ok();
+        }
+    };
+    let buttons = match status {
+        HunkStatus::Pending => h_flex()
+            .gap_1()
+            .child(
+                Button::new(("accept_hunk", ix), "Accept")
+                    .icon(IconName::Check)
+                    .on_click(set_status(HunkStatus::Accepted)),
+            )
+            .child(
+                Button::new(("reject_hunk", ix), "Reject")
+                    .icon(IconName::Close)
+                    .on_click(set_status(HunkStatus::Rejected)),
+            ),
+        HunkStatus::Accepted | HunkStatus::Rejected => h_flex()
+            .gap_1()
+            .child(
+                Label::new(if status == HunkStatus::Accepted {
+                    "Accepted"
+                } else {
+                    "Rejected"
+                })
+                .color(Color::Muted),
+            )
+            .child(
+                Button::new(("undo_hunk", ix), "Undo")
+                    .tooltip(|cx| Tooltip::text("Review this edit again", cx))
+                    .on_click(set_status(HunkStatus::Pending)),
+            ),
+    };
+
+    let text_style = cx.editor_style.text.clone();
+    let mut created_background = cx.editor_style.status.created_background;
+    created_background.fade_out(0.6);
+    v_flex()
+        .id(("hunk", ix))
+        .w_full()
+        .pl(cx.gutter_width)
+        .child(h_flex().h(cx.line_height).child(buttons))
+        .children(new_lines.iter().map(|line| {
+            div()
+                .h(cx.line_height)
+                .w_full()
+                .bg(created_background)
+                .child(StyledText::new(line.clone()).with_highlights(&text_style, None))
+        }))
+        .into_any_element()
+}
+
+impl EventEmitter<EditorEvent> for EditReview {}
+
+impl FocusableView for EditReview {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.editor.focus_handle(cx)
+    }
+}
+
+impl Render for EditReview {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .size_full()
+            .bg(cx.theme().colors().editor_background)
+            .child(self.render_header(cx))
+            .child(div().flex_1().child(self.editor.clone()))
+    }
+}
+
+impl Item for EditReview {
+    type Event = EditorEvent;
+
+    fn to_item_events(event: &EditorEvent, f: impl FnMut(ItemEvent)) {
+        Editor::to_item_events(event, f)
+    }
+
+    fn deactivated(&mut self, cx: &mut ViewContext<Self>) {
+        self.editor.update(cx, |editor, cx| editor.deactivated(cx));
+    }
+
+    fn navigate(&mut self, data: Box<dyn Any>, cx: &mut ViewContext<Self>) -> bool {
+        self.editor
+            .update(cx, |editor, cx| editor.navigate(data, cx))
+    }
+
+    fn tab_content(&self, _detail: Option<usize>, selected: bool, _: &WindowContext) -> AnyElement {
+        Label::new("Proposed Edits")
+            .color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            })
+            .into_any_element()
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("assistant edit review")
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(gpui::EntityId, &dyn project::Item),
+    ) {
+        self.editor.for_each_project_item(cx, f)
+    }
+
+    fn is_singleton(&self, _: &AppContext) -> bool {
+        false
+    }
+
+    fn set_nav_history(&mut self, nav_history: ItemNavHistory, cx: &mut ViewContext<Self>) {
+        self.editor.update(cx, |editor, _| {
+            editor.set_nav_history(Some(nav_history));
+        });
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.multibuffer.read(cx).is_dirty(cx)
+    }
+
+    fn has_conflict(&self, cx: &AppContext) -> bool {
+        self.multibuffer.read(cx).has_conflict(cx)
+    }
+
+    fn can_save(&self, _: &AppContext) -> bool {
+        true
+    }
+
+    fn save(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.editor.save(project, cx)
+    }
+
+    fn save_as(
+        &mut self,
+        _: Model<Project>,
+        _: PathBuf,
+        _: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        unreachable!()
+    }
+
+    fn reload(&mut self, project: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.editor.reload(project, cx)
+    }
+
+    fn act_as_type<'a>(
+        &'a self,
+        type_id: TypeId,
+        self_handle: &'a View<Self>,
+        _: &'a AppContext,
+    ) -> Option<AnyView> {
+        if type_id == TypeId::of::<Self>() {
+            Some(self_handle.to_any())
+        } else if type_id == TypeId::of::<Editor>() {
+            Some(self.editor.to_any())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+    use language::OffsetRangeExt;
+    use serde_json::json;
+    use settings::SettingsStore;
+
+    fn init_test(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            let settings_store = SettingsStore::test(cx);
+            cx.set_global(settings_store);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            Project::init_settings(cx);
+            editor::init_settings(cx);
+        });
+    }
+
+    fn proposal(path: &str, old_text: &str, new_text: &str) -> EditProposal {
+        EditProposal {
+            path: path.into(),
+            old_text: old_text.into(),
+            new_text: new_text.into(),
+        }
+    }
+
+    async fn resolve(
+        proposals: Vec<EditProposal>,
+        cx: &mut TestAppContext,
+    ) -> (Model<Project>, ResolvedEdits) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_tree(
+            "/root",
+            json!({
+                "a.rs": "fn a() {}\nfn b() {}\nfn a() {}\n",
+                "b.rs": "fn c() {\n    1\n}\n",
+            }),
+        )
+        .await;
+        let project = Project::test(fs, ["/root".as_ref()], cx).await;
+        let resolved = cx
+            .spawn(|mut cx| {
+                let project = project.clone();
+                async move { resolve_edit_proposals(project, proposals, &mut cx).await }
+            })
+            .await
+            .unwrap();
+        (project, resolved)
+    }
+
+    #[gpui::test]
+    async fn test_resolve_edit_proposals(cx: &mut TestAppContext) {
+        init_test(cx);
+        let (_, resolved) = resolve(
+            vec![
+                proposal("a.rs", "fn b() {}\n", "fn b2() {}\n"),
+                proposal("a.rs", "fn a() {}\n", "fn a2() {}\n"),
+                proposal("a.rs", "fn z() {}\n", "fn z2() {}\n"),
+                proposal("missing.rs", "fn a() {}\n", ""),
+                proposal("b.rs", "fn c() {\n    1\n}\n", "fn c() {\n    2\n}\n"),
+            ],
+            cx,
+        )
+        .await;
+
+        assert_eq!(
+            resolved.unresolved(),
+            [
+                "a.rs: the text to replace occurs 2 times",
+                "a.rs: couldn't find the text to replace",
+                "missing.rs: no such file in the project",
+            ]
+        );
+        let edits = cx.update(|cx| {
+            resolved
+                .edits
+                .iter()
+                .map(|(buffer, edits)| {
+                    let buffer = buffer.read(cx);
+                    let edits = edits
+                        .iter()
+                        .map(|(range, new_text)| {
+                            let range = range.to_offset(buffer);
+                            (buffer.text()[range].to_string(), new_text.clone())
+                        })
+                        .collect::<Vec<_>>();
+                    (buffer.file().unwrap().path().to_path_buf(), edits)
+                })
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            edits,
+            [
+                (
+                    PathBuf::from("a.rs"),
+                    vec![("fn b() {}\n".to_string(), "fn b2() {}\n".to_string())]
+                ),
+                (
+                    PathBuf::from("b.rs"),
+                    vec![("    1\n".to_string(), "    2\n".to_string())]
+                ),
+            ]
+        );
+    }
+
+    #[gpui::test]
+    async fn test_apply_accepted_hunks(cx: &mut TestAppContext) {
+        init_test(cx);
+        let (project, resolved) = resolve(
+            vec![
+                proposal("a.rs", "fn b() {}\n", "fn b2() {}\n"),
+                proposal("b.rs", "fn c() {\n    1\n}\n", "fn c() {\n    2\n}\n"),
+            ],
+            cx,
+        )
+        .await;
+        let buffers = resolved
+            .edits
+            .iter()
+            .map(|(buffer, _)| buffer.clone())
+            .collect::<Vec<_>>();
+
+        let review = cx.add_window(|cx| EditReview::new(project, resolved, cx));
+        review
+            .update(cx, |review, cx| {
+                review.set_hunk_status(0, HunkStatus::Accepted, cx);
+                review.set_hunk_status(1, HunkStatus::Rejected, cx);
+                review.apply(cx);
+                assert_eq!(review.applied_count, Some(1));
+                assert!(review.hunks.is_empty());
+            })
+            .unwrap();
+        cx.run_until_parked();
+
+        let texts = cx.update(|cx| {
+            buffers
+                .iter()
+                .map(|buffer| buffer.read(cx).text())
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(
+            texts,
+            ["fn a() {}\nfn b2() {}\nfn a() {}\n", "fn c() {\n    1\n}\n",]
+        );
+    }
+}
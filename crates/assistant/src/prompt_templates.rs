@@ -0,0 +1,269 @@
+use ai::{
+    models::{LanguageModel, TruncationDirection},
+    prompts::base::{PromptArguments, PromptChain, PromptPriority, PromptTemplate},
+};
+use anyhow::{anyhow, Result};
+use collections::HashMap;
+use fs::Fs;
+use futures::StreamExt;
+use std::{ffi::OsStr, sync::Arc};
+use util::{paths::PROMPTS_DIR, ResultExt};
+
+/// Context that a user prompt can pull in with a `{{placeholder}}`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Placeholder {
+    Selection,
+    File,
+    Diagnostics,
+    Diff,
+}
+
+impl Placeholder {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "selection" => Some(Self::Selection),
+            "file" => Some(Self::File),
+            "diagnostics" => Some(Self::Diagnostics),
+            "diff" => Some(Self::Diff),
+            _ => None,
+        }
+    }
+
+    /// Placeholders with a higher order are truncated first when the expanded
+    /// prompt doesn't fit in the model's context.
+    fn priority(&self) -> PromptPriority {
+        let order = match self {
+            Placeholder::Selection => 0,
+            Placeholder::Diagnostics => 1,
+            Placeholder::File => 2,
+            Placeholder::Diff => 3,
+        };
+        PromptPriority::Ordered { order }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// A prompt template stored as `<name>.md` in the prompts directory, which
+/// the user expands by typing `/<name>` on its own line in the assistant panel.
+///
+/// Anything typed after the name replaces `{{input}}`, while `{{selection}}`,
+/// `{{file}}`, `{{diagnostics}}` and `{{diff}}` are replaced by the
+/// corresponding context, each on a line of its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserPrompt {
+    pub name: String,
+    /// The first line of the template, shown when completing its name.
+    pub description: String,
+    segments: Vec<Segment>,
+}
+
+impl UserPrompt {
+    pub fn parse(name: impl Into<String>, source: &str) -> Result<Self> {
+        let name = name.into();
+        let description = source
+            .lines()
+            .map(|line| line.trim_start_matches('#').trim())
+            .find(|line| !line.is_empty())
+            .unwrap_or_default()
+            .to_string();
+
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start..].find("}}") else {
+                break;
+            };
+            text.push_str(&rest[..start]);
+            let placeholder_name = rest[start + 2..start + len].trim();
+            rest = &rest[start + len + 2..];
+
+            if placeholder_name == "input" {
+                text.push_str("{{input}}");
+                continue;
+            }
+            let placeholder = Placeholder::parse(placeholder_name).ok_or_else(|| {
+                anyhow!("unknown placeholder {{{{{placeholder_name}}}}} in prompt {name}")
+            })?;
+
+            // The chain puts every placeholder on a line of its own.
+            if text.ends_with('\n') {
+                text.pop();
+            }
+            segments.push(Segment::Text(std::mem::take(&mut text)));
+            segments.push(Segment::Placeholder(placeholder));
+            rest = rest.strip_prefix('\n').unwrap_or(rest);
+        }
+        text.push_str(rest);
+        segments.push(Segment::Text(text));
+
+        Ok(Self {
+            name,
+            description,
+            segments,
+        })
+    }
+
+    /// Loads every prompt in the prompts directory, skipping the ones that
+    /// can't be parsed.
+    pub async fn list(fs: Arc<dyn Fs>) -> Result<Vec<Self>> {
+        fs.create_dir(&PROMPTS_DIR).await?;
+
+        let mut paths = fs.read_dir(&PROMPTS_DIR).await?;
+        let mut prompts = Vec::new();
+        while let Some(path) = paths.next().await {
+            let path = path?;
+            if path.extension() != Some(OsStr::new("md")) {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            let source = fs.load(&path).await?;
+            if let Some(prompt) = Self::parse(name, &source).log_err() {
+                prompts.push(prompt);
+            }
+        }
+        prompts.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(prompts)
+    }
+
+    pub fn placeholders(&self) -> impl Iterator<Item = Placeholder> + '_ {
+        self.segments.iter().filter_map(|segment| match segment {
+            Segment::Placeholder(placeholder) => Some(*placeholder),
+            Segment::Text(_) => None,
+        })
+    }
+
+    /// Expands the prompt, truncating the context that fills its placeholders
+    /// so that it fits in `model`'s context alongside `reserved_tokens`.
+    pub fn expand(
+        &self,
+        input: &str,
+        values: &HashMap<Placeholder, String>,
+        model: Arc<dyn LanguageModel>,
+        reserved_tokens: usize,
+    ) -> Result<String> {
+        let templates = self
+            .segments
+            .iter()
+            .map(|segment| -> (PromptPriority, Box<dyn PromptTemplate>) {
+                match segment {
+                    Segment::Text(text) => (
+                        PromptPriority::Mandatory,
+                        Box::new(Fragment(text.replace("{{input}}", input))),
+                    ),
+                    Segment::Placeholder(placeholder) => (
+                        placeholder.priority(),
+                        Box::new(Fragment(
+                            values.get(placeholder).cloned().unwrap_or_default(),
+                        )),
+                    ),
+                }
+            })
+            .collect();
+
+        let args = PromptArguments {
+            model,
+            user_prompt: None,
+            language_name: None,
+            project_name: None,
+            snippets: Vec::new(),
+            reserved_tokens,
+            buffer: None,
+            selected_range: None,
+        };
+        let (prompt, _) = PromptChain::new(args, templates).generate(true)?;
+        Ok(prompt)
+    }
+}
+
+/// A piece of an expanded prompt, cut short when there's no room for all of it.
+struct Fragment(String);
+
+impl PromptTemplate for Fragment {
+    fn generate(
+        &self,
+        args: &PromptArguments,
+        max_token_length: Option<usize>,
+    ) -> Result<(String, usize)> {
+        let token_count = args.model.count_tokens(&self.0)?;
+        match max_token_length {
+            Some(max_token_length) if token_count > max_token_length => {
+                let truncated =
+                    args.model
+                        .truncate(&self.0, max_token_length, TruncationDirection::End)?;
+                Ok((truncated, max_token_length))
+            }
+            _ => Ok((self.0.clone(), token_count)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ai::test::FakeLanguageModel;
+    use indoc::indoc;
+
+    #[test]
+    fn test_parse_user_prompt() {
+        let prompt = UserPrompt::parse(
+            "explain",
+            indoc! {"
+                # Explain some code
+                Explain what this code does, focusing on {{input}}:
+                {{selection}}
+                Here is the rest of the file:
+                {{ file }}
+            "},
+        )
+        .unwrap();
+        assert_eq!(prompt.description, "Explain some code");
+        assert_eq!(
+            prompt.placeholders().collect::<Vec<_>>(),
+            [Placeholder::Selection, Placeholder::File]
+        );
+
+        assert!(UserPrompt::parse("bad", "Look at {{clipboard}}").is_err());
+        assert_eq!(
+            UserPrompt::parse("unclosed", "Keep {{ this")
+                .unwrap()
+                .segments,
+            [Segment::Text("Keep {{ this".into())]
+        );
+    }
+
+    #[test]
+    fn test_expand_user_prompt() {
+        let prompt = UserPrompt::parse(
+            "review",
+            "Review {{input}}.\n{{selection}}\nAlso consider:\n{{diff}}\nThanks!",
+        )
+        .unwrap();
+        let values = HashMap::from_iter([
+            (Placeholder::Selection, "fn a() {}".to_string()),
+            (Placeholder::Diff, "+ let b = 2;".to_string()),
+        ]);
+
+        let model: Arc<dyn LanguageModel> = Arc::new(FakeLanguageModel { capacity: 1000 });
+        assert_eq!(
+            prompt.expand("my code", &values, model, 0).unwrap(),
+            "Review my code.\nfn a() {}\nAlso consider:\n+ let b = 2;\nThanks!"
+        );
+
+        // The diff is truncated before the selection, and the prompt's own text
+        // is never truncated.
+        let model: Arc<dyn LanguageModel> = Arc::new(FakeLanguageModel { capacity: 57 });
+        assert_eq!(
+            prompt.expand("my code", &values, model, 5).unwrap(),
+            "Review my code.\nfn a() {}\nAlso consider:\n+ l\nThanks!"
+        );
+    }
+}
@@ -0,0 +1,596 @@
+use crate::{
+    attachments::{severity_label, AttachmentKind, ContextAttachment},
+    prompt_templates::{Placeholder, UserPrompt},
+};
+use ai::models::LanguageModel;
+use anyhow::{anyhow, Result};
+use collections::{HashMap, HashSet};
+use editor::{CompletionProvider, Editor};
+use fs::Fs;
+use fuzzy::StringMatchCandidate;
+use gpui::{AppContext, Model, Task, ViewContext, WeakView};
+use language::{
+    Buffer, CodeLabel, Completion, DiagnosticSeverity, Documentation, LanguageServerId, Point,
+    ToOffset, ToPoint,
+};
+use parking_lot::RwLock;
+use project::{search::SearchQuery, Project, ProjectPath};
+use std::{fmt::Write, ops::Range, path::Path, sync::Arc};
+use util::ResultExt;
+use workspace::Workspace;
+
+const MAX_SEARCH_RESULTS: usize = 50;
+const MAX_PATH_COMPLETIONS: usize = 20;
+
+/// The commands that are always available, along with a description shown
+/// when completing them and whether they take an argument.
+const BUILTIN_COMMANDS: &[(&str, &str, bool)] = &[
+    ("file", "Attach a file from the project", true),
+    ("symbol", "Attach the definition of a symbol", true),
+    ("search", "Attach the results of a project search", true),
+    (
+        "diagnostics",
+        "Attach the errors and warnings in the project",
+        false,
+    ),
+    ("diff", "Attach the uncommitted changes", false),
+];
+
+/// A command typed on a line of its own in a user message, which is expanded
+/// into context before the message is sent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlashCommand {
+    File(String),
+    Symbol(String),
+    Search(String),
+    Diagnostics,
+    Diff,
+    /// Expands the user prompt with the given name, if there is one.
+    Prompt {
+        name: String,
+        input: String,
+    },
+}
+
+impl SlashCommand {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end().strip_prefix('/')?;
+        let (name, argument) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(name, argument)| (name, argument.trim()));
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return None;
+        }
+
+        let argument = argument.to_string();
+        Some(match name {
+            "file" => Self::File(argument),
+            "symbol" => Self::Symbol(argument),
+            "search" => Self::Search(argument),
+            "diagnostics" => Self::Diagnostics,
+            "diff" => Self::Diff,
+            _ => Self::Prompt {
+                name: name.to_string(),
+                input: argument,
+            },
+        })
+    }
+}
+
+/// Finds the slash commands in a message, skipping fenced code blocks. The
+/// returned ranges cover the whole line, including its newline.
+pub fn slash_commands_in(text: &str) -> Vec<(Range<usize>, SlashCommand)> {
+    let mut commands = Vec::new();
+    let mut in_code_block = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let range = offset..offset + line.len();
+        offset = range.end;
+
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block {
+            if let Some(command) = SlashCommand::parse(line) {
+                commands.push((range, command));
+            }
+        }
+    }
+    commands
+}
+
+/// What a slash command's line is replaced with.
+pub(crate) enum Expansion {
+    Attachment(ContextAttachment),
+    Text(String),
+}
+
+/// Runs a slash command, resolving to `None` when it names a prompt that
+/// doesn't exist, in which case the line is left as it is.
+pub(crate) fn run_slash_command(
+    command: SlashCommand,
+    prompts: &[UserPrompt],
+    model: Arc<dyn LanguageModel>,
+    reserved_tokens: usize,
+    workspace: &Workspace,
+    cx: &mut AppContext,
+) -> Task<Result<Option<Expansion>>> {
+    let project = workspace.project().clone();
+    let attachment = match command {
+        SlashCommand::File(path) if path.is_empty() => {
+            Task::ready(Err(anyhow!("/file needs a path")))
+        }
+        SlashCommand::File(path) => file_attachment(project, &path, cx),
+        SlashCommand::Symbol(name) if name.is_empty() => {
+            Task::ready(Err(anyhow!("/symbol needs the name of a symbol")))
+        }
+        SlashCommand::Symbol(name) => symbol_attachment(project, name, cx),
+        SlashCommand::Search(query) if query.is_empty() => {
+            Task::ready(Err(anyhow!("/search needs a query")))
+        }
+        SlashCommand::Search(query) => search_attachment(project, query, cx),
+        SlashCommand::Diagnostics => diagnostics_attachment(project, cx),
+        SlashCommand::Diff => {
+            let diff = ContextAttachment::for_git_diff(project.read(cx), cx);
+            cx.spawn(|_| async move {
+                diff.await?
+                    .ok_or_else(|| anyhow!("There are no uncommitted changes"))
+            })
+        }
+        SlashCommand::Prompt { name, input } => {
+            let Some(prompt) = prompts.iter().find(|prompt| prompt.name == name).cloned() else {
+                return Task::ready(Ok(None));
+            };
+            return expand_prompt(prompt, input, model, reserved_tokens, workspace, cx);
+        }
+    };
+    cx.spawn(|_| async move { Ok(Some(Expansion::Attachment(attachment.await?))) })
+}
+
+fn expand_prompt(
+    prompt: UserPrompt,
+    input: String,
+    model: Arc<dyn LanguageModel>,
+    reserved_tokens: usize,
+    workspace: &Workspace,
+    cx: &mut AppContext,
+) -> Task<Result<Option<Expansion>>> {
+    // Placeholders whose context isn't available are left empty.
+    let mut values = HashMap::default();
+    let mut pending_values = Vec::new();
+    for placeholder in prompt.placeholders().collect::<HashSet<_>>() {
+        let project = workspace.project().clone();
+        match placeholder {
+            Placeholder::Selection => {
+                if let Some(selection) = ContextAttachment::for_selection(workspace, cx) {
+                    values.insert(placeholder, selection.to_prompt_section());
+                }
+            }
+            Placeholder::File => {
+                if let Some(file) = ContextAttachment::for_active_file(workspace, cx) {
+                    values.insert(placeholder, file.to_prompt_section());
+                }
+            }
+            Placeholder::Diagnostics => {
+                pending_values.push((placeholder, diagnostics_attachment(project, cx)));
+            }
+            Placeholder::Diff => {
+                let diff = ContextAttachment::for_git_diff(project.read(cx), cx);
+                let diff = cx.spawn(|_| async move {
+                    diff.await?.ok_or_else(|| anyhow!("no uncommitted changes"))
+                });
+                pending_values.push((placeholder, diff));
+            }
+        }
+    }
+
+    cx.spawn(|_| async move {
+        for (placeholder, value) in pending_values {
+            if let Ok(value) = value.await {
+                values.insert(placeholder, value.to_prompt_section());
+            }
+        }
+        let text = prompt.expand(&input, &values, model, reserved_tokens)?;
+        Ok(Some(Expansion::Text(text)))
+    })
+}
+
+pub(crate) fn file_attachment(
+    project: Model<Project>,
+    path: &str,
+    cx: &mut AppContext,
+) -> Task<Result<ContextAttachment>> {
+    let Some(project_path) = find_project_path(project.read(cx), Path::new(path), cx) else {
+        return Task::ready(Err(anyhow!("There is no file at {path} in the project")));
+    };
+    let buffer = project.update(cx, |project, cx| project.open_buffer(project_path, cx));
+    cx.spawn(|cx| async move {
+        let buffer = buffer.await?;
+        buffer.read_with(&cx, |buffer, _| ContextAttachment::for_buffer(buffer))
+    })
+}
+
+/// Finds a file in the project, given its path relative to one of the
+/// project's worktrees, optionally preceded by the worktree's name.
+fn find_project_path(project: &Project, path: &Path, cx: &AppContext) -> Option<ProjectPath> {
+    project.visible_worktrees(cx).find_map(|worktree| {
+        let worktree = worktree.read(cx);
+        let relative_paths = [Some(path), path.strip_prefix(worktree.root_name()).ok()];
+        relative_paths.into_iter().flatten().find_map(|path| {
+            let entry = worktree.entry_for_path(path)?;
+            entry.is_file().then(|| ProjectPath {
+                worktree_id: worktree.id(),
+                path: entry.path.clone(),
+            })
+        })
+    })
+}
+
+fn symbol_attachment(
+    project: Model<Project>,
+    name: String,
+    cx: &mut AppContext,
+) -> Task<Result<ContextAttachment>> {
+    let symbols = project.update(cx, |project, cx| project.symbols(&name, cx));
+    cx.spawn(|mut cx| async move {
+        let symbols = symbols.await?;
+        let symbol = symbols
+            .iter()
+            .find(|symbol| symbol.name == name)
+            .or_else(|| symbols.first())
+            .ok_or_else(|| anyhow!("There is no symbol named {name} in the project"))?;
+        let buffer = project
+            .update(&mut cx, |project, cx| {
+                project.open_buffer_for_symbol(symbol, cx)
+            })?
+            .await?;
+
+        buffer.read_with(&cx, |buffer, _| {
+            let max_row = buffer.max_point().row;
+            let start_row = symbol.range.start.0.row.min(max_row);
+            let end_row = symbol.range.end.0.row.min(max_row);
+            let range = Point::new(start_row, 0)..Point::new(end_row, buffer.line_len(end_row));
+            ContextAttachment {
+                kind: AttachmentKind::Symbol,
+                source: format!(
+                    "{} ({}:{}-{})",
+                    symbol.name,
+                    symbol.path.path.to_string_lossy(),
+                    start_row + 1,
+                    end_row + 1
+                ),
+                language: buffer
+                    .language()
+                    .map(|language| language.name().to_lowercase()),
+                text: buffer.text_for_range(range).collect(),
+            }
+        })
+    })
+}
+
+pub(crate) fn search_attachment(
+    project: Model<Project>,
+    query: String,
+    cx: &mut AppContext,
+) -> Task<Result<ContextAttachment>> {
+    let search_query = match SearchQuery::text(&query, false, false, false, Vec::new(), Vec::new())
+    {
+        Ok(search_query) => search_query,
+        Err(error) => return Task::ready(Err(error)),
+    };
+    let results = project.update(cx, |project, cx| project.search(search_query, cx));
+    cx.spawn(|cx| async move {
+        let mut text = String::new();
+        let mut match_count = 0;
+        'buffers: while let Ok((buffer, ranges)) = results.recv().await {
+            let buffer = buffer.read_with(&cx, |buffer, _| buffer.snapshot())?;
+            let path = buffer.file().map_or_else(
+                || "untitled".into(),
+                |file| file.path().to_string_lossy().into_owned(),
+            );
+            let mut last_row = None;
+            for range in ranges {
+                let row = range.start.to_point(&buffer).row;
+                if last_row.replace(row) == Some(row) {
+                    continue;
+                }
+                if match_count == MAX_SEARCH_RESULTS {
+                    break 'buffers;
+                }
+
+                let line = buffer
+                    .text_for_range(Point::new(row, 0)..Point::new(row, buffer.line_len(row)))
+                    .collect::<String>();
+                writeln!(text, "{path}:{}: {}", row + 1, line.trim()).unwrap();
+                match_count += 1;
+            }
+        }
+
+        if text.is_empty() {
+            return Err(anyhow!("There are no results for {query:?}"));
+        }
+        Ok(ContextAttachment {
+            kind: AttachmentKind::SearchResults,
+            source: query,
+            language: None,
+            text,
+        })
+    })
+}
+
+/// Collects the errors and warnings in every file of the project.
+fn diagnostics_attachment(
+    project: Model<Project>,
+    cx: &mut AppContext,
+) -> Task<Result<ContextAttachment>> {
+    let mut paths = project
+        .read(cx)
+        .diagnostic_summaries(false, cx)
+        .filter(|(_, _, summary)| summary.error_count + summary.warning_count > 0)
+        .map(|(path, _, _)| path)
+        .collect::<Vec<_>>();
+    paths.dedup();
+    let buffers = paths
+        .into_iter()
+        .map(|path| project.update(cx, |project, cx| project.open_buffer(path, cx)))
+        .collect::<Vec<_>>();
+
+    cx.spawn(|cx| async move {
+        let mut text = String::new();
+        for buffer in buffers {
+            let buffer = buffer
+                .await?
+                .read_with(&cx, |buffer, _| buffer.snapshot())?;
+            let path = buffer.file().map_or_else(
+                || "untitled".into(),
+                |file| file.path().to_string_lossy().into_owned(),
+            );
+            for entry in buffer.diagnostics_in_range::<_, Point>(0..buffer.len(), false) {
+                let diagnostic = &entry.diagnostic;
+                let is_error_or_warning = matches!(
+                    diagnostic.severity,
+                    DiagnosticSeverity::ERROR | DiagnosticSeverity::WARNING
+                );
+                if !diagnostic.is_primary || !is_error_or_warning {
+                    continue;
+                }
+
+                let (start, end) = (entry.range.start, entry.range.end);
+                let lines = Point::new(start.row, 0)..Point::new(end.row, buffer.line_len(end.row));
+                writeln!(
+                    text,
+                    "{} at {path}:{}: {}",
+                    severity_label(diagnostic.severity),
+                    start.row + 1,
+                    diagnostic.message
+                )
+                .unwrap();
+                text.extend(buffer.text_for_range(lines));
+                text.push_str("\n\n");
+            }
+        }
+
+        if text.is_empty() {
+            return Err(anyhow!("There are no errors or warnings in the project"));
+        }
+        Ok(ContextAttachment {
+            kind: AttachmentKind::Diagnostics,
+            source: "project".into(),
+            language: None,
+            text: text.trim_end().to_string(),
+        })
+    })
+}
+
+/// Completes the names of slash commands, and the paths given to `/file`, in
+/// a conversation's editor.
+pub(crate) struct SlashCommandCompletionProvider {
+    workspace: WeakView<Workspace>,
+    fs: Arc<dyn Fs>,
+}
+
+impl SlashCommandCompletionProvider {
+    pub fn new(workspace: WeakView<Workspace>, fs: Arc<dyn Fs>) -> Self {
+        Self { workspace, fs }
+    }
+
+    fn command_completions(
+        &self,
+        range: Range<language::Anchor>,
+        cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<Vec<Completion>>> {
+        let fs = self.fs.clone();
+        cx.spawn(|_, _| async move {
+            let prompts = UserPrompt::list(fs).await.log_err().unwrap_or_default();
+            let builtin_commands =
+                BUILTIN_COMMANDS
+                    .iter()
+                    .map(|(name, description, takes_argument)| {
+                        (name.to_string(), description.to_string(), *takes_argument)
+                    });
+            let prompts = prompts
+                .into_iter()
+                .map(|prompt| (prompt.name, prompt.description, true));
+
+            Ok(builtin_commands
+                .chain(prompts)
+                .map(|(name, description, takes_argument)| {
+                    let label = format!("/{name}");
+                    let new_text = if takes_argument { name + " " } else { name };
+                    completion(range.clone(), new_text, label, 1, Some(description))
+                })
+                .collect())
+        })
+    }
+
+    fn path_completions(
+        &self,
+        query: String,
+        range: Range<language::Anchor>,
+        cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<Vec<Completion>>> {
+        let Some(workspace) = self.workspace.upgrade() else {
+            return Task::ready(Ok(Vec::new()));
+        };
+        let project = workspace.read(cx).project().read(cx);
+        let include_root_name = project.visible_worktrees(cx).count() > 1;
+        let mut candidates = Vec::new();
+        for worktree in project.visible_worktrees(cx) {
+            let worktree = worktree.read(cx);
+            for entry in worktree.files(false, 0) {
+                let path = if include_root_name {
+                    Path::new(worktree.root_name()).join(&entry.path)
+                } else {
+                    entry.path.to_path_buf()
+                };
+                let path = path.to_string_lossy().into_owned();
+                candidates.push(StringMatchCandidate {
+                    id: candidates.len(),
+                    char_bag: path.chars().collect(),
+                    string: path,
+                });
+            }
+        }
+
+        cx.spawn(|_, cx| async move {
+            let matches = fuzzy::match_strings(
+                &candidates,
+                &query,
+                false,
+                MAX_PATH_COMPLETIONS,
+                &Default::default(),
+                cx.background_executor().clone(),
+            )
+            .await;
+            Ok(matches
+                .into_iter()
+                .map(|mat| completion(range.clone(), mat.string.clone(), mat.string, 0, None))
+                .collect())
+        })
+    }
+}
+
+impl CompletionProvider for SlashCommandCompletionProvider {
+    fn completions(
+        &self,
+        buffer: &Model<Buffer>,
+        buffer_position: language::Anchor,
+        cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<Vec<Completion>>> {
+        let buffer = buffer.read(cx);
+        let position = buffer_position.to_point(buffer);
+        let line_start = Point::new(position.row, 0);
+        let line = buffer
+            .text_for_range(line_start..position)
+            .collect::<String>();
+        let Some(command) = line.strip_prefix('/') else {
+            return Task::ready(Ok(Vec::new()));
+        };
+
+        match command.split_once(' ') {
+            None => {
+                let name_start = buffer.anchor_after(line_start.to_offset(buffer) + 1);
+                self.command_completions(name_start..buffer_position, cx)
+            }
+            Some(("file", path)) => {
+                let path_start = buffer.anchor_after(position.to_offset(buffer) - path.len());
+                self.path_completions(path.to_string(), path_start..buffer_position, cx)
+            }
+            Some(_) => Task::ready(Ok(Vec::new())),
+        }
+    }
+
+    fn resolve_completions(
+        &self,
+        _completion_indices: Vec<usize>,
+        _completions: Arc<RwLock<Box<[Completion]>>>,
+        _cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<bool>> {
+        Task::ready(Ok(false))
+    }
+
+    fn apply_additional_edits_for_completion(
+        &self,
+        _buffer: Model<Buffer>,
+        _completion: Completion,
+        _push_to_history: bool,
+        _cx: &mut ViewContext<Editor>,
+    ) -> Task<Result<Option<language::Transaction>>> {
+        Task::ready(Ok(None))
+    }
+}
+
+fn completion(
+    old_range: Range<language::Anchor>,
+    new_text: String,
+    label: String,
+    filter_start: usize,
+    documentation: Option<String>,
+) -> Completion {
+    Completion {
+        old_range,
+        new_text,
+        label: CodeLabel {
+            filter_range: filter_start..label.len(),
+            text: label,
+            runs: Vec::new(),
+        },
+        documentation: documentation.map(Documentation::SingleLine),
+        server_id: LanguageServerId(0),
+        lsp_completion: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_parse_slash_command() {
+        assert_eq!(
+            SlashCommand::parse("/file src/main.rs\n"),
+            Some(SlashCommand::File("src/main.rs".into()))
+        );
+        assert_eq!(
+            SlashCommand::parse("/search  fn main "),
+            Some(SlashCommand::Search("fn main".into()))
+        );
+        assert_eq!(
+            SlashCommand::parse("/diagnostics"),
+            Some(SlashCommand::Diagnostics)
+        );
+        assert_eq!(
+            SlashCommand::parse("/explain the error handling"),
+            Some(SlashCommand::Prompt {
+                name: "explain".into(),
+                input: "the error handling".into()
+            })
+        );
+        assert_eq!(SlashCommand::parse("/usr/bin is on the path"), None);
+        assert_eq!(SlashCommand::parse("// a comment"), None);
+        assert_eq!(SlashCommand::parse("/ nothing"), None);
+        assert_eq!(SlashCommand::parse("see /file"), None);
+    }
+
+    #[test]
+    fn test_slash_commands_in() {
+        let text = indoc! {"
+            What's wrong with this?
+            /file src/lib.rs
+            ```
+            /diff
+            ```
+            /diff"};
+        assert_eq!(
+            slash_commands_in(text),
+            [
+                (24..41, SlashCommand::File("src/lib.rs".into())),
+                (text.len() - 5..text.len(), SlashCommand::Diff),
+            ]
+        );
+    }
+}
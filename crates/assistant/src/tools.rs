@@ -0,0 +1,402 @@
+use crate::slash_commands::{file_attachment, search_attachment};
+use anyhow::{anyhow, Result};
+use collections::HashMap;
+use futures::{AsyncRead, AsyncReadExt as _};
+use gpui::{AppContext, Model, Task};
+use project::Project;
+use serde::{Deserialize, Serialize};
+use smol::future::FutureExt as _;
+use std::{fmt::Write, os::unix::process::CommandExt as _, process::Stdio, time::Duration};
+use util::ResultExt;
+
+const MAX_READ_LINES: usize = 500;
+const MAX_SYMBOLS: usize = 100;
+const MAX_COMMAND_OUTPUT_LINES: usize = 200;
+const MAX_COMMAND_OUTPUT_BYTES: usize = 64 * 1024;
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// An action that the assistant asks to perform by ending its message with a
+/// fenced `tool` block containing the call as JSON.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "tool", rename_all = "snake_case")]
+pub enum ToolCall {
+    ReadFile {
+        path: String,
+        start_line: Option<usize>,
+        end_line: Option<usize>,
+    },
+    Grep {
+        query: String,
+    },
+    ListSymbols {
+        query: String,
+    },
+    /// Runs one of the commands configured in the `tool_commands` setting.
+    RunCommand {
+        name: String,
+    },
+}
+
+impl ToolCall {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToolCall::ReadFile { .. } => "read_file",
+            ToolCall::Grep { .. } => "grep",
+            ToolCall::ListSymbols { .. } => "list_symbols",
+            ToolCall::RunCommand { .. } => "run_command",
+        }
+    }
+
+    /// Describes what the call does, for the user to approve it.
+    pub fn description(&self, commands: &HashMap<String, String>) -> String {
+        match self {
+            ToolCall::ReadFile {
+                path,
+                start_line,
+                end_line,
+            } => match (start_line, end_line) {
+                (None, None) => format!("read {path}"),
+                (start_line, end_line) => format!(
+                    "read lines {}-{} of {path}",
+                    start_line.unwrap_or(1),
+                    end_line.map_or_else(|| "end".to_string(), |line| line.to_string())
+                ),
+            },
+            ToolCall::Grep { query } => format!("search the project for {query:?}"),
+            ToolCall::ListSymbols { query } => {
+                format!("list the project's symbols matching {query:?}")
+            }
+            ToolCall::RunCommand { name } => match commands.get(name) {
+                Some(command) => format!("run `{command}`"),
+                None => format!("run the unknown command {name:?}"),
+            },
+        }
+    }
+
+    pub fn run(
+        &self,
+        commands: &HashMap<String, String>,
+        project: Model<Project>,
+        cx: &mut AppContext,
+    ) -> Task<Result<String>> {
+        match self {
+            ToolCall::ReadFile {
+                path,
+                start_line,
+                end_line,
+            } => {
+                let start = start_line.unwrap_or(1).max(1) - 1;
+                let end = end_line.unwrap_or(usize::MAX);
+                if end <= start {
+                    return Task::ready(Err(anyhow!("The line range is empty")));
+                }
+                let file = file_attachment(project, path, cx);
+                cx.spawn(|_| async move {
+                    let file = file.await?;
+                    let line_count = end - start;
+                    let mut output = String::new();
+                    let mut lines = file.text.lines().enumerate().skip(start);
+                    for (row, line) in lines.by_ref().take(line_count.min(MAX_READ_LINES)) {
+                        writeln!(output, "{}: {line}", row + 1).unwrap();
+                    }
+                    if line_count > MAX_READ_LINES {
+                        if let Some((row, _)) = lines.next() {
+                            write!(output, "(truncated, continue from line {})", row + 1).unwrap();
+                        }
+                    }
+                    Ok(output)
+                })
+            }
+            ToolCall::Grep { query } => {
+                let results = search_attachment(project, query.clone(), cx);
+                cx.spawn(|_| async move { Ok(results.await?.text) })
+            }
+            ToolCall::ListSymbols { query } => {
+                let query = query.clone();
+                let symbols = project.update(cx, |project, cx| project.symbols(&query, cx));
+                cx.spawn(|_| async move {
+                    let symbols = symbols.await?;
+                    if symbols.is_empty() {
+                        return Ok(format!("There are no symbols matching {query:?}"));
+                    }
+
+                    let mut output = String::new();
+                    for symbol in symbols.iter().take(MAX_SYMBOLS) {
+                        writeln!(
+                            output,
+                            "{} ({}:{})",
+                            symbol.label.text,
+                            symbol.path.path.to_string_lossy(),
+                            symbol.range.start.0.row + 1
+                        )
+                        .unwrap();
+                    }
+                    Ok(output)
+                })
+            }
+            ToolCall::RunCommand { name } => {
+                let Some(command) = commands.get(name).cloned() else {
+                    return Task::ready(Err(anyhow!("There is no command named {name:?}")));
+                };
+                let project = project.read(cx);
+                if !project.is_local() {
+                    return Task::ready(Err(anyhow!("Commands can only be run in local projects")));
+                }
+                let Some(root) = project
+                    .visible_worktrees(cx)
+                    .next()
+                    .map(|worktree| worktree.read(cx).abs_path())
+                else {
+                    return Task::ready(Err(anyhow!("The project has no folders")));
+                };
+
+                let executor = cx.background_executor().clone();
+                cx.background_executor().spawn(async move {
+                    // The command gets its own process group, so that everything it started
+                    // is killed along with it if the call is cancelled or times out.
+                    let mut shell = std::process::Command::new("/bin/sh");
+                    shell
+                        .arg("-c")
+                        .arg(&command)
+                        .current_dir(root.as_ref())
+                        .process_group(0);
+                    let mut child = smol::process::Command::from(shell)
+                        .stdin(Stdio::null())
+                        .stdout(Stdio::piped())
+                        .stderr(Stdio::piped())
+                        .kill_on_drop(true)
+                        .spawn()?;
+                    let mut process_group = ProcessGroup(Some(child.id()));
+                    let stdout = read_tail(child.stdout.take().unwrap());
+                    let stderr = read_tail(child.stderr.take().unwrap());
+                    let (stdout, stderr, status) = async {
+                        let (stdout, stderr) = futures::join!(stdout, stderr);
+                        let status = child.status().await?;
+                        // The shell has been reaped, so its id may be reused from now on.
+                        process_group.0.take();
+                        anyhow::Ok((stdout?, stderr?, status))
+                    }
+                    .or(async {
+                        executor.timer(COMMAND_TIMEOUT).await;
+                        Err(anyhow!(
+                            "The command didn't finish within {} seconds",
+                            COMMAND_TIMEOUT.as_secs()
+                        ))
+                    })
+                    .await?;
+
+                    let mut text = stdout;
+                    text.push_str(&stderr);
+                    // The end of the output usually matters most, e.g. for test failures.
+                    let lines = text.lines().collect::<Vec<_>>();
+                    let omitted_lines = lines.len().saturating_sub(MAX_COMMAND_OUTPUT_LINES);
+                    let mut result = String::new();
+                    if omitted_lines > 0 {
+                        writeln!(result, "({omitted_lines} earlier lines omitted)").unwrap();
+                    }
+                    for line in &lines[omitted_lines..] {
+                        writeln!(result, "{line}").unwrap();
+                    }
+                    write!(result, "{status}").unwrap();
+                    Ok(result)
+                })
+            }
+        }
+    }
+}
+
+/// The process group of a running command, which is killed when this is dropped
+/// before the command exits.
+struct ProcessGroup(Option<u32>);
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(id) = self.0 {
+            unsafe {
+                libc::killpg(id as libc::pid_t, libc::SIGKILL);
+            }
+        }
+    }
+}
+
+/// Reads the output of a command, keeping at most its last
+/// [`MAX_COMMAND_OUTPUT_BYTES`] bytes and starting at a line boundary if
+/// anything was dropped.
+async fn read_tail(mut reader: impl AsyncRead + Unpin) -> Result<String> {
+    let mut output = Vec::new();
+    let mut truncated = false;
+    let mut chunk = [0; 8192];
+    loop {
+        let len = reader.read(&mut chunk).await?;
+        if len == 0 {
+            break;
+        }
+        output.extend_from_slice(&chunk[..len]);
+        if output.len() > 2 * MAX_COMMAND_OUTPUT_BYTES {
+            output.drain(..output.len() - MAX_COMMAND_OUTPUT_BYTES);
+            truncated = true;
+        }
+    }
+    if output.len() > MAX_COMMAND_OUTPUT_BYTES {
+        output.drain(..output.len() - MAX_COMMAND_OUTPUT_BYTES);
+        truncated = true;
+    }
+
+    let mut output = String::from_utf8_lossy(&output).into_owned();
+    if truncated {
+        let first_line_end = output.find('\n').map_or(output.len(), |ix| ix + 1);
+        output.replace_range(..first_line_end, "(earlier output omitted)\n");
+    }
+    Ok(output)
+}
+
+/// Explains the tools to the model, including the commands it may run.
+pub fn tools_prompt(commands: &HashMap<String, String>) -> String {
+    let mut prompt = String::from(
+        "You can use tools to look at the user's project. To use one, end your message with a \
+        single code block tagged `tool` containing a JSON object with the tool's name and its \
+        arguments, then stop writing. The user approves each call and replies with its output \
+        in a <tool_result> tag.\n\nThe available tools are:\n\
+        - {\"tool\": \"read_file\", \"path\": \"src/main.rs\", \"start_line\": 1, \"end_line\": 40} \
+        reads the lines of a file. The line range is optional.\n\
+        - {\"tool\": \"grep\", \"query\": \"fn main\"} searches the project's files for text.\n\
+        - {\"tool\": \"list_symbols\", \"query\": \"Workspace\"} lists the project's symbols \
+        whose names match the query.",
+    );
+    if !commands.is_empty() {
+        let mut names = commands.keys().collect::<Vec<_>>();
+        names.sort();
+        write!(
+            prompt,
+            "\n- {{\"tool\": \"run_command\", \"name\": \"{}\"}} runs a command in the \
+            project's root. The available commands are:",
+            names[0]
+        )
+        .unwrap();
+        for name in names {
+            write!(prompt, "\n  - {name}: `{}`", commands[name]).unwrap();
+        }
+    }
+    prompt
+}
+
+/// Finds the last tool call in an assistant's message.
+pub fn parse_tool_call(message: &str) -> Option<ToolCall> {
+    const OPENING_FENCE: &str = "```tool\n";
+
+    let start = message.rfind(OPENING_FENCE)? + OPENING_FENCE.len();
+    let end = start + message[start..].find("```")?;
+    serde_json::from_str(message[start..end].trim()).log_err()
+}
+
+/// Formats the output of a tool call as the user's reply to the assistant.
+pub fn tool_result(call: &ToolCall, output: &Result<String>) -> String {
+    let (error, output) = match output {
+        Ok(output) => ("", output.trim_end().to_string()),
+        Err(error) => (" error=\"true\"", error.to_string()),
+    };
+    format!(
+        "<tool_result tool=\"{}\"{error}>\n{output}\n</tool_result>",
+        call.name()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_parse_tool_call() {
+        let message = indoc! {r#"
+            Let me look at the definition first.
+
+            ```tool
+            {"tool": "read_file", "path": "src/lib.rs", "start_line": 10}
+            ```
+        "#};
+        assert_eq!(
+            parse_tool_call(message),
+            Some(ToolCall::ReadFile {
+                path: "src/lib.rs".into(),
+                start_line: Some(10),
+                end_line: None
+            })
+        );
+
+        let message = indoc! {r#"
+            ```tool
+            {"tool": "grep", "query": "a"}
+            ```
+            That didn't help, so let me run the tests.
+            ```tool
+            {"tool": "run_command", "name": "test"}
+            ```
+        "#};
+        assert_eq!(
+            parse_tool_call(message),
+            Some(ToolCall::RunCommand {
+                name: "test".into()
+            })
+        );
+
+        assert_eq!(
+            parse_tool_call("```tool\n{\"tool\": \"delete\"}\n```"),
+            None
+        );
+        assert_eq!(parse_tool_call("```rust\nfn main() {}\n```"), None);
+    }
+
+    #[test]
+    fn test_tool_result() {
+        let call = ToolCall::Grep { query: "a".into() };
+        assert_eq!(
+            tool_result(&call, &Ok("src/a.rs:1: a\n".into())),
+            "<tool_result tool=\"grep\">\nsrc/a.rs:1: a\n</tool_result>"
+        );
+        assert_eq!(
+            tool_result(&call, &Err(anyhow!("no results"))),
+            "<tool_result tool=\"grep\" error=\"true\">\nno results\n</tool_result>"
+        );
+    }
+
+    #[test]
+    fn test_read_tail() {
+        let output = (0..20_000)
+            .map(|ix| format!("line {ix}\n"))
+            .collect::<String>();
+        let tail =
+            smol::block_on(read_tail(futures::io::Cursor::new(output.into_bytes()))).unwrap();
+        let marker = "(earlier output omitted)\n";
+        assert!(tail.len() <= MAX_COMMAND_OUTPUT_BYTES + marker.len());
+        assert!(tail.starts_with(&format!("{marker}line ")));
+        assert!(tail.ends_with("line 19998\nline 19999\n"));
+
+        let tail = smol::block_on(read_tail(futures::io::Cursor::new(b"a\nb\n".to_vec()))).unwrap();
+        assert_eq!(tail, "a\nb\n");
+    }
+
+    #[test]
+    fn test_process_group_kills_background_processes() {
+        use std::io::{BufRead as _, BufReader, Read as _};
+
+        let mut shell = std::process::Command::new("/bin/sh");
+        shell
+            .arg("-c")
+            .arg("sleep 30 & echo started; wait")
+            .stdout(Stdio::piped())
+            .process_group(0);
+        let mut child = shell.spawn().unwrap();
+        let mut stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut line = String::new();
+        stdout.read_line(&mut line).unwrap();
+        assert_eq!(line, "started\n");
+
+        let started_at = std::time::Instant::now();
+        drop(ProcessGroup(Some(child.id())));
+        assert!(!child.wait().unwrap().success());
+        // The output only ends once the background `sleep` has been killed too.
+        stdout.read_to_string(&mut line).unwrap();
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -1266,6 +1266,13 @@ mod tests {
             unimplemented!()
         }
 
+        fn load_rope(
+            &self,
+            _: &AppContext,
+        ) -> Task<Result<(language::Rope, language::LineEnding)>> {
+            unimplemented!()
+        }
+
         fn buffer_reloaded(
             &self,
             _: BufferId,
@@ -199,6 +199,7 @@ gpui::actions!(
         PageUp,
         Paste,
         Redo,
+        RedoChronologically,
         RedoSelection,
         Rename,
         RestartLanguageServer,
@@ -236,6 +237,7 @@ gpui::actions!(
         ToggleSoftWrap,
         Transpose,
         Undo,
+        UndoChronologically,
         UndoSelection,
         UnfoldLines,
     ]
@@ -0,0 +1,245 @@
+use crate::{Direction, InlineCompletionProvider};
+use client::telemetry::Telemetry;
+use copilot::Copilot;
+use gpui::{AppContext, EntityId, Model, ModelContext, Task};
+use language::{language_settings::all_language_settings, Buffer, OffsetRangeExt, ToOffset};
+use std::{path::Path, sync::Arc, time::Duration};
+use util::ResultExt;
+
+pub const COPILOT_DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(75);
+
+/// Supplies inline completions from GitHub Copilot.
+pub struct CopilotCompletionProvider {
+    cycled: bool,
+    buffer_id: Option<EntityId>,
+    completions: Vec<copilot::Completion>,
+    active_completion_index: usize,
+    file_extension: Option<String>,
+    pending_refresh: Task<Option<()>>,
+    pending_cycling_refresh: Task<Option<()>>,
+    copilot: Model<Copilot>,
+    telemetry: Option<Arc<Telemetry>>,
+}
+
+impl CopilotCompletionProvider {
+    pub fn new(copilot: Model<Copilot>) -> Self {
+        Self {
+            cycled: false,
+            buffer_id: None,
+            completions: Vec::new(),
+            active_completion_index: 0,
+            file_extension: None,
+            pending_refresh: Task::ready(Some(())),
+            pending_cycling_refresh: Task::ready(Some(())),
+            copilot,
+            telemetry: None,
+        }
+    }
+
+    pub fn with_telemetry(mut self, telemetry: Arc<Telemetry>) -> Self {
+        self.telemetry = Some(telemetry);
+        self
+    }
+
+    fn active_completion(&self) -> Option<&copilot::Completion> {
+        self.completions.get(self.active_completion_index)
+    }
+
+    fn push_completion(&mut self, new_completion: copilot::Completion) {
+        for completion in &self.completions {
+            if completion.text == new_completion.text && completion.range == new_completion.range {
+                return;
+            }
+        }
+        self.completions.push(new_completion);
+    }
+}
+
+impl InlineCompletionProvider for CopilotCompletionProvider {
+    fn is_enabled(
+        &self,
+        buffer: &Model<Buffer>,
+        cursor_position: language::Anchor,
+        cx: &AppContext,
+    ) -> bool {
+        if !self.copilot.read(cx).status().is_authorized() {
+            return false;
+        }
+
+        let buffer = buffer.read(cx);
+        let file = buffer.file();
+        let language = buffer.language_at(cursor_position);
+        let settings = all_language_settings(file, cx);
+        settings.copilot_enabled(language.as_ref(), file.map(|f| f.path().as_ref()))
+    }
+
+    fn refresh(
+        &mut self,
+        buffer: Model<Buffer>,
+        cursor_position: language::Anchor,
+        debounce: bool,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let copilot = self.copilot.clone();
+        self.pending_refresh = cx.spawn(|this, mut cx| async move {
+            if debounce {
+                cx.background_executor()
+                    .timer(COPILOT_DEBOUNCE_TIMEOUT)
+                    .await;
+            }
+
+            let completions = copilot
+                .update(&mut cx, |copilot, cx| {
+                    copilot.completions(&buffer, cursor_position, cx)
+                })
+                .log_err()?
+                .await
+                .log_err()?;
+
+            this.update(&mut cx, |this, cx| {
+                if !completions.is_empty() {
+                    this.cycled = false;
+                    this.pending_cycling_refresh = Task::ready(None);
+                    this.completions.clear();
+                    this.active_completion_index = 0;
+                    this.buffer_id = Some(buffer.entity_id());
+                    this.file_extension = buffer.read(cx).file().and_then(|file| {
+                        Some(
+                            Path::new(file.file_name(cx))
+                                .extension()?
+                                .to_str()?
+                                .to_string(),
+                        )
+                    });
+
+                    for completion in completions {
+                        this.push_completion(completion);
+                    }
+                    cx.notify();
+                }
+            })
+            .log_err()
+        });
+    }
+
+    fn cycle(
+        &mut self,
+        buffer: Model<Buffer>,
+        cursor_position: language::Anchor,
+        direction: Direction,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let cycle = move |this: &mut Self| match direction {
+            Direction::Prev => {
+                this.active_completion_index = if this.active_completion_index == 0 {
+                    this.completions.len().saturating_sub(1)
+                } else {
+                    this.active_completion_index - 1
+                };
+            }
+            Direction::Next => {
+                if this.completions.is_empty() {
+                    this.active_completion_index = 0
+                } else {
+                    this.active_completion_index =
+                        (this.active_completion_index + 1) % this.completions.len();
+                }
+            }
+        };
+
+        if self.cycled {
+            cycle(self);
+            cx.notify();
+        } else {
+            let copilot = self.copilot.clone();
+            self.pending_cycling_refresh = cx.spawn(|this, mut cx| async move {
+                let completions = copilot
+                    .update(&mut cx, |copilot, cx| {
+                        copilot.completions_cycling(&buffer, cursor_position, cx)
+                    })
+                    .log_err()?
+                    .await;
+
+                this.update(&mut cx, |this, cx| {
+                    this.cycled = true;
+                    for completion in completions.log_err().into_iter().flatten() {
+                        this.push_completion(completion);
+                    }
+                    cycle(this);
+                    cx.notify();
+                })
+                .log_err()
+            });
+        }
+    }
+
+    fn accept(&mut self, cx: &mut ModelContext<Self>) {
+        if let Some(completion) = self.active_completion() {
+            self.copilot
+                .update(cx, |copilot, cx| copilot.accept_completion(completion, cx))
+                .detach_and_log_err(cx);
+            if let Some(telemetry) = self.telemetry.as_ref() {
+                telemetry.report_copilot_event(
+                    Some(completion.uuid.clone()),
+                    true,
+                    self.file_extension.clone(),
+                );
+            }
+        }
+    }
+
+    fn discard(&mut self, cx: &mut ModelContext<Self>) {
+        self.copilot
+            .update(cx, |copilot, cx| {
+                copilot.discard_completions(&self.completions, cx)
+            })
+            .detach_and_log_err(cx);
+        if let Some(telemetry) = self.telemetry.as_ref() {
+            telemetry.report_copilot_event(None, false, self.file_extension.clone());
+        }
+    }
+
+    fn active_completion_text<'a>(
+        &'a self,
+        buffer: &Model<Buffer>,
+        cursor_position: language::Anchor,
+        cx: &'a AppContext,
+    ) -> Option<&'a str> {
+        let buffer_id = buffer.entity_id();
+        let buffer = buffer.read(cx);
+        let completion = self.active_completion()?;
+        if Some(buffer_id) != self.buffer_id
+            || !completion.range.start.is_valid(buffer)
+            || !completion.range.end.is_valid(buffer)
+        {
+            return None;
+        }
+
+        let mut completion_range = completion.range.to_offset(buffer);
+        let prefix_len = common_prefix(
+            buffer.chars_for_range(completion_range.clone()),
+            completion.text.chars(),
+        );
+        completion_range.start += prefix_len;
+        let suffix_len = common_prefix(
+            buffer.reversed_chars_for_range(completion_range.clone()),
+            completion.text[prefix_len..].chars().rev(),
+        );
+        completion_range.end = completion_range.end.saturating_sub(suffix_len);
+
+        if completion_range.is_empty()
+            && completion_range.start == cursor_position.to_offset(buffer)
+        {
+            Some(&completion.text[prefix_len..completion.text.len() - suffix_len])
+        } else {
+            None
+        }
+    }
+}
+
+fn common_prefix<T1: Iterator<Item = char>, T2: Iterator<Item = char>>(a: T1, b: T2) -> usize {
+    a.zip(b)
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum()
+}
@@ -16,6 +16,7 @@ pub struct EditorSettings {
     pub relative_line_numbers: bool,
     pub seed_search_query_from_cursor: SeedQuerySetting,
     pub redact_private_values: bool,
+    pub persistent_undo: bool,
 }
 
 /// When to populate a new search's query based on the text under the cursor.
@@ -117,6 +118,12 @@ pub struct EditorSettingsContent {
     ///
     /// Default: false
     pub redact_private_values: Option<bool>,
+
+    /// Whether to remember the undo history of files when they are saved, so
+    /// that edits can still be undone after closing and reopening them.
+    ///
+    /// Default: true
+    pub persistent_undo: Option<bool>,
 }
 
 // Toolbar related settings
@@ -1,5 +1,6 @@
 use super::*;
 use crate::{
+    copilot_completion_provider::COPILOT_DEBOUNCE_TIMEOUT,
     scroll::scroll_amount::ScrollAmount,
     test::{
         assert_text_with_selections, build_editor, editor_lsp_test_context::EditorLspTestContext,
@@ -8,6 +9,7 @@ use crate::{
     JoinLines,
 };
 
+use copilot::Copilot;
 use futures::StreamExt;
 use gpui::{div, TestAppContext, VisualTestContext, WindowBounds, WindowOptions};
 use indoc::indoc;
@@ -7226,7 +7228,7 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
     init_test(cx, |_| {});
 
     let (copilot, copilot_lsp) = Copilot::fake(cx);
-    _ = cx.update(|cx| Copilot::set_global(copilot, cx));
+    _ = cx.update(|cx| Copilot::set_global(copilot.clone(), cx));
     let mut cx = EditorLspTestContext::new_rust(
         lsp::ServerCapabilities {
             completion_provider: Some(lsp::CompletionOptions {
@@ -7238,6 +7240,10 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
         cx,
     )
     .await;
+    let copilot_provider = cx.new_model(|_| CopilotCompletionProvider::new(copilot));
+    cx.update_editor(|editor, cx| {
+        editor.set_inline_completion_provider(Some(copilot_provider), cx)
+    });
 
     // When inserting, ensure autocompletion is favored over Copilot suggestions.
     cx.set_state(indoc! {"
@@ -7267,7 +7273,7 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
     cx.update_editor(|editor, cx| {
         assert!(editor.context_menu_visible());
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
 
         // Confirming a completion inserts it and hides the context menu, without showing
         // the copilot suggestion afterwards.
@@ -7276,7 +7282,7 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
             .unwrap()
             .detach();
         assert!(!editor.context_menu_visible());
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(editor.text(cx), "one.completion_a\ntwo\nthree\n");
         assert_eq!(editor.display_text(cx), "one.completion_a\ntwo\nthree\n");
     });
@@ -7309,7 +7315,7 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
     cx.update_editor(|editor, cx| {
         assert!(!editor.context_menu_visible());
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.copilot1\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.\ntwo\nthree\n");
     });
@@ -7342,12 +7348,12 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
     cx.update_editor(|editor, cx| {
         assert!(editor.context_menu_visible());
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
 
         // When hiding the context menu, the Copilot suggestion becomes visible.
         editor.hide_context_menu(cx);
         assert!(!editor.context_menu_visible());
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.copilot1\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.\ntwo\nthree\n");
     });
@@ -7357,7 +7363,7 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
     executor.run_until_parked();
     cx.update_editor(|editor, cx| {
         assert!(!editor.context_menu_visible());
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.copilot1\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.c\ntwo\nthree\n");
     });
@@ -7375,25 +7381,25 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
     cx.update_editor(|editor, cx| {
         assert!(!editor.context_menu_visible());
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.copilot2\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.c\ntwo\nthree\n");
 
         // Canceling should remove the active Copilot suggestion.
         editor.cancel(&Default::default(), cx);
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.c\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.c\ntwo\nthree\n");
 
         // After canceling, tabbing shouldn't insert the previously shown suggestion.
         editor.tab(&Default::default(), cx);
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.c   \ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.c   \ntwo\nthree\n");
 
         // When undoing the previously active suggestion is shown again.
         editor.undo(&Default::default(), cx);
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.copilot2\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.c\ntwo\nthree\n");
     });
@@ -7401,25 +7407,25 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
     // If an edit occurs outside of this editor, the suggestion is still correctly interpolated.
     cx.update_buffer(|buffer, cx| buffer.edit([(5..5, "o")], None, cx));
     cx.update_editor(|editor, cx| {
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.copilot2\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.co\ntwo\nthree\n");
 
         // Tabbing when there is an active suggestion inserts it.
         editor.tab(&Default::default(), cx);
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.copilot2\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.copilot2\ntwo\nthree\n");
 
         // When undoing the previously active suggestion is shown again.
         editor.undo(&Default::default(), cx);
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.copilot2\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.co\ntwo\nthree\n");
 
         // Hide suggestion.
         editor.cancel(&Default::default(), cx);
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.co\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.co\ntwo\nthree\n");
     });
@@ -7428,7 +7434,7 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
     // we won't make it visible.
     cx.update_buffer(|buffer, cx| buffer.edit([(6..6, "p")], None, cx));
     cx.update_editor(|editor, cx| {
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one.cop\ntwo\nthree\n");
         assert_eq!(editor.text(cx), "one.cop\ntwo\nthree\n");
     });
@@ -7450,22 +7456,22 @@ async fn test_copilot(executor: BackgroundExecutor, cx: &mut gpui::TestAppContex
         vec![],
     );
 
-    cx.update_editor(|editor, cx| editor.next_copilot_suggestion(&Default::default(), cx));
+    cx.update_editor(|editor, cx| editor.next_inline_completion(&Default::default(), cx));
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
     cx.update_editor(|editor, cx| {
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "fn foo() {\n    let x = 4;\n}");
         assert_eq!(editor.text(cx), "fn foo() {\n  \n}");
 
         // Tabbing inside of leading whitespace inserts indentation without accepting the suggestion.
         editor.tab(&Default::default(), cx);
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.text(cx), "fn foo() {\n    \n}");
         assert_eq!(editor.display_text(cx), "fn foo() {\n    let x = 4;\n}");
 
         // Tabbing again accepts the suggestion.
         editor.tab(&Default::default(), cx);
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(editor.text(cx), "fn foo() {\n    let x = 4;\n}");
         assert_eq!(editor.display_text(cx), "fn foo() {\n    let x = 4;\n}");
     });
@@ -7479,7 +7485,7 @@ async fn test_copilot_completion_invalidation(
     init_test(cx, |_| {});
 
     let (copilot, copilot_lsp) = Copilot::fake(cx);
-    _ = cx.update(|cx| Copilot::set_global(copilot, cx));
+    _ = cx.update(|cx| Copilot::set_global(copilot.clone(), cx));
     let mut cx = EditorLspTestContext::new_rust(
         lsp::ServerCapabilities {
             completion_provider: Some(lsp::CompletionOptions {
@@ -7491,6 +7497,10 @@ async fn test_copilot_completion_invalidation(
         cx,
     )
     .await;
+    let copilot_provider = cx.new_model(|_| CopilotCompletionProvider::new(copilot));
+    cx.update_editor(|editor, cx| {
+        editor.set_inline_completion_provider(Some(copilot_provider), cx)
+    });
 
     cx.set_state(indoc! {"
         one
@@ -7507,32 +7517,32 @@ async fn test_copilot_completion_invalidation(
         }],
         vec![],
     );
-    cx.update_editor(|editor, cx| editor.next_copilot_suggestion(&Default::default(), cx));
+    cx.update_editor(|editor, cx| editor.next_inline_completion(&Default::default(), cx));
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
     cx.update_editor(|editor, cx| {
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one\ntwo.foo()\nthree\n");
         assert_eq!(editor.text(cx), "one\ntw\nthree\n");
 
         editor.backspace(&Default::default(), cx);
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one\ntwo.foo()\nthree\n");
         assert_eq!(editor.text(cx), "one\nt\nthree\n");
 
         editor.backspace(&Default::default(), cx);
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one\ntwo.foo()\nthree\n");
         assert_eq!(editor.text(cx), "one\n\nthree\n");
 
         // Deleting across the original suggestion range invalidates it.
         editor.backspace(&Default::default(), cx);
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one\nthree\n");
         assert_eq!(editor.text(cx), "one\nthree\n");
 
         // Undoing the deletion restores the suggestion.
         editor.undo(&Default::default(), cx);
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(editor.display_text(cx), "one\ntwo.foo()\nthree\n");
         assert_eq!(editor.text(cx), "one\n\nthree\n");
     });
@@ -7543,7 +7553,7 @@ async fn test_copilot_multibuffer(executor: BackgroundExecutor, cx: &mut gpui::T
     init_test(cx, |_| {});
 
     let (copilot, copilot_lsp) = Copilot::fake(cx);
-    _ = cx.update(|cx| Copilot::set_global(copilot, cx));
+    _ = cx.update(|cx| Copilot::set_global(copilot.clone(), cx));
 
     let buffer_1 = cx.new_model(|cx| {
         Buffer::new(
@@ -7580,6 +7590,10 @@ async fn test_copilot_multibuffer(executor: BackgroundExecutor, cx: &mut gpui::T
         multibuffer
     });
     let editor = cx.add_window(|cx| build_editor(multibuffer, cx));
+    let copilot_provider = cx.new_model(|_| CopilotCompletionProvider::new(copilot));
+    _ = editor.update(cx, |editor, cx| {
+        editor.set_inline_completion_provider(Some(copilot_provider), cx)
+    });
 
     handle_copilot_completion_request(
         &copilot_lsp,
@@ -7595,11 +7609,11 @@ async fn test_copilot_multibuffer(executor: BackgroundExecutor, cx: &mut gpui::T
         editor.change_selections(None, cx, |s| {
             s.select_ranges([Point::new(1, 5)..Point::new(1, 5)])
         });
-        editor.next_copilot_suggestion(&Default::default(), cx);
+        editor.next_inline_completion(&Default::default(), cx);
     });
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
     _ = editor.update(cx, |editor, cx| {
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(
             editor.display_text(cx),
             "\n\na = 1\nb = 2 + a\n\n\n\nc = 3\nd = 4\n"
@@ -7621,7 +7635,7 @@ async fn test_copilot_multibuffer(executor: BackgroundExecutor, cx: &mut gpui::T
         editor.change_selections(None, cx, |s| {
             s.select_ranges([Point::new(4, 5)..Point::new(4, 5)])
         });
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(
             editor.display_text(cx),
             "\n\na = 1\nb = 2\n\n\n\nc = 3\nd = 4\n"
@@ -7630,7 +7644,7 @@ async fn test_copilot_multibuffer(executor: BackgroundExecutor, cx: &mut gpui::T
 
         // Type a character, ensuring we don't even try to interpolate the previous suggestion.
         editor.handle_input(" ", cx);
-        assert!(!editor.has_active_copilot_suggestion(cx));
+        assert!(!editor.has_active_inline_completion(cx));
         assert_eq!(
             editor.display_text(cx),
             "\n\na = 1\nb = 2\n\n\n\nc = 3\nd = 4 \n"
@@ -7641,7 +7655,7 @@ async fn test_copilot_multibuffer(executor: BackgroundExecutor, cx: &mut gpui::T
     // Ensure the new suggestion is displayed when the debounce timeout expires.
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
     _ = editor.update(cx, |editor, cx| {
-        assert!(editor.has_active_copilot_suggestion(cx));
+        assert!(editor.has_active_inline_completion(cx));
         assert_eq!(
             editor.display_text(cx),
             "\n\na = 1\nb = 2\n\n\n\nc = 3\nd = 4 + c\n"
@@ -7660,7 +7674,7 @@ async fn test_copilot_disabled_globs(executor: BackgroundExecutor, cx: &mut gpui
     });
 
     let (copilot, copilot_lsp) = Copilot::fake(cx);
-    _ = cx.update(|cx| Copilot::set_global(copilot, cx));
+    _ = cx.update(|cx| Copilot::set_global(copilot.clone(), cx));
 
     let fs = FakeFs::new(cx.executor());
     fs.insert_tree(
@@ -7707,6 +7721,10 @@ async fn test_copilot_disabled_globs(executor: BackgroundExecutor, cx: &mut gpui
         multibuffer
     });
     let editor = cx.add_window(|cx| build_editor(multibuffer, cx));
+    let copilot_provider = cx.new_model(|_| CopilotCompletionProvider::new(copilot));
+    _ = editor.update(cx, |editor, cx| {
+        editor.set_inline_completion_provider(Some(copilot_provider), cx)
+    });
 
     let mut copilot_requests = copilot_lsp
         .handle_request::<copilot::request::GetCompletions, _, _>(move |_params, _cx| async move {
@@ -7723,7 +7741,7 @@ async fn test_copilot_disabled_globs(executor: BackgroundExecutor, cx: &mut gpui
         editor.change_selections(None, cx, |selections| {
             selections.select_ranges([Point::new(0, 0)..Point::new(0, 0)])
         });
-        editor.next_copilot_suggestion(&Default::default(), cx);
+        editor.next_inline_completion(&Default::default(), cx);
     });
 
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
@@ -7733,7 +7751,7 @@ async fn test_copilot_disabled_globs(executor: BackgroundExecutor, cx: &mut gpui
         editor.change_selections(None, cx, |s| {
             s.select_ranges([Point::new(2, 0)..Point::new(2, 0)])
         });
-        editor.next_copilot_suggestion(&Default::default(), cx);
+        editor.next_inline_completion(&Default::default(), cx);
     });
 
     executor.advance_clock(COPILOT_DEBOUNCE_TIMEOUT);
@@ -11,22 +11,22 @@ use crate::{
     items::BufferSearchHighlights,
     mouse_context_menu,
     scroll::scroll_amount::ScrollAmount,
-    CursorShape, DisplayPoint, DocumentHighlightRead, DocumentHighlightWrite, Editor, EditorMode,
-    EditorSettings, EditorSnapshot, EditorStyle, HalfPageDown, HalfPageUp, HoveredCursor, LineDown,
-    LineUp, OpenExcerpts, PageDown, PageUp, Point, SelectPhase, Selection, SoftWrap, ToPoint,
-    CURSORS_VISIBLE_FOR, MAX_LINE_LEN,
+    AnchorRangeExt, CursorShape, DisplayPoint, DocumentHighlightRead, DocumentHighlightWrite,
+    Editor, EditorMode, EditorSettings, EditorSnapshot, EditorStyle, HalfPageDown, HalfPageUp,
+    HoveredCursor, LineDown, LineUp, OpenExcerpts, PageDown, PageUp, Point, SelectPhase, Selection,
+    SoftWrap, ToPoint, CURSORS_VISIBLE_FOR, MAX_LINE_LEN,
 };
 use anyhow::Result;
 use collections::{BTreeMap, HashMap};
 use git::diff::DiffHunkStatus;
 use gpui::{
-    div, fill, outline, overlay, point, px, quad, relative, size, transparent_black, Action,
-    AnchorCorner, AnyElement, AvailableSpace, Bounds, ContentMask, Corners, CursorStyle,
-    DispatchPhase, Edges, Element, ElementInputHandler, Entity, Hsla, InteractiveBounds,
-    InteractiveElement, IntoElement, ModifiersChangedEvent, MouseButton, MouseDownEvent,
-    MouseMoveEvent, MouseUpEvent, ParentElement, Pixels, ScrollDelta, ScrollWheelEvent, ShapedLine,
-    SharedString, Size, StackingOrder, StatefulInteractiveElement, Style, Styled, TextRun,
-    TextStyle, View, ViewContext, WindowContext,
+    div, fill, outline, overlay, point, px, quad, relative, size, transparent_black,
+    AccessibilityNode, AccessibilityRole, Action, AnchorCorner, AnyElement, AvailableSpace, Bounds,
+    ContentMask, Corners, CursorStyle, DispatchPhase, Edges, Element, ElementInputHandler, Entity,
+    Hsla, InteractiveBounds, InteractiveElement, IntoElement, ModifiersChangedEvent, MouseButton,
+    MouseDownEvent, MouseMoveEvent, MouseUpEvent, ParentElement, Pixels, ScrollDelta,
+    ScrollWheelEvent, ShapedLine, SharedString, Size, StackingOrder, StatefulInteractiveElement,
+    Style, Styled, TextRun, TextStyle, View, ViewContext, WindowContext,
 };
 use itertools::Itertools;
 use language::language_settings::ShowWhitespaceSetting;
@@ -186,6 +186,8 @@ impl EditorElement {
         register_action(view, cx, Editor::paste);
         register_action(view, cx, Editor::undo);
         register_action(view, cx, Editor::redo);
+        register_action(view, cx, Editor::undo_chronologically);
+        register_action(view, cx, Editor::redo_chronologically);
         register_action(view, cx, Editor::move_page_up);
         register_action(view, cx, Editor::move_page_down);
         register_action(view, cx, Editor::next_screen);
@@ -322,9 +324,9 @@ impl EditorElement {
                 cx.propagate();
             }
         });
-        register_action(view, cx, Editor::next_copilot_suggestion);
-        register_action(view, cx, Editor::previous_copilot_suggestion);
-        register_action(view, cx, Editor::copilot_suggest);
+        register_action(view, cx, Editor::next_inline_completion);
+        register_action(view, cx, Editor::previous_inline_completion);
+        register_action(view, cx, Editor::show_inline_completion);
         register_action(view, cx, Editor::context_menu_first);
         register_action(view, cx, Editor::context_menu_prev);
         register_action(view, cx, Editor::context_menu_next);
@@ -874,6 +876,53 @@ impl EditorElement {
         }
     }
 
+    /// Describes the editor to assistive technologies, exposing the visible
+    /// text and the newest selection within it.
+    fn accessibility_node(
+        &self,
+        bounds: Bounds<Pixels>,
+        layout: &LayoutState,
+        cx: &WindowContext,
+    ) -> AccessibilityNode {
+        let editor = self.editor.read(cx);
+        let role = match layout.mode {
+            EditorMode::SingleLine | EditorMode::AutoHeight { .. } => AccessibilityRole::TextInput,
+            EditorMode::Full => AccessibilityRole::MultilineTextInput,
+        };
+        let mut node =
+            AccessibilityNode::new(role, bounds).focused(editor.focus_handle.is_focused(cx));
+        let label = match layout.mode {
+            EditorMode::Full => Some(SharedString::from(
+                editor.buffer.read(cx).title(cx).to_string(),
+            )),
+            _ => editor
+                .placeholder_text
+                .as_ref()
+                .map(|placeholder| SharedString::from(placeholder.to_string())),
+        };
+        if let Some(label) = label {
+            node = node.label(label);
+        }
+
+        // Don't expose text the editor hides, like passwords.
+        if layout.redacted_ranges.is_empty() {
+            let buffer = &layout.position_map.snapshot.buffer_snapshot;
+            let visible_range = layout.visible_anchor_range.to_offset(buffer);
+            node = node.value(
+                buffer
+                    .text_for_range(visible_range.clone())
+                    .collect::<String>(),
+            );
+            let selection = editor.selections.newest::<usize>(cx).range();
+            if visible_range.start <= selection.start && selection.end <= visible_range.end {
+                node = node.selection(
+                    selection.start - visible_range.start..selection.end - visible_range.start,
+                );
+            }
+        }
+        node
+    }
+
     fn paint_text(
         &mut self,
         text_bounds: Bounds<Pixels>,
@@ -3091,7 +3140,15 @@ impl Element for EditorElement {
                             if layout.gutter_size.width > Pixels::ZERO {
                                 self.paint_gutter(gutter_bounds, &mut layout, cx);
                             }
-                            self.paint_text(text_bounds, &mut layout, cx);
+                            if cx.is_accessibility_enabled() {
+                                let accessibility_node =
+                                    self.accessibility_node(bounds, &layout, cx);
+                                cx.with_accessibility_node(accessibility_node, |cx| {
+                                    self.paint_text(text_bounds, &mut layout, cx);
+                                });
+                            } else {
+                                self.paint_text(text_bounds, &mut layout, cx);
+                            }
 
                             cx.with_z_index(0, |cx| {
                                 self.paint_mouse_listeners(
@@ -0,0 +1,141 @@
+use crate::Direction;
+use gpui::{AppContext, Model, ModelContext};
+use language::Buffer;
+
+/// A source of the inline completions that the editor shows as ghost text at
+/// the cursor.
+///
+/// Providers hold on to the completions they fetched and notify their model
+/// when they change, at which point the editor asks for the text to display.
+pub trait InlineCompletionProvider: 'static + Sized {
+    /// Whether completions should be requested at the given position.
+    fn is_enabled(
+        &self,
+        buffer: &Model<Buffer>,
+        cursor_position: language::Anchor,
+        cx: &AppContext,
+    ) -> bool;
+
+    /// Fetches completions for the given position, replacing any pending
+    /// request.
+    fn refresh(
+        &mut self,
+        buffer: Model<Buffer>,
+        cursor_position: language::Anchor,
+        debounce: bool,
+        cx: &mut ModelContext<Self>,
+    );
+
+    /// Switches to the next or previous completion, fetching alternatives if
+    /// there are none yet.
+    fn cycle(
+        &mut self,
+        buffer: Model<Buffer>,
+        cursor_position: language::Anchor,
+        direction: Direction,
+        cx: &mut ModelContext<Self>,
+    );
+
+    /// Called when the user inserts the active completion.
+    fn accept(&mut self, cx: &mut ModelContext<Self>);
+
+    /// Called when the active completion stops being displayed without being
+    /// accepted.
+    fn discard(&mut self, cx: &mut ModelContext<Self>);
+
+    /// Returns the text to insert at the cursor, if the active completion
+    /// still applies to it.
+    fn active_completion_text<'a>(
+        &'a self,
+        buffer: &Model<Buffer>,
+        cursor_position: language::Anchor,
+        cx: &'a AppContext,
+    ) -> Option<&'a str>;
+}
+
+pub(crate) trait InlineCompletionProviderHandle {
+    fn is_enabled(
+        &self,
+        buffer: &Model<Buffer>,
+        cursor_position: language::Anchor,
+        cx: &AppContext,
+    ) -> bool;
+    fn refresh(
+        &self,
+        buffer: Model<Buffer>,
+        cursor_position: language::Anchor,
+        debounce: bool,
+        cx: &mut AppContext,
+    );
+    fn cycle(
+        &self,
+        buffer: Model<Buffer>,
+        cursor_position: language::Anchor,
+        direction: Direction,
+        cx: &mut AppContext,
+    );
+    fn accept(&self, cx: &mut AppContext);
+    fn discard(&self, cx: &mut AppContext);
+    fn active_completion_text<'a>(
+        &'a self,
+        buffer: &Model<Buffer>,
+        cursor_position: language::Anchor,
+        cx: &'a AppContext,
+    ) -> Option<&'a str>;
+}
+
+impl<T> InlineCompletionProviderHandle for Model<T>
+where
+    T: InlineCompletionProvider,
+{
+    fn is_enabled(
+        &self,
+        buffer: &Model<Buffer>,
+        cursor_position: language::Anchor,
+        cx: &AppContext,
+    ) -> bool {
+        self.read(cx).is_enabled(buffer, cursor_position, cx)
+    }
+
+    fn refresh(
+        &self,
+        buffer: Model<Buffer>,
+        cursor_position: language::Anchor,
+        debounce: bool,
+        cx: &mut AppContext,
+    ) {
+        self.update(cx, |this, cx| {
+            this.refresh(buffer, cursor_position, debounce, cx)
+        })
+    }
+
+    fn cycle(
+        &self,
+        buffer: Model<Buffer>,
+        cursor_position: language::Anchor,
+        direction: Direction,
+        cx: &mut AppContext,
+    ) {
+        self.update(cx, |this, cx| {
+            this.cycle(buffer, cursor_position, direction, cx)
+        })
+    }
+
+    fn accept(&self, cx: &mut AppContext) {
+        self.update(cx, |this, cx| this.accept(cx))
+    }
+
+    fn discard(&self, cx: &mut AppContext) {
+        self.update(cx, |this, cx| this.discard(cx))
+    }
+
+    fn active_completion_text<'a>(
+        &'a self,
+        buffer: &Model<Buffer>,
+        cursor_position: language::Anchor,
+        cx: &'a AppContext,
+    ) -> Option<&'a str> {
+        self.read(cx)
+            .active_completion_text(buffer, cursor_position, cx)
+    }
+}
@@ -3,7 +3,7 @@ use crate::{
     Editor, EditorEvent, EditorSettings, ExcerptId, ExcerptRange, MultiBuffer, MultiBufferSnapshot,
     NavigationData, ToPoint as _,
 };
-use anyhow::{anyhow, Context as _, Result};
+use anyhow::{Context as _, Result};
 use collections::HashSet;
 use futures::future::try_join_all;
 use gpui::{
@@ -12,8 +12,8 @@ use gpui::{
     Subscription, Task, View, ViewContext, VisualContext, WeakView, WindowContext,
 };
 use language::{
-    proto::serialize_anchor as serialize_text_anchor, Bias, Buffer, CharKind, OffsetRangeExt,
-    Point, SelectionGoal,
+    proto::{serialize_anchor as serialize_text_anchor, serialize_fingerprint},
+    Bias, Buffer, CharKind, OffsetRangeExt, Point, SelectionGoal, SerializedHistory,
 };
 use project::repository::GitFileStatus;
 use project::{search::SearchQuery, FormatTrigger, Item as _, Project, ProjectPath};
@@ -29,6 +29,7 @@ use std::{
     ops::Range,
     path::{Path, PathBuf},
     sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use text::{BufferId, Selection};
 use theme::Theme;
@@ -45,6 +46,12 @@ use workspace::{
 };
 
 pub const MAX_TAB_TITLE_LEN: usize = 24;
+/// The maximum number of bytes of edited text kept when persisting a file's undo history.
+const MAX_PERSISTED_UNDO_HISTORY_LEN: usize = 1024 * 1024;
+/// The maximum number of files whose undo history is persisted.
+const MAX_PERSISTED_UNDO_HISTORIES: usize = 200;
+/// How long a file's persisted undo history is kept after it was last saved.
+const PERSISTED_UNDO_HISTORY_MAX_AGE: Duration = Duration::from_secs(60 * 60 * 24 * 30);
 
 impl FollowableItem for Editor {
     fn remote_id(&self) -> Option<ViewId> {
@@ -460,6 +467,88 @@ async fn update_editor_from_message(
     Ok(())
 }
 
+/// Persists the buffer's undo history alongside a fingerprint of its saved contents, so
+/// that it can be restored the next time the file is opened.
+fn save_undo_history(buffer: &Model<Buffer>, cx: &mut AppContext) {
+    let buffer = buffer.read(cx);
+    let Some(path) = buffer
+        .file()
+        .and_then(|file| file.as_local())
+        .map(|file| file.abs_path(cx))
+    else {
+        return;
+    };
+    let buffer = buffer.fork_local_history();
+
+    cx.background_executor()
+        .spawn(async move {
+            let history = buffer.serialize_history(MAX_PERSISTED_UNDO_HISTORY_LEN);
+            if history.is_empty() {
+                return DB.delete_undo_history(path).await;
+            }
+
+            let fingerprint = serialize_fingerprint(buffer.as_rope().fingerprint());
+            let history = serde_json::to_string(&history)?;
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+            DB.save_undo_history(path, fingerprint, history, now.as_secs() as i64)
+                .await?;
+            let saved_before = now.saturating_sub(PERSISTED_UNDO_HISTORY_MAX_AGE);
+            DB.prune_undo_histories(saved_before.as_secs() as i64, MAX_PERSISTED_UNDO_HISTORIES)
+                .await
+        })
+        .detach_and_log_err(cx);
+}
+
+/// Restores the undo history persisted for the buffer's file, as long as the buffer has no
+/// history of its own and its contents haven't changed since the history was saved.
+fn restore_undo_history(buffer: Model<Buffer>, cx: &mut ViewContext<Editor>) {
+    let Some(path) = buffer
+        .read(cx)
+        .file()
+        .and_then(|file| file.as_local())
+        .map(|file| file.abs_path(cx))
+    else {
+        return;
+    };
+    if !can_restore_undo_history(buffer.read(cx)) {
+        return;
+    }
+
+    let history = cx.background_executor().spawn({
+        let path = path.clone();
+        async move { DB.get_undo_history(path) }
+    });
+    cx.spawn(|_, mut cx| async move {
+        let Some((fingerprint, history)) = history.await? else {
+            return Ok(());
+        };
+        let file_changed = buffer.update(&mut cx, |buffer, _| {
+            !buffer.is_dirty()
+                && serialize_fingerprint(buffer.as_rope().fingerprint()) != fingerprint
+        })?;
+        if file_changed {
+            // The history no longer matches the file's contents, so it can never be restored.
+            return DB.delete_undo_history(path).await;
+        }
+
+        let history = serde_json::from_str::<SerializedHistory>(&history)?;
+        buffer.update(&mut cx, |buffer, cx| {
+            if can_restore_undo_history(buffer)
+                && serialize_fingerprint(buffer.as_rope().fingerprint()) == fingerprint
+            {
+                buffer.restore_history(&history, cx)
+            } else {
+                Ok(())
+            }
+        })?
+    })
+    .detach_and_log_err(cx);
+}
+
+fn can_restore_undo_history(buffer: &Buffer) -> bool {
+    !buffer.is_dirty() && buffer.peek_undo_stack().is_none() && buffer.peek_redo_stack().is_none()
+}
+
 fn serialize_excerpt(
     buffer_id: BufferId,
     id: &ExcerptId,
@@ -864,10 +953,13 @@ impl Item for Editor {
 
         if let Some(buffer) = self.buffer().read(cx).as_singleton() {
             serialize(buffer.clone(), workspace_id, item_id, cx);
+            if EditorSettings::get_global(cx).persistent_undo {
+                restore_undo_history(buffer.clone(), cx);
+            }
 
-            cx.subscribe(&buffer, |this, buffer, event, cx| {
-                if let Some((_, workspace_id)) = this.workspace.as_ref() {
-                    if let language::Event::FileHandleChanged = event {
+            cx.subscribe(&buffer, |this, buffer, event, cx| match event {
+                language::Event::FileHandleChanged => {
+                    if let Some((_, workspace_id)) = this.workspace.as_ref() {
                         serialize(
                             buffer,
                             *workspace_id,
@@ -876,6 +968,12 @@ impl Item for Editor {
                         );
                     }
                 }
+                language::Event::Saved => {
+                    if EditorSettings::get_global(cx).persistent_undo {
+                        save_undo_history(&buffer, cx);
+                    }
+                }
+                _ => {}
             })
             .detach();
         }
@@ -940,16 +1038,13 @@ impl Item for Editor {
                 path: path.into(),
             };
 
-            Ok(project.open_path(project_path, cx))
+            Ok(project.open_buffer(project_path, cx))
         });
 
         project_item
             .map(|project_item| {
                 cx.spawn(|pane, mut cx| async move {
-                    let (_, project_item) = project_item.await?;
-                    let buffer = project_item
-                        .downcast::<Buffer>()
-                        .map_err(|_| anyhow!("Project item at stored path was not a buffer"))?;
+                    let buffer = project_item.await?;
                     Ok(pane.update(&mut cx, |_, cx| {
                         cx.new_view(|cx| {
                             let mut editor = Editor::for_buffer(buffer, Some(project), cx);
@@ -0,0 +1,53 @@
+use crate::Editor;
+use gpui::EventEmitter;
+use project::project_settings::ProjectSettings;
+use settings::Settings;
+use ui::prelude::*;
+use workspace::{item::ItemHandle, ToolbarItemEvent, ToolbarItemLocation, ToolbarItemView};
+
+/// Explains which features are turned off for editors showing a file that was opened
+/// in large file mode.
+pub struct LargeFileBanner;
+
+impl LargeFileBanner {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Render for LargeFileBanner {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let threshold_mb = ProjectSettings::get_global(cx).large_file_threshold() / 1024 / 1024;
+        h_flex()
+            .gap_2()
+            .child(Icon::new(IconName::ExclamationTriangle).color(Color::Warning))
+            .child(
+                Label::new(format!(
+                    "This file is larger than {threshold_mb} MB. Syntax highlighting, \
+                     language servers, git diffs and soft wrap are turned off, \
+                     but search and navigation still work."
+                ))
+                .size(LabelSize::Small)
+                .color(Color::Muted),
+            )
+    }
+}
+
+impl EventEmitter<ToolbarItemEvent> for LargeFileBanner {}
+
+impl ToolbarItemView for LargeFileBanner {
+    fn set_active_pane_item(
+        &mut self,
+        active_pane_item: Option<&dyn ItemHandle>,
+        cx: &mut ViewContext<Self>,
+    ) -> ToolbarItemLocation {
+        let is_large_file = active_pane_item
+            .and_then(|item| item.downcast::<Editor>())
+            .map_or(false, |editor| editor.read(cx).is_large_file(cx));
+        if is_large_file {
+            ToolbarItemLocation::Secondary
+        } else {
+            ToolbarItemLocation::Hidden
+        }
+    }
+}
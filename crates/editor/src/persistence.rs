@@ -15,6 +15,12 @@ define_connection!(
     //   scroll_vertical_offset: f32,
     //   scroll_horizontal_offset: f32,
     // )
+    // undo_histories(
+    //   path: PathBuf,
+    //   fingerprint: String,
+    //   history: String,
+    //   saved_at: i64, // Seconds since the Unix epoch
+    // )
     pub static ref DB: EditorDb<WorkspaceDb> =
         &[sql! (
             CREATE TABLE editors(
@@ -31,6 +37,16 @@ define_connection!(
             ALTER TABLE editors ADD COLUMN scroll_top_row INTEGER NOT NULL DEFAULT 0;
             ALTER TABLE editors ADD COLUMN scroll_horizontal_offset REAL NOT NULL DEFAULT 0;
             ALTER TABLE editors ADD COLUMN scroll_vertical_offset REAL NOT NULL DEFAULT 0;
+        ),
+        sql! (
+            CREATE TABLE undo_histories(
+                path BLOB NOT NULL PRIMARY KEY,
+                fingerprint TEXT NOT NULL,
+                history TEXT NOT NULL
+            ) STRICT;
+        ),
+        sql! (
+            ALTER TABLE undo_histories ADD COLUMN saved_at INTEGER NOT NULL DEFAULT 0;
         )];
 );
 
@@ -80,4 +96,53 @@ impl EditorDb {
             WHERE item_id = ?1 AND workspace_id = ?2
         }
     }
+
+    // Returns the fingerprint of the file's contents when the history was saved, and the
+    // serialized history itself
+    query! {
+        pub fn get_undo_history(path: PathBuf) -> Result<Option<(String, String)>> {
+            SELECT fingerprint, history
+            FROM undo_histories
+            WHERE path = ?
+        }
+    }
+
+    query! {
+        pub async fn save_undo_history(
+            path: PathBuf,
+            fingerprint: String,
+            history: String,
+            saved_at: i64
+        ) -> Result<()> {
+            INSERT INTO undo_histories
+                (path, fingerprint, history, saved_at)
+            VALUES
+                (?1, ?2, ?3, ?4)
+            ON CONFLICT DO UPDATE SET
+                fingerprint = ?2,
+                history = ?3,
+                saved_at = ?4
+        }
+    }
+
+    query! {
+        pub async fn delete_undo_history(path: PathBuf) -> Result<()> {
+            DELETE FROM undo_histories
+            WHERE path = ?
+        }
+    }
+
+    // Deletes the histories saved before `saved_before`, and all but the `max_count`
+    // most recently saved ones
+    query! {
+        pub async fn prune_undo_histories(saved_before: i64, max_count: usize) -> Result<()> {
+            DELETE FROM undo_histories
+            WHERE saved_at < ?1
+                OR path NOT IN (
+                    SELECT path FROM undo_histories
+                    ORDER BY saved_at DESC
+                    LIMIT ?2
+                )
+        }
+    }
 }
@@ -1,3 +1,4 @@
+pub mod local_history;
 pub mod repository;
 
 use anyhow::{anyhow, Result};
@@ -13,6 +14,7 @@ use notify::{Config, Watcher};
 
 use futures::{future::BoxFuture, Stream, StreamExt};
 use git2::Repository as LibGitRepository;
+use local_history::{LocalHistory, SnapshotReason};
 use parking_lot::Mutex;
 use repository::GitRepository;
 use rope::Rope;
@@ -21,6 +23,7 @@ use std::io::Write;
 use std::sync::Arc;
 use std::{
     io,
+    ops::Range,
     os::unix::fs::MetadataExt,
     path::{Component, Path, PathBuf},
     pin::Pin,
@@ -47,8 +50,24 @@ pub trait Fs: Send + Sync {
     async fn remove_file(&self, path: &Path, options: RemoveOptions) -> Result<()>;
     async fn open_sync(&self, path: &Path) -> Result<Box<dyn io::Read>>;
     async fn load(&self, path: &Path) -> Result<String>;
+    /// Loads a text file into a rope a chunk at a time, normalizing its line endings.
+    async fn load_rope(&self, path: &Path) -> Result<(Rope, LineEnding)>;
+    /// Loads a text file a chunk at a time like [`Fs::load_rope`], yielding each chunk as
+    /// soon as it's read, so that the start of the file can be shown before the rest.
+    async fn load_chunks(&self, path: &Path) -> Result<TextChunkStream>;
+    /// Loads the bytes of a file from the given offset to its end, to follow a file that
+    /// is being appended to.
+    async fn load_tail(&self, path: &Path, offset: u64) -> Result<Vec<u8>>;
+    /// Loads the bytes of a file in the given range, which is clipped to the end of the file.
+    async fn load_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>>;
     async fn atomic_write(&self, path: PathBuf, text: String) -> Result<()>;
     async fn save(&self, path: &Path, text: &Rope, line_ending: LineEnding) -> Result<()>;
+    /// Writes the given bytes to a file as they are, creating the file's parent directories
+    /// if needed.
+    async fn write(&self, path: &Path, content: &[u8]) -> Result<()>;
+    /// Overwrites bytes of an existing file at the given offsets, replacing the file
+    /// atomically so that it's never left partially written.
+    async fn patch_bytes(&self, path: &Path, patches: Vec<(u64, Vec<u8>)>) -> Result<()>;
     async fn canonicalize(&self, path: &Path) -> Result<PathBuf>;
     async fn is_file(&self, path: &Path) -> bool;
     async fn metadata(&self, path: &Path) -> Result<Option<Metadata>>;
@@ -65,6 +84,7 @@ pub trait Fs: Send + Sync {
     ) -> Pin<Box<dyn Send + Stream<Item = Vec<Event>>>>;
 
     fn open_repo(&self, abs_dot_git: &Path) -> Option<Arc<Mutex<dyn GitRepository>>>;
+    fn local_history(&self) -> Option<Arc<LocalHistory>>;
     fn is_fake(&self) -> bool;
     #[cfg(any(test, feature = "test-support"))]
     fn as_fake(&self) -> &FakeFs;
@@ -98,11 +118,31 @@ pub struct RemoveOptions {
 pub struct Metadata {
     pub inode: u64,
     pub mtime: SystemTime,
+    pub len: u64,
     pub is_symlink: bool,
     pub is_dir: bool,
 }
 
-pub struct RealFs;
+#[derive(Default)]
+pub struct RealFs {
+    local_history: Option<Arc<LocalHistory>>,
+}
+
+impl RealFs {
+    /// Creates a file system that snapshots files into the given local history whenever
+    /// they are saved, overwritten or removed.
+    pub fn with_local_history(local_history: LocalHistory) -> Self {
+        Self {
+            local_history: Some(Arc::new(local_history)),
+        }
+    }
+
+    async fn record_snapshot(&self, path: &Path, reason: SnapshotReason) {
+        if let Some(local_history) = &self.local_history {
+            local_history.record_existing(path, reason).await.log_err();
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl Fs for RealFs {
@@ -144,11 +184,18 @@ impl Fs for RealFs {
             }
         }
 
+        self.record_snapshot(target, SnapshotReason::Replaced).await;
         smol::fs::rename(source, target).await?;
         Ok(())
     }
 
     async fn remove_dir(&self, path: &Path, options: RemoveOptions) -> Result<()> {
+        if let Some(local_history) = self.local_history.as_ref().filter(|_| options.recursive) {
+            local_history
+                .record_existing_dir(path, SnapshotReason::Removed)
+                .await
+                .log_err();
+        }
         let result = if options.recursive {
             smol::fs::remove_dir_all(path).await
         } else {
@@ -164,6 +211,7 @@ impl Fs for RealFs {
     }
 
     async fn remove_file(&self, path: &Path, options: RemoveOptions) -> Result<()> {
+        self.record_snapshot(path, SnapshotReason::Removed).await;
         match smol::fs::remove_file(path).await {
             Ok(()) => Ok(()),
             Err(err) if err.kind() == io::ErrorKind::NotFound && options.ignore_if_not_exists => {
@@ -184,6 +232,51 @@ impl Fs for RealFs {
         Ok(text)
     }
 
+    async fn load_rope(&self, path: &Path) -> Result<(Rope, LineEnding)> {
+        let path = path.to_path_buf();
+        smol::unblock(move || read_rope(std::fs::File::open(path)?)).await
+    }
+
+    async fn load_chunks(&self, path: &Path) -> Result<TextChunkStream> {
+        let path = path.to_path_buf();
+        let file = smol::unblock(move || std::fs::File::open(path)).await?;
+        let chunks = futures::stream::unfold(TextChunks::new(file), |mut chunks| async move {
+            let (chunk, chunks) = smol::unblock(move || (chunks.next(), chunks)).await;
+            let line_ending = chunks.line_ending();
+            Some((chunk?.map(|text| (text, line_ending)), chunks))
+        });
+        Ok(Box::pin(chunks))
+    }
+
+    async fn load_tail(&self, path: &Path, offset: u64) -> Result<Vec<u8>> {
+        use std::io::{Read as _, Seek as _};
+
+        let path = path.to_path_buf();
+        smol::unblock(move || {
+            let mut file = std::fs::File::open(path)?;
+            file.seek(io::SeekFrom::Start(offset))?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+        .await
+    }
+
+    async fn load_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>> {
+        use std::io::{Read as _, Seek as _};
+
+        let path = path.to_path_buf();
+        smol::unblock(move || {
+            let mut file = std::fs::File::open(path)?;
+            file.seek(io::SeekFrom::Start(range.start))?;
+            let mut bytes = Vec::new();
+            file.take(range.end.saturating_sub(range.start))
+                .read_to_end(&mut bytes)?;
+            Ok(bytes)
+        })
+        .await
+    }
+
     async fn atomic_write(&self, path: PathBuf, data: String) -> Result<()> {
         smol::unblock(move || {
             let mut tmp_file = NamedTempFile::new()?;
@@ -201,12 +294,55 @@ impl Fs for RealFs {
         if let Some(path) = path.parent() {
             self.create_dir(path).await?;
         }
+        // Keep whatever is on disk if it was written by someone else since the last save.
+        self.record_snapshot(path, SnapshotReason::Replaced).await;
         let file = smol::fs::File::create(path).await?;
         let mut writer = smol::io::BufWriter::with_capacity(buffer_size, file);
         for chunk in chunks(text, line_ending) {
             writer.write_all(chunk.as_bytes()).await?;
         }
         writer.flush().await?;
+        self.record_snapshot(path, SnapshotReason::Saved).await;
+        Ok(())
+    }
+
+    async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        if let Some(path) = path.parent() {
+            self.create_dir(path).await?;
+        }
+        self.record_snapshot(path, SnapshotReason::Replaced).await;
+        smol::fs::write(path, content).await?;
+        self.record_snapshot(path, SnapshotReason::Saved).await;
+        Ok(())
+    }
+
+    async fn patch_bytes(&self, path: &Path, patches: Vec<(u64, Vec<u8>)>) -> Result<()> {
+        use std::io::Seek as _;
+
+        self.record_snapshot(path, SnapshotReason::Replaced).await;
+        let target = path.to_path_buf();
+        smol::unblock(move || {
+            // Write the patched copy next to the original, so that the rename stays on
+            // one file system and readers see either the old or the new contents.
+            let parent = target
+                .parent()
+                .ok_or_else(|| anyhow!("no parent directory for {target:?}"))?;
+            let mut original = std::fs::File::open(&target)?;
+            let mut tmp_file = NamedTempFile::new_in(parent)?;
+            io::copy(&mut original, tmp_file.as_file_mut())?;
+            for (offset, bytes) in patches {
+                tmp_file.seek(io::SeekFrom::Start(offset))?;
+                tmp_file.write_all(&bytes)?;
+            }
+            tmp_file.as_file().sync_all()?;
+            tmp_file
+                .as_file()
+                .set_permissions(original.metadata()?.permissions())?;
+            tmp_file.persist(&target)?;
+            Ok::<(), anyhow::Error>(())
+        })
+        .await?;
+        self.record_snapshot(path, SnapshotReason::Saved).await;
         Ok(())
     }
 
@@ -241,6 +377,7 @@ impl Fs for RealFs {
         Ok(Some(Metadata {
             inode: metadata.ino(),
             mtime: metadata.modified().unwrap(),
+            len: metadata.len(),
             is_symlink,
             is_dir: metadata.file_type().is_dir(),
         }))
@@ -316,6 +453,10 @@ impl Fs for RealFs {
             })
     }
 
+    fn local_history(&self) -> Option<Arc<LocalHistory>> {
+        self.local_history.clone()
+    }
+
     fn is_fake(&self) -> bool {
         false
     }
@@ -344,6 +485,7 @@ pub struct FakeFs {
     // Use an unfair lock to ensure tests are deterministic.
     state: Mutex<FakeFsState>,
     executor: gpui::BackgroundExecutor,
+    local_history: Mutex<Option<Arc<LocalHistory>>>,
 }
 
 #[cfg(any(test, feature = "test-support"))]
@@ -364,7 +506,7 @@ enum FakeFsEntry {
     File {
         inode: u64,
         mtime: SystemTime,
-        content: String,
+        content: Vec<u8>,
     },
     Dir {
         inode: u64,
@@ -507,13 +649,52 @@ impl FakeFs {
                 read_dir_call_count: 0,
                 metadata_call_count: 0,
             }),
+            local_history: Mutex::new(None),
         })
     }
 
+    /// Snapshots files into the given local history whenever they are saved or removed,
+    /// like [`RealFs::with_local_history`]. The history itself is kept on the real file
+    /// system.
+    pub fn set_local_history(&self, local_history: LocalHistory) {
+        *self.local_history.lock() = Some(Arc::new(local_history));
+    }
+
+    async fn record_snapshot(&self, path: &Path, reason: SnapshotReason) {
+        let Some(local_history) = self.local_history.lock().clone() else {
+            return;
+        };
+        let content = {
+            let state = self.state.lock();
+            let Ok(entry) = state.read_path(path) else {
+                return;
+            };
+            let entry = entry.lock();
+            let Ok(content) = entry.file_content(path) else {
+                return;
+            };
+            content.clone()
+        };
+        local_history.record(path, content, reason).await.log_err();
+    }
+
     pub async fn insert_file(&self, path: impl AsRef<Path>, content: String) {
         self.write_file_internal(path, content).unwrap()
     }
 
+    pub async fn insert_file_bytes(&self, path: impl AsRef<Path>, content: Vec<u8>) {
+        self.write_file_internal(path, content).unwrap()
+    }
+
+    pub async fn load_bytes(&self, path: &Path) -> Result<Vec<u8>> {
+        let path = normalize_path(path);
+        self.simulate_random_delay().await;
+        let state = self.state.lock();
+        let entry = state.read_path(&path)?;
+        let entry = entry.lock();
+        entry.file_content(&path).cloned()
+    }
+
     pub async fn insert_symlink(&self, path: impl AsRef<Path>, target: PathBuf) {
         let mut state = self.state.lock();
         let path = path.as_ref();
@@ -533,7 +714,11 @@ impl FakeFs {
         state.emit_event(&[path]);
     }
 
-    pub fn write_file_internal(&self, path: impl AsRef<Path>, content: String) -> Result<()> {
+    pub fn write_file_internal(
+        &self,
+        path: impl AsRef<Path>,
+        content: impl Into<Vec<u8>>,
+    ) -> Result<()> {
         let mut state = self.state.lock();
         let path = path.as_ref();
         let inode = state.next_inode;
@@ -543,7 +728,7 @@ impl FakeFs {
         let file = Arc::new(Mutex::new(FakeFsEntry::File {
             inode,
             mtime,
-            content,
+            content: content.into(),
         }));
         state.write_path(path, move |entry| {
             match entry {
@@ -767,7 +952,7 @@ impl FakeFsEntry {
         matches!(self, Self::Symlink { .. })
     }
 
-    fn file_content(&self, path: &Path) -> Result<&String> {
+    fn file_content(&self, path: &Path) -> Result<&Vec<u8>> {
         if let Self::File { content, .. } = self {
             Ok(content)
         } else {
@@ -775,7 +960,7 @@ impl FakeFsEntry {
         }
     }
 
-    fn set_file_content(&mut self, path: &Path, new_content: String) -> Result<()> {
+    fn set_file_content(&mut self, path: &Path, new_content: Vec<u8>) -> Result<()> {
         if let Self::File { content, mtime, .. } = self {
             *mtime = SystemTime::now();
             *content = new_content;
@@ -844,7 +1029,7 @@ impl Fs for FakeFs {
         let file = Arc::new(Mutex::new(FakeFsEntry::File {
             inode,
             mtime,
-            content: String::new(),
+            content: Vec::new(),
         }));
         state.write_path(path, |entry| {
             match entry {
@@ -935,7 +1120,7 @@ impl Fs for FakeFs {
                 e.insert(Arc::new(Mutex::new(FakeFsEntry::File {
                     inode,
                     mtime,
-                    content: String::new(),
+                    content: Vec::new(),
                 })))
                 .clone(),
             )),
@@ -988,6 +1173,7 @@ impl Fs for FakeFs {
         self.simulate_random_delay().await;
 
         let path = normalize_path(path);
+        self.record_snapshot(&path, SnapshotReason::Removed).await;
         let parent_path = path
             .parent()
             .ok_or_else(|| anyhow!("cannot remove the root"))?;
@@ -1014,17 +1200,41 @@ impl Fs for FakeFs {
     }
 
     async fn open_sync(&self, path: &Path) -> Result<Box<dyn io::Read>> {
-        let text = self.load(path).await?;
-        Ok(Box::new(io::Cursor::new(text)))
+        let bytes = self.load_bytes(path).await?;
+        Ok(Box::new(io::Cursor::new(bytes)))
     }
 
     async fn load(&self, path: &Path) -> Result<String> {
-        let path = normalize_path(path);
-        self.simulate_random_delay().await;
-        let state = self.state.lock();
-        let entry = state.read_path(&path)?;
-        let entry = entry.lock();
-        entry.file_content(&path).cloned()
+        let bytes = self.load_bytes(path).await?;
+        Ok(String::from_utf8(bytes)?)
+    }
+
+    async fn load_rope(&self, path: &Path) -> Result<(Rope, LineEnding)> {
+        let bytes = self.load_bytes(path).await?;
+        read_rope(io::Cursor::new(bytes))
+    }
+
+    async fn load_chunks(&self, path: &Path) -> Result<TextChunkStream> {
+        let bytes = self.load_bytes(path).await?;
+        let mut chunks = TextChunks::new(io::Cursor::new(bytes));
+        let mut loaded = Vec::new();
+        while let Some(chunk) = chunks.next() {
+            loaded.push(chunk.map(|text| (text, chunks.line_ending())));
+        }
+        Ok(Box::pin(futures::stream::iter(loaded)))
+    }
+
+    async fn load_tail(&self, path: &Path, offset: u64) -> Result<Vec<u8>> {
+        let mut bytes = self.load_bytes(path).await?;
+        bytes.drain(..bytes.len().min(offset as usize));
+        Ok(bytes)
+    }
+
+    async fn load_range(&self, path: &Path, range: Range<u64>) -> Result<Vec<u8>> {
+        let bytes = self.load_bytes(path).await?;
+        let end = bytes.len().min(range.end as usize);
+        let start = end.min(range.start as usize);
+        Ok(bytes[start..end].to_vec())
     }
 
     async fn atomic_write(&self, path: PathBuf, data: String) -> Result<()> {
@@ -1038,10 +1248,39 @@ impl Fs for FakeFs {
     async fn save(&self, path: &Path, text: &Rope, line_ending: LineEnding) -> Result<()> {
         self.simulate_random_delay().await;
         let path = normalize_path(path);
-        let content = chunks(text, line_ending).collect();
+        let content = chunks(text, line_ending).collect::<String>();
         if let Some(path) = path.parent() {
             self.create_dir(path).await?;
         }
+        self.record_snapshot(&path, SnapshotReason::Replaced).await;
+        self.write_file_internal(&path, content)?;
+        self.record_snapshot(&path, SnapshotReason::Saved).await;
+        Ok(())
+    }
+
+    async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+        self.simulate_random_delay().await;
+        let path = normalize_path(path);
+        if let Some(path) = path.parent() {
+            self.create_dir(path).await?;
+        }
+        self.record_snapshot(&path, SnapshotReason::Replaced).await;
+        self.write_file_internal(&path, content)?;
+        self.record_snapshot(&path, SnapshotReason::Saved).await;
+        Ok(())
+    }
+
+    async fn patch_bytes(&self, path: &Path, patches: Vec<(u64, Vec<u8>)>) -> Result<()> {
+        let path = normalize_path(path);
+        let mut content = self.load_bytes(&path).await?;
+        for (offset, bytes) in patches {
+            let offset = offset as usize;
+            let end = offset + bytes.len();
+            if content.len() < end {
+                content.resize(end, 0);
+            }
+            content[offset..end].copy_from_slice(&bytes);
+        }
         self.write_file_internal(path, content)?;
         Ok(())
     }
@@ -1085,15 +1324,21 @@ impl Fs for FakeFs {
 
             let entry = entry.lock();
             Ok(Some(match &*entry {
-                FakeFsEntry::File { inode, mtime, .. } => Metadata {
+                FakeFsEntry::File {
+                    inode,
+                    mtime,
+                    content,
+                } => Metadata {
                     inode: *inode,
                     mtime: *mtime,
+                    len: content.len() as u64,
                     is_dir: false,
                     is_symlink,
                 },
                 FakeFsEntry::Dir { inode, mtime, .. } => Metadata {
                     inode: *inode,
                     mtime: *mtime,
+                    len: 0,
                     is_dir: true,
                     is_symlink,
                 },
@@ -1172,6 +1417,10 @@ impl Fs for FakeFs {
         }
     }
 
+    fn local_history(&self) -> Option<Arc<LocalHistory>> {
+        self.local_history.lock().clone()
+    }
+
     fn is_fake(&self) -> bool {
         true
     }
@@ -1197,6 +1446,87 @@ fn chunks(rope: &Rope, line_ending: LineEnding) -> impl Iterator<Item = &str> {
     })
 }
 
+/// How many bytes [`TextChunks`] reads from disk at a time.
+const LOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The text of a file that's being loaded a chunk at a time, along with the line ending
+/// detected so far.
+pub type TextChunkStream = Pin<Box<dyn Send + Stream<Item = Result<(String, LineEnding)>>>>;
+
+/// Reads text a chunk at a time without ever holding the whole file in one string,
+/// normalizing its line endings. Bytes of a character or a `\r\n` pair that straddle
+/// two chunks are carried over to the next one.
+struct TextChunks<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    pending: Vec<u8>,
+    line_ending: Option<LineEnding>,
+    done: bool,
+}
+
+impl<R: io::Read> TextChunks<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: vec![0; LOAD_CHUNK_SIZE],
+            pending: Vec::new(),
+            line_ending: None,
+            done: false,
+        }
+    }
+
+    fn line_ending(&self) -> LineEnding {
+        self.line_ending.unwrap_or_default()
+    }
+
+    fn read_chunk(&mut self) -> Result<String> {
+        let read_len = self.reader.read(&mut self.buffer)?;
+        let is_eof = read_len == 0;
+        self.done = is_eof;
+        self.pending.extend_from_slice(&self.buffer[..read_len]);
+
+        let mut text_len = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            Err(error) if error.error_len().is_none() && !is_eof => error.valid_up_to(),
+            Err(error) => return Err(error.into()),
+        };
+        if !is_eof && self.pending[..text_len].ends_with(b"\r") {
+            text_len -= 1;
+        }
+        let mut text = String::from_utf8(self.pending.drain(..text_len).collect())?;
+        if self.line_ending.is_none() && text.contains('\n') {
+            self.line_ending = Some(LineEnding::detect(&text));
+        }
+        LineEnding::normalize(&mut text);
+        Ok(text)
+    }
+}
+
+impl<R: io::Read> Iterator for TextChunks<R> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let chunk = self.read_chunk();
+        if chunk.is_err() {
+            self.done = true;
+        }
+        Some(chunk)
+    }
+}
+
+/// Reads text into a rope a chunk at a time, see [`TextChunks`].
+fn read_rope(reader: impl io::Read) -> Result<(Rope, LineEnding)> {
+    let mut chunks = TextChunks::new(reader);
+    let mut rope = Rope::new();
+    for chunk in &mut chunks {
+        rope.push(&chunk?);
+    }
+    Ok((rope, chunks.line_ending()))
+}
+
 pub fn normalize_path(path: &Path) -> PathBuf {
     let mut components = path.components().peekable();
     let mut ret = if let Some(c @ Component::Prefix(..)) = components.peek().cloned() {
@@ -1330,4 +1660,30 @@ mod tests {
             "D",
         );
     }
+
+    #[test]
+    fn test_read_rope_across_chunk_boundaries() {
+        // Yields a few bytes per read, splitting characters and line endings.
+        struct Trickle<'a>(&'a [u8]);
+
+        impl io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = self.0.len().min(buf.len()).min(3);
+                buf[..len].copy_from_slice(&self.0[..len]);
+                self.0 = &self.0[len..];
+                Ok(len)
+            }
+        }
+
+        let (rope, line_ending) = read_rope(Trickle("αβ\r\nγδε\r\nλ\r".as_bytes())).unwrap();
+        assert_eq!(rope.to_string(), "αβ\nγδε\nλ\n");
+        assert_eq!(line_ending, LineEnding::Windows);
+
+        let (rope, line_ending) = read_rope(Trickle(b"one\ntwo")).unwrap();
+        assert_eq!(rope.to_string(), "one\ntwo");
+        assert_eq!(line_ending, LineEnding::Unix);
+
+        assert!(read_rope(Trickle(b"ok\xff")).is_err());
+        assert!(read_rope(Trickle("ok\u{3b1}".as_bytes().split_last().unwrap().1)).is_err());
+    }
 }
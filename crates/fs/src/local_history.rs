@@ -0,0 +1,470 @@
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use std::{
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The name of the file storing the original path of the file whose snapshots are
+/// kept in the same directory.
+const PATH_FILE_NAME: &str = "path";
+
+/// Bounded snapshots of file contents, recorded whenever a file is saved, replaced or
+/// removed through [`RealFs`](crate::RealFs), so that earlier versions of a file can be
+/// recovered independently of version control.
+///
+/// Each file gets its own directory of snapshots, named after a hash of the file's path.
+pub struct LocalHistory {
+    dir: PathBuf,
+    limits: Mutex<LocalHistoryLimits>,
+}
+
+/// How much the local history is allowed to keep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LocalHistoryLimits {
+    /// The number of snapshots kept for each file. Zero turns the local history off.
+    pub max_snapshots_per_file: usize,
+    /// Files larger than this many bytes are not snapshotted.
+    pub max_file_len: u64,
+    /// Removed directories containing more files than this are not snapshotted.
+    pub max_files_per_removed_dir: usize,
+    /// Removed directories whose files add up to more bytes than this are not snapshotted.
+    pub max_removed_dir_len: u64,
+}
+
+impl Default for LocalHistoryLimits {
+    fn default() -> Self {
+        Self {
+            max_snapshots_per_file: 50,
+            max_file_len: 2 * 1024 * 1024,
+            max_files_per_removed_dir: 1000,
+            max_removed_dir_len: 64 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotReason {
+    /// The file's contents right after it was saved.
+    Saved,
+    /// Contents that were about to be overwritten without having been snapshotted before,
+    /// for example because they were written by another program.
+    Replaced,
+    /// The file's contents right before it was removed.
+    Removed,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalHistorySnapshot {
+    /// The location of the snapshot's contents inside the history directory.
+    pub snapshot_path: PathBuf,
+    pub timestamp: SystemTime,
+    pub reason: SnapshotReason,
+}
+
+impl SnapshotReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SnapshotReason::Saved => "saved",
+            SnapshotReason::Replaced => "replaced",
+            SnapshotReason::Removed => "removed",
+        }
+    }
+
+    fn from_str(reason: &str) -> Option<Self> {
+        match reason {
+            "saved" => Some(SnapshotReason::Saved),
+            "replaced" => Some(SnapshotReason::Replaced),
+            "removed" => Some(SnapshotReason::Removed),
+            _ => None,
+        }
+    }
+}
+
+impl LocalHistory {
+    pub fn new(dir: impl Into<PathBuf>, limits: LocalHistoryLimits) -> Self {
+        Self {
+            dir: dir.into(),
+            limits: Mutex::new(limits),
+        }
+    }
+
+    pub fn limits(&self) -> LocalHistoryLimits {
+        *self.limits.lock()
+    }
+
+    /// Changes the limits used for the snapshots recorded from now on.
+    pub fn set_limits(&self, limits: LocalHistoryLimits) {
+        *self.limits.lock() = limits;
+    }
+
+    /// Records a snapshot of the given contents for the file at `abs_path`, unless they are
+    /// identical to its most recent snapshot.
+    pub async fn record(
+        &self,
+        abs_path: &Path,
+        content: Vec<u8>,
+        reason: SnapshotReason,
+    ) -> Result<()> {
+        let limits = self.limits();
+        if content.len() as u64 > limits.max_file_len || limits.max_snapshots_per_file == 0 {
+            return Ok(());
+        }
+
+        let dir = self.dir_for_path(abs_path);
+        let abs_path = abs_path.to_path_buf();
+        let max_snapshots = limits.max_snapshots_per_file;
+        smol::unblock(move || record_snapshot(&dir, &abs_path, &content, reason, max_snapshots))
+            .await
+    }
+
+    /// Records a snapshot of the file currently stored at `abs_path`, if there is one and
+    /// it is not too large.
+    pub async fn record_existing(&self, abs_path: &Path, reason: SnapshotReason) -> Result<()> {
+        match smol::fs::metadata(abs_path).await {
+            Ok(metadata) if metadata.is_file() && metadata.len() <= self.limits().max_file_len => {}
+            _ => return Ok(()),
+        }
+        let content = smol::fs::read(abs_path).await?;
+        self.record(abs_path, content, reason).await
+    }
+
+    /// Records a snapshot of every file in the directory at `abs_path` that isn't ignored by
+    /// git. Nothing is recorded when the directory holds more files or bytes than the limits
+    /// allow, since a partial snapshot of it wouldn't be much use.
+    pub async fn record_existing_dir(&self, abs_path: &Path, reason: SnapshotReason) -> Result<()> {
+        let limits = self.limits();
+        if limits.max_snapshots_per_file == 0 {
+            return Ok(());
+        }
+
+        let root = abs_path.to_path_buf();
+        let files = smol::unblock(move || {
+            let mut files = Vec::new();
+            let mut total_len = 0;
+            let walk = ignore::WalkBuilder::new(&root)
+                .hidden(false)
+                .require_git(false)
+                .filter_entry(|entry| entry.file_name() != ".git")
+                .build();
+            for entry in walk {
+                let entry = entry?;
+                if !entry
+                    .file_type()
+                    .map_or(false, |file_type| file_type.is_file())
+                {
+                    continue;
+                }
+                let len = entry.metadata()?.len();
+                if len > limits.max_file_len {
+                    continue;
+                }
+                total_len += len;
+                files.push(entry.into_path());
+                if files.len() > limits.max_files_per_removed_dir
+                    || total_len > limits.max_removed_dir_len
+                {
+                    log::info!("not recording {root:?} in the local history, it is too large");
+                    return Ok(Vec::new());
+                }
+            }
+            anyhow::Ok(files)
+        })
+        .await?;
+
+        for file in files {
+            self.record_existing(&file, reason).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the snapshots recorded for the file at `abs_path`, newest first.
+    pub async fn snapshots(&self, abs_path: &Path) -> Result<Vec<LocalHistorySnapshot>> {
+        let dir = self.dir_for_path(abs_path);
+        smol::unblock(move || list_snapshots(&dir)).await
+    }
+
+    /// Returns the paths of all files that have snapshots, including files that have since
+    /// been removed.
+    pub async fn recorded_paths(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.dir.clone();
+        smol::unblock(move || {
+            let entries = match std::fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(error) => return Err(error.into()),
+            };
+            let mut paths = Vec::new();
+            for entry in entries {
+                if let Ok(path) = std::fs::read(entry?.path().join(PATH_FILE_NAME)) {
+                    paths.push(PathBuf::from(OsStr::from_bytes(&path)));
+                }
+            }
+            paths.sort();
+            Ok(paths)
+        })
+        .await
+    }
+
+    /// Loads the contents of a snapshot, which needn't be valid UTF-8.
+    pub async fn load(&self, snapshot: &LocalHistorySnapshot) -> Result<Vec<u8>> {
+        if !snapshot.snapshot_path.starts_with(&self.dir) {
+            return Err(anyhow!(
+                "snapshot {:?} is not part of the local history",
+                snapshot.snapshot_path
+            ));
+        }
+        Ok(smol::fs::read(&snapshot.snapshot_path).await?)
+    }
+
+    fn dir_for_path(&self, abs_path: &Path) -> PathBuf {
+        // FNV-1a, which is stable across releases unlike the standard library's hasher.
+        let mut hash: u64 = 0xcbf29ce484222325;
+        for byte in abs_path.as_os_str().as_bytes() {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+        self.dir.join(format!("{hash:016x}"))
+    }
+}
+
+fn record_snapshot(
+    dir: &Path,
+    abs_path: &Path,
+    content: &[u8],
+    reason: SnapshotReason,
+    max_snapshots: usize,
+) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(PATH_FILE_NAME), abs_path.as_os_str().as_bytes())?;
+
+    let snapshots = list_snapshots(dir)?;
+    let mut timestamp = SystemTime::now();
+    if let Some(latest) = snapshots.first() {
+        if std::fs::read(&latest.snapshot_path)? == content {
+            return Ok(());
+        }
+        // Keep snapshot names unique and ordered, even if the clock goes backwards.
+        timestamp = timestamp.max(latest.timestamp + Duration::from_millis(1));
+    }
+
+    let millis = timestamp.duration_since(UNIX_EPOCH)?.as_millis();
+    std::fs::write(dir.join(format!("{millis}-{}", reason.as_str())), content)?;
+
+    for snapshot in snapshots.iter().skip(max_snapshots.saturating_sub(1)) {
+        std::fs::remove_file(&snapshot.snapshot_path)?;
+    }
+    Ok(())
+}
+
+fn list_snapshots(dir: &Path) -> Result<Vec<LocalHistorySnapshot>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error.into()),
+    };
+
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let snapshot_path = entry?.path();
+        let Some((millis, reason)) = snapshot_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.split_once('-'))
+        else {
+            continue;
+        };
+        let (Ok(millis), Some(reason)) = (millis.parse(), SnapshotReason::from_str(reason)) else {
+            continue;
+        };
+        snapshots.push(LocalHistorySnapshot {
+            timestamp: UNIX_EPOCH + Duration::from_millis(millis),
+            reason,
+            snapshot_path,
+        });
+    }
+    snapshots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(snapshots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fs, RealFs, RemoveOptions};
+    use text::LineEnding;
+
+    #[test]
+    fn test_local_history() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let history = LocalHistory::new(
+            history_dir.path(),
+            LocalHistoryLimits {
+                max_snapshots_per_file: 3,
+                max_file_len: 10,
+                ..Default::default()
+            },
+        );
+        let path = Path::new("/project/src/main.rs");
+
+        smol::block_on(async {
+            for content in ["one", "two", "two", "three", "four"] {
+                history
+                    .record(path, content.as_bytes().to_vec(), SnapshotReason::Saved)
+                    .await
+                    .unwrap();
+            }
+            history
+                .record(
+                    path,
+                    b"too large to keep".to_vec(),
+                    SnapshotReason::Replaced,
+                )
+                .await
+                .unwrap();
+            history
+                .record(path, b"five".to_vec(), SnapshotReason::Removed)
+                .await
+                .unwrap();
+
+            // Duplicates and large files are skipped, and only the newest snapshots are kept.
+            let snapshots = history.snapshots(path).await.unwrap();
+            let mut contents = Vec::new();
+            for snapshot in &snapshots {
+                contents.push(history.load(snapshot).await.unwrap());
+            }
+            assert_eq!(contents, [b"five".as_slice(), b"four", b"three"]);
+            assert_eq!(
+                snapshots
+                    .iter()
+                    .map(|snapshot| snapshot.reason)
+                    .collect::<Vec<_>>(),
+                [
+                    SnapshotReason::Removed,
+                    SnapshotReason::Saved,
+                    SnapshotReason::Saved
+                ]
+            );
+
+            assert!(history
+                .snapshots(Path::new("/project/src/lib.rs"))
+                .await
+                .unwrap()
+                .is_empty());
+        });
+    }
+
+    #[test]
+    fn test_real_fs_records_snapshots() {
+        let history_dir = tempfile::tempdir().unwrap();
+        let project_dir = tempfile::tempdir().unwrap();
+        let fs = RealFs::with_local_history(LocalHistory::new(
+            history_dir.path(),
+            LocalHistoryLimits {
+                max_snapshots_per_file: 10,
+                max_file_len: 1024,
+                max_files_per_removed_dir: 2,
+                ..Default::default()
+            },
+        ));
+        let history = fs.local_history().unwrap();
+        let path = project_dir.path().join("src/main.rs");
+        let load_all = |path: PathBuf| {
+            let history = history.clone();
+            async move {
+                let mut contents = Vec::new();
+                for snapshot in history.snapshots(&path).await.unwrap() {
+                    let content = history.load(&snapshot).await.unwrap();
+                    contents.push((String::from_utf8(content).unwrap(), snapshot.reason));
+                }
+                contents
+            }
+        };
+
+        smol::block_on(async {
+            fs.save(&path, &"one".into(), LineEnding::Unix)
+                .await
+                .unwrap();
+            // Contents written by another program are kept before they are overwritten.
+            std::fs::write(&path, "external").unwrap();
+            fs.save(&path, &"two".into(), LineEnding::Unix)
+                .await
+                .unwrap();
+            std::fs::write(&path, "three").unwrap();
+            fs.remove_file(&path, RemoveOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(
+                load_all(path.clone()).await,
+                [
+                    ("three".to_string(), SnapshotReason::Removed),
+                    ("two".to_string(), SnapshotReason::Saved),
+                    ("external".to_string(), SnapshotReason::Replaced),
+                    ("one".to_string(), SnapshotReason::Saved),
+                ]
+            );
+
+            // Removing a directory keeps the files inside it.
+            let nested_path = project_dir.path().join("docs/guide/intro.md");
+            fs.save(&nested_path, &"intro".into(), LineEnding::Unix)
+                .await
+                .unwrap();
+            std::fs::write(&nested_path, "edited intro").unwrap();
+            fs.remove_dir(
+                &project_dir.path().join("docs"),
+                RemoveOptions {
+                    recursive: true,
+                    ignore_if_not_exists: false,
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(
+                load_all(nested_path.clone()).await,
+                [
+                    ("edited intro".to_string(), SnapshotReason::Removed),
+                    ("intro".to_string(), SnapshotReason::Saved),
+                ]
+            );
+
+            assert_eq!(history.recorded_paths().await.unwrap(), {
+                let mut paths = vec![path.clone(), nested_path.clone()];
+                paths.sort();
+                paths
+            });
+
+            // Files ignored by git are left out, and so are directories with too many files.
+            let app_dir = project_dir.path().join("app");
+            let kept_path = app_dir.join("src/lib.rs");
+            let ignored_path = app_dir.join("target/build.log");
+            std::fs::create_dir_all(kept_path.parent().unwrap()).unwrap();
+            std::fs::create_dir_all(ignored_path.parent().unwrap()).unwrap();
+            std::fs::write(app_dir.join(".gitignore"), "target\n").unwrap();
+            std::fs::write(&kept_path, "lib").unwrap();
+            std::fs::write(&ignored_path, "build").unwrap();
+            let many_dir = project_dir.path().join("many");
+            std::fs::create_dir_all(&many_dir).unwrap();
+            for name in ["a", "b", "c"] {
+                std::fs::write(many_dir.join(name), name).unwrap();
+            }
+            for dir in [&app_dir, &many_dir] {
+                fs.remove_dir(
+                    dir,
+                    RemoveOptions {
+                        recursive: true,
+                        ignore_if_not_exists: false,
+                    },
+                )
+                .await
+                .unwrap();
+            }
+            assert_eq!(
+                load_all(kept_path).await,
+                [("lib".to_string(), SnapshotReason::Removed)]
+            );
+            assert!(load_all(ignored_path).await.is_empty());
+            assert!(load_all(many_dir.join("a")).await.is_empty());
+        });
+    }
+}
@@ -55,6 +55,10 @@ pub trait GitRepository: Send {
     fn branches(&self) -> Result<Vec<Branch>>;
     fn change_branch(&self, _: &str) -> Result<()>;
     fn create_branch(&self, _: &str) -> Result<()>;
+
+    /// Returns the changes in the working directory, staged or not, with respect
+    /// to the HEAD commit, as a unified diff.
+    fn working_tree_diff(&self) -> Result<String>;
 }
 
 impl std::fmt::Debug for dyn GitRepository {
@@ -211,6 +215,28 @@ impl GitRepository for LibGitRepository {
 
         Ok(())
     }
+
+    fn working_tree_diff(&self) -> Result<String> {
+        // Repositories without any commits yet are diffed against the empty tree.
+        let head_tree = self.head().ok().and_then(|head| head.peel_to_tree().ok());
+        // Untracked files are shown as added, since they're part of the work in progress.
+        let mut options = git2::DiffOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .show_untracked_content(true);
+        let diff = self.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut options))?;
+
+        let mut patch = Vec::new();
+        diff.print(git2::DiffFormat::Patch, |_, _, line| {
+            if matches!(line.origin(), ' ' | '+' | '-') {
+                patch.push(line.origin() as u8);
+            }
+            patch.extend_from_slice(line.content());
+            true
+        })?;
+        Ok(String::from_utf8_lossy(&patch).into_owned())
+    }
 }
 
 fn matches_index(repo: &LibGitRepository, path: &RepoPath, mtime: SystemTime) -> bool {
@@ -319,6 +345,10 @@ impl GitRepository for FakeGitRepository {
         state.branch_name = Some(name.to_owned());
         Ok(())
     }
+
+    fn working_tree_diff(&self) -> Result<String> {
+        Ok(String::new())
+    }
 }
 
 fn check_path_to_repo_path_errors(relative_file_path: &Path) -> Result<()> {
@@ -437,3 +467,31 @@ impl<'a> MapSeekTarget<RepoPath> for RepoPathDescendants<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_working_tree_diff_includes_untracked_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = LibGitRepository::init(dir.path()).unwrap();
+        std::fs::write(dir.path().join("tracked.txt"), "one\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("tracked.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+            .unwrap();
+
+        std::fs::write(dir.path().join("tracked.txt"), "two\n").unwrap();
+        std::fs::create_dir(dir.path().join("new")).unwrap();
+        std::fs::write(dir.path().join("new/untracked.txt"), "three\n").unwrap();
+
+        let diff = repo.working_tree_diff().unwrap();
+        assert!(diff.contains("-one\n+two\n"), "{diff}");
+        assert!(diff.contains("+++ b/new/untracked.txt\n"), "{diff}");
+        assert!(diff.contains("+three\n"), "{diff}");
+    }
+}
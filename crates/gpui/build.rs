@@ -6,6 +6,11 @@ use std::{
 use cbindgen::Config;
 
 fn main() {
+    // Build scripts are compiled for the host, so check the target platform at runtime.
+    if env::var("CARGO_CFG_TARGET_OS").as_deref() != Ok("macos") {
+        return;
+    }
+
     generate_dispatch_bindings();
     let header_path = generate_shader_bindings();
     #[cfg(feature = "runtime_shaders")]
@@ -0,0 +1,473 @@
+//! The accessibility tree describes what a window shows in terms that
+//! assistive technologies such as screen readers understand.
+//!
+//! Elements add nodes to the tree while they paint, with
+//! [`ElementContext::with_accessibility_node`]. Nodes added while painting the
+//! children of another node become its children, and elements that don't add
+//! a node, like most divs used for layout, are skipped over. Once a frame is
+//! drawn, the window hands the nodes that changed since the previous frame to
+//! its platform window, which bridges them to the platform's accessibility
+//! APIs.
+//!
+//! [`ElementContext::with_accessibility_node`]: crate::ElementContext::with_accessibility_node
+
+use crate::{Bounds, EntityId, GlobalElementId, Pixels, SharedString};
+use collections::FxHashMap;
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Range,
+};
+
+/// What a node in the accessibility tree represents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AccessibilityRole {
+    /// The root of a window's tree.
+    Window,
+    /// A container that groups related nodes, such as a focusable element
+    /// that has no more specific role.
+    Group,
+    /// Something that performs an action when clicked.
+    Button,
+    /// A button that toggles between checked and unchecked.
+    Checkbox,
+    /// A button that navigates somewhere else.
+    Link,
+    /// A picture or icon.
+    Image,
+    /// Text that can't be edited.
+    StaticText,
+    /// A single line of editable text.
+    TextInput,
+    /// Editable text spanning multiple lines.
+    MultilineTextInput,
+    /// A list of items, some of which may not be painted.
+    List,
+    /// An item of a list.
+    ListItem,
+    /// A row of tabs.
+    TabList,
+    /// A tab in a [`AccessibilityRole::TabList`].
+    Tab,
+    /// A list of commands, such as a context menu.
+    Menu,
+    /// A command in a menu.
+    MenuItem,
+    /// A window within the window, such as a modal.
+    Dialog,
+    /// A row of buttons.
+    Toolbar,
+}
+
+/// Identifies a node across frames, for as long as the element that adds it
+/// keeps its place in the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AccessibilityNodeId(u64);
+
+/// A node in the accessibility tree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessibilityNode {
+    /// Assigned when the node is added to the tree.
+    pub id: AccessibilityNodeId,
+    /// What the node represents.
+    pub role: AccessibilityRole,
+    /// Where the node is in the window.
+    pub bounds: Bounds<Pixels>,
+    /// What the node is called, like the text of a button.
+    pub label: Option<SharedString>,
+    /// The text the node shows. For text inputs, this is the visible text.
+    pub value: Option<SharedString>,
+    /// The selected range in the node's value, in bytes.
+    pub selection: Option<Range<usize>>,
+    /// Whether the node has keyboard focus.
+    pub focused: bool,
+    /// The node's position among its siblings, when it differs from the
+    /// order they were painted in, such as for the items of a scrolled list.
+    pub index: Option<usize>,
+    /// The node's children, in the order they were painted.
+    pub children: Vec<AccessibilityNodeId>,
+    pub(crate) parent: Option<AccessibilityNodeId>,
+    pub(crate) view_id: Option<EntityId>,
+}
+
+impl AccessibilityNode {
+    /// Creates a node with the given role, covering the given bounds.
+    pub fn new(role: AccessibilityRole, bounds: Bounds<Pixels>) -> Self {
+        Self {
+            id: AccessibilityNodeId(0),
+            role,
+            bounds,
+            label: None,
+            value: None,
+            selection: None,
+            focused: false,
+            index: None,
+            children: Vec::new(),
+            parent: None,
+            view_id: None,
+        }
+    }
+
+    /// Sets what the node is called.
+    pub fn label(mut self, label: impl Into<SharedString>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Sets the text the node shows.
+    pub fn value(mut self, value: impl Into<SharedString>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    /// Sets the selected range in the node's value.
+    pub fn selection(mut self, selection: Range<usize>) -> Self {
+        self.selection = Some(selection);
+        self
+    }
+
+    /// Sets whether the node has keyboard focus.
+    pub fn focused(mut self, focused: bool) -> Self {
+        self.focused = focused;
+        self
+    }
+
+    /// Sets the node's position among its siblings.
+    pub fn index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+}
+
+/// The nodes that changed between two frames.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AccessibilityTreeUpdate {
+    /// Nodes that were added or changed.
+    pub nodes: Vec<AccessibilityNode>,
+    /// Nodes that are no longer in the tree.
+    pub removed: Vec<AccessibilityNodeId>,
+    /// The root of the tree.
+    pub root: Option<AccessibilityNodeId>,
+    /// The node with keyboard focus.
+    pub focus: Option<AccessibilityNodeId>,
+}
+
+/// The accessibility nodes of a window. Platforms that keep their own copy of
+/// the tree can keep it up to date with [`AccessibilityTree::apply`].
+#[derive(Clone, Debug, Default)]
+pub struct AccessibilityTree {
+    root: Option<AccessibilityNodeId>,
+    focus: Option<AccessibilityNodeId>,
+    nodes: FxHashMap<AccessibilityNodeId, AccessibilityNode>,
+    nodes_by_view: FxHashMap<EntityId, Vec<AccessibilityNodeId>>,
+    /// The nodes whose children are being painted.
+    stack: Vec<AccessibilityNodeId>,
+}
+
+impl AccessibilityTree {
+    /// The root of the tree, if anything was painted.
+    pub fn root(&self) -> Option<&AccessibilityNode> {
+        self.node(self.root?)
+    }
+
+    /// The node with keyboard focus.
+    pub fn focus(&self) -> Option<&AccessibilityNode> {
+        self.node(self.focus?)
+    }
+
+    /// Looks up a node by its id.
+    pub fn node(&self, id: AccessibilityNodeId) -> Option<&AccessibilityNode> {
+        self.nodes.get(&id)
+    }
+
+    /// The children of the given node.
+    pub fn children<'a>(
+        &'a self,
+        node: &'a AccessibilityNode,
+    ) -> impl Iterator<Item = &'a AccessibilityNode> + 'a {
+        node.children.iter().filter_map(|id| self.node(*id))
+    }
+
+    /// Returns the nodes that changed since the previous tree, or `None` if
+    /// nothing did.
+    pub fn diff(&self, previous: &Self) -> Option<AccessibilityTreeUpdate> {
+        let mut nodes = self
+            .nodes
+            .values()
+            .filter(|node| previous.nodes.get(&node.id) != Some(node))
+            .cloned()
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|node| node.id);
+        let mut removed = previous
+            .nodes
+            .keys()
+            .filter(|id| !self.nodes.contains_key(id))
+            .copied()
+            .collect::<Vec<_>>();
+        removed.sort();
+
+        if nodes.is_empty()
+            && removed.is_empty()
+            && self.root == previous.root
+            && self.focus == previous.focus
+        {
+            return None;
+        }
+        Some(AccessibilityTreeUpdate {
+            nodes,
+            removed,
+            root: self.root,
+            focus: self.focus,
+        })
+    }
+
+    /// Brings this tree up to date with the given update.
+    pub fn apply(&mut self, update: AccessibilityTreeUpdate) {
+        for id in update.removed {
+            self.nodes.remove(&id);
+        }
+        for node in update.nodes {
+            self.nodes.insert(node.id, node);
+        }
+        self.root = update.root;
+        self.focus = update.focus;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.root = None;
+        self.focus = None;
+        self.nodes.clear();
+        self.nodes_by_view.clear();
+        debug_assert!(self.stack.is_empty());
+    }
+
+    /// Adds a node as the last child of the node being painted, and makes it
+    /// the parent of the nodes added until [`AccessibilityTree::pop_node`].
+    pub(crate) fn push_node(
+        &mut self,
+        mut node: AccessibilityNode,
+        element_id: &GlobalElementId,
+        view_id: Option<EntityId>,
+    ) {
+        let parent = self.stack.last().copied();
+        let position = parent
+            .and_then(|parent| self.nodes.get(&parent))
+            .map_or(0, |parent| parent.children.len());
+
+        let mut hasher = collections::FxHasher::default();
+        (parent, element_id, node.role, node.index.is_some()).hash(&mut hasher);
+        node.index.unwrap_or(position).hash(&mut hasher);
+        let mut id = AccessibilityNodeId(hasher.finish());
+        // Siblings that look alike still need distinct ids.
+        while self.nodes.contains_key(&id) {
+            id.0.hash(&mut hasher);
+            id = AccessibilityNodeId(hasher.finish());
+        }
+
+        node.id = id;
+        node.parent = parent;
+        node.view_id = view_id;
+        node.children.clear();
+        self.insert(node);
+        self.stack.push(id);
+    }
+
+    pub(crate) fn pop_node(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Copies the nodes of views that were reused from the previous frame,
+    /// attaching them to the node being painted.
+    pub(crate) fn reuse_views(&mut self, view_ids: &[EntityId], previous: &Self) {
+        let parent = self.stack.last().copied();
+        for view_id in view_ids {
+            let Some(node_ids) = previous.nodes_by_view.get(view_id) else {
+                continue;
+            };
+            for node_id in node_ids {
+                let Some(node) = previous.nodes.get(node_id) else {
+                    continue;
+                };
+                let mut node = node.clone();
+                let attached_to_reused_view = node
+                    .parent
+                    .and_then(|parent| previous.nodes.get(&parent))
+                    .map_or(false, |parent| {
+                        parent
+                            .view_id
+                            .map_or(false, |view_id| view_ids.contains(&view_id))
+                    });
+                if !attached_to_reused_view {
+                    node.parent = parent;
+                }
+                self.insert(node);
+            }
+        }
+    }
+
+    fn insert(&mut self, node: AccessibilityNode) {
+        match node.parent.and_then(|parent| self.nodes.get_mut(&parent)) {
+            Some(parent) => {
+                if !parent.children.contains(&node.id) {
+                    parent.children.push(node.id);
+                }
+            }
+            None => {
+                if self.root.is_none() {
+                    self.root = Some(node.id);
+                }
+            }
+        }
+        if node.focused {
+            self.focus = Some(node.id);
+        }
+        if let Some(view_id) = node.view_id {
+            self.nodes_by_view.entry(view_id).or_default().push(node.id);
+        }
+        self.nodes.insert(node.id, node);
+    }
+}
+
+/// Prints the tree with one node per line, indenting children below their
+/// parents, so that tests can compare it against a snapshot.
+impl fmt::Display for AccessibilityTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_node(
+            tree: &AccessibilityTree,
+            node: &AccessibilityNode,
+            depth: usize,
+            f: &mut fmt::Formatter<'_>,
+        ) -> fmt::Result {
+            write!(f, "{:indent$}{:?}", "", node.role, indent = depth * 2)?;
+            if let Some(label) = &node.label {
+                write!(f, " {label:?}")?;
+            }
+            if let Some(value) = &node.value {
+                write!(f, " value={value:?}")?;
+            }
+            if let Some(selection) = &node.selection {
+                write!(f, " selection={selection:?}")?;
+            }
+            if node.focused {
+                write!(f, " focused")?;
+            }
+            writeln!(f)?;
+            for child in tree.children(node) {
+                write_node(tree, child, depth + 1, f)?;
+            }
+            Ok(())
+        }
+
+        match self.root() {
+            Some(root) => write_node(self, root, 0, f),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        self as gpui, div, px, uniform_list, FocusHandle, InteractiveElement, IntoElement,
+        ParentElement, Render, Styled, TestAppContext, ViewContext,
+    };
+
+    struct TestView {
+        focus_handle: FocusHandle,
+        items: Vec<&'static str>,
+    }
+
+    impl Render for TestView {
+        fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+            div()
+                .size_full()
+                .child(
+                    div()
+                        .id("save")
+                        .accessibility_role(AccessibilityRole::Button)
+                        .child("Save"),
+                )
+                .child(
+                    div()
+                        .track_focus(&self.focus_handle)
+                        .accessibility_label("Notes")
+                        .child("Nothing yet"),
+                )
+                .child(
+                    uniform_list(
+                        cx.view().clone(),
+                        "items",
+                        self.items.len(),
+                        |this, range, _| {
+                            range
+                                .map(|ix| div().h(px(10.)).child(this.items[ix]))
+                                .collect()
+                        },
+                    )
+                    .h(px(25.)),
+                )
+        }
+    }
+
+    #[gpui::test]
+    fn test_accessibility_tree(cx: &mut TestAppContext) {
+        let (view, cx) = cx.add_window_view(|cx| TestView {
+            focus_handle: cx.focus_handle(),
+            items: vec!["One", "Two", "Three", "Four"],
+        });
+        cx.update(|cx| cx.set_window_title("Notes"));
+        cx.run_until_parked();
+        // Windows don't build a tree until assistive technologies ask for one.
+        assert!(cx.update(|cx| cx.accessibility_tree().root().is_none()));
+
+        cx.update(|cx| cx.set_accessibility_enabled(true));
+        cx.run_until_parked();
+        assert_eq!(
+            cx.update(|cx| cx.accessibility_tree().to_string()),
+            concat!(
+                "Window \"Notes\"\n",
+                "  Button\n",
+                "    StaticText value=\"Save\"\n",
+                "  Group \"Notes\"\n",
+                "    StaticText value=\"Nothing yet\"\n",
+                "  List\n",
+                "    ListItem\n",
+                "      StaticText value=\"One\"\n",
+                "    ListItem\n",
+                "      StaticText value=\"Two\"\n",
+                "    ListItem\n",
+                "      StaticText value=\"Three\"\n",
+            )
+        );
+
+        let mut mirror = AccessibilityTree::default();
+        let previous = cx.update(|cx| cx.accessibility_tree().clone());
+        mirror.apply(previous.diff(&AccessibilityTree::default()).unwrap());
+        assert_eq!(mirror.to_string(), previous.to_string());
+
+        // Only the nodes that changed are sent to the platform.
+        view.update(cx, |view, cx| cx.focus(&view.focus_handle));
+        cx.run_until_parked();
+        let update = cx.update(|cx| cx.accessibility_tree().diff(&previous).unwrap());
+        assert_eq!(update.nodes.len(), 1);
+        assert_eq!(update.nodes[0].label, Some("Notes".into()));
+        assert!(update.nodes[0].focused);
+        assert_eq!(update.focus, Some(update.nodes[0].id));
+        assert!(update.removed.is_empty());
+
+        mirror.apply(update);
+        assert_eq!(
+            mirror.to_string(),
+            cx.update(|cx| cx.accessibility_tree().to_string())
+        );
+        assert_eq!(
+            mirror.focus().and_then(|node| node.label.clone()),
+            Some("Notes".into())
+        );
+
+        cx.update(|cx| cx.set_accessibility_enabled(false));
+        cx.run_until_parked();
+        assert!(cx.update(|cx| cx.accessibility_tree().root().is_none()));
+    }
+}
@@ -17,10 +17,11 @@ use time::UtcOffset;
 use crate::{
     current_platform, image_cache::ImageCache, init_app_menus, Action, ActionRegistry, Any,
     AnyView, AnyWindowHandle, AppMetadata, AssetSource, BackgroundExecutor, ClipboardItem, Context,
-    DispatchPhase, DisplayId, Entity, EventEmitter, ForegroundExecutor, Global, KeyBinding, Keymap,
-    Keystroke, LayoutId, Menu, PathPromptOptions, Pixels, Platform, PlatformDisplay, Point, Render,
-    SharedString, SubscriberSet, Subscription, SvgRenderer, Task, TextStyle, TextStyleRefinement,
-    TextSystem, View, ViewContext, Window, WindowContext, WindowHandle, WindowId,
+    DispatchPhase, DisplayId, Entity, EventEmitter, ForegroundExecutor, Global, HeadlessPlatform,
+    KeyBinding, Keymap, Keystroke, LayoutId, Menu, PathPromptOptions, Pixels, Platform,
+    PlatformDisplay, Point, Render, SharedString, SubscriberSet, Subscription, SvgRenderer, Task,
+    TextStyle, TextStyleRefinement, TextSystem, View, ViewContext, Window, WindowContext,
+    WindowHandle, WindowId,
 };
 use anyhow::{anyhow, Result};
 use collections::{FxHashMap, FxHashSet, VecDeque};
@@ -108,10 +109,29 @@ pub struct App(Rc<AppCell>);
 /// configured, you'll start the app with `App::run`.
 impl App {
     /// Builds an app with the given asset source.
+    ///
+    /// Panics if the platform can't be started; see [`App::try_new`].
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
+        Self::try_new().expect("failed to start the platform")
+    }
+
+    /// Builds an app, failing when the platform can't be started, e.g. on Linux
+    /// when there's no display server to connect to.
+    pub fn try_new() -> Result<Self> {
+        Ok(Self(AppContext::new(
+            current_platform()?,
+            Arc::new(()),
+            http::client(),
+        )))
+    }
+
+    /// Builds an app that runs without a display, for driving it from scripts.
+    /// Its windows are never shown, but unlike in tests its tasks run on real
+    /// threads and its timers take real time.
+    pub fn headless() -> Self {
         Self(AppContext::new(
-            current_platform(),
+            Rc::new(HeadlessPlatform::new()),
             Arc::new(()),
             http::client(),
         ))
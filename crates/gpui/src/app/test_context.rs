@@ -1,13 +1,15 @@
 use crate::{
-    Action, AnyElement, AnyView, AnyWindowHandle, AppCell, AppContext, AsyncAppContext,
-    AvailableSpace, BackgroundExecutor, Bounds, ClipboardItem, Context, Entity, EventEmitter,
-    ForegroundExecutor, Global, InputEvent, Keystroke, Model, ModelContext, Modifiers,
-    ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels,
-    Platform, Point, Render, Result, Size, Task, TestDispatcher, TestPlatform, TestWindow,
-    TextSystem, View, ViewContext, VisualContext, WindowContext, WindowHandle, WindowOptions,
+    check_golden_image, Action, AnyElement, AnyView, AnyWindowHandle, AppCell, AppContext,
+    AssetSource, AsyncAppContext, AvailableSpace, BackgroundExecutor, Bounds, ClipboardItem,
+    Context, CosmicTextSystem, Entity, EventEmitter, ForegroundExecutor, Global, ImageTolerance,
+    InputEvent, Keystroke, Model, ModelContext, Modifiers, ModifiersChangedEvent, MouseButton,
+    MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels, Platform, Point, Render, Result, Size,
+    SvgRenderer, Task, TestDispatcher, TestPlatform, TestWindow, TextSystem, View, ViewContext,
+    VisualContext, WindowContext, WindowHandle, WindowOptions,
 };
-use anyhow::{anyhow, bail};
+use anyhow::{anyhow, bail, Context as _};
 use futures::{Stream, StreamExt};
+use image::RgbaImage;
 use std::{cell::RefCell, future::Future, ops::Deref, rc::Rc, sync::Arc, time::Duration};
 
 /// A TestAppContext is provided to tests created with `#[gpui::test]`, it provides
@@ -112,6 +114,14 @@ impl TestAppContext {
         self.fn_name
     }
 
+    /// Sets the source the app loads assets such as icons from, which is empty by default in tests.
+    pub fn set_asset_source(&self, asset_source: impl AssetSource) {
+        let mut cx = self.app.borrow_mut();
+        let asset_source = Arc::new(asset_source);
+        cx.asset_source = asset_source.clone();
+        cx.svg_renderer = SvgRenderer::new(asset_source);
+    }
+
     /// Checks whether there have been any new path prompts received by the platform.
     pub fn did_prompt_for_new_path(&self) -> bool {
         self.test_platform.did_prompt_for_new_path()
@@ -219,6 +229,25 @@ impl TestAppContext {
         self.test_platform.read_from_clipboard()
     }
 
+    /// Rasterizes the sprites of the windows opened from now on into images
+    /// rather than only recording their positions, so that their frames can be
+    /// rendered with [`VisualTestContext::render_frame`]. This is slower, so
+    /// it's only meant for tests that inspect pixels.
+    pub fn enable_software_rendering(&self) {
+        self.test_platform.software_rendering.set(true);
+    }
+
+    /// Shapes and rasterizes text with the portable text system and the bundled fonts,
+    /// which is what the test platform uses everywhere but on macOS, so that text renders
+    /// the same on every platform. Call this before opening windows or loading fonts.
+    pub fn enable_portable_text_system(&mut self) {
+        let text_system = Arc::new(TextSystem::new(Arc::new(
+            CosmicTextSystem::with_bundled_fonts(),
+        )));
+        self.app.borrow_mut().text_system = text_system.clone();
+        self.text_system = text_system;
+    }
+
     /// Simulates choosing a File in the platform's "Open" dialog.
     pub fn simulate_new_path_selection(
         &self,
@@ -696,6 +725,35 @@ impl<'a> VisualTestContext {
         })
     }
 
+    /// Renders the frame most recently drawn to the window into an image,
+    /// at the window's scale factor. Useful for inspecting what a test drew.
+    ///
+    /// The window must have been opened after calling
+    /// [`TestAppContext::enable_software_rendering`].
+    pub fn render_frame(&mut self) -> RgbaImage {
+        let window = self.test_window(self.window);
+        self.update(|cx| window.render(&cx.window.rendered_frame.scene))
+    }
+
+    /// Renders the frame most recently drawn to the window and saves it as a PNG at the given path.
+    pub fn save_frame(&mut self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        let path = path.as_ref();
+        self.render_frame()
+            .save_with_format(path, image::ImageFormat::Png)
+            .with_context(|| format!("failed to save frame to {path:?}"))
+    }
+
+    /// Renders the frame most recently drawn to the window and compares it
+    /// against the golden image at the given path, panicking if they differ.
+    /// See [`check_golden_image`] for how golden images are recorded.
+    #[track_caller]
+    pub fn assert_frame_matches_golden_image(&mut self, path: impl AsRef<std::path::Path>) {
+        let frame = self.render_frame();
+        if let Err(error) = check_golden_image(&frame, path.as_ref(), ImageTolerance::default()) {
+            panic!("{error:?}");
+        }
+    }
+
     /// Simulate an event from the platform, e.g. a SrollWheelEvent
     /// Make sure you've called [VisualTestContext::draw] first!
     pub fn simulate_event<E: InputEvent>(&mut self, event: E) {
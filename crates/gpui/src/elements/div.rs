@@ -16,12 +16,12 @@
 //! constructed by combining these two systems into an all-in-one element.
 
 use crate::{
-    point, px, size, Action, AnyDrag, AnyElement, AnyTooltip, AnyView, AppContext, Bounds,
-    ClickEvent, DispatchPhase, Element, ElementContext, ElementId, FocusHandle, Global,
-    IntoElement, IsZero, KeyContext, KeyDownEvent, KeyUpEvent, LayoutId, MouseButton,
-    MouseDownEvent, MouseMoveEvent, MouseUpEvent, ParentElement, Pixels, Point, Render,
-    ScrollWheelEvent, SharedString, Size, StackingOrder, Style, StyleRefinement, Styled, Task,
-    View, Visibility, WindowContext,
+    point, px, size, AccessibilityNode, AccessibilityRole, Action, AnyDrag, AnyElement, AnyTooltip,
+    AnyView, AppContext, Bounds, ClickEvent, DispatchPhase, Element, ElementContext, ElementId,
+    FocusHandle, Global, IntoElement, IsZero, KeyContext, KeyDownEvent, KeyUpEvent, LayoutId,
+    MouseButton, MouseDownEvent, MouseMoveEvent, MouseUpEvent, ParentElement, Pixels, Point,
+    Render, ScrollWheelEvent, SharedString, Size, StackingOrder, Style, StyleRefinement, Styled,
+    Task, View, Visibility, WindowContext,
 };
 
 use collections::HashMap;
@@ -515,6 +515,21 @@ pub trait InteractiveElement: Sized {
         Focusable { element: self }
     }
 
+    /// Describe what this element is to assistive technologies, adding it to
+    /// the window's accessibility tree. Focusable elements are added as groups
+    /// even without a role.
+    fn accessibility_role(mut self, role: AccessibilityRole) -> Self {
+        self.interactivity().accessibility_role = Some(role);
+        self
+    }
+
+    /// Set the name assistive technologies use for this element, for elements
+    /// whose children don't describe them, like icon buttons.
+    fn accessibility_label(mut self, label: impl Into<SharedString>) -> Self {
+        self.interactivity().accessibility_label = Some(label.into());
+        self
+    }
+
     /// Set the keymap context for this element. This will be used to determine
     /// which action to dispatch from the keymap.
     fn key_context<C, E>(mut self, key_context: C) -> Self
@@ -1120,19 +1135,28 @@ impl Element for Div {
             (child_max - child_min).into()
         };
 
-        self.interactivity.paint(
-            bounds,
-            content_size,
-            &mut element_state.interactive_state,
-            cx,
-            |_style, scroll_offset, cx| {
-                cx.with_element_offset(scroll_offset, |cx| {
-                    for child in &mut self.children {
-                        child.paint(cx);
-                    }
-                })
-            },
-        );
+        let accessibility_node =
+            self.interactivity
+                .accessibility_node(bounds, &element_state.interactive_state, cx);
+        let paint = |cx: &mut ElementContext| {
+            self.interactivity.paint(
+                bounds,
+                content_size,
+                &mut element_state.interactive_state,
+                cx,
+                |_style, scroll_offset, cx| {
+                    cx.with_element_offset(scroll_offset, |cx| {
+                        for child in &mut self.children {
+                            child.paint(cx);
+                        }
+                    })
+                },
+            );
+        };
+        match accessibility_node {
+            Some(node) => cx.with_accessibility_node(node, paint),
+            None => paint(cx),
+        }
     }
 }
 
@@ -1203,6 +1227,8 @@ pub struct Interactivity {
     pub(crate) hover_listener: Option<Box<dyn Fn(&bool, &mut WindowContext)>>,
     pub(crate) tooltip_builder: Option<TooltipBuilder>,
     pub(crate) block_mouse: bool,
+    pub(crate) accessibility_role: Option<AccessibilityRole>,
+    pub(crate) accessibility_label: Option<SharedString>,
 
     #[cfg(debug_assertions)]
     pub(crate) location: Option<core::panic::Location<'static>>,
@@ -1274,6 +1300,31 @@ impl Interactivity {
         (layout_id, element_state)
     }
 
+    /// The node describing this element in the window's accessibility tree, if
+    /// it has a role or can be focused.
+    pub fn accessibility_node(
+        &self,
+        bounds: Bounds<Pixels>,
+        element_state: &InteractiveElementState,
+        cx: &WindowContext,
+    ) -> Option<AccessibilityNode> {
+        if !cx.is_accessibility_enabled() {
+            return None;
+        }
+        let role = self
+            .accessibility_role
+            .or(self.focusable.then_some(AccessibilityRole::Group))?;
+        let focused = element_state
+            .focus_handle
+            .as_ref()
+            .map_or(false, |focus_handle| focus_handle.is_focused(cx));
+        let mut node = AccessibilityNode::new(role, bounds).focused(focused);
+        if let Some(label) = self.accessibility_label.clone() {
+            node = node.label(label);
+        }
+        Some(node)
+    }
+
     /// Paint this element according to this interactivity state's configured styles
     /// and bind the element's mouse and keyboard events.
     ///
@@ -1,8 +1,8 @@
 use crate::{
-    ActiveTooltip, AnyTooltip, AnyView, Bounds, DispatchPhase, Element, ElementContext, ElementId,
-    HighlightStyle, IntoElement, LayoutId, MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels,
-    Point, SharedString, Size, TextRun, TextStyle, WhiteSpace, WindowContext, WrappedLine,
-    TOOLTIP_DELAY,
+    AccessibilityNode, AccessibilityRole, ActiveTooltip, AnyTooltip, AnyView, Bounds,
+    DispatchPhase, Element, ElementContext, ElementId, HighlightStyle, IntoElement, LayoutId,
+    MouseDownEvent, MouseMoveEvent, MouseUpEvent, Pixels, Point, SharedString, Size, TextRun,
+    TextStyle, WhiteSpace, WindowContext, WrappedLine, TOOLTIP_DELAY,
 };
 use anyhow::anyhow;
 use parking_lot::{Mutex, MutexGuard};
@@ -30,7 +30,7 @@ impl Element for &'static str {
     }
 
     fn paint(&mut self, bounds: Bounds<Pixels>, state: &mut TextState, cx: &mut ElementContext) {
-        state.paint(bounds, self, cx)
+        state.paint(bounds, SharedString::from(*self), cx)
     }
 }
 
@@ -72,8 +72,7 @@ impl Element for SharedString {
     }
 
     fn paint(&mut self, bounds: Bounds<Pixels>, state: &mut TextState, cx: &mut ElementContext) {
-        let text_str: &str = self.as_ref();
-        state.paint(bounds, text_str, cx)
+        state.paint(bounds, self.clone(), cx)
     }
 }
 
@@ -151,7 +150,7 @@ impl Element for StyledText {
     }
 
     fn paint(&mut self, bounds: Bounds<Pixels>, state: &mut Self::State, cx: &mut ElementContext) {
-        state.paint(bounds, &self.text, cx)
+        state.paint(bounds, self.text.clone(), cx)
     }
 }
 
@@ -262,19 +261,22 @@ impl TextState {
         layout_id
     }
 
-    fn paint(&mut self, bounds: Bounds<Pixels>, text: &str, cx: &mut ElementContext) {
+    fn paint(&mut self, bounds: Bounds<Pixels>, text: SharedString, cx: &mut ElementContext) {
         let element_state = self.lock();
         let element_state = element_state
             .as_ref()
             .ok_or_else(|| anyhow!("measurement has not been performed on {}", text))
             .unwrap();
 
-        let line_height = element_state.line_height;
-        let mut line_origin = bounds.origin;
-        for line in &element_state.lines {
-            line.paint(line_origin, line_height, cx).log_err();
-            line_origin.y += line.size(line_height).height;
-        }
+        let node = AccessibilityNode::new(AccessibilityRole::StaticText, bounds).value(text);
+        cx.with_accessibility_node(node, |cx| {
+            let line_height = element_state.line_height;
+            let mut line_origin = bounds.origin;
+            for line in &element_state.lines {
+                line.paint(line_origin, line_height, cx).log_err();
+                line_origin.y += line.size(line_height).height;
+            }
+        });
     }
 
     fn index_for_position(&self, bounds: Bounds<Pixels>, position: Point<Pixels>) -> Option<usize> {
@@ -5,9 +5,10 @@
 //! elements with uniform height.
 
 use crate::{
-    point, px, size, AnyElement, AvailableSpace, Bounds, ContentMask, Element, ElementContext,
-    ElementId, InteractiveElement, InteractiveElementState, Interactivity, IntoElement, LayoutId,
-    Pixels, Render, Size, StyleRefinement, Styled, View, ViewContext, WindowContext,
+    point, px, size, AccessibilityNode, AccessibilityRole, AnyElement, AvailableSpace, Bounds,
+    ContentMask, Element, ElementContext, ElementId, InteractiveElement, InteractiveElementState,
+    Interactivity, IntoElement, LayoutId, Pixels, Render, Size, StyleRefinement, Styled, View,
+    ViewContext, WindowContext,
 };
 use smallvec::SmallVec;
 use std::{cell::RefCell, cmp, ops::Range, rc::Rc};
@@ -49,6 +50,7 @@ where
         interactivity: Interactivity {
             element_id: Some(id),
             base_style: Box::new(base_style),
+            accessibility_role: Some(AccessibilityRole::List),
 
             #[cfg(debug_assertions)]
             location: Some(*core::panic::Location::caller()),
@@ -190,6 +192,10 @@ impl Element for UniformList {
             .as_mut()
             .and_then(|handle| handle.deferred_scroll_to_item.take());
 
+        let accessibility_node =
+            self.interactivity
+                .accessibility_node(bounds, &element_state.interactive, cx);
+
         self.interactivity.paint(
             bounds,
             content_size,
@@ -238,23 +244,39 @@ impl Element for UniformList {
                         ..cmp::min(last_visible_element_ix, self.item_count);
 
                     let mut items = (self.render_items)(visible_range.clone(), cx);
-                    cx.with_z_index(1, |cx| {
-                        let content_mask = ContentMask { bounds };
-                        cx.with_content_mask(Some(content_mask), |cx| {
-                            for (item, ix) in items.iter_mut().zip(visible_range) {
-                                let item_origin = padded_bounds.origin
-                                    + point(
-                                        px(0.),
-                                        item_height * ix + scroll_offset.y + padding.top,
+                    let paint_items = |cx: &mut ElementContext| {
+                        cx.with_z_index(1, |cx| {
+                            let content_mask = ContentMask { bounds };
+                            cx.with_content_mask(Some(content_mask), |cx| {
+                                for (item, ix) in items.iter_mut().zip(visible_range) {
+                                    let item_origin = padded_bounds.origin
+                                        + point(
+                                            px(0.),
+                                            item_height * ix + scroll_offset.y + padding.top,
+                                        );
+                                    let available_space = size(
+                                        AvailableSpace::Definite(padded_bounds.size.width),
+                                        AvailableSpace::Definite(item_height),
                                     );
-                                let available_space = size(
-                                    AvailableSpace::Definite(padded_bounds.size.width),
-                                    AvailableSpace::Definite(item_height),
-                                );
-                                item.draw(item_origin, available_space, cx);
-                            }
-                        });
-                    });
+                                    let node = AccessibilityNode::new(
+                                        AccessibilityRole::ListItem,
+                                        Bounds::new(
+                                            item_origin,
+                                            size(padded_bounds.size.width, item_height),
+                                        ),
+                                    )
+                                    .index(ix);
+                                    cx.with_accessibility_node(node, |cx| {
+                                        item.draw(item_origin, available_space, cx);
+                                    });
+                                }
+                            });
+                        })
+                    };
+                    match accessibility_node {
+                        Some(node) => cx.with_accessibility_node(node, paint_items),
+                        None => paint_items(cx),
+                    }
                 }
             },
         )
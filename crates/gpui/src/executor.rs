@@ -1,5 +1,8 @@
 use crate::{AppContext, PlatformDispatcher};
-use futures::{channel::mpsc, pin_mut, FutureExt};
+use futures::{
+    channel::{mpsc, oneshot},
+    pin_mut, FutureExt,
+};
 use smol::prelude::*;
 use std::{
     fmt::Debug,
@@ -297,6 +300,19 @@ impl BackgroundExecutor {
         Task::Spawned(task)
     }
 
+    /// Returns a future that resolves once no task is queued or running, not
+    /// counting timers that have yet to fire. On platforms that don't keep
+    /// track of their tasks, it resolves right away.
+    pub fn wait_until_idle(&self) -> impl Future<Output = ()> {
+        let (tx, rx) = oneshot::channel();
+        self.dispatcher.on_idle(Box::new(move || {
+            tx.send(()).ok();
+        }));
+        async move {
+            rx.await.ok();
+        }
+    }
+
     /// in tests, start_waiting lets you indicate which task is waiting (for debugging only)
     #[cfg(any(test, feature = "test-support"))]
     pub fn start_waiting(&self) {
@@ -64,6 +64,7 @@
 #![allow(clippy::type_complexity)]
 
 #[macro_use]
+mod accessibility;
 mod action;
 mod app;
 
@@ -111,6 +112,7 @@ mod seal {
     pub trait Sealed {}
 }
 
+pub use accessibility::*;
 pub use action::*;
 pub use anyhow::Result;
 pub use app::*;
@@ -1,15 +1,22 @@
 mod app_menu;
+// Only the test platform can opt into this text system on macOS.
+#[cfg_attr(target_os = "macos", allow(dead_code))]
+mod cosmic_text;
+mod headless;
 mod keystroke;
+#[cfg(target_os = "linux")]
+mod linux;
 #[cfg(target_os = "macos")]
 mod mac;
+mod software;
 #[cfg(any(test, feature = "test-support"))]
 mod test;
 
 use crate::{
-    Action, AnyWindowHandle, AsyncWindowContext, BackgroundExecutor, Bounds, DevicePixels, Font,
-    FontId, FontMetrics, FontRun, ForegroundExecutor, GlobalPixels, GlyphId, Keymap, LineLayout,
-    Pixels, PlatformInput, Point, RenderGlyphParams, RenderImageParams, RenderSvgParams, Scene,
-    SharedString, Size, Task, TaskLabel, WindowContext,
+    AccessibilityTreeUpdate, Action, AnyWindowHandle, AsyncWindowContext, BackgroundExecutor,
+    Bounds, DevicePixels, Font, FontId, FontMetrics, FontRun, ForegroundExecutor, GlobalPixels,
+    GlyphId, Keymap, LineLayout, Pixels, PlatformInput, Point, RenderGlyphParams,
+    RenderImageParams, RenderSvgParams, Scene, SharedString, Size, Task, TaskLabel, WindowContext,
 };
 use anyhow::Result;
 use async_task::Runnable;
@@ -31,18 +38,29 @@ use std::{
 };
 use uuid::Uuid;
 
+#[cfg_attr(target_os = "macos", allow(unused_imports))]
+pub(crate) use self::cosmic_text::*;
 pub use app_menu::*;
+pub(crate) use headless::*;
 pub use keystroke::*;
+#[cfg(target_os = "linux")]
+pub(crate) use linux::*;
 #[cfg(target_os = "macos")]
 pub(crate) use mac::*;
+pub(crate) use software::*;
 #[cfg(any(test, feature = "test-support"))]
 pub(crate) use test::*;
 use time::UtcOffset;
 pub use util::SemanticVersion;
 
 #[cfg(target_os = "macos")]
-pub(crate) fn current_platform() -> Rc<dyn Platform> {
-    Rc::new(MacPlatform::new())
+pub(crate) fn current_platform() -> Result<Rc<dyn Platform>> {
+    Ok(Rc::new(MacPlatform::new()))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn current_platform() -> Result<Rc<dyn Platform>> {
+    Ok(Rc::new(LinuxPlatform::new()?))
 }
 
 pub(crate) trait Platform: 'static {
@@ -177,6 +195,15 @@ pub(crate) trait PlatformWindow: HasWindowHandle + HasDisplayHandle {
     fn is_topmost_for_position(&self, position: Point<Pixels>) -> bool;
     fn draw(&self, scene: &Scene);
 
+    /// Registers a callback for when assistive technologies start or stop
+    /// reading the window, so that it only builds its accessibility tree while
+    /// they do.
+    fn on_accessibility_requested(&self, _callback: Box<dyn FnMut(bool)>) {}
+
+    /// Called after each frame whose accessibility tree changed, with the
+    /// nodes that did.
+    fn update_accessibility_tree(&self, _update: AccessibilityTreeUpdate) {}
+
     fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas>;
 
     #[cfg(any(test, feature = "test-support"))]
@@ -197,6 +224,11 @@ pub trait PlatformDispatcher: Send + Sync {
     fn park(&self);
     fn unparker(&self) -> Unparker;
 
+    /// Calls back once no task is queued or running, not counting timers that
+    /// have yet to fire. Dispatchers that don't keep track of their tasks drop
+    /// the callback.
+    fn on_idle(&self, _callback: Box<dyn FnOnce() + Send>) {}
+
     #[cfg(any(test, feature = "test-support"))]
     fn as_test(&self) -> Option<&TestDispatcher> {
         None
@@ -324,6 +356,39 @@ impl From<etagere::AllocId> for TileId {
     }
 }
 
+impl From<Size<DevicePixels>> for etagere::Size {
+    fn from(size: Size<DevicePixels>) -> Self {
+        etagere::Size::new(size.width.into(), size.height.into())
+    }
+}
+
+impl From<etagere::Point> for Point<DevicePixels> {
+    fn from(value: etagere::Point) -> Self {
+        Point {
+            x: DevicePixels::from(value.x),
+            y: DevicePixels::from(value.y),
+        }
+    }
+}
+
+impl From<etagere::Size> for Size<DevicePixels> {
+    fn from(size: etagere::Size) -> Self {
+        Size {
+            width: DevicePixels::from(size.width),
+            height: DevicePixels::from(size.height),
+        }
+    }
+}
+
+impl From<etagere::Rectangle> for Bounds<DevicePixels> {
+    fn from(rectangle: etagere::Rectangle) -> Self {
+        Bounds {
+            origin: rectangle.min.into(),
+            size: rectangle.size().into(),
+        }
+    }
+}
+
 impl From<TileId> for etagere::AllocId {
     fn from(id: TileId) -> Self {
         Self::deserialize(id.0)
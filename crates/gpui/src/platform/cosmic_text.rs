@@ -0,0 +1,7 @@
+//! A text system built on portable Rust crates rather than on a platform's
+//! native text APIs: fontdb for font discovery, rustybuzz (through
+//! cosmic-text) for shaping and fallback, and swash for rasterization.
+
+mod text_system;
+
+pub(crate) use text_system::*;
@@ -0,0 +1,572 @@
+use crate::{
+    point, px, size, Bounds, DevicePixels, Font, FontId, FontMetrics, FontRun, FontStyle, GlyphId,
+    LineLayout, Pixels, PlatformTextSystem, RenderGlyphParams, Result, ShapedGlyph, ShapedRun,
+    Size, SUBPIXEL_VARIANTS,
+};
+use anyhow::anyhow;
+use collections::{BTreeSet, HashMap};
+use cosmic_text::{
+    fontdb, Attrs, AttrsList, BufferLine, Family, Font as CosmicTextFont, FontSystem, Shaping, Wrap,
+};
+use parking_lot::{RwLock, RwLockUpgradableReadGuard};
+use smallvec::smallvec;
+use std::{borrow::Cow, sync::Arc};
+use swash::{
+    scale::{image::Content, image::Image, Render, ScaleContext, Source, StrikeWith},
+    tag_from_bytes,
+    zeno::{Format, Vector},
+};
+
+pub(crate) struct CosmicTextSystem(RwLock<CosmicTextSystemState>);
+
+struct CosmicTextSystemState {
+    font_system: FontSystem,
+    scale_context: ScaleContext,
+    fonts: Vec<LoadedFont>,
+    font_selections: HashMap<Font, FontId>,
+    font_ids_by_database_id: HashMap<fontdb::ID, FontId>,
+}
+
+struct LoadedFont {
+    font: Arc<CosmicTextFont>,
+    is_emoji: bool,
+}
+
+impl CosmicTextSystem {
+    pub(crate) fn new() -> Self {
+        Self::with_font_system(FontSystem::new())
+    }
+
+    /// A text system that only knows the fonts bundled with Zed, so that tests
+    /// neither depend on nor spend time scanning the fonts installed on the
+    /// machine running them.
+    #[cfg(any(test, feature = "test-support"))]
+    pub(crate) fn with_bundled_fonts() -> Self {
+        let text_system = Self::with_font_system(FontSystem::new_with_locale_and_db(
+            "en-US".into(),
+            fontdb::Database::new(),
+        ));
+        text_system
+            .add_fonts(vec![
+                Cow::Borrowed(include_bytes!(
+                    "../../../../../assets/fonts/zed-mono/zed-mono-extended.ttf"
+                )),
+                Cow::Borrowed(include_bytes!(
+                    "../../../../../assets/fonts/zed-mono/zed-mono-extendedbold.ttf"
+                )),
+                Cow::Borrowed(include_bytes!(
+                    "../../../../../assets/fonts/zed-sans/zed-sans-extended.ttf"
+                )),
+            ])
+            .unwrap();
+        text_system
+    }
+
+    fn with_font_system(font_system: FontSystem) -> Self {
+        Self(RwLock::new(CosmicTextSystemState {
+            font_system,
+            scale_context: ScaleContext::new(),
+            fonts: Vec::new(),
+            font_selections: HashMap::default(),
+            font_ids_by_database_id: HashMap::default(),
+        }))
+    }
+}
+
+impl Default for CosmicTextSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlatformTextSystem for CosmicTextSystem {
+    fn add_fonts(&self, fonts: Vec<Cow<'static, [u8]>>) -> Result<()> {
+        let mut lock = self.0.write();
+        let database = lock.font_system.db_mut();
+        for font in fonts {
+            database.load_font_data(font.into_owned());
+        }
+        Ok(())
+    }
+
+    fn all_font_names(&self) -> Vec<String> {
+        self.all_font_families()
+    }
+
+    fn all_font_families(&self) -> Vec<String> {
+        self.0
+            .read()
+            .font_system
+            .db()
+            .faces()
+            .filter_map(|face| Some(face.families.first()?.0.clone()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    fn font_id(&self, font: &Font) -> Result<FontId> {
+        let lock = self.0.upgradable_read();
+        if let Some(font_id) = lock.font_selections.get(font) {
+            Ok(*font_id)
+        } else {
+            let mut lock = RwLockUpgradableReadGuard::upgrade(lock);
+            // cosmic-text doesn't support OpenType features yet, so the font's
+            // features are ignored.
+            let database_id = lock
+                .font_system
+                .db()
+                .query(&fontdb::Query {
+                    families: &[Family::Name(&font.family)],
+                    weight: fontdb::Weight(font.weight.0.round() as u16),
+                    stretch: fontdb::Stretch::Normal,
+                    style: match font.style {
+                        FontStyle::Normal => fontdb::Style::Normal,
+                        FontStyle::Italic => fontdb::Style::Italic,
+                        FontStyle::Oblique => fontdb::Style::Oblique,
+                    },
+                })
+                .ok_or_else(|| anyhow!("no font found for family {:?}", font.family))?;
+            let font_id = lock.font_id_for_database_id(database_id)?;
+            lock.font_selections.insert(font.clone(), font_id);
+            Ok(font_id)
+        }
+    }
+
+    fn font_metrics(&self, font_id: FontId) -> FontMetrics {
+        let lock = self.0.read();
+        let font = lock.fonts[font_id.0].font.as_swash();
+        let metrics = font.metrics(&[]);
+        FontMetrics {
+            units_per_em: metrics.units_per_em as u32,
+            ascent: metrics.ascent,
+            // swash measures the descent downwards from the baseline.
+            descent: -metrics.descent,
+            line_gap: metrics.leading,
+            underline_position: metrics.underline_offset,
+            underline_thickness: metrics.stroke_size,
+            cap_height: metrics.cap_height,
+            x_height: metrics.x_height,
+            bounding_box: head_table_bounds(font.table(tag_from_bytes(b"head")))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn typographic_bounds(&self, font_id: FontId, glyph_id: GlyphId) -> Result<Bounds<f32>> {
+        self.0.write().typographic_bounds(font_id, glyph_id)
+    }
+
+    fn advance(&self, font_id: FontId, glyph_id: GlyphId) -> Result<Size<f32>> {
+        let lock = self.0.read();
+        let glyph_metrics = lock.fonts[font_id.0].font.as_swash().glyph_metrics(&[]);
+        Ok(size(glyph_metrics.advance_width(glyph_id.0 as u16), 0.))
+    }
+
+    fn glyph_for_char(&self, font_id: FontId, ch: char) -> Option<GlyphId> {
+        let glyph_id = self.0.read().fonts[font_id.0]
+            .font
+            .as_swash()
+            .charmap()
+            .map(ch);
+        if glyph_id == 0 {
+            None
+        } else {
+            Some(GlyphId(glyph_id.into()))
+        }
+    }
+
+    fn glyph_raster_bounds(&self, params: &RenderGlyphParams) -> Result<Bounds<DevicePixels>> {
+        self.0.write().raster_bounds(params)
+    }
+
+    fn rasterize_glyph(
+        &self,
+        params: &RenderGlyphParams,
+        raster_bounds: Bounds<DevicePixels>,
+    ) -> Result<(Size<DevicePixels>, Vec<u8>)> {
+        self.0.write().rasterize_glyph(params, raster_bounds)
+    }
+
+    fn layout_line(&self, text: &str, font_size: Pixels, font_runs: &[FontRun]) -> LineLayout {
+        self.0.write().layout_line(text, font_size, font_runs)
+    }
+
+    fn wrap_line(
+        &self,
+        text: &str,
+        font_id: FontId,
+        font_size: Pixels,
+        width: Pixels,
+    ) -> Vec<usize> {
+        self.0.write().wrap_line(text, font_id, font_size, width)
+    }
+}
+
+impl CosmicTextSystemState {
+    fn font_id_for_database_id(&mut self, database_id: fontdb::ID) -> Result<FontId> {
+        if let Some(font_id) = self.font_ids_by_database_id.get(&database_id) {
+            return Ok(*font_id);
+        }
+
+        let font = self
+            .font_system
+            .get_font(database_id)
+            .ok_or_else(|| anyhow!("failed to load font {database_id:?}"))?;
+        // Color glyphs are stored as layers (COLR), bitmaps (CBDT) or images (sbix).
+        let is_emoji = [b"COLR", b"CBDT", b"sbix"]
+            .into_iter()
+            .any(|tag| font.as_swash().table(tag_from_bytes(tag)).is_some());
+        let font_id = FontId(self.fonts.len());
+        self.fonts.push(LoadedFont { font, is_emoji });
+        self.font_ids_by_database_id.insert(database_id, font_id);
+        Ok(font_id)
+    }
+
+    fn attrs_list(&self, text_len: usize, font_runs: &[FontRun]) -> AttrsList {
+        let mut attrs_list = AttrsList::new(Attrs::new());
+        let mut offset = 0;
+        for run in font_runs {
+            let database_id = self.fonts[run.font_id.0].font.id();
+            if let Some(face) = self.font_system.db().face(database_id) {
+                if let Some((family, _)) = face.families.first() {
+                    let end = (offset + run.len).min(text_len);
+                    attrs_list.add_span(
+                        offset..end,
+                        Attrs::new()
+                            .family(Family::Name(family))
+                            .weight(face.weight)
+                            .style(face.style)
+                            .stretch(face.stretch),
+                    );
+                }
+            }
+            offset += run.len;
+        }
+        attrs_list
+    }
+
+    fn typographic_bounds(&mut self, font_id: FontId, glyph_id: GlyphId) -> Result<Bounds<f32>> {
+        let font = self.fonts[font_id.0].font.as_swash();
+        // Without a size, the scaler produces outlines in font units.
+        let mut scaler = self.scale_context.builder(font).build();
+        let Some(outline) = scaler.scale_outline(glyph_id.0 as u16) else {
+            return Ok(Bounds::default());
+        };
+        let bounds = outline.bounds();
+        Ok(Bounds {
+            origin: point(bounds.min.x, bounds.min.y),
+            size: size(bounds.width(), bounds.height()),
+        })
+    }
+
+    fn render_glyph(&mut self, params: &RenderGlyphParams, offset: Vector) -> Option<Image> {
+        let font = self.fonts[params.font_id.0].font.as_swash();
+        let mut scaler = self
+            .scale_context
+            .builder(font)
+            .size(f32::from(params.font_size) * params.scale_factor)
+            .hint(false)
+            .build();
+        Render::new(&[
+            Source::ColorOutline(0),
+            Source::ColorBitmap(StrikeWith::BestFit),
+            Source::Outline,
+        ])
+        .format(Format::Alpha)
+        .offset(offset)
+        .render(&mut scaler, params.glyph_id.0 as u16)
+    }
+
+    fn raster_bounds(&mut self, params: &RenderGlyphParams) -> Result<Bounds<DevicePixels>> {
+        let Some(image) = self.render_glyph(params, Vector::new(0., 0.)) else {
+            return Ok(Bounds::default());
+        };
+        Ok(Bounds {
+            origin: point(
+                DevicePixels(image.placement.left),
+                DevicePixels(-image.placement.top),
+            ),
+            size: size(
+                DevicePixels(image.placement.width as i32),
+                DevicePixels(image.placement.height as i32),
+            ),
+        })
+    }
+
+    fn rasterize_glyph(
+        &mut self,
+        params: &RenderGlyphParams,
+        glyph_bounds: Bounds<DevicePixels>,
+    ) -> Result<(Size<DevicePixels>, Vec<u8>)> {
+        if glyph_bounds.size.width.0 == 0 || glyph_bounds.size.height.0 == 0 {
+            return Err(anyhow!("glyph bounds are empty"));
+        }
+
+        // Add an extra pixel when the subpixel variant isn't zero to make room for anti-aliasing.
+        let mut bitmap_size = glyph_bounds.size;
+        if params.subpixel_variant.x > 0 {
+            bitmap_size.width += DevicePixels(1);
+        }
+        if params.subpixel_variant.y > 0 {
+            bitmap_size.height += DevicePixels(1);
+        }
+
+        let subpixel_shift = params
+            .subpixel_variant
+            .map(|v| v as f32 / SUBPIXEL_VARIANTS as f32);
+        let image = self
+            .render_glyph(params, Vector::new(subpixel_shift.x, subpixel_shift.y))
+            .ok_or_else(|| anyhow!("failed to render glyph {:?}", params.glyph_id))?;
+
+        // The shifted glyph may be placed differently than the unshifted one, so
+        // copy it into a bitmap covering the raster bounds.
+        let bytes_per_pixel = if params.is_emoji { 4 } else { 1 };
+        let width = bitmap_size.width.0 as usize;
+        let height = bitmap_size.height.0 as usize;
+        let mut bytes = vec![0; width * height * bytes_per_pixel];
+        let image_bytes_per_pixel = match image.content {
+            Content::Mask => 1,
+            Content::SubpixelMask | Content::Color => 4,
+        };
+        let left = image.placement.left - glyph_bounds.origin.x.0;
+        let top = -image.placement.top - glyph_bounds.origin.y.0;
+        for image_row in 0..image.placement.height as i32 {
+            let row = top + image_row;
+            if row < 0 || row as usize >= height {
+                continue;
+            }
+            for image_column in 0..image.placement.width as i32 {
+                let column = left + image_column;
+                if column < 0 || column as usize >= width {
+                    continue;
+                }
+
+                let source = (image_row as usize * image.placement.width as usize
+                    + image_column as usize)
+                    * image_bytes_per_pixel;
+                let source = &image.data[source..source + image_bytes_per_pixel];
+                let target = (row as usize * width + column as usize) * bytes_per_pixel;
+                let target = &mut bytes[target..target + bytes_per_pixel];
+                match (image_bytes_per_pixel, bytes_per_pixel) {
+                    // Emoji are stored as BGRA, with straight alpha.
+                    (4, 4) => target.copy_from_slice(&[source[2], source[1], source[0], source[3]]),
+                    (1, 4) => target.copy_from_slice(&[255, 255, 255, source[0]]),
+                    _ => target[0] = source[image_bytes_per_pixel - 1],
+                }
+            }
+        }
+
+        Ok((bitmap_size, bytes))
+    }
+
+    fn layout_line(&mut self, text: &str, font_size: Pixels, font_runs: &[FontRun]) -> LineLayout {
+        let attrs_list = self.attrs_list(text.len(), font_runs);
+        let mut line = BufferLine::new(text, attrs_list, Shaping::Advanced);
+        let layout_lines = line.layout(
+            &mut self.font_system,
+            f32::from(font_size),
+            f32::MAX,
+            Wrap::None,
+        );
+        let Some(layout) = layout_lines.first() else {
+            return LineLayout {
+                font_size,
+                len: text.len(),
+                ..Default::default()
+            };
+        };
+
+        // cosmic-text picks a font for every glyph, falling back to other fonts
+        // for the characters the requested font can't display. Group consecutive
+        // glyphs from the same font into runs.
+        let mut runs = Vec::<ShapedRun>::new();
+        for glyph in &layout.glyphs {
+            let Ok(font_id) = self.font_id_for_database_id(glyph.font_id) else {
+                continue;
+            };
+            let shaped_glyph = ShapedGlyph {
+                id: GlyphId(glyph.glyph_id as u32),
+                position: point(px(glyph.x), px(glyph.y)),
+                index: glyph.start,
+                is_emoji: self.fonts[font_id.0].is_emoji,
+            };
+            match runs.last_mut() {
+                Some(run) if run.font_id == font_id => run.glyphs.push(shaped_glyph),
+                _ => runs.push(ShapedRun {
+                    font_id,
+                    glyphs: smallvec![shaped_glyph],
+                }),
+            }
+        }
+
+        LineLayout {
+            font_size,
+            width: px(layout.w),
+            ascent: px(layout.max_ascent),
+            descent: px(layout.max_descent),
+            runs,
+            len: text.len(),
+        }
+    }
+
+    fn wrap_line(
+        &mut self,
+        text: &str,
+        font_id: FontId,
+        font_size: Pixels,
+        width: Pixels,
+    ) -> Vec<usize> {
+        let attrs_list = self.attrs_list(
+            text.len(),
+            &[FontRun {
+                font_id,
+                len: text.len(),
+            }],
+        );
+        let mut line = BufferLine::new(text, attrs_list, Shaping::Advanced);
+        let layout_lines = line.layout(
+            &mut self.font_system,
+            f32::from(font_size),
+            f32::from(width),
+            Wrap::Word,
+        );
+
+        // Every wrapped line starts at its leftmost character in logical order.
+        layout_lines
+            .iter()
+            .skip(1)
+            .filter_map(|line| line.glyphs.iter().map(|glyph| glyph.start).min())
+            .collect()
+    }
+}
+
+/// Reads the bounding box of all the font's glyphs from its `head` table.
+fn head_table_bounds(head: Option<&[u8]>) -> Option<Bounds<f32>> {
+    let read = |offset: usize| -> Option<f32> {
+        let bytes = head?.get(offset..offset + 2)?;
+        Some(i16::from_be_bytes([bytes[0], bytes[1]]) as f32)
+    };
+    let (x_min, y_min, x_max, y_max) = (read(36)?, read(38)?, read(40)?, read(42)?);
+    Some(Bounds {
+        origin: point(x_min, y_min),
+        size: size(x_max - x_min, y_max - y_min),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{font, FontWeight};
+
+    #[test]
+    fn test_font_id() {
+        let text_system = CosmicTextSystem::with_bundled_fonts();
+        assert_eq!(text_system.all_font_families(), ["Zed Mono", "Zed Sans"]);
+
+        let regular = text_system.font_id(&font("Zed Mono")).unwrap();
+        let bold = text_system.font_id(&font("Zed Mono").bold()).unwrap();
+        assert_ne!(regular, bold);
+        assert_eq!(text_system.font_id(&font("Zed Mono")).unwrap(), regular);
+
+        // The closest weight is used when the requested one doesn't exist.
+        let mut semibold = font("Zed Mono");
+        semibold.weight = FontWeight::SEMIBOLD;
+        assert_eq!(text_system.font_id(&semibold).unwrap(), bold);
+
+        assert!(text_system.font_id(&font("Helvetica")).is_err());
+    }
+
+    #[test]
+    fn test_layout_line() {
+        let text_system = CosmicTextSystem::with_bundled_fonts();
+        let mono = text_system.font_id(&font("Zed Mono")).unwrap();
+        let sans = text_system.font_id(&font("Zed Sans")).unwrap();
+        let glyph_id = text_system.glyph_for_char(mono, 'a').unwrap();
+        let units_per_em = text_system.font_metrics(mono).units_per_em as f32;
+        let advance = text_system.advance(mono, glyph_id).unwrap().width / units_per_em * 16.;
+
+        let text = "abc def";
+        let layout = text_system.layout_line(
+            text,
+            px(16.),
+            &[
+                FontRun {
+                    font_id: mono,
+                    len: 4,
+                },
+                FontRun {
+                    font_id: sans,
+                    len: 3,
+                },
+            ],
+        );
+        assert_eq!(layout.len, text.len());
+        assert_eq!(layout.runs.len(), 2);
+        assert_eq!(layout.runs[0].font_id, mono);
+        assert_eq!(
+            layout.runs[0]
+                .glyphs
+                .iter()
+                .map(|glyph| glyph.index)
+                .collect::<Vec<_>>(),
+            [0, 1, 2, 3]
+        );
+        assert_eq!(layout.runs[0].glyphs[0].id, glyph_id);
+        assert!((f32::from(layout.runs[0].glyphs[1].position.x) - advance).abs() < 0.01);
+        assert_eq!(layout.runs[1].font_id, sans);
+        assert_eq!(layout.runs[1].glyphs[0].index, 4);
+        assert!(layout.width > px(advance * 4.));
+        assert!(layout.ascent > px(0.) && layout.descent > px(0.));
+    }
+
+    #[test]
+    fn test_wrap_line() {
+        let text_system = CosmicTextSystem::with_bundled_fonts();
+        let font_id = text_system.font_id(&font("Zed Mono")).unwrap();
+        let glyph_id = text_system.glyph_for_char(font_id, 'm').unwrap();
+        let units_per_em = text_system.font_metrics(font_id).units_per_em as f32;
+        let advance = text_system.advance(font_id, glyph_id).unwrap().width / units_per_em * 16.;
+
+        // Nine and a half characters fit on each line.
+        let wrap_boundaries = text_system.wrap_line(
+            "one two three four five",
+            font_id,
+            px(16.),
+            px(advance * 9.5),
+        );
+        assert_eq!(wrap_boundaries, &["one two ".len(), "one two three ".len()]);
+    }
+
+    #[test]
+    fn test_rasterize_glyph() {
+        let text_system = CosmicTextSystem::with_bundled_fonts();
+        let font_id = text_system.font_id(&font("Zed Mono")).unwrap();
+        let mut params = RenderGlyphParams {
+            font_id,
+            glyph_id: text_system.glyph_for_char(font_id, 'x').unwrap(),
+            font_size: px(16.),
+            subpixel_variant: point(0, 0),
+            scale_factor: 2.,
+            is_emoji: false,
+        };
+
+        let bounds = text_system.glyph_raster_bounds(&params).unwrap();
+        assert!(bounds.size.width.0 > 0 && bounds.size.height.0 > 0);
+        let (bitmap_size, bytes) = text_system.rasterize_glyph(&params, bounds).unwrap();
+        assert_eq!(bitmap_size, bounds.size);
+        assert_eq!(
+            bytes.len(),
+            (bitmap_size.width.0 * bitmap_size.height.0) as usize
+        );
+        assert!(bytes.iter().any(|alpha| *alpha > 0));
+
+        // Shifted glyphs get an extra column for the anti-aliased edge.
+        params.subpixel_variant = point(2, 0);
+        let (bitmap_size, bytes) = text_system.rasterize_glyph(&params, bounds).unwrap();
+        assert_eq!(bitmap_size.width, bounds.size.width + DevicePixels(1));
+        assert_eq!(
+            bytes.len(),
+            (bitmap_size.width.0 * bitmap_size.height.0) as usize
+        );
+    }
+}
@@ -0,0 +1,13 @@
+//! A platform for running apps without a display, e.g. to script them from
+//! the command line. Tasks run on real threads like they would on any other
+//! platform, and windows are kept in memory and never shown.
+
+mod dispatcher;
+mod display;
+mod platform;
+mod window;
+
+pub(crate) use dispatcher::*;
+pub(crate) use display::*;
+pub(crate) use platform::*;
+pub(crate) use window::*;
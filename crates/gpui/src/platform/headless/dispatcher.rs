@@ -0,0 +1,181 @@
+use crate::{PlatformDispatcher, TaskLabel};
+use async_task::Runnable;
+use collections::BTreeMap;
+use parking::{Parker, Unparker};
+use parking_lot::Mutex;
+use std::{
+    mem,
+    sync::Arc,
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+/// Sends main thread tasks to a channel that the platform drains in its run
+/// loop, and runs background tasks and timers on threads of their own.
+pub(crate) struct HeadlessDispatcher {
+    parker: Mutex<Parker>,
+    main_thread_id: ThreadId,
+    main_sender: flume::Sender<Runnable>,
+    background_sender: flume::Sender<Runnable>,
+    timer_sender: flume::Sender<(Instant, Runnable)>,
+    activity: Arc<Activity>,
+    _background_threads: Vec<thread::JoinHandle<()>>,
+    _timer_thread: thread::JoinHandle<()>,
+}
+
+impl HeadlessDispatcher {
+    /// Creates a dispatcher whose main thread is the current thread, along
+    /// with the receiving end of its main thread tasks.
+    pub(crate) fn new() -> (Self, flume::Receiver<Runnable>) {
+        let (main_sender, main_receiver) = flume::unbounded::<Runnable>();
+
+        let activity = Arc::new(Activity::default());
+        let (background_sender, background_receiver) = flume::unbounded::<Runnable>();
+        let background_threads = (0..num_cpus::get())
+            .map(|i| {
+                let receiver = background_receiver.clone();
+                let activity = activity.clone();
+                thread::Builder::new()
+                    .name(format!("background-{i}"))
+                    .spawn(move || {
+                        for runnable in receiver {
+                            activity.run(runnable);
+                        }
+                    })
+                    .expect("failed to spawn a background thread")
+            })
+            .collect();
+
+        let (timer_sender, timer_receiver) = flume::unbounded::<(Instant, Runnable)>();
+        let timer_thread = thread::Builder::new()
+            .name("timers".into())
+            .spawn(move || run_timers(timer_receiver))
+            .expect("failed to spawn the timer thread");
+
+        let dispatcher = Self {
+            parker: Mutex::new(Parker::new()),
+            main_thread_id: thread::current().id(),
+            main_sender,
+            background_sender,
+            timer_sender,
+            activity,
+            _background_threads: background_threads,
+            _timer_thread: timer_thread,
+        };
+        (dispatcher, main_receiver)
+    }
+
+    /// Runs a task received from the main thread channel.
+    pub(crate) fn run_on_main_thread(&self, runnable: Runnable) {
+        self.activity.run(runnable);
+    }
+}
+
+/// Keeps count of the tasks that are queued or running, to tell when the app
+/// has nothing left to do. Timers only count once they fire.
+#[derive(Default)]
+struct Activity(Mutex<ActivityState>);
+
+#[derive(Default)]
+struct ActivityState {
+    tasks: usize,
+    idle_callbacks: Vec<Box<dyn FnOnce() + Send>>,
+}
+
+impl Activity {
+    fn task_queued(&self) {
+        self.0.lock().tasks += 1;
+    }
+
+    fn run(&self, runnable: Runnable) {
+        runnable.run();
+        let mut state = self.0.lock();
+        state.tasks -= 1;
+        let idle_callbacks = if state.tasks == 0 {
+            mem::take(&mut state.idle_callbacks)
+        } else {
+            Vec::new()
+        };
+        drop(state);
+        for callback in idle_callbacks {
+            callback();
+        }
+    }
+
+    fn on_idle(&self, callback: Box<dyn FnOnce() + Send>) {
+        let mut state = self.0.lock();
+        if state.tasks == 0 {
+            drop(state);
+            callback();
+        } else {
+            state.idle_callbacks.push(callback);
+        }
+    }
+}
+
+fn run_timers(receiver: flume::Receiver<(Instant, Runnable)>) {
+    let mut timers = BTreeMap::<(Instant, usize), Runnable>::default();
+    let mut next_id = 0;
+    loop {
+        let received = match timers.keys().next() {
+            Some(&(deadline, _)) => receiver.recv_deadline(deadline),
+            None => receiver
+                .recv()
+                .map_err(|_| flume::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok((deadline, runnable)) => {
+                timers.insert((deadline, next_id), runnable);
+                next_id += 1;
+            }
+            Err(flume::RecvTimeoutError::Timeout) => {}
+            Err(flume::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        while let Some(entry) = timers.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            entry.remove().run();
+        }
+    }
+}
+
+impl PlatformDispatcher for HeadlessDispatcher {
+    fn is_main_thread(&self) -> bool {
+        thread::current().id() == self.main_thread_id
+    }
+
+    fn dispatch(&self, runnable: Runnable, _: Option<TaskLabel>) {
+        self.activity.task_queued();
+        self.background_sender.send(runnable).ok();
+    }
+
+    fn dispatch_on_main_thread(&self, runnable: Runnable) {
+        self.activity.task_queued();
+        self.main_sender.send(runnable).ok();
+    }
+
+    fn dispatch_after(&self, duration: Duration, runnable: Runnable) {
+        self.timer_sender
+            .send((Instant::now() + duration, runnable))
+            .ok();
+    }
+
+    fn tick(&self, _background_only: bool) -> bool {
+        false
+    }
+
+    fn park(&self) {
+        self.parker.lock().park()
+    }
+
+    fn unparker(&self) -> Unparker {
+        self.parker.lock().unparker()
+    }
+
+    fn on_idle(&self, callback: Box<dyn FnOnce() + Send>) {
+        self.activity.on_idle(callback);
+    }
+}
@@ -0,0 +1,35 @@
+use crate::{Bounds, DisplayId, GlobalPixels, PlatformDisplay, Point};
+use anyhow::Result;
+use uuid::Uuid;
+
+/// The only display of the headless platform. Its size only matters for
+/// laying out windows that don't ask for one.
+#[derive(Debug)]
+pub(crate) struct HeadlessDisplay {
+    bounds: Bounds<GlobalPixels>,
+}
+
+impl HeadlessDisplay {
+    pub(crate) fn new() -> Self {
+        Self {
+            bounds: Bounds::from_corners(
+                Point::default(),
+                Point::new(GlobalPixels(1920.), GlobalPixels(1080.)),
+            ),
+        }
+    }
+}
+
+impl PlatformDisplay for HeadlessDisplay {
+    fn id(&self) -> DisplayId {
+        DisplayId(0)
+    }
+
+    fn uuid(&self) -> Result<Uuid> {
+        Ok(Uuid::nil())
+    }
+
+    fn bounds(&self) -> Bounds<GlobalPixels> {
+        self.bounds
+    }
+}
@@ -0,0 +1,335 @@
+use super::{HeadlessDispatcher, HeadlessDisplay, HeadlessWindow, HeadlessWindowState};
+use crate::{
+    Action, AnyWindowHandle, BackgroundExecutor, ClipboardItem, CursorStyle, DisplayId,
+    ForegroundExecutor, Keymap, Menu, PathPromptOptions, Platform, PlatformDisplay, PlatformInput,
+    PlatformTextSystem, PlatformWindow, SemanticVersion, Task, WindowOptions,
+};
+use anyhow::{anyhow, Context as _, Result};
+use async_task::Runnable;
+use futures::channel::oneshot;
+use std::{
+    cell::RefCell,
+    env,
+    path::{Path, PathBuf},
+    rc::{Rc, Weak},
+    sync::Arc,
+    time::Duration,
+};
+use time::UtcOffset;
+
+/// A platform without a display, for running apps from scripts and CI.
+pub(crate) struct HeadlessPlatform {
+    background_executor: BackgroundExecutor,
+    foreground_executor: ForegroundExecutor,
+    text_system: Arc<dyn PlatformTextSystem>,
+    dispatcher: Arc<HeadlessDispatcher>,
+    main_receiver: flume::Receiver<Runnable>,
+    display: Rc<HeadlessDisplay>,
+    state: RefCell<HeadlessPlatformState>,
+}
+
+struct HeadlessPlatformState {
+    quitting: bool,
+    windows: Vec<Weak<HeadlessWindowState>>,
+    quit_callbacks: Vec<Box<dyn FnMut()>>,
+    clipboard: Option<ClipboardItem>,
+}
+
+impl Default for HeadlessPlatform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HeadlessPlatform {
+    pub(crate) fn new() -> Self {
+        let (dispatcher, main_receiver) = HeadlessDispatcher::new();
+        let dispatcher = Arc::new(dispatcher);
+
+        #[cfg(target_os = "macos")]
+        let text_system = Arc::new(crate::platform::mac::MacTextSystem::new());
+        #[cfg(not(target_os = "macos"))]
+        let text_system = Arc::new(crate::platform::cosmic_text::CosmicTextSystem::new());
+
+        Self {
+            background_executor: BackgroundExecutor::new(dispatcher.clone()),
+            foreground_executor: ForegroundExecutor::new(dispatcher.clone()),
+            text_system,
+            dispatcher,
+            main_receiver,
+            display: Rc::new(HeadlessDisplay::new()),
+            state: RefCell::new(HeadlessPlatformState {
+                quitting: false,
+                windows: Vec::new(),
+                quit_callbacks: Vec::new(),
+                clipboard: None,
+            }),
+        }
+    }
+
+    fn windows(&self) -> Vec<Rc<HeadlessWindowState>> {
+        let mut state = self.state.borrow_mut();
+        state.windows.retain(|window| window.strong_count() > 0);
+        state
+            .windows
+            .iter()
+            .filter_map(|window| window.upgrade())
+            .collect()
+    }
+}
+
+impl Platform for HeadlessPlatform {
+    fn background_executor(&self) -> BackgroundExecutor {
+        self.background_executor.clone()
+    }
+
+    fn foreground_executor(&self) -> ForegroundExecutor {
+        self.foreground_executor.clone()
+    }
+
+    fn text_system(&self) -> Arc<dyn PlatformTextSystem> {
+        self.text_system.clone()
+    }
+
+    fn run(&self, on_finish_launching: Box<dyn FnOnce()>) {
+        on_finish_launching();
+
+        while !self.state.borrow().quitting {
+            // Windows draw in between tasks, so that each task sees the views
+            // changed by the previous ones laid out.
+            for window in self.windows() {
+                window.request_frame();
+            }
+            let Ok(runnable) = self.main_receiver.recv() else {
+                break;
+            };
+            self.dispatcher.run_on_main_thread(runnable);
+        }
+
+        let mut quit_callbacks = std::mem::take(&mut self.state.borrow_mut().quit_callbacks);
+        for callback in &mut quit_callbacks {
+            callback();
+        }
+    }
+
+    fn quit(&self) {
+        self.state.borrow_mut().quitting = true;
+    }
+
+    fn restart(&self) {}
+
+    fn activate(&self, _ignoring_other_apps: bool) {}
+
+    fn hide(&self) {}
+
+    fn hide_other_apps(&self) {}
+
+    fn unhide_other_apps(&self) {}
+
+    fn displays(&self) -> Vec<Rc<dyn PlatformDisplay>> {
+        vec![self.display.clone() as Rc<dyn PlatformDisplay>]
+    }
+
+    fn display(&self, id: DisplayId) -> Option<Rc<dyn PlatformDisplay>> {
+        (self.display.id() == id).then(|| self.display.clone() as Rc<dyn PlatformDisplay>)
+    }
+
+    fn active_window(&self) -> Option<AnyWindowHandle> {
+        self.windows().last().map(|window| window.handle())
+    }
+
+    fn open_window(
+        &self,
+        handle: AnyWindowHandle,
+        options: WindowOptions,
+    ) -> Box<dyn PlatformWindow> {
+        let window = HeadlessWindow::new(handle, options, self.display.clone());
+        self.state
+            .borrow_mut()
+            .windows
+            .push(Rc::downgrade(&window.0));
+        Box::new(window)
+    }
+
+    // Nothing is ever displayed, so there are no frames to wait for.
+    fn set_display_link_output_callback(
+        &self,
+        _display_id: DisplayId,
+        _callback: Box<dyn FnMut() + Send>,
+    ) {
+    }
+
+    fn start_display_link(&self, _display_id: DisplayId) {}
+
+    fn stop_display_link(&self, _display_id: DisplayId) {}
+
+    fn open_url(&self, url: &str) {
+        log::info!("not opening {url} without a display");
+    }
+
+    fn on_open_urls(&self, _callback: Box<dyn FnMut(Vec<String>)>) {}
+
+    fn prompt_for_paths(
+        &self,
+        _options: PathPromptOptions,
+    ) -> oneshot::Receiver<Option<Vec<PathBuf>>> {
+        let (done_tx, done_rx) = oneshot::channel();
+        done_tx.send(None).ok();
+        done_rx
+    }
+
+    fn prompt_for_new_path(&self, _directory: &Path) -> oneshot::Receiver<Option<PathBuf>> {
+        let (done_tx, done_rx) = oneshot::channel();
+        done_tx.send(None).ok();
+        done_rx
+    }
+
+    fn reveal_path(&self, _path: &Path) {}
+
+    fn on_become_active(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_resign_active(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_quit(&self, callback: Box<dyn FnMut()>) {
+        self.state.borrow_mut().quit_callbacks.push(callback);
+    }
+
+    fn on_reopen(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_event(&self, _callback: Box<dyn FnMut(PlatformInput) -> bool>) {}
+
+    fn set_menus(&self, _menus: Vec<Menu>, _keymap: &Keymap) {}
+
+    fn on_app_menu_action(&self, _callback: Box<dyn FnMut(&dyn Action)>) {}
+
+    fn on_will_open_app_menu(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_validate_app_menu_command(&self, _callback: Box<dyn FnMut(&dyn Action) -> bool>) {}
+
+    fn os_name(&self) -> &'static str {
+        env::consts::OS
+    }
+
+    fn os_version(&self) -> Result<SemanticVersion> {
+        Err(anyhow!("os version is not available without a display"))
+    }
+
+    fn app_version(&self) -> Result<SemanticVersion> {
+        Err(anyhow!("app version is not available without a display"))
+    }
+
+    fn app_path(&self) -> Result<PathBuf> {
+        env::current_exe().context("failed to get the path of the executable")
+    }
+
+    fn local_timezone(&self) -> UtcOffset {
+        UtcOffset::UTC
+    }
+
+    fn double_click_interval(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+
+    fn path_for_auxiliary_executable(&self, name: &str) -> Result<PathBuf> {
+        let path = self
+            .app_path()?
+            .parent()
+            .ok_or_else(|| anyhow!("executable has no parent directory"))?
+            .join(name);
+        if path.exists() {
+            Ok(path)
+        } else {
+            Err(anyhow!("resource not found"))
+        }
+    }
+
+    fn set_cursor_style(&self, _style: CursorStyle) {}
+
+    fn should_auto_hide_scrollbars(&self) -> bool {
+        true
+    }
+
+    // The clipboard is private to the app, so that scripts don't clobber the
+    // clipboard of whoever runs them.
+    fn write_to_clipboard(&self, item: ClipboardItem) {
+        self.state.borrow_mut().clipboard = Some(item);
+    }
+
+    fn read_from_clipboard(&self) -> Option<ClipboardItem> {
+        self.state.borrow().clipboard.clone()
+    }
+
+    fn write_credentials(&self, _url: &str, _username: &str, _password: &[u8]) -> Task<Result<()>> {
+        Task::ready(Err(anyhow!(
+            "storing credentials is not supported without a display"
+        )))
+    }
+
+    fn read_credentials(&self, _url: &str) -> Task<Result<Option<(String, Vec<u8>)>>> {
+        Task::ready(Ok(None))
+    }
+
+    fn delete_credentials(&self, _url: &str) -> Task<Result<()>> {
+        Task::ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        self as gpui, actions, div, App, FocusHandle, InteractiveElement as _, IntoElement, Render,
+        ViewContext, VisualContext as _, WindowOptions,
+    };
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    actions!(headless, [Ping]);
+
+    struct PingView {
+        focus_handle: FocusHandle,
+        pings: Rc<Cell<usize>>,
+    }
+
+    impl Render for PingView {
+        fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+            div()
+                .track_focus(&self.focus_handle)
+                .on_action(cx.listener(|this, _: &Ping, _| {
+                    this.pings.set(this.pings.get() + 1);
+                }))
+        }
+    }
+
+    #[test]
+    fn test_headless_app() {
+        let pings = Rc::new(Cell::new(0));
+        App::headless().run({
+            let pings = pings.clone();
+            move |cx| {
+                let window = cx.open_window(WindowOptions::default(), |cx| {
+                    cx.new_view(|cx| {
+                        let focus_handle = cx.focus_handle();
+                        cx.focus(&focus_handle);
+                        PingView {
+                            focus_handle,
+                            pings,
+                        }
+                    })
+                });
+                cx.spawn(|mut cx| async move {
+                    cx.background_executor()
+                        .timer(Duration::from_millis(10))
+                        .await;
+                    window
+                        .update(&mut cx, |_, cx| cx.dispatch_action(Box::new(Ping)))
+                        .unwrap();
+                    cx.update(|cx| cx.quit()).unwrap();
+                })
+                .detach();
+            }
+        });
+
+        // The action reached the view, because the window was drawn before
+        // it was dispatched, and the app stopped running once it quit.
+        assert_eq!(pings.get(), 1);
+    }
+}
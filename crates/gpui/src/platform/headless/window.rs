@@ -0,0 +1,190 @@
+use crate::{
+    px, AnyWindowHandle, Modifiers, Pixels, PlatformAtlas, PlatformDisplay, PlatformInput,
+    PlatformInputHandler, PlatformWindow, Point, PromptLevel, Scene, Size, SoftwareAtlas,
+    WindowAppearance, WindowBounds, WindowOptions,
+};
+use futures::channel::oneshot;
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
+};
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    rc::Rc,
+    sync::Arc,
+};
+
+pub(crate) struct HeadlessWindowState {
+    handle: AnyWindowHandle,
+    bounds: WindowBounds,
+    display: Rc<dyn PlatformDisplay>,
+    sprite_atlas: Arc<SoftwareAtlas>,
+    input_handler: Cell<Option<PlatformInputHandler>>,
+    request_frame_callback: RefCell<Option<Box<dyn FnMut()>>>,
+}
+
+/// A window that is never shown. It still lays out and paints its views
+/// whenever they change, so that actions can be dispatched to them.
+pub(crate) struct HeadlessWindow(pub(crate) Rc<HeadlessWindowState>);
+
+impl HeadlessWindowState {
+    pub(crate) fn handle(&self) -> AnyWindowHandle {
+        self.handle
+    }
+
+    /// Lets the window draw a new frame, if any of its views changed.
+    pub(crate) fn request_frame(&self) {
+        let Some(mut callback) = self.request_frame_callback.borrow_mut().take() else {
+            return;
+        };
+        callback();
+        self.request_frame_callback
+            .borrow_mut()
+            .get_or_insert(callback);
+    }
+}
+
+impl HeadlessWindow {
+    pub(crate) fn new(
+        handle: AnyWindowHandle,
+        options: WindowOptions,
+        display: Rc<dyn PlatformDisplay>,
+    ) -> Self {
+        Self(Rc::new(HeadlessWindowState {
+            handle,
+            bounds: options.bounds,
+            display,
+            sprite_atlas: Arc::new(SoftwareAtlas::new()),
+            input_handler: Cell::new(None),
+            request_frame_callback: RefCell::new(None),
+        }))
+    }
+}
+
+impl HasWindowHandle for HeadlessWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        Err(HandleError::Unavailable)
+    }
+}
+
+impl HasDisplayHandle for HeadlessWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        Err(HandleError::Unavailable)
+    }
+}
+
+impl PlatformWindow for HeadlessWindow {
+    fn bounds(&self) -> WindowBounds {
+        self.0.bounds
+    }
+
+    fn content_size(&self) -> Size<Pixels> {
+        let bounds = match self.0.bounds {
+            WindowBounds::Fixed(bounds) => bounds,
+            WindowBounds::Maximized | WindowBounds::Fullscreen => self.0.display.bounds(),
+        };
+        bounds.size.map(|size| px(size.0))
+    }
+
+    fn scale_factor(&self) -> f32 {
+        1.0
+    }
+
+    fn titlebar_height(&self) -> Pixels {
+        px(0.)
+    }
+
+    fn appearance(&self) -> WindowAppearance {
+        WindowAppearance::Light
+    }
+
+    fn display(&self) -> Rc<dyn PlatformDisplay> {
+        self.0.display.clone()
+    }
+
+    fn mouse_position(&self) -> Point<Pixels> {
+        Point::default()
+    }
+
+    fn modifiers(&self) -> Modifiers {
+        Modifiers::default()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_input_handler(&mut self, input_handler: PlatformInputHandler) {
+        self.0.input_handler.set(Some(input_handler));
+    }
+
+    fn take_input_handler(&mut self) -> Option<PlatformInputHandler> {
+        self.0.input_handler.take()
+    }
+
+    // There's nobody to answer prompts, so they're answered with the last
+    // option, which is the one that cancels by convention.
+    fn prompt(
+        &self,
+        level: PromptLevel,
+        msg: &str,
+        detail: Option<&str>,
+        answers: &[&str],
+    ) -> oneshot::Receiver<usize> {
+        let answer = answers.len().saturating_sub(1);
+        log::warn!(
+            "answering {level:?} prompt {msg:?} ({}) with {:?}",
+            detail.unwrap_or_default(),
+            answers.get(answer)
+        );
+        let (done_tx, done_rx) = oneshot::channel();
+        done_tx.send(answer).ok();
+        done_rx
+    }
+
+    fn activate(&self) {}
+
+    fn set_title(&mut self, _title: &str) {}
+
+    fn set_edited(&mut self, _edited: bool) {}
+
+    fn show_character_palette(&self) {}
+
+    fn minimize(&self) {}
+
+    fn zoom(&self) {}
+
+    fn toggle_full_screen(&self) {}
+
+    fn on_request_frame(&self, callback: Box<dyn FnMut()>) {
+        *self.0.request_frame_callback.borrow_mut() = Some(callback);
+    }
+
+    // Headless windows never receive input or change their bounds, so the
+    // callbacks for those are never called.
+    fn on_input(&self, _callback: Box<dyn FnMut(PlatformInput) -> bool>) {}
+
+    fn on_active_status_change(&self, _callback: Box<dyn FnMut(bool)>) {}
+
+    fn on_resize(&self, _callback: Box<dyn FnMut(Size<Pixels>, f32)>) {}
+
+    fn on_fullscreen(&self, _callback: Box<dyn FnMut(bool)>) {}
+
+    fn on_moved(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_should_close(&self, _callback: Box<dyn FnMut() -> bool>) {}
+
+    fn on_close(&self, _callback: Box<dyn FnOnce()>) {}
+
+    fn on_appearance_changed(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn is_topmost_for_position(&self, _position: Point<Pixels>) -> bool {
+        true
+    }
+
+    fn draw(&self, _scene: &Scene) {}
+
+    fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas> {
+        self.0.sprite_atlas.clone()
+    }
+}
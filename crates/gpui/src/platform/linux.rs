@@ -0,0 +1,22 @@
+//! Linux support, for both X11 and Wayland sessions. Windows are drawn on
+//! the CPU with the software renderer and presented through shared memory,
+//! keyboard input goes through xkbcommon, and the main thread runs a calloop
+//! event loop.
+mod client;
+mod dialogs;
+mod dispatcher;
+mod display_linker;
+mod keyboard;
+mod platform;
+mod wayland;
+mod window;
+mod x11;
+
+pub(crate) use client::*;
+pub(crate) use dispatcher::*;
+pub(crate) use display_linker::*;
+pub(crate) use keyboard::*;
+pub(crate) use platform::*;
+pub(crate) use wayland::*;
+pub(crate) use window::*;
+pub(crate) use x11::*;
@@ -0,0 +1,86 @@
+use crate::{
+    AnyWindowHandle, Bounds, CursorStyle, DisplayId, GlobalPixels, PlatformDisplay, PlatformWindow,
+    WindowOptions,
+};
+use anyhow::Result;
+use std::rc::Rc;
+use uuid::Uuid;
+
+/// A connection to the display server, which is either X11 or Wayland.
+pub(crate) trait Client {
+    fn displays(&self) -> Vec<Rc<dyn PlatformDisplay>>;
+    fn display(&self, id: DisplayId) -> Option<Rc<dyn PlatformDisplay>> {
+        self.displays()
+            .into_iter()
+            .find(|display| display.id() == id)
+    }
+    fn open_window(
+        &self,
+        handle: AnyWindowHandle,
+        options: WindowOptions,
+    ) -> Box<dyn PlatformWindow>;
+    fn active_window(&self) -> Option<AnyWindowHandle>;
+    fn set_cursor_style(&self, style: CursorStyle);
+    fn write_to_clipboard(&self, text: String);
+    fn read_from_clipboard(&self) -> Option<String>;
+}
+
+/// The name of a cursor style in the cursor theme. Themes name their cursors
+/// after the CSS cursor values, so we use those, falling back to `left_ptr`
+/// (the arrow in every theme) for styles that CSS doesn't have.
+pub(crate) fn cursor_name(style: CursorStyle) -> &'static str {
+    match style {
+        CursorStyle::Arrow => "left_ptr",
+        CursorStyle::IBeam => "text",
+        CursorStyle::Crosshair => "crosshair",
+        CursorStyle::ClosedHand => "grabbing",
+        CursorStyle::OpenHand => "grab",
+        CursorStyle::PointingHand => "pointer",
+        CursorStyle::ResizeLeft => "w-resize",
+        CursorStyle::ResizeRight => "e-resize",
+        CursorStyle::ResizeLeftRight => "col-resize",
+        CursorStyle::ResizeUp => "n-resize",
+        CursorStyle::ResizeDown => "s-resize",
+        CursorStyle::ResizeUpDown => "row-resize",
+        CursorStyle::DisappearingItem => "left_ptr",
+        CursorStyle::IBeamCursorForVerticalLayout => "vertical-text",
+        CursorStyle::OperationNotAllowed => "not-allowed",
+        CursorStyle::DragLink => "alias",
+        CursorStyle::DragCopy => "copy",
+        CursorStyle::ContextualMenu => "context-menu",
+    }
+}
+
+/// A monitor, as reported by the display server.
+#[derive(Debug)]
+pub(crate) struct LinuxDisplay {
+    id: DisplayId,
+    name: String,
+    bounds: Bounds<GlobalPixels>,
+}
+
+impl LinuxDisplay {
+    pub fn new(id: u32, name: String, bounds: Bounds<GlobalPixels>) -> Self {
+        Self {
+            id: DisplayId(id),
+            name,
+            bounds,
+        }
+    }
+}
+
+impl PlatformDisplay for LinuxDisplay {
+    fn id(&self) -> DisplayId {
+        self.id
+    }
+
+    // Displays don't have UUIDs on Linux, so we derive one from the name of
+    // the output they're connected to, which is stable across restarts.
+    fn uuid(&self) -> Result<Uuid> {
+        Ok(Uuid::from_u64_pair(seahash::hash(self.name.as_bytes()), 0))
+    }
+
+    fn bounds(&self) -> Bounds<GlobalPixels> {
+        self.bounds
+    }
+}
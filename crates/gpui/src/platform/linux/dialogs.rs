@@ -0,0 +1,115 @@
+//! Neither X11 nor Wayland provide dialogs, so we show them with `zenity`,
+//! which is installed alongside most desktop environments.
+
+use crate::{PathPromptOptions, PromptLevel};
+use futures::channel::oneshot;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+};
+
+/// Runs zenity on a background thread, passing its output to `parse` once the
+/// dialog closes. The output is `None` when the user cancelled the dialog.
+fn run_zenity<T: Send + 'static>(
+    args: Vec<String>,
+    parse: impl FnOnce(Option<String>) -> T + Send + 'static,
+) -> oneshot::Receiver<T> {
+    let (done_tx, done_rx) = oneshot::channel();
+    thread::spawn(move || {
+        let output = match Command::new("zenity").args(&args).output() {
+            Ok(output) => output,
+            Err(error) => {
+                log::error!("failed to run zenity: {error}");
+                return;
+            }
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stdout = stdout.strip_suffix('\n').unwrap_or(&stdout);
+        let result = (output.status.success() || !stdout.is_empty()).then(|| stdout.to_string());
+        done_tx.send(parse(result)).ok();
+    });
+    done_rx
+}
+
+pub(crate) fn prompt(
+    level: PromptLevel,
+    msg: &str,
+    detail: Option<&str>,
+    answers: &[&str],
+) -> oneshot::Receiver<usize> {
+    let level = match level {
+        PromptLevel::Info => "--info",
+        PromptLevel::Warning => "--warning",
+        PromptLevel::Critical => "--error",
+    };
+    let text = match detail {
+        Some(detail) => format!("{msg}\n\n{detail}"),
+        None => msg.to_string(),
+    };
+
+    // Each answer is shown as an extra button, whose label zenity prints when
+    // it's clicked. Closing the dialog picks the last answer, which is the
+    // one used for cancellation.
+    let mut args = vec![
+        level.to_string(),
+        "--no-markup".into(),
+        "--text".into(),
+        text,
+        "--ok-label".into(),
+        answers.first().copied().unwrap_or("OK").into(),
+    ];
+    for answer in answers.iter().skip(1) {
+        args.push("--extra-button".into());
+        args.push(answer.to_string());
+    }
+
+    let answers = answers
+        .iter()
+        .map(|answer| answer.to_string())
+        .collect::<Vec<_>>();
+    run_zenity(args, move |output| {
+        match output {
+            Some(label) if label.is_empty() => Some(0),
+            Some(label) => answers.iter().position(|answer| *answer == label),
+            None => None,
+        }
+        .unwrap_or(answers.len().saturating_sub(1))
+    })
+}
+
+pub(crate) fn prompt_for_paths(
+    options: PathPromptOptions,
+) -> oneshot::Receiver<Option<Vec<PathBuf>>> {
+    let mut args = vec![
+        "--file-selection".to_string(),
+        "--separator".into(),
+        "\n".into(),
+    ];
+    if options.directories && !options.files {
+        args.push("--directory".into());
+    }
+    if options.multiple {
+        args.push("--multiple".into());
+    }
+
+    run_zenity(args, |output| {
+        output.map(|output| output.lines().map(PathBuf::from).collect())
+    })
+}
+
+pub(crate) fn prompt_for_new_path(directory: &Path) -> oneshot::Receiver<Option<PathBuf>> {
+    let mut filename = directory.to_string_lossy().into_owned();
+    if !filename.ends_with('/') {
+        filename.push('/');
+    }
+    let args = vec![
+        "--file-selection".to_string(),
+        "--save".into(),
+        "--confirm-overwrite".into(),
+        "--filename".into(),
+        filename,
+    ];
+
+    run_zenity(args, |output| output.map(PathBuf::from))
+}
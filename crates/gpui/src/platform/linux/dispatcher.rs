@@ -0,0 +1,182 @@
+use crate::{PlatformDispatcher, TaskLabel};
+use async_task::Runnable;
+use calloop::{
+    channel::{self, Sender},
+    LoopHandle,
+};
+use collections::BTreeMap;
+use parking::{Parker, Unparker};
+use parking_lot::Mutex;
+use std::{
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+/// Runs main thread tasks on a calloop event loop, background tasks on a pool
+/// of worker threads, and timers on a thread of their own, so that they fire
+/// even while the main thread is blocked.
+pub(crate) struct LinuxDispatcher {
+    parker: Mutex<Parker>,
+    main_thread_id: ThreadId,
+    main_sender: Sender<Runnable>,
+    background_sender: flume::Sender<Runnable>,
+    timer_sender: flume::Sender<(Instant, Runnable)>,
+    _background_threads: Vec<thread::JoinHandle<()>>,
+    _timer_thread: thread::JoinHandle<()>,
+}
+
+impl LinuxDispatcher {
+    /// Creates a dispatcher whose main thread is the current thread, running
+    /// main thread tasks whenever the given event loop is dispatched.
+    pub(crate) fn new<Data>(handle: &LoopHandle<'static, Data>) -> Self {
+        let (main_sender, main_channel) = channel::channel::<Runnable>();
+        handle
+            .insert_source(main_channel, |event, _, _| {
+                if let channel::Event::Msg(runnable) = event {
+                    runnable.run();
+                }
+            })
+            .expect("failed to add the main thread task source to the event loop");
+
+        let (background_sender, background_receiver) = flume::unbounded::<Runnable>();
+        let background_threads = (0..num_cpus::get())
+            .map(|i| {
+                let receiver = background_receiver.clone();
+                thread::Builder::new()
+                    .name(format!("background-{i}"))
+                    .spawn(move || {
+                        for runnable in receiver {
+                            runnable.run();
+                        }
+                    })
+                    .expect("failed to spawn a background thread")
+            })
+            .collect();
+
+        let (timer_sender, timer_receiver) = flume::unbounded::<(Instant, Runnable)>();
+        let timer_thread = thread::Builder::new()
+            .name("timers".into())
+            .spawn(move || run_timers(timer_receiver))
+            .expect("failed to spawn the timer thread");
+
+        Self {
+            parker: Mutex::new(Parker::new()),
+            main_thread_id: thread::current().id(),
+            main_sender,
+            background_sender,
+            timer_sender,
+            _background_threads: background_threads,
+            _timer_thread: timer_thread,
+        }
+    }
+}
+
+fn run_timers(receiver: flume::Receiver<(Instant, Runnable)>) {
+    let mut timers = BTreeMap::<(Instant, usize), Runnable>::default();
+    let mut next_id = 0;
+    loop {
+        let received = match timers.keys().next() {
+            Some(&(deadline, _)) => receiver.recv_deadline(deadline),
+            None => receiver
+                .recv()
+                .map_err(|_| flume::RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok((deadline, runnable)) => {
+                timers.insert((deadline, next_id), runnable);
+                next_id += 1;
+            }
+            Err(flume::RecvTimeoutError::Timeout) => {}
+            Err(flume::RecvTimeoutError::Disconnected) => break,
+        }
+
+        let now = Instant::now();
+        while let Some(entry) = timers.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            entry.remove().run();
+        }
+    }
+}
+
+impl PlatformDispatcher for LinuxDispatcher {
+    fn is_main_thread(&self) -> bool {
+        thread::current().id() == self.main_thread_id
+    }
+
+    fn dispatch(&self, runnable: Runnable, _: Option<TaskLabel>) {
+        self.background_sender.send(runnable).ok();
+    }
+
+    fn dispatch_on_main_thread(&self, runnable: Runnable) {
+        self.main_sender.send(runnable).ok();
+    }
+
+    fn dispatch_after(&self, duration: Duration, runnable: Runnable) {
+        self.timer_sender
+            .send((Instant::now() + duration, runnable))
+            .ok();
+    }
+
+    fn tick(&self, _background_only: bool) -> bool {
+        false
+    }
+
+    fn park(&self) {
+        self.parker.lock().park()
+    }
+
+    fn unparker(&self) -> Unparker {
+        self.parker.lock().unparker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BackgroundExecutor, ForegroundExecutor};
+    use calloop::EventLoop;
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    #[test]
+    fn test_dispatcher() {
+        let mut event_loop = EventLoop::<()>::try_new().unwrap();
+        let dispatcher = Arc::new(LinuxDispatcher::new(&event_loop.handle()));
+        let background = BackgroundExecutor::new(dispatcher.clone());
+        let foreground = ForegroundExecutor::new(dispatcher.clone());
+        assert!(dispatcher.is_main_thread());
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let signal = event_loop.get_signal();
+        foreground
+            .spawn({
+                let events = events.clone();
+                let background = background.clone();
+                async move {
+                    let dispatcher = dispatcher.clone();
+                    let ran_on_main_thread = background
+                        .spawn(async move { dispatcher.is_main_thread() })
+                        .await;
+                    events
+                        .borrow_mut()
+                        .push(format!("background: {ran_on_main_thread}"));
+
+                    let start = Instant::now();
+                    background.timer(Duration::from_millis(20)).await;
+                    assert!(start.elapsed() >= Duration::from_millis(20));
+                    events.borrow_mut().push("timer".into());
+                    signal.stop();
+                }
+            })
+            .detach();
+
+        event_loop
+            .run(Duration::from_secs(5), &mut (), |_| {})
+            .unwrap();
+        assert_eq!(*events.borrow(), ["background: false", "timer"]);
+
+        // Timers fire even while the main thread is blocked on them.
+        background.block(background.timer(Duration::from_millis(10)));
+    }
+}
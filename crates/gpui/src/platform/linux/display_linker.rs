@@ -0,0 +1,99 @@
+use crate::DisplayId;
+use collections::HashMap;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering::SeqCst},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the display link fires. Neither X11 nor Wayland tell us when a
+/// display refreshes without a surface to present to, so we assume 60Hz.
+const FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+type OutputCallback = Arc<Mutex<Box<dyn FnMut() + Send>>>;
+
+pub(crate) struct LinuxDisplayLinker {
+    links: HashMap<DisplayId, LinuxDisplayLink>,
+}
+
+struct LinuxDisplayLink {
+    output_callback: OutputCallback,
+    running: Option<Arc<AtomicBool>>,
+}
+
+impl LinuxDisplayLinker {
+    pub fn new() -> Self {
+        LinuxDisplayLinker {
+            links: Default::default(),
+        }
+    }
+
+    pub fn set_output_callback(
+        &mut self,
+        display_id: DisplayId,
+        output_callback: Box<dyn FnMut() + Send>,
+    ) {
+        self.stop(display_id);
+        self.links.insert(
+            display_id,
+            LinuxDisplayLink {
+                output_callback: Arc::new(Mutex::new(output_callback)),
+                running: None,
+            },
+        );
+    }
+
+    pub fn start(&mut self, display_id: DisplayId) {
+        let Some(link) = self.links.get_mut(&display_id) else {
+            log::warn!("No display link callback registered for {:?}", display_id);
+            return;
+        };
+        if link.running.is_some() {
+            return;
+        }
+
+        let running = Arc::new(AtomicBool::new(true));
+        let output_callback = link.output_callback.clone();
+        link.running = Some(running.clone());
+        thread::Builder::new()
+            .name(format!("display-link-{}", display_id.0))
+            .spawn(move || {
+                let mut next_frame = Instant::now();
+                while running.load(SeqCst) {
+                    (output_callback.lock())();
+                    next_frame += FRAME_INTERVAL;
+                    let now = Instant::now();
+                    if next_frame > now {
+                        thread::sleep(next_frame - now);
+                    } else {
+                        next_frame = now;
+                    }
+                }
+            })
+            .expect("failed to spawn a display link thread");
+    }
+
+    pub fn stop(&mut self, display_id: DisplayId) {
+        if let Some(running) = self
+            .links
+            .get_mut(&display_id)
+            .and_then(|link| link.running.take())
+        {
+            running.store(false, SeqCst);
+        }
+    }
+}
+
+impl Drop for LinuxDisplayLinker {
+    fn drop(&mut self) {
+        for link in self.links.values() {
+            if let Some(running) = &link.running {
+                running.store(false, SeqCst);
+            }
+        }
+    }
+}
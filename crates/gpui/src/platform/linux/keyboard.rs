@@ -0,0 +1,244 @@
+use crate::{Keystroke, Modifiers};
+use std::env;
+use xkbcommon::xkb::{self, compose, keysyms, Keycode, Keysym};
+
+/// The result of pressing a key, after running it through the compose table.
+pub(crate) enum KeyInput {
+    /// A key was pressed. If it completed a compose sequence, the composed
+    /// text is the keystroke's `ime_key`.
+    Keystroke(Keystroke),
+    /// A compose sequence is in progress, and this is the text typed so far.
+    Preedit(String),
+    /// A compose sequence was abandoned.
+    CancelPreedit,
+}
+
+/// The keyboard state of a seat: its keymap, the modifiers that are held,
+/// and any compose sequence in progress.
+pub(crate) struct Keyboard {
+    state: xkb::State,
+    compose: Option<compose::State>,
+    preedit: String,
+}
+
+impl Keyboard {
+    pub fn new(context: &xkb::Context, state: xkb::State) -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .into_iter()
+            .find_map(|name| env::var_os(name).filter(|value| !value.is_empty()))
+            .unwrap_or_else(|| "C".into());
+        let compose = compose::Table::new_from_locale(context, &locale, compose::COMPILE_NO_FLAGS)
+            .map(|table| compose::State::new(&table, compose::STATE_NO_FLAGS))
+            .map_err(|_| log::warn!("no compose table for locale {locale:?}"))
+            .ok();
+        Self {
+            state,
+            compose,
+            preedit: String::new(),
+        }
+    }
+
+    /// Updates the modifiers from the masks reported by the compositor.
+    pub fn update_mask(&mut self, depressed: u32, latched: u32, locked: u32, group: u32) {
+        self.state
+            .update_mask(depressed, latched, locked, 0, 0, group);
+    }
+
+    /// Updates the modifiers when the server only reports key presses.
+    pub fn update_key(&mut self, keycode: Keycode, pressed: bool) {
+        let direction = if pressed {
+            xkb::KeyDirection::Down
+        } else {
+            xkb::KeyDirection::Up
+        };
+        self.state.update_key(keycode, direction);
+    }
+
+    /// Whether holding the key down should repeat it.
+    pub fn key_repeats(&self, keycode: Keycode) -> bool {
+        self.state.get_keymap().key_repeats(keycode)
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        let is_active = |name| {
+            self.state
+                .mod_name_is_active(name, xkb::STATE_MODS_EFFECTIVE)
+        };
+        Modifiers {
+            control: is_active(xkb::MOD_NAME_CTRL),
+            alt: is_active(xkb::MOD_NAME_ALT),
+            shift: is_active(xkb::MOD_NAME_SHIFT),
+            command: is_active(xkb::MOD_NAME_LOGO),
+            function: false,
+        }
+    }
+
+    /// Returns the keystroke for a key, without feeding it to the compose
+    /// table. Used for key releases.
+    pub fn keystroke(&self, keycode: Keycode) -> Keystroke {
+        let keysym = self.state.key_get_one_sym(keycode);
+        let layout = self.state.key_get_layout(keycode);
+        let base_keysym = self
+            .state
+            .get_keymap()
+            .key_get_syms_by_level(keycode, layout, 0)
+            .first()
+            .copied()
+            .unwrap_or(keysym);
+        keystroke_for_keysyms(keysym, base_keysym, self.modifiers())
+    }
+
+    pub fn key_down(&mut self, keycode: Keycode) -> KeyInput {
+        let mut keystroke = self.keystroke(keycode);
+        let Some(compose) = self.compose.as_mut() else {
+            return KeyInput::Keystroke(keystroke);
+        };
+
+        let keysym = self.state.key_get_one_sym(keycode);
+        if matches!(compose.feed(keysym), compose::FeedResult::Ignored) {
+            return KeyInput::Keystroke(keystroke);
+        }
+        match compose.status() {
+            compose::Status::Composing => {
+                if let Some(ch) = keysym_char(keysym) {
+                    self.preedit.push(ch);
+                }
+                KeyInput::Preedit(self.preedit.clone())
+            }
+            compose::Status::Composed => {
+                keystroke.ime_key = compose.utf8();
+                compose.reset();
+                self.preedit.clear();
+                KeyInput::Keystroke(keystroke)
+            }
+            compose::Status::Cancelled => {
+                compose.reset();
+                self.preedit.clear();
+                KeyInput::CancelPreedit
+            }
+            compose::Status::Nothing => KeyInput::Keystroke(keystroke),
+        }
+    }
+}
+
+/// Builds a keystroke following the same conventions as on macOS: shifted
+/// letters are reported as the lowercase letter with shift held, while other
+/// keys that shift turns into a different symbol are reported as that symbol
+/// without shift.
+fn keystroke_for_keysyms(keysym: Keysym, base_keysym: Keysym, modifiers: Modifiers) -> Keystroke {
+    let mut modifiers = modifiers;
+    let ch = keysym_char(keysym);
+    let key = match key_name(keysym) {
+        Some(name) => name.to_string(),
+        None => {
+            let chars = ch.map(String::from).unwrap_or_default();
+            let base_chars = keysym_char(base_keysym)
+                .map(String::from)
+                .unwrap_or_default();
+            if modifiers.shift {
+                if base_chars == chars.to_lowercase() {
+                    base_chars
+                } else {
+                    if base_chars != chars {
+                        modifiers.shift = false;
+                    }
+                    chars
+                }
+            } else {
+                chars
+            }
+        }
+    };
+
+    let ime_key = ch
+        .filter(|_| !modifiers.control && !modifiers.alt && !modifiers.command)
+        .map(String::from);
+    Keystroke {
+        modifiers,
+        key,
+        ime_key,
+    }
+}
+
+fn keysym_char(keysym: Keysym) -> Option<char> {
+    char::from_u32(xkb::keysym_to_utf32(keysym)).filter(|ch| *ch != '\0' && !ch.is_control())
+}
+
+fn key_name(keysym: Keysym) -> Option<&'static str> {
+    Some(match keysym.raw() {
+        keysyms::KEY_space => "space",
+        keysyms::KEY_BackSpace => "backspace",
+        keysyms::KEY_Return | keysyms::KEY_KP_Enter => "enter",
+        keysyms::KEY_Escape => "escape",
+        keysyms::KEY_Tab | keysyms::KEY_ISO_Left_Tab => "tab",
+        keysyms::KEY_Up => "up",
+        keysyms::KEY_Down => "down",
+        keysyms::KEY_Left => "left",
+        keysyms::KEY_Right => "right",
+        keysyms::KEY_Page_Up => "pageup",
+        keysyms::KEY_Page_Down => "pagedown",
+        keysyms::KEY_Home => "home",
+        keysyms::KEY_End => "end",
+        keysyms::KEY_Delete => "delete",
+        keysyms::KEY_F1 => "f1",
+        keysyms::KEY_F2 => "f2",
+        keysyms::KEY_F3 => "f3",
+        keysyms::KEY_F4 => "f4",
+        keysyms::KEY_F5 => "f5",
+        keysyms::KEY_F6 => "f6",
+        keysyms::KEY_F7 => "f7",
+        keysyms::KEY_F8 => "f8",
+        keysyms::KEY_F9 => "f9",
+        keysyms::KEY_F10 => "f10",
+        keysyms::KEY_F11 => "f11",
+        keysyms::KEY_F12 => "f12",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keystroke_for_keysyms() {
+        let keystroke = |keysym, base_keysym, modifiers| {
+            let keystroke =
+                keystroke_for_keysyms(Keysym::new(keysym), Keysym::new(base_keysym), modifiers);
+            (keystroke.key, keystroke.modifiers, keystroke.ime_key)
+        };
+        let shift = Modifiers {
+            shift: true,
+            ..Default::default()
+        };
+        let control = Modifiers {
+            control: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            keystroke(keysyms::KEY_a, keysyms::KEY_a, Modifiers::default()),
+            ("a".into(), Modifiers::default(), Some("a".into()))
+        );
+        assert_eq!(
+            keystroke(keysyms::KEY_A, keysyms::KEY_a, shift),
+            ("a".into(), shift, Some("A".into()))
+        );
+        assert_eq!(
+            keystroke(keysyms::KEY_exclam, keysyms::KEY_1, shift),
+            ("!".into(), Modifiers::default(), Some("!".into()))
+        );
+        assert_eq!(
+            keystroke(keysyms::KEY_a, keysyms::KEY_a, control),
+            ("a".into(), control, None)
+        );
+        assert_eq!(
+            keystroke(keysyms::KEY_ISO_Left_Tab, keysyms::KEY_Tab, shift),
+            ("tab".into(), shift, None)
+        );
+        assert_eq!(
+            keystroke(keysyms::KEY_space, keysyms::KEY_space, Modifiers::default()),
+            ("space".into(), Modifiers::default(), Some(" ".into()))
+        );
+    }
+}
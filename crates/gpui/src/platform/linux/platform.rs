@@ -0,0 +1,390 @@
+use super::{
+    dialogs, Client, LinuxDispatcher, LinuxDisplayLinker, WaylandClient, X11Client,
+    DOUBLE_CLICK_INTERVAL,
+};
+use crate::{
+    Action, AnyWindowHandle, BackgroundExecutor, ClipboardItem, CosmicTextSystem, CursorStyle,
+    DisplayId, ForegroundExecutor, Keymap, Menu, PathPromptOptions, Platform, PlatformDisplay,
+    PlatformInput, PlatformTextSystem, PlatformWindow, SemanticVersion, Task, WindowOptions,
+};
+use anyhow::{anyhow, Context as _, Result};
+use calloop::{EventLoop, LoopHandle, LoopSignal};
+use chrono::{Local, Offset as _};
+use futures::channel::oneshot;
+use std::{
+    cell::RefCell,
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+use time::UtcOffset;
+use util::ResultExt as _;
+
+/// The label of the keyring items holding the credentials we store.
+const KEYRING_LABEL: &str = "zed-github-account";
+
+pub(crate) struct LinuxPlatform {
+    background_executor: BackgroundExecutor,
+    foreground_executor: ForegroundExecutor,
+    text_system: Arc<CosmicTextSystem>,
+    event_loop: RefCell<Option<EventLoop<'static, ()>>>,
+    loop_signal: LoopSignal,
+    client: Rc<dyn Client>,
+    state: RefCell<LinuxPlatformState>,
+}
+
+struct LinuxPlatformState {
+    display_linker: LinuxDisplayLinker,
+    quit_callbacks: Vec<Box<dyn FnMut()>>,
+    /// The metadata of the last item we wrote to the clipboard, along with a
+    /// hash of its text, so we can tell whether the clipboard still holds it.
+    clipboard_metadata: Option<(u64, String)>,
+}
+
+impl LinuxPlatform {
+    /// Fails when there's no display server to connect to.
+    pub(crate) fn new() -> Result<Self> {
+        let event_loop = EventLoop::try_new().context("failed to create the event loop")?;
+        let dispatcher = Arc::new(LinuxDispatcher::new(&event_loop.handle()));
+        let client = connect(event_loop.handle())?;
+        Ok(Self {
+            background_executor: BackgroundExecutor::new(dispatcher.clone()),
+            foreground_executor: ForegroundExecutor::new(dispatcher),
+            text_system: Arc::new(CosmicTextSystem::new()),
+            loop_signal: event_loop.get_signal(),
+            event_loop: RefCell::new(Some(event_loop)),
+            client,
+            state: RefCell::new(LinuxPlatformState {
+                display_linker: LinuxDisplayLinker::new(),
+                quit_callbacks: Vec::new(),
+                clipboard_metadata: None,
+            }),
+        })
+    }
+}
+
+/// Connects to Wayland when the session provides it, and to X11 otherwise.
+fn connect(handle: LoopHandle<'static, ()>) -> Result<Rc<dyn Client>> {
+    if env::var_os("WAYLAND_DISPLAY").is_some() {
+        match WaylandClient::new(handle.clone()) {
+            Ok(client) => return Ok(client),
+            Err(error) => log::warn!("failed to connect to Wayland, trying X11: {error:?}"),
+        }
+    }
+    let client =
+        X11Client::new(handle).context("failed to connect to a Wayland or X11 display server")?;
+    Ok(client)
+}
+
+fn open(path: impl AsRef<std::ffi::OsStr>) {
+    Command::new("xdg-open")
+        .arg(path)
+        .spawn()
+        .context("failed to run xdg-open")
+        .log_err();
+}
+
+impl Platform for LinuxPlatform {
+    fn background_executor(&self) -> BackgroundExecutor {
+        self.background_executor.clone()
+    }
+
+    fn foreground_executor(&self) -> ForegroundExecutor {
+        self.foreground_executor.clone()
+    }
+
+    fn text_system(&self) -> Arc<dyn PlatformTextSystem> {
+        self.text_system.clone()
+    }
+
+    fn run(&self, on_finish_launching: Box<dyn FnOnce()>) {
+        on_finish_launching();
+
+        // The event loop is taken out while it runs, so that callbacks can
+        // borrow the platform.
+        let mut event_loop = self
+            .event_loop
+            .borrow_mut()
+            .take()
+            .expect("the platform is already running");
+        event_loop
+            .run(None, &mut (), |_| {})
+            .context("failed to run the event loop")
+            .log_err();
+        *self.event_loop.borrow_mut() = Some(event_loop);
+
+        let mut quit_callbacks = std::mem::take(&mut self.state.borrow_mut().quit_callbacks);
+        for callback in &mut quit_callbacks {
+            callback();
+        }
+    }
+
+    fn quit(&self) {
+        self.loop_signal.stop();
+        self.loop_signal.wakeup();
+    }
+
+    fn restart(&self) {
+        use std::os::unix::process::CommandExt as _;
+
+        let app_pid = std::process::id().to_string();
+        let Some(app_path) = self.app_path().log_err() else {
+            return;
+        };
+
+        // Wait until this process has exited and then re-open this path.
+        let script = r#"
+            while kill -0 $0 2> /dev/null; do
+                sleep 0.1
+            done
+            "$1"
+        "#;
+
+        let restart_process = Command::new("/bin/bash")
+            .arg("-c")
+            .arg(script)
+            .arg(app_pid)
+            .arg(app_path)
+            .process_group(0)
+            .spawn();
+
+        match restart_process {
+            Ok(_) => self.quit(),
+            Err(e) => log::error!("failed to spawn restart script: {:?}", e),
+        }
+    }
+
+    // Window managers decide which application is active.
+    fn activate(&self, _ignoring_other_apps: bool) {}
+
+    fn hide(&self) {}
+
+    fn hide_other_apps(&self) {}
+
+    fn unhide_other_apps(&self) {}
+
+    fn displays(&self) -> Vec<Rc<dyn PlatformDisplay>> {
+        self.client.displays()
+    }
+
+    fn display(&self, id: DisplayId) -> Option<Rc<dyn PlatformDisplay>> {
+        self.client.display(id)
+    }
+
+    fn active_window(&self) -> Option<AnyWindowHandle> {
+        self.client.active_window()
+    }
+
+    fn open_window(
+        &self,
+        handle: AnyWindowHandle,
+        options: WindowOptions,
+    ) -> Box<dyn PlatformWindow> {
+        self.client.open_window(handle, options)
+    }
+
+    fn set_display_link_output_callback(
+        &self,
+        display_id: DisplayId,
+        callback: Box<dyn FnMut() + Send>,
+    ) {
+        self.state
+            .borrow_mut()
+            .display_linker
+            .set_output_callback(display_id, callback);
+    }
+
+    fn start_display_link(&self, display_id: DisplayId) {
+        self.state.borrow_mut().display_linker.start(display_id);
+    }
+
+    fn stop_display_link(&self, display_id: DisplayId) {
+        self.state.borrow_mut().display_linker.stop(display_id);
+    }
+
+    fn open_url(&self, url: &str) {
+        open(url);
+    }
+
+    // Opening URLs with the app requires a desktop entry registering it as
+    // their handler, which isn't installed yet.
+    fn on_open_urls(&self, _callback: Box<dyn FnMut(Vec<String>)>) {}
+
+    fn prompt_for_paths(
+        &self,
+        options: PathPromptOptions,
+    ) -> oneshot::Receiver<Option<Vec<PathBuf>>> {
+        dialogs::prompt_for_paths(options)
+    }
+
+    fn prompt_for_new_path(&self, directory: &Path) -> oneshot::Receiver<Option<PathBuf>> {
+        dialogs::prompt_for_new_path(directory)
+    }
+
+    fn reveal_path(&self, path: &Path) {
+        if path.is_dir() {
+            open(path);
+        } else if let Some(parent) = path.parent() {
+            open(parent);
+        }
+    }
+
+    fn on_become_active(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_resign_active(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_quit(&self, callback: Box<dyn FnMut()>) {
+        self.state.borrow_mut().quit_callbacks.push(callback);
+    }
+
+    fn on_reopen(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_event(&self, _callback: Box<dyn FnMut(PlatformInput) -> bool>) {}
+
+    // There's no global menu bar on Linux.
+    fn set_menus(&self, _menus: Vec<Menu>, _keymap: &Keymap) {}
+
+    fn on_app_menu_action(&self, _callback: Box<dyn FnMut(&dyn Action)>) {}
+
+    fn on_will_open_app_menu(&self, _callback: Box<dyn FnMut()>) {}
+
+    fn on_validate_app_menu_command(&self, _callback: Box<dyn FnMut(&dyn Action) -> bool>) {}
+
+    fn os_name(&self) -> &'static str {
+        "Linux"
+    }
+
+    fn os_version(&self) -> Result<SemanticVersion> {
+        let release = fs::read_to_string("/proc/sys/kernel/osrelease")
+            .context("failed to read the kernel release")?;
+        // Releases look like "6.5.0-14-generic", so drop everything after the
+        // version numbers.
+        let version = release
+            .trim()
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()
+            .unwrap_or_default();
+        version.parse()
+    }
+
+    fn app_version(&self) -> Result<SemanticVersion> {
+        Err(anyhow!("app version is not available on Linux"))
+    }
+
+    fn app_path(&self) -> Result<PathBuf> {
+        env::current_exe().context("failed to get the path of the executable")
+    }
+
+    fn local_timezone(&self) -> UtcOffset {
+        let seconds_from_utc = Local::now().offset().fix().local_minus_utc();
+        UtcOffset::from_whole_seconds(seconds_from_utc).unwrap_or(UtcOffset::UTC)
+    }
+
+    fn double_click_interval(&self) -> Duration {
+        DOUBLE_CLICK_INTERVAL
+    }
+
+    fn path_for_auxiliary_executable(&self, name: &str) -> Result<PathBuf> {
+        let path = self
+            .app_path()?
+            .parent()
+            .ok_or_else(|| anyhow!("executable has no parent directory"))?
+            .join(name);
+        if path.exists() {
+            Ok(path)
+        } else {
+            Err(anyhow!("resource not found"))
+        }
+    }
+
+    fn set_cursor_style(&self, style: CursorStyle) {
+        self.client.set_cursor_style(style);
+    }
+
+    fn should_auto_hide_scrollbars(&self) -> bool {
+        false
+    }
+
+    fn write_to_clipboard(&self, item: ClipboardItem) {
+        self.state.borrow_mut().clipboard_metadata = item
+            .metadata
+            .map(|metadata| (ClipboardItem::text_hash(&item.text), metadata));
+        self.client.write_to_clipboard(item.text);
+    }
+
+    // Other applications can't store metadata in the clipboard, so it's only
+    // returned while the clipboard still holds the text we wrote.
+    fn read_from_clipboard(&self) -> Option<ClipboardItem> {
+        let text = self.client.read_from_clipboard()?;
+        let metadata = self
+            .state
+            .borrow()
+            .clipboard_metadata
+            .as_ref()
+            .filter(|(hash, _)| *hash == ClipboardItem::text_hash(&text))
+            .map(|(_, metadata)| metadata.clone());
+        Some(ClipboardItem { text, metadata })
+    }
+
+    fn write_credentials(&self, url: &str, username: &str, password: &[u8]) -> Task<Result<()>> {
+        let url = url.to_string();
+        let username = username.to_string();
+        let password = password.to_vec();
+        self.background_executor.spawn(async move {
+            let keyring = unlocked_keyring().await?;
+            let attributes = vec![("url", url.as_str()), ("username", username.as_str())];
+            keyring
+                .create_item(KEYRING_LABEL, &attributes, password, true)
+                .await
+                .context("failed to store the credentials in the Secret Service")?;
+            Ok(())
+        })
+    }
+
+    fn read_credentials(&self, url: &str) -> Task<Result<Option<(String, Vec<u8>)>>> {
+        let url = url.to_string();
+        self.background_executor.spawn(async move {
+            let keyring = unlocked_keyring().await?;
+            for item in keyring.search_items(&vec![("url", url.as_str())]).await? {
+                if item.label().await? != KEYRING_LABEL {
+                    continue;
+                }
+                let Some(username) = item.attributes().await?.remove("username") else {
+                    continue;
+                };
+                let password = item.secret().await?;
+                return Ok(Some((username, password.to_vec())));
+            }
+            Ok(None)
+        })
+    }
+
+    fn delete_credentials(&self, url: &str) -> Task<Result<()>> {
+        let url = url.to_string();
+        self.background_executor.spawn(async move {
+            let keyring = unlocked_keyring().await?;
+            for item in keyring.search_items(&vec![("url", url.as_str())]).await? {
+                if item.label().await? == KEYRING_LABEL {
+                    item.delete().await?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Opens the user's keyring through the Secret Service API, which desktop
+/// environments provide with GNOME Keyring or KWallet.
+async fn unlocked_keyring() -> Result<oo7::Keyring> {
+    let keyring = oo7::Keyring::new()
+        .await
+        .context("the Secret Service isn't available to store credentials")?;
+    keyring
+        .unlock()
+        .await
+        .context("failed to unlock the keyring")?;
+    Ok(keyring)
+}
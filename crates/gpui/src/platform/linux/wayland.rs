@@ -0,0 +1,5 @@
+mod client;
+mod window;
+
+pub(crate) use client::*;
+pub(crate) use window::*;
@@ -0,0 +1,975 @@
+use super::WaylandWindow;
+use crate::{
+    platform::linux::{
+        cursor_name, Client, KeyInput, Keyboard, LinuxDisplay, LinuxWindow, LinuxWindowState,
+    },
+    point, px, size, AnyWindowHandle, Bounds, CursorStyle, GlobalPixels, Keystroke, Modifiers,
+    MouseButton, NavigationDirection, Pixels, PlatformDisplay, PlatformWindow, Point, ScrollDelta,
+    Size, WindowBounds, WindowOptions,
+};
+use anyhow::{Context as _, Result};
+use calloop::{
+    generic::{FdWrapper, Generic},
+    timer::{TimeoutAction, Timer},
+    Interest, LoopHandle, Mode, PostAction, RegistrationToken,
+};
+use collections::HashMap;
+use copypasta::{
+    wayland_clipboard::{self, Clipboard},
+    ClipboardProvider as _,
+};
+use std::{
+    cell::RefCell,
+    mem,
+    os::fd::AsRawFd as _,
+    rc::{Rc, Weak},
+    time::Duration,
+};
+use util::ResultExt as _;
+use wayland_client::{
+    backend::ObjectId,
+    delegate_noop,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{
+        wl_buffer, wl_compositor, wl_keyboard, wl_output, wl_pointer, wl_registry, wl_seat, wl_shm,
+        wl_shm_pool, wl_surface,
+    },
+    Connection, Dispatch, EventQueue, Proxy as _, QueueHandle, WEnum,
+};
+use wayland_cursor::CursorTheme;
+use wayland_protocols::{
+    wp::text_input::zv3::client::{zwp_text_input_manager_v3, zwp_text_input_v3},
+    xdg::shell::client::{xdg_surface, xdg_toplevel, xdg_wm_base},
+};
+use xkbcommon::xkb::{self, Keycode};
+
+/// The Linux input event codes of the mouse buttons.
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
+
+/// The size of the cursor, in surface coordinates.
+const CURSOR_SIZE: u32 = 24;
+
+pub(crate) struct WaylandClient {
+    conn: Connection,
+    qh: QueueHandle<WaylandState>,
+    compositor: wl_compositor::WlCompositor,
+    wm_base: xdg_wm_base::XdgWmBase,
+    shm: wl_shm::WlShm,
+    event_queue: RefCell<EventQueue<WaylandState>>,
+    state: Rc<RefCell<WaylandState>>,
+    clipboard: RefCell<Clipboard>,
+    loop_handle: LoopHandle<'static, ()>,
+}
+
+/// Window events are collected while the event queue is being dispatched,
+/// and handled once it's done, so that the application can call back into
+/// the client.
+enum WindowEvent {
+    Configure {
+        size: Option<Size<Pixels>>,
+        fullscreen: bool,
+    },
+    Close,
+    ActiveStatusChange(bool),
+    KeyDown(KeyInput),
+    ImeCommit(String),
+    KeyUp(Keystroke),
+    Modifiers(Modifiers),
+    MouseMove(Point<Pixels>),
+    MouseExit,
+    MouseButton(MouseButton, bool),
+    Scroll(ScrollDelta),
+}
+
+struct WindowEntry {
+    state: Weak<LinuxWindowState>,
+    native: Weak<WaylandWindow>,
+    /// The size and states from the last toplevel configure event, which
+    /// apply once the surface's configure event arrives.
+    pending_configure: Option<(Option<Size<Pixels>>, Vec<xdg_toplevel::State>)>,
+}
+
+struct Output {
+    global_name: u32,
+    name: Option<String>,
+    origin: Point<i32>,
+    size: Size<i32>,
+    scale: i32,
+}
+
+struct KeyRepeat {
+    /// How many times a held key repeats per second.
+    rate: i32,
+    delay: Duration,
+    current: Option<(Keycode, RegistrationToken)>,
+}
+
+/// The seat's text input, through which input methods compose text. While it's
+/// enabled, the input method takes over from the compose table.
+struct TextInput {
+    text_input: zwp_text_input_v3::ZwpTextInputV3,
+    /// The surface the text input is enabled on.
+    focus: Option<ObjectId>,
+    /// The changes the input method sent, which apply on its next done event.
+    pending_preedit: Option<String>,
+    pending_commit: Option<String>,
+    has_preedit: bool,
+    /// The cursor rectangle last sent to the input method.
+    cursor_rectangle: Option<Bounds<Pixels>>,
+}
+
+pub(crate) struct WaylandState {
+    this: Weak<RefCell<WaylandState>>,
+    loop_handle: LoopHandle<'static, ()>,
+    windows: HashMap<ObjectId, WindowEntry>,
+    pending_events: Vec<(Rc<LinuxWindowState>, WindowEvent)>,
+    outputs: Vec<Output>,
+    xkb_context: xkb::Context,
+    keyboard: Option<Keyboard>,
+    keyboard_focus: Option<ObjectId>,
+    key_repeat: KeyRepeat,
+    text_input: Option<TextInput>,
+    pointer: Option<wl_pointer::WlPointer>,
+    pointer_focus: Option<(ObjectId, u32)>,
+    cursor_theme: Option<CursorTheme>,
+    cursor_surface: Option<wl_surface::WlSurface>,
+    cursor_style: CursorStyle,
+    scale: i32,
+}
+
+impl WaylandClient {
+    pub(crate) fn new(loop_handle: LoopHandle<'static, ()>) -> Result<Rc<Self>> {
+        let conn = Connection::connect_to_env().context("failed to connect to Wayland")?;
+        let (globals, event_queue) = registry_queue_init::<WaylandState>(&conn)?;
+        let qh = event_queue.handle();
+
+        let compositor: wl_compositor::WlCompositor = globals.bind(&qh, 1..=5, ())?;
+        let wm_base: xdg_wm_base::XdgWmBase = globals.bind(&qh, 1..=2, ())?;
+        let shm: wl_shm::WlShm = globals.bind(&qh, 1..=1, ())?;
+        let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=5, ())?;
+        // Not all compositors support input methods, in which case we fall
+        // back to the compose table.
+        let text_input = globals
+            .bind::<zwp_text_input_manager_v3::ZwpTextInputManagerV3, _, _>(&qh, 1..=1, ())
+            .ok()
+            .map(|manager| TextInput {
+                text_input: manager.get_text_input(&seat, &qh, ()),
+                focus: None,
+                pending_preedit: None,
+                pending_commit: None,
+                has_preedit: false,
+                cursor_rectangle: None,
+            });
+        for global in globals.contents().clone_list() {
+            if global.interface == wl_output::WlOutput::interface().name {
+                globals.registry().bind::<wl_output::WlOutput, _, _>(
+                    global.name,
+                    global.version.min(4),
+                    &qh,
+                    global.name,
+                );
+            }
+        }
+
+        let state = Rc::new_cyclic(|this| {
+            RefCell::new(WaylandState {
+                this: this.clone(),
+                loop_handle: loop_handle.clone(),
+                windows: HashMap::default(),
+                pending_events: Vec::new(),
+                outputs: Vec::new(),
+                xkb_context: xkb::Context::new(xkb::CONTEXT_NO_FLAGS),
+                keyboard: None,
+                keyboard_focus: None,
+                key_repeat: KeyRepeat {
+                    rate: 25,
+                    delay: Duration::from_millis(600),
+                    current: None,
+                },
+                text_input,
+                pointer: None,
+                pointer_focus: None,
+                cursor_theme: CursorTheme::load(&conn, shm.clone(), CURSOR_SIZE).log_err(),
+                cursor_surface: None,
+                cursor_style: CursorStyle::Arrow,
+                scale: 1,
+            })
+        });
+
+        // Learn about the outputs, the seat's capabilities and its keymap
+        // before any windows are opened.
+        let mut event_queue = event_queue;
+        event_queue.roundtrip(&mut *state.borrow_mut())?;
+        event_queue.roundtrip(&mut *state.borrow_mut())?;
+        {
+            let mut state = state.borrow_mut();
+            state.scale = state
+                .outputs
+                .iter()
+                .map(|output| output.scale)
+                .max()
+                .unwrap_or(1);
+            state.cursor_surface = Some(compositor.create_surface(&qh, ()));
+        }
+
+        // SAFETY: the display pointer stays valid for as long as the
+        // connection is open, which is as long as the client exists.
+        let (_, clipboard) = unsafe {
+            wayland_clipboard::create_clipboards_from_external(conn.backend().display_ptr().cast())
+        };
+
+        let fd = conn.backend().poll_fd().as_raw_fd();
+        let client = Rc::new(Self {
+            conn,
+            qh,
+            compositor,
+            wm_base,
+            shm,
+            event_queue: RefCell::new(event_queue),
+            state,
+            clipboard: RefCell::new(clipboard),
+            loop_handle: loop_handle.clone(),
+        });
+
+        let this = Rc::downgrade(&client);
+        // SAFETY: the connection's file descriptor stays open for as long as
+        // the client does, and the source is removed when the client is gone.
+        let source = Generic::new(unsafe { FdWrapper::new(fd) }, Interest::READ, Mode::Level);
+        loop_handle
+            .insert_source(source, move |_, _, _| {
+                let Some(client) = this.upgrade() else {
+                    return Ok(PostAction::Remove);
+                };
+                client.dispatch();
+                Ok(PostAction::Continue)
+            })
+            .map_err(|error| error.error)
+            .context("failed to add the Wayland connection to the event loop")?;
+
+        Ok(client)
+    }
+
+    fn dispatch(&self) {
+        let mut event_queue = self.event_queue.borrow_mut();
+        if let Some(guard) = event_queue.prepare_read() {
+            guard.read().ok();
+        }
+        let pending_events = {
+            let mut state = self.state.borrow_mut();
+            event_queue.dispatch_pending(&mut *state).log_err();
+            mem::take(&mut state.pending_events)
+        };
+        drop(event_queue);
+
+        for (window, event) in pending_events {
+            match event {
+                WindowEvent::Configure { size, fullscreen } => {
+                    if let Some(size) = size {
+                        window.handle_resize(size, self.state.borrow().scale as f32);
+                    }
+                    window.handle_fullscreen(fullscreen);
+                    window.handle_expose();
+                }
+                WindowEvent::Close => window.handle_close_request(),
+                WindowEvent::ActiveStatusChange(active) => {
+                    window.handle_active_status_change(active)
+                }
+                WindowEvent::KeyDown(input) => window.handle_key_down(input, false),
+                WindowEvent::ImeCommit(text) => window.handle_ime_commit(text),
+                WindowEvent::KeyUp(keystroke) => window.handle_key_up(keystroke),
+                WindowEvent::Modifiers(modifiers) => window.handle_modifiers(modifiers),
+                WindowEvent::MouseMove(position) => window.handle_mouse_move(position),
+                WindowEvent::MouseExit => window.handle_mouse_exit(),
+                WindowEvent::MouseButton(button, pressed) => {
+                    window.handle_mouse_button(button, pressed)
+                }
+                WindowEvent::Scroll(delta) => window.handle_scroll(delta),
+            }
+        }
+        self.update_text_input_cursor();
+        self.conn.flush().log_err();
+    }
+
+    /// Tells the input method where the cursor is, so that it can show its
+    /// candidates next to it.
+    fn update_text_input_cursor(&self) {
+        let window = {
+            let state = self.state.borrow();
+            let Some(surface_id) = state
+                .text_input
+                .as_ref()
+                .and_then(|text_input| text_input.focus.as_ref())
+            else {
+                return;
+            };
+            state.window(surface_id)
+        };
+        let Some(bounds) = window.and_then(|window| window.ime_cursor_bounds()) else {
+            return;
+        };
+
+        let mut state = self.state.borrow_mut();
+        let Some(text_input) = state.text_input.as_mut() else {
+            return;
+        };
+        if text_input.cursor_rectangle == Some(bounds) {
+            return;
+        }
+        text_input.cursor_rectangle = Some(bounds);
+        text_input.text_input.set_cursor_rectangle(
+            bounds.origin.x.0 as i32,
+            bounds.origin.y.0 as i32,
+            bounds.size.width.0 as i32,
+            bounds.size.height.0 as i32,
+        );
+        text_input.text_input.commit();
+    }
+}
+
+impl WaylandState {
+    fn window(&self, surface_id: &ObjectId) -> Option<Rc<LinuxWindowState>> {
+        self.windows.get(surface_id)?.state.upgrade()
+    }
+
+    fn push_event(&mut self, surface_id: &ObjectId, event: WindowEvent) {
+        if let Some(window) = self.window(surface_id) {
+            self.pending_events.push((window, event));
+        }
+    }
+
+    fn focused_window(&self) -> Option<Rc<LinuxWindowState>> {
+        self.window(self.keyboard_focus.as_ref()?)
+    }
+
+    fn update_cursor(&mut self) {
+        let (Some(pointer), Some((_, serial)), Some(theme), Some(surface)) = (
+            self.pointer.as_ref(),
+            self.pointer_focus.as_ref(),
+            self.cursor_theme.as_mut(),
+            self.cursor_surface.as_ref(),
+        ) else {
+            return;
+        };
+        let cursor = match theme.get_cursor(cursor_name(self.cursor_style)) {
+            Some(cursor) => cursor,
+            None => match theme.get_cursor(cursor_name(CursorStyle::Arrow)) {
+                Some(cursor) => cursor,
+                None => return,
+            },
+        };
+        let image = &cursor[0];
+        let (hotspot_x, hotspot_y) = image.hotspot();
+        surface.attach(Some(&**image), 0, 0);
+        surface.damage_buffer(0, 0, i32::MAX, i32::MAX);
+        surface.commit();
+        pointer.set_cursor(*serial, Some(surface), hotspot_x as i32, hotspot_y as i32);
+    }
+
+    fn stop_key_repeat(&mut self) {
+        if let Some((_, token)) = self.key_repeat.current.take() {
+            self.loop_handle.remove(token);
+        }
+    }
+
+    /// Repeats a held key until it's released or the window loses focus.
+    fn start_key_repeat(&mut self, keycode: Keycode) {
+        self.stop_key_repeat();
+        if self.key_repeat.rate <= 0 {
+            return;
+        }
+        let interval = Duration::from_secs(1) / self.key_repeat.rate as u32;
+        let this = self.this.clone();
+        let token = self
+            .loop_handle
+            .insert_source(
+                Timer::from_duration(self.key_repeat.delay),
+                move |_, _, _| {
+                    let Some(this) = this.upgrade() else {
+                        return TimeoutAction::Drop;
+                    };
+                    let state = this.borrow();
+                    let (Some(keyboard), Some(window)) =
+                        (state.keyboard.as_ref(), state.focused_window())
+                    else {
+                        return TimeoutAction::Drop;
+                    };
+                    let keystroke = keyboard.keystroke(keycode);
+                    drop(state);
+                    window.handle_key_down(KeyInput::Keystroke(keystroke), true);
+                    TimeoutAction::ToDuration(interval)
+                },
+            )
+            .log_err();
+        self.key_repeat.current = token.map(|token| (keycode, token));
+    }
+}
+
+impl Client for WaylandClient {
+    fn displays(&self) -> Vec<Rc<dyn PlatformDisplay>> {
+        self.state
+            .borrow()
+            .outputs
+            .iter()
+            .map(|output| {
+                let scale = output.scale as f32;
+                Rc::new(LinuxDisplay::new(
+                    output.global_name,
+                    output
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| format!("output-{}", output.global_name)),
+                    Bounds {
+                        origin: point(
+                            GlobalPixels(output.origin.x as f32),
+                            GlobalPixels(output.origin.y as f32),
+                        ),
+                        size: size(
+                            GlobalPixels(output.size.width as f32 / scale),
+                            GlobalPixels(output.size.height as f32 / scale),
+                        ),
+                    },
+                )) as Rc<dyn PlatformDisplay>
+            })
+            .collect()
+    }
+
+    fn open_window(
+        &self,
+        handle: AnyWindowHandle,
+        options: WindowOptions,
+    ) -> Box<dyn PlatformWindow> {
+        let display = options
+            .display_id
+            .and_then(|id| self.display(id))
+            .or_else(|| self.displays().into_iter().next())
+            .unwrap_or_else(|| {
+                Rc::new(LinuxDisplay::new(0, "unknown".into(), Bounds::default()))
+                    as Rc<dyn PlatformDisplay>
+            });
+
+        let surface = self.compositor.create_surface(&self.qh, ());
+        let xdg_surface = self
+            .wm_base
+            .get_xdg_surface(&surface, &self.qh, surface.id());
+        let toplevel = xdg_surface.get_toplevel(&self.qh, surface.id());
+        if let Some(title) = options
+            .titlebar
+            .as_ref()
+            .and_then(|titlebar| titlebar.title.as_ref())
+        {
+            toplevel.set_title(title.to_string());
+        }
+        let bounds = match options.bounds {
+            WindowBounds::Fixed(bounds) => bounds,
+            WindowBounds::Maximized => {
+                toplevel.set_maximized();
+                display.bounds()
+            }
+            WindowBounds::Fullscreen => {
+                toplevel.set_fullscreen(None);
+                display.bounds()
+            }
+        };
+
+        let scale = self.state.borrow().scale;
+        let surface_id = surface.id();
+        let native = Rc::new(WaylandWindow::new(
+            self.conn.clone(),
+            self.qh.clone(),
+            self.shm.clone(),
+            surface,
+            xdg_surface,
+            toplevel,
+            scale,
+        ));
+        let window = LinuxWindowState::new(
+            handle,
+            Box::new(native.clone()),
+            display,
+            size(px(bounds.size.width.0), px(bounds.size.height.0)),
+            native.scale() as f32,
+            &self.loop_handle,
+        );
+        let mut state = self.state.borrow_mut();
+        state
+            .windows
+            .retain(|_, entry| entry.state.strong_count() > 0);
+        state.windows.insert(
+            surface_id,
+            WindowEntry {
+                state: Rc::downgrade(&window),
+                native: Rc::downgrade(&native),
+                pending_configure: None,
+            },
+        );
+        drop(state);
+        self.conn.flush().log_err();
+        Box::new(LinuxWindow(window))
+    }
+
+    fn active_window(&self) -> Option<AnyWindowHandle> {
+        self.state
+            .borrow()
+            .focused_window()
+            .map(|window| window.handle())
+    }
+
+    fn set_cursor_style(&self, style: CursorStyle) {
+        let mut state = self.state.borrow_mut();
+        state.cursor_style = style;
+        state.update_cursor();
+        drop(state);
+        self.conn.flush().log_err();
+    }
+
+    fn write_to_clipboard(&self, text: String) {
+        self.clipboard
+            .borrow_mut()
+            .set_contents(text)
+            .map_err(|error| log::error!("failed to write to the clipboard: {error}"))
+            .ok();
+    }
+
+    fn read_from_clipboard(&self) -> Option<String> {
+        self.clipboard.borrow_mut().get_contents().ok()
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+delegate_noop!(WaylandState: ignore wl_compositor::WlCompositor);
+delegate_noop!(WaylandState: ignore wl_surface::WlSurface);
+delegate_noop!(WaylandState: ignore wl_shm::WlShm);
+delegate_noop!(WaylandState: ignore wl_shm_pool::WlShmPool);
+delegate_noop!(WaylandState: ignore zwp_text_input_manager_v3::ZwpTextInputManagerV3);
+
+impl Dispatch<wl_buffer::WlBuffer, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        buffer: &wl_buffer::WlBuffer,
+        event: wl_buffer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_buffer::Event::Release = event {
+            buffer.destroy();
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, u32> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &wl_output::WlOutput,
+        event: wl_output::Event,
+        global_name: &u32,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let output = match state
+            .outputs
+            .iter()
+            .position(|output| output.global_name == *global_name)
+        {
+            Some(ix) => &mut state.outputs[ix],
+            None => {
+                state.outputs.push(Output {
+                    global_name: *global_name,
+                    name: None,
+                    origin: Point::default(),
+                    size: Size::default(),
+                    scale: 1,
+                });
+                state.outputs.last_mut().unwrap()
+            }
+        };
+        match event {
+            wl_output::Event::Geometry { x, y, .. } => output.origin = point(x, y),
+            wl_output::Event::Mode {
+                flags: WEnum::Value(flags),
+                width,
+                height,
+                ..
+            } if flags.contains(wl_output::Mode::Current) => output.size = size(width, height),
+            wl_output::Event::Scale { factor } => output.scale = factor,
+            wl_output::Event::Name { name } => output.name = Some(name),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<xdg_wm_base::XdgWmBase, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        wm_base: &xdg_wm_base::XdgWmBase,
+        event: xdg_wm_base::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let xdg_wm_base::Event::Ping { serial } = event {
+            wm_base.pong(serial);
+        }
+    }
+}
+
+impl Dispatch<xdg_surface::XdgSurface, ObjectId> for WaylandState {
+    fn event(
+        state: &mut Self,
+        xdg_surface: &xdg_surface::XdgSurface,
+        event: xdg_surface::Event,
+        surface_id: &ObjectId,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let xdg_surface::Event::Configure { serial } = event else {
+            return;
+        };
+        xdg_surface.ack_configure(serial);
+        let Some(entry) = state.windows.get_mut(surface_id) else {
+            return;
+        };
+        let (size, states) = entry.pending_configure.take().unwrap_or_default();
+        if let Some(native) = entry.native.upgrade() {
+            native.set_configured(states.contains(&xdg_toplevel::State::Maximized));
+        }
+        let fullscreen = states.contains(&xdg_toplevel::State::Fullscreen);
+        state.push_event(surface_id, WindowEvent::Configure { size, fullscreen });
+    }
+}
+
+impl Dispatch<xdg_toplevel::XdgToplevel, ObjectId> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &xdg_toplevel::XdgToplevel,
+        event: xdg_toplevel::Event,
+        surface_id: &ObjectId,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            xdg_toplevel::Event::Configure {
+                width,
+                height,
+                states,
+            } => {
+                // A size of zero leaves the choice to us, so we keep ours.
+                let size =
+                    (width > 0 && height > 0).then(|| size(px(width as f32), px(height as f32)));
+                let states = states
+                    .chunks_exact(4)
+                    .filter_map(|chunk| {
+                        let state = u32::from_ne_bytes(chunk.try_into().ok()?);
+                        xdg_toplevel::State::try_from(state).ok()
+                    })
+                    .collect();
+                if let Some(entry) = state.windows.get_mut(surface_id) {
+                    entry.pending_configure = Some((size, states));
+                }
+            }
+            xdg_toplevel::Event::Close => state.push_event(surface_id, WindowEvent::Close),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        seat: &wl_seat::WlSeat,
+        event: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_seat::Event::Capabilities {
+            capabilities: WEnum::Value(capabilities),
+        } = event
+        {
+            if capabilities.contains(wl_seat::Capability::Keyboard) {
+                seat.get_keyboard(qh, ());
+            }
+            if capabilities.contains(wl_seat::Capability::Pointer) {
+                state.pointer = Some(seat.get_pointer(qh, ()));
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_keyboard::WlKeyboard, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &wl_keyboard::WlKeyboard,
+        event: wl_keyboard::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_keyboard::Event::Keymap {
+                format: WEnum::Value(wl_keyboard::KeymapFormat::XkbV1),
+                fd,
+                size,
+            } => {
+                // SAFETY: the compositor sends a file containing a keymap of
+                // the given size.
+                let keymap = unsafe {
+                    xkb::Keymap::new_from_fd(
+                        &state.xkb_context,
+                        fd,
+                        size as usize,
+                        xkb::KEYMAP_FORMAT_TEXT_V1,
+                        xkb::KEYMAP_COMPILE_NO_FLAGS,
+                    )
+                };
+                match keymap {
+                    Ok(Some(keymap)) => {
+                        state.keyboard =
+                            Some(Keyboard::new(&state.xkb_context, xkb::State::new(&keymap)));
+                    }
+                    Ok(None) => log::error!("the compositor sent an invalid keymap"),
+                    Err(error) => log::error!("failed to load the keymap: {error}"),
+                }
+            }
+            wl_keyboard::Event::RepeatInfo { rate, delay } => {
+                state.key_repeat.rate = rate;
+                state.key_repeat.delay = Duration::from_millis(delay.max(0) as u64);
+            }
+            wl_keyboard::Event::Enter { surface, .. } => {
+                let surface_id = surface.id();
+                state.push_event(&surface_id, WindowEvent::ActiveStatusChange(true));
+                state.keyboard_focus = Some(surface_id);
+            }
+            wl_keyboard::Event::Leave { surface, .. } => {
+                state.stop_key_repeat();
+                state.push_event(&surface.id(), WindowEvent::ActiveStatusChange(false));
+                state.keyboard_focus = None;
+            }
+            wl_keyboard::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => {
+                let Some(keyboard) = state.keyboard.as_mut() else {
+                    return;
+                };
+                keyboard.update_mask(mods_depressed, mods_latched, mods_locked, group);
+                let modifiers = keyboard.modifiers();
+                if let Some(surface_id) = state.keyboard_focus.clone() {
+                    state.push_event(&surface_id, WindowEvent::Modifiers(modifiers));
+                }
+            }
+            wl_keyboard::Event::Key {
+                key,
+                state: WEnum::Value(key_state),
+                ..
+            } => {
+                let (Some(keyboard), Some(surface_id)) =
+                    (state.keyboard.as_mut(), state.keyboard_focus.clone())
+                else {
+                    return;
+                };
+                // Wayland sends evdev key codes, which XKB offsets by 8.
+                let keycode = Keycode::new(key + 8);
+                let ime_enabled = state
+                    .text_input
+                    .as_ref()
+                    .map_or(false, |text_input| text_input.focus.is_some());
+                match key_state {
+                    wl_keyboard::KeyState::Pressed => {
+                        let input = if ime_enabled {
+                            KeyInput::Keystroke(keyboard.keystroke(keycode))
+                        } else {
+                            keyboard.key_down(keycode)
+                        };
+                        let repeats = keyboard.key_repeats(keycode);
+                        state.push_event(&surface_id, WindowEvent::KeyDown(input));
+                        if repeats {
+                            state.start_key_repeat(keycode);
+                        }
+                    }
+                    wl_keyboard::KeyState::Released => {
+                        let keystroke = keyboard.keystroke(keycode);
+                        state.push_event(&surface_id, WindowEvent::KeyUp(keystroke));
+                        if state
+                            .key_repeat
+                            .current
+                            .as_ref()
+                            .map_or(false, |(repeating, _)| *repeating == keycode)
+                        {
+                            state.stop_key_repeat();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_pointer::WlPointer, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &wl_pointer::WlPointer,
+        event: wl_pointer::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_pointer::Event::Enter {
+                serial,
+                surface,
+                surface_x,
+                surface_y,
+            } => {
+                let surface_id = surface.id();
+                state.pointer_focus = Some((surface_id.clone(), serial));
+                state.update_cursor();
+                let position = point(px(surface_x as f32), px(surface_y as f32));
+                state.push_event(&surface_id, WindowEvent::MouseMove(position));
+            }
+            wl_pointer::Event::Leave { surface, .. } => {
+                state.pointer_focus = None;
+                state.push_event(&surface.id(), WindowEvent::MouseExit);
+            }
+            wl_pointer::Event::Motion {
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                let Some((surface_id, _)) = state.pointer_focus.clone() else {
+                    return;
+                };
+                let position = point(px(surface_x as f32), px(surface_y as f32));
+                state.push_event(&surface_id, WindowEvent::MouseMove(position));
+            }
+            wl_pointer::Event::Button {
+                button,
+                state: WEnum::Value(button_state),
+                ..
+            } => {
+                let Some((surface_id, _)) = state.pointer_focus.clone() else {
+                    return;
+                };
+                let button = match button {
+                    BTN_LEFT => MouseButton::Left,
+                    BTN_RIGHT => MouseButton::Right,
+                    BTN_MIDDLE => MouseButton::Middle,
+                    BTN_SIDE => MouseButton::Navigate(NavigationDirection::Back),
+                    BTN_EXTRA => MouseButton::Navigate(NavigationDirection::Forward),
+                    _ => return,
+                };
+                let pressed = button_state == wl_pointer::ButtonState::Pressed;
+                state.push_event(&surface_id, WindowEvent::MouseButton(button, pressed));
+            }
+            wl_pointer::Event::Axis {
+                axis: WEnum::Value(axis),
+                value,
+                ..
+            } => {
+                let Some((surface_id, _)) = state.pointer_focus.clone() else {
+                    return;
+                };
+                // Positive values scroll down or right, the opposite of ours.
+                let value = px(-value as f32);
+                let delta = match axis {
+                    wl_pointer::Axis::VerticalScroll => point(px(0.), value),
+                    wl_pointer::Axis::HorizontalScroll => point(value, px(0.)),
+                    _ => return,
+                };
+                state.push_event(&surface_id, WindowEvent::Scroll(ScrollDelta::Pixels(delta)));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<zwp_text_input_v3::ZwpTextInputV3, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &zwp_text_input_v3::ZwpTextInputV3,
+        event: zwp_text_input_v3::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(text_input) = state.text_input.as_mut() else {
+            return;
+        };
+        match event {
+            zwp_text_input_v3::Event::Enter { surface } => {
+                text_input.text_input.enable();
+                text_input.text_input.set_content_type(
+                    zwp_text_input_v3::ContentHint::None,
+                    zwp_text_input_v3::ContentPurpose::Normal,
+                );
+                text_input.text_input.commit();
+                text_input.focus = Some(surface.id());
+                text_input.cursor_rectangle = None;
+            }
+            zwp_text_input_v3::Event::Leave { surface } => {
+                text_input.text_input.disable();
+                text_input.text_input.commit();
+                text_input.focus = None;
+                text_input.pending_preedit = None;
+                text_input.pending_commit = None;
+                if mem::take(&mut text_input.has_preedit) {
+                    state.push_event(&surface.id(), WindowEvent::KeyDown(KeyInput::CancelPreedit));
+                }
+            }
+            zwp_text_input_v3::Event::PreeditString { text, .. } => {
+                text_input.pending_preedit = text;
+            }
+            zwp_text_input_v3::Event::CommitString { text } => {
+                text_input.pending_commit = text;
+            }
+            zwp_text_input_v3::Event::Done { .. } => {
+                let Some(surface_id) = text_input.focus.clone() else {
+                    return;
+                };
+                let commit = text_input.pending_commit.take();
+                let preedit = text_input
+                    .pending_preedit
+                    .take()
+                    .filter(|preedit| !preedit.is_empty());
+                let had_preedit = mem::replace(&mut text_input.has_preedit, preedit.is_some());
+
+                // Committed text replaces the preedit text, after which the
+                // new preedit text is inserted.
+                if let Some(commit) = commit {
+                    state.push_event(&surface_id, WindowEvent::ImeCommit(commit));
+                }
+                match preedit {
+                    Some(preedit) => state.push_event(
+                        &surface_id,
+                        WindowEvent::KeyDown(KeyInput::Preedit(preedit)),
+                    ),
+                    None if had_preedit => {
+                        state.push_event(&surface_id, WindowEvent::KeyDown(KeyInput::CancelPreedit))
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use super::WaylandState;
+use crate::platform::linux::{bgra_pixels, NativeWindow};
+use anyhow::{Context as _, Result};
+use image::RgbaImage;
+use memfd::MemfdOptions;
+use raw_window_handle::{
+    RawDisplayHandle, RawWindowHandle, WaylandDisplayHandle, WaylandWindowHandle,
+};
+use std::{cell::Cell, io::Write as _, os::fd::AsFd as _, ptr::NonNull};
+use util::ResultExt as _;
+use wayland_client::{
+    protocol::{wl_buffer::WlBuffer, wl_shm, wl_surface::WlSurface},
+    Connection, Proxy as _, QueueHandle,
+};
+use wayland_protocols::xdg::shell::client::{xdg_surface::XdgSurface, xdg_toplevel::XdgToplevel};
+
+pub(crate) struct WaylandWindow {
+    conn: Connection,
+    qh: QueueHandle<WaylandState>,
+    shm: wl_shm::WlShm,
+    surface: WlSurface,
+    xdg_surface: XdgSurface,
+    toplevel: XdgToplevel,
+    /// The number of device pixels per surface coordinate.
+    scale: i32,
+    /// Buffers may only be attached once the compositor has configured the
+    /// surface.
+    configured: Cell<bool>,
+    maximized: Cell<bool>,
+}
+
+impl WaylandWindow {
+    pub fn new(
+        conn: Connection,
+        qh: QueueHandle<WaylandState>,
+        shm: wl_shm::WlShm,
+        surface: WlSurface,
+        xdg_surface: XdgSurface,
+        toplevel: XdgToplevel,
+        scale: i32,
+    ) -> Self {
+        surface.set_buffer_scale(scale);
+        surface.commit();
+        Self {
+            conn,
+            qh,
+            shm,
+            surface,
+            xdg_surface,
+            toplevel,
+            scale,
+            configured: Cell::new(false),
+            maximized: Cell::new(false),
+        }
+    }
+
+    pub fn set_configured(&self, maximized: bool) {
+        self.configured.set(true);
+        self.maximized.set(maximized);
+    }
+
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    /// Copies a frame into a new shared memory buffer. The buffer destroys
+    /// itself once the compositor releases it.
+    fn create_buffer(&self, image: &RgbaImage) -> Result<WlBuffer> {
+        let pixels = bgra_pixels(image);
+        let mut file = MemfdOptions::default()
+            .close_on_exec(true)
+            .create("gpui-frame")
+            .context("failed to create a shared memory file")?
+            .into_file();
+        file.write_all(&pixels)?;
+
+        let pool = self
+            .shm
+            .create_pool(file.as_fd(), pixels.len() as i32, &self.qh, ());
+        let buffer = pool.create_buffer(
+            0,
+            image.width() as i32,
+            image.height() as i32,
+            image.width() as i32 * 4,
+            wl_shm::Format::Argb8888,
+            &self.qh,
+            (),
+        );
+        pool.destroy();
+        Ok(buffer)
+    }
+}
+
+impl Drop for WaylandWindow {
+    fn drop(&mut self) {
+        self.toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.surface.destroy();
+        self.conn.flush().log_err();
+    }
+}
+
+impl NativeWindow for WaylandWindow {
+    fn present(&self, image: &RgbaImage) {
+        if !self.configured.get() {
+            return;
+        }
+        let Some(buffer) = self.create_buffer(image).log_err() else {
+            return;
+        };
+        self.surface.attach(Some(&buffer), 0, 0);
+        self.surface
+            .damage_buffer(0, 0, image.width() as i32, image.height() as i32);
+        self.surface.commit();
+        self.conn.flush().log_err();
+    }
+
+    fn set_title(&self, title: &str) {
+        self.toplevel.set_title(title.to_string());
+        self.conn.flush().log_err();
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool) {
+        if fullscreen {
+            self.toplevel.set_fullscreen(None);
+        } else {
+            self.toplevel.unset_fullscreen();
+        }
+        self.conn.flush().log_err();
+    }
+
+    fn minimize(&self) {
+        self.toplevel.set_minimized();
+        self.conn.flush().log_err();
+    }
+
+    fn zoom(&self) {
+        if self.maximized.get() {
+            self.toplevel.unset_maximized();
+        } else {
+            self.toplevel.set_maximized();
+        }
+        self.conn.flush().log_err();
+    }
+
+    // Activating a window needs a token from the xdg-activation protocol,
+    // which is only handed out in response to user input.
+    fn activate(&self) {}
+
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        let surface = NonNull::new(self.surface.id().as_ptr().cast())
+            .expect("the surface was created by this client");
+        WaylandWindowHandle::new(surface).into()
+    }
+
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        let display = NonNull::new(self.conn.backend().display_ptr().cast())
+            .expect("the connection was created by this client");
+        WaylandDisplayHandle::new(display).into()
+    }
+}
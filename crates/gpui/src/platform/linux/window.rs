@@ -0,0 +1,602 @@
+use super::{dialogs, keyboard::KeyInput};
+use crate::{
+    px, AnyWindowHandle, Bounds, DevicePixels, GlobalPixels, KeyDownEvent, KeyUpEvent, Keystroke,
+    Modifiers, ModifiersChangedEvent, MouseButton, MouseDownEvent, MouseExitEvent, MouseMoveEvent,
+    MouseUpEvent, Pixels, PlatformAtlas, PlatformDisplay, PlatformInput, PlatformInputHandler,
+    PlatformWindow, Point, PromptLevel, Scene, ScrollDelta, ScrollWheelEvent, Size, SoftwareAtlas,
+    SoftwareRenderer, TouchPhase, WindowAppearance, WindowBounds,
+};
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle,
+};
+use futures::channel::oneshot;
+use image::RgbaImage;
+use raw_window_handle::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle,
+    RawWindowHandle, WindowHandle,
+};
+use std::{
+    any::Any,
+    cell::RefCell,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// How long may pass between clicks for them to count as a double click.
+pub(crate) const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// How far the mouse may move between clicks for them to count as a double
+/// click.
+const DOUBLE_CLICK_DISTANCE: Pixels = px(5.);
+
+/// How often windows are asked to draw a frame.
+const FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// The parts of a window that differ between X11 and Wayland.
+pub(crate) trait NativeWindow {
+    /// Shows a frame that was drawn at the window's size in device pixels.
+    fn present(&self, image: &RgbaImage);
+    fn set_title(&self, title: &str);
+    fn set_fullscreen(&self, fullscreen: bool);
+    fn minimize(&self);
+    fn zoom(&self);
+    fn activate(&self);
+    fn raw_window_handle(&self) -> RawWindowHandle;
+    fn raw_display_handle(&self) -> RawDisplayHandle;
+}
+
+/// Converts a frame to the little endian 32 bit pixels that both X11 and
+/// Wayland expect, which are laid out in memory as BGRA.
+pub(crate) fn bgra_pixels(image: &RgbaImage) -> Vec<u8> {
+    let mut pixels = image.as_raw().clone();
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    pixels
+}
+
+impl<T: NativeWindow> NativeWindow for Rc<T> {
+    fn present(&self, image: &RgbaImage) {
+        T::present(self, image)
+    }
+
+    fn set_title(&self, title: &str) {
+        T::set_title(self, title)
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool) {
+        T::set_fullscreen(self, fullscreen)
+    }
+
+    fn minimize(&self) {
+        T::minimize(self)
+    }
+
+    fn zoom(&self) {
+        T::zoom(self)
+    }
+
+    fn activate(&self) {
+        T::activate(self)
+    }
+
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        T::raw_window_handle(self)
+    }
+
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        T::raw_display_handle(self)
+    }
+}
+
+#[derive(Default)]
+struct Callbacks {
+    request_frame: Option<Box<dyn FnMut()>>,
+    input: Option<Box<dyn FnMut(PlatformInput) -> bool>>,
+    active_status_change: Option<Box<dyn FnMut(bool)>>,
+    resize: Option<Box<dyn FnMut(Size<Pixels>, f32)>>,
+    fullscreen: Option<Box<dyn FnMut(bool)>>,
+    moved: Option<Box<dyn FnMut()>>,
+    should_close: Option<Box<dyn FnMut() -> bool>>,
+    close: Option<Box<dyn FnOnce()>>,
+}
+
+struct WindowState {
+    origin: Point<Pixels>,
+    size: Size<Pixels>,
+    scale_factor: f32,
+    fullscreen: bool,
+    active: bool,
+    mouse_position: Point<Pixels>,
+    modifiers: Modifiers,
+    pressed_button: Option<MouseButton>,
+    last_click: Option<(MouseButton, Point<Pixels>, Instant)>,
+    click_count: usize,
+}
+
+/// A window on X11 or Wayland. The backend owns the native window and feeds
+/// its events in through the `handle_*` methods; everything else is shared.
+pub(crate) struct LinuxWindowState {
+    handle: AnyWindowHandle,
+    native: Box<dyn NativeWindow>,
+    display: Rc<dyn PlatformDisplay>,
+    sprite_atlas: Arc<SoftwareAtlas>,
+    last_frame: RefCell<Option<RgbaImage>>,
+    state: RefCell<WindowState>,
+    callbacks: RefCell<Callbacks>,
+    input_handler: RefCell<Option<PlatformInputHandler>>,
+}
+
+impl LinuxWindowState {
+    pub fn new(
+        handle: AnyWindowHandle,
+        native: Box<dyn NativeWindow>,
+        display: Rc<dyn PlatformDisplay>,
+        size: Size<Pixels>,
+        scale_factor: f32,
+        event_loop: &LoopHandle<'static, ()>,
+    ) -> Rc<Self> {
+        let this = Rc::new(Self {
+            handle,
+            native,
+            display,
+            sprite_atlas: Arc::new(SoftwareAtlas::new()),
+            last_frame: Default::default(),
+            state: RefCell::new(WindowState {
+                origin: Point::default(),
+                size,
+                scale_factor,
+                fullscreen: false,
+                active: false,
+                mouse_position: Point::default(),
+                modifiers: Modifiers::default(),
+                pressed_button: None,
+                last_click: None,
+                click_count: 0,
+            }),
+            callbacks: Default::default(),
+            input_handler: Default::default(),
+        });
+
+        let window = Rc::downgrade(&this);
+        event_loop
+            .insert_source(Timer::immediate(), move |_, _, _| {
+                let Some(window) = window.upgrade() else {
+                    return TimeoutAction::Drop;
+                };
+                window.request_frame();
+                TimeoutAction::ToDuration(FRAME_INTERVAL)
+            })
+            .expect("failed to add the frame timer to the event loop");
+        this
+    }
+
+    pub fn handle(&self) -> AnyWindowHandle {
+        self.handle
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state.borrow().active
+    }
+
+    fn request_frame(&self) {
+        let Some(mut callback) = self.callbacks.borrow_mut().request_frame.take() else {
+            return;
+        };
+        callback();
+        self.callbacks
+            .borrow_mut()
+            .request_frame
+            .get_or_insert(callback);
+    }
+
+    /// Shows the last frame again, after the display server discarded the
+    /// window's contents.
+    pub fn handle_expose(&self) {
+        if let Some(frame) = self.last_frame.borrow().as_ref() {
+            self.native.present(frame);
+        }
+    }
+
+    pub fn handle_input(&self, event: PlatformInput) -> bool {
+        let Some(mut callback) = self.callbacks.borrow_mut().input.take() else {
+            return false;
+        };
+        let handled = callback(event);
+        self.callbacks.borrow_mut().input.get_or_insert(callback);
+        handled
+    }
+
+    fn with_input_handler<R>(&self, f: impl FnOnce(&mut PlatformInputHandler) -> R) -> Option<R> {
+        let mut input_handler = self.input_handler.borrow_mut().take()?;
+        let result = f(&mut input_handler);
+        self.input_handler.borrow_mut().get_or_insert(input_handler);
+        Some(result)
+    }
+
+    pub fn handle_key_down(&self, input: KeyInput, is_held: bool) {
+        match input {
+            KeyInput::Keystroke(keystroke) => {
+                let handled = self.handle_input(PlatformInput::KeyDown(KeyDownEvent {
+                    keystroke: keystroke.clone(),
+                    is_held,
+                }));
+                if let Some(text) = keystroke.ime_key.filter(|_| !handled) {
+                    self.handle_ime_commit(text);
+                }
+            }
+            KeyInput::Preedit(text) => {
+                self.with_input_handler(|input_handler| {
+                    let len = text.encode_utf16().count();
+                    input_handler.replace_and_mark_text_in_range(None, &text, Some(len..len));
+                });
+            }
+            KeyInput::CancelPreedit => {
+                self.with_input_handler(|input_handler| {
+                    input_handler.replace_and_mark_text_in_range(None, "", None);
+                    input_handler.unmark_text();
+                });
+            }
+        }
+    }
+
+    /// Inserts text composed by an input method, replacing any preedit text.
+    pub fn handle_ime_commit(&self, text: String) {
+        self.with_input_handler(|input_handler| input_handler.replace_text_in_range(None, &text));
+    }
+
+    /// The bounds of the selection, next to which input methods show their
+    /// candidates.
+    pub fn ime_cursor_bounds(&self) -> Option<Bounds<Pixels>> {
+        self.with_input_handler(|input_handler| {
+            let range = input_handler.selected_text_range()?;
+            input_handler.bounds_for_range(range)
+        })
+        .flatten()
+    }
+
+    pub fn handle_key_up(&self, keystroke: Keystroke) {
+        self.handle_input(PlatformInput::KeyUp(KeyUpEvent { keystroke }));
+    }
+
+    pub fn handle_modifiers(&self, modifiers: Modifiers) {
+        let mut state = self.state.borrow_mut();
+        if state.modifiers == modifiers {
+            return;
+        }
+        state.modifiers = modifiers;
+        drop(state);
+        self.handle_input(PlatformInput::ModifiersChanged(ModifiersChangedEvent {
+            modifiers,
+        }));
+    }
+
+    pub fn handle_mouse_move(&self, position: Point<Pixels>) {
+        let mut state = self.state.borrow_mut();
+        state.mouse_position = position;
+        let event = MouseMoveEvent {
+            position,
+            pressed_button: state.pressed_button,
+            modifiers: state.modifiers,
+        };
+        drop(state);
+        self.handle_input(PlatformInput::MouseMove(event));
+    }
+
+    pub fn handle_mouse_exit(&self) {
+        let state = self.state.borrow();
+        let event = MouseExitEvent {
+            position: state.mouse_position,
+            pressed_button: state.pressed_button,
+            modifiers: state.modifiers,
+        };
+        drop(state);
+        self.handle_input(PlatformInput::MouseExited(event));
+    }
+
+    pub fn handle_mouse_button(&self, button: MouseButton, pressed: bool) {
+        let mut state = self.state.borrow_mut();
+        let position = state.mouse_position;
+        let modifiers = state.modifiers;
+        let event = if pressed {
+            let now = Instant::now();
+            let is_repeated_click =
+                state
+                    .last_click
+                    .map_or(false, |(last_button, last_position, last_time)| {
+                        last_button == button
+                            && now - last_time < DOUBLE_CLICK_INTERVAL
+                            && (position.x - last_position.x).abs() < DOUBLE_CLICK_DISTANCE
+                            && (position.y - last_position.y).abs() < DOUBLE_CLICK_DISTANCE
+                    });
+            state.click_count = if is_repeated_click {
+                state.click_count + 1
+            } else {
+                1
+            };
+            state.last_click = Some((button, position, now));
+            state.pressed_button = Some(button);
+            PlatformInput::MouseDown(MouseDownEvent {
+                button,
+                position,
+                modifiers,
+                click_count: state.click_count,
+            })
+        } else {
+            state.pressed_button = None;
+            PlatformInput::MouseUp(MouseUpEvent {
+                button,
+                position,
+                modifiers,
+                click_count: state.click_count,
+            })
+        };
+        drop(state);
+        self.handle_input(event);
+    }
+
+    pub fn handle_scroll(&self, delta: ScrollDelta) {
+        let state = self.state.borrow();
+        let event = ScrollWheelEvent {
+            position: state.mouse_position,
+            delta,
+            modifiers: state.modifiers,
+            touch_phase: TouchPhase::Moved,
+        };
+        drop(state);
+        self.handle_input(PlatformInput::ScrollWheel(event));
+    }
+
+    pub fn handle_resize(&self, size: Size<Pixels>, scale_factor: f32) {
+        let mut state = self.state.borrow_mut();
+        if state.size == size && state.scale_factor == scale_factor {
+            return;
+        }
+        state.size = size;
+        state.scale_factor = scale_factor;
+        drop(state);
+
+        let Some(mut callback) = self.callbacks.borrow_mut().resize.take() else {
+            return;
+        };
+        callback(size, scale_factor);
+        self.callbacks.borrow_mut().resize.get_or_insert(callback);
+    }
+
+    pub fn handle_move(&self, origin: Point<Pixels>) {
+        self.state.borrow_mut().origin = origin;
+        let Some(mut callback) = self.callbacks.borrow_mut().moved.take() else {
+            return;
+        };
+        callback();
+        self.callbacks.borrow_mut().moved.get_or_insert(callback);
+    }
+
+    pub fn handle_active_status_change(&self, active: bool) {
+        let mut state = self.state.borrow_mut();
+        if state.active == active {
+            return;
+        }
+        state.active = active;
+        drop(state);
+
+        let Some(mut callback) = self.callbacks.borrow_mut().active_status_change.take() else {
+            return;
+        };
+        callback(active);
+        self.callbacks
+            .borrow_mut()
+            .active_status_change
+            .get_or_insert(callback);
+    }
+
+    pub fn handle_fullscreen(&self, fullscreen: bool) {
+        let mut state = self.state.borrow_mut();
+        if state.fullscreen == fullscreen {
+            return;
+        }
+        state.fullscreen = fullscreen;
+        drop(state);
+
+        let Some(mut callback) = self.callbacks.borrow_mut().fullscreen.take() else {
+            return;
+        };
+        callback(fullscreen);
+        self.callbacks
+            .borrow_mut()
+            .fullscreen
+            .get_or_insert(callback);
+    }
+
+    /// Handles the window manager asking for the window to close, closing it
+    /// unless the application objects.
+    pub fn handle_close_request(&self) {
+        let should_close = self.callbacks.borrow_mut().should_close.take();
+        let should_close = match should_close {
+            Some(mut callback) => {
+                let should_close = callback();
+                self.callbacks
+                    .borrow_mut()
+                    .should_close
+                    .get_or_insert(callback);
+                should_close
+            }
+            None => true,
+        };
+        if should_close {
+            let close = self.callbacks.borrow_mut().close.take();
+            if let Some(close) = close {
+                close();
+            }
+        }
+    }
+}
+
+pub(crate) struct LinuxWindow(pub(crate) Rc<LinuxWindowState>);
+
+impl HasWindowHandle for LinuxWindow {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        // SAFETY: the native window lives as long as this window does.
+        Ok(unsafe { WindowHandle::borrow_raw(self.0.native.raw_window_handle()) })
+    }
+}
+
+impl HasDisplayHandle for LinuxWindow {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        // SAFETY: the connection to the display server outlives every window.
+        Ok(unsafe { DisplayHandle::borrow_raw(self.0.native.raw_display_handle()) })
+    }
+}
+
+impl PlatformWindow for LinuxWindow {
+    fn bounds(&self) -> WindowBounds {
+        let state = self.0.state.borrow();
+        if state.fullscreen {
+            WindowBounds::Fullscreen
+        } else {
+            WindowBounds::Fixed(Bounds {
+                origin: state.origin.map(|x| GlobalPixels(x.0)),
+                size: state.size.map(|x| GlobalPixels(x.0)),
+            })
+        }
+    }
+
+    fn content_size(&self) -> Size<Pixels> {
+        self.0.state.borrow().size
+    }
+
+    fn scale_factor(&self) -> f32 {
+        self.0.state.borrow().scale_factor
+    }
+
+    // Windows are decorated by the window manager, outside of their content.
+    fn titlebar_height(&self) -> Pixels {
+        px(0.)
+    }
+
+    fn appearance(&self) -> WindowAppearance {
+        WindowAppearance::Light
+    }
+
+    fn display(&self) -> Rc<dyn PlatformDisplay> {
+        self.0.display.clone()
+    }
+
+    fn mouse_position(&self) -> Point<Pixels> {
+        self.0.state.borrow().mouse_position
+    }
+
+    fn modifiers(&self) -> Modifiers {
+        self.0.state.borrow().modifiers
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn set_input_handler(&mut self, input_handler: PlatformInputHandler) {
+        *self.0.input_handler.borrow_mut() = Some(input_handler);
+    }
+
+    fn take_input_handler(&mut self) -> Option<PlatformInputHandler> {
+        self.0.input_handler.borrow_mut().take()
+    }
+
+    fn prompt(
+        &self,
+        level: PromptLevel,
+        msg: &str,
+        detail: Option<&str>,
+        answers: &[&str],
+    ) -> oneshot::Receiver<usize> {
+        dialogs::prompt(level, msg, detail, answers)
+    }
+
+    fn activate(&self) {
+        self.0.native.activate();
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.0.native.set_title(title);
+    }
+
+    fn set_edited(&mut self, _edited: bool) {}
+
+    fn show_character_palette(&self) {}
+
+    fn minimize(&self) {
+        self.0.native.minimize();
+    }
+
+    fn zoom(&self) {
+        self.0.native.zoom();
+    }
+
+    fn toggle_full_screen(&self) {
+        let fullscreen = self.0.state.borrow().fullscreen;
+        self.0.native.set_fullscreen(!fullscreen);
+    }
+
+    fn on_request_frame(&self, callback: Box<dyn FnMut()>) {
+        self.0.callbacks.borrow_mut().request_frame = Some(callback);
+    }
+
+    fn on_input(&self, callback: Box<dyn FnMut(PlatformInput) -> bool>) {
+        self.0.callbacks.borrow_mut().input = Some(callback);
+    }
+
+    fn on_active_status_change(&self, callback: Box<dyn FnMut(bool)>) {
+        self.0.callbacks.borrow_mut().active_status_change = Some(callback);
+    }
+
+    fn on_resize(&self, callback: Box<dyn FnMut(Size<Pixels>, f32)>) {
+        self.0.callbacks.borrow_mut().resize = Some(callback);
+    }
+
+    fn on_fullscreen(&self, callback: Box<dyn FnMut(bool)>) {
+        self.0.callbacks.borrow_mut().fullscreen = Some(callback);
+    }
+
+    fn on_moved(&self, callback: Box<dyn FnMut()>) {
+        self.0.callbacks.borrow_mut().moved = Some(callback);
+    }
+
+    fn on_should_close(&self, callback: Box<dyn FnMut() -> bool>) {
+        self.0.callbacks.borrow_mut().should_close = Some(callback);
+    }
+
+    fn on_close(&self, callback: Box<dyn FnOnce()>) {
+        self.0.callbacks.borrow_mut().close = Some(callback);
+    }
+
+    // The appearance is always light, so it never changes.
+    fn on_appearance_changed(&self, _callback: Box<dyn FnMut()>) {}
+
+    // Neither X11 nor Wayland let us ask which window is on top at a point,
+    // so we assume that it's this one whenever the point is inside it.
+    fn is_topmost_for_position(&self, position: Point<Pixels>) -> bool {
+        let size = self.0.state.borrow().size;
+        Bounds {
+            origin: Point::default(),
+            size,
+        }
+        .contains(&position)
+    }
+
+    fn draw(&self, scene: &Scene) {
+        let state = self.0.state.borrow();
+        let viewport_size = state
+            .size
+            .map(|size| DevicePixels::from((f32::from(size) * state.scale_factor).ceil() as i32));
+        drop(state);
+        let image = SoftwareRenderer::new(self.0.sprite_atlas.clone()).draw(scene, viewport_size);
+        self.0.native.present(&image);
+        *self.0.last_frame.borrow_mut() = Some(image);
+    }
+
+    fn sprite_atlas(&self) -> Arc<dyn PlatformAtlas> {
+        self.0.sprite_atlas.clone()
+    }
+}
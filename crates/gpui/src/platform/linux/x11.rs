@@ -0,0 +1,7 @@
+mod client;
+mod window;
+mod xim_handler;
+
+pub(crate) use client::*;
+pub(crate) use window::*;
+pub(crate) use xim_handler::*;
@@ -0,0 +1,568 @@
+use super::{X11Window, X11WindowBounds, XcbAtoms, XimEvent, XimHandler};
+use crate::{
+    platform::linux::{
+        cursor_name, Client, KeyInput, Keyboard, LinuxDisplay, LinuxWindow, LinuxWindowState,
+    },
+    point, px, size, AnyWindowHandle, Bounds, CursorStyle, DisplayId, GlobalPixels, MouseButton,
+    NavigationDirection, PlatformDisplay, PlatformWindow, Point, ScrollDelta, WindowBounds,
+    WindowOptions,
+};
+use anyhow::{Context as _, Result};
+use calloop::{
+    generic::{FdWrapper, Generic},
+    Interest, LoopHandle, Mode, PostAction,
+};
+use collections::HashMap;
+use copypasta::{x11_clipboard::X11ClipboardContext, ClipboardProvider as _};
+use std::{
+    cell::RefCell,
+    env,
+    os::fd::AsRawFd as _,
+    rc::{Rc, Weak},
+};
+use util::ResultExt as _;
+use x11rb::{
+    connection::Connection as _,
+    cursor,
+    protocol::{
+        xkb::ConnectionExt as _,
+        xproto::{self, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, KeyButMask},
+        Event,
+    },
+    resource_manager,
+    xcb_ffi::XCBConnection,
+    NONE,
+};
+use xim::{x11rb::X11rbClient, Client as _, ClientError, ForwardEventFlag};
+use xkbcommon::xkb::{self, Keycode};
+
+/// A client of an X server. Keys are translated with xkbcommon, including compose
+/// sequences. When XMODIFIERS names an input method, key presses go through it over
+/// XIM first, and the text it composes is shown as preedit text in the window.
+pub(crate) struct X11Client {
+    xcb: Rc<XCBConnection>,
+    screen_num: usize,
+    atoms: XcbAtoms,
+    display: Rc<LinuxDisplay>,
+    scale_factor: f32,
+    loop_handle: LoopHandle<'static, ()>,
+    state: RefCell<X11ClientState>,
+}
+
+struct X11ClientState {
+    windows: HashMap<xproto::Window, Weak<LinuxWindowState>>,
+    keyboard: Keyboard,
+    cursor_handle: cursor::Handle,
+    cursor_style: CursorStyle,
+    clipboard: Option<X11ClipboardContext>,
+    xim: Option<Xim>,
+}
+
+struct Xim {
+    client: X11rbClient<Rc<XCBConnection>>,
+    handler: XimHandler,
+}
+
+impl X11Client {
+    pub(crate) fn new(loop_handle: LoopHandle<'static, ()>) -> Result<Rc<Self>> {
+        let (xcb, screen_num) =
+            XCBConnection::connect(None).context("failed to connect to the X server")?;
+        let xcb = Rc::new(xcb);
+        let atoms = XcbAtoms::new(&*xcb)?.reply()?;
+
+        xcb.xkb_use_extension(1, 0)?
+            .reply()
+            .context("the X server doesn't support XKB")?;
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let device_id = xkb::x11::get_core_keyboard_device_id(&*xcb);
+        let keymap = xkb::x11::keymap_new_from_device(
+            &context,
+            &*xcb,
+            device_id,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        );
+        let keyboard = Keyboard::new(
+            &context,
+            xkb::x11::state_new_from_device(&keymap, &*xcb, device_id),
+        );
+
+        let xim = if env::var("XMODIFIERS").map_or(false, |modifiers| modifiers.contains("@im=")) {
+            X11rbClient::init(xcb.clone(), screen_num, None)
+                .map_err(|error| log::warn!("failed to connect to the input method: {error}"))
+                .ok()
+                .map(|client| Xim {
+                    client,
+                    handler: XimHandler::default(),
+                })
+        } else {
+            None
+        };
+
+        let resources = resource_manager::new_from_default(&*xcb)?;
+        let scale_factor = resources
+            .get_value::<f32>("Xft.dpi", "")
+            .ok()
+            .flatten()
+            .map_or(1., |dpi| dpi / 96.);
+        let cursor_handle = cursor::Handle::new(&*xcb, screen_num, &resources)?.reply()?;
+
+        let screen = &xcb.setup().roots[screen_num];
+        let display = Rc::new(LinuxDisplay::new(
+            screen_num as u32,
+            format!("screen-{screen_num}"),
+            Bounds {
+                origin: Point::default(),
+                size: size(
+                    GlobalPixels(screen.width_in_pixels as f32 / scale_factor),
+                    GlobalPixels(screen.height_in_pixels as f32 / scale_factor),
+                ),
+            },
+        ));
+
+        let clipboard = X11ClipboardContext::new()
+            .map_err(|error| log::error!("failed to access the clipboard: {error}"))
+            .ok();
+
+        let fd = xcb.as_raw_fd();
+        let client = Rc::new(Self {
+            xcb,
+            screen_num,
+            atoms,
+            display,
+            scale_factor,
+            loop_handle: loop_handle.clone(),
+            state: RefCell::new(X11ClientState {
+                windows: HashMap::default(),
+                keyboard,
+                cursor_handle,
+                cursor_style: CursorStyle::Arrow,
+                clipboard,
+                xim,
+            }),
+        });
+
+        let this = Rc::downgrade(&client);
+        // SAFETY: the connection's file descriptor stays open for as long as
+        // the client does, and the source is removed when the client is gone.
+        let source = Generic::new(unsafe { FdWrapper::new(fd) }, Interest::READ, Mode::Level);
+        loop_handle
+            .insert_source(source, move |_, _, _| {
+                let Some(client) = this.upgrade() else {
+                    return Ok(PostAction::Remove);
+                };
+                client.handle_events();
+                Ok(PostAction::Continue)
+            })
+            .map_err(|error| error.error)
+            .context("failed to add the X11 connection to the event loop")?;
+
+        Ok(client)
+    }
+
+    fn handle_events(&self) {
+        while let Some(event) = self.xcb.poll_for_event().log_err().flatten() {
+            // Events that belong to the XIM connection itself are handled by it.
+            if !self.with_xim(|client, handler| client.filter_event(&event, handler)) {
+                self.handle_event(event);
+            }
+        }
+        self.update_xim_spot_location();
+        self.xcb.flush().log_err();
+    }
+
+    /// Calls `f` with the XIM client and handler, if an input method is connected, and
+    /// then handles what the input method sent. The input method is dropped if it fails,
+    /// and keys are translated without it from then on.
+    fn with_xim<R: Default>(
+        &self,
+        f: impl FnOnce(&mut X11rbClient<Rc<XCBConnection>>, &mut XimHandler) -> Result<R, ClientError>,
+    ) -> R {
+        let (result, events) = {
+            let mut state = self.state.borrow_mut();
+            let Some(xim) = state.xim.as_mut() else {
+                return R::default();
+            };
+            let result = f(&mut xim.client, &mut xim.handler);
+            let events = xim.handler.take_events();
+            if let Err(error) = &result {
+                log::error!("lost the connection to the input method: {error}");
+                state.xim = None;
+            }
+            (result, events)
+        };
+        for event in events {
+            self.handle_xim_event(event);
+        }
+        result.unwrap_or_default()
+    }
+
+    fn handle_xim_event(&self, event: XimEvent) {
+        match event {
+            XimEvent::KeyPress(event) => self.handle_key_event(event),
+            XimEvent::Preedit(window_id, text) => {
+                if let Some(window) = self.window(window_id) {
+                    let input = if text.is_empty() {
+                        KeyInput::CancelPreedit
+                    } else {
+                        KeyInput::Preedit(text)
+                    };
+                    window.handle_key_down(input, false);
+                }
+            }
+            XimEvent::Commit(window_id, text) => {
+                if let Some(window) = self.window(window_id) {
+                    window.handle_ime_commit(text);
+                }
+            }
+        }
+    }
+
+    /// Tells the input method where the cursor is in the focused window, so that its
+    /// candidates are shown next to the text being composed.
+    fn update_xim_spot_location(&self) {
+        let Some(window_id) = self
+            .state
+            .borrow()
+            .xim
+            .as_ref()
+            .and_then(|xim| xim.handler.focus())
+        else {
+            return;
+        };
+        let Some(bounds) = self
+            .window(window_id)
+            .and_then(|window| window.ime_cursor_bounds())
+        else {
+            return;
+        };
+        // The spot is where the baseline of the text starts, in device pixels.
+        let x = (bounds.origin.x.0 * self.scale_factor) as i16;
+        let y = ((bounds.origin.y.0 + bounds.size.height.0) * self.scale_factor) as i16;
+        self.with_xim(|client, handler| handler.set_spot_location(client, window_id, x, y));
+    }
+
+    fn window(&self, window_id: xproto::Window) -> Option<Rc<LinuxWindowState>> {
+        self.state
+            .borrow()
+            .windows
+            .get(&window_id)
+            .and_then(Weak::upgrade)
+    }
+
+    fn position(&self, x: i16, y: i16) -> Point<crate::Pixels> {
+        point(
+            px(x as f32 / self.scale_factor),
+            px(y as f32 / self.scale_factor),
+        )
+    }
+
+    /// Brings the keyboard's modifiers up to date with the ones the server
+    /// reports alongside input events, which are the ones held before the event.
+    fn update_modifiers(&self, mask: KeyButMask) {
+        let mask = u16::from(mask) as u32;
+        let locked = mask & u16::from(KeyButMask::LOCK) as u32;
+        let group = (mask >> 13) & 0b11;
+        self.state
+            .borrow_mut()
+            .keyboard
+            .update_mask(mask & !locked & 0xff, 0, locked, group);
+    }
+
+    fn handle_event(&self, event: Event) {
+        match event {
+            Event::ClientMessage(event) => {
+                let [message, ..] = event.data.as_data32();
+                if message == self.atoms.WM_DELETE_WINDOW {
+                    if let Some(window) = self.window(event.window) {
+                        window.handle_close_request();
+                    }
+                }
+            }
+            Event::DestroyNotify(event) => {
+                self.state.borrow_mut().windows.remove(&event.window);
+                self.with_xim(|client, handler| handler.window_destroyed(client, event.window));
+            }
+            Event::Expose(event) => {
+                if event.count == 0 {
+                    if let Some(window) = self.window(event.window) {
+                        window.handle_expose();
+                    }
+                }
+            }
+            Event::ConfigureNotify(event) => {
+                let Some(window) = self.window(event.window) else {
+                    return;
+                };
+                let size = size(
+                    px(event.width as f32 / self.scale_factor),
+                    px(event.height as f32 / self.scale_factor),
+                );
+                window.handle_resize(size, self.scale_factor);
+                window.handle_move(self.position(event.x, event.y));
+            }
+            Event::PropertyNotify(event) => {
+                if event.atom != self.atoms._NET_WM_STATE {
+                    return;
+                }
+                let Some(window) = self.window(event.window) else {
+                    return;
+                };
+                let fullscreen = self
+                    .xcb
+                    .get_property(
+                        false,
+                        event.window,
+                        self.atoms._NET_WM_STATE,
+                        AtomEnum::ATOM,
+                        0,
+                        1024,
+                    )
+                    .ok()
+                    .and_then(|cookie| cookie.reply().log_err())
+                    .and_then(|reply| {
+                        reply.value32().map(|mut states| {
+                            states.any(|state| state == self.atoms._NET_WM_STATE_FULLSCREEN)
+                        })
+                    })
+                    .unwrap_or(false);
+                window.handle_fullscreen(fullscreen);
+            }
+            Event::FocusIn(event) => {
+                if let Some(window) = self.window(event.event) {
+                    window.handle_active_status_change(true);
+                }
+                self.with_xim(|client, handler| handler.focus_in(client, event.event));
+            }
+            Event::FocusOut(event) => {
+                if let Some(window) = self.window(event.event) {
+                    window.handle_active_status_change(false);
+                }
+                self.with_xim(|client, handler| handler.focus_out(client, event.event));
+            }
+            Event::KeyPress(event) => {
+                // Key presses go to the input method first, which sends back the ones
+                // it doesn't use.
+                let forwarded = self.with_xim(|client, handler| {
+                    let Some((input_method_id, input_context_id)) =
+                        handler.input_context(event.event)
+                    else {
+                        return Ok(false);
+                    };
+                    client.forward_event(
+                        input_method_id,
+                        input_context_id,
+                        ForwardEventFlag::empty(),
+                        &event,
+                    )?;
+                    Ok(true)
+                });
+                if !forwarded {
+                    self.handle_key_event(event);
+                }
+            }
+            Event::KeyRelease(event) => self.handle_key_event(event),
+            Event::ButtonPress(event) | Event::ButtonRelease(event) => {
+                let Some(window) = self.window(event.event) else {
+                    return;
+                };
+                let pressed = event.response_type & 0x7f == xproto::BUTTON_PRESS_EVENT;
+                self.update_modifiers(event.state);
+                let modifiers = self.state.borrow().keyboard.modifiers();
+                window.handle_modifiers(modifiers);
+                window.handle_mouse_move(self.position(event.event_x, event.event_y));
+
+                let button = match event.detail {
+                    1 => MouseButton::Left,
+                    2 => MouseButton::Middle,
+                    3 => MouseButton::Right,
+                    8 => MouseButton::Navigate(NavigationDirection::Back),
+                    9 => MouseButton::Navigate(NavigationDirection::Forward),
+                    // The scroll wheel is reported as buttons 4 to 7, which
+                    // are pressed and released for each step.
+                    4..=7 => {
+                        if pressed {
+                            let delta = match event.detail {
+                                4 => point(0., 1.),
+                                5 => point(0., -1.),
+                                6 => point(1., 0.),
+                                _ => point(-1., 0.),
+                            };
+                            window.handle_scroll(ScrollDelta::Lines(delta));
+                        }
+                        return;
+                    }
+                    _ => return,
+                };
+                window.handle_mouse_button(button, pressed);
+            }
+            Event::MotionNotify(event) => {
+                if let Some(window) = self.window(event.event) {
+                    window.handle_mouse_move(self.position(event.event_x, event.event_y));
+                }
+            }
+            Event::LeaveNotify(event) => {
+                if let Some(window) = self.window(event.event) {
+                    window.handle_mouse_exit();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_key_event(&self, event: xproto::KeyPressEvent) {
+        let Some(window) = self.window(event.event) else {
+            return;
+        };
+        let pressed = event.response_type & 0x7f == xproto::KEY_PRESS_EVENT;
+        let keycode = Keycode::new(event.detail as u32);
+        self.update_modifiers(event.state);
+
+        let mut state = self.state.borrow_mut();
+        let input = if pressed {
+            Some(state.keyboard.key_down(keycode))
+        } else {
+            None
+        };
+        let keystroke = state.keyboard.keystroke(keycode);
+        state.keyboard.update_key(keycode, pressed);
+        let modifiers = state.keyboard.modifiers();
+        drop(state);
+
+        window.handle_modifiers(modifiers);
+        match input {
+            Some(input) => window.handle_key_down(input, false),
+            None => window.handle_key_up(keystroke),
+        }
+    }
+
+    fn load_cursor(&self, style: CursorStyle) -> xproto::Cursor {
+        let state = self.state.borrow();
+        let load = |name| {
+            state
+                .cursor_handle
+                .load_cursor(&*self.xcb, name)
+                .log_err()
+                .unwrap_or(NONE)
+        };
+        match load(cursor_name(style)) {
+            NONE => load(cursor_name(CursorStyle::Arrow)),
+            cursor => cursor,
+        }
+    }
+}
+
+impl Client for X11Client {
+    fn displays(&self) -> Vec<Rc<dyn PlatformDisplay>> {
+        vec![self.display.clone()]
+    }
+
+    fn display(&self, id: DisplayId) -> Option<Rc<dyn PlatformDisplay>> {
+        (self.display.id() == id).then(|| self.display.clone() as Rc<dyn PlatformDisplay>)
+    }
+
+    fn open_window(
+        &self,
+        handle: AnyWindowHandle,
+        options: WindowOptions,
+    ) -> Box<dyn PlatformWindow> {
+        let display_bounds = self.display.bounds();
+        let bounds = match options.bounds {
+            WindowBounds::Fixed(bounds) => bounds,
+            WindowBounds::Maximized | WindowBounds::Fullscreen => display_bounds,
+        };
+        let to_device = |value: GlobalPixels| (value.0 * self.scale_factor).round();
+        let native = X11Window::new(
+            self.xcb.clone(),
+            self.screen_num,
+            self.atoms,
+            X11WindowBounds {
+                x: to_device(bounds.origin.x) as i16,
+                y: to_device(bounds.origin.y) as i16,
+                width: to_device(bounds.size.width).max(1.) as u16,
+                height: to_device(bounds.size.height).max(1.) as u16,
+            },
+            options
+                .titlebar
+                .as_ref()
+                .and_then(|titlebar| titlebar.title.as_deref()),
+            options.show,
+        )
+        .expect("failed to create an X11 window");
+        let window_id = native.id();
+        match options.bounds {
+            WindowBounds::Fixed(_) => {}
+            WindowBounds::Maximized => native.zoom(),
+            WindowBounds::Fullscreen => native.set_fullscreen(true),
+        }
+
+        let window = LinuxWindowState::new(
+            handle,
+            Box::new(native),
+            self.display.clone(),
+            size(px(bounds.size.width.0), px(bounds.size.height.0)),
+            self.scale_factor,
+            &self.loop_handle,
+        );
+
+        let cursor = self.load_cursor(self.state.borrow().cursor_style);
+        self.xcb
+            .change_window_attributes(window_id, &ChangeWindowAttributesAux::new().cursor(cursor))
+            .log_err();
+        self.xcb.flush().log_err();
+
+        self.state
+            .borrow_mut()
+            .windows
+            .insert(window_id, Rc::downgrade(&window));
+        Box::new(LinuxWindow(window))
+    }
+
+    fn active_window(&self) -> Option<AnyWindowHandle> {
+        self.state
+            .borrow()
+            .windows
+            .values()
+            .filter_map(Weak::upgrade)
+            .find(|window| window.is_active())
+            .map(|window| window.handle())
+    }
+
+    fn set_cursor_style(&self, style: CursorStyle) {
+        self.state.borrow_mut().cursor_style = style;
+        let cursor = self.load_cursor(style);
+        let window_ids = self
+            .state
+            .borrow()
+            .windows
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        for window_id in window_ids {
+            self.xcb
+                .change_window_attributes(
+                    window_id,
+                    &ChangeWindowAttributesAux::new().cursor(cursor),
+                )
+                .log_err();
+        }
+        self.xcb.flush().log_err();
+    }
+
+    fn write_to_clipboard(&self, text: String) {
+        if let Some(clipboard) = self.state.borrow_mut().clipboard.as_mut() {
+            clipboard
+                .set_contents(text)
+                .map_err(|error| log::error!("failed to write to the clipboard: {error}"))
+                .ok();
+        }
+    }
+
+    fn read_from_clipboard(&self) -> Option<String> {
+        self.state
+            .borrow_mut()
+            .clipboard
+            .as_mut()?
+            .get_contents()
+            .ok()
+    }
+}
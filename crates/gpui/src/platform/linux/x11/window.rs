@@ -0,0 +1,233 @@
+use crate::platform::linux::{bgra_pixels, NativeWindow};
+use anyhow::Result;
+use image::RgbaImage;
+use raw_window_handle::{RawDisplayHandle, RawWindowHandle, XcbDisplayHandle, XcbWindowHandle};
+use std::{borrow::Cow, num::NonZeroU32, ptr::NonNull, rc::Rc};
+use util::ResultExt as _;
+use x11rb::{
+    connection::Connection as _,
+    image::{BitsPerPixel, Image, ImageOrder, ScanlinePad},
+    protocol::xproto::{
+        self, AtomEnum, ClientMessageEvent, ConnectionExt as _, CreateGCAux, CreateWindowAux,
+        EventMask, PropMode, Screen, WindowClass,
+    },
+    wrapper::ConnectionExt as _,
+    xcb_ffi::XCBConnection,
+    COPY_DEPTH_FROM_PARENT, CURRENT_TIME,
+};
+
+x11rb::atom_manager! {
+    pub(crate) XcbAtoms: XcbAtomsCookie {
+        UTF8_STRING,
+        WM_PROTOCOLS,
+        WM_DELETE_WINDOW,
+        WM_CHANGE_STATE,
+        _NET_WM_NAME,
+        _NET_WM_STATE,
+        _NET_WM_STATE_MAXIMIZED_VERT,
+        _NET_WM_STATE_MAXIMIZED_HORZ,
+        _NET_WM_STATE_FULLSCREEN,
+        _NET_ACTIVE_WINDOW,
+    }
+}
+
+const _NET_WM_STATE_REMOVE: u32 = 0;
+const _NET_WM_STATE_ADD: u32 = 1;
+const _NET_WM_STATE_TOGGLE: u32 = 2;
+const ICONIC_STATE: u32 = 3;
+
+/// The bounds of a new window, in device pixels.
+pub(crate) struct X11WindowBounds {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+}
+
+pub(crate) struct X11Window {
+    xcb: Rc<XCBConnection>,
+    screen_num: usize,
+    root: xproto::Window,
+    depth: u8,
+    atoms: XcbAtoms,
+    window_id: xproto::Window,
+    gc: xproto::Gcontext,
+}
+
+impl X11Window {
+    pub fn new(
+        xcb: Rc<XCBConnection>,
+        screen_num: usize,
+        atoms: XcbAtoms,
+        bounds: X11WindowBounds,
+        title: Option<&str>,
+        show: bool,
+    ) -> Result<Self> {
+        let screen: &Screen = &xcb.setup().roots[screen_num];
+        let (root, depth) = (screen.root, screen.root_depth);
+        let window_id = xcb.generate_id()?;
+        let aux = CreateWindowAux::new()
+            .background_pixel(screen.white_pixel)
+            .event_mask(
+                EventMask::EXPOSURE
+                    | EventMask::STRUCTURE_NOTIFY
+                    | EventMask::PROPERTY_CHANGE
+                    | EventMask::FOCUS_CHANGE
+                    | EventMask::KEY_PRESS
+                    | EventMask::KEY_RELEASE
+                    | EventMask::BUTTON_PRESS
+                    | EventMask::BUTTON_RELEASE
+                    | EventMask::POINTER_MOTION
+                    | EventMask::LEAVE_WINDOW,
+            );
+        xcb.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window_id,
+            root,
+            bounds.x,
+            bounds.y,
+            bounds.width,
+            bounds.height,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            screen.root_visual,
+            &aux,
+        )?;
+        xcb.change_property32(
+            PropMode::REPLACE,
+            window_id,
+            atoms.WM_PROTOCOLS,
+            AtomEnum::ATOM,
+            &[atoms.WM_DELETE_WINDOW],
+        )?;
+        let gc = xcb.generate_id()?;
+        xcb.create_gc(gc, window_id, &CreateGCAux::new())?;
+
+        let this = Self {
+            xcb,
+            screen_num,
+            root,
+            depth,
+            atoms,
+            window_id,
+            gc,
+        };
+        if let Some(title) = title {
+            this.set_title(title);
+        }
+        if show {
+            this.xcb.map_window(window_id)?;
+        }
+        this.xcb.flush()?;
+        Ok(this)
+    }
+
+    pub fn id(&self) -> xproto::Window {
+        self.window_id
+    }
+
+    /// Asks the window manager to add, remove or toggle states in the
+    /// window's `_NET_WM_STATE`.
+    fn change_state(&self, action: u32, first: xproto::Atom, second: xproto::Atom) {
+        self.send_to_window_manager(self.atoms._NET_WM_STATE, [action, first, second, 1, 0]);
+    }
+
+    fn send_to_window_manager(&self, message_type: xproto::Atom, data: [u32; 5]) {
+        let event = ClientMessageEvent::new(32, self.window_id, message_type, data);
+        self.xcb
+            .send_event(
+                false,
+                self.root,
+                EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+                event,
+            )
+            .log_err();
+        self.xcb.flush().log_err();
+    }
+}
+
+impl Drop for X11Window {
+    fn drop(&mut self) {
+        self.xcb.free_gc(self.gc).log_err();
+        self.xcb.destroy_window(self.window_id).log_err();
+        self.xcb.flush().log_err();
+    }
+}
+
+impl NativeWindow for X11Window {
+    fn present(&self, image: &RgbaImage) {
+        let Some(image) = Image::new(
+            image.width() as u16,
+            image.height() as u16,
+            ScanlinePad::Pad32,
+            self.depth,
+            BitsPerPixel::B32,
+            ImageOrder::LsbFirst,
+            Cow::Owned(bgra_pixels(image)),
+        )
+        .log_err() else {
+            return;
+        };
+        image
+            .put(&*self.xcb, self.window_id, self.gc, 0, 0)
+            .log_err();
+        self.xcb.flush().log_err();
+    }
+
+    fn set_title(&self, title: &str) {
+        self.xcb
+            .change_property8(
+                PropMode::REPLACE,
+                self.window_id,
+                AtomEnum::WM_NAME,
+                AtomEnum::STRING,
+                title.as_bytes(),
+            )
+            .log_err();
+        self.xcb
+            .change_property8(
+                PropMode::REPLACE,
+                self.window_id,
+                self.atoms._NET_WM_NAME,
+                self.atoms.UTF8_STRING,
+                title.as_bytes(),
+            )
+            .log_err();
+        self.xcb.flush().log_err();
+    }
+
+    fn set_fullscreen(&self, fullscreen: bool) {
+        let action = if fullscreen {
+            _NET_WM_STATE_ADD
+        } else {
+            _NET_WM_STATE_REMOVE
+        };
+        self.change_state(action, self.atoms._NET_WM_STATE_FULLSCREEN, 0);
+    }
+
+    fn minimize(&self) {
+        self.send_to_window_manager(self.atoms.WM_CHANGE_STATE, [ICONIC_STATE, 0, 0, 0, 0]);
+    }
+
+    fn zoom(&self) {
+        self.change_state(
+            _NET_WM_STATE_TOGGLE,
+            self.atoms._NET_WM_STATE_MAXIMIZED_VERT,
+            self.atoms._NET_WM_STATE_MAXIMIZED_HORZ,
+        );
+    }
+
+    fn activate(&self) {
+        self.send_to_window_manager(self.atoms._NET_ACTIVE_WINDOW, [1, CURRENT_TIME, 0, 0, 0]);
+    }
+
+    fn raw_window_handle(&self) -> RawWindowHandle {
+        let window_id = NonZeroU32::new(self.window_id).expect("window ids are never zero");
+        XcbWindowHandle::new(window_id).into()
+    }
+
+    fn raw_display_handle(&self) -> RawDisplayHandle {
+        let connection = NonNull::new(self.xcb.get_raw_xcb_connection());
+        XcbDisplayHandle::new(connection, self.screen_num as i32).into()
+    }
+}
@@ -0,0 +1,276 @@
+use collections::HashMap;
+use std::{collections::VecDeque, env};
+use x11rb::protocol::xproto;
+use xim::{
+    AttributeName, Client, ClientError, ClientHandler, InputStyle, Point, PreeditDrawStatus,
+};
+
+/// Something the input method sent, which is handled once the event that carried it
+/// has been filtered.
+pub(crate) enum XimEvent {
+    /// A key press the input method didn't consume, to be handled as usual.
+    KeyPress(xproto::KeyPressEvent),
+    /// The text being composed in a window changed. It's empty once composition ends.
+    Preedit(xproto::Window, String),
+    /// Text composed in a window, which replaces the preedit text.
+    Commit(xproto::Window, String),
+}
+
+/// Follows an XIM connection: opens the input method once connected, creates an input
+/// context for each window that gets focused, and collects what the input method sends
+/// for the client to handle.
+#[derive(Default)]
+pub(crate) struct XimHandler {
+    input_method_id: Option<u16>,
+    input_contexts: HashMap<xproto::Window, u16>,
+    /// Windows whose input contexts were requested, in the order the replies arrive.
+    pending_input_contexts: VecDeque<xproto::Window>,
+    focus: Option<xproto::Window>,
+    /// The last cursor position sent for the focused window's input context.
+    spot_location: Option<(xproto::Window, i16, i16)>,
+    /// The text being composed, which the input method edits a range of characters at
+    /// a time.
+    preedit: Vec<char>,
+    events: Vec<XimEvent>,
+}
+
+impl XimHandler {
+    pub fn take_events(&mut self) -> Vec<XimEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// The input method and input context that key presses in the window are forwarded
+    /// to, once both have been opened.
+    pub fn input_context(&self, window: xproto::Window) -> Option<(u16, u16)> {
+        Some((self.input_method_id?, *self.input_contexts.get(&window)?))
+    }
+
+    pub fn focus(&self) -> Option<xproto::Window> {
+        self.focus
+    }
+
+    pub fn focus_in<C: Client>(
+        &mut self,
+        client: &mut C,
+        window: xproto::Window,
+    ) -> Result<(), ClientError> {
+        self.focus = Some(window);
+        let Some(input_method_id) = self.input_method_id else {
+            return Ok(());
+        };
+        match self.input_contexts.get(&window) {
+            Some(&input_context_id) => client.set_focus(input_method_id, input_context_id),
+            None => self.create_input_context(client, input_method_id, window),
+        }
+    }
+
+    pub fn focus_out<C: Client>(
+        &mut self,
+        client: &mut C,
+        window: xproto::Window,
+    ) -> Result<(), ClientError> {
+        if self.focus == Some(window) {
+            self.focus = None;
+        }
+        // Composition doesn't carry over to another window.
+        if !self.preedit.is_empty() {
+            self.preedit.clear();
+            self.events.push(XimEvent::Preedit(window, String::new()));
+        }
+        match self.input_context(window) {
+            Some((input_method_id, input_context_id)) => {
+                client.unset_focus(input_method_id, input_context_id)
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn window_destroyed<C: Client>(
+        &mut self,
+        client: &mut C,
+        window: xproto::Window,
+    ) -> Result<(), ClientError> {
+        if self.focus == Some(window) {
+            self.focus = None;
+        }
+        match (self.input_method_id, self.input_contexts.remove(&window)) {
+            (Some(input_method_id), Some(input_context_id)) => {
+                client.destroy_ic(input_method_id, input_context_id)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Tells the input method where the cursor is in the window, so that it can show its
+    /// candidates next to it.
+    pub fn set_spot_location<C: Client>(
+        &mut self,
+        client: &mut C,
+        window: xproto::Window,
+        x: i16,
+        y: i16,
+    ) -> Result<(), ClientError> {
+        let Some((input_method_id, input_context_id)) = self.input_context(window) else {
+            return Ok(());
+        };
+        if self.spot_location == Some((window, x, y)) {
+            return Ok(());
+        }
+        self.spot_location = Some((window, x, y));
+        let attributes = client
+            .build_ic_attributes()
+            .nested_list(AttributeName::PreeditAttributes, |builder| {
+                builder.push(AttributeName::SpotLocation, Point { x, y });
+            })
+            .build();
+        client.set_ic_values(input_method_id, input_context_id, attributes)
+    }
+
+    fn create_input_context<C: Client>(
+        &mut self,
+        client: &mut C,
+        input_method_id: u16,
+        window: xproto::Window,
+    ) -> Result<(), ClientError> {
+        if self.pending_input_contexts.contains(&window) {
+            return Ok(());
+        }
+        // The preedit text is drawn by the window itself, like text composed through
+        // the compose table.
+        let attributes = client
+            .build_ic_attributes()
+            .push(
+                AttributeName::InputStyle,
+                InputStyle::PREEDIT_CALLBACKS | InputStyle::STATUS_NOTHING,
+            )
+            .push(AttributeName::ClientWindow, window)
+            .push(AttributeName::FocusWindow, window)
+            .build();
+        client.create_ic(input_method_id, attributes)?;
+        self.pending_input_contexts.push_back(window);
+        Ok(())
+    }
+
+    fn push_preedit(&mut self) {
+        if let Some(window) = self.focus {
+            let preedit = self.preedit.iter().collect();
+            self.events.push(XimEvent::Preedit(window, preedit));
+        }
+    }
+}
+
+impl<C: Client<XEvent = xproto::KeyPressEvent>> ClientHandler<C> for XimHandler {
+    fn handle_connect(&mut self, client: &mut C) -> Result<(), ClientError> {
+        client.open(&locale())
+    }
+
+    fn handle_open(&mut self, client: &mut C, input_method_id: u16) -> Result<(), ClientError> {
+        self.input_method_id = Some(input_method_id);
+        match self.focus {
+            Some(window) => self.create_input_context(client, input_method_id, window),
+            None => Ok(()),
+        }
+    }
+
+    fn handle_create_ic(
+        &mut self,
+        client: &mut C,
+        input_method_id: u16,
+        input_context_id: u16,
+    ) -> Result<(), ClientError> {
+        let Some(window) = self.pending_input_contexts.pop_front() else {
+            return Ok(());
+        };
+        self.input_contexts.insert(window, input_context_id);
+        if self.focus == Some(window) {
+            client.set_focus(input_method_id, input_context_id)?;
+        }
+        Ok(())
+    }
+
+    fn handle_close(&mut self, client: &mut C, _: u16) -> Result<(), ClientError> {
+        client.disconnect()
+    }
+
+    fn handle_disconnect(&mut self) {
+        self.input_method_id = None;
+        self.input_contexts.clear();
+        self.pending_input_contexts.clear();
+        self.spot_location = None;
+    }
+
+    fn handle_forward_event(
+        &mut self,
+        _: &mut C,
+        _: u16,
+        _: u16,
+        _: xim::ForwardEventFlag,
+        event: xproto::KeyPressEvent,
+    ) -> Result<(), ClientError> {
+        if event.response_type & 0x7f == xproto::KEY_PRESS_EVENT {
+            self.events.push(XimEvent::KeyPress(event));
+        }
+        Ok(())
+    }
+
+    fn handle_commit(&mut self, _: &mut C, _: u16, _: u16, text: &str) -> Result<(), ClientError> {
+        self.preedit.clear();
+        if let Some(window) = self.focus {
+            self.events.push(XimEvent::Commit(window, text.to_string()));
+        }
+        Ok(())
+    }
+
+    fn handle_preedit_draw(
+        &mut self,
+        _: &mut C,
+        _: u16,
+        _: u16,
+        _caret: i32,
+        chg_first: i32,
+        chg_len: i32,
+        status: PreeditDrawStatus,
+        preedit_string: &str,
+        _: Vec<xim::Feedback>,
+    ) -> Result<(), ClientError> {
+        let start = (chg_first.max(0) as usize).min(self.preedit.len());
+        let end = (start + chg_len.max(0) as usize).min(self.preedit.len());
+        let new_text = if status.contains(PreeditDrawStatus::NO_STRING) {
+            ""
+        } else {
+            preedit_string
+        };
+        let had_preedit = !self.preedit.is_empty();
+        let mut preedit = self.preedit[..start].to_vec();
+        preedit.extend(new_text.chars());
+        preedit.extend_from_slice(&self.preedit[end..]);
+        self.preedit = preedit;
+        if had_preedit || !self.preedit.is_empty() {
+            self.push_preedit();
+        }
+        Ok(())
+    }
+
+    fn handle_preedit_done(&mut self, _: &mut C, _: u16, _: u16) -> Result<(), ClientError> {
+        // The preedit text is already gone if it was committed.
+        if !self.preedit.is_empty() {
+            self.preedit.clear();
+            self.push_preedit();
+        }
+        Ok(())
+    }
+}
+
+/// The locale the input method is opened with, without its encoding, since the text it
+/// sends is converted to UTF-8 either way.
+fn locale() -> String {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .into_iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|locale| !locale.is_empty())
+        .and_then(|locale| {
+            let locale = locale.split(['.', '@']).next()?.to_string();
+            (!locale.is_empty()).then_some(locale)
+        })
+        .unwrap_or_else(|| "en_US".into())
+}
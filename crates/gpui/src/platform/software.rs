@@ -0,0 +1,11 @@
+//! Renders scenes on the CPU, so that frames can be inspected on machines
+//! without a GPU. Windows on Linux are drawn this way too, and headless
+//! windows keep their sprites in the same atlas without drawing them.
+
+mod atlas;
+#[cfg(any(test, feature = "test-support", target_os = "linux"))]
+mod renderer;
+
+pub(crate) use atlas::*;
+#[cfg(any(test, feature = "test-support", target_os = "linux"))]
+pub(crate) use renderer::*;
@@ -0,0 +1,193 @@
+use crate::{
+    AtlasKey, AtlasTextureId, AtlasTextureKind, AtlasTile, Bounds, DevicePixels, PlatformAtlas,
+    Size,
+};
+use anyhow::Result;
+use collections::FxHashMap;
+use etagere::BucketedAtlasAllocator;
+use parking_lot::Mutex;
+use std::borrow::Cow;
+
+/// An atlas whose textures are kept in memory, for the software renderer to
+/// sample from.
+pub(crate) struct SoftwareAtlas(pub(super) Mutex<SoftwareAtlasState>);
+
+impl SoftwareAtlas {
+    pub(crate) fn new() -> Self {
+        SoftwareAtlas(Mutex::new(SoftwareAtlasState {
+            monochrome_textures: Default::default(),
+            polychrome_textures: Default::default(),
+            path_textures: Default::default(),
+            tiles_by_key: Default::default(),
+        }))
+    }
+}
+
+pub(super) struct SoftwareAtlasState {
+    monochrome_textures: Vec<SoftwareAtlasTexture>,
+    polychrome_textures: Vec<SoftwareAtlasTexture>,
+    path_textures: Vec<SoftwareAtlasTexture>,
+    tiles_by_key: FxHashMap<AtlasKey, AtlasTile>,
+}
+
+impl PlatformAtlas for SoftwareAtlas {
+    fn get_or_insert_with<'a>(
+        &self,
+        key: &AtlasKey,
+        build: &mut dyn FnMut() -> Result<(Size<DevicePixels>, Cow<'a, [u8]>)>,
+    ) -> Result<AtlasTile> {
+        let mut lock = self.0.lock();
+        if let Some(tile) = lock.tiles_by_key.get(key) {
+            Ok(tile.clone())
+        } else {
+            let (size, bytes) = build()?;
+            let tile = lock.allocate(size, key.texture_kind());
+            lock.texture_mut(tile.texture_id)
+                .upload(tile.bounds, &bytes);
+            lock.tiles_by_key.insert(key.clone(), tile.clone());
+            Ok(tile)
+        }
+    }
+}
+
+impl SoftwareAtlasState {
+    fn allocate(&mut self, size: Size<DevicePixels>, texture_kind: AtlasTextureKind) -> AtlasTile {
+        let textures = match texture_kind {
+            AtlasTextureKind::Monochrome => &mut self.monochrome_textures,
+            AtlasTextureKind::Polychrome => &mut self.polychrome_textures,
+            AtlasTextureKind::Path => &mut self.path_textures,
+        };
+        textures
+            .iter_mut()
+            .rev()
+            .find_map(|texture| texture.allocate(size))
+            .unwrap_or_else(|| {
+                let texture = self.push_texture(size, texture_kind);
+                texture.allocate(size).unwrap()
+            })
+    }
+
+    fn push_texture(
+        &mut self,
+        min_size: Size<DevicePixels>,
+        kind: AtlasTextureKind,
+    ) -> &mut SoftwareAtlasTexture {
+        const DEFAULT_ATLAS_SIZE: Size<DevicePixels> = Size {
+            width: DevicePixels(1024),
+            height: DevicePixels(1024),
+        };
+
+        let size = min_size.max(&DEFAULT_ATLAS_SIZE);
+        let bytes_per_pixel = match kind {
+            AtlasTextureKind::Monochrome | AtlasTextureKind::Path => 1,
+            AtlasTextureKind::Polychrome => 4,
+        };
+        let textures = match kind {
+            AtlasTextureKind::Monochrome => &mut self.monochrome_textures,
+            AtlasTextureKind::Polychrome => &mut self.polychrome_textures,
+            AtlasTextureKind::Path => &mut self.path_textures,
+        };
+        let atlas_texture = SoftwareAtlasTexture {
+            id: AtlasTextureId {
+                index: textures.len() as u32,
+                kind,
+            },
+            allocator: BucketedAtlasAllocator::new(size.into()),
+            size,
+            bytes_per_pixel,
+            bytes: vec![0; size.width.0 as usize * size.height.0 as usize * bytes_per_pixel],
+        };
+        textures.push(atlas_texture);
+        textures.last_mut().unwrap()
+    }
+
+    #[cfg(any(test, feature = "test-support", target_os = "linux"))]
+    pub(super) fn texture(&self, id: AtlasTextureId) -> &SoftwareAtlasTexture {
+        let textures = match id.kind {
+            AtlasTextureKind::Monochrome => &self.monochrome_textures,
+            AtlasTextureKind::Polychrome => &self.polychrome_textures,
+            AtlasTextureKind::Path => &self.path_textures,
+        };
+        &textures[id.index as usize]
+    }
+
+    fn texture_mut(&mut self, id: AtlasTextureId) -> &mut SoftwareAtlasTexture {
+        let textures = match id.kind {
+            AtlasTextureKind::Monochrome => &mut self.monochrome_textures,
+            AtlasTextureKind::Polychrome => &mut self.polychrome_textures,
+            AtlasTextureKind::Path => &mut self.path_textures,
+        };
+        &mut textures[id.index as usize]
+    }
+}
+
+pub(super) struct SoftwareAtlasTexture {
+    id: AtlasTextureId,
+    allocator: BucketedAtlasAllocator,
+    size: Size<DevicePixels>,
+    bytes_per_pixel: usize,
+    bytes: Vec<u8>,
+}
+
+impl SoftwareAtlasTexture {
+    fn allocate(&mut self, size: Size<DevicePixels>) -> Option<AtlasTile> {
+        let allocation = self.allocator.allocate(size.into())?;
+        let tile = AtlasTile {
+            texture_id: self.id,
+            tile_id: allocation.id.into(),
+            bounds: Bounds {
+                origin: allocation.rectangle.min.into(),
+                size,
+            },
+        };
+        Some(tile)
+    }
+
+    fn upload(&mut self, bounds: Bounds<DevicePixels>, bytes: &[u8]) {
+        let row_len = bounds.size.width.0 as usize * self.bytes_per_pixel;
+        for (row, source) in bytes.chunks_exact(row_len).enumerate() {
+            let start = self.offset(bounds.origin.x.0, bounds.origin.y.0 + row as i32);
+            self.bytes[start..start + row_len].copy_from_slice(source);
+        }
+    }
+
+    fn offset(&self, x: i32, y: i32) -> usize {
+        (y as usize * self.size.width.0 as usize + x as usize) * self.bytes_per_pixel
+    }
+
+    /// Samples the tile at the given position, measured in pixels from the
+    /// tile's origin, interpolating linearly between the nearest pixels like
+    /// a GPU sampler would.
+    #[cfg(any(test, feature = "test-support", target_os = "linux"))]
+    pub(super) fn sample(&self, tile: &Bounds<DevicePixels>, x: f32, y: f32) -> [f32; 4] {
+        // Pixel centers lie half a pixel away from their edges.
+        let x = x - 0.5;
+        let y = y - 0.5;
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let (tx, ty) = (x - x0, y - y0);
+
+        let mut result = [0.; 4];
+        for (dx, dy, weight) in [
+            (0, 0, (1. - tx) * (1. - ty)),
+            (1, 0, tx * (1. - ty)),
+            (0, 1, (1. - tx) * ty),
+            (1, 1, tx * ty),
+        ] {
+            if weight == 0. {
+                continue;
+            }
+            // Clamp to the tile's edges, so that neighboring tiles don't bleed in.
+            let px = (x0 as i32 + dx).clamp(0, tile.size.width.0 - 1) + tile.origin.x.0;
+            let py = (y0 as i32 + dy).clamp(0, tile.size.height.0 - 1) + tile.origin.y.0;
+            let offset = self.offset(px, py);
+            for (channel, value) in self.bytes[offset..offset + self.bytes_per_pixel]
+                .iter()
+                .enumerate()
+            {
+                result[channel] += *value as f32 / 255. * weight;
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,733 @@
+use super::SoftwareAtlas;
+use crate::{
+    AtlasTextureId, Bounds, ContentMask, Corners, DevicePixels, Hsla, MonochromeSprite, Path,
+    PolychromeSprite, PrimitiveBatch, Quad, Rgba, ScaledPixels, Scene, Shadow, Size, Surface,
+    Underline,
+};
+use image::RgbaImage;
+use std::{f32::consts::PI, sync::Arc};
+
+/// Draws scenes into an image in memory, following the same rules as the
+/// shaders used by the GPU renderers, so that a frame drawn on the CPU looks
+/// like the one that would have been presented on screen.
+pub(crate) struct SoftwareRenderer {
+    atlas: Arc<SoftwareAtlas>,
+}
+
+impl SoftwareRenderer {
+    pub(crate) fn new(atlas: Arc<SoftwareAtlas>) -> Self {
+        Self { atlas }
+    }
+
+    pub(crate) fn draw(&self, scene: &Scene, viewport_size: Size<DevicePixels>) -> RgbaImage {
+        let mut frame = Frame::new(viewport_size);
+        for batch in scene.batches() {
+            match batch {
+                PrimitiveBatch::Shadows(shadows) => {
+                    for shadow in shadows {
+                        frame.draw_shadow(shadow);
+                    }
+                }
+                PrimitiveBatch::Quads(quads) => {
+                    for quad in quads {
+                        frame.draw_quad(quad);
+                    }
+                }
+                PrimitiveBatch::Paths(paths) => {
+                    for path in paths {
+                        frame.draw_path(path);
+                    }
+                }
+                PrimitiveBatch::Underlines(underlines) => {
+                    for underline in underlines {
+                        frame.draw_underline(underline);
+                    }
+                }
+                PrimitiveBatch::MonochromeSprites {
+                    texture_id,
+                    sprites,
+                } => self.draw_monochrome_sprites(&mut frame, texture_id, sprites),
+                PrimitiveBatch::PolychromeSprites {
+                    texture_id,
+                    sprites,
+                } => self.draw_polychrome_sprites(&mut frame, texture_id, sprites),
+                PrimitiveBatch::Surfaces(surfaces) => {
+                    for surface in surfaces {
+                        frame.draw_surface(surface);
+                    }
+                }
+            }
+        }
+        frame.into_image()
+    }
+
+    fn draw_monochrome_sprites(
+        &self,
+        frame: &mut Frame,
+        texture_id: AtlasTextureId,
+        sprites: &[MonochromeSprite],
+    ) {
+        let atlas = self.atlas.0.lock();
+        let texture = atlas.texture(texture_id);
+        for sprite in sprites {
+            let color = rgba(sprite.color);
+            let tile_scale = tile_scale(&sprite.bounds, &sprite.tile.bounds);
+            frame.fill(&sprite.bounds, &sprite.content_mask, |x, y| {
+                let sample = texture.sample(
+                    &sprite.tile.bounds,
+                    (x - sprite.bounds.origin.x.0) * tile_scale.0,
+                    (y - sprite.bounds.origin.y.0) * tile_scale.1,
+                );
+                let mut color = color;
+                color[3] *= sample[0];
+                color
+            });
+        }
+    }
+
+    fn draw_polychrome_sprites(
+        &self,
+        frame: &mut Frame,
+        texture_id: AtlasTextureId,
+        sprites: &[PolychromeSprite],
+    ) {
+        let atlas = self.atlas.0.lock();
+        let texture = atlas.texture(texture_id);
+        for sprite in sprites {
+            let tile_scale = tile_scale(&sprite.bounds, &sprite.tile.bounds);
+            frame.fill(&sprite.bounds, &sprite.content_mask, |x, y| {
+                // Polychrome tiles are stored in BGRA order.
+                let [b, g, r, a] = texture.sample(
+                    &sprite.tile.bounds,
+                    (x - sprite.bounds.origin.x.0) * tile_scale.0,
+                    (y - sprite.bounds.origin.y.0) * tile_scale.1,
+                );
+                let mut color = [r, g, b, a];
+                if sprite.grayscale {
+                    let grayscale = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+                    color = [grayscale, grayscale, grayscale, a];
+                }
+                color[3] *= saturate(0.5 - quad_sdf(x, y, &sprite.bounds, &sprite.corner_radii));
+                color
+            });
+        }
+    }
+}
+
+/// The pixels of a frame being drawn, stored as linear RGBA components.
+struct Frame {
+    width: i32,
+    height: i32,
+    pixels: Vec<[f32; 4]>,
+}
+
+impl Frame {
+    fn new(size: Size<DevicePixels>) -> Self {
+        let width = size.width.0.max(0);
+        let height = size.height.0.max(0);
+        Self {
+            width,
+            height,
+            pixels: vec![[0., 0., 0., 1.]; width as usize * height as usize],
+        }
+    }
+
+    /// Returns the range of pixels whose centers lie within the given bounds,
+    /// the content mask and the frame.
+    fn pixel_range(
+        &self,
+        bounds: &Bounds<ScaledPixels>,
+        content_mask: &ContentMask<ScaledPixels>,
+    ) -> (std::ops::Range<i32>, std::ops::Range<i32>) {
+        let bounds = bounds.intersect(&content_mask.bounds);
+        let lower_right = bounds.lower_right();
+        let first_pixel = |edge: f32, max: i32| ((edge - 0.5).ceil() as i32).clamp(0, max);
+        (
+            first_pixel(bounds.origin.x.0, self.width)..first_pixel(lower_right.x.0, self.width),
+            first_pixel(bounds.origin.y.0, self.height)..first_pixel(lower_right.y.0, self.height),
+        )
+    }
+
+    /// Shades every pixel covered by the given bounds, blending the result
+    /// over what has been drawn so far.
+    fn fill(
+        &mut self,
+        bounds: &Bounds<ScaledPixels>,
+        content_mask: &ContentMask<ScaledPixels>,
+        mut shade: impl FnMut(f32, f32) -> [f32; 4],
+    ) {
+        let (columns, rows) = self.pixel_range(bounds, content_mask);
+        for y in rows {
+            for x in columns.clone() {
+                let color = shade(x as f32 + 0.5, y as f32 + 0.5);
+                self.blend(x, y, color);
+            }
+        }
+    }
+
+    fn blend(&mut self, x: i32, y: i32, color: [f32; 4]) {
+        let pixel = &mut self.pixels[(y * self.width + x) as usize];
+        let [r, g, b, a] = color;
+        if a.is_nan() || a <= 0. {
+            return;
+        }
+        pixel[0] = r * a + pixel[0] * (1. - a);
+        pixel[1] = g * a + pixel[1] * (1. - a);
+        pixel[2] = b * a + pixel[2] * (1. - a);
+        pixel[3] = (a + pixel[3]).min(1.);
+    }
+
+    fn draw_shadow(&mut self, shadow: &Shadow) {
+        let color = rgba(shadow.color);
+        let blur_radius = shadow.blur_radius.0;
+        let margin = 3. * blur_radius;
+        let bounds = Bounds {
+            origin: crate::point(
+                shadow.bounds.origin.x - ScaledPixels(margin),
+                shadow.bounds.origin.y - ScaledPixels(margin),
+            ),
+            size: crate::size(
+                shadow.bounds.size.width + ScaledPixels(2. * margin),
+                shadow.bounds.size.height + ScaledPixels(2. * margin),
+            ),
+        };
+        let half_width = shadow.bounds.size.width.0 / 2.;
+        let half_height = shadow.bounds.size.height.0 / 2.;
+        let center_x = shadow.bounds.origin.x.0 + half_width;
+        let center_y = shadow.bounds.origin.y.0 + half_height;
+
+        self.fill(&bounds, &shadow.content_mask, |x, y| {
+            let point_x = x - center_x;
+            let point_y = y - center_y;
+            let corner_radius = corner_radius(point_x, point_y, &shadow.corner_radii);
+
+            // The signal is only non-zero in a limited range, so don't waste samples.
+            let low = point_y - half_height;
+            let high = point_y + half_height;
+            let start = (-margin).clamp(low, high);
+            let end = margin.clamp(low, high);
+
+            let step = (end - start) / 4.;
+            let mut y = start + step * 0.5;
+            let mut alpha = 0.;
+            for _ in 0..4 {
+                alpha += blur_along_x(
+                    point_x,
+                    point_y - y,
+                    blur_radius,
+                    corner_radius,
+                    (half_width, half_height),
+                ) * gaussian(y, blur_radius)
+                    * step;
+                y += step;
+            }
+
+            let mut color = color;
+            color[3] *= alpha;
+            color
+        });
+    }
+
+    fn draw_quad(&mut self, quad: &Quad) {
+        let background = rgba(quad.background);
+        let border = rgba(quad.border_color);
+        let radii = &quad.corner_radii;
+        let widths = &quad.border_widths;
+
+        // Fast path when the quad is not rounded and doesn't have any border.
+        if radii.top_left.0 == 0.
+            && radii.top_right.0 == 0.
+            && radii.bottom_right.0 == 0.
+            && radii.bottom_left.0 == 0.
+            && widths.top.0 == 0.
+            && widths.right.0 == 0.
+            && widths.bottom.0 == 0.
+            && widths.left.0 == 0.
+        {
+            self.fill(&quad.bounds, &quad.content_mask, |_, _| background);
+            return;
+        }
+
+        let half_width = quad.bounds.size.width.0 / 2.;
+        let half_height = quad.bounds.size.height.0 / 2.;
+        let center_x = quad.bounds.origin.x.0 + half_width;
+        let center_y = quad.bounds.origin.y.0 + half_height;
+        let blended_border = over(background, border);
+
+        self.fill(&quad.bounds, &quad.content_mask, |x, y| {
+            let point_x = x - center_x;
+            let point_y = y - center_y;
+            let corner_radius = corner_radius(point_x, point_y, radii);
+            let distance =
+                rounded_rect_sdf(point_x, point_y, (half_width, half_height), corner_radius);
+
+            let vertical_border = if point_x <= 0. {
+                widths.left.0
+            } else {
+                widths.right.0
+            };
+            let horizontal_border = if point_y <= 0. {
+                widths.top.0
+            } else {
+                widths.bottom.0
+            };
+            let to_inset_corner_x = point_x.abs() - (half_width - corner_radius - vertical_border);
+            let to_inset_corner_y =
+                point_y.abs() - (half_height - corner_radius - horizontal_border);
+            let border_width = if to_inset_corner_x < 0. && to_inset_corner_y < 0. {
+                0.
+            } else if to_inset_corner_y > to_inset_corner_x {
+                horizontal_border
+            } else {
+                vertical_border
+            };
+
+            let mut color = if border_width == 0. {
+                background
+            } else {
+                // Blend the border on top of the background and then linearly interpolate
+                // between the two as we slide inside the background.
+                let inset_distance = distance + border_width;
+                mix(blended_border, background, saturate(0.5 - inset_distance))
+            };
+            color[3] *= saturate(0.5 - distance);
+            color
+        });
+    }
+
+    fn draw_underline(&mut self, underline: &Underline) {
+        let color = rgba(underline.color);
+        if !underline.wavy {
+            self.fill(&underline.bounds, &underline.content_mask, |_, _| color);
+            return;
+        }
+
+        let thickness = underline.thickness.0;
+        let half_thickness = thickness * 0.5;
+        let height = underline.bounds.size.height.0;
+        let frequency = (PI * (3. * thickness)) / 8.;
+        let amplitude = 1. / (2. * thickness);
+        self.fill(&underline.bounds, &underline.content_mask, |x, y| {
+            let st_x = (x - underline.bounds.origin.x.0) / height;
+            let st_y = (y - underline.bounds.origin.y.0) / height - 0.5;
+            let sine = (st_x * frequency).sin() * amplitude;
+            let d_sine = (st_x * frequency).cos() * amplitude * frequency;
+            let distance = (st_y - sine) / (1. + d_sine * d_sine).sqrt();
+            let distance_in_pixels = distance * height;
+            let distance_from_top_border = distance_in_pixels - half_thickness;
+            let distance_from_bottom_border = distance_in_pixels + half_thickness;
+            let mut color = color;
+            color[3] *=
+                saturate(0.5 - (-distance_from_bottom_border).max(distance_from_top_border));
+            color
+        });
+    }
+
+    /// Paths are drawn by accumulating the coverage of each of their triangles
+    /// and then filling the pixels covered an odd number of times, so that
+    /// overlapping contours cancel each other out.
+    fn draw_path(&mut self, path: &Path<ScaledPixels>) {
+        let (columns, rows) = self.pixel_range(&path.bounds, &path.content_mask);
+        if columns.is_empty() || rows.is_empty() {
+            return;
+        }
+        let stride = columns.len();
+        let mut coverage = vec![0f32; stride * rows.len()];
+
+        for triangle in path.vertices.chunks_exact(3) {
+            let mut positions = triangle
+                .iter()
+                .map(|vertex| (vertex.xy_position.x.0, vertex.xy_position.y.0));
+            let mut st = triangle
+                .iter()
+                .map(|vertex| (vertex.st_position.x, vertex.st_position.y));
+            let mut v = [(0., 0.); 3];
+            let mut s = [(0., 0.); 3];
+            for i in 0..3 {
+                v[i] = positions.next().unwrap();
+                s[i] = st.next().unwrap();
+            }
+
+            let mut area = edge_function(v[0], v[1], v[2]);
+            if area == 0. {
+                continue;
+            } else if area < 0. {
+                v.swap(1, 2);
+                s.swap(1, 2);
+                area = -area;
+            }
+
+            let edges = [(v[1], v[2]), (v[2], v[0]), (v[0], v[1])];
+            // The barycentric weights vary linearly across the triangle, so
+            // their derivatives (and those of the texture coordinates) are constant.
+            let mut dsdx = (0., 0.);
+            let mut dsdy = (0., 0.);
+            for (i, (a, b)) in edges.iter().enumerate() {
+                let dwdx = -(b.1 - a.1) / area;
+                let dwdy = (b.0 - a.0) / area;
+                dsdx.0 += s[i].0 * dwdx;
+                dsdx.1 += s[i].1 * dwdx;
+                dsdy.0 += s[i].0 * dwdy;
+                dsdy.1 += s[i].1 * dwdy;
+            }
+
+            let min_x = v.iter().map(|v| v.0).fold(f32::INFINITY, f32::min);
+            let max_x = v.iter().map(|v| v.0).fold(f32::NEG_INFINITY, f32::max);
+            let min_y = v.iter().map(|v| v.1).fold(f32::INFINITY, f32::min);
+            let max_y = v.iter().map(|v| v.1).fold(f32::NEG_INFINITY, f32::max);
+            let first_x = ((min_x - 0.5).ceil() as i32).max(columns.start);
+            let last_x = ((max_x - 0.5).floor() as i32).min(columns.end - 1);
+            let first_y = ((min_y - 0.5).ceil() as i32).max(rows.start);
+            let last_y = ((max_y - 0.5).floor() as i32).min(rows.end - 1);
+
+            for y in first_y..=last_y {
+                for x in first_x..=last_x {
+                    let p = (x as f32 + 0.5, y as f32 + 0.5);
+                    let mut weights = [0.; 3];
+                    let inside = edges.iter().enumerate().all(|(i, (a, b))| {
+                        weights[i] = edge_function(*a, *b, p);
+                        weights[i] > 0. || (weights[i] == 0. && owns_edge(*a, *b))
+                    });
+                    if !inside {
+                        continue;
+                    }
+
+                    let st_x = (0..3).map(|i| s[i].0 * weights[i]).sum::<f32>() / area;
+                    let st_y = (0..3).map(|i| s[i].1 * weights[i]).sum::<f32>() / area;
+                    let gradient_x = 2. * st_x * dsdx.0 - dsdx.1;
+                    let gradient_y = 2. * st_x * dsdy.0 - dsdy.1;
+                    let f = st_x * st_x - st_y;
+                    let distance = f / (gradient_x * gradient_x + gradient_y * gradient_y).sqrt();
+                    let index = (y - rows.start) as usize * stride + (x - columns.start) as usize;
+                    coverage[index] += saturate(0.5 - distance);
+                }
+            }
+        }
+
+        let color = rgba(path.color);
+        for (row, y) in rows.enumerate() {
+            for (column, x) in columns.clone().enumerate() {
+                let coverage = coverage[row * stride + column];
+                if coverage == 0. {
+                    continue;
+                }
+                let mask = 1. - (1. - coverage % 2.).abs();
+                let mut color = color;
+                color[3] *= mask;
+                self.blend(x, y, color);
+            }
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn draw_surface(&mut self, surface: &Surface) {
+        use crate::ResultExt;
+        use media::core_video::kCVPixelFormatType_420YpCbCr8BiPlanarFullRange;
+
+        let image_buffer = &surface.image_buffer;
+        assert_eq!(
+            image_buffer.pixel_format_type(),
+            kCVPixelFormatType_420YpCbCr8BiPlanarFullRange
+        );
+        let Some(((y_plane, y_stride), (cb_cr_plane, cb_cr_stride))) = image_buffer
+            .copy_plane(0)
+            .and_then(|y| Ok((y, image_buffer.copy_plane(1)?)))
+            .log_err()
+        else {
+            return;
+        };
+        let y_size = (image_buffer.plane_width(0), image_buffer.plane_height(0));
+        let cb_cr_size = (image_buffer.plane_width(1), image_buffer.plane_height(1));
+
+        // Samples a plane with linear filtering, given a position normalized to the unit square.
+        let sample =
+            |plane: &[u8], stride: usize, size: (usize, usize), channel, u: f32, v: f32| {
+                let x = u * size.0 as f32 - 0.5;
+                let y = v * size.1 as f32 - 0.5;
+                let (x0, y0) = (x.floor(), y.floor());
+                let (tx, ty) = (x - x0, y - y0);
+                let texel = |dx: i32, dy: i32| {
+                    let x = (x0 as i32 + dx).clamp(0, size.0 as i32 - 1) as usize;
+                    let y = (y0 as i32 + dy).clamp(0, size.1 as i32 - 1) as usize;
+                    plane[y * stride + x * bytes_per_pixel + channel] as f32 / 255.
+                };
+                let top = texel(0, 0) * (1. - tx) + texel(1, 0) * tx;
+                let bottom = texel(0, 1) * (1. - tx) + texel(1, 1) * tx;
+                top * (1. - ty) + bottom * ty
+            };
+
+        self.fill(&surface.bounds, &surface.content_mask, |x, y| {
+            let u = (x - surface.bounds.origin.x.0) / surface.bounds.size.width.0;
+            let v = (y - surface.bounds.origin.y.0) / surface.bounds.size.height.0;
+            let luma = sample(&y_plane, y_stride, y_size, 1, 0, u, v);
+            let cb = sample(&cb_cr_plane, cb_cr_stride, cb_cr_size, 2, 0, u, v);
+            let cr = sample(&cb_cr_plane, cb_cr_stride, cb_cr_size, 2, 1, u, v);
+            [
+                luma + 1.402 * cr - 0.701,
+                luma - 0.3441 * cb - 0.7141 * cr + 0.5291,
+                luma + 1.772 * cb - 0.886,
+                1.,
+            ]
+        });
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    fn draw_surface(&mut self, _: &Surface) {}
+
+    fn into_image(self) -> RgbaImage {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+        for pixel in &self.pixels {
+            bytes.extend(
+                pixel
+                    .iter()
+                    .map(|component| (component.clamp(0., 1.) * 255.).round() as u8),
+            );
+        }
+        RgbaImage::from_raw(self.width as u32, self.height as u32, bytes).unwrap()
+    }
+}
+
+fn rgba(color: Hsla) -> [f32; 4] {
+    let color = Rgba::from(color);
+    [color.r, color.g, color.b, color.a]
+}
+
+/// Clamps a value to the unit interval, treating NaN as zero.
+fn saturate(value: f32) -> f32 {
+    if value.is_nan() {
+        0.
+    } else {
+        value.clamp(0., 1.)
+    }
+}
+
+fn mix(a: [f32; 4], b: [f32; 4], t: f32) -> [f32; 4] {
+    [0, 1, 2, 3].map(|i| a[i] * (1. - t) + b[i] * t)
+}
+
+fn over(below: [f32; 4], above: [f32; 4]) -> [f32; 4] {
+    let alpha = above[3] + below[3] * (1. - above[3]);
+    if alpha == 0. {
+        return [0.; 4];
+    }
+    let [r, g, b] =
+        [0, 1, 2].map(|i| (above[i] * above[3] + below[i] * below[3] * (1. - above[3])) / alpha);
+    [r, g, b, alpha]
+}
+
+/// The ratio between the size of a tile and the size of the bounds it's drawn into.
+fn tile_scale(bounds: &Bounds<ScaledPixels>, tile: &Bounds<DevicePixels>) -> (f32, f32) {
+    (
+        tile.size.width.0 as f32 / bounds.size.width.0,
+        tile.size.height.0 as f32 / bounds.size.height.0,
+    )
+}
+
+fn corner_radius(x: f32, y: f32, corner_radii: &Corners<ScaledPixels>) -> f32 {
+    match (x < 0., y < 0.) {
+        (true, true) => corner_radii.top_left.0,
+        (true, false) => corner_radii.bottom_left.0,
+        (false, true) => corner_radii.top_right.0,
+        (false, false) => corner_radii.bottom_right.0,
+    }
+}
+
+fn rounded_rect_sdf(x: f32, y: f32, half_size: (f32, f32), corner_radius: f32) -> f32 {
+    let edge_x = x.abs() - half_size.0 + corner_radius;
+    let edge_y = y.abs() - half_size.1 + corner_radius;
+    edge_x.max(0.).hypot(edge_y.max(0.)) + edge_x.max(edge_y).min(0.) - corner_radius
+}
+
+fn quad_sdf(
+    x: f32,
+    y: f32,
+    bounds: &Bounds<ScaledPixels>,
+    corner_radii: &Corners<ScaledPixels>,
+) -> f32 {
+    let half_width = bounds.size.width.0 / 2.;
+    let half_height = bounds.size.height.0 / 2.;
+    let x = x - (bounds.origin.x.0 + half_width);
+    let y = y - (bounds.origin.y.0 + half_height);
+    rounded_rect_sdf(
+        x,
+        y,
+        (half_width, half_height),
+        corner_radius(x, y, corner_radii),
+    )
+}
+
+/// A standard gaussian function, used for weighting samples.
+fn gaussian(x: f32, sigma: f32) -> f32 {
+    (-(x * x) / (2. * sigma * sigma)).exp() / ((2. * PI).sqrt() * sigma)
+}
+
+/// Approximates the error function, needed for the gaussian integral.
+fn erf(x: f32) -> f32 {
+    let s = x.signum();
+    let a = x.abs();
+    let mut x = 1. + (0.278393 + (0.230389 + 0.078108 * (a * a)) * a) * a;
+    x *= x;
+    s - s / (x * x)
+}
+
+fn blur_along_x(x: f32, y: f32, sigma: f32, corner: f32, half_size: (f32, f32)) -> f32 {
+    let delta = (half_size.1 - corner - y.abs()).min(0.);
+    let curved = half_size.0 - corner + (corner * corner - delta * delta).max(0.).sqrt();
+    let scale = 0.5f32.sqrt() / sigma;
+    let start = 0.5 + 0.5 * erf((x - curved) * scale);
+    let end = 0.5 + 0.5 * erf((x + curved) * scale);
+    end - start
+}
+
+/// Twice the signed area of the triangle formed by the edge from `a` to `b`
+/// and the given point.
+fn edge_function(a: (f32, f32), b: (f32, f32), point: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (point.1 - a.1) - (b.1 - a.1) * (point.0 - a.0)
+}
+
+/// Decides which of the two triangles sharing an edge covers the pixels whose
+/// centers lie exactly on it, so that they aren't counted twice.
+fn owns_edge(a: (f32, f32), b: (f32, f32)) -> bool {
+    b.1 > a.1 || (b.1 == a.1 && b.0 < a.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        point, px, size, AtlasKey, AtlasTextureKind, Edges, FontId, GlyphId, PlatformAtlas,
+        RenderGlyphParams,
+    };
+    use std::borrow::Cow;
+
+    fn bounds(x: f32, y: f32, width: f32, height: f32) -> Bounds<ScaledPixels> {
+        Bounds {
+            origin: point(ScaledPixels(x), ScaledPixels(y)),
+            size: size(ScaledPixels(width), ScaledPixels(height)),
+        }
+    }
+
+    fn pixel(image: &RgbaImage, x: u32, y: u32) -> [u8; 4] {
+        image.get_pixel(x, y).0
+    }
+
+    fn opaque(color: Hsla) -> [u8; 4] {
+        rgba(color).map(|component| (component * 255.).round() as u8)
+    }
+
+    #[test]
+    fn test_draw_quads() {
+        let renderer = SoftwareRenderer::new(Arc::new(SoftwareAtlas::new()));
+        let mut scene = Scene::default();
+        scene.insert(
+            &Default::default(),
+            Quad {
+                bounds: bounds(0., 0., 8., 8.),
+                content_mask: ContentMask {
+                    bounds: bounds(0., 0., 16., 16.),
+                },
+                background: Hsla::red(),
+                ..Default::default()
+            },
+        );
+        scene.insert(
+            &Default::default(),
+            Quad {
+                bounds: bounds(8., 0., 8., 8.),
+                content_mask: ContentMask {
+                    bounds: bounds(0., 0., 16., 16.),
+                },
+                background: Hsla::green(),
+                border_color: Hsla::blue(),
+                border_widths: Edges::all(ScaledPixels(2.)),
+                corner_radii: Corners::all(ScaledPixels(4.)),
+                ..Default::default()
+            },
+        );
+        scene.finish();
+
+        let image = renderer.draw(
+            &scene,
+            Size {
+                width: DevicePixels(16),
+                height: DevicePixels(8),
+            },
+        );
+        assert_eq!(pixel(&image, 0, 0), opaque(Hsla::red()));
+        assert_eq!(pixel(&image, 7, 7), opaque(Hsla::red()));
+        // The rounded corner leaves the background showing through.
+        assert_eq!(pixel(&image, 8, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&image, 12, 1), opaque(Hsla::blue()));
+        assert_eq!(pixel(&image, 12, 4), opaque(Hsla::green()));
+    }
+
+    #[test]
+    fn test_draw_sprites_and_paths() {
+        let atlas = Arc::new(SoftwareAtlas::new());
+        let renderer = SoftwareRenderer::new(atlas.clone());
+        let key = AtlasKey::Glyph(RenderGlyphParams {
+            font_id: FontId(0),
+            glyph_id: GlyphId(0),
+            font_size: px(4.),
+            subpixel_variant: Default::default(),
+            scale_factor: 1.,
+            is_emoji: false,
+        });
+        let tile = atlas
+            .get_or_insert_with(&key, &mut || {
+                Ok((
+                    Size {
+                        width: DevicePixels(2),
+                        height: DevicePixels(1),
+                    },
+                    Cow::Borrowed(&[255, 0]),
+                ))
+            })
+            .unwrap();
+        assert_eq!(tile.texture_id.kind, AtlasTextureKind::Monochrome);
+
+        let mut scene = Scene::default();
+        scene.insert(
+            &Default::default(),
+            MonochromeSprite {
+                view_id: Default::default(),
+                layer_id: 0,
+                order: 0,
+                bounds: bounds(0., 0., 2., 1.),
+                content_mask: ContentMask {
+                    bounds: bounds(0., 0., 8., 8.),
+                },
+                color: Hsla::white(),
+                tile,
+            },
+        );
+        let mut path = Path::new(point(px(4.), px(0.)));
+        path.line_to(point(px(8.), px(0.)));
+        path.line_to(point(px(8.), px(4.)));
+        path.line_to(point(px(4.), px(4.)));
+        path.line_to(point(px(4.), px(0.)));
+        let mut path = path.scale(1.);
+        path.content_mask = ContentMask {
+            bounds: bounds(0., 0., 8., 8.),
+        };
+        path.color = Hsla::white();
+        scene.insert(&Default::default(), path);
+        scene.finish();
+
+        let image = renderer.draw(
+            &scene,
+            Size {
+                width: DevicePixels(8),
+                height: DevicePixels(4),
+            },
+        );
+        assert_eq!(pixel(&image, 0, 0), [255, 255, 255, 255]);
+        assert_eq!(pixel(&image, 1, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&image, 0, 1), [0, 0, 0, 255]);
+        // Pixels on the edge shared by the path's two triangles are filled once.
+        for (x, y) in [(4, 0), (5, 1), (6, 2), (7, 3), (5, 3)] {
+            assert_eq!(pixel(&image, x, y), [255, 255, 255, 255]);
+        }
+        assert_eq!(pixel(&image, 3, 3), [0, 0, 0, 255]);
+    }
+}
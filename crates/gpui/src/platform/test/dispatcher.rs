@@ -7,6 +7,7 @@ use parking_lot::Mutex;
 use rand::prelude::*;
 use std::{
     future::Future,
+    mem,
     ops::RangeInclusive,
     pin::Pin,
     sync::Arc,
@@ -39,6 +40,7 @@ struct TestDispatcherState {
     waiting_backtrace: Option<Backtrace>,
     deprioritized_task_labels: HashSet<TaskLabel>,
     block_on_ticks: RangeInclusive<usize>,
+    idle_callbacks: Vec<Box<dyn FnOnce() + Send>>,
 }
 
 impl TestDispatcher {
@@ -57,6 +59,7 @@ impl TestDispatcher {
             waiting_backtrace: None,
             deprioritized_task_labels: Default::default(),
             block_on_ticks: 0..=1000,
+            idle_callbacks: Vec::new(),
         };
 
         TestDispatcher {
@@ -235,7 +238,15 @@ impl PlatformDispatcher for TestDispatcher {
         if foreground_len == 0 && background_len == 0 {
             let deprioritized_background_len = state.deprioritized_background.len();
             if deprioritized_background_len == 0 {
-                return false;
+                if background_only || state.idle_callbacks.is_empty() {
+                    return false;
+                }
+                let idle_callbacks = mem::take(&mut state.idle_callbacks);
+                drop(state);
+                for callback in idle_callbacks {
+                    callback();
+                }
+                return true;
             }
             let ix = state.random.gen_range(0..deprioritized_background_len);
             main_thread = false;
@@ -278,6 +289,10 @@ impl PlatformDispatcher for TestDispatcher {
         self.unparker.clone()
     }
 
+    fn on_idle(&self, callback: Box<dyn FnOnce() + Send>) {
+        self.state.lock().idle_callbacks.push(callback);
+    }
+
     fn as_test(&self) -> Option<&TestDispatcher> {
         Some(self)
     }
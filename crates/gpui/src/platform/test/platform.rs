@@ -8,7 +8,7 @@ use collections::VecDeque;
 use futures::channel::oneshot;
 use parking_lot::Mutex;
 use std::{
-    cell::RefCell,
+    cell::{Cell, RefCell},
     path::PathBuf,
     rc::{Rc, Weak},
     sync::Arc,
@@ -26,6 +26,8 @@ pub(crate) struct TestPlatform {
     current_clipboard_item: Mutex<Option<ClipboardItem>>,
     pub(crate) prompts: RefCell<TestPrompts>,
     pub opened_url: RefCell<Option<String>>,
+    /// Whether windows opened from now on can render their frames to images.
+    pub(crate) software_rendering: Cell<bool>,
     weak: Weak<Self>,
 }
 
@@ -47,6 +49,7 @@ impl TestPlatform {
             current_clipboard_item: Mutex::new(None),
             weak: weak.clone(),
             opened_url: Default::default(),
+            software_rendering: Cell::new(false),
         })
     }
 
@@ -120,7 +123,11 @@ impl Platform for TestPlatform {
     }
 
     fn text_system(&self) -> Arc<dyn PlatformTextSystem> {
-        Arc::new(crate::platform::mac::MacTextSystem::new())
+        #[cfg(target_os = "macos")]
+        return Arc::new(crate::platform::mac::MacTextSystem::new());
+
+        #[cfg(not(target_os = "macos"))]
+        return Arc::new(crate::platform::cosmic_text::CosmicTextSystem::with_bundled_fonts());
     }
 
     fn run(&self, _on_finish_launching: Box<dyn FnOnce()>) {
@@ -174,6 +181,7 @@ impl Platform for TestPlatform {
             handle,
             self.weak.clone(),
             self.active_display.clone(),
+            self.software_rendering.get(),
         );
         Box::new(window)
     }
@@ -1,9 +1,11 @@
 use crate::{
-    px, AnyWindowHandle, AtlasKey, AtlasTextureId, AtlasTile, Bounds, KeyDownEvent, Keystroke,
-    Pixels, PlatformAtlas, PlatformDisplay, PlatformInput, PlatformInputHandler, PlatformWindow,
-    Point, Size, TestPlatform, TileId, WindowAppearance, WindowBounds, WindowOptions,
+    px, AnyWindowHandle, AtlasKey, AtlasTextureId, AtlasTile, Bounds, DevicePixels, KeyDownEvent,
+    Keystroke, Pixels, PlatformAtlas, PlatformDisplay, PlatformInput, PlatformInputHandler,
+    PlatformWindow, Point, Scene, Size, SoftwareAtlas, SoftwareRenderer, TestPlatform, TileId,
+    WindowAppearance, WindowBounds, WindowOptions,
 };
 use collections::HashMap;
+use image::RgbaImage;
 use parking_lot::Mutex;
 use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
 use std::{
@@ -19,6 +21,9 @@ pub(crate) struct TestWindowState {
     pub(crate) edited: bool,
     platform: Weak<TestPlatform>,
     sprite_atlas: Arc<dyn PlatformAtlas>,
+    /// The atlas that keeps the rasterized sprites, when the window's frames
+    /// can be rendered to images.
+    software_atlas: Option<Arc<SoftwareAtlas>>,
     pub(crate) should_close_handler: Option<Box<dyn FnMut() -> bool>>,
     input_callback: Option<Box<dyn FnMut(PlatformInput) -> bool>>,
     active_status_change_callback: Option<Box<dyn FnMut(bool)>>,
@@ -52,13 +57,20 @@ impl TestWindow {
         handle: AnyWindowHandle,
         platform: Weak<TestPlatform>,
         display: Rc<dyn PlatformDisplay>,
+        software_rendering: bool,
     ) -> Self {
+        let software_atlas = software_rendering.then(|| Arc::new(SoftwareAtlas::new()));
+        let sprite_atlas: Arc<dyn PlatformAtlas> = match software_atlas.clone() {
+            Some(software_atlas) => software_atlas,
+            None => Arc::new(TestAtlas::new()),
+        };
         Self(Arc::new(Mutex::new(TestWindowState {
             bounds: options.bounds,
             display,
             platform,
             handle,
-            sprite_atlas: Arc::new(TestAtlas::new()),
+            sprite_atlas,
+            software_atlas,
             title: Default::default(),
             edited: false,
             should_close_handler: None,
@@ -147,6 +159,18 @@ impl TestWindow {
 
         self.0.lock().input_handler = Some(input_handler);
     }
+
+    /// Draws the given scene on the CPU, at the window's size and scale factor.
+    pub(crate) fn render(&self, scene: &Scene) -> RgbaImage {
+        let scale_factor = self.scale_factor();
+        let viewport_size = self
+            .content_size()
+            .map(|size| DevicePixels::from((f32::from(size) * scale_factor).ceil() as i32));
+        let atlas = self.0.lock().software_atlas.clone().expect(
+            "call TestAppContext::enable_software_rendering before opening a window to render its frames",
+        );
+        SoftwareRenderer::new(atlas).draw(scene, viewport_size)
+    }
 }
 
 impl PlatformWindow for TestWindow {
@@ -282,9 +306,9 @@ impl PlatformWindow for TestWindow {
         unimplemented!()
     }
 
-    fn draw(&self, _scene: &crate::Scene) {}
+    fn draw(&self, _scene: &Scene) {}
 
-    fn sprite_atlas(&self) -> sync::Arc<dyn crate::PlatformAtlas> {
+    fn sprite_atlas(&self) -> sync::Arc<dyn PlatformAtlas> {
         self.0.lock().sprite_atlas.clone()
     }
 
@@ -25,6 +25,10 @@
 //!   assert!(true)
 //! }
 //! ```
+mod golden_image;
+
+pub use golden_image::*;
+
 use crate::{Entity, Subscription, TestAppContext, TestDispatcher};
 use futures::StreamExt as _;
 use rand::prelude::*;
@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Context as _, Result};
+use image::{Rgba, RgbaImage};
+use std::{env, fs, path::Path};
+
+/// The largest possible value of [`color_delta`], between black and white.
+const MAX_COLOR_DELTA: f32 = 35215.;
+
+/// How far an image may stray from its golden image before they're considered different.
+#[derive(Clone, Copy, Debug)]
+pub struct ImageTolerance {
+    /// How perceptually different two pixels may be before they count as
+    /// different, from 0 (identical) to 1 (black and white).
+    pub threshold: f32,
+    /// How many pixels may differ before the images count as different.
+    pub max_differing_pixels: usize,
+}
+
+impl Default for ImageTolerance {
+    fn default() -> Self {
+        Self {
+            threshold: 0.1,
+            max_differing_pixels: 0,
+        }
+    }
+}
+
+/// The differences found between two images of the same size.
+pub struct ImageDiff {
+    /// The number of pixels that differ by more than the tolerance's threshold.
+    pub differing_pixels: usize,
+    /// A faded copy of the expected image, with the differing pixels in red.
+    pub image: RgbaImage,
+}
+
+/// Compares two images of the same size pixel by pixel, returning their
+/// differences if there are more than the tolerance allows.
+pub fn diff_images(
+    expected: &RgbaImage,
+    actual: &RgbaImage,
+    tolerance: ImageTolerance,
+) -> Result<Option<ImageDiff>> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err(anyhow!(
+            "expected a {:?} image, but got one that is {:?}",
+            expected.dimensions(),
+            actual.dimensions()
+        ));
+    }
+
+    let max_delta = MAX_COLOR_DELTA * tolerance.threshold * tolerance.threshold;
+    let mut differing_pixels = 0;
+    let mut image = RgbaImage::new(expected.width(), expected.height());
+    for ((expected, actual), diff) in expected
+        .pixels()
+        .zip(actual.pixels())
+        .zip(image.pixels_mut())
+    {
+        if color_delta(expected, actual) > max_delta {
+            differing_pixels += 1;
+            *diff = Rgba([255, 0, 0, 255]);
+        } else {
+            let (luma, _, _) = yiq(expected);
+            let faded = (255. + (luma - 255.) * 0.1) as u8;
+            *diff = Rgba([faded, faded, faded, 255]);
+        }
+    }
+
+    if differing_pixels > tolerance.max_differing_pixels {
+        Ok(Some(ImageDiff {
+            differing_pixels,
+            image,
+        }))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Compares an image against the golden image stored at the given path.
+///
+/// When the `UPDATE_GOLDEN_IMAGES` environment variable is set, the image is
+/// saved as the new golden image instead. A missing golden image is an error
+/// otherwise, so that a test can't pass by recording its own expectations.
+///
+/// When the images differ, the actual image and an image highlighting the
+/// differences are saved next to the golden image, with the `.actual.png`
+/// and `.diff.png` extensions.
+pub fn check_golden_image(
+    actual: &RgbaImage,
+    golden_path: &Path,
+    tolerance: ImageTolerance,
+) -> Result<()> {
+    if env::var("UPDATE_GOLDEN_IMAGES").map_or(false, |value| !value.is_empty()) {
+        if let Some(parent) = golden_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        actual
+            .save(golden_path)
+            .with_context(|| format!("failed to save golden image {golden_path:?}"))?;
+        log::info!("recorded golden image {golden_path:?}");
+        return Ok(());
+    }
+    if !golden_path.exists() {
+        return Err(anyhow!(
+            "golden image {golden_path:?} is missing; record it by running the test with UPDATE_GOLDEN_IMAGES=1"
+        ));
+    }
+
+    let expected = image::open(golden_path)
+        .with_context(|| format!("failed to load golden image {golden_path:?}"))?
+        .into_rgba8();
+    let actual_path = golden_path.with_extension("actual.png");
+    let diff_path = golden_path.with_extension("diff.png");
+    let save_actual = || {
+        actual
+            .save(&actual_path)
+            .with_context(|| format!("failed to save image {actual_path:?}"))
+    };
+    match diff_images(&expected, actual, tolerance) {
+        Ok(None) => {
+            fs::remove_file(&actual_path).ok();
+            fs::remove_file(&diff_path).ok();
+            Ok(())
+        }
+        Ok(Some(diff)) => {
+            save_actual()?;
+            diff.image
+                .save(&diff_path)
+                .with_context(|| format!("failed to save image {diff_path:?}"))?;
+            Err(anyhow!(
+                "{} pixels differ from golden image {golden_path:?}; see {diff_path:?}",
+                diff.differing_pixels
+            ))
+        }
+        Err(error) => {
+            save_actual()?;
+            Err(error.context(format!("image differs from golden image {golden_path:?}")))
+        }
+    }
+}
+
+/// Converts a pixel to the YIQ color space, after blending it over white.
+fn yiq(pixel: &Rgba<u8>) -> (f32, f32, f32) {
+    let [r, g, b, a] = pixel.0.map(|component| component as f32);
+    let alpha = a / 255.;
+    let [r, g, b] = [r, g, b].map(|component| 255. + (component - 255.) * alpha);
+    (
+        r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+        r * 0.59597799 - g * 0.2741761 - b * 0.32180189,
+        r * 0.21147017 - g * 0.52261711 + b * 0.31114694,
+    )
+}
+
+/// Measures how different two colors look, weighting brightness more than
+/// hue, as described in "Measuring perceived color difference using YIQ
+/// NTSC transmission color space in mobile applications" by Kotsarenko and
+/// Ramos.
+fn color_delta(a: &Rgba<u8>, b: &Rgba<u8>) -> f32 {
+    if a == b {
+        return 0.;
+    }
+    let (y1, i1, q1) = yiq(a);
+    let (y2, i2, q2) = yiq(b);
+    let (y, i, q) = (y1 - y2, i1 - i2, q1 - q2);
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_images() {
+        let mut expected = RgbaImage::from_pixel(4, 2, Rgba([255, 255, 255, 255]));
+        expected.put_pixel(0, 0, Rgba([0, 0, 0, 255]));
+
+        // Slight changes in color are tolerated.
+        let mut actual = expected.clone();
+        actual.put_pixel(1, 0, Rgba([250, 252, 255, 255]));
+        assert!(diff_images(&expected, &actual, ImageTolerance::default())
+            .unwrap()
+            .is_none());
+
+        actual.put_pixel(2, 1, Rgba([255, 0, 0, 255]));
+        let diff = diff_images(&expected, &actual, ImageTolerance::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(diff.differing_pixels, 1);
+        assert_eq!(diff.image.get_pixel(2, 1), &Rgba([255, 0, 0, 255]));
+        assert_eq!(diff.image.get_pixel(3, 1), &Rgba([255, 255, 255, 255]));
+        assert_eq!(diff.image.get_pixel(0, 0), &Rgba([229, 229, 229, 255]));
+
+        let tolerance = ImageTolerance {
+            max_differing_pixels: 1,
+            ..Default::default()
+        };
+        assert!(diff_images(&expected, &actual, tolerance)
+            .unwrap()
+            .is_none());
+
+        let smaller = RgbaImage::new(2, 2);
+        assert!(diff_images(&expected, &smaller, tolerance).is_err());
+    }
+}
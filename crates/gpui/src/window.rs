@@ -1,13 +1,13 @@
 use crate::{
-    px, size, transparent_black, Action, AnyDrag, AnyView, AppContext, Arena, AsyncWindowContext,
-    AvailableSpace, Bounds, Context, Corners, CursorStyle, DispatchActionListener, DispatchNodeId,
-    DispatchTree, DisplayId, Edges, Effect, Entity, EntityId, EventEmitter, FileDropEvent, Flatten,
-    Global, GlobalElementId, Hsla, KeyBinding, KeyContext, KeyDownEvent, KeyMatch, KeymatchMode,
-    KeymatchResult, Keystroke, KeystrokeEvent, Model, ModelContext, Modifiers, MouseButton,
-    MouseMoveEvent, MouseUpEvent, Pixels, PlatformAtlas, PlatformDisplay, PlatformInput,
-    PlatformWindow, Point, PromptLevel, Render, ScaledPixels, SharedString, Size, SubscriberSet,
-    Subscription, TaffyLayoutEngine, Task, View, VisualContext, WeakView, WindowAppearance,
-    WindowBounds, WindowOptions, WindowTextSystem,
+    px, size, transparent_black, AccessibilityNode, AccessibilityRole, AccessibilityTree, Action,
+    AnyDrag, AnyView, AppContext, Arena, AsyncWindowContext, AvailableSpace, Bounds, Context,
+    Corners, CursorStyle, DispatchActionListener, DispatchNodeId, DispatchTree, DisplayId, Edges,
+    Effect, Entity, EntityId, EventEmitter, FileDropEvent, Flatten, Global, GlobalElementId, Hsla,
+    KeyBinding, KeyContext, KeyDownEvent, KeyMatch, KeymatchMode, KeymatchResult, Keystroke,
+    KeystrokeEvent, Model, ModelContext, Modifiers, MouseButton, MouseMoveEvent, MouseUpEvent,
+    Pixels, PlatformAtlas, PlatformDisplay, PlatformInput, PlatformWindow, Point, PromptLevel,
+    Render, ScaledPixels, SharedString, Size, SubscriberSet, Subscription, TaffyLayoutEngine, Task,
+    View, VisualContext, WeakView, WindowAppearance, WindowBounds, WindowOptions, WindowTextSystem,
 };
 use anyhow::{anyhow, Context as _, Result};
 use collections::FxHashSet;
@@ -249,6 +249,8 @@ pub struct Window {
     pub(crate) handle: AnyWindowHandle,
     pub(crate) removed: bool,
     pub(crate) platform_window: Box<dyn PlatformWindow>,
+    title: Option<SharedString>,
+    accessibility_enabled: bool,
     display_id: DisplayId,
     sprite_atlas: Arc<dyn PlatformAtlas>,
     text_system: Arc<WindowTextSystem>,
@@ -330,6 +332,10 @@ impl Window {
         options: WindowOptions,
         cx: &mut AppContext,
     ) -> Self {
+        let title = options
+            .titlebar
+            .as_ref()
+            .and_then(|titlebar| titlebar.title.clone());
         let platform_window = cx.platform.open_window(handle, options);
         let display_id = platform_window.display().id();
         let sprite_atlas = platform_window.sprite_atlas();
@@ -388,6 +394,14 @@ impl Window {
                     .log_err();
             }
         }));
+        platform_window.on_accessibility_requested(Box::new({
+            let mut cx = cx.to_async();
+            move |enabled| {
+                handle
+                    .update(&mut cx, |_, cx| cx.set_accessibility_enabled(enabled))
+                    .log_err();
+            }
+        }));
         platform_window.on_active_status_change(Box::new({
             let mut cx = cx.to_async();
             move |active| {
@@ -417,6 +431,8 @@ impl Window {
             handle,
             removed: false,
             platform_window,
+            title,
+            accessibility_enabled: false,
             display_id,
             sprite_atlas,
             text_system,
@@ -791,9 +807,48 @@ impl<'a> WindowContext<'a> {
 
     /// Updates the window's title at the platform level.
     pub fn set_window_title(&mut self, title: &str) {
+        if self
+            .window
+            .title
+            .as_ref()
+            .map_or(true, |current| *current != title)
+        {
+            self.window.title = Some(SharedString::from(title.to_string()));
+            // The title labels the root of the accessibility tree.
+            if self.window.accessibility_enabled && !self.window.drawing {
+                self.window.dirty.set(true);
+            }
+        }
         self.window.platform_window.set_title(title);
     }
 
+    /// The accessibility tree of the last frame drawn in this window, which is
+    /// empty unless the window builds one.
+    pub fn accessibility_tree(&self) -> &AccessibilityTree {
+        &self.window.rendered_frame.accessibility_tree
+    }
+
+    /// Whether the window builds an accessibility tree as it draws. It only does
+    /// while assistive technologies read it, as describing every element each
+    /// frame has a cost.
+    pub fn is_accessibility_enabled(&self) -> bool {
+        self.window.accessibility_enabled
+    }
+
+    /// Starts or stops building the window's accessibility tree.
+    pub fn set_accessibility_enabled(&mut self, enabled: bool) {
+        if self.window.accessibility_enabled == enabled {
+            return;
+        }
+        self.window.accessibility_enabled = enabled;
+        if !enabled {
+            self.window.rendered_frame.accessibility_tree.clear();
+        }
+        // Views reused from the last frame wouldn't have the nodes of their
+        // elements, so everything is drawn anew.
+        self.refresh();
+    }
+
     /// Mark the window as dirty at the platform level.
     pub fn set_window_edited(&mut self, edited: bool) {
         self.window.platform_window.set_edited(edited);
@@ -997,8 +1052,17 @@ impl<'a> WindowContext<'a> {
                         }
                     }
 
-                    let available_space = cx.window.viewport_size.map(Into::into);
-                    root_view.draw(Point::default(), available_space, cx);
+                    let viewport_size = cx.window.viewport_size;
+                    let mut node = AccessibilityNode::new(
+                        AccessibilityRole::Window,
+                        Bounds::new(Point::default(), viewport_size),
+                    );
+                    if let Some(title) = cx.window.title.clone() {
+                        node = node.label(title);
+                    }
+                    cx.with_accessibility_node(node, |cx| {
+                        root_view.draw(Point::default(), viewport_size.map(Into::into), cx);
+                    });
                 })
             })
         });
@@ -1075,6 +1139,18 @@ impl<'a> WindowContext<'a> {
         let previous_focus_path = self.window.rendered_frame.focus_path();
         let previous_window_active = self.window.rendered_frame.window_active;
         mem::swap(&mut self.window.rendered_frame, &mut self.window.next_frame);
+        if self.window.accessibility_enabled {
+            if let Some(update) = self
+                .window
+                .rendered_frame
+                .accessibility_tree
+                .diff(&self.window.next_frame.accessibility_tree)
+            {
+                self.window
+                    .platform_window
+                    .update_accessibility_tree(update);
+            }
+        }
         self.window.next_frame.clear();
         let current_focus_path = self.window.rendered_frame.focus_path();
         let current_window_active = self.window.rendered_frame.window_active;
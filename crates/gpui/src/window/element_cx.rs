@@ -23,19 +23,20 @@ use std::{
 use anyhow::Result;
 use collections::{FxHashMap, FxHashSet};
 use derive_more::{Deref, DerefMut};
+#[cfg(target_os = "macos")]
 use media::core_video::CVImageBuffer;
 use smallvec::SmallVec;
 use util::post_inc;
 
 use crate::{
-    prelude::*, size, AnyTooltip, AppContext, AvailableSpace, Bounds, BoxShadow, ContentMask,
-    Corners, CursorStyle, DevicePixels, DispatchPhase, DispatchTree, ElementId, ElementStateBox,
-    EntityId, FocusHandle, FocusId, FontId, GlobalElementId, GlyphId, Hsla, ImageData,
-    InputHandler, IsZero, KeyContext, KeyEvent, KeymatchMode, LayoutId, MonochromeSprite,
-    MouseEvent, PaintQuad, Path, Pixels, PlatformInputHandler, Point, PolychromeSprite, Quad,
-    RenderGlyphParams, RenderImageParams, RenderSvgParams, Scene, Shadow, SharedString, Size,
-    StackingContext, StackingOrder, Style, Surface, TextStyleRefinement, Underline, UnderlineStyle,
-    Window, WindowContext, SUBPIXEL_VARIANTS,
+    prelude::*, size, AccessibilityNode, AccessibilityTree, AnyTooltip, AppContext, AvailableSpace,
+    Bounds, BoxShadow, ContentMask, Corners, CursorStyle, DevicePixels, DispatchPhase,
+    DispatchTree, ElementId, ElementStateBox, EntityId, FocusHandle, FocusId, FontId,
+    GlobalElementId, GlyphId, Hsla, ImageData, InputHandler, IsZero, KeyContext, KeyEvent,
+    KeymatchMode, LayoutId, MonochromeSprite, MouseEvent, PaintQuad, Path, Pixels,
+    PlatformInputHandler, Point, PolychromeSprite, Quad, RenderGlyphParams, RenderImageParams,
+    RenderSvgParams, Scene, Shadow, SharedString, Size, StackingContext, StackingOrder, Style,
+    TextStyleRefinement, Underline, UnderlineStyle, Window, WindowContext, SUBPIXEL_VARIANTS,
 };
 
 type AnyMouseListener = Box<dyn FnMut(&dyn Any, DispatchPhase, &mut ElementContext) + 'static>;
@@ -69,6 +70,7 @@ pub(crate) struct Frame {
     pub(crate) requested_cursor_style: Option<CursorStyle>,
     pub(crate) view_stack: Vec<EntityId>,
     pub(crate) reused_views: FxHashSet<EntityId>,
+    pub(crate) accessibility_tree: AccessibilityTree,
 
     #[cfg(any(test, feature = "test-support"))]
     pub(crate) debug_bounds: collections::FxHashMap<String, Bounds<Pixels>>,
@@ -95,6 +97,7 @@ impl Frame {
             requested_cursor_style: None,
             view_stack: Vec::new(),
             reused_views: FxHashSet::default(),
+            accessibility_tree: AccessibilityTree::default(),
 
             #[cfg(any(test, feature = "test-support"))]
             debug_bounds: FxHashMap::default(),
@@ -114,6 +117,7 @@ impl Frame {
         self.tooltip_request.take();
         self.cursor_styles.clear();
         self.requested_cursor_style.take();
+        self.accessibility_tree.clear();
         debug_assert_eq!(self.view_stack.len(), 0);
     }
 
@@ -317,6 +321,11 @@ impl<'a> ElementContext<'a> {
             .next_frame
             .dispatch_tree
             .reuse_view(view_id, &mut self.cx.window.rendered_frame.dispatch_tree);
+        let window = &mut self.cx.window;
+        window
+            .next_frame
+            .accessibility_tree
+            .reuse_views(&grafted_view_ids, &window.rendered_frame.accessibility_tree);
         for view_id in grafted_view_ids {
             assert!(self.window.next_frame.reused_views.insert(view_id));
 
@@ -419,6 +428,29 @@ impl<'a> ElementContext<'a> {
         }
     }
 
+    /// Adds a node to the window's accessibility tree and invokes the given closure, adding
+    /// the nodes of the elements painted within it as the node's children. The node is
+    /// dropped unless the window builds an accessibility tree; elements whose node is
+    /// costly to describe should check [`WindowContext::is_accessibility_enabled`] first.
+    pub fn with_accessibility_node<R>(
+        &mut self,
+        node: AccessibilityNode,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        if !self.window.accessibility_enabled {
+            return f(self);
+        }
+        let window = &mut self.cx.window;
+        let view_id = window.next_frame.view_stack.last().copied();
+        window
+            .next_frame
+            .accessibility_tree
+            .push_node(node, &window.element_id_stack, view_id);
+        let result = f(self);
+        self.window.next_frame.accessibility_tree.pop_node();
+        result
+    }
+
     /// Invoke the given function with the given content mask after intersecting it
     /// with the current mask.
     pub fn with_content_mask<R>(
@@ -962,6 +994,7 @@ impl<'a> ElementContext<'a> {
     }
 
     /// Paint a surface into the scene for the next frame at the current z-index.
+    #[cfg(target_os = "macos")]
     pub fn paint_surface(&mut self, bounds: Bounds<Pixels>, image_buffer: CVImageBuffer) {
         let scale_factor = self.scale_factor();
         let bounds = bounds.scale(scale_factor);
@@ -970,7 +1003,7 @@ impl<'a> ElementContext<'a> {
         let window = &mut *self.window;
         window.next_frame.scene.insert(
             &window.next_frame.z_index_stack,
-            Surface {
+            crate::Surface {
                 view_id: view_id.into(),
                 layer_id: 0,
                 order: 0,
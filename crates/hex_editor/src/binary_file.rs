@@ -0,0 +1,535 @@
+use anyhow::{anyhow, Result};
+use fs::Fs;
+use gpui::{AppContext, Model, ModelContext, Subscription, Task};
+use project::{
+    Event as ProjectEvent, PathChange, Project, ProjectEntryId, ProjectPath, UnsupportedContent,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Extensions of files that are opened in the hex editor without looking at their contents.
+const BINARY_EXTENSIONS: &[&str] = &[
+    "a", "bin", "class", "dat", "db", "dll", "dylib", "exe", "o", "obj", "pb", "so", "sqlite",
+    "sqlite3", "wasm",
+];
+
+/// How many bytes at the start of other files are checked to tell binary files from text.
+const SNIFF_LEN: usize = 8 * 1024;
+
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// How many pages are kept in memory, so that scrolling through a large file doesn't
+/// end up loading all of it.
+const MAX_CACHED_PAGES: usize = 64;
+
+const SEARCH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The raw contents of a file in the project, edited byte by byte. The file is read a
+/// page at a time as its bytes are shown, and edits are kept apart from the pages until
+/// they're saved.
+pub struct BinaryFile {
+    project_path: ProjectPath,
+    entry_id: Option<ProjectEntryId>,
+    abs_path: PathBuf,
+    fs: Arc<dyn Fs>,
+    len: usize,
+    pages: HashMap<usize, Vec<u8>>,
+    loading_pages: HashSet<usize>,
+    /// Bumped whenever the file on disk changes, so that pages read before are dropped.
+    generation: usize,
+    edits: BTreeMap<usize, u8>,
+    version: usize,
+    saved_version: usize,
+    has_conflict: bool,
+    _project_subscription: Subscription,
+}
+
+impl BinaryFile {
+    pub fn open(
+        project: &Model<Project>,
+        project_path: ProjectPath,
+        cx: &mut AppContext,
+    ) -> Task<Result<Model<Self>>> {
+        Self::open_internal(project, project_path, false, cx)
+    }
+
+    /// Opens the file at the given path, failing with [`UnsupportedContent`] if `sniff`
+    /// is set and the start of the file looks like text.
+    fn open_internal(
+        project: &Model<Project>,
+        project_path: ProjectPath,
+        sniff: bool,
+        cx: &mut AppContext,
+    ) -> Task<Result<Model<Self>>> {
+        let project_handle = project.read(cx);
+        if !project_handle.is_local() {
+            return Task::ready(Err(anyhow!("binary files can only be opened locally")));
+        }
+        let Some(abs_path) = project_handle.absolute_path(&project_path, cx) else {
+            return Task::ready(Err(anyhow!("no such path {project_path:?}")));
+        };
+        let entry_id = project_handle
+            .entry_for_path(&project_path, cx)
+            .map(|entry| entry.id);
+        let fs = project_handle.fs().clone();
+        let project = project.clone();
+        cx.spawn(|mut cx| async move {
+            // Most sniffed files are text, so they're turned away before anything else is read.
+            if sniff {
+                let head = fs.load_range(&abs_path, 0..SNIFF_LEN as u64).await?;
+                if !looks_binary(&head) {
+                    return Err(UnsupportedContent(abs_path).into());
+                }
+            }
+            let metadata = fs
+                .metadata(&abs_path)
+                .await?
+                .ok_or_else(|| anyhow!("no such file {abs_path:?}"))?;
+            if metadata.is_dir {
+                return Err(UnsupportedContent(abs_path).into());
+            }
+            cx.new_model(|cx| {
+                let subscription = cx.subscribe(&project, Self::handle_project_event);
+                Self {
+                    project_path,
+                    entry_id,
+                    abs_path,
+                    fs,
+                    len: metadata.len as usize,
+                    pages: HashMap::default(),
+                    loading_pages: HashSet::default(),
+                    generation: 0,
+                    edits: BTreeMap::default(),
+                    version: 0,
+                    saved_version: 0,
+                    has_conflict: false,
+                    _project_subscription: subscription,
+                }
+            })
+        })
+    }
+
+    fn handle_project_event(
+        &mut self,
+        _: Model<Project>,
+        event: &ProjectEvent,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let ProjectEvent::WorktreeUpdatedEntries(worktree_id, changes) = event else {
+            return;
+        };
+        if *worktree_id != self.project_path.worktree_id {
+            return;
+        }
+        for (path, entry_id, change) in changes.iter() {
+            if *path == self.project_path.path
+                && matches!(
+                    change,
+                    PathChange::Added | PathChange::Updated | PathChange::AddedOrUpdated
+                )
+            {
+                self.entry_id = Some(*entry_id);
+                if self.is_dirty() {
+                    self.has_conflict = true;
+                    cx.notify();
+                } else {
+                    self.reload(cx).detach_and_log_err(cx);
+                }
+            }
+        }
+    }
+
+    /// Returns the byte at the given offset, or `None` if it's past the end of the
+    /// file or hasn't been loaded yet.
+    pub fn byte(&self, offset: usize) -> Option<u8> {
+        if offset >= self.len {
+            return None;
+        }
+        self.edits.get(&offset).copied().or_else(|| {
+            self.pages
+                .get(&(offset / PAGE_SIZE))?
+                .get(offset % PAGE_SIZE)
+                .copied()
+        })
+    }
+
+    /// Starts loading the pages holding the given range of bytes, notifying once
+    /// each of them has been loaded.
+    pub fn load_range(&mut self, range: Range<usize>, cx: &mut ModelContext<Self>) {
+        let end = range.end.min(self.len);
+        if range.start >= end {
+            return;
+        }
+        for page in range.start / PAGE_SIZE..=(end - 1) / PAGE_SIZE {
+            if self.pages.contains_key(&page) || !self.loading_pages.insert(page) {
+                continue;
+            }
+            let fs = self.fs.clone();
+            let abs_path = self.abs_path.clone();
+            let generation = self.generation;
+            cx.spawn(|this, mut cx| async move {
+                let bytes = fs.load_range(&abs_path, page_range(page)).await;
+                this.update(&mut cx, |this, cx| {
+                    if this.generation != generation {
+                        return Ok(());
+                    }
+                    this.loading_pages.remove(&page);
+                    this.insert_page(page, bytes?);
+                    cx.notify();
+                    Ok(())
+                })?
+            })
+            .detach_and_log_err(cx);
+        }
+    }
+
+    fn insert_page(&mut self, page: usize, bytes: Vec<u8>) {
+        self.pages.insert(page, bytes);
+        // Drop the pages farthest from the one just loaded, as they're the least
+        // likely to be shown next.
+        while self.pages.len() > MAX_CACHED_PAGES {
+            let Some(farthest) = self
+                .pages
+                .keys()
+                .copied()
+                .max_by_key(|other| other.abs_diff(page))
+            else {
+                break;
+            };
+            self.pages.remove(&farthest);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn abs_path(&self) -> &PathBuf {
+        &self.abs_path
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.version != self.saved_version
+    }
+
+    pub fn has_conflict(&self) -> bool {
+        self.has_conflict
+    }
+
+    /// Overwrites the byte at the given offset. Bytes can't be inserted or removed, and
+    /// bytes that haven't been loaded yet are left alone.
+    pub fn set_byte(&mut self, offset: usize, value: u8, cx: &mut ModelContext<Self>) {
+        if self.byte(offset).map_or(false, |byte| byte != value) {
+            self.edits.insert(offset, value);
+            self.version += 1;
+            cx.notify();
+        }
+    }
+
+    /// Searches the file for `needle` a chunk at a time, returning where the match picked
+    /// by [`find_bytes`] starts.
+    pub fn search(
+        &self,
+        needle: Vec<u8>,
+        offset: usize,
+        reverse: bool,
+        cx: &AppContext,
+    ) -> Task<Result<Option<usize>>> {
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        let len = self.len;
+        let edits = self.edits.clone();
+        cx.background_executor().spawn(async move {
+            if needle.is_empty() {
+                return Ok(None);
+            }
+            let mut picker = MatchPicker::new(offset, reverse);
+            let mut chunk_start = 0;
+            while chunk_start < len {
+                // Overlap the chunks, so that matches spanning two of them are found.
+                let chunk_end = (chunk_start + SEARCH_CHUNK_SIZE + needle.len() - 1).min(len);
+                let mut chunk = fs
+                    .load_range(&abs_path, chunk_start as u64..chunk_end as u64)
+                    .await?;
+                for (&edit_offset, &byte) in edits.range(chunk_start..chunk_end) {
+                    if let Some(chunk_byte) = chunk.get_mut(edit_offset - chunk_start) {
+                        *chunk_byte = byte;
+                    }
+                }
+                for start in match_starts(&chunk, &needle) {
+                    if start >= SEARCH_CHUNK_SIZE {
+                        break;
+                    }
+                    if picker.push(chunk_start + start) {
+                        return Ok(picker.finish());
+                    }
+                }
+                chunk_start += SEARCH_CHUNK_SIZE;
+            }
+            Ok(picker.finish())
+        })
+    }
+
+    /// Writes the edited bytes into the file, replacing it atomically.
+    pub fn save(&mut self, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        let edits = self.edits.clone();
+        let version = self.version;
+        let mut patches: Vec<(u64, Vec<u8>)> = Vec::new();
+        for (&offset, &byte) in &edits {
+            match patches.last_mut() {
+                Some((start, bytes)) if *start as usize + bytes.len() == offset => bytes.push(byte),
+                _ => patches.push((offset as u64, vec![byte])),
+            }
+        }
+        cx.spawn(|this, mut cx| async move {
+            fs.patch_bytes(&abs_path, patches).await?;
+            this.update(&mut cx, |this, cx| {
+                for (offset, byte) in edits {
+                    if let Some(page_byte) = this
+                        .pages
+                        .get_mut(&(offset / PAGE_SIZE))
+                        .and_then(|page| page.get_mut(offset % PAGE_SIZE))
+                    {
+                        *page_byte = byte;
+                    }
+                    if this.edits.get(&offset) == Some(&byte) {
+                        this.edits.remove(&offset);
+                    }
+                }
+                this.loading_pages.clear();
+                this.generation += 1;
+                this.saved_version = version;
+                this.has_conflict = false;
+                cx.notify();
+            })
+        })
+    }
+
+    /// Discards the edits and reads the file again, along with the pages that were loaded.
+    pub fn reload(&mut self, cx: &mut ModelContext<Self>) -> Task<Result<()>> {
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        let loaded_pages = self.pages.keys().copied().collect::<Vec<_>>();
+        cx.spawn(|this, mut cx| async move {
+            let len = fs
+                .metadata(&abs_path)
+                .await?
+                .ok_or_else(|| anyhow!("no such file {abs_path:?}"))?
+                .len as usize;
+            let mut pages = HashMap::default();
+            for page in loaded_pages {
+                if page * PAGE_SIZE < len {
+                    pages.insert(page, fs.load_range(&abs_path, page_range(page)).await?);
+                }
+            }
+            this.update(&mut cx, |this, cx| {
+                this.len = len;
+                this.pages = pages;
+                this.loading_pages.clear();
+                this.generation += 1;
+                this.edits.clear();
+                this.version += 1;
+                this.saved_version = this.version;
+                this.has_conflict = false;
+                cx.notify();
+            })
+        })
+    }
+}
+
+fn page_range(page: usize) -> Range<u64> {
+    (page * PAGE_SIZE) as u64..((page + 1) * PAGE_SIZE) as u64
+}
+
+/// Whether the start of a file looks like binary data rather than text, because it
+/// holds a NUL byte or isn't valid UTF-8. A character cut off at the end is ignored.
+fn looks_binary(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => false,
+        Err(error) => error.error_len().is_some(),
+    }
+}
+
+fn has_binary_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            BINARY_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        })
+}
+
+impl project::Item for BinaryFile {
+    fn try_open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Option<Task<Result<Model<Self>>>> {
+        let project_handle = project.read(cx);
+        if !project_handle.is_local()
+            || project_handle
+                .entry_for_path(path, cx)
+                .map_or(false, |entry| entry.is_dir())
+        {
+            return None;
+        }
+        let sniff = !has_binary_extension(&path.path);
+        Some(Self::open_internal(project, path.clone(), sniff, cx))
+    }
+
+    fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
+        self.entry_id
+    }
+
+    fn project_path(&self, _: &AppContext) -> Option<ProjectPath> {
+        Some(self.project_path.clone())
+    }
+}
+
+/// Parses a search pattern, written either as hex bytes such as `de ad 0xbe ef`
+/// or as quoted text such as `"PNG"`.
+pub fn parse_byte_pattern(pattern: &str) -> Result<Vec<u8>> {
+    let pattern = pattern.trim();
+    if let Some(text) = pattern
+        .strip_prefix('"')
+        .and_then(|pattern| pattern.strip_suffix('"'))
+    {
+        return Ok(text.as_bytes().to_vec());
+    }
+
+    let mut digits = String::new();
+    for token in pattern.split_whitespace() {
+        let token = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        digits.push_str(token);
+    }
+    if let Some(invalid) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(anyhow!("invalid hex digit {invalid:?}"));
+    }
+    if digits.len() % 2 != 0 {
+        return Err(anyhow!("hex pattern has an odd number of digits"));
+    }
+    Ok((0..digits.len())
+        .step_by(2)
+        .map(|ix| u8::from_str_radix(&digits[ix..ix + 2], 16).unwrap())
+        .collect())
+}
+
+/// Finds the first occurrence of `needle` at or after `offset`, or the last one before it
+/// when searching in `reverse`, wrapping around the end of `haystack`.
+pub fn find_bytes(haystack: &[u8], needle: &[u8], offset: usize, reverse: bool) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    let mut picker = MatchPicker::new(offset, reverse);
+    for start in match_starts(haystack, needle) {
+        if picker.push(start) {
+            break;
+        }
+    }
+    picker.finish()
+}
+
+fn match_starts<'a>(haystack: &'a [u8], needle: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(move |(_, window)| *window == needle)
+        .map(|(ix, _)| ix)
+}
+
+/// Picks the match a search lands on, as matches are found from the start of the
+/// file to its end.
+struct MatchPicker {
+    offset: usize,
+    reverse: bool,
+    first: Option<usize>,
+    before_offset: Option<usize>,
+    after_offset: Option<usize>,
+}
+
+impl MatchPicker {
+    fn new(offset: usize, reverse: bool) -> Self {
+        Self {
+            offset,
+            reverse,
+            first: None,
+            before_offset: None,
+            after_offset: None,
+        }
+    }
+
+    /// Records the next match, returning whether the rest of the file can be skipped.
+    fn push(&mut self, start: usize) -> bool {
+        self.first.get_or_insert(start);
+        if start < self.offset {
+            self.before_offset = Some(start);
+            false
+        } else {
+            self.after_offset = Some(start);
+            !self.reverse
+        }
+    }
+
+    fn finish(self) -> Option<usize> {
+        if self.reverse {
+            self.before_offset.or(self.after_offset)
+        } else {
+            self.after_offset.or(self.first)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_byte_pattern() {
+        assert_eq!(
+            parse_byte_pattern("de ad 0xBE ef").unwrap(),
+            vec![0xde, 0xad, 0xbe, 0xef]
+        );
+        assert_eq!(parse_byte_pattern("0a0B").unwrap(), vec![0x0a, 0x0b]);
+        assert_eq!(parse_byte_pattern("\"PNG\"").unwrap(), b"PNG".to_vec());
+        assert!(parse_byte_pattern("abc").is_err());
+        assert!(parse_byte_pattern("zz").is_err());
+    }
+
+    #[test]
+    fn test_find_bytes() {
+        let haystack = b"abcabcab";
+        assert_eq!(find_bytes(haystack, b"bc", 0, false), Some(1));
+        assert_eq!(find_bytes(haystack, b"bc", 1, false), Some(1));
+        assert_eq!(find_bytes(haystack, b"bc", 2, false), Some(4));
+        assert_eq!(find_bytes(haystack, b"bc", 5, false), Some(1));
+        assert_eq!(find_bytes(haystack, b"bc", 4, true), Some(1));
+        assert_eq!(find_bytes(haystack, b"bc", 1, true), Some(4));
+        assert_eq!(find_bytes(haystack, b"ab", 7, false), Some(0));
+        assert_eq!(find_bytes(haystack, b"xy", 0, false), None);
+        assert_eq!(find_bytes(haystack, b"", 0, false), None);
+    }
+
+    #[test]
+    fn test_looks_binary() {
+        assert!(!looks_binary(b""));
+        assert!(!looks_binary("fn main() {}\n// résumé".as_bytes()));
+        assert!(!looks_binary(&"é".as_bytes()[..1]));
+        assert!(looks_binary(b"\x7fELF\x02\x01\x01\x00"));
+        assert!(looks_binary(b"\xff\xfe text"));
+    }
+}
@@ -0,0 +1,679 @@
+mod binary_file;
+
+use anyhow::{anyhow, Result};
+pub use binary_file::*;
+use editor::Editor;
+use gpui::{
+    actions, uniform_list, AnyElement, AppContext, EntityId, EventEmitter, FocusHandle,
+    FocusableView, KeyDownEvent, Model, MouseButton, Subscription, Task, UniformListScrollHandle,
+    View,
+};
+use project::Project;
+use settings::Settings;
+use std::{mem, ops::Range, path::PathBuf};
+use theme::ThemeSettings;
+use ui::{prelude::*, IconButton};
+use workspace::{
+    item::{Item, ItemEvent, ProjectItem},
+    OpenPriority, Workspace, WorkspaceId,
+};
+
+const BYTES_PER_ROW: usize = 16;
+
+actions!(
+    hex_editor,
+    [
+        OpenHexEditor,
+        MoveLeft,
+        MoveRight,
+        MoveUp,
+        MoveDown,
+        ToggleEndianness,
+        FindNext,
+        FindPrevious,
+        FocusSearch
+    ]
+);
+
+pub fn init(cx: &mut AppContext) {
+    workspace::register_project_item::<HexEditor>(OpenPriority::Content, cx);
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(HexEditor::open);
+    })
+    .detach();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+/// Shows a file as rows of offsets, hex bytes and ASCII, with an inspector that
+/// decodes the bytes under the cursor. Typing hex digits overwrites the byte under
+/// the cursor, one nibble at a time.
+pub struct HexEditor {
+    file: Model<BinaryFile>,
+    focus_handle: FocusHandle,
+    cursor: usize,
+    editing_low_nibble: bool,
+    endianness: Endianness,
+    search_editor: View<Editor>,
+    search_match: Option<Range<usize>>,
+    search_error: Option<SharedString>,
+    pending_search: Option<Task<()>>,
+    scroll_handle: UniformListScrollHandle,
+    _observe_file: Subscription,
+}
+
+impl HexEditor {
+    fn open(workspace: &mut Workspace, _: &OpenHexEditor, cx: &mut ViewContext<Workspace>) {
+        let Some(project_path) = workspace
+            .active_item(cx)
+            .and_then(|item| item.project_path(cx))
+        else {
+            cx.propagate();
+            return;
+        };
+        let open = BinaryFile::open(workspace.project(), project_path, cx);
+        cx.spawn(|workspace, mut cx| async move {
+            let file = open.await?;
+            workspace.update(&mut cx, |workspace, cx| {
+                let view = cx.new_view(|cx| Self::new(file, cx));
+                workspace.add_item(Box::new(view), cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn new(file: Model<BinaryFile>, cx: &mut ViewContext<Self>) -> Self {
+        let search_editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("Find bytes: de ad be ef or \"text\"", cx);
+            editor
+        });
+        let observe_file = cx.observe(&file, |this, file, cx| {
+            this.cursor = this.cursor.min(file.read(cx).len().saturating_sub(1));
+            cx.emit(ItemEvent::UpdateTab);
+            cx.notify();
+        });
+        Self {
+            file,
+            focus_handle: cx.focus_handle(),
+            cursor: 0,
+            editing_low_nibble: false,
+            endianness: Endianness::Little,
+            search_editor,
+            search_match: None,
+            search_error: None,
+            pending_search: None,
+            scroll_handle: UniformListScrollHandle::new(),
+            _observe_file: observe_file,
+        }
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    fn set_cursor(&mut self, offset: usize, cx: &mut ViewContext<Self>) {
+        let len = self.file.read(cx).len();
+        self.cursor = offset.min(len.saturating_sub(1));
+        self.editing_low_nibble = false;
+        self.scroll_handle
+            .scroll_to_item(self.cursor / BYTES_PER_ROW);
+        cx.notify();
+    }
+
+    fn move_by(&mut self, delta: isize, cx: &mut ViewContext<Self>) {
+        let Some(offset) = self.cursor.checked_add_signed(delta) else {
+            return;
+        };
+        if offset < self.file.read(cx).len() {
+            self.set_cursor(offset, cx);
+        }
+    }
+
+    fn move_left(&mut self, _: &MoveLeft, cx: &mut ViewContext<Self>) {
+        self.move_by(-1, cx);
+    }
+
+    fn move_right(&mut self, _: &MoveRight, cx: &mut ViewContext<Self>) {
+        self.move_by(1, cx);
+    }
+
+    fn move_up(&mut self, _: &MoveUp, cx: &mut ViewContext<Self>) {
+        self.move_by(-(BYTES_PER_ROW as isize), cx);
+    }
+
+    fn move_down(&mut self, _: &MoveDown, cx: &mut ViewContext<Self>) {
+        self.move_by(BYTES_PER_ROW as isize, cx);
+    }
+
+    fn toggle_endianness(&mut self, _: &ToggleEndianness, cx: &mut ViewContext<Self>) {
+        self.endianness = match self.endianness {
+            Endianness::Little => Endianness::Big,
+            Endianness::Big => Endianness::Little,
+        };
+        cx.notify();
+    }
+
+    fn focus_search(&mut self, _: &FocusSearch, cx: &mut ViewContext<Self>) {
+        cx.focus_view(&self.search_editor);
+    }
+
+    fn find_next(&mut self, _: &FindNext, cx: &mut ViewContext<Self>) {
+        self.find(false, cx);
+    }
+
+    fn find_previous(&mut self, _: &FindPrevious, cx: &mut ViewContext<Self>) {
+        self.find(true, cx);
+    }
+
+    fn find(&mut self, reverse: bool, cx: &mut ViewContext<Self>) {
+        let query = self.search_editor.read(cx).text(cx);
+        let pattern = match parse_byte_pattern(&query) {
+            Ok(pattern) => pattern,
+            Err(error) => {
+                self.search_match = None;
+                self.search_error = Some(error.to_string().into());
+                cx.notify();
+                return;
+            }
+        };
+
+        // Step past the current match, so that repeated searches visit every match.
+        let offset = match &self.search_match {
+            Some(search_match) if search_match.start == self.cursor && !reverse => self.cursor + 1,
+            _ => self.cursor,
+        };
+        let pattern_len = pattern.len();
+        let search = self.file.read(cx).search(pattern, offset, reverse, cx);
+        self.pending_search = Some(cx.spawn(|this, mut cx| async move {
+            let found = search.await;
+            this.update(&mut cx, |this, cx| {
+                this.pending_search = None;
+                match found {
+                    Ok(Some(start)) => {
+                        this.search_match = Some(start..start + pattern_len);
+                        this.search_error = None;
+                        this.set_cursor(start, cx);
+                    }
+                    Ok(None) => {
+                        this.search_match = None;
+                        this.search_error = Some("No matches".into());
+                        cx.notify();
+                    }
+                    Err(error) => {
+                        this.search_match = None;
+                        this.search_error = Some(error.to_string().into());
+                        cx.notify();
+                    }
+                }
+            })
+            .ok();
+        }));
+    }
+
+    fn handle_key_down(&mut self, event: &KeyDownEvent, cx: &mut ViewContext<Self>) {
+        let modifiers = &event.keystroke.modifiers;
+        if modifiers.command || modifiers.control || modifiers.alt || modifiers.function {
+            return;
+        }
+        let mut chars = event.keystroke.key.chars();
+        let (Some(char), None) = (chars.next(), chars.next()) else {
+            return;
+        };
+        let Some(nibble) = char.to_digit(16) else {
+            return;
+        };
+        self.write_nibble(nibble as u8, cx);
+        cx.stop_propagation();
+    }
+
+    fn write_nibble(&mut self, nibble: u8, cx: &mut ViewContext<Self>) {
+        let cursor = self.cursor;
+        let Some(byte) = self.file.read(cx).byte(cursor) else {
+            return;
+        };
+        let value = if self.editing_low_nibble {
+            byte & 0xf0 | nibble
+        } else {
+            nibble << 4 | byte & 0x0f
+        };
+        self.file
+            .update(cx, |file, cx| file.set_byte(cursor, value, cx));
+        if self.editing_low_nibble {
+            self.move_by(1, cx);
+            self.editing_low_nibble = false;
+        } else {
+            self.editing_low_nibble = true;
+        }
+        cx.notify();
+    }
+
+    fn render_row(&self, row: usize, cx: &ViewContext<Self>) -> impl IntoElement {
+        let colors = cx.theme().colors();
+        let file = self.file.read(cx);
+        let start = row * BYTES_PER_ROW;
+        let end = (start + BYTES_PER_ROW).min(file.len());
+        let bytes = (start..end).map(|offset| file.byte(offset));
+
+        let background = |offset: usize| {
+            if offset == self.cursor {
+                Some(colors.element_selected)
+            } else if self
+                .search_match
+                .as_ref()
+                .map_or(false, |search_match| search_match.contains(&offset))
+            {
+                Some(colors.search_match_background)
+            } else {
+                None
+            }
+        };
+
+        h_flex()
+            .gap_4()
+            .px_2()
+            .child(
+                div()
+                    .text_color(colors.text_muted)
+                    .child(format!("{start:08x}")),
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .children(bytes.clone().enumerate().map(|(ix, byte)| {
+                        let offset = start + ix;
+                        div()
+                            .id(offset)
+                            .when_some(background(offset), |this, color| this.bg(color))
+                            .child(
+                                byte.map_or_else(|| "··".to_string(), |byte| format!("{byte:02x}")),
+                            )
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |this, _, cx| {
+                                    this.set_cursor(offset, cx);
+                                    cx.focus(&this.focus_handle);
+                                }),
+                            )
+                    }))
+                    .children((end..start + BYTES_PER_ROW).map(|_| div().child("  "))),
+            )
+            .child(h_flex().children(bytes.enumerate().map(|(ix, byte)| {
+                let char = match byte {
+                    Some(byte) if byte.is_ascii_graphic() || byte == b' ' => byte as char,
+                    Some(_) => '.',
+                    None => '·',
+                };
+                div()
+                    .when_some(background(start + ix), |this, color| this.bg(color))
+                    .child(char.to_string())
+            })))
+    }
+
+    fn render_search_bar(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let file = self.file.read(cx);
+        h_flex()
+            .px_2()
+            .py_1()
+            .gap_2()
+            .border_b_1()
+            .border_color(cx.theme().colors().border)
+            .child(div().flex_1().child(self.search_editor.clone()))
+            .child(
+                IconButton::new("find-previous", IconName::ChevronLeft)
+                    .on_click(cx.listener(|this, _, cx| this.find(true, cx))),
+            )
+            .child(
+                IconButton::new("find-next", IconName::ChevronRight)
+                    .on_click(cx.listener(|this, _, cx| this.find(false, cx))),
+            )
+            .when_some(self.search_error.clone(), |this, error| {
+                this.child(Label::new(error).size(LabelSize::Small).color(Color::Error))
+            })
+            .when(file.has_conflict(), |this| {
+                this.child(
+                    Label::new("The file has changed on disk")
+                        .size(LabelSize::Small)
+                        .color(Color::Warning),
+                )
+            })
+    }
+
+    fn render_inspector(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let file = self.file.read(cx);
+        let bytes = (self.cursor..file.len())
+            .map_while(|offset| file.byte(offset))
+            .take(mem::size_of::<u64>())
+            .collect::<Vec<_>>();
+        let values = inspect_values(&bytes, self.endianness);
+        v_flex()
+            .w(px(240.))
+            .h_full()
+            .p_2()
+            .gap_1()
+            .border_l_1()
+            .border_color(cx.theme().colors().border)
+            .child(
+                h_flex()
+                    .justify_between()
+                    .child(Label::new(format!("Offset {:#x}", self.cursor)))
+                    .child(
+                        Button::new(
+                            "endianness",
+                            match self.endianness {
+                                Endianness::Little => "Little endian",
+                                Endianness::Big => "Big endian",
+                            },
+                        )
+                        .on_click(
+                            cx.listener(|this, _, cx| {
+                                this.toggle_endianness(&ToggleEndianness, cx)
+                            }),
+                        ),
+                    ),
+            )
+            .children(values.into_iter().map(|(name, value)| {
+                h_flex()
+                    .justify_between()
+                    .child(Label::new(name).color(Color::Muted))
+                    .child(Label::new(value.unwrap_or_else(|| "—".to_string())))
+            }))
+    }
+}
+
+/// Decodes the bytes at the start of `bytes` as each integer and float type, or `None`
+/// for types that need more bytes than are left.
+pub fn inspect_values(bytes: &[u8], endianness: Endianness) -> Vec<(&'static str, Option<String>)> {
+    macro_rules! decode {
+        ($ty:ty) => {
+            bytes
+                .get(..std::mem::size_of::<$ty>())
+                .and_then(|bytes| bytes.try_into().ok())
+                .map(|bytes| {
+                    match endianness {
+                        Endianness::Little => <$ty>::from_le_bytes(bytes),
+                        Endianness::Big => <$ty>::from_be_bytes(bytes),
+                    }
+                    .to_string()
+                })
+        };
+    }
+
+    vec![
+        ("u8", decode!(u8)),
+        ("i8", decode!(i8)),
+        ("u16", decode!(u16)),
+        ("i16", decode!(i16)),
+        ("u32", decode!(u32)),
+        ("i32", decode!(i32)),
+        ("u64", decode!(u64)),
+        ("i64", decode!(i64)),
+        ("f32", decode!(f32)),
+        ("f64", decode!(f64)),
+    ]
+}
+
+impl Render for HexEditor {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let settings = ThemeSettings::get_global(cx);
+        let font_family = settings.buffer_font.family.clone();
+        let font_size = settings.buffer_font_size(cx);
+        let row_count = (self.file.read(cx).len() + BYTES_PER_ROW - 1) / BYTES_PER_ROW;
+        let cursor = self.cursor;
+        self.file.update(cx, |file, cx| {
+            file.load_range(cursor..cursor + mem::size_of::<u64>(), cx)
+        });
+        v_flex()
+            .key_context("HexEditor")
+            .size_full()
+            .bg(cx.theme().colors().editor_background)
+            .on_action(cx.listener(Self::toggle_endianness))
+            .on_action(cx.listener(Self::focus_search))
+            .on_action(cx.listener(Self::find_next))
+            .on_action(cx.listener(Self::find_previous))
+            .child(self.render_search_bar(cx))
+            .child(
+                h_flex()
+                    .flex_1()
+                    .w_full()
+                    .child(
+                        div()
+                            .id("hex-editor-bytes")
+                            .track_focus(&self.focus_handle)
+                            .on_key_down(cx.listener(Self::handle_key_down))
+                            .on_action(cx.listener(Self::move_left))
+                            .on_action(cx.listener(Self::move_right))
+                            .on_action(cx.listener(Self::move_up))
+                            .on_action(cx.listener(Self::move_down))
+                            .flex_1()
+                            .h_full()
+                            .font(font_family)
+                            .text_size(font_size)
+                            .child(
+                                uniform_list(
+                                    cx.view().clone(),
+                                    "hex-editor-rows",
+                                    row_count,
+                                    |this, range, cx| {
+                                        let bytes =
+                                            range.start * BYTES_PER_ROW..range.end * BYTES_PER_ROW;
+                                        this.file.update(cx, |file, cx| file.load_range(bytes, cx));
+                                        range.map(|row| this.render_row(row, cx)).collect()
+                                    },
+                                )
+                                .size_full()
+                                .track_scroll(self.scroll_handle.clone()),
+                            ),
+                    )
+                    .child(self.render_inspector(cx)),
+            )
+    }
+}
+
+impl FocusableView for HexEditor {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl EventEmitter<ItemEvent> for HexEditor {}
+
+impl Item for HexEditor {
+    type Event = ItemEvent;
+
+    fn tab_content(&self, _: Option<usize>, selected: bool, cx: &WindowContext) -> AnyElement {
+        let title = self
+            .file
+            .read(cx)
+            .abs_path()
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+        Label::new(title)
+            .color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            })
+            .into_any_element()
+    }
+
+    fn tab_tooltip_text(&self, cx: &AppContext) -> Option<SharedString> {
+        let abs_path = self.file.read(cx).abs_path();
+        Some(abs_path.to_string_lossy().to_string().into())
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("hex editor")
+    }
+
+    fn to_item_events(event: &Self::Event, mut f: impl FnMut(ItemEvent)) {
+        f(*event)
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(EntityId, &dyn project::Item),
+    ) {
+        f(self.file.entity_id(), self.file.read(cx))
+    }
+
+    fn is_singleton(&self, _: &AppContext) -> bool {
+        true
+    }
+
+    fn clone_on_split(&self, _: WorkspaceId, cx: &mut ViewContext<Self>) -> Option<View<Self>> {
+        let file = self.file.clone();
+        Some(cx.new_view(|cx| Self::new(file, cx)))
+    }
+
+    fn is_dirty(&self, cx: &AppContext) -> bool {
+        self.file.read(cx).is_dirty()
+    }
+
+    fn has_conflict(&self, cx: &AppContext) -> bool {
+        self.file.read(cx).has_conflict()
+    }
+
+    fn can_save(&self, _: &AppContext) -> bool {
+        true
+    }
+
+    fn save(&mut self, _: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.file.update(cx, |file, cx| file.save(cx))
+    }
+
+    fn save_as(
+        &mut self,
+        _: Model<Project>,
+        abs_path: PathBuf,
+        _: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        Task::ready(Err(anyhow!(
+            "binary files can't be saved to a new path ({abs_path:?})"
+        )))
+    }
+
+    fn reload(&mut self, _: Model<Project>, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        self.file.update(cx, |file, cx| file.reload(cx))
+    }
+}
+
+impl ProjectItem for HexEditor {
+    type Item = BinaryFile;
+
+    fn for_project_item(
+        _: Model<Project>,
+        file: Model<Self::Item>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        Self::new(file, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::{KeyBinding, TestAppContext};
+    use serde_json::json;
+    use std::{path::Path, sync::Arc};
+    use workspace::AppState;
+
+    #[test]
+    fn test_inspect_values() {
+        let bytes = [0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f];
+        let values = inspect_values(&bytes, Endianness::Little);
+        let value = |name: &str| {
+            values
+                .iter()
+                .find(|(value_name, _)| *value_name == name)
+                .unwrap()
+                .1
+                .clone()
+        };
+        assert_eq!(value("u8").as_deref(), Some("1"));
+        assert_eq!(value("u16").as_deref(), Some("513"));
+        assert_eq!(value("f64").as_deref(), Some("1.000000000000114"));
+
+        let values = inspect_values(&bytes[..2], Endianness::Big);
+        assert_eq!(values[2], ("u16", Some("258".to_string())));
+        assert_eq!(values[4], ("u32", None));
+    }
+
+    #[gpui::test]
+    async fn test_edit_search_and_save(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let fs = app_state.fs.as_fake();
+        fs.insert_tree("/root", json!({ "notes": "not binary\n" }))
+            .await;
+        fs.insert_file_bytes("/root/data", vec![0x00, 0x01, 0xde, 0xad, 0xbe, 0xef, 0xff])
+            .await;
+        let project = Project::test(app_state.fs.clone(), ["/root".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+
+        let item = workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_path((worktree_id, "notes"), None, true, cx)
+            })
+            .await
+            .unwrap();
+        assert!(item.downcast::<Editor>().is_some());
+
+        let item = workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_path((worktree_id, "data"), None, true, cx)
+            })
+            .await
+            .unwrap();
+        let hex_editor = item.downcast::<HexEditor>().unwrap();
+        cx.run_until_parked();
+
+        cx.simulate_keystrokes("right right 4 2");
+        hex_editor.update(cx, |hex_editor, cx| {
+            assert_eq!(hex_editor.cursor(), 3);
+            assert_eq!(hex_editor.file.read(cx).byte(2), Some(0x42));
+            assert!(hex_editor.is_dirty(cx));
+
+            hex_editor.search_editor.update(cx, |editor, cx| {
+                editor.set_text("be 0xEF", cx);
+            });
+            hex_editor.find(false, cx);
+        });
+        cx.run_until_parked();
+        hex_editor.update(cx, |hex_editor, _| {
+            assert_eq!(hex_editor.cursor(), 4);
+            assert_eq!(hex_editor.search_match, Some(4..6));
+        });
+
+        hex_editor
+            .update(cx, |hex_editor, cx| hex_editor.save(project.clone(), cx))
+            .await
+            .unwrap();
+        assert_eq!(
+            fs.load_bytes(Path::new("/root/data")).await.unwrap(),
+            vec![0x00, 0x01, 0x42, 0xad, 0xbe, 0xef, 0xff]
+        );
+        hex_editor.update(cx, |hex_editor, cx| assert!(!hex_editor.is_dirty(cx)));
+    }
+
+    fn init_test(cx: &mut TestAppContext) -> Arc<AppState> {
+        cx.update(|cx| {
+            let state = AppState::test(cx);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            editor::init(cx);
+            workspace::init_settings(cx);
+            Project::init_settings(cx);
+            super::init(cx);
+            cx.bind_keys([KeyBinding::new("right", MoveRight, Some("HexEditor"))]);
+            state
+        })
+    }
+}
@@ -0,0 +1,741 @@
+//! Opens image files from the project in a workspace item that can be zoomed
+//! and panned, in place of the text editor.
+
+use anyhow::{anyhow, Result};
+use fs::Fs;
+use gpui::{
+    actions, canvas, fill, hsla, point, size, AnyElement, AppContext, BackgroundExecutor, Bounds,
+    ContentMask, Corners, EntityId, EventEmitter, FocusHandle, FocusableView, ImageData, Model,
+    ModelContext, MouseButton, MouseDownEvent, MouseMoveEvent, Point, ScrollWheelEvent, Size,
+    Subscription, Task, View,
+};
+use image::{
+    codecs::gif::GifDecoder, AnimationDecoder, Bgra, DynamicImage, ImageBuffer, ImageFormat,
+};
+use project::{Event as ProjectEvent, PathChange, Project, ProjectEntryId, ProjectPath};
+use std::{
+    cell::Cell,
+    io::{Cursor, Read},
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+use ui::prelude::*;
+use util::ResultExt;
+use workspace::{
+    item::{Item, ItemEvent, ProjectItem},
+    OpenPriority, WorkspaceId,
+};
+
+actions!(image_viewer, [ZoomIn, ZoomOut, ZoomToFit, ActualSize]);
+
+const IMAGE_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "tif", "tiff", "webp", "svg",
+];
+const ZOOM_STEP: f32 = 1.25;
+const MIN_SCALE: f32 = 0.01;
+const MAX_SCALE: f32 = 64.;
+const CHECKER_SIZE: Pixels = px(8.);
+/// Browsers play GIF frames with tiny delays at this rate instead, and so do we.
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+pub fn init(cx: &mut AppContext) {
+    workspace::register_project_item::<ImageView>(OpenPriority::FileType, cx);
+}
+
+/// An image file decoded into frames that can be painted.
+pub struct DecodedImage {
+    pub format: SharedString,
+    pub width: u32,
+    pub height: u32,
+    pub file_size: u64,
+    pub frames: Vec<ImageFrame>,
+}
+
+pub struct ImageFrame {
+    pub data: Arc<ImageData>,
+    pub delay: Duration,
+}
+
+impl DecodedImage {
+    pub fn decode(bytes: &[u8], extension: &str) -> Result<Self> {
+        if extension.eq_ignore_ascii_case("svg") {
+            return Self::decode_svg(bytes);
+        }
+
+        let format = image::guess_format(bytes)?;
+        let frames = if format == ImageFormat::Gif {
+            GifDecoder::new(Cursor::new(bytes))?
+                .into_frames()
+                .collect_frames()?
+                .into_iter()
+                .map(|frame| {
+                    let (numer, denom) = frame.delay().numer_denom_ms();
+                    let delay = Duration::from_millis((numer / denom.max(1)) as u64);
+                    ImageFrame {
+                        data: Arc::new(ImageData::new(
+                            DynamicImage::ImageRgba8(frame.into_buffer()).into_bgra8(),
+                        )),
+                        delay: if delay <= Duration::from_millis(10) {
+                            DEFAULT_FRAME_DELAY
+                        } else {
+                            delay
+                        },
+                    }
+                })
+                .collect::<Vec<_>>()
+        } else {
+            let image = image::load_from_memory_with_format(bytes, format)?;
+            vec![ImageFrame {
+                data: Arc::new(ImageData::new(image.into_bgra8())),
+                delay: Duration::ZERO,
+            }]
+        };
+
+        let first_frame = frames
+            .first()
+            .ok_or_else(|| anyhow!("image has no frames"))?;
+        let frame_size = first_frame.data.size();
+        Ok(Self {
+            format: format!("{format:?}").to_uppercase().into(),
+            width: frame_size.width.into(),
+            height: frame_size.height.into(),
+            file_size: bytes.len() as u64,
+            frames,
+        })
+    }
+
+    fn decode_svg(bytes: &[u8]) -> Result<Self> {
+        let tree = usvg::Tree::from_data(bytes, &usvg::Options::default())?;
+        let svg_size = tree.svg_node().size;
+        let width = svg_size.width().ceil() as u32;
+        let height = svg_size.height().ceil() as u32;
+        let mut pixmap = tiny_skia::Pixmap::new(width, height)
+            .ok_or_else(|| anyhow!("invalid svg size {width}x{height}"))?;
+        resvg::render(&tree, usvg::FitTo::Original, pixmap.as_mut());
+
+        let mut buffer = ImageBuffer::<Bgra<u8>, Vec<u8>>::new(width, height);
+        for (target, pixel) in buffer.pixels_mut().zip(pixmap.pixels()) {
+            let color = pixel.demultiply();
+            *target = Bgra([color.blue(), color.green(), color.red(), color.alpha()]);
+        }
+
+        Ok(Self {
+            format: "SVG".into(),
+            width,
+            height,
+            file_size: bytes.len() as u64,
+            frames: vec![ImageFrame {
+                data: Arc::new(ImageData::new(buffer)),
+                delay: Duration::ZERO,
+            }],
+        })
+    }
+}
+
+/// An image file in the project, reloaded whenever it changes on disk.
+pub struct ImageItem {
+    project_path: ProjectPath,
+    entry_id: Option<ProjectEntryId>,
+    abs_path: PathBuf,
+    fs: Arc<dyn Fs>,
+    /// Images are read from the local file system, so those in remote
+    /// projects aren't loaded.
+    is_local: bool,
+    image: Result<Arc<DecodedImage>, SharedString>,
+    reload_task: Option<Task<()>>,
+    _project_subscription: Subscription,
+}
+
+impl ImageItem {
+    fn new(
+        project: &Model<Project>,
+        project_path: ProjectPath,
+        entry_id: Option<ProjectEntryId>,
+        abs_path: PathBuf,
+        image: Result<Arc<DecodedImage>, SharedString>,
+        cx: &mut ModelContext<Self>,
+    ) -> Self {
+        let subscription = cx.subscribe(project, |this, _, event: &ProjectEvent, cx| {
+            let ProjectEvent::WorktreeUpdatedEntries(worktree_id, changes) = event else {
+                return;
+            };
+            if *worktree_id != this.project_path.worktree_id {
+                return;
+            }
+            for (path, entry_id, change) in changes.iter() {
+                if *path == this.project_path.path
+                    && matches!(
+                        change,
+                        PathChange::Added | PathChange::Updated | PathChange::AddedOrUpdated
+                    )
+                {
+                    this.entry_id = Some(*entry_id);
+                    this.reload(cx);
+                }
+            }
+        });
+
+        let project = project.read(cx);
+        Self {
+            fs: project.fs().clone(),
+            is_local: project.is_local(),
+            project_path,
+            entry_id,
+            abs_path,
+            image,
+            reload_task: None,
+            _project_subscription: subscription,
+        }
+    }
+
+    pub fn image(&self) -> Result<&Arc<DecodedImage>, &SharedString> {
+        self.image.as_ref()
+    }
+
+    fn reload(&mut self, cx: &mut ModelContext<Self>) {
+        if !self.is_local {
+            return;
+        }
+        let load = load_image(
+            self.fs.clone(),
+            self.abs_path.clone(),
+            cx.background_executor(),
+        );
+        self.reload_task = Some(cx.spawn(|this, mut cx| async move {
+            let image = load.await;
+            this.update(&mut cx, |this, cx| {
+                this.image = image;
+                cx.notify();
+            })
+            .ok();
+        }));
+    }
+}
+
+fn load_image(
+    fs: Arc<dyn Fs>,
+    abs_path: PathBuf,
+    executor: &BackgroundExecutor,
+) -> Task<Result<Arc<DecodedImage>, SharedString>> {
+    executor.spawn(async move {
+        let load = async {
+            let mut bytes = Vec::new();
+            fs.open_sync(&abs_path).await?.read_to_end(&mut bytes)?;
+            let extension = abs_path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default();
+            DecodedImage::decode(&bytes, extension)
+        };
+        load.await
+            .map(Arc::new)
+            .map_err(|error| format!("Could not open {abs_path:?}: {error}").into())
+    })
+}
+
+impl project::Item for ImageItem {
+    fn try_open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Option<Task<Result<Model<Self>>>> {
+        let extension = path.path.extension()?.to_str()?.to_lowercase();
+        if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            return None;
+        }
+        let project_handle = project.read(cx);
+        let abs_path = project_handle.absolute_path(path, cx)?;
+        let entry_id = project_handle
+            .entry_for_path(path, cx)
+            .map(|entry| entry.id);
+        let load = if project_handle.is_local() {
+            load_image(
+                project_handle.fs().clone(),
+                abs_path.clone(),
+                cx.background_executor(),
+            )
+        } else {
+            // Opening the image as text wouldn't show anything useful either,
+            // so we say why it isn't shown instead.
+            Task::ready(Err("Images in remote projects can't be shown yet".into()))
+        };
+        let project = project.clone();
+        let path = path.clone();
+        Some(cx.spawn(|mut cx| async move {
+            let image = load.await;
+            cx.new_model(|cx| ImageItem::new(&project, path, entry_id, abs_path, image, cx))
+        }))
+    }
+
+    fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
+        self.entry_id
+    }
+
+    fn project_path(&self, _: &AppContext) -> Option<ProjectPath> {
+        Some(self.project_path.clone())
+    }
+}
+
+pub struct ImageView {
+    item: Model<ImageItem>,
+    focus_handle: FocusHandle,
+    /// `None` fits the image into the view, without enlarging it.
+    scale: Option<f32>,
+    /// Offset of the image center from the center of the view.
+    pan: Point<Pixels>,
+    drag_position: Option<Point<Pixels>>,
+    bounds: Rc<Cell<Bounds<Pixels>>>,
+    frame_ix: usize,
+    animation: Option<Task<Result<()>>>,
+    _observe_item: Subscription,
+}
+
+impl ImageView {
+    pub fn new(item: Model<ImageItem>, cx: &mut ViewContext<Self>) -> Self {
+        let observe_item = cx.observe(&item, |this, _, cx| {
+            this.start_animation(cx);
+            cx.notify();
+        });
+        let mut this = Self {
+            item,
+            focus_handle: cx.focus_handle(),
+            scale: None,
+            pan: Point::default(),
+            drag_position: None,
+            bounds: Rc::default(),
+            frame_ix: 0,
+            animation: None,
+            _observe_item: observe_item,
+        };
+        this.start_animation(cx);
+        this
+    }
+
+    fn start_animation(&mut self, cx: &mut ViewContext<Self>) {
+        self.frame_ix = 0;
+        self.animation = None;
+        let Ok(image) = self.item.read(cx).image.clone() else {
+            return;
+        };
+        if image.frames.len() < 2 {
+            return;
+        }
+        self.animation = Some(cx.spawn(|this, mut cx| async move {
+            loop {
+                let delay = this.update(&mut cx, |this, _| image.frames[this.frame_ix].delay)?;
+                cx.background_executor().timer(delay).await;
+                this.update(&mut cx, |this, cx| {
+                    this.frame_ix = (this.frame_ix + 1) % image.frames.len();
+                    cx.notify();
+                })?;
+            }
+        }));
+    }
+
+    fn image_size(&self, cx: &AppContext) -> Option<Size<Pixels>> {
+        let image = self.item.read(cx).image.as_ref().ok()?;
+        Some(size(px(image.width as f32), px(image.height as f32)))
+    }
+
+    fn current_scale(&self, cx: &AppContext) -> f32 {
+        self.scale.unwrap_or_else(|| {
+            self.image_size(cx).map_or(1., |image_size| {
+                fit_scale(image_size, self.bounds.get().size)
+            })
+        })
+    }
+
+    /// Zooms by the given factor, keeping the image point under `anchor` in place.
+    fn zoom(&mut self, factor: f32, anchor: Point<Pixels>, cx: &mut ViewContext<Self>) {
+        let old_scale = self.current_scale(cx);
+        let new_scale = (old_scale * factor).clamp(MIN_SCALE, MAX_SCALE);
+        let anchor = anchor - self.bounds.get().center();
+        self.pan = anchor - (anchor - self.pan) * (new_scale / old_scale);
+        self.scale = Some(new_scale);
+        cx.notify();
+    }
+
+    fn zoom_in(&mut self, _: &ZoomIn, cx: &mut ViewContext<Self>) {
+        self.zoom(ZOOM_STEP, self.bounds.get().center(), cx);
+    }
+
+    fn zoom_out(&mut self, _: &ZoomOut, cx: &mut ViewContext<Self>) {
+        self.zoom(1. / ZOOM_STEP, self.bounds.get().center(), cx);
+    }
+
+    fn zoom_to_fit(&mut self, _: &ZoomToFit, cx: &mut ViewContext<Self>) {
+        self.scale = None;
+        self.pan = Point::default();
+        cx.notify();
+    }
+
+    fn actual_size(&mut self, _: &ActualSize, cx: &mut ViewContext<Self>) {
+        self.scale = Some(1.);
+        self.pan = Point::default();
+        cx.notify();
+    }
+
+    fn scroll(&mut self, event: &ScrollWheelEvent, cx: &mut ViewContext<Self>) {
+        let line_height = cx.line_height();
+        let delta = event.delta.pixel_delta(line_height);
+        if event.modifiers.command || event.modifiers.control {
+            self.zoom(ZOOM_STEP.powf(delta.y / line_height), event.position, cx);
+        } else {
+            self.pan = self.pan + delta;
+            cx.notify();
+        }
+    }
+
+    fn render_image(&self, image: &DecodedImage, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let data = image.frames[self.frame_ix.min(image.frames.len() - 1)]
+            .data
+            .clone();
+        let image_size = size(px(image.width as f32), px(image.height as f32));
+        let scale = self.scale;
+        let pan = self.pan;
+        let view_bounds = self.bounds.clone();
+
+        div()
+            .id("image")
+            .flex_1()
+            .size_full()
+            .overflow_hidden()
+            .on_scroll_wheel(cx.listener(Self::scroll))
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|this, event: &MouseDownEvent, cx| {
+                    this.drag_position = Some(event.position);
+                    cx.focus(&this.focus_handle);
+                }),
+            )
+            .on_mouse_up(
+                MouseButton::Left,
+                cx.listener(|this, _, _| this.drag_position = None),
+            )
+            .on_mouse_move(cx.listener(|this, event: &MouseMoveEvent, cx| {
+                let Some(drag_position) = this.drag_position else {
+                    return;
+                };
+                if event.pressed_button != Some(MouseButton::Left) {
+                    this.drag_position = None;
+                    return;
+                }
+                this.pan = this.pan + (event.position - drag_position);
+                this.drag_position = Some(event.position);
+                cx.notify();
+            }))
+            .child(
+                canvas(move |bounds, cx| {
+                    view_bounds.set(*bounds);
+                    let scale = scale.unwrap_or_else(|| fit_scale(image_size, bounds.size));
+                    let scaled_size = size(image_size.width * scale, image_size.height * scale);
+                    let image_bounds = Bounds {
+                        origin: bounds.center() + pan
+                            - point(scaled_size.width / 2., scaled_size.height / 2.),
+                        size: scaled_size,
+                    };
+                    let visible_bounds = image_bounds.intersect(bounds);
+                    if visible_bounds.size.width <= px(0.) || visible_bounds.size.height <= px(0.) {
+                        return;
+                    }
+
+                    cx.with_content_mask(Some(ContentMask { bounds: *bounds }), |cx| {
+                        paint_checkerboard(image_bounds, visible_bounds, cx);
+                        cx.with_z_index(1, |cx| {
+                            cx.paint_image(image_bounds, Corners::default(), data, false)
+                                .log_err();
+                        });
+                    });
+                })
+                .size_full(),
+            )
+    }
+
+    fn render_footer(&self, image: &DecodedImage, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let zoom = (self.current_scale(cx) * 100.).round();
+        h_flex()
+            .w_full()
+            .px_2()
+            .py_1()
+            .gap_3()
+            .border_t_1()
+            .border_color(cx.theme().colors().border)
+            .bg(cx.theme().colors().status_bar_background)
+            .child(
+                Label::new(image.format.clone())
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(
+                Label::new(format!("{} × {}", image.width, image.height))
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(
+                Label::new(human_bytes::human_bytes(image.file_size as f64))
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+            .child(
+                Label::new(format!("{zoom}%"))
+                    .size(LabelSize::Small)
+                    .color(Color::Muted),
+            )
+    }
+}
+
+/// Paints a checkerboard beneath the visible part of an image, so that its
+/// transparent areas can be told apart from the background.
+fn paint_checkerboard(
+    image_bounds: Bounds<Pixels>,
+    visible_bounds: Bounds<Pixels>,
+    cx: &mut ElementContext,
+) {
+    cx.paint_quad(fill(visible_bounds, hsla(0., 0., 0.8, 1.)));
+    let first_column = ((visible_bounds.left() - image_bounds.left()) / CHECKER_SIZE) as i32;
+    let first_row = ((visible_bounds.top() - image_bounds.top()) / CHECKER_SIZE) as i32;
+    let last_column = ((visible_bounds.right() - image_bounds.left()) / CHECKER_SIZE).ceil() as i32;
+    let last_row = ((visible_bounds.bottom() - image_bounds.top()) / CHECKER_SIZE).ceil() as i32;
+    cx.with_content_mask(
+        Some(ContentMask {
+            bounds: visible_bounds,
+        }),
+        |cx| {
+            for row in first_row..last_row {
+                for column in first_column..last_column {
+                    if (row + column) % 2 == 0 {
+                        continue;
+                    }
+                    let origin = image_bounds.origin
+                        + point(CHECKER_SIZE * column as f32, CHECKER_SIZE * row as f32);
+                    cx.paint_quad(fill(
+                        Bounds::new(origin, size(CHECKER_SIZE, CHECKER_SIZE)),
+                        hsla(0., 0., 0.6, 1.),
+                    ));
+                }
+            }
+        },
+    );
+}
+
+/// The scale at which an image fits into the given size, capped at its actual size.
+fn fit_scale(image_size: Size<Pixels>, view_size: Size<Pixels>) -> f32 {
+    if image_size.width <= px(0.)
+        || image_size.height <= px(0.)
+        || view_size.width <= px(0.)
+        || view_size.height <= px(0.)
+    {
+        return 1.;
+    }
+    (view_size.width / image_size.width)
+        .min(view_size.height / image_size.height)
+        .min(1.)
+}
+
+impl Render for ImageView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let image = self.item.read(cx).image.clone();
+        let container = v_flex()
+            .key_context("ImageViewer")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::zoom_in))
+            .on_action(cx.listener(Self::zoom_out))
+            .on_action(cx.listener(Self::zoom_to_fit))
+            .on_action(cx.listener(Self::actual_size))
+            .size_full()
+            .bg(cx.theme().colors().editor_background);
+
+        match image {
+            Ok(image) => container
+                .child(self.render_image(&image, cx))
+                .child(self.render_footer(&image, cx)),
+            Err(error) => container
+                .items_center()
+                .justify_center()
+                .child(Label::new(error).color(Color::Error)),
+        }
+    }
+}
+
+impl FocusableView for ImageView {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl EventEmitter<()> for ImageView {}
+
+impl Item for ImageView {
+    type Event = ();
+
+    fn tab_content(&self, _: Option<usize>, selected: bool, cx: &WindowContext) -> AnyElement {
+        let title = self
+            .item
+            .read(cx)
+            .project_path
+            .path
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+        Label::new(title)
+            .color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            })
+            .into_any_element()
+    }
+
+    fn tab_tooltip_text(&self, cx: &AppContext) -> Option<SharedString> {
+        Some(
+            self.item
+                .read(cx)
+                .abs_path
+                .to_string_lossy()
+                .to_string()
+                .into(),
+        )
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("image viewer")
+    }
+
+    fn to_item_events(_: &Self::Event, _: impl FnMut(ItemEvent)) {}
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(EntityId, &dyn project::Item),
+    ) {
+        f(self.item.entity_id(), self.item.read(cx))
+    }
+
+    fn is_singleton(&self, _: &AppContext) -> bool {
+        true
+    }
+
+    fn clone_on_split(&self, _: WorkspaceId, cx: &mut ViewContext<Self>) -> Option<View<Self>> {
+        let item = self.item.clone();
+        let scale = self.scale;
+        let pan = self.pan;
+        Some(cx.new_view(|cx| {
+            let mut view = ImageView::new(item, cx);
+            view.scale = scale;
+            view.pan = pan;
+            view
+        }))
+    }
+}
+
+impl ProjectItem for ImageView {
+    type Item = ImageItem;
+
+    fn for_project_item(
+        _: Model<Project>,
+        item: Model<Self::Item>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        Self::new(item, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+    use image::{ImageOutputFormat, Rgba, RgbaImage};
+    use serde_json::json;
+    use workspace::{AppState, Workspace};
+
+    const SVG: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="3">
+        <rect width="4" height="3" fill="#ff0000"/>
+    </svg>"##;
+
+    #[test]
+    fn test_decode_png() {
+        let mut image = RgbaImage::new(3, 2);
+        image.put_pixel(1, 0, Rgba([10, 20, 30, 255]));
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgba8(image)
+            .write_to(&mut bytes, ImageOutputFormat::Png)
+            .unwrap();
+
+        let decoded = DecodedImage::decode(&bytes, "png").unwrap();
+        assert_eq!(decoded.format.as_ref(), "PNG");
+        assert_eq!((decoded.width, decoded.height), (3, 2));
+        assert_eq!(decoded.file_size, bytes.len() as u64);
+        assert_eq!(decoded.frames.len(), 1);
+        assert_eq!(&decoded.frames[0].data.as_bytes()[4..8], &[30, 20, 10, 255]);
+
+        assert!(DecodedImage::decode(b"not an image", "png").is_err());
+    }
+
+    #[test]
+    fn test_decode_svg() {
+        let decoded = DecodedImage::decode(SVG.as_bytes(), "svg").unwrap();
+        assert_eq!(decoded.format.as_ref(), "SVG");
+        assert_eq!((decoded.width, decoded.height), (4, 3));
+        assert_eq!(&decoded.frames[0].data.as_bytes()[0..4], &[0, 0, 255, 255]);
+    }
+
+    #[gpui::test]
+    async fn test_open_and_reload_image(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree("/root", json!({ "logo.svg": SVG, "notes.txt": "hello" }))
+            .await;
+        let project = Project::test(app_state.fs.clone(), ["/root".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+
+        cx.update(|cx| {
+            let path = (worktree_id, "notes.txt").into();
+            assert!(<ImageItem as project::Item>::try_open(&project, &path, cx).is_none());
+        });
+
+        let item = workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_path((worktree_id, "logo.svg"), None, true, cx)
+            })
+            .await
+            .unwrap();
+        let image_view = item.downcast::<ImageView>().unwrap();
+        let image_size = |cx: &mut gpui::VisualTestContext| {
+            image_view.update(cx, |view, cx| {
+                let image = view.item.read(cx).image().unwrap();
+                (image.width, image.height)
+            })
+        };
+        assert_eq!(image_size(cx), (4, 3));
+
+        app_state
+            .fs
+            .as_fake()
+            .insert_file("/root/logo.svg", SVG.replace("\"4\"", "\"8\""))
+            .await;
+        cx.run_until_parked();
+        assert_eq!(image_size(cx), (8, 3));
+
+        cx.dispatch_action(ZoomIn);
+        image_view.update(cx, |view, _| assert_eq!(view.scale, Some(ZOOM_STEP)));
+        cx.dispatch_action(ZoomToFit);
+        image_view.update(cx, |view, _| assert_eq!(view.scale, None));
+    }
+
+    fn init_test(cx: &mut TestAppContext) -> Arc<AppState> {
+        cx.update(|cx| {
+            let state = AppState::test(cx);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            workspace::init_settings(cx);
+            Project::init_settings(cx);
+            super::init(cx);
+            state
+        })
+    }
+}
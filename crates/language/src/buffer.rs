@@ -17,7 +17,7 @@ use crate::{
 };
 use anyhow::{anyhow, Context, Result};
 pub use clock::ReplicaId;
-use futures::channel::oneshot;
+use futures::{channel::oneshot, Stream, StreamExt};
 use gpui::{AppContext, EventEmitter, HighlightStyle, ModelContext, Task, TaskLabel};
 use lazy_static::lazy_static;
 use lsp::LanguageServerId;
@@ -46,13 +46,14 @@ use text::*;
 pub use text::{
     Anchor, Bias, Buffer as TextBuffer, BufferId, BufferSnapshot as TextBufferSnapshot, Edit,
     OffsetRangeExt, OffsetUtf16, Patch, Point, PointUtf16, Rope, RopeFingerprint, Selection,
-    SelectionGoal, Subscription, TextDimension, TextSummary, ToOffset, ToOffsetUtf16, ToPoint,
-    ToPointUtf16, Transaction, TransactionId, Unclipped,
+    SelectionGoal, SerializedHistory, SerializedTransaction, Subscription, TextDimension,
+    TextSummary, ToOffset, ToOffsetUtf16, ToPoint, ToPointUtf16, Transaction, TransactionId,
+    Unclipped, UndoTree, UndoTreeNode, UndoTreeVersion,
 };
 use theme::SyntaxTheme;
 #[cfg(any(test, feature = "test-support"))]
 use util::RandomCharIter;
-use util::RangeExt;
+use util::{RangeExt, ResultExt};
 
 #[cfg(any(test, feature = "test-support"))]
 pub use {tree_sitter_rust, tree_sitter_typescript};
@@ -65,6 +66,10 @@ lazy_static! {
     pub static ref BUFFER_DIFF_TASK: TaskLabel = TaskLabel::new();
 }
 
+/// How much text is inserted at once when a large file is reloaded, so that its text
+/// is never copied into a single string.
+const LARGE_FILE_EDIT_LEN: usize = 4 * 1024 * 1024;
+
 /// Indicate whether a [Buffer] has permissions to edit.
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum Capability {
@@ -110,6 +115,11 @@ pub struct Buffer {
     completion_triggers_timestamp: clock::Lamport,
     deferred_ops: OperationQueue<Operation>,
     capability: Capability,
+    /// Whether the buffer was loaded in large file mode, see [Buffer::with_large_file].
+    large_file: bool,
+    /// The task appending the rest of a large file, along with the capability to
+    /// restore once it's fully loaded.
+    remaining_text_load: Option<(Capability, Task<()>)>,
 }
 
 /// An immutable, cheaply cloneable representation of a fixed
@@ -396,6 +406,10 @@ pub trait LocalFile: File {
     /// Loads the file's contents from disk.
     fn load(&self, cx: &AppContext) -> Task<Result<String>>;
 
+    /// Loads the file's contents from disk a chunk at a time, normalizing its line
+    /// endings. Used for files in large file mode.
+    fn load_rope(&self, cx: &AppContext) -> Task<Result<(Rope, LineEnding)>>;
+
     /// Called when the buffer is reloaded from disk.
     fn buffer_reloaded(
         &self,
@@ -646,6 +660,81 @@ impl Buffer {
         self
     }
 
+    /// Marks the buffer as a large file, returning the buffer. Large files are never
+    /// parsed, diffed against git or synced with language servers.
+    pub fn with_large_file(mut self) -> Self {
+        self.large_file = true;
+        self
+    }
+
+    /// Whether this buffer was loaded in large file mode.
+    pub fn is_large_file(&self) -> bool {
+        self.large_file
+    }
+
+    /// Appends the rest of a large file as it's read from disk, after the buffer was
+    /// created from the start of it. The buffer is read-only until the file is fully
+    /// loaded, and the appended text isn't recorded in the undo history.
+    pub fn load_remaining_text(
+        &mut self,
+        mut chunks: impl 'static + Unpin + Stream<Item = Result<(String, LineEnding)>>,
+        cx: &mut ModelContext<Self>,
+    ) {
+        let capability = self.capability;
+        self.set_capability(Capability::ReadOnly, cx);
+        let task = cx.spawn(|this, mut cx| async move {
+            let mut line_ending = None;
+            while let Some(chunk) = chunks.next().await {
+                let Some((text, chunk_line_ending)) = chunk.log_err() else {
+                    // Keep the buffer read-only, so that it isn't saved over the file.
+                    return;
+                };
+                line_ending = Some(chunk_line_ending);
+                if this
+                    .update(&mut cx, |this, cx| this.append_loaded_text(text, cx))
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            this.update(&mut cx, |this, cx| {
+                this.remaining_text_load.take();
+                this.set_capability(capability, cx);
+                this.did_reload(
+                    this.version(),
+                    this.as_rope().fingerprint(),
+                    line_ending.unwrap_or(this.line_ending()),
+                    this.saved_mtime,
+                    cx,
+                );
+            })
+            .ok();
+        });
+        self.remaining_text_load = Some((capability, task));
+    }
+
+    fn append_loaded_text(&mut self, text: String, cx: &mut ModelContext<Self>) {
+        let len = self.len();
+        self.edit_without_history([(len..len, text)], cx);
+        self.saved_version = self.version();
+        self.file_fingerprint = self.as_rope().fingerprint();
+    }
+
+    /// Replaces the whole text of the buffer with a rope, a few megabytes at a time, without
+    /// recording the replaced text in the undo history.
+    fn replace_text_with_rope(&mut self, rope: &Rope, cx: &mut ModelContext<Self>) {
+        self.edit_without_history([(0..self.len(), "")], cx);
+        let mut text = String::new();
+        let mut chunks = rope.chunks().peekable();
+        while let Some(chunk) = chunks.next() {
+            text.push_str(chunk);
+            if text.len() >= LARGE_FILE_EDIT_LEN || chunks.peek().is_none() {
+                let len = self.len();
+                self.edit_without_history([(len..len, mem::take(&mut text))], cx);
+            }
+        }
+    }
+
     /// Returns the [Capability] of this buffer.
     pub fn capability(&self) -> Capability {
         self.capability
@@ -698,6 +787,8 @@ impl Buffer {
             completion_triggers: Default::default(),
             completion_triggers_timestamp: Default::default(),
             deferred_ops: OperationQueue::new(),
+            large_file: false,
+            remaining_text_load: None,
         }
     }
 
@@ -798,6 +889,9 @@ impl Buffer {
         &mut self,
         cx: &mut ModelContext<Self>,
     ) -> oneshot::Receiver<Option<Transaction>> {
+        if self.large_file {
+            return self.reload_large_file(cx);
+        }
         let (tx, rx) = futures::channel::oneshot::channel();
         let prev_version = self.text.version();
         self.reload_task = Some(cx.spawn(|this, mut cx| async move {
@@ -842,6 +936,62 @@ impl Buffer {
         rx
     }
 
+    /// Reloads a buffer in large file mode, reading the file a chunk at a time and
+    /// replacing the whole text rather than diffing it against the file. The reload can't
+    /// be undone, so that the history doesn't keep a copy of the file for every reload.
+    fn reload_large_file(
+        &mut self,
+        cx: &mut ModelContext<Self>,
+    ) -> oneshot::Receiver<Option<Transaction>> {
+        let (tx, rx) = futures::channel::oneshot::channel();
+        // The whole file is read again, so stop appending the rest of it if it was
+        // still loading. The buffer stays read-only until it's been replaced.
+        let restored_capability = self
+            .remaining_text_load
+            .take()
+            .map(|(capability, _)| capability);
+        let prev_version = self.text.version();
+        self.reload_task = Some(cx.spawn(|this, mut cx| async move {
+            let Some((new_mtime, new_text)) = this.update(&mut cx, |this, cx| {
+                let file = this.file.as_ref()?.as_local()?;
+                Some((file.mtime(), file.load_rope(cx)))
+            })?
+            else {
+                return Ok(());
+            };
+
+            let (new_text, line_ending) = new_text.await?;
+            this.update(&mut cx, |this, cx| {
+                if this.version() == prev_version {
+                    this.replace_text_with_rope(&new_text, cx);
+                    tx.send(None).ok();
+                    if let Some(capability) = restored_capability {
+                        this.set_capability(capability, cx);
+                    }
+
+                    this.did_reload(
+                        this.version(),
+                        this.as_rope().fingerprint(),
+                        line_ending,
+                        new_mtime,
+                        cx,
+                    );
+                } else {
+                    this.did_reload(
+                        prev_version,
+                        new_text.fingerprint(),
+                        this.line_ending(),
+                        this.saved_mtime,
+                        cx,
+                    );
+                }
+
+                this.reload_task.take();
+            })
+        }));
+        rx
+    }
+
     /// This method is called to signal that the buffer has been reloaded.
     pub fn did_reload(
         &mut self,
@@ -916,6 +1066,9 @@ impl Buffer {
     /// Sets the text that will be used to compute a Git diff
     /// against the buffer text.
     pub fn set_diff_base(&mut self, diff_base: Option<String>, cx: &mut ModelContext<Self>) {
+        if self.large_file {
+            return;
+        }
         self.diff_base = diff_base;
         self.git_diff_recalc(cx);
         cx.emit(Event::DiffBaseChanged);
@@ -923,6 +1076,9 @@ impl Buffer {
 
     /// Recomputes the Git diff status.
     pub fn git_diff_recalc(&mut self, cx: &mut ModelContext<Self>) -> Option<Task<()>> {
+        if self.large_file {
+            return None;
+        }
         let diff_base = self.diff_base.clone()?; // TODO: Make this an Arc
         let snapshot = self.snapshot();
 
@@ -1024,7 +1180,7 @@ impl Buffer {
     /// for the same buffer, we only initiate a new parse if we are not already
     /// parsing in the background.
     pub fn reparse(&mut self, cx: &mut ModelContext<Self>) {
-        if self.parsing_in_background {
+        if self.parsing_in_background || self.large_file {
             return;
         }
         let language = if let Some(language) = self.language.clone() {
@@ -1810,6 +1966,22 @@ impl Buffer {
         Some(edit_id)
     }
 
+    /// Applies the given edits without recording them in the undo history, for text that's
+    /// loaded rather than typed, which the history would otherwise keep a copy of.
+    pub fn edit_without_history<I, S, T>(&mut self, edits_iter: I, cx: &mut ModelContext<Self>)
+    where
+        I: IntoIterator<Item = (Range<S>, T)>,
+        S: ToOffset,
+        T: Into<Arc<str>>,
+    {
+        self.finalize_last_transaction();
+        self.start_transaction();
+        self.edit(edits_iter, None, cx);
+        if let Some(transaction_id) = self.end_transaction(cx) {
+            self.forget_transaction(transaction_id);
+        }
+    }
+
     fn did_edit(
         &mut self,
         old_version: &clock::Global,
@@ -1997,6 +2169,65 @@ impl Buffer {
         }
     }
 
+    /// Restores a previously serialized undo history into a buffer that has no history
+    /// of its own. Fails without modifying the buffer if the history doesn't match the
+    /// buffer's current text.
+    pub fn restore_history(
+        &mut self,
+        history: &SerializedHistory,
+        cx: &mut ModelContext<Self>,
+    ) -> Result<()> {
+        let was_dirty = self.is_dirty();
+        let old_version = self.version.clone();
+
+        for operation in self.text.restore_history(history)? {
+            self.send_operation(Operation::Buffer(operation), cx);
+        }
+        self.did_edit(&old_version, was_dirty, cx);
+        Ok(())
+    }
+
+    /// Moves to the state right after the given transaction in the buffer's undo tree,
+    /// or to its initial state if `target` is `None`.
+    pub fn navigate_to_transaction(
+        &mut self,
+        target: Option<TransactionId>,
+        cx: &mut ModelContext<Self>,
+    ) -> bool {
+        self.apply_history_navigation(|buffer| buffer.navigate_to_transaction(target), cx)
+    }
+
+    /// Moves to the state that was created right before the current one, regardless of
+    /// which branch of the undo tree it is on.
+    pub fn undo_chronologically(&mut self, cx: &mut ModelContext<Self>) -> bool {
+        self.apply_history_navigation(|buffer| buffer.undo_chronologically(), cx)
+    }
+
+    /// Moves to the state that was created right after the current one, regardless of
+    /// which branch of the undo tree it is on.
+    pub fn redo_chronologically(&mut self, cx: &mut ModelContext<Self>) -> bool {
+        self.apply_history_navigation(|buffer| buffer.redo_chronologically(), cx)
+    }
+
+    fn apply_history_navigation(
+        &mut self,
+        navigate: impl FnOnce(&mut TextBuffer) -> Vec<text::Operation>,
+        cx: &mut ModelContext<Self>,
+    ) -> bool {
+        let was_dirty = self.is_dirty();
+        let old_version = self.version.clone();
+
+        let operations = navigate(&mut self.text);
+        if operations.is_empty() {
+            return false;
+        }
+        for operation in operations {
+            self.send_operation(Operation::Buffer(operation), cx);
+        }
+        self.did_edit(&old_version, was_dirty, cx);
+        true
+    }
+
     /// Manually undoes a specific transaction in the buffer's undo history.
     pub fn undo_transaction(
         &mut self,
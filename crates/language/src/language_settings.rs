@@ -42,6 +42,8 @@ pub fn all_language_settings<'a>(
 pub struct AllLanguageSettings {
     /// The settings for GitHub Copilot.
     pub copilot: CopilotSettings,
+    /// Which provider supplies inline completions in the editor.
+    pub inline_completion_provider: InlineCompletionProvider,
     defaults: LanguageSettings,
     languages: HashMap<Arc<str>, LanguageSettings>,
 }
@@ -231,6 +233,22 @@ pub struct CopilotSettingsContent {
 pub struct FeaturesContent {
     /// Whether the GitHub Copilot feature is enabled.
     pub copilot: Option<bool>,
+    /// Which provider supplies inline completions in the editor.
+    pub inline_completion_provider: Option<InlineCompletionProvider>,
+}
+
+/// The provider of the inline completions shown as ghost text in the editor.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InlineCompletionProvider {
+    /// Do not show inline completions.
+    None,
+    /// Use GitHub Copilot.
+    #[default]
+    Copilot,
+    /// Use the completion model configured for the assistant, asking it to fill
+    /// in the text at the cursor.
+    Assistant,
 }
 
 /// Controls the soft-wrapping behavior in the editor.
@@ -365,10 +383,16 @@ impl AllLanguageSettings {
 
     /// Returns whether GitHub Copilot is enabled for the given language and path.
     pub fn copilot_enabled(&self, language: Option<&Arc<Language>>, path: Option<&Path>) -> bool {
-        if !self.copilot.feature_enabled {
-            return false;
-        }
+        self.copilot.feature_enabled && self.inline_completions_enabled(language, path)
+    }
 
+    /// Returns whether inline completions should be shown for the given language
+    /// and path, regardless of which provider supplies them.
+    pub fn inline_completions_enabled(
+        &self,
+        language: Option<&Arc<Language>>,
+        path: Option<&Path>,
+    ) -> bool {
         if let Some(path) = path {
             if !self.copilot_enabled_for_path(path) {
                 return false;
@@ -437,6 +461,11 @@ impl settings::Settings for AllLanguageSettings {
             .as_ref()
             .and_then(|f| f.copilot)
             .ok_or_else(Self::missing_default)?;
+        let mut inline_completion_provider = default_value
+            .features
+            .as_ref()
+            .and_then(|f| f.inline_completion_provider)
+            .unwrap_or_default();
         let mut copilot_globs = default_value
             .copilot
             .as_ref()
@@ -447,6 +476,13 @@ impl settings::Settings for AllLanguageSettings {
             if let Some(copilot) = user_settings.features.as_ref().and_then(|f| f.copilot) {
                 copilot_enabled = copilot;
             }
+            if let Some(provider) = user_settings
+                .features
+                .as_ref()
+                .and_then(|f| f.inline_completion_provider)
+            {
+                inline_completion_provider = provider;
+            }
             if let Some(globs) = user_settings
                 .copilot
                 .as_ref()
@@ -481,6 +517,7 @@ impl settings::Settings for AllLanguageSettings {
                     .filter_map(|g| Some(globset::Glob::new(g).ok()?.compile_matcher()))
                     .collect(),
             },
+            inline_completion_provider,
             defaults,
             languages,
         })
@@ -0,0 +1,212 @@
+use crate::{LocalHistoryView, RecoverDeletedFile};
+use fuzzy::{match_strings, StringMatch, StringMatchCandidate};
+use gpui::{
+    AppContext, DismissEvent, EventEmitter, FocusHandle, FocusableView, Render, Task, View,
+    ViewContext, VisualContext as _, WeakView,
+};
+use picker::{Picker, PickerDelegate};
+use std::{path::PathBuf, sync::Arc};
+use ui::{prelude::*, HighlightedLabel, ListItem, ListItemSpacing};
+use util::ResultExt;
+use workspace::{ModalView, Workspace};
+
+/// Lists the files in the project's worktrees that have a local history but don't exist
+/// anymore, so that they can be recovered.
+pub struct DeletedFilePicker {
+    picker: View<Picker<DeletedFilePickerDelegate>>,
+}
+
+impl DeletedFilePicker {
+    pub(crate) fn toggle(
+        workspace: &mut Workspace,
+        _: &RecoverDeletedFile,
+        cx: &mut ViewContext<Workspace>,
+    ) {
+        let fs = workspace.app_state().fs.clone();
+        let Some(local_history) = fs.local_history() else {
+            cx.propagate();
+            return;
+        };
+        let roots = workspace
+            .project()
+            .read(cx)
+            .visible_worktrees(cx)
+            .map(|worktree| worktree.read(cx).abs_path())
+            .collect::<Vec<_>>();
+
+        cx.spawn(|workspace, mut cx| async move {
+            let mut deleted_paths = Vec::new();
+            for abs_path in local_history.recorded_paths().await? {
+                let Some(root) = roots.iter().find(|root| abs_path.starts_with(root)) else {
+                    continue;
+                };
+                if let Ok(None) = fs.metadata(&abs_path).await {
+                    // Include the worktree's name, to tell apart files from different roots.
+                    let relative_path = abs_path
+                        .strip_prefix(root.parent().unwrap_or(&**root))
+                        .unwrap_or(&abs_path)
+                        .to_string_lossy()
+                        .into_owned();
+                    deleted_paths.push((abs_path, relative_path));
+                }
+            }
+
+            workspace.update(&mut cx, |workspace, cx| {
+                let weak_workspace = cx.view().downgrade();
+                workspace.toggle_modal(cx, move |cx| {
+                    let delegate = DeletedFilePickerDelegate::new(
+                        cx.view().downgrade(),
+                        weak_workspace,
+                        deleted_paths,
+                    );
+                    Self {
+                        picker: cx.new_view(|cx| Picker::new(delegate, cx)),
+                    }
+                });
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+}
+
+impl Render for DeletedFilePicker {
+    fn render(&mut self, _cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex().w(rems(34.)).child(self.picker.clone())
+    }
+}
+
+impl FocusableView for DeletedFilePicker {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.picker.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<DismissEvent> for DeletedFilePicker {}
+impl ModalView for DeletedFilePicker {}
+
+pub struct DeletedFilePickerDelegate {
+    deleted_file_picker: WeakView<DeletedFilePicker>,
+    workspace: WeakView<Workspace>,
+    abs_paths: Vec<PathBuf>,
+    candidates: Vec<StringMatchCandidate>,
+    matches: Vec<StringMatch>,
+    selected_index: usize,
+}
+
+impl DeletedFilePickerDelegate {
+    fn new(
+        deleted_file_picker: WeakView<DeletedFilePicker>,
+        workspace: WeakView<Workspace>,
+        deleted_paths: Vec<(PathBuf, String)>,
+    ) -> Self {
+        let (abs_paths, relative_paths): (Vec<_>, Vec<_>) = deleted_paths.into_iter().unzip();
+        let candidates = relative_paths
+            .into_iter()
+            .enumerate()
+            .map(|(candidate_id, path)| StringMatchCandidate::new(candidate_id, path))
+            .collect();
+        Self {
+            deleted_file_picker,
+            workspace,
+            abs_paths,
+            candidates,
+            matches: Vec::new(),
+            selected_index: 0,
+        }
+    }
+}
+
+impl PickerDelegate for DeletedFilePickerDelegate {
+    type ListItem = ListItem;
+
+    fn placeholder_text(&self) -> Arc<str> {
+        "Recover a deleted file...".into()
+    }
+
+    fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn confirm(&mut self, _: bool, cx: &mut ViewContext<Picker<Self>>) {
+        if let Some(mat) = self.matches.get(self.selected_index) {
+            let abs_path = self.abs_paths[mat.candidate_id].clone();
+            self.workspace
+                .update(cx, |workspace, cx| {
+                    LocalHistoryView::open_path(workspace, abs_path, cx).detach_and_log_err(cx)
+                })
+                .log_err();
+        }
+        self.dismissed(cx);
+    }
+
+    fn dismissed(&mut self, cx: &mut ViewContext<Picker<Self>>) {
+        self.deleted_file_picker
+            .update(cx, |_, cx| cx.emit(DismissEvent))
+            .log_err();
+    }
+
+    fn selected_index(&self) -> usize {
+        self.selected_index
+    }
+
+    fn set_selected_index(&mut self, ix: usize, _: &mut ViewContext<Picker<Self>>) {
+        self.selected_index = ix;
+    }
+
+    fn update_matches(&mut self, query: String, cx: &mut ViewContext<Picker<Self>>) -> Task<()> {
+        let background = cx.background_executor().clone();
+        let candidates = self.candidates.clone();
+        cx.spawn(|this, mut cx| async move {
+            let matches = if query.is_empty() {
+                candidates
+                    .into_iter()
+                    .map(|candidate| StringMatch {
+                        candidate_id: candidate.id,
+                        string: candidate.string,
+                        positions: Vec::new(),
+                        score: 0.0,
+                    })
+                    .collect()
+            } else {
+                match_strings(
+                    &candidates,
+                    &query,
+                    false,
+                    100,
+                    &Default::default(),
+                    background,
+                )
+                .await
+            };
+
+            this.update(&mut cx, |this, cx| {
+                let delegate = &mut this.delegate;
+                delegate.matches = matches;
+                delegate.selected_index = delegate
+                    .selected_index
+                    .min(delegate.matches.len().saturating_sub(1));
+                cx.notify();
+            })
+            .log_err();
+        })
+    }
+
+    fn render_match(
+        &self,
+        ix: usize,
+        selected: bool,
+        _: &mut ViewContext<Picker<Self>>,
+    ) -> Option<Self::ListItem> {
+        let mat = &self.matches[ix];
+        Some(
+            ListItem::new(ix)
+                .inset(true)
+                .spacing(ListItemSpacing::Sparse)
+                .selected(selected)
+                .child(HighlightedLabel::new(
+                    mat.string.clone(),
+                    mat.positions.clone(),
+                )),
+        )
+    }
+}
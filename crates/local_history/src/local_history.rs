@@ -0,0 +1,753 @@
+mod deleted_file_picker;
+mod local_history_settings;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use editor::Editor;
+use fs::{
+    local_history::{LocalHistory, LocalHistorySnapshot, SnapshotReason},
+    Fs,
+};
+use gpui::{
+    actions, div, px, uniform_list, AnyElement, AppContext, EventEmitter, FocusHandle,
+    FocusableView, InteractiveElement, IntoElement, Model, ParentElement, Render, SharedString,
+    Styled, Subscription, Task, UniformListScrollHandle, View, ViewContext, VisualContext as _,
+    WeakView, WindowContext,
+};
+use language::{Buffer, LineEnding};
+use menu::{Confirm, SelectNext, SelectPrev};
+use settings::{Settings, SettingsStore};
+use similar::{ChangeTag, TextDiff};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use ui::{h_flex, prelude::*, v_flex, Button, DateTimeType, FormatDistance, Label, ListItem};
+use util::ResultExt;
+use workspace::{
+    item::{Item, ItemEvent},
+    Workspace,
+};
+
+pub use deleted_file_picker::DeletedFilePicker;
+pub use local_history_settings::LocalHistorySettings;
+
+/// The number of unchanged lines shown around each change in the diff.
+const DIFF_CONTEXT_LINES: usize = 3;
+/// How long to wait after the buffer was edited before diffing it again.
+const DIFF_DEBOUNCE: Duration = Duration::from_millis(250);
+
+actions!(local_history, [Open, RecoverDeletedFile]);
+
+pub fn init(fs: Arc<dyn Fs>, cx: &mut AppContext) {
+    LocalHistorySettings::register(cx);
+    if let Some(local_history) = fs.local_history() {
+        local_history.set_limits(LocalHistorySettings::get_global(cx).limits());
+        cx.observe_global::<SettingsStore>(move |cx| {
+            local_history.set_limits(LocalHistorySettings::get_global(cx).limits());
+        })
+        .detach();
+    }
+
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(LocalHistoryView::open);
+        workspace.register_action(DeletedFilePicker::toggle);
+    })
+    .detach();
+}
+
+/// Lists the snapshots that were recorded for a file in the local history, showing how each
+/// one differs from the file's current contents and allowing it to be restored.
+pub struct LocalHistoryView {
+    focus_handle: FocusHandle,
+    fs: Arc<dyn Fs>,
+    local_history: Arc<LocalHistory>,
+    workspace: WeakView<Workspace>,
+    abs_path: PathBuf,
+    /// The buffer for the file, or `None` if the file doesn't exist anymore.
+    buffer: Option<Model<Buffer>>,
+    snapshots: Vec<LocalHistorySnapshot>,
+    selected_ix: Option<usize>,
+    selected_contents: Option<SnapshotContents>,
+    /// The diff between the selected snapshot and the file's current contents, or `None`
+    /// while it hasn't been computed yet.
+    diff: Option<Vec<DiffLine>>,
+    scroll_handle: UniformListScrollHandle,
+    diff_scroll_handle: UniformListScrollHandle,
+    load_snapshots: Task<Option<()>>,
+    load_selected_contents: Task<Option<()>>,
+    pending_diff: Task<()>,
+    _buffer_subscription: Option<Subscription>,
+}
+
+#[derive(Clone)]
+enum SnapshotContents {
+    Text(Arc<str>),
+    /// Contents that aren't valid UTF-8, which can be restored but not diffed.
+    Binary(Arc<[u8]>),
+}
+
+impl SnapshotContents {
+    fn new(bytes: Vec<u8>) -> Self {
+        match String::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.into()),
+            Err(error) => Self::Binary(error.into_bytes().into()),
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Text(text) => text.as_bytes(),
+            Self::Binary(bytes) => bytes,
+        }
+    }
+}
+
+struct DiffLine {
+    text: SharedString,
+    color: Color,
+}
+
+impl LocalHistoryView {
+    fn open(workspace: &mut Workspace, _: &Open, cx: &mut ViewContext<Workspace>) {
+        let Some(local_history) = workspace.app_state().fs.local_history() else {
+            cx.propagate();
+            return;
+        };
+        let Some(buffer) = workspace
+            .active_item_as::<Editor>(cx)
+            .and_then(|editor| editor.read(cx).buffer().read(cx).as_singleton())
+        else {
+            cx.propagate();
+            return;
+        };
+        let Some(abs_path) = buffer
+            .read(cx)
+            .file()
+            .and_then(|file| Some(file.as_local()?.abs_path(cx)))
+        else {
+            cx.propagate();
+            return;
+        };
+
+        let view =
+            cx.new_view(|cx| Self::new(local_history, abs_path, Some(buffer), workspace, cx));
+        workspace.add_item(Box::new(view), cx);
+    }
+
+    /// Opens the local history of the file at `abs_path`, which doesn't need to be open or
+    /// even exist anymore.
+    pub fn open_path(
+        workspace: &mut Workspace,
+        abs_path: PathBuf,
+        cx: &mut ViewContext<Workspace>,
+    ) -> Task<Result<View<Self>>> {
+        let fs = workspace.app_state().fs.clone();
+        let Some(local_history) = fs.local_history() else {
+            return Task::ready(Err(anyhow!("local history is disabled")));
+        };
+        let project = workspace.project().clone();
+        cx.spawn(|workspace, mut cx| async move {
+            let buffer = if fs.is_file(&abs_path).await {
+                let open_buffer = project.update(&mut cx, |project, cx| {
+                    project.open_local_buffer(&abs_path, cx)
+                })?;
+                open_buffer.await.log_err()
+            } else {
+                None
+            };
+            workspace.update(&mut cx, |workspace, cx| {
+                let view =
+                    cx.new_view(|cx| Self::new(local_history, abs_path, buffer, workspace, cx));
+                workspace.add_item(Box::new(view.clone()), cx);
+                view
+            })
+        })
+    }
+
+    pub fn new(
+        local_history: Arc<LocalHistory>,
+        abs_path: PathBuf,
+        buffer: Option<Model<Buffer>>,
+        workspace: &Workspace,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        let mut this = Self {
+            focus_handle: cx.focus_handle(),
+            fs: workspace.app_state().fs.clone(),
+            local_history,
+            workspace: workspace.weak_handle(),
+            abs_path,
+            buffer: None,
+            snapshots: Vec::new(),
+            selected_ix: None,
+            selected_contents: None,
+            diff: None,
+            scroll_handle: UniformListScrollHandle::new(),
+            diff_scroll_handle: UniformListScrollHandle::new(),
+            load_snapshots: Task::ready(None),
+            load_selected_contents: Task::ready(None),
+            pending_diff: Task::ready(()),
+            _buffer_subscription: None,
+        };
+        if let Some(buffer) = buffer {
+            this.set_buffer(buffer, cx);
+        }
+        this.load_snapshots(cx);
+        this
+    }
+
+    fn set_buffer(&mut self, buffer: Model<Buffer>, cx: &mut ViewContext<Self>) {
+        self._buffer_subscription = Some(cx.subscribe(&buffer, |this, _, event, cx| match event {
+            language::Event::Saved => this.load_snapshots(cx),
+            language::Event::Edited => this.update_diff(Some(DIFF_DEBOUNCE), cx),
+            _ => {}
+        }));
+        self.buffer = Some(buffer);
+        self.update_diff(None, cx);
+        cx.notify();
+    }
+
+    fn load_snapshots(&mut self, cx: &mut ViewContext<Self>) {
+        let local_history = self.local_history.clone();
+        let abs_path = self.abs_path.clone();
+        self.load_snapshots = cx.spawn(|this, mut cx| async move {
+            let snapshots = local_history.snapshots(&abs_path).await.log_err()?;
+            this.update(&mut cx, |this, cx| {
+                let selected_snapshot = this
+                    .selected_ix
+                    .and_then(|ix| this.snapshots.get(ix))
+                    .map(|snapshot| snapshot.snapshot_path.clone());
+                this.snapshots = snapshots;
+                let ix = selected_snapshot
+                    .and_then(|path| {
+                        this.snapshots
+                            .iter()
+                            .position(|snapshot| snapshot.snapshot_path == path)
+                    })
+                    .or_else(|| (!this.snapshots.is_empty()).then_some(0));
+                match ix {
+                    Some(ix) => this.select_snapshot(ix, cx),
+                    None => {
+                        this.selected_ix = None;
+                        this.selected_contents = None;
+                        this.update_diff(None, cx);
+                        cx.notify();
+                    }
+                }
+            })
+            .ok()
+        });
+    }
+
+    fn select_next(&mut self, _: &SelectNext, cx: &mut ViewContext<Self>) {
+        if self.snapshots.is_empty() {
+            return;
+        }
+        let ix = self
+            .selected_ix
+            .map_or(0, |ix| (ix + 1).min(self.snapshots.len() - 1));
+        self.select_snapshot(ix, cx);
+    }
+
+    fn select_prev(&mut self, _: &SelectPrev, cx: &mut ViewContext<Self>) {
+        if self.snapshots.is_empty() {
+            return;
+        }
+        let ix = self
+            .selected_ix
+            .map_or(self.snapshots.len() - 1, |ix| ix.saturating_sub(1));
+        self.select_snapshot(ix, cx);
+    }
+
+    fn confirm(&mut self, _: &Confirm, cx: &mut ViewContext<Self>) {
+        self.restore_selected_snapshot(cx).detach_and_log_err(cx);
+    }
+
+    fn select_snapshot(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let Some(snapshot) = self.snapshots.get(ix).cloned() else {
+            return;
+        };
+        if self.selected_ix != Some(ix) {
+            self.selected_contents = None;
+            self.diff = None;
+        }
+        self.selected_ix = Some(ix);
+        self.scroll_handle.scroll_to_item(ix);
+        cx.notify();
+
+        let local_history = self.local_history.clone();
+        self.load_selected_contents = cx.spawn(|this, mut cx| async move {
+            let contents = local_history.load(&snapshot).await.log_err()?;
+            this.update(&mut cx, |this, cx| {
+                if this.selected_ix == Some(ix) {
+                    this.selected_contents = Some(SnapshotContents::new(contents));
+                    this.update_diff(None, cx);
+                    cx.notify();
+                }
+            })
+            .ok()
+        });
+    }
+
+    /// Replaces the buffer's contents with the selected snapshot, as a single undoable edit.
+    /// The buffer is left unsaved, so the restored contents can be reviewed first. A file
+    /// that doesn't exist anymore, or a snapshot that isn't text, is written back to disk
+    /// instead.
+    fn restore_selected_snapshot(&mut self, cx: &mut ViewContext<Self>) -> Task<Result<()>> {
+        let Some(contents) = self.selected_contents.clone() else {
+            return Task::ready(Ok(()));
+        };
+        let (SnapshotContents::Text(text), Some(buffer)) = (&contents, self.buffer.clone()) else {
+            return self.write_file(contents, cx);
+        };
+        let diff = buffer.read(cx).diff(text.to_string(), cx);
+        cx.spawn(|_, mut cx| async move {
+            let diff = diff.await;
+            buffer.update(&mut cx, |buffer, cx| {
+                buffer.finalize_last_transaction();
+                buffer.apply_diff(diff, cx);
+                buffer.finalize_last_transaction();
+            })
+        })
+    }
+
+    /// Writes a snapshot back to disk, opening the file if it had been deleted. An open
+    /// buffer picks up the new contents from disk.
+    fn write_file(
+        &mut self,
+        contents: SnapshotContents,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Result<()>> {
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        let workspace = self.workspace.clone();
+        let is_open = self.buffer.is_some();
+        cx.spawn(|this, mut cx| async move {
+            // The snapshot keeps the file's original line endings, so it's written as is.
+            fs.write(&abs_path, contents.as_bytes()).await?;
+            if is_open {
+                return this.update(&mut cx, |this, cx| this.load_snapshots(cx));
+            }
+            // The file is restored even if nothing can show it, so failing to open it isn't
+            // an error.
+            let item = workspace
+                .update(&mut cx, |workspace, cx| {
+                    workspace.open_abs_path(abs_path, true, cx)
+                })?
+                .await
+                .log_err();
+            this.update(&mut cx, |this, cx| {
+                let buffer = item
+                    .and_then(|item| item.downcast::<Editor>())
+                    .and_then(|editor| editor.read(cx).buffer().read(cx).as_singleton());
+                if let Some(buffer) = buffer {
+                    this.set_buffer(buffer, cx);
+                }
+                this.load_snapshots(cx);
+            })
+        })
+    }
+
+    /// Diffs the selected snapshot against the buffer's contents on the background
+    /// executor, once `debounce` has elapsed without the diff being updated again.
+    fn update_diff(&mut self, debounce: Option<Duration>, cx: &mut ViewContext<Self>) {
+        if !matches!(self.selected_contents, Some(SnapshotContents::Text(_))) {
+            self.diff = None;
+            self.pending_diff = Task::ready(());
+            return;
+        }
+
+        self.pending_diff = cx.spawn(|this, mut cx| async move {
+            if let Some(debounce) = debounce {
+                cx.background_executor().timer(debounce).await;
+            }
+            let Some((old_text, new_text)) = this
+                .update(&mut cx, |this, cx| {
+                    let Some(SnapshotContents::Text(old_text)) = this.selected_contents.clone()
+                    else {
+                        return None;
+                    };
+                    let new_text = this
+                        .buffer
+                        .as_ref()
+                        .map(|buffer| buffer.read(cx).as_rope().clone());
+                    Some((old_text, new_text))
+                })
+                .ok()
+                .flatten()
+            else {
+                return;
+            };
+            let diff = cx
+                .background_executor()
+                .spawn(async move {
+                    // The buffer's line endings are normalized, so the snapshot's need to be
+                    // as well for the lines to compare equal.
+                    let mut old_text = old_text.to_string();
+                    LineEnding::normalize(&mut old_text);
+                    let new_text = new_text.map_or_else(String::new, |text| text.to_string());
+                    diff_lines(&old_text, &new_text)
+                })
+                .await;
+            this.update(&mut cx, |this, cx| {
+                this.diff = Some(diff);
+                cx.notify();
+            })
+            .ok();
+        });
+    }
+
+    fn render_snapshot(&self, ix: usize, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let snapshot = &self.snapshots[ix];
+        let recorded_at = FormatDistance::from_now(DateTimeType::Local(DateTime::<Local>::from(
+            snapshot.timestamp,
+        )))
+        .add_suffix(true)
+        .to_string();
+        let reason = match snapshot.reason {
+            SnapshotReason::Saved => "Saved",
+            SnapshotReason::Replaced => "Changed on disk",
+            SnapshotReason::Removed => "Deleted",
+        };
+
+        ListItem::new(ix)
+            .selected(self.selected_ix == Some(ix))
+            .child(
+                v_flex().child(Label::new(recorded_at)).child(
+                    Label::new(reason)
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                ),
+            )
+            .on_click(cx.listener(move |this, event: &gpui::ClickEvent, cx| {
+                this.select_snapshot(ix, cx);
+                if event.up.click_count > 1 {
+                    this.restore_selected_snapshot(cx).detach_and_log_err(cx);
+                }
+            }))
+    }
+
+    fn render_diff(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        div()
+            .flex_1()
+            .h_full()
+            .p_2()
+            .bg(cx.theme().colors().editor_background)
+            .map(|this| match self.diff.as_ref() {
+                None if matches!(self.selected_contents, Some(SnapshotContents::Binary(_))) => this
+                    .child(
+                        Label::new("This revision isn't text, so it can't be compared.")
+                            .color(Color::Muted),
+                    ),
+                None => this,
+                Some(diff) if diff.is_empty() => this.child(
+                    Label::new("This revision matches the current contents.").color(Color::Muted),
+                ),
+                Some(diff) => this.child(
+                    uniform_list(
+                        cx.view().clone(),
+                        "local-history-diff",
+                        diff.len(),
+                        |this, range, _| {
+                            let Some(diff) = this.diff.as_ref() else {
+                                return Vec::new();
+                            };
+                            diff[range]
+                                .iter()
+                                .map(|line| {
+                                    Label::new(line.text.clone())
+                                        .size(LabelSize::Small)
+                                        .color(line.color)
+                                })
+                                .collect()
+                        },
+                    )
+                    .size_full()
+                    .track_scroll(self.diff_scroll_handle.clone()),
+                ),
+            })
+    }
+
+    fn file_name(&self) -> String {
+        self.abs_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.abs_path.to_string_lossy().into_owned())
+    }
+}
+
+impl Render for LocalHistoryView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("LocalHistory")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_prev))
+            .on_action(cx.listener(Self::confirm))
+            .size_full()
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    .justify_between()
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border)
+                    .child(Label::new(format!(
+                        "Local History of {}{}",
+                        self.abs_path.display(),
+                        if self.buffer.is_some() {
+                            ""
+                        } else {
+                            " (deleted)"
+                        }
+                    )))
+                    .child(
+                        Button::new("restore", "Restore")
+                            .disabled(self.selected_contents.is_none())
+                            .on_click(cx.listener(|this, _, cx| {
+                                this.restore_selected_snapshot(cx).detach_and_log_err(cx);
+                            })),
+                    ),
+            )
+            .map(|this| {
+                if self.snapshots.is_empty() {
+                    this.child(
+                        div().p_4().child(
+                            Label::new("No revisions have been recorded for this file yet.")
+                                .color(Color::Muted),
+                        ),
+                    )
+                } else {
+                    this.child(
+                        h_flex()
+                            .flex_1()
+                            .items_start()
+                            .child(
+                                uniform_list(
+                                    cx.view().clone(),
+                                    "local-history-snapshots",
+                                    self.snapshots.len(),
+                                    |this, range, cx| {
+                                        range.map(|ix| this.render_snapshot(ix, cx)).collect()
+                                    },
+                                )
+                                .w(px(240.))
+                                .h_full()
+                                .border_r_1()
+                                .border_color(cx.theme().colors().border)
+                                .track_scroll(self.scroll_handle.clone()),
+                            )
+                            .child(self.render_diff(cx)),
+                    )
+                }
+            })
+    }
+}
+
+impl FocusableView for LocalHistoryView {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl EventEmitter<ItemEvent> for LocalHistoryView {}
+
+impl Item for LocalHistoryView {
+    type Event = ItemEvent;
+
+    fn tab_content(&self, _: Option<usize>, selected: bool, _: &WindowContext) -> AnyElement {
+        Label::new(format!("History: {}", self.file_name()))
+            .color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            })
+            .into_any_element()
+    }
+
+    fn tab_tooltip_text(&self, _: &AppContext) -> Option<SharedString> {
+        Some(self.abs_path.to_string_lossy().into_owned().into())
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("local history")
+    }
+
+    fn to_item_events(event: &Self::Event, mut f: impl FnMut(ItemEvent)) {
+        f(*event)
+    }
+}
+
+fn diff_lines(old_text: &str, new_text: &str) -> Vec<DiffLine> {
+    let mut lines = Vec::new();
+    let diff = TextDiff::from_lines(old_text, new_text);
+    for (ix, group) in diff.grouped_ops(DIFF_CONTEXT_LINES).iter().enumerate() {
+        if ix > 0 {
+            lines.push(DiffLine {
+                text: "⋯".into(),
+                color: Color::Muted,
+            });
+        }
+        for op in group {
+            for change in diff.iter_changes(op) {
+                let (prefix, color) = match change.tag() {
+                    ChangeTag::Delete => ("-", Color::Deleted),
+                    ChangeTag::Insert => ("+", Color::Created),
+                    ChangeTag::Equal => (" ", Color::Default),
+                };
+                let line = change.value().trim_end_matches(['\r', '\n']);
+                lines.push(DiffLine {
+                    text: format!("{prefix} {line}").into(),
+                    color,
+                });
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::{local_history::LocalHistoryLimits, FakeFs};
+    use gpui::{TestAppContext, VisualTestContext};
+    use project::Project;
+    use serde_json::json;
+    use std::mem;
+    use workspace::AppState;
+
+    #[gpui::test]
+    async fn test_record_diff_and_restore(cx: &mut TestAppContext) {
+        // The local history is kept on the real file system.
+        cx.executor().allow_parking();
+        init_test(cx);
+        let history_dir = tempfile::tempdir().unwrap();
+        let fs = FakeFs::new(cx.executor());
+        fs.set_local_history(LocalHistory::new(
+            history_dir.path(),
+            LocalHistoryLimits::default(),
+        ));
+        let local_history = fs.local_history().unwrap();
+        fs.insert_tree("/root", json!({ "main.rs": "one\r\ntwo\r\n" }))
+            .await;
+        let project = Project::test(fs.clone(), ["/root".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+
+        // Saving records the contents on disk before and after the save.
+        let buffer = project
+            .update(cx, |project, cx| {
+                project.open_local_buffer("/root/main.rs", cx)
+            })
+            .await
+            .unwrap();
+        buffer.update(cx, |buffer, cx| buffer.edit([(0..3, "ONE")], None, cx));
+        project
+            .update(cx, |project, cx| project.save_buffer(buffer.clone(), cx))
+            .await
+            .unwrap();
+
+        let view = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| {
+                LocalHistoryView::new(
+                    local_history.clone(),
+                    "/root/main.rs".into(),
+                    Some(buffer.clone()),
+                    workspace,
+                    cx,
+                )
+            })
+        });
+        load_snapshots(&view, cx).await;
+        view.update(cx, |view, _| {
+            assert_eq!(
+                view.snapshots
+                    .iter()
+                    .map(|snapshot| snapshot.reason)
+                    .collect::<Vec<_>>(),
+                [SnapshotReason::Saved, SnapshotReason::Replaced]
+            );
+            assert_eq!(view.selected_ix, Some(0));
+            // The snapshot keeps the file's CRLF line endings, which don't count as changes.
+            assert_eq!(diff_texts(view), Some(Vec::new()));
+        });
+
+        view.update(cx, |view, cx| view.select_snapshot(1, cx));
+        load_selected_contents(&view, cx).await;
+        view.update(cx, |view, _| {
+            assert_eq!(
+                diff_texts(view),
+                Some(vec!["- one".into(), "+ ONE".into(), "  two".into()])
+            );
+        });
+
+        // Restoring edits the buffer without saving it.
+        view.update(cx, |view, cx| view.restore_selected_snapshot(cx))
+            .await
+            .unwrap();
+        buffer.update(cx, |buffer, _| {
+            assert_eq!(buffer.text(), "one\ntwo\n");
+            assert!(buffer.is_dirty());
+        });
+        cx.executor().advance_clock(DIFF_DEBOUNCE);
+        cx.run_until_parked();
+        view.update(cx, |view, _| assert_eq!(diff_texts(view), Some(Vec::new())));
+
+        // Snapshots that aren't valid UTF-8 can't be diffed, but can still be restored.
+        let bytes = [0xff, 0xfe, b'\n', 0x00];
+        fs.write("/root/data.bin".as_ref(), &bytes).await.unwrap();
+        fs.remove_file("/root/data.bin".as_ref(), Default::default())
+            .await
+            .unwrap();
+        let view = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| {
+                LocalHistoryView::new(local_history, "/root/data.bin".into(), None, workspace, cx)
+            })
+        });
+        load_snapshots(&view, cx).await;
+        view.update(cx, |view, _| {
+            assert!(matches!(
+                view.selected_contents,
+                Some(SnapshotContents::Binary(_))
+            ));
+            assert_eq!(diff_texts(view), None);
+        });
+        view.update(cx, |view, cx| view.restore_selected_snapshot(cx))
+            .await
+            .unwrap();
+        assert_eq!(
+            fs.load_bytes("/root/data.bin".as_ref()).await.unwrap(),
+            bytes
+        );
+    }
+
+    /// Waits for the view's snapshots to be listed and for the first one to be loaded.
+    async fn load_snapshots(view: &View<LocalHistoryView>, cx: &mut VisualTestContext) {
+        let task = view.update(cx, |view, _| {
+            mem::replace(&mut view.load_snapshots, Task::ready(None))
+        });
+        task.await;
+        load_selected_contents(view, cx).await;
+    }
+
+    async fn load_selected_contents(view: &View<LocalHistoryView>, cx: &mut VisualTestContext) {
+        let task = view.update(cx, |view, _| {
+            mem::replace(&mut view.load_selected_contents, Task::ready(None))
+        });
+        task.await;
+        cx.run_until_parked();
+    }
+
+    fn diff_texts(view: &LocalHistoryView) -> Option<Vec<String>> {
+        let diff = view.diff.as_ref()?;
+        Some(diff.iter().map(|line| line.text.to_string()).collect())
+    }
+
+    fn init_test(cx: &mut TestAppContext) {
+        cx.update(|cx| {
+            AppState::test(cx);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            editor::init(cx);
+            workspace::init_settings(cx);
+            Project::init_settings(cx);
+        });
+    }
+}
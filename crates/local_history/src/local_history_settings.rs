@@ -0,0 +1,67 @@
+use fs::local_history::LocalHistoryLimits;
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use settings::Settings;
+
+#[derive(Deserialize, Debug)]
+pub struct LocalHistorySettings {
+    pub enabled: bool,
+    pub max_snapshots_per_file: usize,
+    pub max_file_size_kb: u64,
+    pub max_files_per_removed_directory: usize,
+    pub max_removed_directory_size_mb: u64,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct LocalHistorySettingsContent {
+    /// Whether to keep snapshots of files when they are saved, replaced or removed.
+    ///
+    /// Default: true
+    pub enabled: Option<bool>,
+    /// How many snapshots to keep for each file.
+    ///
+    /// Default: 50
+    pub max_snapshots_per_file: Option<usize>,
+    /// Files larger than this many kilobytes are not snapshotted.
+    ///
+    /// Default: 2048
+    pub max_file_size_kb: Option<u64>,
+    /// Removed directories containing more files than this are not snapshotted.
+    ///
+    /// Default: 1000
+    pub max_files_per_removed_directory: Option<usize>,
+    /// Removed directories whose files add up to more than this many megabytes are not
+    /// snapshotted.
+    ///
+    /// Default: 64
+    pub max_removed_directory_size_mb: Option<u64>,
+}
+
+impl LocalHistorySettings {
+    pub fn limits(&self) -> LocalHistoryLimits {
+        LocalHistoryLimits {
+            max_snapshots_per_file: if self.enabled {
+                self.max_snapshots_per_file
+            } else {
+                0
+            },
+            max_file_len: self.max_file_size_kb * 1024,
+            max_files_per_removed_dir: self.max_files_per_removed_directory,
+            max_removed_dir_len: self.max_removed_directory_size_mb * 1024 * 1024,
+        }
+    }
+}
+
+impl Settings for LocalHistorySettings {
+    const KEY: Option<&'static str> = Some("local_history");
+
+    type FileContent = LocalHistorySettingsContent;
+
+    fn load(
+        default_value: &Self::FileContent,
+        user_values: &[&Self::FileContent],
+        _: &mut gpui::AppContext,
+    ) -> anyhow::Result<Self> {
+        Self::load_via_json_merge(default_value, user_values)
+    }
+}
@@ -0,0 +1,534 @@
+use anyhow::Result;
+use fs::Fs;
+use futures::StreamExt;
+use gpui::{AppContext, Context, EventEmitter, Model, ModelContext, Task};
+use language::{Buffer, BufferId, LineEnding, Point};
+use project::{project_settings::ProjectSettings, Project, ProjectEntryId, ProjectPath};
+use settings::Settings;
+use std::{
+    mem,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use util::ResultExt;
+
+/// Extensions of files in the project that are opened in the log viewer.
+const LOG_EXTENSIONS: &[&str] = &["log"];
+const WATCH_LATENCY: Duration = Duration::from_millis(100);
+
+pub enum LogFileEvent {
+    /// Text was appended to the buffer, the first of it in the given row, after
+    /// `removed_rows` rows were removed from the start of the buffer to stay within its
+    /// maximum number of lines.
+    Appended { start_row: u32, removed_rows: u32 },
+    /// The file shrank, e.g. because it was rotated, or grew by more than can be read at
+    /// once, and its end was read again.
+    Reloaded,
+}
+
+/// Limits how much of a log is read when it's opened, and how much of it is kept as it's
+/// followed.
+#[derive(Clone, Debug)]
+pub struct LogFileOptions {
+    previous_log: Option<PathBuf>,
+    max_len: u64,
+    max_lines: Option<usize>,
+}
+
+impl Default for LogFileOptions {
+    fn default() -> Self {
+        Self {
+            previous_log: None,
+            max_len: u64::MAX,
+            max_lines: None,
+        }
+    }
+}
+
+impl LogFileOptions {
+    /// Shows the lines of a log that was rotated away from this one before its own lines.
+    pub fn with_previous_log(mut self, previous_log: PathBuf) -> Self {
+        self.previous_log = Some(previous_log);
+        self
+    }
+
+    /// Reads at most this many bytes from the end of each file, and from the end of what
+    /// was appended to it at once.
+    pub fn with_max_len(mut self, max_len: u64) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Shows at most this many lines from the end of the log, which must be at least one.
+    /// Lines at the start are removed as new ones are appended.
+    pub fn with_max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines);
+        self
+    }
+}
+
+/// A log file whose buffer follows the file on disk as it is appended to, reading only
+/// the bytes that were added instead of reloading it.
+pub struct LogFile {
+    project_path: Option<ProjectPath>,
+    entry_id: Option<ProjectEntryId>,
+    abs_path: PathBuf,
+    fs: Arc<dyn Fs>,
+    options: LogFileOptions,
+    buffer: Model<Buffer>,
+    /// How many bytes of the file have been read into the buffer.
+    offset: u64,
+    /// Bytes at the end of the file that don't form a complete character yet.
+    pending: Vec<u8>,
+    /// Whether lines at the start of the log were left out when it was opened.
+    is_truncated: bool,
+    tail_task: Option<Task<()>>,
+    tail_again: bool,
+    _watch_task: Task<()>,
+}
+
+impl EventEmitter<LogFileEvent> for LogFile {}
+
+impl LogFile {
+    pub fn open(
+        fs: Arc<dyn Fs>,
+        abs_path: PathBuf,
+        project_path: Option<ProjectPath>,
+        entry_id: Option<ProjectEntryId>,
+        options: LogFileOptions,
+        cx: &mut AppContext,
+    ) -> Task<Result<Model<Self>>> {
+        cx.spawn(|mut cx| async move {
+            let mut text = String::new();
+            let mut is_truncated = false;
+            if let Some(previous_log) = &options.previous_log {
+                // The previous log is optional, as there's none until a log is rotated.
+                if fs.metadata(previous_log).await?.is_some() {
+                    let (range, bytes) =
+                        load_end(fs.as_ref(), previous_log, options.max_len).await?;
+                    text = decode(&mut Vec::new(), bytes);
+                    if !text.is_empty() && !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    is_truncated |= range.start > 0;
+                }
+            }
+
+            let (range, bytes) = load_end(fs.as_ref(), &abs_path, options.max_len).await?;
+            let offset = range.end;
+            let mut pending = Vec::new();
+            text.push_str(&decode(&mut pending, bytes));
+            is_truncated |= range.start > 0;
+            if let Some(max_lines) = options.max_lines {
+                is_truncated |= keep_last_lines(&mut text, max_lines);
+            }
+
+            cx.new_model(|cx| {
+                let buffer = cx.new_model(|cx| {
+                    Buffer::new(0, BufferId::new(cx.entity_id().as_u64()).unwrap(), text)
+                });
+                let watch_task = Self::watch(fs.clone(), abs_path.clone(), cx);
+                Self {
+                    project_path,
+                    entry_id,
+                    abs_path,
+                    fs,
+                    options,
+                    buffer,
+                    offset,
+                    pending,
+                    is_truncated,
+                    tail_task: None,
+                    tail_again: false,
+                    _watch_task: watch_task,
+                }
+            })
+        })
+    }
+
+    fn watch(fs: Arc<dyn Fs>, abs_path: PathBuf, cx: &mut ModelContext<Self>) -> Task<()> {
+        cx.spawn(|this, mut cx| async move {
+            let mut events = fs.watch(&abs_path, WATCH_LATENCY).await;
+            while events.next().await.is_some() {
+                if this.update(&mut cx, |this, cx| this.tail(cx)).is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    pub fn buffer(&self) -> &Model<Buffer> {
+        &self.buffer
+    }
+
+    pub fn abs_path(&self) -> &PathBuf {
+        &self.abs_path
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.is_truncated
+    }
+
+    /// Reads whatever was appended to the file since it was last read.
+    pub fn tail(&mut self, cx: &mut ModelContext<Self>) {
+        if self.tail_task.is_some() {
+            self.tail_again = true;
+            return;
+        }
+
+        let fs = self.fs.clone();
+        let abs_path = self.abs_path.clone();
+        let offset = self.offset;
+        let max_len = self.options.max_len;
+        self.tail_task = Some(cx.spawn(|this, mut cx| async move {
+            let read = async {
+                let len = fs
+                    .metadata(&abs_path)
+                    .await?
+                    .map_or(0, |metadata| metadata.len);
+                if len == offset {
+                    return Ok(None);
+                }
+                // A file that shrank was truncated or replaced, and one that grew by too
+                // much at once would be too slow to read, so only their end is read again.
+                if len < offset || len - offset > max_len {
+                    let (range, bytes) = load_end(fs.as_ref(), &abs_path, max_len).await?;
+                    return anyhow::Ok(Some(TailRead::Reloaded { range, bytes }));
+                }
+                let bytes = fs.load_tail(&abs_path, offset).await?;
+                anyhow::Ok(Some(TailRead::Appended(bytes)))
+            };
+            let read = read.await;
+            this.update(&mut cx, |this, cx| {
+                this.tail_task = None;
+                match read.log_err().flatten() {
+                    Some(TailRead::Appended(bytes)) => this.append(bytes, cx),
+                    Some(TailRead::Reloaded { range, bytes }) => this.reload(range, bytes, cx),
+                    None => {}
+                }
+                if mem::take(&mut this.tail_again) {
+                    this.tail(cx);
+                }
+            })
+            .ok();
+        }));
+    }
+
+    fn append(&mut self, bytes: Vec<u8>, cx: &mut ModelContext<Self>) {
+        self.offset += bytes.len() as u64;
+        let text = decode(&mut self.pending, bytes);
+        if text.is_empty() {
+            return;
+        }
+
+        let start_row = self.buffer.read(cx).max_point().row;
+        self.buffer.update(cx, |buffer, cx| {
+            let len = buffer.len();
+            buffer.edit_without_history([(len..len, text)], cx);
+        });
+        let removed_rows = self.remove_excess_lines(cx);
+        if removed_rows > start_row {
+            // The first of the appended lines were removed as well.
+            cx.emit(LogFileEvent::Reloaded);
+        } else {
+            cx.emit(LogFileEvent::Appended {
+                start_row: start_row - removed_rows,
+                removed_rows,
+            });
+        }
+    }
+
+    fn reload(&mut self, range: Range<u64>, bytes: Vec<u8>, cx: &mut ModelContext<Self>) {
+        self.pending.clear();
+        self.offset = range.end;
+        let mut text = decode(&mut self.pending, bytes);
+        self.is_truncated = range.start > 0;
+        if let Some(max_lines) = self.options.max_lines {
+            self.is_truncated |= keep_last_lines(&mut text, max_lines);
+        }
+        self.buffer.update(cx, |buffer, cx| {
+            buffer.edit_without_history([(0..buffer.len(), text)], cx);
+        });
+        cx.emit(LogFileEvent::Reloaded);
+    }
+
+    /// Removes the lines at the start of the buffer beyond its maximum number of lines,
+    /// returning how many were removed.
+    fn remove_excess_lines(&mut self, cx: &mut ModelContext<Self>) -> u32 {
+        let Some(max_lines) = self.options.max_lines else {
+            return 0;
+        };
+        let removed_rows = self.buffer.update(cx, |buffer, cx| {
+            let max_point = buffer.max_point();
+            // Like in `keep_last_lines`, a trailing newline doesn't start another line.
+            let line_count = if max_point.column == 0 {
+                max_point.row
+            } else {
+                max_point.row + 1
+            };
+            let removed_rows = line_count.saturating_sub(max_lines as u32);
+            if removed_rows > 0 {
+                let end = buffer.point_to_offset(Point::new(removed_rows, 0));
+                buffer.edit_without_history([(0..end, "")], cx);
+            }
+            removed_rows
+        });
+        self.is_truncated |= removed_rows > 0;
+        removed_rows
+    }
+}
+
+enum TailRead {
+    /// The bytes appended to the file since it was last read.
+    Appended(Vec<u8>),
+    /// The end of the file, read again from the given range.
+    Reloaded { range: Range<u64>, bytes: Vec<u8> },
+}
+
+/// Reads at most `max_len` bytes from the end of a file, returning the range of the file
+/// they were read from. When the start of the file is left out, so is the line that was
+/// cut off.
+async fn load_end(fs: &dyn Fs, abs_path: &Path, max_len: u64) -> Result<(Range<u64>, Vec<u8>)> {
+    let len = fs
+        .metadata(abs_path)
+        .await?
+        .map_or(0, |metadata| metadata.len);
+    let start = len.saturating_sub(max_len);
+    let mut bytes = fs.load_tail(abs_path, start).await?;
+    let range = start..start + bytes.len() as u64;
+    if start > 0 {
+        let line_start = bytes
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(bytes.len(), |ix| ix + 1);
+        bytes.drain(..line_start);
+    }
+    Ok((range, bytes))
+}
+
+/// Removes the lines before the last `max_lines` ones, returning whether any were removed.
+fn keep_last_lines(text: &mut String, max_lines: usize) -> bool {
+    let body_len = text.strip_suffix('\n').map_or(text.len(), str::len);
+    let Some((newline_ix, _)) = text[..body_len]
+        .rmatch_indices('\n')
+        .nth(max_lines.saturating_sub(1))
+    else {
+        return false;
+    };
+    text.drain(..=newline_ix);
+    true
+}
+
+/// Decodes the given bytes following the pending ones, keeping back any bytes at the end
+/// that could still be followed by the rest of a character or of a `\r\n` line ending.
+fn decode(pending: &mut Vec<u8>, bytes: Vec<u8>) -> String {
+    pending.extend(bytes);
+    let mut complete_len = match std::str::from_utf8(pending) {
+        Err(error) if error.error_len().is_none() => error.valid_up_to(),
+        _ => pending.len(),
+    };
+    if pending[..complete_len].ends_with(b"\r") {
+        complete_len -= 1;
+    }
+    let incomplete = pending.split_off(complete_len);
+    let mut text = String::from_utf8_lossy(&mem::replace(pending, incomplete)).into_owned();
+    LineEnding::normalize(&mut text);
+    text
+}
+
+impl project::Item for LogFile {
+    fn try_open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Option<Task<Result<Model<Self>>>> {
+        let extension = path.path.extension()?.to_str()?.to_lowercase();
+        if !LOG_EXTENSIONS.contains(&extension.as_str()) {
+            return None;
+        }
+        let project = project.read(cx);
+        if !project.is_local() {
+            return None;
+        }
+        let abs_path = project.absolute_path(path, cx)?;
+        let entry_id = project.entry_for_path(path, cx).map(|entry| entry.id);
+        let fs = project.fs().clone();
+        let options = LogFileOptions::default()
+            .with_max_len(ProjectSettings::get_global(cx).large_file_threshold());
+        Some(Self::open(
+            fs,
+            abs_path,
+            Some(path.clone()),
+            entry_id,
+            options,
+            cx,
+        ))
+    }
+
+    fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
+        self.entry_id
+    }
+
+    fn project_path(&self, _: &AppContext) -> Option<ProjectPath> {
+        self.project_path.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs::FakeFs;
+    use gpui::TestAppContext;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_decode() {
+        let mut pending = Vec::new();
+        assert_eq!(decode(&mut pending, b"one\r".to_vec()), "one");
+        assert_eq!(decode(&mut pending, b"\ntwo \xce".to_vec()), "\ntwo ");
+        assert_eq!(decode(&mut pending, b"\xb1\n".to_vec()), "\u{3b1}\n");
+        assert!(pending.is_empty());
+    }
+
+    #[gpui::test]
+    async fn test_tail_log_file(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_file("/app.log", "first\n".into()).await;
+        let log_file = cx
+            .update(|cx| {
+                LogFile::open(
+                    fs.clone(),
+                    "/app.log".into(),
+                    None,
+                    None,
+                    LogFileOptions::default(),
+                    cx,
+                )
+            })
+            .await
+            .unwrap();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        cx.update(|cx| {
+            let events = events.clone();
+            cx.subscribe(&log_file, move |_, event, _| {
+                events.borrow_mut().push(match event {
+                    LogFileEvent::Appended { start_row, .. } => Some(*start_row),
+                    LogFileEvent::Reloaded => None,
+                });
+            })
+            .detach();
+        });
+        let text = |cx: &mut TestAppContext| {
+            log_file.read_with(cx, |log_file, cx| log_file.buffer().read(cx).text())
+        };
+        assert_eq!(text(cx), "first\n");
+
+        fs.insert_file("/app.log", "first\nsecond\nthi".into())
+            .await;
+        cx.run_until_parked();
+        assert_eq!(text(cx), "first\nsecond\nthi");
+
+        fs.insert_file("/app.log", "first\nsecond\nthird\n".into())
+            .await;
+        cx.run_until_parked();
+        assert_eq!(text(cx), "first\nsecond\nthird\n");
+
+        // The log was rotated.
+        fs.insert_file("/app.log", "new\n".into()).await;
+        cx.run_until_parked();
+        assert_eq!(text(cx), "new\n");
+        assert_eq!(&*events.borrow(), &[Some(1), Some(2), None]);
+
+        // Appending to the log doesn't add to its undo history.
+        log_file.update(cx, |log_file, cx| {
+            log_file.buffer().update(cx, |buffer, cx| {
+                assert_eq!(buffer.undo(cx), None);
+                assert_eq!(buffer.text(), "new\n");
+            })
+        });
+    }
+
+    #[gpui::test]
+    async fn test_open_end_of_log(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_file("/app.log.old", "one\ntwo\nthree".into())
+            .await;
+        fs.insert_file("/app.log", "four\nfive\nsix\n".into()).await;
+        let open = |options: LogFileOptions, cx: &mut TestAppContext| {
+            let fs = fs.clone();
+            cx.update(|cx| LogFile::open(fs, "/app.log".into(), None, None, options, cx))
+        };
+        let text = |log_file: &Model<LogFile>, cx: &mut TestAppContext| {
+            log_file.read_with(cx, |log_file, cx| {
+                (log_file.buffer().read(cx).text(), log_file.is_truncated())
+            })
+        };
+
+        let options = LogFileOptions::default().with_previous_log("/app.log.old".into());
+        let log_file = open(options.clone(), cx).await.unwrap();
+        assert_eq!(
+            text(&log_file, cx),
+            ("one\ntwo\nthree\nfour\nfive\nsix\n".into(), false)
+        );
+
+        let log_file = open(options.clone().with_max_lines(4), cx).await.unwrap();
+        assert_eq!(
+            text(&log_file, cx),
+            ("three\nfour\nfive\nsix\n".into(), true)
+        );
+
+        // Only the end of each file is read, starting with a whole line.
+        let log_file = open(options.with_max_len(7), cx).await.unwrap();
+        assert_eq!(text(&log_file, cx), ("three\nsix\n".into(), true));
+
+        // The log is followed from where it was read up to.
+        fs.insert_file("/app.log", "four\nfive\nsix\nseven\n".into())
+            .await;
+        cx.run_until_parked();
+        assert_eq!(text(&log_file, cx), ("three\nsix\nseven\n".into(), true));
+    }
+
+    #[gpui::test]
+    async fn test_tail_keeps_max_lines(cx: &mut TestAppContext) {
+        let fs = FakeFs::new(cx.executor());
+        fs.insert_file("/app.log", "one\ntwo\n".into()).await;
+        let log_file = cx
+            .update(|cx| {
+                let options = LogFileOptions::default().with_max_lines(3);
+                LogFile::open(fs.clone(), "/app.log".into(), None, None, options, cx)
+            })
+            .await
+            .unwrap();
+        let text = |cx: &mut TestAppContext| {
+            log_file.read_with(cx, |log_file, cx| {
+                (log_file.buffer().read(cx).text(), log_file.is_truncated())
+            })
+        };
+        assert_eq!(text(cx), ("one\ntwo\n".into(), false));
+
+        // Lines at the start are removed as lines are appended.
+        fs.insert_file("/app.log", "one\ntwo\nthree\nfour\n".into())
+            .await;
+        cx.run_until_parked();
+        assert_eq!(text(cx), ("two\nthree\nfour\n".into(), true));
+
+        // A rotated log is limited too.
+        fs.insert_file("/app.log", "a\nb\nc\nd\n".into()).await;
+        cx.run_until_parked();
+        assert_eq!(text(cx), ("b\nc\nd\n".into(), true));
+    }
+
+    #[test]
+    fn test_keep_last_lines() {
+        let mut text = "one\ntwo\nthree\n".to_string();
+        assert!(!keep_last_lines(&mut text, 3));
+        assert!(keep_last_lines(&mut text, 2));
+        assert_eq!(text, "two\nthree\n");
+
+        let mut text = "one\ntwo".to_string();
+        assert!(keep_last_lines(&mut text, 1));
+        assert_eq!(text, "two");
+    }
+}
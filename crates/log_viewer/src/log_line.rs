@@ -0,0 +1,142 @@
+use crate::LogViewerSettings;
+use anyhow::Context as _;
+use regex::Regex;
+use std::ops::Range;
+use util::ResultExt;
+
+/// The severity of a log line, from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// All levels, from most to least severe.
+    pub const ALL: [Self; 5] = [
+        Self::Error,
+        Self::Warn,
+        Self::Info,
+        Self::Debug,
+        Self::Trace,
+    ];
+
+    /// Parses the level names and abbreviations commonly written by loggers.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_ascii_uppercase().as_str() {
+            "E" | "ERR" | "ERROR" | "FATAL" | "CRITICAL" => Some(Self::Error),
+            "W" | "WARN" | "WARNING" => Some(Self::Warn),
+            "I" | "INFO" => Some(Self::Info),
+            "D" | "DEBUG" => Some(Self::Debug),
+            "T" | "TRACE" => Some(Self::Trace),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Error => "Error",
+            Self::Warn => "Warn",
+            Self::Info => "Info",
+            Self::Debug => "Debug",
+            Self::Trace => "Trace",
+        }
+    }
+}
+
+/// The timestamp and level found in a log line, as byte ranges within it.
+#[derive(Debug, Default, PartialEq)]
+pub struct ParsedLine {
+    pub timestamp: Option<Range<usize>>,
+    pub level: Option<(LogLevel, Range<usize>)>,
+}
+
+/// The patterns from [LogViewerSettings], compiled. Invalid patterns are logged and
+/// then ignored.
+#[derive(Default)]
+pub struct LogPatterns {
+    timestamp: Option<Regex>,
+    level: Option<Regex>,
+}
+
+impl LogPatterns {
+    pub fn new(timestamp_pattern: &str, level_pattern: &str) -> Self {
+        Self {
+            timestamp: compile(timestamp_pattern),
+            level: compile(level_pattern),
+        }
+    }
+
+    pub fn from_settings(settings: &LogViewerSettings) -> Self {
+        Self::new(&settings.timestamp_pattern, &settings.level_pattern)
+    }
+
+    pub fn parse_line(&self, line: &str) -> ParsedLine {
+        let timestamp = self
+            .timestamp
+            .as_ref()
+            .and_then(|timestamp| timestamp.find(line))
+            .map(|timestamp| timestamp.range());
+        let level = self.level.as_ref().and_then(|level| {
+            let captures = level.captures(line)?;
+            let level = captures.get(1).or_else(|| captures.get(0))?;
+            Some((LogLevel::parse(level.as_str())?, level.range()))
+        });
+        ParsedLine { timestamp, level }
+    }
+}
+
+fn compile(pattern: &str) -> Option<Regex> {
+    if pattern.is_empty() {
+        return None;
+    }
+    Regex::new(pattern)
+        .with_context(|| format!("invalid log viewer pattern {pattern:?}"))
+        .log_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let patterns = LogPatterns::new(
+            r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?",
+            r"\b(ERROR|WARN|INFO|DEBUG|TRACE)\b",
+        );
+        assert_eq!(
+            patterns.parse_line("2024-02-01T10:20:30+01:00 [ERROR] failed"),
+            ParsedLine {
+                timestamp: Some(0..25),
+                level: Some((LogLevel::Error, 27..32)),
+            }
+        );
+        assert_eq!(
+            patterns.parse_line("    at main.rs:10"),
+            ParsedLine::default()
+        );
+
+        // Without a capture group, the whole match is the level.
+        let patterns = LogPatterns::new("", r"\b[EWIDT]\b");
+        assert_eq!(
+            patterns.parse_line("[W] low disk space").level,
+            Some((LogLevel::Warn, 1..2))
+        );
+
+        // Invalid patterns are ignored.
+        let patterns = LogPatterns::new("(", "(");
+        assert_eq!(patterns.parse_line("ERROR"), ParsedLine::default());
+    }
+
+    #[test]
+    fn test_level_order() {
+        assert!(LogLevel::Error > LogLevel::Warn);
+        assert!(LogLevel::Debug > LogLevel::Trace);
+        assert_eq!(LogLevel::parse("warning"), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::parse("verbose"), None);
+    }
+}
@@ -0,0 +1,858 @@
+//! Opens log files in a read-only editor that follows them as they are appended to,
+//! highlights their levels and timestamps, and filters their lines by level or regex
+//! by folding away the lines that don't match.
+
+mod log_file;
+mod log_line;
+mod log_viewer_settings;
+
+use editor::{actions::MoveToEnd, Anchor, DisplayPoint, Editor, EditorEvent, MultiBufferSnapshot};
+use gpui::{
+    actions, AnyElement, AppContext, EntityId, EventEmitter, FocusHandle, FocusableView,
+    HighlightStyle, Hsla, Model, Subscription, Task, View,
+};
+use language::Point;
+use project::{search::SearchQuery, Project};
+use regex::Regex;
+use settings::{Settings, SettingsStore};
+use std::{ops::Range, path::PathBuf, sync::Arc};
+use ui::{prelude::*, Tooltip};
+use workspace::{
+    item::{Item, ItemEvent, ProjectItem},
+    searchable::{SearchEvent, SearchableItem, SearchableItemHandle},
+    OpenPriority, Workspace,
+};
+
+pub use log_file::{LogFile, LogFileEvent, LogFileOptions};
+pub use log_line::{LogLevel, LogPatterns, ParsedLine};
+pub use log_viewer_settings::LogViewerSettings;
+
+actions!(log_viewer, [ToggleFollow, ClearFilter, OpenAsText]);
+
+pub fn init(cx: &mut AppContext) {
+    LogViewerSettings::register(cx);
+    workspace::register_project_item::<LogView>(OpenPriority::FileType, cx);
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(LogView::open_as_text);
+    })
+    .detach();
+}
+
+/// Opens a log file from outside of the project, such as Zed's own log.
+pub fn open_log_file(
+    workspace: &mut Workspace,
+    abs_path: PathBuf,
+    options: LogFileOptions,
+    cx: &mut ViewContext<Workspace>,
+) {
+    let fs = workspace.app_state().fs.clone();
+    let open = LogFile::open(fs, abs_path, None, None, options, cx);
+    cx.spawn(|workspace, mut cx| async move {
+        let log_file = open.await?;
+        workspace.update(&mut cx, |workspace, cx| {
+            let log_view = cx.new_view(|cx| LogView::new(log_file, cx));
+            workspace.add_item(Box::new(log_view), cx);
+        })
+    })
+    .detach_and_log_err(cx);
+}
+
+/// How many rows above and below the visible ones are highlighted, so that highlights
+/// are already there when the log is scrolled a little.
+const HIGHLIGHT_MARGIN_ROWS: u32 = 200;
+
+enum TimestampHighlight {}
+enum ErrorHighlight {}
+enum WarnHighlight {}
+enum InfoHighlight {}
+enum DebugHighlight {}
+enum TraceHighlight {}
+
+#[derive(Clone, Copy, PartialEq)]
+enum HighlightKind {
+    Timestamp,
+    Level(LogLevel),
+}
+
+/// What the log view knows about a row of the log.
+#[derive(Clone, Copy)]
+struct Row {
+    /// The level of the row, or of the closest row above it that has one.
+    level: Option<LogLevel>,
+    visible: bool,
+}
+
+pub struct LogView {
+    log_file: Model<LogFile>,
+    editor: View<Editor>,
+    filter_editor: View<Editor>,
+    patterns: LogPatterns,
+    pattern_settings: (String, String),
+    min_level: Option<LogLevel>,
+    filter: Option<Regex>,
+    filter_is_invalid: bool,
+    rows: Vec<Row>,
+    highlights: Vec<(u32, HighlightKind, Range<Anchor>)>,
+    /// The rows whose highlights were sent to the editor. Only the rows around the visible
+    /// ones are highlighted, so that the cost of an update doesn't grow with the log.
+    highlighted_rows: Range<u32>,
+    /// The folds that hide the rows that don't match the filter, in order.
+    folds: Vec<Range<Point>>,
+    following: bool,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl LogView {
+    /// Opens the active log in a regular editor, for logs that aren't meant to be viewed
+    /// as logs or that need to be edited.
+    fn open_as_text(workspace: &mut Workspace, _: &OpenAsText, cx: &mut ViewContext<Workspace>) {
+        let Some(log_view) = workspace.active_item_as::<LogView>(cx) else {
+            cx.propagate();
+            return;
+        };
+        let abs_path = log_view.read(cx).log_file.read(cx).abs_path().clone();
+        let open_buffer = workspace
+            .project()
+            .update(cx, |project, cx| project.open_local_buffer(abs_path, cx));
+        cx.spawn(|workspace, mut cx| async move {
+            let buffer = open_buffer.await?;
+            workspace.update(&mut cx, |workspace, cx| {
+                workspace.open_project_item::<Editor>(buffer, cx);
+            })
+        })
+        .detach_and_log_err(cx);
+    }
+
+    pub fn new(log_file: Model<LogFile>, cx: &mut ViewContext<Self>) -> Self {
+        let buffer = log_file.read(cx).buffer().clone();
+        let editor = cx.new_view(|cx| {
+            let mut editor = Editor::for_buffer(buffer, None, cx);
+            editor.set_read_only(true);
+            editor.set_show_inline_completions(false);
+            editor
+        });
+        let filter_editor = cx.new_view(|cx| {
+            let mut editor = Editor::single_line(cx);
+            editor.set_placeholder_text("Filter lines by regex…", cx);
+            editor
+        });
+
+        let subscriptions = vec![
+            cx.subscribe(&log_file, |this, _, event, cx| match event {
+                LogFileEvent::Appended {
+                    start_row,
+                    removed_rows,
+                } => {
+                    this.remove_rows(*removed_rows);
+                    this.update_rows(*start_row, cx);
+                }
+                LogFileEvent::Reloaded => this.update_rows(0, cx),
+            }),
+            cx.subscribe(&editor, Self::handle_editor_event),
+            cx.subscribe(&editor, |_, _, event: &SearchEvent, cx| {
+                cx.emit(event.clone())
+            }),
+            cx.subscribe(&filter_editor, |this, _, event: &EditorEvent, cx| {
+                if let EditorEvent::BufferEdited = event {
+                    this.update_filter(cx);
+                }
+            }),
+            cx.observe_global::<SettingsStore>(|this, cx| {
+                let settings = LogViewerSettings::get_global(cx);
+                let pattern_settings = (
+                    settings.timestamp_pattern.clone(),
+                    settings.level_pattern.clone(),
+                );
+                if pattern_settings != this.pattern_settings {
+                    this.patterns = LogPatterns::from_settings(settings);
+                    this.pattern_settings = pattern_settings;
+                    this.update_rows(0, cx);
+                }
+            }),
+        ];
+
+        let settings = LogViewerSettings::get_global(cx);
+        let mut this = Self {
+            log_file,
+            editor,
+            filter_editor,
+            patterns: LogPatterns::from_settings(settings),
+            pattern_settings: (
+                settings.timestamp_pattern.clone(),
+                settings.level_pattern.clone(),
+            ),
+            min_level: None,
+            filter: None,
+            filter_is_invalid: false,
+            rows: Vec::new(),
+            highlights: Vec::new(),
+            highlighted_rows: 0..0,
+            folds: Vec::new(),
+            following: settings.follow,
+            _subscriptions: subscriptions,
+        };
+        this.update_rows(0, cx);
+        this
+    }
+
+    /// Recomputes the level, highlights and visibility of the rows from `start_row` on,
+    /// after text was appended to them. Everything is recomputed when it's 0.
+    fn update_rows(&mut self, start_row: u32, cx: &mut ViewContext<Self>) {
+        let snapshot = self.editor.read(cx).buffer().read(cx).snapshot(cx);
+        if start_row == 0 {
+            self.folds.clear();
+            self.editor.update(cx, |editor, cx| {
+                editor.unfold_ranges([0..snapshot.len()], true, false, cx)
+            });
+        }
+
+        let start_row = start_row.min(self.rows.len() as u32);
+        self.rows.truncate(start_row as usize);
+        let highlights_len = self
+            .highlights
+            .partition_point(|(row, _, _)| *row < start_row);
+        self.highlights.truncate(highlights_len);
+
+        let mut level = self.rows.last().and_then(|row| row.level);
+        let mut line = String::new();
+        for row in start_row..=snapshot.max_point().row {
+            line.clear();
+            line.extend(
+                snapshot
+                    .text_for_range(Point::new(row, 0)..Point::new(row, snapshot.line_len(row))),
+            );
+            let anchor_range = |range: Range<usize>| {
+                snapshot.anchor_after(Point::new(row, range.start as u32))
+                    ..snapshot.anchor_before(Point::new(row, range.end as u32))
+            };
+
+            let parsed = self.patterns.parse_line(&line);
+            if let Some(range) = parsed.timestamp {
+                self.highlights
+                    .push((row, HighlightKind::Timestamp, anchor_range(range)));
+            }
+            if let Some((line_level, range)) = parsed.level {
+                level = Some(line_level);
+                self.highlights
+                    .push((row, HighlightKind::Level(line_level), anchor_range(range)));
+            }
+            let visible = self.is_visible(level, &line);
+            self.rows.push(Row { level, visible });
+        }
+
+        self.refold(start_row, &snapshot, cx);
+        self.apply_highlights(Some(start_row), cx);
+        if self.following {
+            self.scroll_to_end(cx);
+        }
+        cx.notify();
+    }
+
+    /// Forgets the given number of rows at the start of the log, after they were removed
+    /// from its buffer.
+    fn remove_rows(&mut self, count: u32) {
+        if count == 0 {
+            return;
+        }
+        self.rows.drain(..(count as usize).min(self.rows.len()));
+        let removed_highlights = self.highlights.partition_point(|(row, _, _)| *row < count);
+        self.highlights.drain(..removed_highlights);
+        for (row, _, _) in &mut self.highlights {
+            *row -= count;
+        }
+        self.highlighted_rows = self.highlighted_rows.start.saturating_sub(count)
+            ..self.highlighted_rows.end.saturating_sub(count);
+        self.folds.retain_mut(|fold| {
+            if fold.end.row < count {
+                return false;
+            }
+            fold.start = if fold.start.row < count {
+                Point::zero()
+            } else {
+                Point::new(fold.start.row - count, fold.start.column)
+            };
+            fold.end.row -= count;
+            true
+        });
+    }
+
+    fn is_visible(&self, level: Option<LogLevel>, line: &str) -> bool {
+        let level_matches = match (self.min_level, level) {
+            (Some(min_level), Some(level)) => level >= min_level,
+            _ => true,
+        };
+        level_matches
+            && self
+                .filter
+                .as_ref()
+                .map_or(true, |filter| filter.is_match(line))
+    }
+
+    /// Folds the hidden rows from `start_row` on, replacing the folds that covered them.
+    fn refold(
+        &mut self,
+        start_row: u32,
+        snapshot: &MultiBufferSnapshot,
+        cx: &mut ViewContext<Self>,
+    ) {
+        // Hidden rows right above `start_row` are folded together with the ones below it.
+        let mut run_start = start_row as usize;
+        while run_start > 0 && !self.rows[run_start - 1].visible {
+            run_start -= 1;
+        }
+        let stale_ix = self
+            .folds
+            .partition_point(|fold| fold.end.row < run_start as u32);
+        let stale_folds = self.folds.split_off(stale_ix);
+
+        let mut new_folds = Vec::new();
+        let mut row = run_start;
+        while row < self.rows.len() {
+            if self.rows[row].visible {
+                row += 1;
+                continue;
+            }
+            let end = (row..self.rows.len())
+                .find(|row| self.rows[*row].visible)
+                .unwrap_or(self.rows.len());
+            let fold = fold_for_rows(row as u32..end as u32, snapshot);
+            if !fold.is_empty() {
+                new_folds.push(fold);
+            }
+            row = end;
+        }
+        self.folds.extend(new_folds.iter().cloned());
+
+        self.editor.update(cx, |editor, cx| {
+            editor.unfold_ranges(stale_folds, false, false, cx);
+            editor.fold_ranges(new_folds, false, cx);
+        });
+    }
+
+    /// The rows around the visible ones, which are the ones that get highlighted.
+    fn rows_to_highlight(&self, cx: &mut ViewContext<Self>) -> Range<u32> {
+        self.editor.update(cx, |editor, cx| {
+            let snapshot = editor.snapshot(cx);
+            let display_snapshot = &snapshot.display_snapshot;
+            let max_row = display_snapshot.max_point().row();
+            let top_row = editor.scroll_position(cx).y.max(0.) as u32;
+            let visible_rows = editor.visible_line_count().unwrap_or(0.).ceil() as u32;
+            let start_row = top_row.saturating_sub(HIGHLIGHT_MARGIN_ROWS).min(max_row);
+            let end_row = (top_row + visible_rows + HIGHLIGHT_MARGIN_ROWS).min(max_row);
+            let start = DisplayPoint::new(start_row, 0).to_point(display_snapshot);
+            let end = DisplayPoint::new(end_row, 0).to_point(display_snapshot);
+            start.row..end.row + 1
+        })
+    }
+
+    /// Replaces the editor's highlights with the ones of the rows around the visible ones,
+    /// if those rows changed, or if `changed_row` or the rows below it are among them.
+    fn apply_highlights(&mut self, changed_row: Option<u32>, cx: &mut ViewContext<Self>) {
+        let rows = self.rows_to_highlight(cx);
+        let rows_changed = changed_row.map_or(false, |changed_row| changed_row < rows.end);
+        if !rows_changed && rows == self.highlighted_rows {
+            return;
+        }
+        self.highlighted_rows = rows.clone();
+
+        let start_ix = self
+            .highlights
+            .partition_point(|(row, _, _)| *row < rows.start);
+        let end_ix = self
+            .highlights
+            .partition_point(|(row, _, _)| *row < rows.end);
+        let highlights = &self.highlights[start_ix..end_ix];
+        let ranges = |kind: HighlightKind| {
+            highlights
+                .iter()
+                .filter(|(_, highlight_kind, _)| *highlight_kind == kind)
+                .map(|(_, _, range)| range.clone())
+                .collect::<Vec<_>>()
+        };
+        let style = |color: Hsla| HighlightStyle {
+            color: Some(color),
+            ..Default::default()
+        };
+        let colors = cx.theme().colors();
+        let status = cx.theme().status();
+        let timestamps = (ranges(HighlightKind::Timestamp), style(colors.text_muted));
+        let errors = (
+            ranges(HighlightKind::Level(LogLevel::Error)),
+            style(status.error),
+        );
+        let warnings = (
+            ranges(HighlightKind::Level(LogLevel::Warn)),
+            style(status.warning),
+        );
+        let infos = (
+            ranges(HighlightKind::Level(LogLevel::Info)),
+            style(status.info),
+        );
+        let debugs = (
+            ranges(HighlightKind::Level(LogLevel::Debug)),
+            style(status.hint),
+        );
+        let traces = (
+            ranges(HighlightKind::Level(LogLevel::Trace)),
+            style(colors.text_placeholder),
+        );
+
+        self.editor.update(cx, |editor, cx| {
+            editor.highlight_text::<TimestampHighlight>(timestamps.0, timestamps.1, cx);
+            editor.highlight_text::<ErrorHighlight>(errors.0, errors.1, cx);
+            editor.highlight_text::<WarnHighlight>(warnings.0, warnings.1, cx);
+            editor.highlight_text::<InfoHighlight>(infos.0, infos.1, cx);
+            editor.highlight_text::<DebugHighlight>(debugs.0, debugs.1, cx);
+            editor.highlight_text::<TraceHighlight>(traces.0, traces.1, cx);
+        });
+    }
+
+    fn handle_editor_event(
+        &mut self,
+        editor: View<Editor>,
+        event: &EditorEvent,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if let EditorEvent::ScrollPositionChanged { .. } = event {
+            self.apply_highlights(None, cx);
+        }
+        match event {
+            // The user scrolled, so the log is followed only while its end is in view.
+            EditorEvent::ScrollPositionChanged {
+                local: true,
+                autoscroll: false,
+            } => {
+                let at_end = editor.update(cx, |editor, cx| {
+                    let Some(visible_line_count) = editor.visible_line_count() else {
+                        return true;
+                    };
+                    let max_row = editor.max_point(cx).row() as f32;
+                    editor.scroll_position(cx).y + visible_line_count > max_row
+                });
+                self.set_following(at_end, cx);
+            }
+            // The user moved the cursor, so the log is followed only while it's at the end.
+            EditorEvent::SelectionsChanged { local: true } => {
+                let at_end = editor.update(cx, |editor, cx| {
+                    let len = editor.buffer().read(cx).len(cx);
+                    editor.selections.newest::<usize>(cx).head() == len
+                });
+                self.set_following(at_end, cx);
+            }
+            _ => {}
+        }
+        cx.emit(event.clone());
+    }
+
+    fn set_following(&mut self, following: bool, cx: &mut ViewContext<Self>) {
+        if self.following != following {
+            self.following = following;
+            cx.notify();
+        }
+    }
+
+    fn scroll_to_end(&mut self, cx: &mut ViewContext<Self>) {
+        self.editor
+            .update(cx, |editor, cx| editor.move_to_end(&MoveToEnd, cx));
+    }
+
+    fn toggle_follow(&mut self, _: &ToggleFollow, cx: &mut ViewContext<Self>) {
+        self.following = !self.following;
+        if self.following {
+            self.scroll_to_end(cx);
+        }
+        cx.notify();
+    }
+
+    fn toggle_min_level(&mut self, level: LogLevel, cx: &mut ViewContext<Self>) {
+        if self.min_level == Some(level) {
+            self.min_level = None;
+        } else {
+            self.min_level = Some(level);
+        }
+        self.update_rows(0, cx);
+    }
+
+    fn update_filter(&mut self, cx: &mut ViewContext<Self>) {
+        let query = self.filter_editor.read(cx).text(cx);
+        if query.is_empty() {
+            self.filter = None;
+            self.filter_is_invalid = false;
+        } else {
+            self.filter = Regex::new(&query).ok();
+            self.filter_is_invalid = self.filter.is_none();
+        }
+        self.update_rows(0, cx);
+    }
+
+    fn clear_filter(&mut self, _: &ClearFilter, cx: &mut ViewContext<Self>) {
+        self.min_level = None;
+        if self.filter_editor.read(cx).text(cx).is_empty() {
+            self.update_rows(0, cx);
+        } else {
+            self.filter_editor
+                .update(cx, |editor, cx| editor.set_text("", cx));
+        }
+    }
+
+    fn render_filter_bar(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let filter_border = if self.filter_is_invalid {
+            cx.theme().status().error_border
+        } else {
+            cx.theme().colors().border
+        };
+        h_flex()
+            .px_2()
+            .py_1()
+            .gap_2()
+            .border_b_1()
+            .border_color(cx.theme().colors().border)
+            .child(
+                h_flex()
+                    .gap_1()
+                    .children(LogLevel::ALL.into_iter().map(|level| {
+                        Button::new(level.label(), level.label())
+                            .selected(self.min_level == Some(level))
+                            .tooltip(move |cx| {
+                                Tooltip::text(
+                                    format!("Only Show {} and More Severe Lines", level.label()),
+                                    cx,
+                                )
+                            })
+                            .on_click(
+                                cx.listener(move |this, _, cx| this.toggle_min_level(level, cx)),
+                            )
+                    })),
+            )
+            .child(
+                div()
+                    .flex_1()
+                    .px_2()
+                    .py_1()
+                    .border_1()
+                    .border_color(filter_border)
+                    .rounded_md()
+                    .child(self.filter_editor.clone()),
+            )
+            .child(
+                IconButton::new("clear-filter", IconName::Close)
+                    .tooltip(|cx| Tooltip::for_action("Clear Filter", &ClearFilter, cx))
+                    .on_click(cx.listener(|this, _, cx| this.clear_filter(&ClearFilter, cx))),
+            )
+            .when(self.log_file.read(cx).is_truncated(), |this| {
+                this.child(
+                    Label::new("Earlier lines aren't shown")
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+            })
+            .child(
+                IconButton::new("open-as-text", IconName::FileDoc)
+                    .tooltip(|cx| Tooltip::for_action("Open as Text", &OpenAsText, cx))
+                    .on_click(cx.listener(|_, _, cx| cx.dispatch_action(Box::new(OpenAsText)))),
+            )
+            .child(
+                IconButton::new("follow", IconName::ArrowDown)
+                    .selected(self.following)
+                    .tooltip(|cx| {
+                        Tooltip::for_action("Follow the End of the Log", &ToggleFollow, cx)
+                    })
+                    .on_click(cx.listener(|this, _, cx| this.toggle_follow(&ToggleFollow, cx))),
+            )
+    }
+}
+
+/// The range to fold to hide the given rows. It starts at the end of the row above them,
+/// so the fold's placeholder is shown after the last visible line.
+fn fold_for_rows(rows: Range<u32>, snapshot: &MultiBufferSnapshot) -> Range<Point> {
+    let last_row = rows.end - 1;
+    if rows.start > 0 {
+        let previous_row = rows.start - 1;
+        Point::new(previous_row, snapshot.line_len(previous_row))
+            ..Point::new(last_row, snapshot.line_len(last_row))
+    } else if rows.end <= snapshot.max_point().row {
+        Point::zero()..Point::new(rows.end, 0)
+    } else {
+        Point::zero()..Point::new(last_row, snapshot.line_len(last_row))
+    }
+}
+
+impl Render for LogView {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("LogView")
+            .size_full()
+            .on_action(cx.listener(Self::toggle_follow))
+            .on_action(cx.listener(Self::clear_filter))
+            .child(self.render_filter_bar(cx))
+            .child(div().flex_1().child(self.editor.clone()))
+    }
+}
+
+impl FocusableView for LogView {
+    fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
+        self.editor.focus_handle(cx)
+    }
+}
+
+impl EventEmitter<EditorEvent> for LogView {}
+impl EventEmitter<SearchEvent> for LogView {}
+
+impl Item for LogView {
+    type Event = EditorEvent;
+
+    fn to_item_events(event: &Self::Event, f: impl FnMut(ItemEvent)) {
+        Editor::to_item_events(event, f)
+    }
+
+    fn tab_content(&self, _: Option<usize>, selected: bool, cx: &WindowContext) -> AnyElement {
+        let title = self
+            .log_file
+            .read(cx)
+            .abs_path()
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().to_string());
+        Label::new(title)
+            .color(if selected {
+                Color::Default
+            } else {
+                Color::Muted
+            })
+            .into_any_element()
+    }
+
+    fn tab_tooltip_text(&self, cx: &AppContext) -> Option<SharedString> {
+        Some(
+            self.log_file
+                .read(cx)
+                .abs_path()
+                .to_string_lossy()
+                .to_string()
+                .into(),
+        )
+    }
+
+    fn telemetry_event_text(&self) -> Option<&'static str> {
+        Some("log viewer")
+    }
+
+    fn for_each_project_item(
+        &self,
+        cx: &AppContext,
+        f: &mut dyn FnMut(EntityId, &dyn project::Item),
+    ) {
+        f(self.log_file.entity_id(), self.log_file.read(cx))
+    }
+
+    fn is_singleton(&self, _: &AppContext) -> bool {
+        true
+    }
+
+    fn as_searchable(&self, handle: &View<Self>) -> Option<Box<dyn SearchableItemHandle>> {
+        Some(Box::new(handle.clone()))
+    }
+}
+
+impl ProjectItem for LogView {
+    type Item = LogFile;
+
+    fn for_project_item(
+        _: Model<Project>,
+        item: Model<Self::Item>,
+        cx: &mut ViewContext<Self>,
+    ) -> Self {
+        Self::new(item, cx)
+    }
+}
+
+impl SearchableItem for LogView {
+    type Match = <Editor as SearchableItem>::Match;
+
+    fn clear_matches(&mut self, cx: &mut ViewContext<Self>) {
+        self.editor.update(cx, |e, cx| e.clear_matches(cx))
+    }
+
+    fn update_matches(&mut self, matches: Vec<Self::Match>, cx: &mut ViewContext<Self>) {
+        self.editor
+            .update(cx, |e, cx| e.update_matches(matches, cx))
+    }
+
+    fn query_suggestion(&mut self, cx: &mut ViewContext<Self>) -> String {
+        self.editor.update(cx, |e, cx| e.query_suggestion(cx))
+    }
+
+    fn activate_match(
+        &mut self,
+        index: usize,
+        matches: Vec<Self::Match>,
+        cx: &mut ViewContext<Self>,
+    ) {
+        self.editor
+            .update(cx, |e, cx| e.activate_match(index, matches, cx))
+    }
+
+    fn select_matches(&mut self, matches: Vec<Self::Match>, cx: &mut ViewContext<Self>) {
+        self.editor
+            .update(cx, |e, cx| e.select_matches(matches, cx))
+    }
+
+    fn find_matches(
+        &mut self,
+        query: Arc<SearchQuery>,
+        cx: &mut ViewContext<Self>,
+    ) -> Task<Vec<Self::Match>> {
+        self.editor.update(cx, |e, cx| e.find_matches(query, cx))
+    }
+
+    fn replace(&mut self, _: &Self::Match, _: &SearchQuery, _: &mut ViewContext<Self>) {
+        // Logs are read-only.
+    }
+
+    fn supported_options() -> workspace::searchable::SearchOptions {
+        workspace::searchable::SearchOptions {
+            case: true,
+            word: true,
+            regex: true,
+            replacement: false,
+        }
+    }
+
+    fn active_match_index(
+        &mut self,
+        matches: Vec<Self::Match>,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<usize> {
+        self.editor
+            .update(cx, |e, cx| e.active_match_index(matches, cx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+    use serde_json::json;
+    use workspace::AppState;
+
+    #[gpui::test]
+    async fn test_filter_and_follow(cx: &mut TestAppContext) {
+        let app_state = init_test(cx);
+        let fs = app_state.fs.as_fake();
+        fs.insert_tree(
+            "/root",
+            json!({
+                "app.log": "2024-02-01 10:00:00 INFO started\n\
+                            2024-02-01 10:00:01 WARN slow\n\
+                            \x20   at main.rs:10\n\
+                            2024-02-01 10:00:02 INFO done\n",
+            }),
+        )
+        .await;
+        let project = Project::test(app_state.fs.clone(), ["/root".as_ref()], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project.clone(), cx));
+        let worktree_id = project.update(cx, |project, cx| {
+            project.worktrees().next().unwrap().read(cx).id()
+        });
+
+        let item = workspace
+            .update(cx, |workspace, cx| {
+                workspace.open_path((worktree_id, "app.log"), None, true, cx)
+            })
+            .await
+            .unwrap();
+        let log_view = item.downcast::<LogView>().unwrap();
+        log_view.update(cx, |log_view, cx| {
+            let editor = log_view.editor.read(cx);
+            assert_eq!(
+                editor
+                    .text_highlights::<TimestampHighlight>(cx)
+                    .unwrap()
+                    .1
+                    .len(),
+                3
+            );
+            assert_eq!(
+                editor.text_highlights::<InfoHighlight>(cx).unwrap().1.len(),
+                2
+            );
+            assert_eq!(
+                editor.text_highlights::<WarnHighlight>(cx).unwrap().1.len(),
+                1
+            );
+            assert!(log_view.following);
+
+            // The stack trace line takes the level of the line above it.
+            log_view.toggle_min_level(LogLevel::Warn, cx);
+            let text = log_view
+                .editor
+                .update(cx, |editor, cx| editor.display_text(cx));
+            assert!(!text.contains("started"));
+            assert!(text.contains("slow"));
+            assert!(text.contains("main.rs"));
+            assert!(!text.contains("done"));
+        });
+
+        // Lines appended to the log are filtered too.
+        fs.insert_file(
+            "/root/app.log",
+            "2024-02-01 10:00:00 INFO started\n\
+             2024-02-01 10:00:01 WARN slow\n\
+             \x20   at main.rs:10\n\
+             2024-02-01 10:00:02 INFO done\n\
+             2024-02-01 10:00:03 ERROR failed\n\
+             2024-02-01 10:00:04 DEBUG retrying\n"
+                .into(),
+        )
+        .await;
+        cx.run_until_parked();
+        log_view.update(cx, |log_view, cx| {
+            let text = log_view
+                .editor
+                .update(cx, |editor, cx| editor.display_text(cx));
+            assert!(text.contains("failed"));
+            assert!(!text.contains("retrying"));
+
+            log_view.clear_filter(&ClearFilter, cx);
+            log_view.filter_editor.update(cx, |editor, cx| {
+                editor.set_text("fail|start", cx);
+            });
+        });
+        log_view.update(cx, |log_view, cx| {
+            let text = log_view
+                .editor
+                .update(cx, |editor, cx| editor.display_text(cx));
+            assert!(text.contains("started"));
+            assert!(text.contains("failed"));
+            assert!(!text.contains("slow"));
+            assert!(!log_view.filter_is_invalid);
+
+            log_view.filter_editor.update(cx, |editor, cx| {
+                editor.set_text("(", cx);
+            });
+        });
+        log_view.update(cx, |log_view, _| assert!(log_view.filter_is_invalid));
+
+        // The log can be opened as text instead.
+        workspace.update(cx, |workspace, cx| {
+            LogView::open_as_text(workspace, &OpenAsText, cx)
+        });
+        cx.run_until_parked();
+        workspace.update(cx, |workspace, cx| {
+            let editor = workspace.active_item_as::<Editor>(cx).unwrap();
+            assert!(editor.read(cx).text(cx).ends_with("DEBUG retrying\n"));
+        });
+    }
+
+    fn init_test(cx: &mut TestAppContext) -> Arc<AppState> {
+        cx.update(|cx| {
+            let state = AppState::test(cx);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            editor::init(cx);
+            workspace::init_settings(cx);
+            Project::init_settings(cx);
+            super::init(cx);
+            state
+        })
+    }
+}
@@ -0,0 +1,41 @@
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+use settings::Settings;
+
+#[derive(Deserialize, Debug)]
+pub struct LogViewerSettings {
+    pub timestamp_pattern: String,
+    pub level_pattern: String,
+    pub follow: bool,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize, JsonSchema, Debug)]
+pub struct LogViewerSettingsContent {
+    /// A regex matching the timestamp of a log line.
+    ///
+    /// Default: "\\d{4}-\\d{2}-\\d{2}[T ]\\d{2}:\\d{2}:\\d{2}(\\.\\d+)?(Z|[+-]\\d{2}:?\\d{2})?"
+    pub timestamp_pattern: Option<String>,
+    /// A regex matching the level of a log line. When it has a capture group, only
+    /// the group is taken as the level.
+    ///
+    /// Default: "\\b(ERROR|WARN|INFO|DEBUG|TRACE)\\b"
+    pub level_pattern: Option<String>,
+    /// Whether to keep the end of the log in view as lines are appended to it.
+    ///
+    /// Default: true
+    pub follow: Option<bool>,
+}
+
+impl Settings for LogViewerSettings {
+    const KEY: Option<&'static str> = Some("log_viewer");
+
+    type FileContent = LogViewerSettingsContent;
+
+    fn load(
+        default_value: &Self::FileContent,
+        user_values: &[&Self::FileContent],
+        _: &mut gpui::AppContext,
+    ) -> anyhow::Result<Self> {
+        Self::load_via_json_merge(default_value, user_values)
+    }
+}
@@ -861,6 +861,14 @@ impl LanguageServer {
         &self.root_path
     }
 
+    /// Whether any request sent to the language server is still awaiting its response.
+    pub fn has_pending_requests(&self) -> bool {
+        self.response_handlers
+            .lock()
+            .as_ref()
+            .map_or(false, |handlers| !handlers.is_empty())
+    }
+
     /// Sends a RPC request to the language server.
     ///
     /// [LSP Specification](https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#requestMessage)
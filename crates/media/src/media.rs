@@ -85,6 +85,39 @@ pub mod core_video {
         pub fn pixel_format_type(&self) -> OSType {
             unsafe { CVPixelBufferGetPixelFormatType(self.as_concrete_TypeRef()) }
         }
+
+        /// Copies the bytes of the given plane, returning them along with the
+        /// number of bytes in each of the plane's rows.
+        pub fn copy_plane(&self, plane: usize) -> Result<(Vec<u8>, usize)> {
+            const READ_ONLY: u64 = 1;
+
+            unsafe {
+                let buffer = self.as_concrete_TypeRef();
+                let result = CVPixelBufferLockBaseAddress(buffer, READ_ONLY);
+                if result != kCVReturnSuccess {
+                    return Err(anyhow!("could not lock pixel buffer, code: {}", result));
+                }
+
+                let address = CVPixelBufferGetBaseAddressOfPlane(buffer, plane);
+                let bytes_per_row = CVPixelBufferGetBytesPerRowOfPlane(buffer, plane);
+                let bytes = if address.is_null() {
+                    Vec::new()
+                } else {
+                    std::slice::from_raw_parts(
+                        address as *const u8,
+                        bytes_per_row * self.plane_height(plane),
+                    )
+                    .to_vec()
+                };
+                CVPixelBufferUnlockBaseAddress(buffer, READ_ONLY);
+
+                if bytes.is_empty() {
+                    Err(anyhow!("pixel buffer has no plane {}", plane))
+                } else {
+                    Ok((bytes, bytes_per_row))
+                }
+            }
+        }
     }
 
     #[link(name = "CoreVideo", kind = "framework")]
@@ -96,6 +129,13 @@ pub mod core_video {
         fn CVPixelBufferGetWidthOfPlane(buffer: CVImageBufferRef, plane: usize) -> usize;
         fn CVPixelBufferGetHeightOfPlane(buffer: CVImageBufferRef, plane: usize) -> usize;
         fn CVPixelBufferGetPixelFormatType(buffer: CVImageBufferRef) -> OSType;
+        fn CVPixelBufferLockBaseAddress(buffer: CVImageBufferRef, flags: u64) -> CVReturn;
+        fn CVPixelBufferUnlockBaseAddress(buffer: CVImageBufferRef, flags: u64) -> CVReturn;
+        fn CVPixelBufferGetBaseAddressOfPlane(
+            buffer: CVImageBufferRef,
+            plane: usize,
+        ) -> *mut c_void;
+        fn CVPixelBufferGetBytesPerRowOfPlane(buffer: CVImageBufferRef, plane: usize) -> usize;
     }
 
     #[repr(C)]
@@ -27,8 +27,8 @@ use futures::{
 };
 use globset::{Glob, GlobSet, GlobSetBuilder};
 use gpui::{
-    AnyModel, AppContext, AsyncAppContext, BackgroundExecutor, Context, Entity, EventEmitter,
-    Model, ModelContext, Task, WeakModel,
+    AppContext, AsyncAppContext, BackgroundExecutor, Context, Entity, EventEmitter, Model,
+    ModelContext, Task, WeakModel,
 };
 use itertools::Itertools;
 use language::{
@@ -95,10 +95,26 @@ pub use worktree::*;
 const MAX_SERVER_REINSTALL_ATTEMPT_COUNT: u64 = 4;
 
 pub trait Item {
+    /// Starts opening the item at the given path, or returns `None` if the
+    /// path doesn't hold this kind of item. Items that can only tell from the
+    /// file's contents fail the task with [`UnsupportedContent`] instead.
+    fn try_open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Option<Task<Result<Model<Self>>>>
+    where
+        Self: Sized;
     fn entry_id(&self, cx: &AppContext) -> Option<ProjectEntryId>;
     fn project_path(&self, cx: &AppContext) -> Option<ProjectPath>;
 }
 
+/// The error an item fails to open with when the file's contents show that the
+/// path doesn't hold this kind of item, so that it can be opened as another item.
+#[derive(Debug, thiserror::Error)]
+#[error("{0:?} can't be opened as this kind of item")]
+pub struct UnsupportedContent(pub PathBuf);
+
 pub struct Project {
     worktrees: Vec<WorktreeHandle>,
     active_entry: Option<ProjectEntryId>,
@@ -1679,23 +1695,6 @@ impl Project {
         Ok(buffer)
     }
 
-    pub fn open_path(
-        &mut self,
-        path: ProjectPath,
-        cx: &mut ModelContext<Self>,
-    ) -> Task<Result<(Option<ProjectEntryId>, AnyModel)>> {
-        let task = self.open_buffer(path.clone(), cx);
-        cx.spawn(move |_, cx| async move {
-            let buffer = task.await?;
-            let project_entry_id = buffer.read_with(&cx, |buffer, cx| {
-                File::from_dyn(buffer.file()).and_then(|file| file.project_entry_id(cx))
-            })?;
-
-            let buffer: &AnyModel = &buffer;
-            Ok((project_entry_id, buffer.clone()))
-        })
-    }
-
     pub fn open_local_buffer(
         &mut self,
         abs_path: impl AsRef<Path>,
@@ -2050,6 +2049,9 @@ impl Project {
     ) {
         let buffer = buffer_handle.read(cx);
         let buffer_id = buffer.remote_id();
+        if buffer.is_large_file() {
+            return;
+        }
 
         if let Some(file) = File::from_dyn(buffer.file()) {
             if !file.is_local() {
@@ -2400,6 +2402,9 @@ impl Project {
             }
 
             BufferEvent::Saved => {
+                if buffer.read(cx).is_large_file() {
+                    return None;
+                }
                 let file = File::from_dyn(buffer.read(cx).file())?;
                 let worktree_id = file.worktree_id(cx);
                 let abs_path = file.as_local()?.abs_path(cx);
@@ -2732,7 +2737,7 @@ impl Project {
         };
         if let Some(file) = buffer_file {
             let worktree = file.worktree.clone();
-            if worktree.read(cx).is_local() {
+            if worktree.read(cx).is_local() && !buffer.read(cx).is_large_file() {
                 self.start_language_servers(&worktree, new_language, cx);
             }
         }
@@ -3204,6 +3209,9 @@ impl Project {
         for buffer in self.opened_buffers.values() {
             if let Some(buffer_handle) = buffer.upgrade() {
                 let buffer = buffer_handle.read(cx);
+                if buffer.is_large_file() {
+                    continue;
+                }
                 let file = match File::from_dyn(buffer.file()) {
                     Some(file) => file,
                     None => continue,
@@ -3776,6 +3784,18 @@ impl Project {
         self.language_server_statuses.values()
     }
 
+    /// Whether any language server is starting, reporting progress, or has yet
+    /// to answer a request.
+    pub fn has_pending_language_server_work(&self) -> bool {
+        self.language_servers.values().any(|state| match state {
+            LanguageServerState::Starting(_) => true,
+            LanguageServerState::Running { server, .. } => server.has_pending_requests(),
+        }) || self
+            .language_server_statuses
+            .values()
+            .any(|status| !status.pending_work.is_empty())
+    }
+
     pub fn update_diagnostics(
         &mut self,
         language_server_id: LanguageServerId,
@@ -9063,6 +9083,14 @@ fn resolve_path(base: &Path, path: &Path) -> PathBuf {
 }
 
 impl Item for Buffer {
+    fn try_open(
+        project: &Model<Project>,
+        path: &ProjectPath,
+        cx: &mut AppContext,
+    ) -> Option<Task<Result<Model<Self>>>> {
+        Some(project.update(cx, |project, cx| project.open_buffer(path.clone(), cx)))
+    }
+
     fn entry_id(&self, cx: &AppContext) -> Option<ProjectEntryId> {
         File::from_dyn(self.file()).and_then(|file| file.project_entry_id(cx))
     }
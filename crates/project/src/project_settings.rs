@@ -39,6 +39,20 @@ pub struct ProjectSettings {
     /// Treat the files matching these globs as `.env` files.
     /// Default: [ "**/.env*" ]
     pub private_files: Option<Vec<String>>,
+
+    /// Files larger than this many megabytes are opened in large file mode, which loads
+    /// them in chunks and turns off syntax highlighting, language servers, git diffs
+    /// and soft wrap.
+    ///
+    /// Default: 32
+    pub large_file_threshold_mb: Option<u64>,
+}
+
+impl ProjectSettings {
+    /// The size in bytes above which files are opened in large file mode.
+    pub fn large_file_threshold(&self) -> u64 {
+        self.large_file_threshold_mb.unwrap_or(32) * 1024 * 1024
+    }
 }
 
 #[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
@@ -12,6 +12,7 @@ use parking_lot::Mutex;
 use pretty_assertions::assert_eq;
 use serde_json::json;
 use std::{os, task::Poll};
+use text::SerializedHistory;
 use unindent::Unindent as _;
 use util::{assert_set_eq, paths::PathMatcher, test::temp_tree};
 
@@ -73,7 +74,7 @@ async fn test_symlinks(cx: &mut gpui::TestAppContext) {
     )
     .unwrap();
 
-    let project = Project::test(Arc::new(RealFs), [root_link_path.as_ref()], cx).await;
+    let project = Project::test(Arc::new(RealFs::default()), [root_link_path.as_ref()], cx).await;
 
     project.update(cx, |project, cx| {
         let tree = project.worktrees().next().unwrap().read(cx);
@@ -528,6 +529,119 @@ async fn test_managing_language_servers(cx: &mut gpui::TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_large_file_mode(cx: &mut gpui::TestAppContext) {
+    init_test(cx);
+    cx.update(|cx| {
+        cx.update_global(|settings: &mut SettingsStore, cx| {
+            settings.update_user_settings::<project_settings::ProjectSettings>(cx, |settings| {
+                settings.large_file_threshold_mb = Some(0);
+            });
+        });
+    });
+
+    let mut rust_language = Language::new(
+        LanguageConfig {
+            name: "Rust".into(),
+            path_suffixes: vec!["rs".to_string()],
+            ..Default::default()
+        },
+        Some(tree_sitter_rust::language()),
+    );
+    let mut fake_rust_servers = rust_language
+        .set_fake_lsp_adapter(Arc::new(FakeLspAdapter {
+            name: "the-rust-language-server",
+            ..Default::default()
+        }))
+        .await;
+
+    let fs = FakeFs::new(cx.executor());
+    fs.insert_tree(
+        "/dir",
+        json!({
+            ".git": {},
+            "big.rs": "fn a() {}\r\nfn b() {}\r\n",
+        }),
+    )
+    .await;
+    fs.set_index_for_repo(
+        Path::new("/dir/.git"),
+        &[(Path::new("big.rs"), "fn a() {}\n".into())],
+    );
+
+    let project = Project::test(fs.clone(), ["/dir".as_ref()], cx).await;
+    project.update(cx, |project, _| {
+        project.languages.add(Arc::new(rust_language));
+    });
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/big.rs", cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+
+    // The file is loaded with its line endings normalized, but never parsed or diffed.
+    buffer.update(cx, |buffer, _| {
+        assert!(buffer.is_large_file());
+        assert_eq!(buffer.text(), "fn a() {}\nfn b() {}\n");
+        assert_eq!(buffer.line_ending(), LineEnding::Windows);
+        assert_eq!(buffer.language().map(|l| l.name()), Some("Rust".into()));
+        assert_eq!(buffer.snapshot().syntax_layers().count(), 0);
+        assert_eq!(buffer.diff_base(), None);
+    });
+
+    // No language server is started for it.
+    assert!(fake_rust_servers.try_next().is_err());
+
+    // Once the file is loaded, it can be edited, and reloading it replaces its text without
+    // keeping the replaced text in the undo history.
+    buffer.update(cx, |buffer, _| {
+        assert_eq!(buffer.capability(), language::Capability::ReadWrite);
+        assert!(!buffer.is_dirty());
+    });
+    let long_text = "fn c() {}\n".repeat(300_000);
+    for text in [long_text.replace('c', "d"), long_text.clone()] {
+        fs.save(
+            "/dir/big.rs".as_ref(),
+            &text.as_str().into(),
+            LineEnding::Unix,
+        )
+        .await
+        .unwrap();
+        cx.executor().run_until_parked();
+        buffer.update(cx, |buffer, _| assert_eq!(buffer.text(), text));
+    }
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.line_ending(), LineEnding::Unix);
+        assert!(!buffer.is_dirty());
+        assert_eq!(
+            buffer.serialize_history(usize::MAX),
+            SerializedHistory::default()
+        );
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), long_text);
+    });
+
+    // Files that take several chunks are appended to the buffer as they're read, without
+    // recording the appended text in the undo history.
+    fs.insert_file("/dir/long.txt", long_text.clone()).await;
+    let buffer = project
+        .update(cx, |project, cx| {
+            project.open_local_buffer("/dir/long.txt", cx)
+        })
+        .await
+        .unwrap();
+    cx.executor().run_until_parked();
+    buffer.update(cx, |buffer, cx| {
+        assert_eq!(buffer.text(), long_text);
+        assert_eq!(buffer.capability(), language::Capability::ReadWrite);
+        assert!(!buffer.is_dirty());
+        buffer.undo(cx);
+        assert_eq!(buffer.text(), long_text);
+    });
+}
+
 #[gpui::test]
 async fn test_reporting_fs_changes_to_language_servers(cx: &mut gpui::TestAppContext) {
     init_test(cx);
@@ -2884,7 +2998,7 @@ async fn test_rescan_and_remote_updates(cx: &mut gpui::TestAppContext) {
         }
     }));
 
-    let project = Project::test(Arc::new(RealFs), [dir.path()], cx).await;
+    let project = Project::test(Arc::new(RealFs::default()), [dir.path()], cx).await;
     let rpc = project.update(cx, |p, _| p.client.clone());
 
     let buffer_for_path = |path: &'static str, cx: &mut gpui::TestAppContext| {
@@ -9,7 +9,7 @@ use clock::ReplicaId;
 use collections::{HashMap, HashSet, VecDeque};
 use fs::{
     repository::{GitFileStatus, GitRepository, RepoPath},
-    Fs,
+    Fs, TextChunkStream,
 };
 use futures::{
     channel::{
@@ -270,6 +270,18 @@ impl DerefMut for LocalSnapshot {
     }
 }
 
+/// The contents of a file loaded by a local worktree.
+enum LoadedText {
+    Text(String),
+    /// A file above the large file threshold, read from disk a chunk at a time. The
+    /// buffer is shown with the first chunk while the rest of them are appended.
+    Large {
+        first_chunk: String,
+        line_ending: LineEnding,
+        remaining_chunks: TextChunkStream,
+    },
+}
+
 enum ScanState {
     Started,
     Updated {
@@ -712,17 +724,43 @@ impl LocalWorktree {
             let (file, contents, diff_base) = this
                 .update(&mut cx, |t, cx| t.as_local().unwrap().load(&path, cx))?
                 .await?;
-            let text_buffer = cx
-                .background_executor()
-                .spawn(async move { text::Buffer::new(0, id, contents) })
-                .await;
-            cx.new_model(|_| {
-                Buffer::build(
+            let (text_buffer, remaining_chunks) = match contents {
+                LoadedText::Text(text) => {
+                    let text_buffer = cx
+                        .background_executor()
+                        .spawn(async move { text::Buffer::new(0, id, text) })
+                        .await;
+                    (text_buffer, None)
+                }
+                LoadedText::Large {
+                    first_chunk,
+                    line_ending,
+                    remaining_chunks,
+                } => {
+                    let text_buffer = text::Buffer::new_normalized(
+                        0,
+                        id,
+                        line_ending,
+                        Rope::from(first_chunk.as_str()),
+                    );
+                    (text_buffer, Some(remaining_chunks))
+                }
+            };
+            cx.new_model(|cx| {
+                let buffer = Buffer::build(
                     text_buffer,
                     diff_base,
                     Some(Arc::new(file)),
                     Capability::ReadWrite,
-                )
+                );
+                match remaining_chunks {
+                    Some(remaining_chunks) => {
+                        let mut buffer = buffer.with_large_file();
+                        buffer.load_remaining_text(remaining_chunks, cx);
+                        buffer
+                    }
+                    None => buffer,
+                }
             })
         })
     }
@@ -991,18 +1029,39 @@ impl LocalWorktree {
         &self,
         path: &Path,
         cx: &mut ModelContext<Worktree>,
-    ) -> Task<Result<(File, String, Option<String>)>> {
+    ) -> Task<Result<(File, LoadedText, Option<String>)>> {
         let path = Arc::from(path);
         let abs_path = self.absolutize(&path);
         let fs = self.fs.clone();
         let entry = self.refresh_entry(path.clone(), None, cx);
+        let large_file_threshold = ProjectSettings::get_global(cx).large_file_threshold();
 
         cx.spawn(|this, mut cx| async move {
             let abs_path = abs_path?;
-            let text = fs.load(&abs_path).await?;
+            let is_large_file = fs
+                .metadata(&abs_path)
+                .await?
+                .map_or(false, |metadata| metadata.len > large_file_threshold);
+            let text = if is_large_file {
+                let mut remaining_chunks = fs.load_chunks(&abs_path).await?;
+                let (first_chunk, line_ending) = remaining_chunks
+                    .next()
+                    .await
+                    .unwrap_or_else(|| Ok((String::new(), LineEnding::default())))?;
+                LoadedText::Large {
+                    first_chunk,
+                    line_ending,
+                    remaining_chunks,
+                }
+            } else {
+                LoadedText::Text(fs.load(&abs_path).await?)
+            };
             let mut index_task = None;
             let snapshot = this.update(&mut cx, |this, _| this.as_local().unwrap().snapshot())?;
-            if let Some(repo) = snapshot.repository_for_path(&path) {
+            if let Some(repo) = snapshot
+                .repository_for_path(&path)
+                .filter(|_| !is_large_file)
+            {
                 if let Some(repo_path) = repo.work_directory.relativize(&snapshot, &path).log_err()
                 {
                     if let Some(git_repo) = snapshot.git_repositories.get(&*repo.work_directory) {
@@ -2887,6 +2946,14 @@ impl language::LocalFile for File {
             .spawn(async move { fs.load(&abs_path?).await })
     }
 
+    fn load_rope(&self, cx: &AppContext) -> Task<Result<(Rope, LineEnding)>> {
+        let worktree = self.worktree.read(cx).as_local().unwrap();
+        let abs_path = worktree.absolutize(&self.path);
+        let fs = worktree.fs.clone();
+        cx.background_executor()
+            .spawn(async move { fs.load_rope(&abs_path?).await })
+    }
+
     fn buffer_reloaded(
         &self,
         buffer_id: BufferId,
@@ -861,7 +861,7 @@ async fn test_write_file(cx: &mut TestAppContext) {
         build_client(cx),
         dir.path(),
         true,
-        Arc::new(RealFs),
+        Arc::new(RealFs::default()),
         Default::default(),
         &mut cx.to_async(),
     )
@@ -941,7 +941,7 @@ async fn test_file_scan_exclusions(cx: &mut TestAppContext) {
         build_client(cx),
         dir.path(),
         true,
-        Arc::new(RealFs),
+        Arc::new(RealFs::default()),
         Default::default(),
         &mut cx.to_async(),
     )
@@ -1045,7 +1045,7 @@ async fn test_fs_events_in_exclusions(cx: &mut TestAppContext) {
         build_client(cx),
         dir.path(),
         true,
-        Arc::new(RealFs),
+        Arc::new(RealFs::default()),
         Default::default(),
         &mut cx.to_async(),
     )
@@ -1247,7 +1247,7 @@ async fn test_create_dir_all_on_create_entry(cx: &mut TestAppContext) {
 
     let client_real = cx.update(|cx| Client::new(FakeHttpClient::with_404_response(), cx));
 
-    let fs_real = Arc::new(RealFs);
+    let fs_real = Arc::new(RealFs::default());
     let temp_root = temp_tree(json!({
         "a": {}
     }));
@@ -1851,7 +1851,7 @@ async fn test_rename_work_directory(cx: &mut TestAppContext) {
         build_client(cx),
         root_path,
         true,
-        Arc::new(RealFs),
+        Arc::new(RealFs::default()),
         Default::default(),
         &mut cx.to_async(),
     )
@@ -1930,7 +1930,7 @@ async fn test_git_repository_for_path(cx: &mut TestAppContext) {
         build_client(cx),
         root.path(),
         true,
-        Arc::new(RealFs),
+        Arc::new(RealFs::default()),
         Default::default(),
         &mut cx.to_async(),
     )
@@ -2071,7 +2071,7 @@ async fn test_git_status(cx: &mut TestAppContext) {
         build_client(cx),
         root.path(),
         true,
-        Arc::new(RealFs),
+        Arc::new(RealFs::default()),
         Default::default(),
         &mut cx.to_async(),
     )
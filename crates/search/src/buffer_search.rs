@@ -667,10 +667,10 @@ impl BufferSearchBar {
         option.as_button(is_active, action)
     }
     pub fn activate_search_mode(&mut self, mode: SearchMode, cx: &mut ViewContext<Self>) {
-        assert_ne!(
-            mode,
-            SearchMode::Semantic,
-            "Semantic search is not supported in buffer search"
+        assert!(
+            !mode.uses_semantic_index(),
+            "{} search is not supported in buffer search",
+            mode.label()
         );
         if mode == self.current_mode {
             return;
@@ -1,6 +1,6 @@
 use gpui::{Action, SharedString};
 
-use crate::{ActivateRegexMode, ActivateSemanticMode, ActivateTextMode};
+use crate::{ActivateHybridMode, ActivateRegexMode, ActivateSemanticMode, ActivateTextMode};
 
 // TODO: Update the default search mode to get from config
 #[derive(Copy, Clone, Debug, Default, PartialEq)]
@@ -9,6 +9,7 @@ pub enum SearchMode {
     Text,
     Semantic,
     Regex,
+    Hybrid,
 }
 
 impl SearchMode {
@@ -17,6 +18,7 @@ impl SearchMode {
             SearchMode::Text => "Text",
             SearchMode::Semantic => "Semantic",
             SearchMode::Regex => "Regex",
+            SearchMode::Hybrid => "Hybrid",
         }
     }
     pub(crate) fn tooltip(&self) -> SharedString {
@@ -27,6 +29,14 @@ impl SearchMode {
             SearchMode::Text => ActivateTextMode.boxed_clone(),
             SearchMode::Semantic => ActivateSemanticMode.boxed_clone(),
             SearchMode::Regex => ActivateRegexMode.boxed_clone(),
+            SearchMode::Hybrid => ActivateHybridMode.boxed_clone(),
+        }
+    }
+    /// Whether searching in this mode requires the project to be indexed.
+    pub(crate) fn uses_semantic_index(&self) -> bool {
+        match self {
+            SearchMode::Text | SearchMode::Regex => false,
+            SearchMode::Semantic | SearchMode::Hybrid => true,
         }
     }
 }
@@ -41,6 +51,7 @@ pub(crate) fn next_mode(mode: &SearchMode, semantic_enabled: bool) -> SearchMode
                 SearchMode::Text
             }
         }
-        SearchMode::Semantic => SearchMode::Text,
+        SearchMode::Semantic => SearchMode::Hybrid,
+        SearchMode::Hybrid => SearchMode::Text,
     }
 }
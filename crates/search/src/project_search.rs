@@ -1,14 +1,17 @@
 use crate::{
-    history::SearchHistory, mode::SearchMode, ActivateRegexMode, ActivateSemanticMode,
-    ActivateTextMode, CycleMode, NextHistoryQuery, PreviousHistoryQuery, ReplaceAll, ReplaceNext,
-    SearchOptions, SelectNextMatch, SelectPrevMatch, ToggleCaseSensitive, ToggleIncludeIgnored,
-    ToggleReplace, ToggleWholeWord,
+    history::SearchHistory, mode::SearchMode, ActivateHybridMode, ActivateRegexMode,
+    ActivateSemanticMode, ActivateTextMode, CycleMode, NextHistoryQuery, PreviousHistoryQuery,
+    ReplaceAll, ReplaceNext, SearchOptions, SelectNextMatch, SelectPrevMatch, ToggleCaseSensitive,
+    ToggleIncludeIgnored, ToggleReplace, ToggleWholeWord,
 };
 use anyhow::{Context as _, Result};
 use collections::HashMap;
 use editor::{
-    actions::SelectAll, items::active_match_index, scroll::Autoscroll, Anchor, Editor, EditorEvent,
-    MultiBuffer, MAX_TAB_TITLE_LEN,
+    actions::SelectAll,
+    display_map::{BlockContext, BlockDisposition, BlockId, BlockProperties, BlockStyle},
+    items::active_match_index,
+    scroll::Autoscroll,
+    Anchor, Editor, EditorEvent, MultiBuffer, MAX_TAB_TITLE_LEN,
 };
 use editor::{EditorElement, EditorStyle};
 use gpui::{
@@ -18,6 +21,7 @@ use gpui::{
     Render, SharedString, Styled, Subscription, Task, TextStyle, View, ViewContext, VisualContext,
     WeakModel, WeakView, WhiteSpace, WindowContext,
 };
+use language::{Buffer, OffsetRangeExt};
 use menu::Confirm;
 use project::{
     search::{SearchInputs, SearchQuery},
@@ -29,10 +33,12 @@ use settings::Settings;
 use smol::stream::StreamExt;
 use std::{
     any::{Any, TypeId},
+    cmp::Reverse,
     collections::HashSet,
     mem,
     ops::{Not, Range},
     path::PathBuf,
+    sync::Arc,
     time::{Duration, Instant},
 };
 use theme::ThemeSettings;
@@ -87,6 +93,9 @@ pub fn init(cx: &mut AppContext) {
                 search_bar.activate_search_mode(SearchMode::Semantic, cx)
             },
         );
+        register_workspace_action(workspace, move |search_bar, _: &ActivateHybridMode, cx| {
+            search_bar.activate_search_mode(SearchMode::Hybrid, cx)
+        });
         register_workspace_action(workspace, move |search_bar, action: &CycleMode, cx| {
             search_bar.cycle_mode(action, cx)
         });
@@ -134,6 +143,8 @@ struct ProjectSearch {
     excerpts: Model<MultiBuffer>,
     pending_search: Option<Task<Option<()>>>,
     match_ranges: Vec<Range<Anchor>>,
+    /// Why each hybrid search result matched, shown above its first match.
+    match_explanations: Vec<(Anchor, SharedString)>,
     active_query: Option<SearchQuery>,
     search_id: usize,
     search_history: SearchHistory,
@@ -153,6 +164,7 @@ pub struct ProjectSearchView {
     query_editor: View<Editor>,
     replacement_editor: View<Editor>,
     results_editor: View<Editor>,
+    explanation_blocks: Vec<BlockId>,
     semantic_state: Option<SemanticState>,
     semantic_permissioned: Option<bool>,
     search_options: SearchOptions,
@@ -196,6 +208,7 @@ impl ProjectSearch {
             excerpts: cx.new_model(|_| MultiBuffer::new(replica_id, capability)),
             pending_search: Default::default(),
             match_ranges: Default::default(),
+            match_explanations: Default::default(),
             active_query: None,
             search_id: 0,
             search_history: SearchHistory::default(),
@@ -211,6 +224,7 @@ impl ProjectSearch {
                 .update(cx, |excerpts, cx| cx.new_model(|cx| excerpts.clone(cx))),
             pending_search: Default::default(),
             match_ranges: self.match_ranges.clone(),
+            match_explanations: self.match_explanations.clone(),
             active_query: self.active_query.clone(),
             search_id: self.search_id,
             search_history: self.search_history.clone(),
@@ -226,6 +240,7 @@ impl ProjectSearch {
         self.search_history.add(query.as_str().to_string());
         self.active_query = Some(query);
         self.match_ranges.clear();
+        self.match_explanations.clear();
         self.pending_search = Some(cx.spawn(|this, mut cx| async move {
             let mut matches = search;
             let this = this.upgrade()?;
@@ -279,6 +294,7 @@ impl ProjectSearch {
         });
         self.search_id += 1;
         self.match_ranges.clear();
+        self.match_explanations.clear();
         self.search_history.add(inputs.as_str().to_string());
         self.no_results = None;
         self.pending_search = Some(cx.spawn(|this, mut cx| async move {
@@ -327,6 +343,126 @@ impl ProjectSearch {
     }
 }
 
+impl ProjectSearch {
+    fn hybrid_search(&mut self, inputs: &SearchInputs, cx: &mut ModelContext<Self>) {
+        let search = SemanticIndex::global(cx).map(|index| {
+            index.update(cx, |semantic_index, cx| {
+                semantic_index.search_project_hybrid(
+                    self.project.clone(),
+                    inputs.as_str().to_owned(),
+                    10,
+                    inputs.files_to_include().to_vec(),
+                    inputs.files_to_exclude().to_vec(),
+                    cx,
+                )
+            })
+        });
+        self.search_id += 1;
+        self.match_ranges.clear();
+        self.match_explanations.clear();
+        self.search_history.add(inputs.as_str().to_string());
+        self.no_results = None;
+        self.pending_search = Some(cx.spawn(|this, mut cx| async move {
+            let results = search?.await.log_err()?;
+
+            this.update(&mut cx, |this, cx| {
+                this.no_results = Some(true);
+                this.excerpts.update(cx, |excerpts, cx| {
+                    excerpts.clear(cx);
+                });
+            })
+            .ok()?;
+            for result in results {
+                let explanation = SharedString::from(result.explanation());
+                let ranges = result
+                    .buffer
+                    .read_with(&cx, |buffer, _| {
+                        term_ranges(buffer, &result.range, &result.matched_terms)
+                    })
+                    .ok()?;
+                let mut match_ranges = this
+                    .update(&mut cx, |this, cx| {
+                        this.no_results = Some(false);
+                        this.excerpts.update(cx, |excerpts, cx| {
+                            excerpts.stream_excerpts_with_context_lines(
+                                result.buffer,
+                                ranges,
+                                3,
+                                cx,
+                            )
+                        })
+                    })
+                    .ok()?;
+                let mut is_first_match = true;
+                while let Some(match_range) = match_ranges.next().await {
+                    this.update(&mut cx, |this, cx| {
+                        if mem::take(&mut is_first_match) && !explanation.is_empty() {
+                            this.match_explanations
+                                .push((match_range.start, explanation.clone()));
+                        }
+                        this.match_ranges.push(match_range);
+                        while let Ok(Some(match_range)) = match_ranges.try_next() {
+                            this.match_ranges.push(match_range);
+                        }
+                        cx.notify();
+                    })
+                    .ok()?;
+                }
+            }
+
+            this.update(&mut cx, |this, cx| {
+                this.pending_search.take();
+                cx.notify();
+            })
+            .ok()?;
+
+            None
+        }));
+        cx.notify();
+    }
+}
+
+/// Returns the ranges within `range` where any of the given lowercase terms occur, ignoring
+/// case, or an empty range at its start if there are none.
+fn term_ranges(
+    buffer: &Buffer,
+    range: &Range<language::Anchor>,
+    terms: &[String],
+) -> Vec<Range<language::Anchor>> {
+    let range = range.to_offset(buffer);
+    let text = buffer
+        .text_for_range(range.clone())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let mut offset_ranges = terms
+        .iter()
+        .flat_map(|term| {
+            text.match_indices(term.as_str())
+                .map(|(ix, term)| range.start + ix..range.start + ix + term.len())
+        })
+        .collect::<Vec<_>>();
+    offset_ranges.sort_unstable_by_key(|range| (range.start, Reverse(range.end)));
+
+    let mut ranges = Vec::<Range<usize>>::new();
+    for offset_range in offset_ranges {
+        match ranges.last_mut() {
+            Some(last) if offset_range.start < last.end => {
+                last.end = last.end.max(offset_range.end);
+            }
+            _ => ranges.push(offset_range),
+        }
+    }
+
+    if ranges.is_empty() {
+        let start = buffer.anchor_before(range.start);
+        return vec![start..start];
+    }
+    ranges
+        .into_iter()
+        .map(|range| buffer.anchor_before(range.start)..buffer.anchor_after(range.end))
+        .collect()
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ViewEvent {
     UpdateTab,
@@ -713,6 +849,7 @@ impl ProjectSearchView {
             model.pending_search = None;
             model.no_results = None;
             model.match_ranges.clear();
+            model.match_explanations.clear();
 
             model.excerpts.update(cx, |excerpts, cx| {
                 excerpts.clear(cx);
@@ -731,7 +868,7 @@ impl ProjectSearchView {
         self.active_match_index = None;
 
         match mode {
-            SearchMode::Semantic => {
+            SearchMode::Semantic | SearchMode::Hybrid => {
                 let has_permission = self.semantic_permissioned(cx);
                 self.active_match_index = None;
                 cx.spawn(|this, mut cx| async move {
@@ -766,7 +903,7 @@ impl ProjectSearchView {
                         } else {
                             this.update(&mut cx, |this, cx| {
                                 this.semantic_permissioned = Some(false);
-                                debug_assert_ne!(previous_mode, SearchMode::Semantic, "Tried to re-enable semantic search mode after user modal was rejected");
+                                debug_assert!(!previous_mode.uses_semantic_index(), "Tried to re-enable semantic search mode after user modal was rejected");
                                 this.activate_search_mode(previous_mode, cx);
                             })?;
                             return anyhow::Ok(());
@@ -952,6 +1089,7 @@ impl ProjectSearchView {
             model,
             query_editor,
             results_editor,
+            explanation_blocks: Vec::new(),
             semantic_state: None,
             semantic_permissioned: None,
             search_options: options,
@@ -1118,6 +1256,14 @@ impl ProjectSearchView {
                     }
                 }
             }
+            SearchMode::Hybrid => {
+                if self.semantic_state.is_some() {
+                    if let Some(query) = self.build_search_query(cx) {
+                        self.model
+                            .update(cx, |model, cx| model.hybrid_search(query.as_inner(), cx));
+                    }
+                }
+            }
 
             _ => {
                 if let Some(query) = self.build_search_query(cx) {
@@ -1283,6 +1429,33 @@ impl ProjectSearchView {
 
     fn model_changed(&mut self, cx: &mut ViewContext<Self>) {
         let match_ranges = self.model.read(cx).match_ranges.clone();
+        let match_explanations = self.model.read(cx).match_explanations.clone();
+        self.results_editor.update(cx, |editor, cx| {
+            let explanation_blocks = mem::take(&mut self.explanation_blocks);
+            editor.remove_blocks(explanation_blocks.into_iter().collect(), None, cx);
+            self.explanation_blocks = editor.insert_blocks(
+                match_explanations
+                    .into_iter()
+                    .map(|(position, explanation)| BlockProperties {
+                        position,
+                        height: 1,
+                        style: BlockStyle::Fixed,
+                        render: Arc::new(move |cx: &mut BlockContext| {
+                            h_flex()
+                                .pl(cx.gutter_width)
+                                .child(
+                                    Label::new(explanation.clone())
+                                        .size(LabelSize::Small)
+                                        .color(Color::Muted),
+                                )
+                                .into_any_element()
+                        }),
+                        disposition: BlockDisposition::Above,
+                    }),
+                None,
+                cx,
+            );
+        });
         if match_ranges.is_empty() {
             self.active_match_index = None;
         } else {
@@ -1335,7 +1508,8 @@ impl ProjectSearchView {
     fn landing_text_minor(&self) -> SharedString {
         match self.current_mode {
             SearchMode::Text | SearchMode::Regex => "Include/exclude specific paths with the filter option. Matching exact word and/or casing is available too.".into(),
-            SearchMode::Semantic => "\nSimply explain the code you are looking to find. ex. 'prompt user for permissions to index their project'".into()
+            SearchMode::Semantic => "\nSimply explain the code you are looking to find. ex. 'prompt user for permissions to index their project'".into(),
+            SearchMode::Hybrid => "\nDescribe the code you are looking for, mentioning any identifiers you know. ex. 'where is index_project called'".into(),
         }
     }
     fn border_color_for(&self, panel: InputPanel, cx: &WindowContext) -> Hsla {
@@ -1696,7 +1870,7 @@ impl Render for ProjectSearchBar {
                                         .unwrap_or_default(),
                                 ),
                         )
-                        .when(search.current_mode != SearchMode::Semantic, |this| {
+                        .when(!search.current_mode.uses_semantic_index(), |this| {
                             this.child(
                                 IconButton::new(
                                     "project-search-case-sensitive",
@@ -1794,6 +1968,23 @@ impl Render for ProjectSearchBar {
                                             cx,
                                         )
                                     })
+                                    .middle(),
+                            )
+                            .child(
+                                ToggleButton::new("project-search-hybrid-button", "Hybrid")
+                                    .style(ButtonStyle::Filled)
+                                    .size(ButtonSize::Large)
+                                    .selected(search.current_mode == SearchMode::Hybrid)
+                                    .on_click(cx.listener(|this, _, cx| {
+                                        this.activate_search_mode(SearchMode::Hybrid, cx)
+                                    }))
+                                    .tooltip(|cx| {
+                                        Tooltip::for_action(
+                                            "Toggle hybrid search",
+                                            &ActivateHybridMode,
+                                            cx,
+                                        )
+                                    })
                                     .last(),
                             )
                         }),
@@ -1900,6 +2091,9 @@ impl Render for ProjectSearchBar {
             .on_action(cx.listener(|this, _: &ActivateSemanticMode, cx| {
                 this.activate_search_mode(SearchMode::Semantic, cx)
             }))
+            .on_action(cx.listener(|this, _: &ActivateHybridMode, cx| {
+                this.activate_search_mode(SearchMode::Hybrid, cx)
+            }))
             .capture_action(cx.listener(|this, action, cx| {
                 this.tab(action, cx);
                 cx.stop_propagation();
@@ -1912,7 +2106,7 @@ impl Render for ProjectSearchBar {
             .on_action(cx.listener(|this, action, cx| {
                 this.cycle_mode(action, cx);
             }))
-            .when(search.current_mode != SearchMode::Semantic, |this| {
+            .when(!search.current_mode.uses_semantic_index(), |this| {
                 this.on_action(cx.listener(|this, action, cx| {
                     this.toggle_replace(action, cx);
                 }))
@@ -1969,7 +2163,7 @@ impl Render for ProjectSearchBar {
                                 .border_color(search.border_color_for(InputPanel::Include, cx))
                                 .rounded_lg()
                                 .child(self.render_text_input(&search.included_files_editor, cx))
-                                .when(search.current_mode != SearchMode::Semantic, |this| {
+                                .when(!search.current_mode.uses_semantic_index(), |this| {
                                     this.child(
                                         SearchOptions::INCLUDE_IGNORED.as_button(
                                             search
@@ -2014,7 +2208,7 @@ impl ToolbarItemView for ProjectSearchBar {
         self.active_project_search = None;
         if let Some(search) = active_pane_item.and_then(|i| i.downcast::<ProjectSearchView>()) {
             search.update(cx, |search, cx| {
-                if search.current_mode == SearchMode::Semantic {
+                if search.current_mode.uses_semantic_index() {
                     search.index_project(cx);
                 }
             });
@@ -35,6 +35,7 @@ actions!(
         ActivateTextMode,
         ActivateSemanticMode,
         ActivateRegexMode,
+        ActivateHybridMode,
         ReplaceAll,
         ReplaceNext,
     ]
@@ -0,0 +1,159 @@
+//! Compares `VectorDatabase::top_k_search`, which scores every embedding of the searched
+//! files, with searching the approximate index and re-ranking its candidates with
+//! `VectorDatabase::rerank_spans`, which is what semantic search does for large projects.
+//!
+//! Embeddings are drawn around random cluster centers, like those of real code, which
+//! are far from uniformly distributed.
+//!
+//! Run with `cargo bench -p semantic_index --bench top_k_search`.
+
+use ai::embedding::Embedding;
+use anyhow::Result;
+use gpui::BackgroundExecutor;
+use project::RealFs;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use semantic_index::{
+    ann_index::AnnIndex,
+    db::VectorDatabase,
+    parsing::{Span, SpanDigest},
+};
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+const DIMENSIONS: usize = 256;
+const QUERY_COUNT: usize = 100;
+const LIMIT: usize = 10;
+const CANDIDATES: usize = LIMIT * 4;
+const EF: usize = 128;
+const SPANS_PER_CLUSTER: usize = 100;
+const SPANS_PER_FILE: usize = 50;
+
+fn main() {
+    gpui::run_test(
+        1,
+        0,
+        &mut |dispatcher, _| {
+            dispatcher.allow_parking();
+            let executor = BackgroundExecutor::new(Arc::new(dispatcher));
+            executor.block(run(executor.clone())).unwrap();
+        },
+        None,
+    );
+}
+
+async fn run(executor: BackgroundExecutor) -> Result<()> {
+    let mut rng = StdRng::seed_from_u64(0);
+    for span_count in [10_000, 50_000, 200_000] {
+        let centers = (0..span_count / SPANS_PER_CLUSTER)
+            .map(|_| random_embedding(&mut rng, DIMENSIONS))
+            .collect::<Vec<_>>();
+        let embeddings = (0..span_count)
+            .map(|_| clustered_embedding(&mut rng, &centers))
+            .collect::<Vec<_>>();
+        let queries = (0..QUERY_COUNT)
+            .map(|_| clustered_embedding(&mut rng, &centers))
+            .collect::<Vec<_>>();
+
+        let dir = tempfile::tempdir()?;
+        let db = VectorDatabase::new(
+            Arc::new(RealFs::default()),
+            dir.path().join("embeddings_db").into(),
+            "bench".into(),
+            executor.clone(),
+        )
+        .await?;
+        let worktree_id = db.find_or_create_worktree(dir.path().into()).await?;
+
+        let mut index = AnnIndex::new("bench".into());
+        let mut build_time = Duration::ZERO;
+        for (file_ix, file_embeddings) in embeddings.chunks(SPANS_PER_FILE).enumerate() {
+            let spans = file_embeddings
+                .iter()
+                .enumerate()
+                .map(|(span_ix, embedding)| Span {
+                    name: String::new(),
+                    range: 0..0,
+                    content: String::new(),
+                    embedding: Some(embedding.clone()),
+                    digest: SpanDigest::from(format!("{file_ix}:{span_ix}").as_str()),
+                    token_count: 0,
+                })
+                .collect();
+            let path = Path::new(&format!("file_{file_ix}.rs")).into();
+            let changes = db
+                .insert_file(worktree_id, path, SystemTime::now(), spans)
+                .await?;
+
+            let t0 = Instant::now();
+            index.apply(&changes);
+            build_time += t0.elapsed();
+        }
+        let file_ids = db
+            .retrieve_included_file_ids(&[worktree_id], &[], &[])
+            .await?;
+
+        let mut brute_force_time = Duration::ZERO;
+        let mut ann_time = Duration::ZERO;
+        let mut found = 0;
+        for query in &queries {
+            let t0 = Instant::now();
+            let expected = db.top_k_search(query, LIMIT, &file_ids).await?;
+            brute_force_time += t0.elapsed();
+
+            let t0 = Instant::now();
+            let candidates = index
+                .search(query, CANDIDATES, EF)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>();
+            let actual = db
+                .rerank_spans(query, LIMIT, &candidates, &file_ids)
+                .await?;
+            ann_time += t0.elapsed();
+
+            found += actual
+                .iter()
+                .filter(|(id, _)| expected.iter().any(|(expected_id, _)| expected_id == id))
+                .count();
+        }
+
+        println!(
+            "{span_count} spans: built index in {build_time:?}, top_k_search {:?}/query, \
+             approximate {:?}/query, recall@{LIMIT} {:.3}",
+            brute_force_time / QUERY_COUNT as u32,
+            ann_time / QUERY_COUNT as u32,
+            found as f64 / (QUERY_COUNT * LIMIT) as f64,
+        );
+    }
+    Ok(())
+}
+
+fn random_embedding(rng: &mut StdRng, dimensions: usize) -> Embedding {
+    let vector = (0..dimensions)
+        .map(|_| rng.gen_range(-1.0..1.0))
+        .collect::<Vec<f32>>();
+    normalize(vector)
+}
+
+fn clustered_embedding(rng: &mut StdRng, centers: &[Embedding]) -> Embedding {
+    let center = &centers[rng.gen_range(0..centers.len())];
+    let noise = random_embedding(rng, center.0.len());
+    let vector = center
+        .0
+        .iter()
+        .zip(&noise.0)
+        .map(|(center, noise)| center + 0.5 * noise)
+        .collect::<Vec<f32>>();
+    normalize(vector)
+}
+
+fn normalize(mut vector: Vec<f32>) -> Embedding {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    for x in &mut vector {
+        *x /= norm;
+    }
+    Embedding(vector)
+}
@@ -0,0 +1,709 @@
+use ai::embedding::Embedding;
+use anyhow::{anyhow, Context, Result};
+use collections::{HashMap, HashSet};
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, collections::BinaryHeap, path::Path, sync::Arc};
+
+/// The maximum number of neighbors of a node on the upper layers.
+const MAX_NEIGHBORS: usize = 16;
+/// The maximum number of neighbors of a node on the bottom layer, which holds every node.
+const MAX_BOTTOM_NEIGHBORS: usize = 2 * MAX_NEIGHBORS;
+/// The number of candidates considered when linking a new node.
+const EF_CONSTRUCTION: usize = 100;
+/// Bumped whenever the serialized format changes, discarding indices written before.
+const FORMAT_VERSION: u32 = 2;
+
+/// An approximate nearest neighbour index over span embeddings, using a hierarchical
+/// navigable small world graph (HNSW).
+///
+/// Every span is a node on the bottom layer and, with exponentially decreasing probability,
+/// on the layers above, which act as express lanes towards the query. Searching visits a
+/// few hundred nodes rather than every stored embedding, at the cost of occasionally
+/// missing a close match, so callers should re-rank the candidates it returns exactly.
+///
+/// The full embeddings stay in the database. The index only keeps a copy quantized to
+/// eight bits per dimension, which takes a quarter of the memory and is precise enough
+/// to walk the graph.
+///
+/// Removed spans are only marked as deleted, because unlinking them would degrade the
+/// graph. The index should be rebuilt once [`AnnIndex::needs_compaction`] returns true.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AnnIndex {
+    version: u32,
+    embedding_model: String,
+    dimensions: Option<usize>,
+    nodes: Vec<Node>,
+    /// The quantized vectors of all nodes, `dimensions` values per node.
+    vectors: Vec<i8>,
+    entry_point: Option<u32>,
+    deleted_count: usize,
+    rng_state: u64,
+    #[serde(skip)]
+    nodes_by_id: HashMap<i64, u32>,
+}
+
+/// Spans removed from and added to the database by a single write.
+#[derive(Clone, Debug, Default)]
+pub struct SpanChanges {
+    pub removed: Vec<i64>,
+    pub inserted: Vec<(i64, Embedding)>,
+}
+
+/// Below this many spans, searching every embedding is fast enough and always exact, so
+/// no index is built.
+pub const MIN_SPANS_FOR_ANN_SEARCH: usize = 10_000;
+
+/// The index shared between the tasks writing spans and the searches reading them.
+///
+/// Readers take a snapshot of the index and release the lock before using it, so that
+/// searching, saving and compacting never block writers. A write only copies the index
+/// if a snapshot of it is still in use.
+pub(crate) enum AnnIndexState {
+    /// The index is being loaded or built. Changes are applied once it is ready.
+    Loading(Vec<SpanChanges>),
+    /// There are too few spans to need an index, so searches score every embedding.
+    Disabled {
+        embedding_model: String,
+        span_count: usize,
+    },
+    Ready {
+        index: Arc<AnnIndex>,
+        is_dirty: bool,
+        /// The changes applied since a compaction started, which are replayed on the
+        /// compacted index.
+        changes_during_compaction: Option<Vec<SpanChanges>>,
+    },
+}
+
+/// Work on the index that should happen without holding the lock on its state.
+pub(crate) enum AnnIndexUpdate {
+    /// Enough spans were added that the index should be built from the database, and
+    /// passed to [`AnnIndexState::finish_loading`].
+    Build { embedding_model: String },
+    /// So many spans were removed that this snapshot should be compacted, and passed to
+    /// [`AnnIndexState::finish_compaction`].
+    Compact(Arc<AnnIndex>),
+}
+
+impl AnnIndexState {
+    pub fn apply(&mut self, changes: SpanChanges) -> Option<AnnIndexUpdate> {
+        match self {
+            AnnIndexState::Loading(pending_changes) => pending_changes.push(changes),
+            AnnIndexState::Disabled {
+                embedding_model,
+                span_count,
+            } => {
+                *span_count =
+                    (*span_count + changes.inserted.len()).saturating_sub(changes.removed.len());
+                if *span_count >= MIN_SPANS_FOR_ANN_SEARCH {
+                    let embedding_model = std::mem::take(embedding_model);
+                    *self = AnnIndexState::Loading(Vec::new());
+                    return Some(AnnIndexUpdate::Build { embedding_model });
+                }
+            }
+            AnnIndexState::Ready {
+                index,
+                is_dirty,
+                changes_during_compaction,
+            } => {
+                Arc::make_mut(index).apply(&changes);
+                *is_dirty = true;
+                if let Some(changes_during_compaction) = changes_during_compaction {
+                    changes_during_compaction.push(changes);
+                } else if index.needs_compaction() {
+                    *changes_during_compaction = Some(Vec::new());
+                    return Some(AnnIndexUpdate::Compact(index.clone()));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn finish_loading(&mut self, mut index: AnnIndex, mut is_dirty: bool) {
+        if let AnnIndexState::Loading(pending_changes) = self {
+            for changes in pending_changes.drain(..) {
+                index.apply(&changes);
+                is_dirty = true;
+            }
+        }
+        *self = AnnIndexState::Ready {
+            index: Arc::new(index),
+            is_dirty,
+            changes_during_compaction: None,
+        };
+    }
+
+    /// Stops maintaining an index until the database holds enough spans to need one.
+    pub fn disable(&mut self, embedding_model: String, span_count: usize) {
+        *self = AnnIndexState::Disabled {
+            embedding_model,
+            span_count,
+        };
+    }
+
+    /// Swaps in an index compacted from a snapshot returned by [`AnnIndexState::apply`].
+    pub fn finish_compaction(&mut self, mut compacted: AnnIndex) {
+        if let AnnIndexState::Ready {
+            index,
+            is_dirty,
+            changes_during_compaction,
+        } = self
+        {
+            for changes in changes_during_compaction.take().unwrap_or_default() {
+                compacted.apply(&changes);
+            }
+            *index = Arc::new(compacted);
+            *is_dirty = true;
+        }
+    }
+
+    pub fn snapshot(&self) -> Option<Arc<AnnIndex>> {
+        match self {
+            AnnIndexState::Ready { index, .. } => Some(index.clone()),
+            AnnIndexState::Loading(_) | AnnIndexState::Disabled { .. } => None,
+        }
+    }
+
+    /// Returns a snapshot of the index to save, if it changed since it was last saved.
+    pub fn take_unsaved_snapshot(&mut self) -> Option<Arc<AnnIndex>> {
+        match self {
+            AnnIndexState::Ready {
+                index, is_dirty, ..
+            } if *is_dirty => {
+                *is_dirty = false;
+                Some(index.clone())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Node {
+    id: i64,
+    /// The factor by which the node's quantized vector is scaled back.
+    scale: f32,
+    /// The node's neighbors on each layer it belongs to, starting with the bottom one.
+    neighbors: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+/// A vector quantized to eight bits per dimension.
+struct Quantized {
+    values: Vec<i8>,
+    scale: f32,
+}
+
+impl Quantized {
+    fn new(vector: &[f32]) -> Self {
+        let max = vector.iter().fold(0f32, |max, x| max.max(x.abs()));
+        if max == 0. {
+            return Self {
+                values: vec![0; vector.len()],
+                scale: 0.,
+            };
+        }
+        let scale = max / i8::MAX as f32;
+        Self {
+            values: vector.iter().map(|x| (x / scale).round() as i8).collect(),
+            scale,
+        }
+    }
+}
+
+impl AnnIndex {
+    pub fn new(embedding_model: String) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            embedding_model,
+            dimensions: None,
+            nodes: Vec::new(),
+            vectors: Vec::new(),
+            entry_point: None,
+            deleted_count: 0,
+            rng_state: 0x853c49e6748fea9b,
+            nodes_by_id: HashMap::default(),
+        }
+    }
+
+    /// Loads an index written by [`AnnIndex::save`], if it was built for the given model.
+    pub fn load(path: &Path, embedding_model: &str) -> Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let mut this: Self = bincode::deserialize(&bytes).context("failed to decode index")?;
+        if this.version != FORMAT_VERSION {
+            return Err(anyhow!("index has an outdated format"));
+        }
+        if this.embedding_model != embedding_model {
+            return Err(anyhow!(
+                "index was built for {:?}, not {:?}",
+                this.embedding_model,
+                embedding_model
+            ));
+        }
+        this.nodes_by_id = this
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| !node.deleted)
+            .map(|(ix, node)| (node.id, ix as u32))
+            .collect();
+        Ok(this)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let bytes = bincode::serialize(self)?;
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, bytes)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// The number of spans in the index, excluding removed ones.
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.deleted_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether so many spans were removed that the index should be rebuilt.
+    pub fn needs_compaction(&self) -> bool {
+        self.deleted_count > 1000 && self.deleted_count > self.nodes.len() / 2
+    }
+
+    /// Returns a new index containing only the spans that weren't removed.
+    pub fn compact(&self) -> Self {
+        let mut compacted = Self::new(self.embedding_model.clone());
+        compacted.dimensions = self.dimensions;
+        for (ix, node) in self.nodes.iter().enumerate() {
+            if !node.deleted {
+                let vector = Quantized {
+                    values: self.vector(ix as u32).to_vec(),
+                    scale: node.scale,
+                };
+                compacted.insert_quantized(node.id, vector);
+            }
+        }
+        compacted
+    }
+
+    /// Adds the embedding of the span with the given id, replacing any embedding that was
+    /// added for it before.
+    pub fn insert(&mut self, id: i64, embedding: &Embedding) {
+        match self.dimensions {
+            Some(dimensions) if dimensions != embedding.0.len() => {
+                log::error!(
+                    "embedding has {} dimensions, but the index expects {}",
+                    embedding.0.len(),
+                    dimensions
+                );
+                return;
+            }
+            Some(_) => {}
+            None => self.dimensions = Some(embedding.0.len()),
+        }
+        self.insert_quantized(id, Quantized::new(&embedding.0));
+    }
+
+    pub fn apply(&mut self, changes: &SpanChanges) {
+        for id in &changes.removed {
+            self.remove(*id);
+        }
+        for (id, embedding) in &changes.inserted {
+            self.insert(*id, embedding);
+        }
+    }
+
+    pub fn remove(&mut self, id: i64) {
+        if let Some(ix) = self.nodes_by_id.remove(&id) {
+            self.nodes[ix as usize].deleted = true;
+            self.deleted_count += 1;
+        }
+    }
+
+    /// Returns the ids of up to `limit` spans whose embeddings are most similar to the
+    /// query, and their approximate similarity, most similar first. Larger values of `ef`
+    /// visit more nodes, improving recall at the expense of speed.
+    pub fn search(&self, query: &Embedding, limit: usize, ef: usize) -> Vec<(i64, f32)> {
+        let Some(entry_point) = self.entry_point else {
+            return Vec::new();
+        };
+        if self.dimensions != Some(query.0.len()) {
+            return Vec::new();
+        }
+
+        let query = Quantized::new(&query.0);
+        let top_layer = self.nodes[entry_point as usize].neighbors.len() - 1;
+        let mut entry_point = entry_point;
+        for layer in (1..=top_layer).rev() {
+            entry_point = self.search_layer(&query, &[entry_point], 1, layer)[0].1;
+        }
+
+        // Removed nodes are still visited, so look a little further to make up for them.
+        let ef = ef.max(limit) + limit.min(self.deleted_count);
+        self.search_layer(&query, &[entry_point], ef, 0)
+            .into_iter()
+            .filter(|(_, ix)| !self.nodes[*ix as usize].deleted)
+            .take(limit)
+            .map(|(similarity, ix)| (self.nodes[ix as usize].id, similarity.0))
+            .collect()
+    }
+
+    fn vector(&self, ix: u32) -> &[i8] {
+        let dimensions = self.dimensions.unwrap_or(0);
+        let start = ix as usize * dimensions;
+        &self.vectors[start..start + dimensions]
+    }
+
+    fn similarity(&self, query: &Quantized, ix: u32) -> f32 {
+        dot(&query.values, self.vector(ix)) as f32 * query.scale * self.nodes[ix as usize].scale
+    }
+
+    fn similarity_between(&self, a: u32, b: u32) -> f32 {
+        dot(self.vector(a), self.vector(b)) as f32
+            * self.nodes[a as usize].scale
+            * self.nodes[b as usize].scale
+    }
+
+    fn insert_quantized(&mut self, id: i64, vector: Quantized) {
+        self.remove(id);
+        let ix = self.nodes.len() as u32;
+        let level = self.random_level();
+        self.nodes.push(Node {
+            id,
+            scale: vector.scale,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.vectors.extend_from_slice(&vector.values);
+        self.nodes_by_id.insert(id, ix);
+
+        let Some(mut entry_point) = self.entry_point else {
+            self.entry_point = Some(ix);
+            return;
+        };
+
+        let top_layer = self.nodes[entry_point as usize].neighbors.len() - 1;
+        for layer in (level + 1..=top_layer).rev() {
+            entry_point = self.search_layer(&vector, &[entry_point], 1, layer)[0].1;
+        }
+
+        let mut entry_points = vec![entry_point];
+        for layer in (0..=level.min(top_layer)).rev() {
+            let candidates = self.search_layer(&vector, &entry_points, EF_CONSTRUCTION, layer);
+            let max_neighbors = Self::max_neighbors(layer);
+            let neighbors = self.select_neighbors(&candidates, max_neighbors);
+            for &neighbor in &neighbors {
+                self.link(neighbor, ix, layer);
+            }
+            self.nodes[ix as usize].neighbors[layer] = neighbors;
+            entry_points = candidates.into_iter().map(|(_, ix)| ix).collect();
+        }
+
+        if level > top_layer {
+            self.entry_point = Some(ix);
+        }
+    }
+
+    fn max_neighbors(layer: usize) -> usize {
+        if layer == 0 {
+            MAX_BOTTOM_NEIGHBORS
+        } else {
+            MAX_NEIGHBORS
+        }
+    }
+
+    /// Adds an edge from `from` to `to`, dropping `from`'s least similar neighbor if it
+    /// has too many.
+    fn link(&mut self, from: u32, to: u32, layer: usize) {
+        let max_neighbors = Self::max_neighbors(layer);
+        let neighbors = &mut self.nodes[from as usize].neighbors[layer];
+        neighbors.push(to);
+        if neighbors.len() <= max_neighbors {
+            return;
+        }
+
+        let mut candidates = self.nodes[from as usize].neighbors[layer]
+            .iter()
+            .map(|&neighbor| {
+                let similarity = self.similarity_between(from, neighbor);
+                (OrderedFloat(similarity), neighbor)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by(|a, b| b.cmp(a));
+        let neighbors = self.select_neighbors(&candidates, max_neighbors);
+        self.nodes[from as usize].neighbors[layer] = neighbors;
+    }
+
+    /// Picks neighbors among candidates sorted by decreasing similarity, preferring ones
+    /// that aren't closer to an already selected neighbor than to the node itself, so that
+    /// edges point in diverse directions. Remaining slots are filled with the most similar
+    /// of the skipped candidates.
+    fn select_neighbors(
+        &self,
+        candidates: &[(OrderedFloat<f32>, u32)],
+        max_neighbors: usize,
+    ) -> Vec<u32> {
+        let mut selected = Vec::with_capacity(max_neighbors);
+        let mut skipped = Vec::new();
+        for &(similarity, candidate) in candidates {
+            if selected.len() == max_neighbors {
+                break;
+            }
+            let diverse = selected
+                .iter()
+                .all(|&neighbor: &u32| self.similarity_between(candidate, neighbor) < similarity.0);
+            if diverse {
+                selected.push(candidate);
+            } else {
+                skipped.push(candidate);
+            }
+        }
+        let remaining = max_neighbors - selected.len();
+        selected.extend(skipped.into_iter().take(remaining));
+        selected
+    }
+
+    /// Returns up to `ef` nodes of the given layer that are most similar to the query, most
+    /// similar first, by greedily walking the graph from the entry points.
+    fn search_layer(
+        &self,
+        query: &Quantized,
+        entry_points: &[u32],
+        ef: usize,
+        layer: usize,
+    ) -> Vec<(OrderedFloat<f32>, u32)> {
+        let mut visited = HashSet::default();
+        let mut candidates = BinaryHeap::new();
+        let mut results = BinaryHeap::new();
+        for &entry_point in entry_points {
+            if visited.insert(entry_point) {
+                let similarity = OrderedFloat(self.similarity(query, entry_point));
+                candidates.push((similarity, entry_point));
+                results.push(Reverse((similarity, entry_point)));
+            }
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some((similarity, candidate)) = candidates.pop() {
+            let Reverse((worst_similarity, _)) = *results.peek().unwrap();
+            if similarity < worst_similarity && results.len() >= ef {
+                break;
+            }
+
+            for &neighbor in &self.nodes[candidate as usize].neighbors[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+
+                let similarity = OrderedFloat(self.similarity(query, neighbor));
+                let Reverse((worst_similarity, _)) = *results.peek().unwrap();
+                if results.len() < ef || similarity > worst_similarity {
+                    candidates.push((similarity, neighbor));
+                    results.push(Reverse((similarity, neighbor)));
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        let mut results = results
+            .into_iter()
+            .map(|Reverse(result)| result)
+            .collect::<Vec<_>>();
+        results.sort_unstable_by(|a, b| b.cmp(a));
+        results
+    }
+
+    /// Draws the highest layer of a new node from a geometric distribution, so that each
+    /// layer holds about `1 / MAX_NEIGHBORS` of the nodes of the layer below.
+    fn random_level(&mut self) -> usize {
+        // SplitMix64, which keeps the index deterministic and its state serializable.
+        self.rng_state = self.rng_state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+
+        let uniform = ((z >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        let level = -uniform.ln() / (MAX_NEIGHBORS as f64).ln();
+        (level as usize).min(16)
+    }
+}
+
+/// Computes the dot product of two quantized vectors with several independent sums,
+/// which lets the compiler vectorize it.
+fn dot(a: &[i8], b: &[i8]) -> i32 {
+    const LANES: usize = 16;
+    let mut sums = [0i32; LANES];
+    for (a, b) in a.chunks_exact(LANES).zip(b.chunks_exact(LANES)) {
+        for lane in 0..LANES {
+            sums[lane] += a[lane] as i32 * b[lane] as i32;
+        }
+    }
+    let remainder = a.len() - a.len() % LANES;
+    let tail = a[remainder..]
+        .iter()
+        .zip(&b[remainder..])
+        .map(|(a, b)| *a as i32 * *b as i32)
+        .sum::<i32>();
+    sums.iter().sum::<i32>() + tail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn random_embedding(rng: &mut StdRng, dimensions: usize) -> Embedding {
+        let mut vector = (0..dimensions)
+            .map(|_| rng.gen_range(-1.0..1.0))
+            .collect::<Vec<f32>>();
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        for x in &mut vector {
+            *x /= norm;
+        }
+        Embedding(vector)
+    }
+
+    fn exact_top_k(embeddings: &[(i64, Embedding)], query: &Embedding, k: usize) -> Vec<i64> {
+        let mut scored = embeddings
+            .iter()
+            .map(|(id, embedding)| (query.similarity(embedding), *id))
+            .collect::<Vec<_>>();
+        scored.sort_unstable_by(|a, b| b.cmp(a));
+        scored.into_iter().take(k).map(|(_, id)| id).collect()
+    }
+
+    #[test]
+    fn test_ann_index_recall() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let embeddings = (0..2000)
+            .map(|id| (id, random_embedding(&mut rng, 32)))
+            .collect::<Vec<_>>();
+        let mut index = AnnIndex::new("model".into());
+        for (id, embedding) in &embeddings {
+            index.insert(*id, embedding);
+        }
+        assert_eq!(index.len(), embeddings.len());
+
+        let mut found = 0;
+        for _ in 0..50 {
+            let query = random_embedding(&mut rng, 32);
+            let expected = exact_top_k(&embeddings, &query, 10);
+            let actual = index.search(&query, 10, 64);
+            assert_eq!(actual.len(), 10);
+            found += actual
+                .iter()
+                .filter(|(id, _)| expected.contains(id))
+                .count();
+        }
+        assert!(found >= 450, "recall too low: {found}/500");
+    }
+
+    #[test]
+    fn test_ann_index_updates_and_persistence() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut index = AnnIndex::new("model".into());
+        let embeddings = (0..100)
+            .map(|id| (id, random_embedding(&mut rng, 8)))
+            .collect::<Vec<_>>();
+        for (id, embedding) in &embeddings {
+            index.insert(*id, embedding);
+        }
+
+        // Removed and replaced spans are no longer returned.
+        let query = embeddings[3].1.clone();
+        assert_eq!(index.search(&query, 1, 16)[0].0, 3);
+        index.remove(3);
+        assert!(index
+            .search(&query, 100, 100)
+            .iter()
+            .all(|(id, _)| *id != 3));
+        index.insert(4, &query);
+        assert_eq!(index.search(&query, 1, 16)[0].0, 4);
+        assert_eq!(index.len(), 99);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.hnsw");
+        index.save(&path).unwrap();
+        assert!(AnnIndex::load(&path, "other-model").is_err());
+        let loaded = AnnIndex::load(&path, "model").unwrap();
+        assert_eq!(loaded.len(), 99);
+        assert_eq!(loaded.search(&query, 5, 16), index.search(&query, 5, 16));
+
+        let compacted = loaded.compact();
+        assert_eq!(compacted.len(), 99);
+        assert_eq!(compacted.nodes.len(), 99);
+        assert_eq!(compacted.vectors.len(), 99 * 8);
+        assert_eq!(compacted.search(&query, 1, 16)[0].0, 4);
+    }
+
+    #[test]
+    fn test_ann_index_state() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut embeddings = (0..MIN_SPANS_FOR_ANN_SEARCH as i64)
+            .map(|id| (id, random_embedding(&mut rng, 8)))
+            .collect::<Vec<_>>();
+
+        // No index is maintained until there are enough spans to need one.
+        let mut state = AnnIndexState::Loading(Vec::new());
+        state.disable("model".into(), 0);
+        let last_span = embeddings.pop().unwrap();
+        assert!(state
+            .apply(SpanChanges {
+                removed: Vec::new(),
+                inserted: embeddings.clone(),
+            })
+            .is_none());
+        assert!(state.snapshot().is_none());
+        let Some(AnnIndexUpdate::Build { embedding_model }) = state.apply(SpanChanges {
+            removed: Vec::new(),
+            inserted: vec![last_span.clone()],
+        }) else {
+            panic!("expected the index to be built");
+        };
+        // Only index a part of the spans, to keep the test fast.
+        let mut index = AnnIndex::new(embedding_model);
+        for (id, embedding) in &embeddings[..2000] {
+            index.insert(*id, embedding);
+        }
+        state.apply(SpanChanges {
+            removed: Vec::new(),
+            inserted: vec![last_span],
+        });
+        state.finish_loading(index, false);
+        assert_eq!(state.snapshot().unwrap().len(), 2001);
+
+        // Removing most spans hands out a snapshot to compact, while further changes
+        // are applied to the live index and replayed on the compacted one.
+        let Some(AnnIndexUpdate::Compact(snapshot)) = state.apply(SpanChanges {
+            removed: (0..1500).collect(),
+            inserted: Vec::new(),
+        }) else {
+            panic!("expected the index to be compacted");
+        };
+        let replacement = random_embedding(&mut rng, 8);
+        assert!(state
+            .apply(SpanChanges {
+                removed: vec![1500],
+                inserted: vec![(20000, replacement.clone())],
+            })
+            .is_none());
+        assert_eq!(snapshot.len(), 501);
+        assert_eq!(state.snapshot().unwrap().len(), 501);
+
+        state.finish_compaction(snapshot.compact());
+        let index = state.snapshot().unwrap();
+        assert_eq!(index.len(), 501);
+        assert_eq!(index.nodes.len(), 502);
+        assert_eq!(index.search(&replacement, 1, 64)[0].0, 20000);
+        assert!(state.take_unsaved_snapshot().is_some());
+        assert!(state.take_unsaved_snapshot().is_none());
+    }
+}
@@ -1,4 +1,6 @@
 use crate::{
+    ann_index::SpanChanges,
+    lexical::{self, Bm25},
     parsing::{Span, SpanDigest},
     SEMANTIC_INDEX_VERSION,
 };
@@ -14,6 +16,7 @@ use rpc::proto::Timestamp;
 use rusqlite::params;
 use rusqlite::types::Value;
 use std::{
+    cmp::Reverse,
     future::Future,
     ops::Range,
     path::{Path, PathBuf},
@@ -45,9 +48,12 @@ pub struct VectorDatabase {
 }
 
 impl VectorDatabase {
+    /// Opens the database at `path`, clearing it if it was created by an older version or
+    /// holds embeddings produced by a model other than `embedding_model`.
     pub async fn new(
         fs: Arc<dyn Fs>,
         path: Arc<Path>,
+        embedding_model: String,
         executor: BackgroundExecutor,
     ) -> Result<Self> {
         if let Some(db_directory) = path.parent() {
@@ -81,7 +87,7 @@ impl VectorDatabase {
             transactions: transactions_tx,
             path,
         };
-        this.initialize_database().await?;
+        this.initialize_database(embedding_model).await?;
         Ok(this)
     }
 
@@ -118,17 +124,32 @@ impl VectorDatabase {
         }
     }
 
-    fn initialize_database(&self) -> impl Future<Output = Result<()>> {
-        self.transact(|db| {
+    fn initialize_database(&self, embedding_model: String) -> impl Future<Output = Result<()>> {
+        self.transact(move |db| {
             rusqlite::vtab::array::load_module(&db)?;
 
-            // Delete existing tables, if SEMANTIC_INDEX_VERSION is bumped
-            let version_query = db.prepare("SELECT version from semantic_index_config");
-            let version = version_query
-                .and_then(|mut query| query.query_row([], |row| Ok(row.get::<_, i64>(0)?)));
-            if version.map_or(false, |version| version == SEMANTIC_INDEX_VERSION as i64) {
-                log::trace!("vector database schema up to date");
-                return Ok(());
+            // Delete existing tables, if SEMANTIC_INDEX_VERSION is bumped or the embedding
+            // model changed, so that every file gets re-indexed.
+            let config_query =
+                db.prepare("SELECT version, embedding_model from semantic_index_config");
+            let config = config_query.and_then(|mut query| {
+                query.query_row([], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })
+            });
+            match config {
+                Ok((version, model))
+                    if version == SEMANTIC_INDEX_VERSION as i64 && model == embedding_model =>
+                {
+                    log::trace!("vector database schema up to date");
+                    return Ok(());
+                }
+                Ok((version, model)) if version == SEMANTIC_INDEX_VERSION as i64 => {
+                    log::info!(
+                        "embedding model changed from {model:?} to {embedding_model:?}. re-indexing..."
+                    );
+                }
+                _ => {}
             }
 
             log::trace!("vector database schema out of date. updating...");
@@ -136,6 +157,8 @@ impl VectorDatabase {
             // `documents` without recreating it if it exists.
             db.execute("DROP TABLE IF EXISTS documents", [])
                 .context("failed to drop 'documents' table")?;
+            db.execute("DROP TABLE IF EXISTS span_terms", [])
+                .context("failed to drop 'span_terms' table")?;
             db.execute("DROP TABLE IF EXISTS spans", [])
                 .context("failed to drop 'spans' table")?;
             db.execute("DROP TABLE IF EXISTS files", [])
@@ -148,14 +171,15 @@ impl VectorDatabase {
             // Initialize Vector Databasing Tables
             db.execute(
                 "CREATE TABLE semantic_index_config (
-                    version INTEGER NOT NULL
+                    version INTEGER NOT NULL,
+                    embedding_model TEXT NOT NULL
                 )",
                 [],
             )?;
 
             db.execute(
-                "INSERT INTO semantic_index_config (version) VALUES (?1)",
-                params![SEMANTIC_INDEX_VERSION],
+                "INSERT INTO semantic_index_config (version, embedding_model) VALUES (?1, ?2)",
+                params![SEMANTIC_INDEX_VERSION, embedding_model],
             )?;
 
             db.execute(
@@ -194,6 +218,7 @@ impl VectorDatabase {
                     name VARCHAR NOT NULL,
                     embedding BLOB NOT NULL,
                     digest BLOB NOT NULL,
+                    term_count INTEGER NOT NULL,
                     FOREIGN KEY(file_id) REFERENCES files(id) ON DELETE CASCADE
                 )",
                 [],
@@ -203,22 +228,42 @@ impl VectorDatabase {
                 [],
             )?;
 
+            db.execute(
+                "CREATE TABLE span_terms (
+                    span_id INTEGER NOT NULL,
+                    term VARCHAR NOT NULL,
+                    frequency INTEGER NOT NULL,
+                    FOREIGN KEY(span_id) REFERENCES spans(id) ON DELETE CASCADE
+                )",
+                [],
+            )?;
+            db.execute(
+                "CREATE INDEX span_terms_term ON span_terms (term)",
+                [],
+            )?;
+
             log::trace!("vector database initialized with updated schema.");
             Ok(())
         })
     }
 
+    /// Deletes the file and returns the ids of its spans.
     pub fn delete_file(
         &self,
         worktree_id: i64,
         delete_path: Arc<Path>,
-    ) -> impl Future<Output = Result<()>> {
+    ) -> impl Future<Output = Result<SpanChanges>> {
         self.transact(move |db| {
+            let removed = span_ids_for_file(db, worktree_id, &delete_path)?;
+            delete_span_terms(db, &removed)?;
             db.execute(
                 "DELETE FROM files WHERE worktree_id = ?1 AND relative_path = ?2",
                 params![worktree_id, delete_path.to_str()],
             )?;
-            Ok(())
+            Ok(SpanChanges {
+                removed,
+                inserted: Vec::new(),
+            })
         })
     }
 
@@ -228,8 +273,11 @@ impl VectorDatabase {
         path: Arc<Path>,
         mtime: SystemTime,
         spans: Vec<Span>,
-    ) -> impl Future<Output = Result<()>> {
+    ) -> impl Future<Output = Result<SpanChanges>> {
         self.transact(move |db| {
+            let removed = span_ids_for_file(db, worktree_id, &path)?;
+            delete_span_terms(db, &removed)?;
+
             // Return the existing ID, if both the file and mtime match
             let mtime = Timestamp::from(mtime);
 
@@ -247,23 +295,173 @@ impl VectorDatabase {
             let mut query = db.prepare(
                 "
                 INSERT INTO spans
-                (file_id, start_byte, end_byte, name, embedding, digest)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                (file_id, start_byte, end_byte, name, embedding, digest, term_count)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
                 ",
             )?;
+            let mut term_query = db
+                .prepare("INSERT INTO span_terms (span_id, term, frequency) VALUES (?1, ?2, ?3)")?;
 
+            let mut inserted = Vec::with_capacity(spans.len());
             for span in spans {
+                let term_frequencies = lexical::term_frequencies(&span.content);
+                let term_count = term_frequencies.values().sum::<u32>();
                 query.execute(params![
                     file_id,
                     span.range.start.to_string(),
                     span.range.end.to_string(),
                     span.name,
                     span.embedding,
-                    span.digest
+                    span.digest,
+                    term_count
                 ])?;
+                let span_id = db.last_insert_rowid();
+                for (term, frequency) in term_frequencies {
+                    term_query.execute(params![span_id, term, frequency])?;
+                }
+                if let Some(embedding) = span.embedding {
+                    inserted.push((span_id, embedding));
+                }
             }
 
-            Ok(())
+            Ok(SpanChanges { removed, inserted })
+        })
+    }
+
+    /// Returns the number of stored spans belonging to a file.
+    pub fn span_count(&self) -> impl Future<Output = Result<usize>> {
+        self.transact(|db| {
+            Ok(db.query_row(
+                "SELECT COUNT(*) FROM spans, files WHERE spans.file_id = files.id",
+                [],
+                |row| row.get(0),
+            )?)
+        })
+    }
+
+    /// Returns the embeddings of every stored span belonging to a file.
+    pub fn embeddings(&self) -> impl Future<Output = Result<Vec<(i64, Embedding)>>> {
+        self.transact(|db| {
+            let mut query = db.prepare(
+                "
+                SELECT spans.id, spans.embedding
+                FROM spans, files
+                WHERE spans.file_id = files.id
+                ",
+            )?;
+            let embeddings = query
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(embeddings)
+        })
+    }
+
+    /// Scores the given spans exactly, returning the `limit` most similar to the query
+    /// among those belonging to the given files.
+    pub fn rerank_spans(
+        &self,
+        query: &Embedding,
+        limit: usize,
+        span_ids: &[i64],
+        file_ids: &[i64],
+    ) -> impl Future<Output = Result<Vec<(i64, OrderedFloat<f32>)>>> {
+        let query = query.clone();
+        let span_ids = span_ids.to_vec();
+        let file_ids = file_ids.to_vec();
+        self.transact(move |db| {
+            let mut statement = db.prepare(
+                "
+                SELECT id, embedding
+                FROM spans
+                WHERE id IN rarray(?1) AND file_id IN rarray(?2)
+                ",
+            )?;
+            let mut results = statement
+                .query_map(
+                    params![ids_to_sql(&span_ids), ids_to_sql(&file_ids)],
+                    |row| Ok((row.get::<_, i64>(0)?, row.get::<_, Embedding>(1)?)),
+                )?
+                .filter_map(|row| row.ok())
+                .map(|(id, embedding)| (id, query.similarity(&embedding)))
+                .collect::<Vec<_>>();
+            results.sort_unstable_by_key(|(_, similarity)| Reverse(*similarity));
+            results.truncate(limit);
+            Ok(results)
+        })
+    }
+
+    /// Ranks the spans of the given files containing any of the query terms with BM25,
+    /// returning the `limit` best scoring spans and the terms each of them contains.
+    pub fn lexical_search(
+        &self,
+        terms: Vec<String>,
+        limit: usize,
+        file_ids: &[i64],
+    ) -> impl Future<Output = Result<Vec<(i64, f32, Vec<String>)>>> {
+        let file_ids = file_ids.to_vec();
+        self.transact(move |db| {
+            let (span_count, average_span_length) = db.query_row(
+                "
+                SELECT COUNT(*), IFNULL(AVG(spans.term_count), 0)
+                FROM spans, files
+                WHERE spans.file_id = files.id
+                ",
+                [],
+                |row| Ok((row.get::<_, usize>(0)?, row.get::<_, f64>(1)?)),
+            )?;
+            let bm25 = Bm25::new(span_count, average_span_length as f32);
+
+            let mut span_frequency_query = db.prepare(
+                "
+                SELECT COUNT(*)
+                FROM span_terms, spans, files
+                WHERE span_terms.term = ?1 AND
+                    span_terms.span_id = spans.id AND
+                    spans.file_id = files.id
+                ",
+            )?;
+            let mut postings_query = db.prepare(
+                "
+                SELECT spans.id, span_terms.frequency, spans.term_count
+                FROM span_terms, spans
+                WHERE span_terms.term = ?1 AND
+                    span_terms.span_id = spans.id AND
+                    spans.file_id IN rarray(?2)
+                ",
+            )?;
+
+            let file_ids = ids_to_sql(&file_ids);
+            let mut scores = HashMap::<i64, (f32, Vec<String>)>::default();
+            for term in terms {
+                let span_frequency =
+                    span_frequency_query.query_row(params![term], |row| row.get::<_, usize>(0))?;
+                if span_frequency == 0 {
+                    continue;
+                }
+
+                let weight = bm25.inverse_span_frequency(span_frequency);
+                let postings = postings_query.query_map(params![term, file_ids], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, u32>(1)?,
+                        row.get::<_, usize>(2)?,
+                    ))
+                })?;
+                for posting in postings {
+                    let (span_id, frequency, span_length) = posting?;
+                    let (score, matched_terms) = scores.entry(span_id).or_default();
+                    *score += bm25.score(frequency, span_length, weight);
+                    matched_terms.push(term.clone());
+                }
+            }
+
+            let mut results = scores
+                .into_iter()
+                .map(|(span_id, (score, matched_terms))| (span_id, score, matched_terms))
+                .collect::<Vec<_>>();
+            results.sort_unstable_by_key(|(_, score, _)| Reverse(OrderedFloat(*score)));
+            results.truncate(limit);
+            Ok(results)
         })
     }
 
@@ -593,6 +791,33 @@ impl VectorDatabase {
     }
 }
 
+fn span_ids_for_file(
+    db: &rusqlite::Transaction,
+    worktree_id: i64,
+    path: &Path,
+) -> Result<Vec<i64>> {
+    let mut query = db.prepare(
+        "
+        SELECT spans.id
+        FROM spans, files
+        WHERE spans.file_id = files.id AND files.worktree_id = ?1 AND files.relative_path = ?2
+        ",
+    )?;
+    let span_ids = query
+        .query_map(params![worktree_id, path.to_str()], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(span_ids)
+}
+
+/// Foreign keys aren't enforced, so terms are deleted explicitly along with their spans.
+fn delete_span_terms(db: &rusqlite::Transaction, span_ids: &[i64]) -> Result<()> {
+    db.execute(
+        "DELETE FROM span_terms WHERE span_id IN rarray(?1)",
+        params![ids_to_sql(span_ids)],
+    )?;
+    Ok(())
+}
+
 fn ids_to_sql(ids: &[i64]) -> Rc<Vec<rusqlite::types::Value>> {
     Rc::new(
         ids.iter()
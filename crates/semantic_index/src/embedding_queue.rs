@@ -34,11 +34,22 @@ impl PartialEq for FileToEmbed {
     }
 }
 
+/// Limits on the size of the batches sent to the embedding provider, in addition to the
+/// provider's own token limit.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EmbeddingBatchLimits {
+    pub max_tokens: Option<usize>,
+    pub max_spans: Option<usize>,
+}
+
 pub struct EmbeddingQueue {
     embedding_provider: Arc<dyn EmbeddingProvider>,
     pending_batch: Vec<FileFragmentToEmbed>,
     executor: BackgroundExecutor,
+    max_tokens_per_batch: usize,
+    max_spans_per_batch: usize,
     pending_batch_token_count: usize,
+    pending_batch_span_count: usize,
     finished_files_tx: channel::Sender<FileToEmbed>,
     finished_files_rx: channel::Receiver<FileToEmbed>,
 }
@@ -52,14 +63,23 @@ pub struct FileFragmentToEmbed {
 impl EmbeddingQueue {
     pub fn new(
         embedding_provider: Arc<dyn EmbeddingProvider>,
+        limits: EmbeddingBatchLimits,
         executor: BackgroundExecutor,
     ) -> Self {
         let (finished_files_tx, finished_files_rx) = channel::unbounded();
+        let max_tokens_per_batch = embedding_provider.max_tokens_per_batch();
         Self {
+            max_tokens_per_batch: limits
+                .max_tokens
+                .map_or(max_tokens_per_batch, |max_tokens| {
+                    max_tokens.min(max_tokens_per_batch)
+                }),
+            max_spans_per_batch: limits.max_spans.unwrap_or(usize::MAX).max(1),
             embedding_provider,
             executor,
             pending_batch: Vec::new(),
             pending_batch_token_count: 0,
+            pending_batch_span_count: 0,
             finished_files_tx,
             finished_files_rx,
         }
@@ -80,14 +100,17 @@ impl EmbeddingQueue {
 
         let mut fragment_range = &mut self.pending_batch.last_mut().unwrap().span_range;
         for (ix, span) in file.lock().spans.iter().enumerate() {
-            let span_token_count = if span.embedding.is_none() {
-                span.token_count
+            let (span_token_count, span_count) = if span.embedding.is_none() {
+                (span.token_count, 1)
             } else {
-                0
+                (0, 0)
             };
 
             let next_token_count = self.pending_batch_token_count + span_token_count;
-            if next_token_count > self.embedding_provider.max_tokens_per_batch() {
+            let next_span_count = self.pending_batch_span_count + span_count;
+            if next_token_count > self.max_tokens_per_batch
+                || next_span_count > self.max_spans_per_batch
+            {
                 let range_end = fragment_range.end;
                 self.flush();
                 self.pending_batch.push(FileFragmentToEmbed {
@@ -99,12 +122,14 @@ impl EmbeddingQueue {
 
             fragment_range.end = ix + 1;
             self.pending_batch_token_count += span_token_count;
+            self.pending_batch_span_count += span_count;
         }
     }
 
     pub fn flush(&mut self) {
         let batch = mem::take(&mut self.pending_batch);
         self.pending_batch_token_count = 0;
+        self.pending_batch_span_count = 0;
         if batch.is_empty() {
             return;
         }
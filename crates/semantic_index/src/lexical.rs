@@ -0,0 +1,159 @@
+use collections::HashMap;
+
+/// Controls how quickly the score of a term saturates as it repeats within a span.
+const K1: f32 = 1.2;
+/// Controls how much longer spans are penalized.
+const B: f32 = 0.75;
+
+/// Returns the number of occurrences of each term in the given text.
+///
+/// Words are lowercased and split into their identifier parts, so that `parseHttpRequest`
+/// yields `parse`, `http` and `request`, as well as `parsehttprequest` itself. Single
+/// characters aren't worth indexing and are skipped.
+pub fn term_frequencies(text: &str) -> HashMap<String, u32> {
+    let mut frequencies = HashMap::default();
+    for term in terms(text) {
+        *frequencies.entry(term).or_insert(0) += 1;
+    }
+    frequencies
+}
+
+/// Returns the distinct terms of a search query, in the order they first appear.
+pub fn query_terms(query: &str) -> Vec<String> {
+    let mut query_terms = Vec::new();
+    for term in terms(query) {
+        if !query_terms.contains(&term) {
+            query_terms.push(term);
+        }
+    }
+    query_terms
+}
+
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .flat_map(|word| {
+            let parts = identifier_parts(word);
+            let whole_word = if parts.len() > 1 {
+                Some(parts.concat())
+            } else {
+                None
+            };
+            parts.into_iter().chain(whole_word)
+        })
+        .filter(|term| term.chars().nth(1).is_some())
+}
+
+/// Splits an identifier at underscores, case changes and digits, lowercasing each part.
+fn identifier_parts(word: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut prev: Option<char> = None;
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '_' {
+            if !current.is_empty() {
+                parts.push(std::mem::take(&mut current));
+            }
+            prev = None;
+            continue;
+        }
+
+        let boundary = match prev {
+            Some(prev) => {
+                (prev.is_lowercase() && c.is_uppercase())
+                    || (prev.is_alphabetic() != c.is_alphabetic())
+                    // The last capital of an acronym starts a new part, as in `HTTPRequest`.
+                    || (prev.is_uppercase()
+                        && c.is_uppercase()
+                        && chars.peek().map_or(false, |next| next.is_lowercase()))
+            }
+            None => false,
+        };
+        if boundary && !current.is_empty() {
+            parts.push(std::mem::take(&mut current));
+        }
+        current.extend(c.to_lowercase());
+        prev = Some(c);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+/// Scores spans against a query with the Okapi BM25 ranking function.
+pub struct Bm25 {
+    span_count: usize,
+    average_span_length: f32,
+}
+
+impl Bm25 {
+    pub fn new(span_count: usize, average_span_length: f32) -> Self {
+        Self {
+            span_count,
+            average_span_length: average_span_length.max(1.0),
+        }
+    }
+
+    /// The weight of a term appearing in `span_frequency` of the indexed spans. Rare terms
+    /// weigh more than common ones.
+    pub fn inverse_span_frequency(&self, span_frequency: usize) -> f32 {
+        let span_count = self.span_count.max(span_frequency) as f32;
+        let span_frequency = span_frequency as f32;
+        ((span_count - span_frequency + 0.5) / (span_frequency + 0.5) + 1.0).ln()
+    }
+
+    /// The contribution of a term occurring `term_frequency` times in a span with
+    /// `span_length` terms.
+    pub fn score(&self, term_frequency: u32, span_length: usize, weight: f32) -> f32 {
+        let term_frequency = term_frequency as f32;
+        let length_ratio = span_length as f32 / self.average_span_length;
+        weight * term_frequency * (K1 + 1.0) / (term_frequency + K1 * (1.0 - B + B * length_ratio))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms() {
+        let frequencies = term_frequencies("fn parse_request(request: &HttpRequest) -> u8");
+        assert_eq!(frequencies.get("request"), Some(&3));
+        assert_eq!(frequencies.get("parse"), Some(&1));
+        assert_eq!(frequencies.get("parserequest"), Some(&1));
+        assert_eq!(frequencies.get("httprequest"), Some(&1));
+        assert_eq!(frequencies.get("fn"), Some(&1));
+        assert_eq!(frequencies.get("u8"), Some(&1));
+        assert_eq!(frequencies.get("u"), None);
+
+        assert_eq!(
+            query_terms("parse the HTTP request, then parse it"),
+            ["parse", "the", "http", "request", "then", "it"]
+        );
+    }
+
+    #[test]
+    fn test_identifier_parts() {
+        assert_eq!(
+            identifier_parts("parseHttpRequest"),
+            ["parse", "http", "request"]
+        );
+        assert_eq!(identifier_parts("HTTPRequest"), ["http", "request"]);
+        assert_eq!(identifier_parts("max_tokens2"), ["max", "tokens", "2"]);
+        assert_eq!(identifier_parts("x"), ["x"]);
+    }
+
+    #[test]
+    fn test_bm25() {
+        let bm25 = Bm25::new(100, 20.0);
+        assert!(bm25.inverse_span_frequency(1) > bm25.inverse_span_frequency(50));
+        assert!(bm25.inverse_span_frequency(100) > 0.0);
+
+        let weight = bm25.inverse_span_frequency(5);
+        assert!(bm25.score(2, 20, weight) > bm25.score(1, 20, weight));
+        assert!(bm25.score(1, 10, weight) > bm25.score(1, 40, weight));
+        // Repeating a term has diminishing returns.
+        assert!(bm25.score(10, 20, weight) < 10.0 * bm25.score(1, 20, weight));
+    }
+}
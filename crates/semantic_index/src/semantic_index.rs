@@ -1,24 +1,29 @@
-mod db;
+pub mod ann_index;
+pub mod db;
 mod embedding_queue;
-mod parsing;
+mod lexical;
+pub mod parsing;
 pub mod semantic_index_settings;
 
 #[cfg(test)]
 mod semantic_index_tests;
 
 use crate::semantic_index_settings::SemanticIndexSettings;
-use ai::embedding::{Embedding, EmbeddingProvider};
-use ai::providers::open_ai::OpenAiEmbeddingProvider;
+use ai::{
+    embedding::{Embedding, EmbeddingProvider},
+    providers::EmbeddingProviderSettings,
+};
+use ann_index::{AnnIndex, AnnIndexState, AnnIndexUpdate, SpanChanges, MIN_SPANS_FOR_ANN_SEARCH};
 use anyhow::{anyhow, Context as _, Result};
 use collections::{BTreeMap, HashMap, HashSet};
 use db::VectorDatabase;
-use embedding_queue::{EmbeddingQueue, FileToEmbed};
+use embedding_queue::{EmbeddingBatchLimits, EmbeddingQueue, FileToEmbed};
 use futures::{future, FutureExt, StreamExt};
 use gpui::{
     AppContext, AsyncAppContext, BorrowWindow, Context, Global, Model, ModelContext, Task,
     ViewContext, WeakModel,
 };
-use language::{Anchor, Bias, Buffer, Language, LanguageRegistry};
+use language::{Anchor, Bias, Buffer, Language, LanguageRegistry, ToOffset};
 use lazy_static::lazy_static;
 use ordered_float::OrderedFloat;
 use parking_lot::Mutex;
@@ -26,7 +31,7 @@ use parsing::{CodeContextRetriever, Span, SpanDigest, PARSEABLE_ENTIRE_FILE_TYPE
 use postage::watch;
 use project::{Fs, PathChange, Project, ProjectEntryId, Worktree, WorktreeId};
 use release_channel::ReleaseChannel;
-use settings::Settings;
+use settings::{Settings, SettingsStore};
 use smol::channel;
 use std::{
     cmp::Reverse,
@@ -39,12 +44,20 @@ use std::{
     time::{Duration, Instant, SystemTime},
 };
 use util::paths::PathMatcher;
-use util::{http::HttpClient, paths::EMBEDDINGS_DIR, ResultExt};
+use util::{http::HttpClient, paths::EMBEDDINGS_DIR, ResultExt, TryFutureExt};
 use workspace::Workspace;
 
-const SEMANTIC_INDEX_VERSION: usize = 11;
+const SEMANTIC_INDEX_VERSION: usize = 13;
 const BACKGROUND_INDEXING_DELAY: Duration = Duration::from_secs(5 * 60);
 const EMBEDDING_QUEUE_FLUSH_TIMEOUT: Duration = Duration::from_millis(250);
+/// The number of candidates fetched from the approximate index per requested result,
+/// before re-ranking them exactly.
+const ANN_CANDIDATES_PER_RESULT: usize = 4;
+const ANN_SEARCH_EF: usize = 128;
+const ANN_INDEX_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+/// The number of candidates fetched from each of the semantic and lexical rankings per
+/// requested result, before fusing them.
+const HYBRID_CANDIDATES_PER_RESULT: usize = 3;
 
 lazy_static! {
     static ref OPENAI_API_KEY: Option<String> = env::var("OPENAI_API_KEY").ok();
@@ -90,25 +103,89 @@ pub fn init(
     )
     .detach();
 
-    cx.spawn(move |cx| async move {
-        let embedding_provider =
-            OpenAiEmbeddingProvider::new(http_client, cx.background_executor().clone()).await;
-        let semantic_index = SemanticIndex::new(
-            fs,
-            db_file_path,
-            Arc::new(embedding_provider),
-            language_registry,
-            cx.clone(),
-        )
-        .await?;
-
-        cx.update(|cx| cx.set_global(GlobalSemanticIndex(semantic_index.clone())))?;
+    let mut embedding_provider = SemanticIndexSettings::get_global(cx)
+        .embedding_provider
+        .clone();
+    build_global_index(
+        embedding_provider.clone(),
+        fs.clone(),
+        db_file_path.clone(),
+        http_client.clone(),
+        language_registry.clone(),
+        cx,
+    )
+    .detach();
 
-        anyhow::Ok(())
+    // Embeddings of different providers can't be compared, so changing the provider
+    // replaces the index, and the projects indexed so far are indexed again.
+    let mut _pending_rebuild = None;
+    cx.observe_global::<SettingsStore>(move |cx| {
+        let new_embedding_provider = &SemanticIndexSettings::get_global(cx).embedding_provider;
+        if *new_embedding_provider != embedding_provider {
+            embedding_provider = new_embedding_provider.clone();
+            _pending_rebuild = Some(build_global_index(
+                embedding_provider.clone(),
+                fs.clone(),
+                db_file_path.clone(),
+                http_client.clone(),
+                language_registry.clone(),
+                cx,
+            ));
+        }
     })
     .detach();
 }
 
+/// Creates a semantic index embedding spans with the given provider, and makes it the
+/// global one. The projects indexed by the previous global index are indexed by the new one.
+fn build_global_index(
+    embedding_provider: EmbeddingProviderSettings,
+    fs: Arc<dyn Fs>,
+    db_file_path: PathBuf,
+    http_client: Arc<dyn HttpClient>,
+    language_registry: Arc<LanguageRegistry>,
+    cx: &mut AppContext,
+) -> Task<Option<()>> {
+    cx.spawn(move |mut cx| {
+        async move {
+            let embedding_provider = embedding_provider
+                .build(http_client, cx.background_executor().clone())
+                .await;
+            let semantic_index = SemanticIndex::new(
+                fs,
+                db_file_path,
+                embedding_provider,
+                language_registry,
+                cx.clone(),
+            )
+            .await?;
+
+            let indexed_projects = cx.update(|cx| {
+                let indexed_projects = SemanticIndex::global(cx)
+                    .map(|previous_index| {
+                        previous_index
+                            .read(cx)
+                            .projects
+                            .keys()
+                            .filter_map(|project| project.upgrade())
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                cx.set_global(GlobalSemanticIndex(semantic_index.clone()));
+                indexed_projects
+            })?;
+            for project in indexed_projects {
+                semantic_index
+                    .update(&mut cx, |index, cx| index.index_project(project, cx))?
+                    .await?;
+            }
+
+            anyhow::Ok(())
+        }
+        .log_err()
+    })
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum SemanticIndexStatus {
     NotAuthenticated,
@@ -126,7 +203,9 @@ pub struct SemanticIndex {
     embedding_provider: Arc<dyn EmbeddingProvider>,
     language_registry: Arc<LanguageRegistry>,
     parsing_files_tx: channel::Sender<(Arc<HashMap<SpanDigest, Embedding>>, PendingFile)>,
+    ann_index: Arc<Mutex<AnnIndexState>>,
     _embedding_task: Task<()>,
+    _ann_index_task: Task<()>,
     _parsing_files_tasks: Vec<Task<()>>,
     projects: HashMap<WeakModel<Project>, ProjectState>,
 }
@@ -277,6 +356,46 @@ pub struct SearchResult {
     pub similarity: OrderedFloat<f32>,
 }
 
+#[derive(Clone)]
+pub struct HybridSearchResult {
+    pub buffer: Model<Buffer>,
+    pub range: Range<Anchor>,
+    /// The fused score that results are ranked by.
+    pub score: f32,
+    /// The similarity of the span to the query, if it was among the semantic candidates.
+    pub similarity: Option<OrderedFloat<f32>>,
+    /// The BM25 score of the span, if it was among the lexical candidates.
+    pub lexical_score: Option<f32>,
+    /// The query terms that the span contains.
+    pub matched_terms: Vec<String>,
+}
+
+impl HybridSearchResult {
+    /// Describes why this result matched, like `matches "parse", "request" · 82% similar`.
+    pub fn explanation(&self) -> String {
+        let mut explanation = Vec::new();
+        if !self.matched_terms.is_empty() {
+            let terms = self
+                .matched_terms
+                .iter()
+                .map(|term| format!("\"{term}\""))
+                .collect::<Vec<_>>();
+            explanation.push(format!("matches {}", terms.join(", ")));
+        }
+        if let Some(similarity) = self.similarity {
+            explanation.push(format!("{:.0}% similar", similarity.0 * 100.));
+        }
+        explanation.join(" · ")
+    }
+}
+
+/// Dampens the difference between the first few ranks when fusing rankings.
+const RECIPROCAL_RANK_OFFSET: f32 = 60.;
+
+fn reciprocal_rank(rank: usize) -> f32 {
+    1. / (RECIPROCAL_RANK_OFFSET + rank as f32 + 1.)
+}
+
 impl SemanticIndex {
     pub fn global(cx: &mut AppContext) -> Option<Model<SemanticIndex>> {
         cx.try_global::<GlobalSemanticIndex>()
@@ -341,9 +460,16 @@ impl SemanticIndex {
         mut cx: AsyncAppContext,
     ) -> Result<Model<Self>> {
         let t0 = Instant::now();
+        let ann_index_path = database_path.with_extension("hnsw");
         let database_path = Arc::from(database_path);
-        let db = VectorDatabase::new(fs.clone(), database_path, cx.background_executor().clone())
-            .await?;
+        let embedding_model = embedding_provider.embedding_model_id();
+        let db = VectorDatabase::new(
+            fs.clone(),
+            database_path,
+            embedding_model.clone(),
+            cx.background_executor().clone(),
+        )
+        .await?;
 
         log::trace!(
             "db initialization took {:?} milliseconds",
@@ -352,16 +478,48 @@ impl SemanticIndex {
 
         cx.new_model(|cx| {
             let t0 = Instant::now();
-            let embedding_queue =
-                EmbeddingQueue::new(embedding_provider.clone(), cx.background_executor().clone());
+            let settings = SemanticIndexSettings::get_global(cx);
+            let embedding_queue = EmbeddingQueue::new(
+                embedding_provider.clone(),
+                EmbeddingBatchLimits {
+                    max_tokens: settings.max_tokens_per_batch,
+                    max_spans: settings.max_spans_per_batch,
+                },
+                cx.background_executor().clone(),
+            );
+            let ann_index = Arc::new(Mutex::new(AnnIndexState::Loading(Vec::new())));
             let _embedding_task = cx.background_executor().spawn({
                 let embedded_files = embedding_queue.finished_files();
                 let db = db.clone();
+                let ann_index = ann_index.clone();
                 async move {
                     while let Ok(file) = embedded_files.recv().await {
-                        db.insert_file(file.worktree_id, file.path, file.mtime, file.spans)
+                        if let Some(changes) = db
+                            .insert_file(file.worktree_id, file.path, file.mtime, file.spans)
                             .await
-                            .log_err();
+                            .log_err()
+                        {
+                            Self::apply_span_changes(&db, &ann_index, changes)
+                                .await
+                                .log_err();
+                        }
+                    }
+                }
+            });
+            let _ann_index_task = cx.background_executor().spawn({
+                let db = db.clone();
+                let ann_index = ann_index.clone();
+                let executor = cx.background_executor().clone();
+                async move {
+                    Self::load_ann_index(&db, &ann_index, &ann_index_path, embedding_model)
+                        .await
+                        .log_err();
+                    loop {
+                        executor.timer(ANN_INDEX_SAVE_INTERVAL).await;
+                        let snapshot = ann_index.lock().take_unsaved_snapshot();
+                        if let Some(snapshot) = snapshot {
+                            snapshot.save(&ann_index_path).log_err();
+                        }
                     }
                 }
             });
@@ -415,13 +573,78 @@ impl SemanticIndex {
                 embedding_provider,
                 language_registry,
                 parsing_files_tx,
+                ann_index,
                 _embedding_task,
+                _ann_index_task,
                 _parsing_files_tasks,
                 projects: Default::default(),
             }
         })
     }
 
+    /// Loads the approximate index saved next to the database, or rebuilds it from the
+    /// stored embeddings if it's missing or out of sync with the database. No index is
+    /// kept while the database holds too few spans to need one.
+    async fn load_ann_index(
+        db: &VectorDatabase,
+        ann_index: &Mutex<AnnIndexState>,
+        path: &Path,
+        embedding_model: String,
+    ) -> Result<()> {
+        let t0 = Instant::now();
+        let span_count = db.span_count().await?;
+        if span_count < MIN_SPANS_FOR_ANN_SEARCH {
+            ann_index.lock().disable(embedding_model, span_count);
+            return Ok(());
+        }
+
+        let (index, is_dirty) = match AnnIndex::load(path, &embedding_model) {
+            Ok(index) if index.len().abs_diff(span_count) <= span_count / 10 => (index, false),
+            result => {
+                if let Err(error) = result {
+                    log::trace!("rebuilding approximate index: {:?}", error);
+                }
+                (Self::build_ann_index(db, embedding_model).await?, true)
+            }
+        };
+        ann_index.lock().finish_loading(index, is_dirty);
+        log::trace!(
+            "loading approximate index took {:?} milliseconds",
+            t0.elapsed().as_millis()
+        );
+        Ok(())
+    }
+
+    async fn build_ann_index(db: &VectorDatabase, embedding_model: String) -> Result<AnnIndex> {
+        let mut index = AnnIndex::new(embedding_model);
+        for (id, embedding) in db.embeddings().await? {
+            index.insert(id, &embedding);
+        }
+        Ok(index)
+    }
+
+    /// Applies spans written to the database to the approximate index. Building or
+    /// compacting the index happens without holding the lock, so searches aren't blocked.
+    async fn apply_span_changes(
+        db: &VectorDatabase,
+        ann_index: &Mutex<AnnIndexState>,
+        changes: SpanChanges,
+    ) -> Result<()> {
+        let update = ann_index.lock().apply(changes);
+        match update {
+            Some(AnnIndexUpdate::Build { embedding_model }) => {
+                let index = Self::build_ann_index(db, embedding_model).await?;
+                ann_index.lock().finish_loading(index, true);
+            }
+            Some(AnnIndexUpdate::Compact(snapshot)) => {
+                let compacted = snapshot.compact();
+                ann_index.lock().finish_compaction(compacted);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
     async fn parse_file(
         fs: &Arc<dyn Fs>,
         pending_file: PendingFile,
@@ -788,139 +1011,334 @@ impl SemanticIndex {
     ) -> Task<Result<Vec<SearchResult>>> {
         let db_path = self.db.path().clone();
         let fs = self.fs.clone();
+        let embedding_model = self.embedding_provider.embedding_model_id();
+        let ann_index = self.ann_index.clone();
         cx.spawn(|this, mut cx| async move {
             let database = VectorDatabase::new(
                 fs.clone(),
                 db_path.clone(),
+                embedding_model.clone(),
                 cx.background_executor().clone(),
             )
             .await?;
 
-            let worktree_db_ids = this.read_with(&cx, |this, _| {
-                let project_state = this
-                    .projects
-                    .get(&project.downgrade())
-                    .context("project was not indexed")?;
-                let worktree_db_ids = project_state
-                    .worktrees
-                    .values()
-                    .filter_map(|worktree| {
-                        if let WorktreeState::Registered(worktree) = worktree {
-                            Some(worktree.db_id)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<Vec<i64>>();
-                anyhow::Ok(worktree_db_ids)
-            })??;
+            let worktree_db_ids =
+                this.read_with(&cx, |this, _| this.worktree_db_ids(&project))??;
 
             let file_ids = database
                 .retrieve_included_file_ids(&worktree_db_ids, &includes, &excludes)
                 .await?;
 
-            let batch_n = cx.background_executor().num_cpus();
-            let ids_len = file_ids.clone().len();
-            let minimum_batch_size = 50;
+            let candidate_ids = cx
+                .background_executor()
+                .spawn({
+                    let query = query.clone();
+                    async move {
+                        let index = ann_index.lock().snapshot()?;
+                        if index.len() < MIN_SPANS_FOR_ANN_SEARCH {
+                            return None;
+                        }
+                        let candidate_count = limit * ANN_CANDIDATES_PER_RESULT;
+                        Some(
+                            index
+                                .search(&query, candidate_count, ANN_SEARCH_EF)
+                                .into_iter()
+                                .map(|(id, _)| id)
+                                .collect::<Vec<_>>(),
+                        )
+                    }
+                })
+                .await;
 
-            let batch_size = {
-                let size = ids_len / batch_n;
-                if size < minimum_batch_size {
-                    minimum_batch_size
-                } else {
-                    size
-                }
-            };
+            // Re-rank the approximate candidates exactly. If too few of them belong to the
+            // included files, fall back to scoring every span of those files.
+            let mut results = Vec::new();
+            if let Some(candidate_ids) = candidate_ids {
+                results = database
+                    .rerank_spans(&query, limit, &candidate_ids, &file_ids)
+                    .await?;
+                log::trace!(
+                    "re-ranked {} approximate candidates, keeping {}",
+                    candidate_ids.len(),
+                    results.len()
+                );
+            }
 
-            let mut batch_results = Vec::new();
-            for batch in file_ids.chunks(batch_size) {
-                let batch = batch.into_iter().map(|v| *v).collect::<Vec<i64>>();
-                let limit = limit.clone();
-                let fs = fs.clone();
-                let db_path = db_path.clone();
-                let query = query.clone();
-                if let Some(db) =
-                    VectorDatabase::new(fs, db_path.clone(), cx.background_executor().clone())
-                        .await
-                        .log_err()
-                {
-                    batch_results.push(async move {
-                        db.top_k_search(&query, limit, batch.as_slice()).await
-                    });
+            if results.len() < limit {
+                let batch_n = cx.background_executor().num_cpus();
+                let ids_len = file_ids.clone().len();
+                let minimum_batch_size = 50;
+
+                let batch_size = {
+                    let size = ids_len / batch_n;
+                    if size < minimum_batch_size {
+                        minimum_batch_size
+                    } else {
+                        size
+                    }
+                };
+
+                let mut batch_results = Vec::new();
+                for batch in file_ids.chunks(batch_size) {
+                    let batch = batch.into_iter().map(|v| *v).collect::<Vec<i64>>();
+                    let limit = limit.clone();
+                    let fs = fs.clone();
+                    let db_path = db_path.clone();
+                    let query = query.clone();
+                    if let Some(db) = VectorDatabase::new(
+                        fs,
+                        db_path.clone(),
+                        embedding_model.clone(),
+                        cx.background_executor().clone(),
+                    )
+                    .await
+                    .log_err()
+                    {
+                        batch_results.push(async move {
+                            db.top_k_search(&query, limit, batch.as_slice()).await
+                        });
+                    }
                 }
-            }
 
-            let batch_results = futures::future::join_all(batch_results).await;
+                let batch_results = futures::future::join_all(batch_results).await;
 
-            let mut results = Vec::new();
-            for batch_result in batch_results {
-                if batch_result.is_ok() {
-                    for (id, similarity) in batch_result.unwrap() {
-                        let ix = match results
-                            .binary_search_by_key(&Reverse(similarity), |(_, s)| Reverse(*s))
-                        {
-                            Ok(ix) => ix,
-                            Err(ix) => ix,
-                        };
+                results.clear();
+                for batch_result in batch_results {
+                    if batch_result.is_ok() {
+                        for (id, similarity) in batch_result.unwrap() {
+                            let ix = match results
+                                .binary_search_by_key(&Reverse(similarity), |(_, s)| Reverse(*s))
+                            {
+                                Ok(ix) => ix,
+                                Err(ix) => ix,
+                            };
 
-                        results.insert(ix, (id, similarity));
-                        results.truncate(limit);
+                            results.insert(ix, (id, similarity));
+                            results.truncate(limit);
+                        }
                     }
                 }
             }
 
             let ids = results.iter().map(|(id, _)| *id).collect::<Vec<i64>>();
-            let scores = results
-                .into_iter()
-                .map(|(_, score)| score)
-                .collect::<Vec<_>>();
             let spans = database.spans_for_ids(ids.as_slice()).await?;
+            let spans = results
+                .into_iter()
+                .map(|(_, similarity)| similarity)
+                .zip(spans)
+                .collect();
+            let results = Self::open_spans(this, project, spans, &mut cx).await?;
+            Ok(results
+                .into_iter()
+                .map(|(similarity, buffer, range)| SearchResult {
+                    buffer,
+                    range,
+                    similarity,
+                })
+                .collect())
+        })
+    }
 
-            let mut tasks = Vec::new();
-            let mut ranges = Vec::new();
-            let weak_project = project.downgrade();
-            project.update(&mut cx, |project, cx| {
-                let this = this.upgrade().context("index was dropped")?;
-                for (worktree_db_id, file_path, byte_range) in spans {
-                    let project_state =
-                        if let Some(state) = this.read(cx).projects.get(&weak_project) {
-                            state
-                        } else {
-                            return Err(anyhow!("project not added"));
-                        };
-                    if let Some(worktree_id) = project_state.worktree_id_for_db_id(worktree_db_id) {
-                        tasks.push(project.open_buffer((worktree_id, file_path), cx));
-                        ranges.push(byte_range);
-                    }
+    /// Searches the project by combining the semantic similarity of spans to the query
+    /// with how well they match its terms, so that spans mentioning an exact identifier
+    /// rank highly even when their embeddings are only loosely related to the query.
+    pub fn search_project_hybrid(
+        &mut self,
+        project: Model<Project>,
+        query: String,
+        limit: usize,
+        includes: Vec<PathMatcher>,
+        excludes: Vec<PathMatcher>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<HybridSearchResult>>> {
+        if query.is_empty() {
+            return Task::ready(Ok(Vec::new()));
+        }
+
+        let terms = lexical::query_terms(&query);
+        let candidate_limit = limit * HYBRID_CANDIDATES_PER_RESULT;
+        let semantic_search = self.search_project(
+            project.clone(),
+            query,
+            candidate_limit,
+            includes.clone(),
+            excludes.clone(),
+            cx,
+        );
+        cx.spawn(|this, mut cx| async move {
+            // Semantic search indexes the project first, which lexical search relies on.
+            let semantic_results = semantic_search.await?;
+            let lexical_results = this
+                .update(&mut cx, |this, cx| {
+                    this.search_files_lexically(
+                        project,
+                        terms,
+                        candidate_limit,
+                        includes,
+                        excludes,
+                        cx,
+                    )
+                })?
+                .await
+                .log_err()
+                .unwrap_or_default();
+
+            // Fuse both rankings by summing the reciprocal ranks of each span, which doesn't
+            // require the similarities and BM25 scores to be on comparable scales.
+            let mut results = Vec::<HybridSearchResult>::new();
+            let mut result_ixs = HashMap::default();
+            for (rank, result) in semantic_results.into_iter().enumerate() {
+                let start = result
+                    .buffer
+                    .read_with(&cx, |buffer, _| result.range.start.to_offset(buffer))?;
+                result_ixs.insert((result.buffer.entity_id(), start), results.len());
+                results.push(HybridSearchResult {
+                    buffer: result.buffer,
+                    range: result.range,
+                    score: reciprocal_rank(rank),
+                    similarity: Some(result.similarity),
+                    lexical_score: None,
+                    matched_terms: Vec::new(),
+                });
+            }
+            for (rank, (buffer, range, lexical_score, matched_terms)) in
+                lexical_results.into_iter().enumerate()
+            {
+                // The spans stored for modified buffers are out of date, and semantic search
+                // already parsed their current contents.
+                let (is_dirty, start) = buffer.read_with(&cx, |buffer, _| {
+                    (buffer.is_dirty(), range.start.to_offset(buffer))
+                })?;
+                if is_dirty {
+                    continue;
                 }
 
-                Ok(())
-            })??;
+                let ix = *result_ixs
+                    .entry((buffer.entity_id(), start))
+                    .or_insert_with(|| {
+                        results.push(HybridSearchResult {
+                            buffer,
+                            range,
+                            score: 0.,
+                            similarity: None,
+                            lexical_score: None,
+                            matched_terms: Vec::new(),
+                        });
+                        results.len() - 1
+                    });
+                let result = &mut results[ix];
+                result.score += reciprocal_rank(rank);
+                result.lexical_score = Some(lexical_score);
+                result.matched_terms = matched_terms;
+            }
+
+            results.sort_by(|a, b| b.score.total_cmp(&a.score));
+            results.truncate(limit);
+            Ok(results)
+        })
+    }
 
-            let buffers = futures::future::join_all(tasks).await;
-            Ok(buffers
+    fn search_files_lexically(
+        &mut self,
+        project: Model<Project>,
+        terms: Vec<String>,
+        limit: usize,
+        includes: Vec<PathMatcher>,
+        excludes: Vec<PathMatcher>,
+        cx: &mut ModelContext<Self>,
+    ) -> Task<Result<Vec<(Model<Buffer>, Range<Anchor>, f32, Vec<String>)>>> {
+        let db = self.db.clone();
+        cx.spawn(|this, mut cx| async move {
+            let worktree_db_ids =
+                this.read_with(&cx, |this, _| this.worktree_db_ids(&project))??;
+            let file_ids = db
+                .retrieve_included_file_ids(&worktree_db_ids, &includes, &excludes)
+                .await?;
+            let results = db.lexical_search(terms, limit, &file_ids).await?;
+
+            let ids = results.iter().map(|(id, _, _)| *id).collect::<Vec<i64>>();
+            let spans = db.spans_for_ids(ids.as_slice()).await?;
+            let spans = results
                 .into_iter()
-                .zip(ranges)
-                .zip(scores)
-                .filter_map(|((buffer, range), similarity)| {
-                    let buffer = buffer.log_err()?;
-                    let range = buffer
-                        .read_with(&cx, |buffer, _| {
-                            let start = buffer.clip_offset(range.start, Bias::Left);
-                            let end = buffer.clip_offset(range.end, Bias::Right);
-                            buffer.anchor_before(start)..buffer.anchor_after(end)
-                        })
-                        .log_err()?;
-                    Some(SearchResult {
-                        buffer,
-                        range,
-                        similarity,
-                    })
+                .map(|(_, score, matched_terms)| (score, matched_terms))
+                .zip(spans)
+                .collect();
+            let results = Self::open_spans(this, project, spans, &mut cx).await?;
+            Ok(results
+                .into_iter()
+                .map(|((score, matched_terms), buffer, range)| {
+                    (buffer, range, score, matched_terms)
                 })
                 .collect())
         })
     }
 
+    fn worktree_db_ids(&self, project: &Model<Project>) -> Result<Vec<i64>> {
+        let project_state = self
+            .projects
+            .get(&project.downgrade())
+            .context("project was not indexed")?;
+        Ok(project_state
+            .worktrees
+            .values()
+            .filter_map(|worktree| {
+                if let WorktreeState::Registered(worktree) = worktree {
+                    Some(worktree.db_id)
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Opens the buffers containing the given spans, returning each span's range in its
+    /// buffer along with the data it was paired with. Spans of removed worktrees or files
+    /// that can't be opened are skipped.
+    async fn open_spans<T>(
+        this: WeakModel<Self>,
+        project: Model<Project>,
+        spans: Vec<(T, (i64, PathBuf, Range<usize>))>,
+        cx: &mut AsyncAppContext,
+    ) -> Result<Vec<(T, Model<Buffer>, Range<Anchor>)>> {
+        let mut tasks = Vec::new();
+        let mut spans_to_open = Vec::new();
+        let weak_project = project.downgrade();
+        project.update(cx, |project, cx| {
+            let this = this.upgrade().context("index was dropped")?;
+            for (data, (worktree_db_id, file_path, byte_range)) in spans {
+                let worktree_id = this
+                    .read(cx)
+                    .projects
+                    .get(&weak_project)
+                    .context("project not added")?
+                    .worktree_id_for_db_id(worktree_db_id);
+                if let Some(worktree_id) = worktree_id {
+                    tasks.push(project.open_buffer((worktree_id, file_path), cx));
+                    spans_to_open.push((data, byte_range));
+                }
+            }
+
+            anyhow::Ok(())
+        })??;
+
+        let buffers = futures::future::join_all(tasks).await;
+        Ok(buffers
+            .into_iter()
+            .zip(spans_to_open)
+            .filter_map(|(buffer, (data, range))| {
+                let buffer = buffer.log_err()?;
+                let range = buffer
+                    .read_with(cx, |buffer, _| {
+                        let start = buffer.clip_offset(range.start, Bias::Left);
+                        let end = buffer.clip_offset(range.end, Bias::Right);
+                        buffer.anchor_before(start)..buffer.anchor_after(end)
+                    })
+                    .log_err()?;
+                Some((data, buffer, range))
+            })
+            .collect())
+    }
+
     fn search_modified_buffers(
         &self,
         project: &Model<Project>,
@@ -960,9 +1378,10 @@ impl SemanticIndex {
         let embedding_provider = self.embedding_provider.clone();
         let fs = self.fs.clone();
         let db_path = self.db.path().clone();
+        let embedding_model = embedding_provider.embedding_model_id();
         let background = cx.background_executor().clone();
         cx.background_executor().spawn(async move {
-            let db = VectorDatabase::new(fs, db_path.clone(), background).await?;
+            let db = VectorDatabase::new(fs, db_path.clone(), embedding_model, background).await?;
             let mut results = Vec::<SearchResult>::new();
 
             let mut retriever = CodeContextRetriever::new(embedding_provider.clone());
@@ -1058,6 +1477,7 @@ impl SemanticIndex {
 
         let mut pending_file_count_rx = project_state.pending_file_count_rx.clone();
         let db = self.db.clone();
+        let ann_index = self.ann_index.clone();
         let language_registry = self.language_registry.clone();
         let parsing_files_tx = self.parsing_files_tx.clone();
         let worktree_registration = self.wait_for_worktree_registration(&project, cx);
@@ -1116,7 +1536,12 @@ impl SemanticIndex {
             cx.background_executor()
                 .spawn(async move {
                     for (worktree_db_id, path) in files_to_delete {
-                        db.delete_file(worktree_db_id, path).await.log_err();
+                        if let Some(changes) = db.delete_file(worktree_db_id, path).await.log_err()
+                        {
+                            Self::apply_span_changes(&db, &ann_index, changes)
+                                .await
+                                .log_err();
+                        }
                     }
 
                     let embeddings_for_digest = {
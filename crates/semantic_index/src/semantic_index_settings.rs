@@ -1,3 +1,4 @@
+use ai::providers::EmbeddingProviderSettings;
 use anyhow;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
@@ -6,6 +7,9 @@ use settings::Settings;
 #[derive(Deserialize, Debug)]
 pub struct SemanticIndexSettings {
     pub enabled: bool,
+    pub embedding_provider: EmbeddingProviderSettings,
+    pub max_tokens_per_batch: Option<usize>,
+    pub max_spans_per_batch: Option<usize>,
 }
 
 /// Configuration of semantic index, an alternate search engine available in
@@ -16,6 +20,20 @@ pub struct SemanticIndexSettingsContent {
     ///
     /// Default: true
     pub enabled: Option<bool>,
+    /// The service that embeds code for semantic search.
+    ///
+    /// Default: { "name": "openai" }
+    pub embedding_provider: Option<EmbeddingProviderSettings>,
+    /// The maximum number of tokens embedded in a single request. The embedding
+    /// provider's own limit applies when it is lower.
+    ///
+    /// Default: null
+    pub max_tokens_per_batch: Option<usize>,
+    /// The maximum number of spans embedded in a single request, for local servers
+    /// that handle large batches poorly.
+    ///
+    /// Default: null
+    pub max_spans_per_batch: Option<usize>,
 }
 
 impl Settings for SemanticIndexSettings {
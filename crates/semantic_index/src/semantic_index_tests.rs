@@ -1,8 +1,10 @@
 use crate::{
-    embedding_queue::EmbeddingQueue,
+    db::VectorDatabase,
+    embedding_queue::{EmbeddingBatchLimits, EmbeddingQueue},
     parsing::{subtract_ranges, CodeContextRetriever, Span, SpanDigest},
     semantic_index_settings::SemanticIndexSettings,
-    FileToEmbed, JobHandle, SearchResult, SemanticIndex, EMBEDDING_QUEUE_FLUSH_TIMEOUT,
+    FileToEmbed, HybridSearchResult, JobHandle, SearchResult, SemanticIndex,
+    EMBEDDING_QUEUE_FLUSH_TIMEOUT,
 };
 use ai::test::FakeEmbeddingProvider;
 
@@ -192,6 +194,82 @@ async fn test_semantic_index(cx: &mut TestAppContext) {
     );
 }
 
+#[gpui::test]
+async fn test_hybrid_search(cx: &mut TestAppContext) {
+    init_test(cx);
+
+    // The fake embeddings only count letters, so both functions are equally similar to the
+    // query, but only one of them contains its terms.
+    let fs = FakeFs::new(cx.background_executor.clone());
+    fs.insert_tree(
+        "/the-root",
+        json!({
+            "src": {
+                "file1.rs": "
+                    fn kpoolu_eltab() {
+                        println!(\"one\");
+                    }
+                ".unindent(),
+                "file2.rs": "
+                    fn lookup_table() {
+                        println!(\"one\");
+                    }
+                ".unindent(),
+            }
+        }),
+    )
+    .await;
+
+    let languages = Arc::new(LanguageRegistry::new(Task::ready(())));
+    languages.add(rust_lang());
+
+    let db_dir = tempfile::Builder::new()
+        .prefix("vector-store")
+        .tempdir()
+        .unwrap();
+    let semantic_index = SemanticIndex::new(
+        fs.clone(),
+        db_dir.path().join("db.sqlite"),
+        Arc::new(FakeEmbeddingProvider::default()),
+        languages,
+        cx.to_async(),
+    )
+    .await
+    .unwrap();
+    let project = Project::test(fs.clone(), ["/the-root".as_ref()], cx).await;
+
+    let search = |excludes: Vec<PathMatcher>, cx: &mut TestAppContext| {
+        semantic_index.update(cx, |index, cx| {
+            index.search_project_hybrid(
+                project.clone(),
+                "lookup table".to_string(),
+                1,
+                vec![],
+                excludes,
+                cx,
+            )
+        })
+    };
+
+    let results = search(vec![], cx);
+    cx.background_executor.run_until_parked();
+    cx.background_executor
+        .advance_clock(EMBEDDING_QUEUE_FLUSH_TIMEOUT);
+    let results = results.await.unwrap();
+    assert_hybrid_search_results(&results, &[(Path::new("src/file2.rs").into(), 0)], cx);
+    assert_eq!(results[0].matched_terms, ["lookup", "table"]);
+    assert!(results[0]
+        .explanation()
+        .starts_with("matches \"lookup\", \"table\" · "));
+
+    let results = search(vec![PathMatcher::new("src/file2.rs").unwrap()], cx)
+        .await
+        .unwrap();
+    assert_hybrid_search_results(&results, &[(Path::new("src/file1.rs").into(), 0)], cx);
+    assert!(results[0].matched_terms.is_empty());
+    assert!(results[0].lexical_score.is_none());
+}
+
 #[gpui::test(iterations = 10)]
 async fn test_embedding_batching(cx: &mut TestAppContext, mut rng: StdRng) {
     let (outstanding_job_count, _) = postage::watch::channel_with(0);
@@ -226,7 +304,14 @@ async fn test_embedding_batching(cx: &mut TestAppContext, mut rng: StdRng) {
 
     let embedding_provider = Arc::new(FakeEmbeddingProvider::default());
 
-    let mut queue = EmbeddingQueue::new(embedding_provider.clone(), cx.background_executor.clone());
+    let mut queue = EmbeddingQueue::new(
+        embedding_provider.clone(),
+        EmbeddingBatchLimits {
+            max_tokens: None,
+            max_spans: Some(7),
+        },
+        cx.background_executor.clone(),
+    );
     for file in &files {
         queue.push(file.clone());
     }
@@ -255,6 +340,49 @@ async fn test_embedding_batching(cx: &mut TestAppContext, mut rng: StdRng) {
     assert_eq!(embedded_files, expected_files);
 }
 
+#[gpui::test]
+async fn test_reindex_on_embedding_model_change(cx: &mut TestAppContext) {
+    let fs = FakeFs::new(cx.background_executor.clone());
+    let db_dir = tempfile::Builder::new()
+        .prefix("vector-store")
+        .tempdir()
+        .unwrap();
+    let db_path: Arc<Path> = db_dir.path().join("db.sqlite").into();
+    let worktree_path = Path::new("/the-root");
+
+    let db = VectorDatabase::new(
+        fs.clone(),
+        db_path.clone(),
+        "model-a".into(),
+        cx.background_executor.clone(),
+    )
+    .await
+    .unwrap();
+    db.find_or_create_worktree(worktree_path.into())
+        .await
+        .unwrap();
+
+    let db = VectorDatabase::new(
+        fs.clone(),
+        db_path.clone(),
+        "model-a".into(),
+        cx.background_executor.clone(),
+    )
+    .await
+    .unwrap();
+    assert!(db.worktree_previously_indexed(worktree_path).await.unwrap());
+
+    let db = VectorDatabase::new(
+        fs.clone(),
+        db_path.clone(),
+        "model-b".into(),
+        cx.background_executor.clone(),
+    )
+    .await
+    .unwrap();
+    assert!(!db.worktree_previously_indexed(worktree_path).await.unwrap());
+}
+
 #[track_caller]
 fn assert_search_results(
     actual: &[SearchResult],
@@ -275,6 +403,25 @@ fn assert_search_results(
     assert_eq!(actual, expected);
 }
 
+fn assert_hybrid_search_results(
+    actual: &[HybridSearchResult],
+    expected: &[(Arc<Path>, usize)],
+    cx: &TestAppContext,
+) {
+    let actual = actual
+        .iter()
+        .map(|search_result| {
+            search_result.buffer.read_with(cx, |buffer, _cx| {
+                (
+                    buffer.file().unwrap().path().clone(),
+                    search_result.range.start.to_offset(buffer),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(actual, expected);
+}
+
 #[gpui::test]
 async fn test_code_context_retrieval_rust() {
     let language = rust_lang();
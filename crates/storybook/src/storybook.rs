@@ -6,7 +6,7 @@ use clap::Parser;
 use dialoguer::FuzzySelect;
 use gpui::{
     div, px, size, AnyView, AppContext, Bounds, Render, ViewContext, VisualContext, WindowBounds,
-    WindowOptions,
+    WindowHandle, WindowOptions,
 };
 use log::LevelFilter;
 use settings::{default_settings, Settings, SettingsStore};
@@ -59,46 +59,50 @@ fn main() {
     let theme_name = args.theme.unwrap_or("One Dark".to_string());
 
     gpui::App::new().with_assets(Assets).run(move |cx| {
-        load_embedded_fonts(cx).unwrap();
-
-        let mut store = SettingsStore::default();
-        store
-            .set_default_settings(default_settings().as_ref(), cx)
-            .unwrap();
-        cx.set_global(store);
-
-        theme::init(theme::LoadThemes::All(Box::new(Assets)), cx);
-
-        let selector = story_selector;
-
-        let theme_registry = ThemeRegistry::global(cx);
-        let mut theme_settings = ThemeSettings::get_global(cx).clone();
-        theme_settings.active_theme = theme_registry.get(&theme_name).unwrap();
-        ThemeSettings::override_global(theme_settings, cx);
-
-        language::init(cx);
-        editor::init(cx);
-
-        let _window = cx.open_window(
-            WindowOptions {
-                bounds: WindowBounds::Fixed(Bounds {
-                    origin: Default::default(),
-                    size: size(px(1500.), px(780.)).into(),
-                }),
-                ..Default::default()
-            },
-            move |cx| {
-                let ui_font_size = ThemeSettings::get_global(cx).ui_font_size;
-                cx.set_rem_size(ui_font_size);
-
-                cx.new_view(|cx| StoryWrapper::new(selector.story(cx)))
-            },
-        );
-
+        init(&theme_name, cx);
+        let _window = open_story_window(story_selector, cx);
         cx.activate(true);
     });
 }
 
+fn init(theme_name: &str, cx: &mut AppContext) {
+    load_embedded_fonts(cx).unwrap();
+
+    let mut store = SettingsStore::default();
+    store
+        .set_default_settings(default_settings().as_ref(), cx)
+        .unwrap();
+    cx.set_global(store);
+
+    theme::init(theme::LoadThemes::All(Box::new(Assets)), cx);
+
+    let theme_registry = ThemeRegistry::global(cx);
+    let mut theme_settings = ThemeSettings::get_global(cx).clone();
+    theme_settings.active_theme = theme_registry.get(theme_name).unwrap();
+    ThemeSettings::override_global(theme_settings, cx);
+
+    language::init(cx);
+    editor::init(cx);
+}
+
+fn open_story_window(selector: StorySelector, cx: &mut AppContext) -> WindowHandle<StoryWrapper> {
+    cx.open_window(
+        WindowOptions {
+            bounds: WindowBounds::Fixed(Bounds {
+                origin: Default::default(),
+                size: size(px(1500.), px(780.)).into(),
+            }),
+            ..Default::default()
+        },
+        move |cx| {
+            let ui_font_size = ThemeSettings::get_global(cx).ui_font_size;
+            cx.set_rem_size(ui_font_size);
+
+            cx.new_view(|cx| StoryWrapper::new(selector.story(cx)))
+        },
+    )
+}
+
 #[derive(Clone)]
 pub struct StoryWrapper {
     story: AnyView,
@@ -133,3 +137,49 @@ fn load_embedded_fonts(cx: &AppContext) -> gpui::Result<()> {
 
     cx.text_system().add_fonts(embedded_fonts)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+    use gpui::{check_golden_image, ImageTolerance, TestAppContext, VisualTestContext};
+    use std::path::PathBuf;
+
+    /// Renders every story and compares it against its golden image in
+    /// `golden_images`. Text is shaped and rasterized with the portable text
+    /// system and only the bundled fonts, so the images are the same on every
+    /// machine.
+    ///
+    /// Run with `UPDATE_GOLDEN_IMAGES=1` to record new golden images after an
+    /// intentional change.
+    #[gpui::test]
+    fn test_stories_match_golden_images(cx: &mut TestAppContext) {
+        cx.set_asset_source(Assets);
+        cx.enable_software_rendering();
+        cx.enable_portable_text_system();
+        cx.update(|cx| init("One Dark", cx));
+
+        let golden_images_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("golden_images");
+        let mut failures = Vec::new();
+        for selector in StorySelector::value_variants() {
+            let name = selector.to_possible_value().unwrap().get_name().to_string();
+            let window = cx.update(|cx| open_story_window(*selector, cx));
+            let mut cx = VisualTestContext::from_window(window.into(), cx);
+            cx.run_until_parked();
+
+            let frame = cx.render_frame();
+            let golden_image = golden_images_dir.join(&name).with_extension("png");
+            if let Err(error) = check_golden_image(&frame, &golden_image, ImageTolerance::default())
+            {
+                failures.push(format!("{name}: {error:?}"));
+            }
+            cx.update(|cx| cx.remove_window());
+        }
+
+        assert!(
+            failures.is_empty(),
+            "stories don't match their golden images:\n{}",
+            failures.join("\n")
+        );
+    }
+}
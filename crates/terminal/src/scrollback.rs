@@ -0,0 +1,223 @@
+//! Serialization of a terminal's grid into a stream of text and SGR escape
+//! sequences, so that scrollback can be persisted and replayed into a fresh
+//! terminal when a workspace is restored.
+
+use alacritty_terminal::{
+    event::EventListener,
+    grid::Dimensions,
+    index::{Column, Line, Point},
+    term::cell::{Cell, Flags},
+    vte::ansi::{Color, NamedColor, Processor},
+    Term,
+};
+use std::fmt::Write;
+
+const RESET: &str = "\x1b[0m";
+
+/// Renders (at most) the last `max_lines` lines of the terminal's history and
+/// screen, including colors and text attributes.
+pub fn serialize<T>(term: &Term<T>, max_lines: usize) -> String {
+    let mut output = String::new();
+    if max_lines == 0 {
+        return output;
+    }
+
+    let grid = term.grid();
+    let columns = grid.columns();
+    let (top, bottom) = line_bounds(term, max_lines);
+
+    let mut current = Cell::default();
+    let mut line = top;
+    while line <= bottom {
+        let row = &grid[line][..Column(columns)];
+        let len = row
+            .iter()
+            .rposition(|cell| !is_blank(cell))
+            .map_or(0, |ix| ix + 1);
+
+        for cell in &row[..len] {
+            if cell
+                .flags
+                .intersects(Flags::WIDE_CHAR_SPACER | Flags::LEADING_WIDE_CHAR_SPACER)
+            {
+                continue;
+            }
+
+            if cell.fg != current.fg || cell.bg != current.bg || cell.flags != current.flags {
+                write_sgr(&mut output, cell);
+                current.fg = cell.fg;
+                current.bg = cell.bg;
+                current.flags = cell.flags;
+            }
+
+            output.push(cell.c);
+            if let Some(zerowidth) = cell.zerowidth() {
+                output.extend(zerowidth);
+            }
+        }
+
+        // Soft-wrapped lines are joined, so that they re-wrap at the new width.
+        let wrapped = row
+            .last()
+            .map_or(false, |cell| cell.flags.contains(Flags::WRAPLINE));
+        if !wrapped || len < columns {
+            if current.fg != Cell::default().fg
+                || current.bg != Cell::default().bg
+                || !current.flags.is_empty()
+            {
+                output.push_str(RESET);
+                current = Cell::default();
+            }
+            if line < bottom {
+                output.push_str("\r\n");
+            }
+        }
+
+        line += 1;
+    }
+
+    if !output.ends_with(RESET) {
+        output.push_str(RESET);
+    }
+    output
+}
+
+/// Renders (at most) the last `max_lines` lines of the terminal's history and
+/// screen as plain text, without any trailing whitespace.
+pub fn plain_text<T>(term: &Term<T>, max_lines: usize) -> String {
+    if max_lines == 0 {
+        return String::new();
+    }
+
+    let (top, bottom) = line_bounds(term, max_lines);
+    let text = term.bounds_to_string(
+        Point::new(top, Column(0)),
+        Point::new(bottom, term.last_column()),
+    );
+    let mut lines = text.lines().map(str::trim_end).collect::<Vec<_>>();
+    while lines.last().map_or(false, |line| line.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// Replays previously serialized content into the terminal, leaving the cursor
+/// at the start of a fresh line below it.
+pub fn restore<T: EventListener>(term: &mut Term<T>, content: &str) {
+    if content.is_empty() {
+        return;
+    }
+
+    let mut processor: Processor = Processor::new();
+    for byte in content.bytes().chain(RESET.bytes()).chain(*b"\r\n") {
+        processor.advance(term, byte);
+    }
+}
+
+/// Returns the first and last lines to capture, ending at the cursor's line.
+fn line_bounds<T>(term: &Term<T>, max_lines: usize) -> (Line, Line) {
+    let bottom = term.grid().cursor.point.line.max(Line(0));
+    let top = Line((bottom.0 + 1 - max_lines as i32).max(term.topmost_line().0));
+    (top, bottom)
+}
+
+fn is_blank(cell: &Cell) -> bool {
+    cell.c == ' '
+        && cell.bg == Color::Named(NamedColor::Background)
+        && !cell
+            .flags
+            .intersects(Flags::INVERSE | Flags::ALL_UNDERLINES | Flags::STRIKEOUT)
+}
+
+fn write_sgr(output: &mut String, cell: &Cell) {
+    output.push_str("\x1b[0");
+
+    for (flag, code) in [
+        (Flags::BOLD, 1),
+        (Flags::DIM, 2),
+        (Flags::ITALIC, 3),
+        (Flags::UNDERLINE, 4),
+        (Flags::INVERSE, 7),
+        (Flags::HIDDEN, 8),
+        (Flags::STRIKEOUT, 9),
+    ] {
+        if cell.flags.contains(flag) {
+            write!(output, ";{code}").ok();
+        }
+    }
+
+    write_color(output, cell.fg, 30);
+    write_color(output, cell.bg, 40);
+    output.push('m');
+}
+
+/// Writes the SGR parameters for a color, where `base` is 30 for the
+/// foreground and 40 for the background.
+fn write_color(output: &mut String, color: Color, base: u8) {
+    match color {
+        Color::Named(name) => {
+            let index = name as usize;
+            if index < 8 {
+                write!(output, ";{}", base as usize + index).ok();
+            } else if index < 16 {
+                write!(output, ";{}", base as usize + 60 + index - 8).ok();
+            }
+        }
+        Color::Indexed(index) => {
+            write!(output, ";{};5;{index}", base + 8).ok();
+        }
+        Color::Spec(rgb) => {
+            write!(output, ";{};2;{};{};{}", base + 8, rgb.r, rgb.g, rgb.b).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alacritty_terminal::{event::VoidListener, term::Config};
+
+    use crate::TerminalSize;
+
+    fn term_with_content(content: &str) -> Term<VoidListener> {
+        let mut term = Term::new(Config::default(), &TerminalSize::default(), VoidListener);
+        let mut processor: Processor = Processor::new();
+        for byte in content.bytes() {
+            processor.advance(&mut term, byte);
+        }
+        term
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let term = term_with_content("plain\r\n\x1b[1;31mbold red\x1b[0m and \x1b[38;5;42mindexed");
+        let serialized = serialize(&term, 100);
+        assert_eq!(
+            serialized,
+            "plain\r\n\x1b[0;1;31mbold red\x1b[0m and \x1b[0;38;5;42mindexed\x1b[0m"
+        );
+
+        let mut restored = Term::new(Config::default(), &TerminalSize::default(), VoidListener);
+        restore(&mut restored, &serialized);
+        let text = restored.bounds_to_string(
+            Point::new(Line(0), Column(0)),
+            Point::new(Line(1), restored.last_column()),
+        );
+        assert_eq!(text.trim_end(), "plain\nbold red and indexed");
+    }
+
+    #[test]
+    fn test_serialize_is_bounded() {
+        let term = term_with_content("one\r\ntwo\r\nthree\r\nfour");
+        assert_eq!(serialize(&term, 2), "three\r\nfour\x1b[0m");
+        assert_eq!(serialize(&term, 0), "");
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let term = term_with_content("one  \r\n\x1b[1;31mtwo\x1b[0m\r\nthree");
+        assert_eq!(plain_text(&term, 100), "one\ntwo\nthree");
+        assert_eq!(plain_text(&term, 2), "two\nthree");
+        assert_eq!(plain_text(&term, 0), "");
+    }
+}
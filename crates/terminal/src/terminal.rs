@@ -1,5 +1,6 @@
 pub mod mappings;
 pub use alacritty_terminal;
+pub mod scrollback;
 pub mod terminal_settings;
 
 use alacritty_terminal::{
@@ -1340,6 +1341,39 @@ impl Terminal {
     pub fn can_navigate_to_selected_word(&self) -> bool {
         self.cmd_pressed && self.hovered_word
     }
+
+    /// The shell-quoted command line of the process running in the foreground
+    /// of the shell, if it is something other than the shell itself.
+    pub fn foreground_command(&self) -> Option<String> {
+        self.foreground_process_info
+            .as_ref()
+            .filter(|info| info.pid != self.shell_pid && !info.argv.is_empty())
+            .map(|info| quote_command(&info.argv))
+    }
+
+    /// Captures up to `max_lines` of scrollback, with colors, on the background executor.
+    pub fn scrollback_snapshot(&self, max_lines: usize, cx: &AppContext) -> Task<String> {
+        let term = self.term.clone();
+        cx.background_executor().spawn(async move {
+            let term = term.lock();
+            scrollback::serialize(&term, max_lines)
+        })
+    }
+
+    /// Captures up to `max_lines` of scrollback, as plain text, on the background executor.
+    pub fn text_snapshot(&self, max_lines: usize, cx: &AppContext) -> Task<String> {
+        let term = self.term.clone();
+        cx.background_executor().spawn(async move {
+            let term = term.lock();
+            scrollback::plain_text(&term, max_lines)
+        })
+    }
+
+    /// Replays previously captured scrollback into this terminal.
+    pub fn restore_scrollback(&mut self, content: &str, cx: &mut ModelContext<Self>) {
+        scrollback::restore(&mut self.term.lock(), content);
+        cx.emit(Event::Wakeup);
+    }
 }
 
 impl Drop for Terminal {
@@ -1350,6 +1384,11 @@ impl Drop for Terminal {
 
 impl EventEmitter<Event> for Terminal {}
 
+/// Joins a command's arguments into a line the shell will split back into the same arguments.
+fn quote_command(argv: &[String]) -> String {
+    shlex::join(argv.iter().map(String::as_str))
+}
+
 /// Based on alacritty/src/display/hint.rs > regex_match_at
 /// Retrieve the match, if the specified point is inside the content matching the regex.
 fn regex_match_at<T>(term: &Term<T>, point: AlacPoint, regex: &mut RegexSearch) -> Option<Match> {
@@ -1491,7 +1530,8 @@ mod tests {
     use rand::{distributions::Alphanumeric, rngs::ThreadRng, thread_rng, Rng};
 
     use crate::{
-        content_index_for_mouse, rgb_for_index, IndexedCell, TerminalContent, TerminalSize,
+        content_index_for_mouse, quote_command, rgb_for_index, IndexedCell, TerminalContent,
+        TerminalSize,
     };
 
     #[test]
@@ -1616,4 +1656,13 @@ mod tests {
             ..Default::default()
         }
     }
+
+    #[test]
+    fn test_quote_command() {
+        let argv = ["git", "commit", "-m", "fix bug"].map(String::from);
+        assert_eq!(quote_command(&argv), r#"git commit -m "fix bug""#);
+
+        let argv = ["echo", "it's", "$HOME"].map(String::from);
+        assert_eq!(shlex::split(&quote_command(&argv)).unwrap(), argv);
+    }
 }
@@ -34,6 +34,7 @@ pub struct TerminalSettings {
     pub default_width: Pixels,
     pub default_height: Pixels,
     pub detect_venv: VenvSettings,
+    pub restore_scrollback_lines: usize,
 }
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize, JsonSchema)]
@@ -145,6 +146,12 @@ pub struct TerminalSettingsContent {
     ///
     /// Default: on
     pub detect_venv: Option<VenvSettings>,
+    /// How many lines of scrollback to persist for each terminal, so that
+    /// its output can be restored along with the workspace. Set this to 0
+    /// to disable restoring scrollback.
+    ///
+    /// Default: 1000
+    pub restore_scrollback_lines: Option<usize>,
 }
 
 impl settings::Settings for TerminalSettings {
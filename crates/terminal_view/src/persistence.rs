@@ -34,6 +34,10 @@ define_connection! {
             DROP TABLE terminals;
 
             ALTER TABLE terminals2 RENAME TO terminals;
+        ),
+        sql!(
+            ALTER TABLE terminals ADD COLUMN scrollback TEXT;
+            ALTER TABLE terminals ADD COLUMN command TEXT;
         )];
 }
 
@@ -56,8 +60,10 @@ impl TerminalDb {
             workspace_id: WorkspaceId,
             working_directory: PathBuf
         ) -> Result<()> {
-            INSERT OR REPLACE INTO terminals(item_id, workspace_id, working_directory)
-            VALUES (?, ?, ?)
+            INSERT INTO terminals(item_id, workspace_id, working_directory)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT DO UPDATE SET
+                working_directory = ?3
         }
     }
 
@@ -68,4 +74,27 @@ impl TerminalDb {
             WHERE item_id = ? AND workspace_id = ?
         }
     }
+
+    query! {
+        pub async fn save_scrollback(
+            item_id: ItemId,
+            workspace_id: WorkspaceId,
+            scrollback: String,
+            command: Option<String>
+        ) -> Result<()> {
+            INSERT INTO terminals(item_id, workspace_id, scrollback, command)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT DO UPDATE SET
+                scrollback = ?3,
+                command = ?4
+        }
+    }
+
+    query! {
+        pub fn get_scrollback(item_id: ItemId, workspace_id: WorkspaceId) -> Result<Option<(Option<String>, Option<String>)>> {
+            SELECT scrollback, command
+            FROM terminals
+            WHERE item_id = ? AND workspace_id = ?
+        }
+    }
 }
@@ -1,11 +1,12 @@
-use std::{ops::ControlFlow, path::PathBuf, sync::Arc};
+use std::{ops::ControlFlow, path::PathBuf, rc::Rc, sync::Arc};
 
 use crate::TerminalView;
+use collections::HashMap;
 use db::kvp::KEY_VALUE_STORE;
 use gpui::{
-    actions, AppContext, AsyncWindowContext, Entity, EventEmitter, ExternalPaths, FocusHandle,
-    FocusableView, IntoElement, ParentElement, Pixels, Render, Styled, Subscription, Task, View,
-    ViewContext, VisualContext, WeakView, WindowContext,
+    actions, AppContext, AsyncWindowContext, Axis, Entity, EntityId, EventEmitter, ExternalPaths,
+    FocusHandle, FocusableView, InteractiveElement, IntoElement, ParentElement, Pixels, Render,
+    Styled, Subscription, Task, View, ViewContext, VisualContext, WeakView, WindowContext,
 };
 use itertools::Itertools;
 use project::{Fs, ProjectEntryId};
@@ -20,7 +21,8 @@ use workspace::{
     item::Item,
     pane,
     ui::IconName,
-    DraggedTab, Pane, Workspace,
+    ActivatePaneInDirection, DraggedTab, Member, Pane, PaneAxis, PaneGroup, SplitDirection,
+    Workspace,
 };
 
 use anyhow::Result;
@@ -43,19 +45,36 @@ pub fn init(cx: &mut AppContext) {
 }
 
 pub struct TerminalPanel {
-    pane: View<Pane>,
+    center: PaneGroup,
+    active_pane: View<Pane>,
     fs: Arc<dyn Fs>,
     workspace: WeakView<Workspace>,
     width: Option<Pixels>,
     height: Option<Pixels>,
     pending_serialization: Task<Option<()>>,
-    _subscriptions: Vec<Subscription>,
+    pane_subscriptions: HashMap<EntityId, Vec<Subscription>>,
 }
 
 impl TerminalPanel {
     fn new(workspace: &Workspace, cx: &mut ViewContext<Self>) -> Self {
+        let pane = Self::build_pane(workspace, cx);
+        let mut this = Self {
+            center: PaneGroup::new(pane.clone()),
+            active_pane: pane.clone(),
+            fs: workspace.app_state().fs.clone(),
+            workspace: workspace.weak_handle(),
+            pending_serialization: Task::ready(None),
+            width: None,
+            height: None,
+            pane_subscriptions: HashMap::default(),
+        };
+        this.subscribe_to_pane(&pane, cx);
+        this
+    }
+
+    fn build_pane(workspace: &Workspace, cx: &mut ViewContext<Self>) -> View<Pane> {
         let terminal_panel = cx.view().downgrade();
-        let pane = cx.new_view(|cx| {
+        cx.new_view(|cx| {
             let mut pane = Pane::new(
                 workspace.weak_handle(),
                 workspace.project().clone(),
@@ -68,6 +87,7 @@ impl TerminalPanel {
             pane.display_nav_history_buttons(false);
             pane.set_render_tab_bar_buttons(cx, move |pane, cx| {
                 let terminal_panel = terminal_panel.clone();
+                let pane_handle = cx.view().clone();
                 h_flex()
                     .gap_2()
                     .child(
@@ -75,11 +95,21 @@ impl TerminalPanel {
                             .icon_size(IconSize::Small)
                             .on_click(move |_, cx| {
                                 terminal_panel
-                                    .update(cx, |panel, cx| panel.add_terminal(None, cx))
+                                    .update(cx, |panel, cx| {
+                                        panel.add_terminal_to_pane(pane_handle.clone(), None, cx)
+                                    })
                                     .log_err();
                             })
                             .tooltip(|cx| Tooltip::text("New Terminal", cx)),
                     )
+                    .child(
+                        IconButton::new("split", IconName::Split)
+                            .icon_size(IconSize::Small)
+                            .on_click(cx.listener(|pane, _, cx| {
+                                pane.split(SplitDirection::Right, cx);
+                            }))
+                            .tooltip(|cx| Tooltip::text("Split Terminal", cx)),
+                    )
                     .child({
                         let zoomed = pane.is_zoomed();
                         IconButton::new("toggle_zoom", IconName::Maximize)
@@ -145,21 +175,21 @@ impl TerminalPanel {
             pane.toolbar()
                 .update(cx, |toolbar, cx| toolbar.add_item(buffer_search_bar, cx));
             pane
-        });
-        let subscriptions = vec![
-            cx.observe(&pane, |_, _, cx| cx.notify()),
-            cx.subscribe(&pane, Self::handle_pane_event),
-        ];
-        let this = Self {
-            pane,
-            fs: workspace.app_state().fs.clone(),
-            workspace: workspace.weak_handle(),
-            pending_serialization: Task::ready(None),
-            width: None,
-            height: None,
-            _subscriptions: subscriptions,
-        };
-        this
+        })
+    }
+
+    fn subscribe_to_pane(&mut self, pane: &View<Pane>, cx: &mut ViewContext<Self>) {
+        self.pane_subscriptions.insert(
+            pane.entity_id(),
+            vec![
+                cx.observe(pane, |_, _, cx| cx.notify()),
+                cx.subscribe(pane, Self::handle_pane_event),
+            ],
+        );
+    }
+
+    fn panes(&self) -> Vec<&View<Pane>> {
+        self.center.panes()
     }
 
     pub async fn load(
@@ -177,77 +207,144 @@ impl TerminalPanel {
             .log_err()
             .flatten();
 
-        let (panel, pane, items) = workspace.update(&mut cx, |workspace, cx| {
+        let (panel, items) = workspace.update(&mut cx, |workspace, cx| {
             let panel = cx.new_view(|cx| TerminalPanel::new(workspace, cx));
-            let items = if let Some(serialized_panel) = serialized_panel.as_ref() {
+            let items = if let Some(serialized_panel) = serialized_panel {
                 panel.update(cx, |panel, cx| {
                     cx.notify();
                     panel.height = serialized_panel.height;
                     panel.width = serialized_panel.width;
-                    panel.pane.update(cx, |_, cx| {
+                    let layout =
                         serialized_panel
-                            .items
-                            .iter()
-                            .map(|item_id| {
-                                TerminalView::deserialize(
-                                    workspace.project().clone(),
-                                    workspace.weak_handle(),
-                                    workspace.database_id(),
-                                    *item_id,
-                                    cx,
-                                )
-                            })
-                            .collect::<Vec<_>>()
-                    })
+                            .layout
+                            .unwrap_or_else(|| SerializedTerminalLayout::Pane {
+                                items: serialized_panel.items,
+                                active_item_id: serialized_panel.active_item_id,
+                                active: true,
+                            });
+                    let root_pane = panel.active_pane.clone();
+                    let mut items = Vec::new();
+                    let root =
+                        panel.deserialize_layout(layout, root_pane, workspace, &mut items, cx);
+                    panel.center = PaneGroup::with_root(root);
+                    items
                 })
             } else {
                 Default::default()
             };
-            let pane = panel.read(cx).pane.clone();
-            (panel, pane, items)
+            (panel, items)
         })?;
 
-        let pane = pane.downgrade();
-        let items = futures::future::join_all(items).await;
-        pane.update(&mut cx, |pane, cx| {
-            let active_item_id = serialized_panel
-                .as_ref()
-                .and_then(|panel| panel.active_item_id);
-            let mut active_ix = None;
-            for item in items {
-                if let Some(item) = item.log_err() {
-                    let item_id = item.entity_id().as_u64();
-                    pane.add_item(Box::new(item), false, false, None, cx);
-                    if Some(item_id) == active_item_id {
-                        active_ix = Some(pane.items_len() - 1);
+        for (pane, item_ids, active_item_id, items) in items {
+            let items = futures::future::join_all(items).await;
+            pane.update(&mut cx, |pane, cx| {
+                let mut active_ix = None;
+                for (item_id, item) in item_ids.into_iter().zip(items) {
+                    if let Some(item) = item.log_err() {
+                        pane.add_item(Box::new(item), false, false, None, cx);
+                        if Some(item_id) == active_item_id {
+                            active_ix = Some(pane.items_len() - 1);
+                        }
                     }
                 }
-            }
 
-            if let Some(active_ix) = active_ix {
-                pane.activate_item(active_ix, false, false, cx)
-            }
-        })?;
+                if let Some(active_ix) = active_ix {
+                    pane.activate_item(active_ix, false, false, cx)
+                }
+            })?;
+        }
 
         Ok(panel)
     }
 
+    /// Rebuilds a serialized layout, reusing `root_pane` for the first pane encountered and
+    /// collecting the terminals that need to be deserialized into each pane.
+    fn deserialize_layout(
+        &mut self,
+        layout: SerializedTerminalLayout,
+        root_pane: View<Pane>,
+        workspace: &Workspace,
+        items: &mut Vec<DeserializedPaneItems>,
+        cx: &mut ViewContext<Self>,
+    ) -> Member {
+        match layout {
+            SerializedTerminalLayout::Pane {
+                items: item_ids,
+                active_item_id,
+                active,
+            } => {
+                let pane = if items.is_empty() {
+                    root_pane
+                } else {
+                    let pane = Self::build_pane(workspace, cx);
+                    self.subscribe_to_pane(&pane, cx);
+                    pane
+                };
+                if active {
+                    self.active_pane = pane.clone();
+                }
+                let tasks = pane.update(cx, |_, cx| {
+                    item_ids
+                        .iter()
+                        .map(|item_id| {
+                            TerminalView::deserialize(
+                                workspace.project().clone(),
+                                workspace.weak_handle(),
+                                workspace.database_id(),
+                                *item_id,
+                                cx,
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                });
+                items.push((pane.clone(), item_ids, active_item_id, tasks));
+                Member::Pane(pane)
+            }
+            SerializedTerminalLayout::Axis {
+                vertical,
+                members,
+                flexes,
+            } => {
+                let axis = if vertical {
+                    Axis::Vertical
+                } else {
+                    Axis::Horizontal
+                };
+                let members = members
+                    .into_iter()
+                    .map(|member| {
+                        self.deserialize_layout(member, root_pane.clone(), workspace, items, cx)
+                    })
+                    .collect::<Vec<_>>();
+                let flexes = (flexes.len() == members.len()).then_some(flexes);
+                Member::Axis(PaneAxis::load(axis, members, flexes))
+            }
+        }
+    }
+
     fn handle_pane_event(
         &mut self,
-        _pane: View<Pane>,
+        pane: View<Pane>,
         event: &pane::Event,
         cx: &mut ViewContext<Self>,
     ) {
         match event {
             pane::Event::ActivateItem { .. } => self.serialize(cx),
             pane::Event::RemoveItem { .. } => self.serialize(cx),
-            pane::Event::Remove => cx.emit(PanelEvent::Close),
+            pane::Event::Remove => self.remove_pane(pane, cx),
             pane::Event::ZoomIn => cx.emit(PanelEvent::ZoomIn),
             pane::Event::ZoomOut => cx.emit(PanelEvent::ZoomOut),
+            pane::Event::Focus => {
+                if self.active_pane != pane {
+                    self.active_pane = pane;
+                    self.serialize(cx);
+                    cx.notify();
+                }
+            }
+            pane::Event::Split(direction) => self.split_pane(pane, *direction, cx),
 
             pane::Event::AddItem { item } => {
                 if let Some(workspace) = self.workspace.upgrade() {
-                    let pane = self.pane.clone();
                     workspace.update(cx, |workspace, cx| item.added_to_pane(workspace, pane, cx))
                 }
             }
@@ -256,6 +353,93 @@ impl TerminalPanel {
         }
     }
 
+    fn split_pane(
+        &mut self,
+        pane_to_split: View<Pane>,
+        direction: SplitDirection,
+        cx: &mut ViewContext<Self>,
+    ) {
+        let Some(workspace) = self.workspace.upgrade() else {
+            return;
+        };
+
+        // Start the new terminal in the same directory as the one being split.
+        let working_directory = pane_to_split
+            .read(cx)
+            .active_item()
+            .and_then(|item| item.downcast::<TerminalView>())
+            .and_then(|terminal_view| {
+                terminal_view
+                    .read(cx)
+                    .terminal()
+                    .read(cx)
+                    .foreground_process_info
+                    .as_ref()
+                    .map(|info| info.cwd.clone())
+            });
+
+        if let Some(new_pane) = self.insert_pane(&workspace, &pane_to_split, direction, cx) {
+            self.add_terminal_to_pane(new_pane, working_directory, cx);
+        }
+    }
+
+    /// Adds an empty pane next to `pane_to_split` and makes it the active one.
+    fn insert_pane(
+        &mut self,
+        workspace: &View<Workspace>,
+        pane_to_split: &View<Pane>,
+        direction: SplitDirection,
+        cx: &mut ViewContext<Self>,
+    ) -> Option<View<Pane>> {
+        let new_pane = Self::build_pane(workspace.read(cx), cx);
+        self.center
+            .split(pane_to_split, &new_pane, direction)
+            .log_err()?;
+        self.subscribe_to_pane(&new_pane, cx);
+        self.active_pane = new_pane.clone();
+        cx.focus_view(&new_pane);
+        cx.notify();
+        Some(new_pane)
+    }
+
+    fn remove_pane(&mut self, pane: View<Pane>, cx: &mut ViewContext<Self>) {
+        if self.center.remove(&pane).log_err() == Some(true) {
+            self.pane_subscriptions.remove(&pane.entity_id());
+            if self.active_pane == pane {
+                self.active_pane = self.center.first_pane();
+                cx.focus_view(&self.active_pane);
+            }
+            self.serialize(cx);
+            cx.notify();
+        } else {
+            cx.emit(PanelEvent::Close);
+        }
+    }
+
+    fn activate_pane_in_direction(
+        &mut self,
+        action: &ActivatePaneInDirection,
+        cx: &mut ViewContext<Self>,
+    ) {
+        if let Some(pane) = self
+            .center
+            .find_pane_in_direction(&self.active_pane, action.0, cx)
+            .cloned()
+        {
+            cx.focus_view(&pane);
+        } else {
+            cx.propagate();
+        }
+    }
+
+    /// The terminal that is active in the panel's focused pane, if any.
+    pub fn active_terminal(&self, cx: &AppContext) -> Option<View<TerminalView>> {
+        self.active_pane
+            .read(cx)
+            .active_item()
+            .and_then(|item| item.downcast::<TerminalView>())
+    }
+
     pub fn open_terminal(
         workspace: &mut Workspace,
         action: &workspace::OpenTerminal,
@@ -284,9 +468,17 @@ impl TerminalPanel {
     }
 
     fn add_terminal(&mut self, working_directory: Option<PathBuf>, cx: &mut ViewContext<Self>) {
+        self.add_terminal_to_pane(self.active_pane.clone(), working_directory, cx);
+    }
+
+    fn add_terminal_to_pane(
+        &mut self,
+        pane: View<Pane>,
+        working_directory: Option<PathBuf>,
+        cx: &mut ViewContext<Self>,
+    ) {
         let workspace = self.workspace.clone();
         cx.spawn(|this, mut cx| async move {
-            let pane = this.update(&mut cx, |this, _| this.pane.clone())?;
             workspace.update(&mut cx, |workspace, cx| {
                 let working_directory = if let Some(working_directory) = working_directory {
                     Some(working_directory)
@@ -323,17 +515,7 @@ impl TerminalPanel {
     }
 
     fn serialize(&mut self, cx: &mut ViewContext<Self>) {
-        let items = self
-            .pane
-            .read(cx)
-            .items()
-            .map(|item| item.item_id().as_u64())
-            .collect::<Vec<_>>();
-        let active_item_id = self
-            .pane
-            .read(cx)
-            .active_item()
-            .map(|item| item.item_id().as_u64());
+        let layout = self.serialize_layout(self.center.root(), cx);
         let height = self.height;
         let width = self.width;
         self.pending_serialization = cx.background_executor().spawn(
@@ -342,10 +524,11 @@ impl TerminalPanel {
                     .write_kvp(
                         TERMINAL_PANEL_KEY.into(),
                         serde_json::to_string(&SerializedTerminalPanel {
-                            items,
-                            active_item_id,
+                            items: Vec::new(),
+                            active_item_id: None,
                             height,
                             width,
+                            layout: Some(layout),
                         })?,
                     )
                     .await?;
@@ -354,6 +537,31 @@ impl TerminalPanel {
             .log_err(),
         );
     }
+
+    fn serialize_layout(&self, member: &Member, cx: &AppContext) -> SerializedTerminalLayout {
+        match member {
+            Member::Pane(pane) => {
+                let pane_state = pane.read(cx);
+                SerializedTerminalLayout::Pane {
+                    items: pane_state
+                        .items()
+                        .map(|item| item.item_id().as_u64())
+                        .collect(),
+                    active_item_id: pane_state.active_item().map(|item| item.item_id().as_u64()),
+                    active: pane == &self.active_pane,
+                }
+            }
+            Member::Axis(axis) => SerializedTerminalLayout::Axis {
+                vertical: axis.axis == Axis::Vertical,
+                members: axis
+                    .members
+                    .iter()
+                    .map(|member| self.serialize_layout(member, cx))
+                    .collect(),
+                flexes: axis.flexes.lock().clone(),
+            },
+        }
+    }
 }
 
 fn add_paths_to_terminal(pane: &mut Pane, paths: &[PathBuf], cx: &mut ViewContext<'_, Pane>) {
@@ -379,7 +587,7 @@ impl Render for TerminalPanel {
         let mut registrar = DivRegistrar::new(
             |panel, cx| {
                 panel
-                    .pane
+                    .active_pane
                     .read(cx)
                     .toolbar()
                     .read(cx)
@@ -388,13 +596,32 @@ impl Render for TerminalPanel {
             cx,
         );
         BufferSearchBar::register(&mut registrar);
-        registrar.into_div().size_full().child(self.pane.clone())
+        let content = if self.active_pane.read(cx).is_zoomed() {
+            self.active_pane.clone().into_any_element()
+        } else {
+            let panel = cx.view().downgrade();
+            self.center
+                .render_panes(
+                    &self.active_pane,
+                    None,
+                    Rc::new(move |cx| {
+                        panel.update(cx, |panel, cx| panel.serialize(cx)).log_err();
+                    }),
+                    cx,
+                )
+                .into_any_element()
+        };
+        registrar
+            .into_div()
+            .size_full()
+            .on_action(cx.listener(Self::activate_pane_in_direction))
+            .child(content)
     }
 }
 
 impl FocusableView for TerminalPanel {
     fn focus_handle(&self, cx: &AppContext) -> FocusHandle {
-        self.pane.focus_handle(cx)
+        self.active_pane.focus_handle(cx)
     }
 }
 
@@ -442,21 +669,31 @@ impl Panel for TerminalPanel {
     }
 
     fn is_zoomed(&self, cx: &WindowContext) -> bool {
-        self.pane.read(cx).is_zoomed()
+        self.active_pane.read(cx).is_zoomed()
     }
 
     fn set_zoomed(&mut self, zoomed: bool, cx: &mut ViewContext<Self>) {
-        self.pane.update(cx, |pane, cx| pane.set_zoomed(zoomed, cx));
+        self.active_pane
+            .update(cx, |pane, cx| pane.set_zoomed(zoomed, cx));
     }
 
     fn set_active(&mut self, active: bool, cx: &mut ViewContext<Self>) {
-        if active && self.pane.read(cx).items_len() == 0 {
+        if active
+            && self
+                .panes()
+                .iter()
+                .all(|pane| pane.read(cx).items_len() == 0)
+        {
             self.add_terminal(None, cx)
         }
     }
 
     fn icon_label(&self, cx: &WindowContext) -> Option<String> {
-        let count = self.pane.read(cx).items_len();
+        let count = self
+            .panes()
+            .iter()
+            .map(|pane| pane.read(cx).items_len())
+            .sum::<usize>();
         if count == 0 {
             None
         } else {
@@ -481,10 +718,146 @@ impl Panel for TerminalPanel {
     }
 }
 
+type DeserializedPaneItems = (
+    View<Pane>,
+    Vec<u64>,
+    Option<u64>,
+    Vec<Task<Result<View<TerminalView>>>>,
+);
+
 #[derive(Serialize, Deserialize)]
 struct SerializedTerminalPanel {
+    /// Terminals of the single pane layout used before the panel could be split.
+    #[serde(default)]
     items: Vec<u64>,
     active_item_id: Option<u64>,
     width: Option<Pixels>,
     height: Option<Pixels>,
+    #[serde(default)]
+    layout: Option<SerializedTerminalLayout>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum SerializedTerminalLayout {
+    Pane {
+        items: Vec<u64>,
+        active_item_id: Option<u64>,
+        active: bool,
+    },
+    Axis {
+        vertical: bool,
+        members: Vec<SerializedTerminalLayout>,
+        flexes: Vec<f32>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+    use project::Project;
+    use workspace::AppState;
+
+    #[gpui::test]
+    async fn test_split_and_remove_panes(cx: &mut TestAppContext) {
+        let (workspace, panel, cx) = init_test(cx).await;
+        panel.update(cx, |panel, cx| {
+            let root_pane = panel.active_pane.clone();
+            let right_pane = panel
+                .insert_pane(&workspace, &root_pane, SplitDirection::Right, cx)
+                .unwrap();
+            let bottom_pane = panel
+                .insert_pane(&workspace, &right_pane, SplitDirection::Down, cx)
+                .unwrap();
+            assert_eq!(panel.panes().len(), 3);
+            assert_eq!(panel.active_pane, bottom_pane);
+
+            panel.remove_pane(bottom_pane, cx);
+            assert_eq!(panel.panes(), [&root_pane, &right_pane]);
+            assert_eq!(panel.active_pane, root_pane);
+            assert_eq!(panel.pane_subscriptions.len(), 2);
+
+            panel.remove_pane(right_pane, cx);
+            assert_eq!(panel.panes(), [&root_pane]);
+        });
+    }
+
+    #[gpui::test]
+    async fn test_serialize_and_restore_layout(cx: &mut TestAppContext) {
+        let (workspace, panel, cx) = init_test(cx).await;
+        let layout = panel.update(cx, |panel, cx| {
+            let root_pane = panel.active_pane.clone();
+            let right_pane = panel
+                .insert_pane(&workspace, &root_pane, SplitDirection::Right, cx)
+                .unwrap();
+            panel
+                .insert_pane(&workspace, &right_pane, SplitDirection::Down, cx)
+                .unwrap();
+            panel.serialize_layout(panel.center.root(), cx)
+        });
+        let empty_pane = |active| SerializedTerminalLayout::Pane {
+            items: Vec::new(),
+            active_item_id: None,
+            active,
+        };
+        let SerializedTerminalLayout::Axis {
+            vertical: false,
+            members,
+            ..
+        } = &layout
+        else {
+            panic!("expected a horizontal split, got {layout:?}");
+        };
+        assert_eq!(members[0], empty_pane(false));
+        assert!(matches!(
+            &members[1],
+            SerializedTerminalLayout::Axis { vertical: true, members, .. }
+                if members == &[empty_pane(false), empty_pane(true)]
+        ));
+
+        // Layouts round-trip through the database's JSON and are rebuilt with the same shape.
+        let json = serde_json::to_string(&layout).unwrap();
+        let layout = serde_json::from_str::<SerializedTerminalLayout>(&json).unwrap();
+        let restored_panel = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| TerminalPanel::new(workspace, cx))
+        });
+        workspace.update(cx, |workspace, cx| {
+            restored_panel.update(cx, |panel, cx| {
+                let root_pane = panel.active_pane.clone();
+                let mut items = Vec::new();
+                let root =
+                    panel.deserialize_layout(layout, root_pane.clone(), workspace, &mut items, cx);
+                panel.center = PaneGroup::with_root(root);
+                assert_eq!(items.len(), 3);
+                assert_eq!(panel.panes()[0], &root_pane);
+                assert_eq!(panel.panes().len(), 3);
+                assert_eq!(&panel.active_pane, panel.panes()[2]);
+
+                let restored_layout = panel.serialize_layout(panel.center.root(), cx);
+                assert_eq!(serde_json::to_string(&restored_layout).unwrap(), json);
+            });
+        });
+    }
+
+    async fn init_test(
+        cx: &mut TestAppContext,
+    ) -> (
+        View<Workspace>,
+        View<TerminalPanel>,
+        &mut gpui::VisualTestContext,
+    ) {
+        let app_state = cx.update(AppState::test);
+        cx.update(|cx| {
+            theme::init(theme::LoadThemes::JustBase, cx);
+            Project::init_settings(cx);
+            language::init(cx);
+            editor::init_settings(cx);
+        });
+        let project = Project::test(app_state.fs.clone(), [], cx).await;
+        let (workspace, cx) = cx.add_window_view(|cx| Workspace::test_new(project, cx));
+        let panel = workspace.update(cx, |workspace, cx| {
+            cx.new_view(|cx| TerminalPanel::new(workspace, cx))
+        });
+        (workspace, panel, cx)
+    }
 }
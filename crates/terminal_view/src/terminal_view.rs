@@ -47,6 +47,7 @@ use std::{
 };
 
 const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+const SCROLLBACK_SERIALIZATION_DELAY: Duration = Duration::from_secs(2);
 
 ///Event to transmit the scroll from the element to the view
 #[derive(Clone, Debug, PartialEq)]
@@ -87,6 +88,11 @@ pub struct TerminalView {
     blink_epoch: usize,
     can_navigate_to_selected_word: bool,
     workspace_id: WorkspaceId,
+    /// The last command run in the foreground of this terminal's shell.
+    last_command: Option<String>,
+    /// A command restored from a previous session, offered to be re-run.
+    restored_command: Option<String>,
+    scrollback_serialization_pending: bool,
     _subscriptions: Vec<Subscription>,
 }
 
@@ -149,6 +155,7 @@ impl TerminalView {
                 cx.emit(Event::Wakeup);
                 cx.emit(ItemEvent::UpdateTab);
                 cx.emit(SearchEvent::MatchesInvalidated);
+                this.serialize_scrollback(cx);
             }
 
             Event::Bell => {
@@ -160,6 +167,9 @@ impl TerminalView {
 
             Event::TitleChanged => {
                 cx.emit(ItemEvent::UpdateTab);
+                if let Some(command) = this.terminal().read(cx).foreground_command() {
+                    this.last_command = Some(command);
+                }
                 if let Some(foreground_info) = &this.terminal().read(cx).foreground_process_info {
                     let cwd = foreground_info.cwd.clone();
 
@@ -319,10 +329,87 @@ impl TerminalView {
             blink_epoch: 0,
             can_navigate_to_selected_word: false,
             workspace_id,
+            last_command: None,
+            restored_command: None,
+            scrollback_serialization_pending: false,
             _subscriptions: vec![focus_in, focus_out],
         }
     }
 
+    /// Persists the terminal's scrollback and last command shortly after new output arrives,
+    /// so it can be restored along with the workspace.
+    fn serialize_scrollback(&mut self, cx: &mut ViewContext<Self>) {
+        let max_lines = TerminalSettings::get_global(cx).restore_scrollback_lines;
+        if max_lines == 0 || self.scrollback_serialization_pending {
+            return;
+        }
+
+        self.scrollback_serialization_pending = true;
+        cx.spawn(|this, mut cx| async move {
+            cx.background_executor()
+                .timer(SCROLLBACK_SERIALIZATION_DELAY)
+                .await;
+            let (item_id, workspace_id, command, scrollback) =
+                this.update(&mut cx, |this, cx| {
+                    this.scrollback_serialization_pending = false;
+                    let scrollback = this.terminal.read(cx).scrollback_snapshot(max_lines, cx);
+                    (
+                        cx.entity_id().as_u64(),
+                        this.workspace_id,
+                        this.last_command.clone(),
+                        scrollback,
+                    )
+                })?;
+            TERMINAL_DB
+                .save_scrollback(item_id, workspace_id, scrollback.await, command)
+                .await
+        })
+        .detach_and_log_err(cx);
+    }
+
+    fn rerun_restored_command(&mut self, cx: &mut ViewContext<Self>) {
+        if let Some(command) = self.restored_command.take() {
+            self.terminal
+                .update(cx, |terminal, _| terminal.input(format!("{command}\r")));
+            cx.focus_self();
+            cx.notify();
+        }
+    }
+
+    fn render_restored_command(&self, cx: &mut ViewContext<Self>) -> Option<impl IntoElement> {
+        let command = self.restored_command.as_ref()?;
+        Some(
+            h_flex()
+                .absolute()
+                .top_2()
+                .right_2()
+                .gap_2()
+                .px_2()
+                .py_1()
+                .rounded_md()
+                .bg(cx.theme().colors().elevated_surface_background)
+                .border_1()
+                .border_color(cx.theme().colors().border)
+                .child(
+                    Label::new(format!("Re-run `{command}`?"))
+                        .size(LabelSize::Small)
+                        .color(Color::Muted),
+                )
+                .child(
+                    Button::new("rerun-restored-command", "Re-run")
+                        .on_click(cx.listener(|this, _, cx| this.rerun_restored_command(cx))),
+                )
+                .child(
+                    IconButton::new("dismiss-restored-command", IconName::Close)
+                        .icon_size(IconSize::Small)
+                        .on_click(cx.listener(|this, _, cx| {
+                            this.restored_command = None;
+                            cx.notify();
+                        })),
+                ),
+        )
+    }
+
     pub fn model(&self) -> &Model<Terminal> {
         &self.terminal
     }
@@ -740,6 +827,7 @@ impl Render for TerminalView {
                     self.can_navigate_to_selected_word,
                 )),
             )
+            .children(self.render_restored_command(cx))
             .children(self.context_menu.as_ref().map(|(menu, position, _)| {
                 overlay()
                     .position(*position)
@@ -851,11 +939,27 @@ impl Item for TerminalView {
                     .flatten()
                 });
 
+            let (scrollback, command) = TERMINAL_DB
+                .get_scrollback(item_id, workspace_id)
+                .log_err()
+                .flatten()
+                .unwrap_or_default();
+
             let terminal = project.update(&mut cx, |project, cx| {
                 project.create_terminal(cwd, window, cx)
             })??;
+            if let Some(scrollback) = scrollback {
+                terminal.update(&mut cx, |terminal, cx| {
+                    terminal.restore_scrollback(&scrollback, cx)
+                })?;
+            }
             pane.update(&mut cx, |_, cx| {
-                cx.new_view(|cx| TerminalView::new(terminal, workspace, workspace_id, cx))
+                cx.new_view(|cx| {
+                    let mut view = TerminalView::new(terminal, workspace, workspace_id, cx);
+                    view.last_command = command.clone();
+                    view.restored_command = command;
+                    view
+                })
             })
         })
     }
@@ -0,0 +1,258 @@
+use crate::{Buffer, BufferSnapshot, Edit, History, Operation, OperationQueue, Topic};
+use anyhow::{anyhow, Result};
+use collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{ops::Range, time::Instant};
+
+/// A buffer's local undo and redo history, expressed as plain text edits so that
+/// it can be persisted and later replayed into a buffer with the same contents.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SerializedHistory {
+    /// Transactions that can be undone, from oldest to newest.
+    pub undo_stack: Vec<SerializedTransaction>,
+    /// Transactions that can be redone, in the order they would be redone.
+    pub redo_stack: Vec<SerializedTransaction>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SerializedTransaction {
+    /// Non-overlapping edits sorted by range, expressed in offsets of the text
+    /// as it was before the transaction was applied.
+    pub edits: Vec<SerializedEdit>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SerializedEdit {
+    pub range: Range<usize>,
+    pub old_text: String,
+    pub new_text: String,
+}
+
+impl SerializedHistory {
+    pub fn is_empty(&self) -> bool {
+        self.undo_stack.is_empty() && self.redo_stack.is_empty()
+    }
+}
+
+impl SerializedTransaction {
+    pub(crate) fn from_edits(
+        edits: Vec<Edit<usize>>,
+        before: &BufferSnapshot,
+        after: &BufferSnapshot,
+    ) -> Self {
+        Self {
+            edits: edits
+                .into_iter()
+                .map(|edit| SerializedEdit {
+                    old_text: before.text_for_range(edit.old.clone()).collect(),
+                    new_text: after.text_for_range(edit.new).collect(),
+                    range: edit.old,
+                })
+                .collect(),
+        }
+    }
+
+    /// The inverse edits, expressed in offsets of the text after the transaction was applied.
+    fn inverse_edits(&self) -> Vec<(Range<usize>, &str, &str)> {
+        let mut delta = 0isize;
+        self.edits
+            .iter()
+            .map(|edit| {
+                let start = (edit.range.start as isize + delta) as usize;
+                delta += edit.new_text.len() as isize - edit.old_text.len() as isize;
+                (
+                    start..start + edit.new_text.len(),
+                    edit.new_text.as_str(),
+                    edit.old_text.as_str(),
+                )
+            })
+            .collect()
+    }
+
+    fn len(&self) -> usize {
+        self.edits
+            .iter()
+            .map(|edit| edit.old_text.len() + edit.new_text.len())
+            .sum()
+    }
+}
+
+impl Buffer {
+    /// Captures the local undo and redo stacks as text edits. The most recent transactions
+    /// are kept until the total size of the edited text would exceed `max_len` bytes.
+    pub fn serialize_history(&self, max_len: usize) -> SerializedHistory {
+        let mut history = SerializedHistory::default();
+        if self.history.transaction_depth > 0 {
+            return history;
+        }
+
+        let mut remaining_len = max_len;
+        let mut buffer = self.fork_local_history();
+        while buffer.peek_undo_stack().is_some() {
+            let before = buffer.snapshot();
+            buffer.undo();
+            let after = buffer.snapshot();
+            let edits = after
+                .edits_since::<usize>(&before.version)
+                .collect::<Vec<_>>();
+            // Undoing maps the text after the transaction to the text before it, so flip it around.
+            let transaction = SerializedTransaction::from_edits(
+                edits
+                    .into_iter()
+                    .map(|edit| Edit {
+                        old: edit.new,
+                        new: edit.old,
+                    })
+                    .collect(),
+                &after,
+                &before,
+            );
+            let Some(len) = remaining_len.checked_sub(transaction.len()) else {
+                break;
+            };
+            remaining_len = len;
+            history.undo_stack.push(transaction);
+        }
+        history.undo_stack.reverse();
+
+        let mut buffer = self.fork_local_history();
+        while buffer.peek_redo_stack().is_some() {
+            let before = buffer.snapshot();
+            buffer.redo();
+            let after = buffer.snapshot();
+            let edits = after.edits_since::<usize>(&before.version).collect();
+            let transaction = SerializedTransaction::from_edits(edits, &before, &after);
+            let Some(len) = remaining_len.checked_sub(transaction.len()) else {
+                break;
+            };
+            remaining_len = len;
+            history.redo_stack.push(transaction);
+        }
+
+        history
+    }
+
+    /// Replays a serialized history into a buffer without any history of its own, such that
+    /// its text is unchanged but the restored transactions can be undone and redone.
+    ///
+    /// Returns the operations that were generated while replaying the history.
+    pub fn restore_history(&mut self, history: &SerializedHistory) -> Result<Vec<Operation>> {
+        if self.history.transaction_depth > 0
+            || self.peek_undo_stack().is_some()
+            || self.peek_redo_stack().is_some()
+        {
+            return Err(anyhow!("buffer already has history"));
+        }
+        if history.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // Rewind a copy of the buffer to the text as it was before the oldest transaction,
+        // bailing out if the history doesn't describe how the buffer's current text came about.
+        let mut rewound = self.fork();
+        for transaction in history.undo_stack.iter().rev() {
+            let edits = transaction.inverse_edits();
+            let matches = edits.iter().all(|(range, expected_text, _)| {
+                range.end <= rewound.len()
+                    && rewound.text_for_range(range.clone()).collect::<String>() == *expected_text
+            });
+            if !matches {
+                return Err(anyhow!("history does not match the buffer's text"));
+            }
+            rewound.edit(
+                edits
+                    .into_iter()
+                    .map(|(range, _, old_text)| (range, old_text.to_string())),
+            );
+        }
+
+        let now = Instant::now();
+        let mut operations = Vec::new();
+
+        let rewind_id = self.start_transaction_at(now);
+        for transaction in history.undo_stack.iter().rev() {
+            operations.push(
+                self.edit(
+                    transaction
+                        .inverse_edits()
+                        .into_iter()
+                        .map(|(range, _, old_text)| (range, old_text.to_string())),
+                ),
+            );
+        }
+        self.end_transaction_at(now);
+        if let Some(rewind_id) = rewind_id {
+            self.forget_transaction(rewind_id);
+        }
+
+        for transaction in history.undo_stack.iter().chain(&history.redo_stack) {
+            self.start_transaction_at(now);
+            operations.push(
+                self.edit(
+                    transaction
+                        .edits
+                        .iter()
+                        .map(|edit| (edit.range.clone(), edit.new_text.clone())),
+                ),
+            );
+            self.end_transaction_at(now);
+            self.finalize_last_transaction();
+        }
+        for _ in &history.redo_stack {
+            operations.extend(self.undo().map(|(_, operation)| operation));
+        }
+
+        Ok(operations)
+    }
+
+    /// Creates a local copy of this buffer that shares its text and history, so that
+    /// undo and redo can be simulated without affecting this buffer.
+    pub(crate) fn fork(&self) -> Buffer {
+        Buffer {
+            snapshot: self.snapshot.clone(),
+            history: self.history.clone(),
+            deferred_ops: OperationQueue::new(),
+            deferred_replicas: Default::default(),
+            lamport_clock: self.lamport_clock,
+            subscriptions: Topic::default(),
+            edit_id_resolvers: Default::default(),
+            wait_for_version_txs: Default::default(),
+        }
+    }
+
+    /// Creates a local copy of this buffer whose history only has the undo and redo stacks,
+    /// along with what undoing and redoing them needs, so that the history can be serialized
+    /// on another thread without copying the rest of it.
+    pub fn fork_local_history(&self) -> Buffer {
+        let history = &self.history;
+        let mut insertion_slices = HashMap::default();
+        for entry in history.undo_stack.iter().chain(&history.redo_stack) {
+            for edit_id in &entry.transaction.edit_ids {
+                if let Some(slices) = history.insertion_slices.get(edit_id) {
+                    insertion_slices.insert(*edit_id, slices.clone());
+                }
+            }
+        }
+
+        Buffer {
+            snapshot: self.snapshot.clone(),
+            history: History {
+                base_text: history.base_text.clone(),
+                operations: history.operations.clone(),
+                insertion_slices,
+                undo_stack: history.undo_stack.clone(),
+                redo_stack: history.redo_stack.clone(),
+                branches: Vec::new(),
+                max_branch_entries: history.max_branch_entries,
+                transaction_depth: history.transaction_depth,
+                group_interval: history.group_interval,
+            },
+            deferred_ops: OperationQueue::new(),
+            deferred_replicas: Default::default(),
+            lamport_clock: self.lamport_clock,
+            subscriptions: Topic::default(),
+            edit_id_resolvers: Default::default(),
+            wait_for_version_txs: Default::default(),
+        }
+    }
+}
@@ -591,6 +591,163 @@ fn test_history() {
     assert_eq!(buffer.text(), "X12cde6");
 }
 
+#[test]
+fn test_serialize_and_restore_history() {
+    let mut buffer = Buffer::new(0, BufferId::new(1).unwrap(), "1234".into());
+    buffer.set_group_interval(Duration::from_secs(0));
+    buffer.edit([(1..1, "abx")]);
+    buffer.edit([(3..4, "yzef")]);
+    buffer.edit([(0..1, ""), (6..7, "cd")]);
+    buffer.undo();
+    assert_eq!(buffer.text(), "1abyzef234");
+
+    let history = buffer.serialize_history(usize::MAX);
+    assert_eq!(history.undo_stack.len(), 2);
+    assert_eq!(history.redo_stack.len(), 1);
+    assert_eq!(
+        buffer.fork_local_history().serialize_history(usize::MAX),
+        history
+    );
+
+    let mut restored = Buffer::new(0, BufferId::new(2).unwrap(), "1abyzef234".into());
+    restored.restore_history(&history).unwrap();
+    assert_eq!(restored.text(), "1abyzef234");
+    restored.redo();
+    assert_eq!(restored.text(), "abyzecd234");
+    restored.undo();
+    restored.undo();
+    assert_eq!(restored.text(), "1abx234");
+    restored.undo();
+    assert_eq!(restored.text(), "1234");
+    assert!(restored.peek_undo_stack().is_none());
+
+    // Only the most recent transactions are kept when the size limit is exceeded.
+    let history = buffer.serialize_history(7);
+    assert_eq!(history.undo_stack.len(), 1);
+    assert!(history.redo_stack.is_empty());
+
+    // History that doesn't match the buffer's text is rejected, leaving the buffer unchanged.
+    let mut mismatched = Buffer::new(0, BufferId::new(3).unwrap(), "1abyzeg234".into());
+    assert!(mismatched
+        .restore_history(&buffer.serialize_history(usize::MAX))
+        .is_err());
+    assert_eq!(mismatched.text(), "1abyzeg234");
+    assert!(mismatched.peek_undo_stack().is_none());
+}
+
+#[test]
+fn test_undo_tree() {
+    let mut buffer = Buffer::new(0, BufferId::new(1).unwrap(), "".into());
+    let mut remote = Buffer::new(1, BufferId::new(1).unwrap(), "".into());
+    let edit = |buffer: &mut Buffer, remote: &mut Buffer, offset: usize, text: &str| {
+        let operation = buffer.edit([(offset..offset, text)]);
+        buffer.finalize_last_transaction();
+        remote.apply_ops([operation]).unwrap();
+        buffer.peek_undo_stack().unwrap().transaction_id()
+    };
+
+    let transaction_1 = edit(&mut buffer, &mut remote, 0, "a");
+    let transaction_2 = edit(&mut buffer, &mut remote, 1, "b");
+    remote
+        .apply_ops(buffer.undo().map(|(_, operation)| operation))
+        .unwrap();
+    let transaction_3 = edit(&mut buffer, &mut remote, 1, "c");
+    assert_eq!(buffer.text(), "ac");
+
+    // Undoing and then editing starts a new branch instead of discarding the undone transaction.
+    let tree = buffer.undo_tree();
+    assert_eq!(tree.current, Some(transaction_3));
+    assert_eq!(
+        tree.children(None)
+            .map(|node| node.transaction_id)
+            .collect::<Vec<_>>(),
+        [transaction_1]
+    );
+    assert_eq!(
+        tree.children(Some(transaction_1))
+            .map(|node| node.transaction_id)
+            .collect::<Vec<_>>(),
+        [transaction_2, transaction_3]
+    );
+    assert!(!tree.node(transaction_2).unwrap().applied);
+    assert_eq!(
+        buffer.transaction_preview(transaction_2).unwrap().edits,
+        [SerializedEdit {
+            range: 1..1,
+            old_text: "".into(),
+            new_text: "b".into(),
+        }]
+    );
+
+    // Remote edits aren't part of the tree, but are preserved when moving between states.
+    let remote_operation = remote.edit([(0..0, "X")]);
+    buffer.apply_ops([remote_operation]).unwrap();
+    assert_eq!(buffer.text(), "Xac");
+    assert_eq!(buffer.undo_tree().nodes.len(), 3);
+
+    // Chronological navigation visits states in the order they were created.
+    for expected_text in ["Xab", "Xa", "X"] {
+        remote.apply_ops(buffer.undo_chronologically()).unwrap();
+        assert_eq!(buffer.text(), expected_text);
+        assert_eq!(remote.text(), expected_text);
+    }
+    assert!(buffer.undo_chronologically().is_empty());
+    for expected_text in ["Xa", "Xab", "Xac"] {
+        remote.apply_ops(buffer.redo_chronologically()).unwrap();
+        assert_eq!(buffer.text(), expected_text);
+        assert_eq!(remote.text(), expected_text);
+    }
+    assert!(buffer.redo_chronologically().is_empty());
+
+    // After navigating, redoing retraces the branch that was left.
+    buffer.navigate_to_transaction(Some(transaction_2));
+    assert_eq!(buffer.text(), "Xab");
+    buffer.navigate_to_transaction(None);
+    assert_eq!(buffer.text(), "X");
+    buffer.redo();
+    buffer.redo();
+    assert_eq!(buffer.text(), "Xab");
+    buffer.undo();
+    buffer.undo();
+    assert_eq!(buffer.text(), "X");
+}
+
+#[test]
+fn test_undo_tree_prunes_abandoned_branches() {
+    let mut buffer = Buffer::new(0, BufferId::new(1).unwrap(), "".into());
+    buffer.history.max_branch_entries = 4;
+    let edit = |buffer: &mut Buffer, text: &str| {
+        buffer.edit([(0..0, text)]);
+        buffer.finalize_last_transaction();
+        buffer.peek_undo_stack().unwrap().transaction_id()
+    };
+
+    // Each round abandons a branch of two transactions.
+    let mut last_branch = None;
+    for _ in 0..3 {
+        let a = edit(&mut buffer, "a");
+        let b = edit(&mut buffer, "b");
+        buffer.undo();
+        buffer.undo();
+        edit(&mut buffer, "c");
+        last_branch = Some((a, b));
+        assert!(buffer.history.branches.len() <= 4);
+    }
+    assert_eq!(buffer.text(), "ccc");
+
+    // The oldest branches were dropped together with the transactions made on top of them.
+    let (a, b) = last_branch.unwrap();
+    let tree = buffer.undo_tree();
+    assert_eq!(tree.nodes.len(), 5);
+    assert!(tree.node(a).is_some());
+    assert!(tree.nodes.iter().all(|node| node
+        .parent
+        .map_or(true, |parent| tree.node(parent).is_some())));
+
+    buffer.navigate_to_transaction(Some(b));
+    assert_eq!(buffer.text(), "bacc");
+}
+
 #[test]
 fn test_finalize_last_transaction() {
     let now = Instant::now();
@@ -5,10 +5,12 @@ pub mod network;
 pub mod operation_queue;
 mod patch;
 mod selection;
+mod serialized_history;
 pub mod subscription;
 #[cfg(test)]
 mod tests;
 mod undo_map;
+mod undo_tree;
 
 pub use anchor::*;
 use anyhow::{anyhow, Context as _, Result};
@@ -23,6 +25,7 @@ use lazy_static::lazy_static;
 use regex::Regex;
 pub use rope::*;
 pub use selection::*;
+pub use serialized_history::*;
 use std::{
     borrow::Cow,
     cmp::{self, Ordering, Reverse},
@@ -39,6 +42,7 @@ pub use subscription::*;
 pub use sum_tree::Bias;
 use sum_tree::{FilterCursor, SumTree, TreeMap};
 use undo_map::UndoMap;
+pub use undo_tree::*;
 use util::ResultExt;
 
 #[cfg(any(test, feature = "test-support"))]
@@ -50,6 +54,9 @@ lazy_static! {
 
 pub type TransactionId = clock::Lamport;
 
+/// How many undone transactions the undo tree keeps on abandoned branches.
+const MAX_BRANCH_ENTRIES: usize = 1024;
+
 pub struct Buffer {
     snapshot: BufferSnapshot,
     history: History,
@@ -107,6 +114,9 @@ pub struct BufferSnapshot {
 #[derive(Clone, Debug)]
 pub struct HistoryEntry {
     transaction: Transaction,
+    /// The transaction that was last applied when this one started, forming a tree of
+    /// local transactions whose root is the buffer's initial state.
+    parent: Option<TransactionId>,
     first_edit_at: Instant,
     last_edit_at: Instant,
     suppress_grouping: bool,
@@ -125,12 +135,18 @@ impl HistoryEntry {
     }
 }
 
+#[derive(Clone)]
 struct History {
     base_text: Rope,
     operations: TreeMap<clock::Lamport, Operation>,
     insertion_slices: HashMap<clock::Lamport, Vec<InsertionSlice>>,
     undo_stack: Vec<HistoryEntry>,
     redo_stack: Vec<HistoryEntry>,
+    /// Undone transactions that are neither on the undo nor the redo stack, because
+    /// new transactions were started after undoing them.
+    branches: Vec<HistoryEntry>,
+    /// How many undone transactions to keep in `branches` before the oldest are dropped.
+    max_branch_entries: usize,
     transaction_depth: usize,
     group_interval: Duration,
 }
@@ -149,6 +165,8 @@ impl History {
             insertion_slices: Default::default(),
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            branches: Vec::new(),
+            max_branch_entries: MAX_BRANCH_ENTRIES,
             transaction_depth: 0,
             // Don't group transactions in tests unless we opt in, because it's a footgun.
             #[cfg(any(test, feature = "test-support"))]
@@ -171,12 +189,14 @@ impl History {
         self.transaction_depth += 1;
         if self.transaction_depth == 1 {
             let id = clock.tick();
+            let parent = self.undo_stack.last().map(|entry| entry.transaction.id);
             self.undo_stack.push(HistoryEntry {
                 transaction: Transaction {
                     id,
                     start,
                     edit_ids: Default::default(),
                 },
+                parent,
                 first_edit_at: now,
                 last_edit_at: now,
                 suppress_grouping: false,
@@ -202,7 +222,8 @@ impl History {
                 self.undo_stack.pop();
                 None
             } else {
-                self.redo_stack.clear();
+                self.branches.append(&mut self.redo_stack);
+                self.prune_branches();
                 let entry = self.undo_stack.last_mut().unwrap();
                 entry.last_edit_at = now;
                 Some(entry)
@@ -259,7 +280,12 @@ impl History {
             }
         }
 
-        self.undo_stack.truncate(new_len);
+        let merged_entries = self.undo_stack.split_off(new_len);
+        if let Some(last_entry_id) = self.undo_stack.last().map(|entry| entry.transaction.id) {
+            for entry in merged_entries {
+                self.reparent(entry.transaction.id, Some(last_entry_id));
+            }
+        }
         self.undo_stack.last().map(|e| e.transaction.id)
     }
 
@@ -272,13 +298,44 @@ impl History {
 
     fn push_transaction(&mut self, transaction: Transaction, now: Instant) {
         assert_eq!(self.transaction_depth, 0);
+        let parent = self.undo_stack.last().map(|entry| entry.transaction.id);
         self.undo_stack.push(HistoryEntry {
             transaction,
+            parent,
             first_edit_at: now,
             last_edit_at: now,
             suppress_grouping: false,
         });
-        self.redo_stack.clear();
+        self.branches.append(&mut self.redo_stack);
+        self.prune_branches();
+    }
+
+    /// Drops the oldest undone branches once there are too many of them, together with
+    /// the transactions that were made on top of them. Halving them at once keeps the
+    /// cost of sorting them rare.
+    fn prune_branches(&mut self) {
+        if self.branches.len() <= self.max_branch_entries {
+            return;
+        }
+
+        // Children are created after their parents, so they sort after them.
+        self.branches
+            .sort_unstable_by_key(|entry| entry.transaction.id);
+        let target_len = self.max_branch_entries / 2;
+        let mut remaining_len = self.branches.len();
+        let mut dropped = HashSet::default();
+        self.branches.retain(|entry| {
+            let parent_dropped = entry
+                .parent
+                .map_or(false, |parent| dropped.contains(&parent));
+            if parent_dropped || remaining_len > target_len {
+                dropped.insert(entry.transaction.id);
+                remaining_len -= 1;
+                false
+            } else {
+                true
+            }
+        });
     }
 
     fn push_undo(&mut self, op_id: clock::Lamport) {
@@ -328,33 +385,59 @@ impl History {
 
     fn forget(&mut self, transaction_id: TransactionId) -> Option<Transaction> {
         assert_eq!(self.transaction_depth, 0);
-        if let Some(entry_ix) = self
+        let entry = if let Some(entry_ix) = self
             .undo_stack
             .iter()
             .rposition(|entry| entry.transaction.id == transaction_id)
         {
-            Some(self.undo_stack.remove(entry_ix).transaction)
+            self.undo_stack.remove(entry_ix)
         } else if let Some(entry_ix) = self
             .redo_stack
             .iter()
             .rposition(|entry| entry.transaction.id == transaction_id)
         {
-            Some(self.redo_stack.remove(entry_ix).transaction)
+            self.redo_stack.remove(entry_ix)
+        } else if let Some(entry_ix) = self
+            .branches
+            .iter()
+            .position(|entry| entry.transaction.id == transaction_id)
+        {
+            self.branches.remove(entry_ix)
         } else {
-            None
+            return None;
+        };
+        self.reparent(transaction_id, entry.parent);
+        Some(entry.transaction)
+    }
+
+    /// Moves the children of the given transaction to a new parent, because the
+    /// transaction is being removed from the history.
+    fn reparent(&mut self, transaction_id: TransactionId, new_parent: Option<TransactionId>) {
+        for entry in self.entries_mut() {
+            if entry.parent == Some(transaction_id) {
+                entry.parent = new_parent;
+            }
         }
     }
 
+    fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.undo_stack
+            .iter()
+            .chain(&self.redo_stack)
+            .chain(&self.branches)
+    }
+
+    fn entries_mut(&mut self) -> impl Iterator<Item = &mut HistoryEntry> {
+        self.undo_stack
+            .iter_mut()
+            .chain(&mut self.redo_stack)
+            .chain(&mut self.branches)
+    }
+
     fn transaction_mut(&mut self, transaction_id: TransactionId) -> Option<&mut Transaction> {
         let entry = self
-            .undo_stack
-            .iter_mut()
-            .rfind(|entry| entry.transaction.id == transaction_id)
-            .or_else(|| {
-                self.redo_stack
-                    .iter_mut()
-                    .rfind(|entry| entry.transaction.id == transaction_id)
-            })?;
+            .entries_mut()
+            .find(|entry| entry.transaction.id == transaction_id)?;
         Some(&mut entry.transaction)
     }
 
@@ -513,8 +596,23 @@ impl Buffer {
     pub fn new(replica_id: u16, remote_id: BufferId, mut base_text: String) -> Buffer {
         let line_ending = LineEnding::detect(&base_text);
         LineEnding::normalize(&mut base_text);
+        Self::new_normalized(
+            replica_id,
+            remote_id,
+            line_ending,
+            Rope::from(base_text.as_ref()),
+        )
+    }
 
-        let history = History::new(Rope::from(base_text.as_ref()));
+    /// Creates a buffer from text whose line endings were already normalized,
+    /// such as a rope that was loaded from disk a chunk at a time.
+    pub fn new_normalized(
+        replica_id: u16,
+        remote_id: BufferId,
+        line_ending: LineEnding,
+        normalized: Rope,
+    ) -> Buffer {
+        let history = History::new(normalized);
         let mut fragments = SumTree::new();
         let mut insertions = SumTree::new();
 
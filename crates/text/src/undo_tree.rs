@@ -0,0 +1,230 @@
+use crate::{Buffer, Operation, SerializedTransaction, TransactionId};
+use collections::HashSet;
+use std::time::Instant;
+use util::ResultExt;
+
+/// A snapshot of a buffer's local transactions, arranged as a tree in which every
+/// transaction is a child of the one that was last applied when it started. Undoing
+/// and then making new edits starts a new branch rather than discarding the undone
+/// transactions, so every state the buffer has been in can be revisited.
+#[derive(Clone, Debug, Default)]
+pub struct UndoTree {
+    /// All transactions in the order they were created, which is also the order of their ids.
+    pub nodes: Vec<UndoTreeNode>,
+    /// The most recently applied transaction, or `None` if the buffer is in its initial state.
+    pub current: Option<TransactionId>,
+}
+
+#[derive(Clone, Debug)]
+pub struct UndoTreeNode {
+    pub transaction_id: TransactionId,
+    pub parent: Option<TransactionId>,
+    pub first_edit_at: Instant,
+    pub last_edit_at: Instant,
+    /// Whether the transaction's edits are currently applied to the buffer.
+    pub applied: bool,
+}
+
+impl UndoTree {
+    pub fn node(&self, transaction_id: TransactionId) -> Option<&UndoTreeNode> {
+        let ix = self
+            .nodes
+            .binary_search_by_key(&transaction_id, |node| node.transaction_id)
+            .ok()?;
+        Some(&self.nodes[ix])
+    }
+
+    /// The children of the given state, from oldest to newest.
+    pub fn children(
+        &self,
+        parent: Option<TransactionId>,
+    ) -> impl DoubleEndedIterator<Item = &UndoTreeNode> + '_ {
+        self.nodes.iter().filter(move |node| node.parent == parent)
+    }
+
+    /// The transactions leading from the initial state to the given one, oldest first.
+    pub fn path_to(&self, transaction_id: Option<TransactionId>) -> Vec<TransactionId> {
+        let mut path = Vec::new();
+        let mut next = transaction_id;
+        while let Some(node) = next.and_then(|id| self.node(id)) {
+            path.push(node.transaction_id);
+            next = node.parent;
+        }
+        path.reverse();
+        path
+    }
+
+    /// The state created immediately before the current one, regardless of branch.
+    pub fn previous_state(&self) -> Option<Option<TransactionId>> {
+        let current = self.current?;
+        Some(
+            self.nodes
+                .iter()
+                .map(|node| node.transaction_id)
+                .filter(|id| *id < current)
+                .max(),
+        )
+    }
+
+    /// The state created immediately after the current one, regardless of branch.
+    pub fn next_state(&self) -> Option<TransactionId> {
+        self.nodes
+            .iter()
+            .map(|node| node.transaction_id)
+            .filter(|id| self.current.map_or(true, |current| *id > current))
+            .min()
+    }
+}
+
+/// Changes whenever a buffer's undo tree does, so that the tree can be cached by
+/// whoever displays it instead of being rebuilt on every edit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UndoTreeVersion {
+    version: clock::Global,
+    stack_lens: [usize; 3],
+}
+
+impl Buffer {
+    pub fn undo_tree_version(&self) -> UndoTreeVersion {
+        // Every change to the tree either applies or undoes an edit, or moves transactions
+        // between the stacks by grouping, forgetting or pruning them.
+        UndoTreeVersion {
+            version: self.version(),
+            stack_lens: [
+                self.history.undo_stack.len(),
+                self.history.redo_stack.len(),
+                self.history.branches.len(),
+            ],
+        }
+    }
+
+    pub fn undo_tree(&self) -> UndoTree {
+        let mut nodes = self
+            .history
+            .entries()
+            .map(|entry| UndoTreeNode {
+                transaction_id: entry.transaction.id,
+                parent: entry.parent,
+                first_edit_at: entry.first_edit_at,
+                last_edit_at: entry.last_edit_at,
+                applied: false,
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|node| node.transaction_id);
+
+        let mut tree = UndoTree {
+            nodes,
+            current: self
+                .history
+                .undo_stack
+                .last()
+                .map(|entry| entry.transaction.id),
+        };
+        let applied = tree
+            .path_to(tree.current)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        for node in &mut tree.nodes {
+            node.applied = applied.contains(&node.transaction_id);
+        }
+        tree
+    }
+
+    /// Undoes and redoes transactions until the buffer is in the state right after the
+    /// given transaction was applied, or in its initial state if `target` is `None`.
+    /// Afterwards, the undo stack contains the path to that state and the redo stack
+    /// continues towards the state the buffer was in before, if it lies further down the
+    /// same branch, or towards the newest state on that branch otherwise.
+    pub fn navigate_to_transaction(&mut self, target: Option<TransactionId>) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        if self.history.transaction_depth > 0 {
+            return operations;
+        }
+
+        let tree = self.undo_tree();
+        if target.map_or(false, |target| tree.node(target).is_none()) || target == tree.current {
+            return operations;
+        }
+        let target_path = tree.path_to(target);
+        let previous_path = tree.path_to(tree.current);
+
+        // Undo the applied transactions that aren't part of the target state.
+        self.history.branches.append(&mut self.history.redo_stack);
+        while let Some(entry) = self.history.undo_stack.last() {
+            if target_path.contains(&entry.transaction.id) {
+                break;
+            }
+            let entry = self.history.undo_stack.pop().unwrap();
+            operations.extend(self.undo_or_redo(entry.transaction.clone()).log_err());
+            self.history.branches.push(entry);
+        }
+
+        // Redo the remaining transactions leading to the target state.
+        for transaction_id in &target_path {
+            if let Some(entry) = self.take_branch(*transaction_id) {
+                operations.extend(self.undo_or_redo(entry.transaction.clone()).log_err());
+                self.history.undo_stack.push(entry);
+            }
+        }
+
+        // Line up the redo stack, so that redoing retraces the most relevant branch.
+        let mut redo_entries = Vec::new();
+        let mut parent = target;
+        loop {
+            let child = tree
+                .children(parent)
+                .find(|node| previous_path.contains(&node.transaction_id))
+                .or_else(|| tree.children(parent).last());
+            let Some(entry) = child.and_then(|child| self.take_branch(child.transaction_id)) else {
+                break;
+            };
+            parent = Some(entry.transaction.id);
+            redo_entries.push(entry);
+        }
+        redo_entries.reverse();
+        self.history.redo_stack = redo_entries;
+        self.history.prune_branches();
+
+        operations
+    }
+
+    /// Moves to the state that was created right before the current one, like Vim's `g-`.
+    pub fn undo_chronologically(&mut self) -> Vec<Operation> {
+        match self.undo_tree().previous_state() {
+            Some(target) => self.navigate_to_transaction(target),
+            None => Vec::new(),
+        }
+    }
+
+    /// Moves to the state that was created right after the current one, like Vim's `g+`.
+    pub fn redo_chronologically(&mut self) -> Vec<Operation> {
+        match self.undo_tree().next_state() {
+            Some(target) => self.navigate_to_transaction(Some(target)),
+            None => Vec::new(),
+        }
+    }
+
+    /// The edits that the given transaction made, relative to the state it was applied to.
+    pub fn transaction_preview(
+        &self,
+        transaction_id: TransactionId,
+    ) -> Option<SerializedTransaction> {
+        let parent = self.undo_tree().node(transaction_id)?.parent;
+        let mut buffer = self.fork();
+        buffer.navigate_to_transaction(parent);
+        let before = buffer.snapshot();
+        buffer.navigate_to_transaction(Some(transaction_id));
+        let after = buffer.snapshot();
+        let edits = after.edits_since::<usize>(&before.version).collect();
+        Some(SerializedTransaction::from_edits(edits, &before, &after))
+    }
+
+    fn take_branch(&mut self, transaction_id: TransactionId) -> Option<crate::HistoryEntry> {
+        let ix = self
+            .history
+            .branches
+            .iter()
+            .position(|entry| entry.transaction.id == transaction_id)?;
+        Some(self.history.branches.remove(ix))
+    }
+}
@@ -93,6 +93,7 @@ pub enum IconName {
     Option,
     PageDown,
     PageUp,
+    Pin,
     Plus,
     Public,
     Quote,
@@ -185,6 +186,7 @@ impl IconName {
             IconName::Option => "icons/option.svg",
             IconName::PageDown => "icons/page_down.svg",
             IconName::PageUp => "icons/page_up.svg",
+            IconName::Pin => "icons/pin.svg",
             IconName::Plus => "icons/plus.svg",
             IconName::Public => "icons/public.svg",
             IconName::Quote => "icons/quote.svg",
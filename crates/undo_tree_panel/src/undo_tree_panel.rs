@@ -0,0 +1,609 @@
+mod undo_tree_panel_settings;
+
+use anyhow::Result;
+use collections::HashMap;
+use db::kvp::KEY_VALUE_STORE;
+use editor::Editor;
+use gpui::{
+    actions, div, px, uniform_list, Action, AppContext, AsyncWindowContext, EventEmitter,
+    FocusHandle, FocusableView, InteractiveElement, IntoElement, Model, ParentElement, Pixels,
+    Render, Styled, Subscription, Task, UniformListScrollHandle, View, ViewContext,
+    VisualContext as _, WeakView, WindowContext,
+};
+use language::{Buffer, SerializedTransaction, TransactionId, UndoTree, UndoTreeVersion};
+use menu::{Confirm, SelectNext, SelectPrev};
+use project::Fs;
+use serde::{Deserialize, Serialize};
+use settings::Settings;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use ui::{h_flex, prelude::*, v_flex, Label, ListItem};
+use undo_tree_panel_settings::UndoTreePanelSettings;
+use util::{ResultExt, TryFutureExt};
+use workspace::{
+    dock::{DockPosition, Panel, PanelEvent},
+    Workspace,
+};
+
+const UNDO_TREE_PANEL_KEY: &'static str = "UndoTreePanel";
+const UPDATE_DEBOUNCE: Duration = Duration::from_millis(100);
+
+actions!(undo_tree_panel, [ToggleFocus]);
+
+pub fn init(cx: &mut AppContext) {
+    UndoTreePanelSettings::register(cx);
+
+    cx.observe_new_views(|workspace: &mut Workspace, _| {
+        workspace.register_action(|workspace, _: &ToggleFocus, cx| {
+            workspace.toggle_panel_focus::<UndoTreePanel>(cx);
+        });
+    })
+    .detach();
+}
+
+/// Shows the undo tree of the active editor's buffer, allowing any state it has
+/// been in to be previewed and restored.
+pub struct UndoTreePanel {
+    fs: Arc<dyn Fs>,
+    focus_handle: FocusHandle,
+    width: Option<Pixels>,
+    active: bool,
+    active_editor: Option<ActiveEditor>,
+    entries: Vec<UndoTreeEntry>,
+    /// The version of the undo tree that `entries` were built from.
+    entries_version: Option<UndoTreeVersion>,
+    pending_update: Task<()>,
+    selected_ix: Option<usize>,
+    preview: Option<SerializedTransaction>,
+    scroll_handle: UniformListScrollHandle,
+    pending_serialization: Task<Option<()>>,
+    _workspace_subscription: Subscription,
+}
+
+struct ActiveEditor {
+    editor: View<Editor>,
+    buffer: Model<Buffer>,
+    _buffer_subscription: Subscription,
+}
+
+/// A state in the undo tree, flattened into a row of the panel.
+struct UndoTreeEntry {
+    /// The transaction that led to this state, or `None` for the buffer's initial state.
+    transaction_id: Option<TransactionId>,
+    /// The position of the state in the order states were created.
+    sequence_number: usize,
+    depth: usize,
+    applied: bool,
+    is_current: bool,
+    edited_at: Option<Instant>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SerializedUndoTreePanel {
+    width: Option<Pixels>,
+}
+
+impl UndoTreePanel {
+    pub fn new(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) -> View<Self> {
+        let fs = workspace.app_state().fs.clone();
+        let workspace_view = cx.view().clone();
+
+        let editor = active_editor(workspace, cx);
+
+        cx.new_view(|cx| {
+            let _workspace_subscription = cx.observe(&workspace_view, |this, workspace, cx| {
+                let editor = active_editor(workspace.read(cx), cx);
+                this.set_active_editor(editor, cx);
+            });
+            let mut this = Self {
+                fs,
+                focus_handle: cx.focus_handle(),
+                width: None,
+                active: false,
+                active_editor: None,
+                entries: Vec::new(),
+                entries_version: None,
+                pending_update: Task::ready(()),
+                selected_ix: None,
+                preview: None,
+                scroll_handle: UniformListScrollHandle::new(),
+                pending_serialization: Task::ready(None),
+                _workspace_subscription,
+            };
+            this.set_active_editor(editor, cx);
+            this
+        })
+    }
+
+    pub fn load(
+        workspace: WeakView<Workspace>,
+        cx: AsyncWindowContext,
+    ) -> Task<Result<View<Self>>> {
+        cx.spawn(|mut cx| async move {
+            let serialized_panel = if let Some(panel) = cx
+                .background_executor()
+                .spawn(async move { KEY_VALUE_STORE.read_kvp(UNDO_TREE_PANEL_KEY) })
+                .await
+                .log_err()
+                .flatten()
+            {
+                Some(serde_json::from_str::<SerializedUndoTreePanel>(&panel)?)
+            } else {
+                None
+            };
+
+            workspace.update(&mut cx, |workspace, cx| {
+                let panel = Self::new(workspace, cx);
+                if let Some(serialized_panel) = serialized_panel {
+                    panel.update(cx, |panel, cx| {
+                        panel.width = serialized_panel.width;
+                        cx.notify();
+                    });
+                }
+                panel
+            })
+        })
+    }
+
+    fn serialize(&mut self, cx: &mut ViewContext<Self>) {
+        let width = self.width;
+        self.pending_serialization = cx.background_executor().spawn(
+            async move {
+                KEY_VALUE_STORE
+                    .write_kvp(
+                        UNDO_TREE_PANEL_KEY.into(),
+                        serde_json::to_string(&SerializedUndoTreePanel { width })?,
+                    )
+                    .await?;
+                anyhow::Ok(())
+            }
+            .log_err(),
+        );
+    }
+
+    fn set_active_editor(&mut self, editor: Option<View<Editor>>, cx: &mut ViewContext<Self>) {
+        if editor.as_ref().map(|editor| editor.entity_id())
+            == self
+                .active_editor
+                .as_ref()
+                .map(|active_editor| active_editor.editor.entity_id())
+        {
+            return;
+        }
+
+        // Only editors for a single buffer have an undo tree; multi-buffers keep their own history.
+        self.active_editor = editor.and_then(|editor| {
+            let buffer = editor.read(cx).buffer().read(cx).as_singleton()?;
+            let _buffer_subscription = cx.subscribe(&buffer, |this, _, event, cx| {
+                if let language::Event::Edited = event {
+                    if this.active {
+                        this.schedule_update(cx);
+                    }
+                }
+            });
+            Some(ActiveEditor {
+                editor,
+                buffer,
+                _buffer_subscription,
+            })
+        });
+        self.selected_ix = None;
+        self.entries_version = None;
+        self.update_entries(cx);
+    }
+
+    /// Updates the entries once edits pause, rather than after every keystroke.
+    fn schedule_update(&mut self, cx: &mut ViewContext<Self>) {
+        self.pending_update = cx.spawn(|this, mut cx| async move {
+            cx.background_executor().timer(UPDATE_DEBOUNCE).await;
+            this.update(&mut cx, |this, cx| this.update_entries(cx))
+                .ok();
+        });
+    }
+
+    fn update_entries(&mut self, cx: &mut ViewContext<Self>) {
+        self.pending_update = Task::ready(());
+        let version = self
+            .active_editor
+            .as_ref()
+            .map(|active_editor| active_editor.buffer.read(cx).undo_tree_version());
+        if version.is_some() && version == self.entries_version {
+            return;
+        }
+
+        let selected_transaction_id = self
+            .selected_ix
+            .and_then(|ix| self.entries.get(ix))
+            .map(|entry| entry.transaction_id);
+
+        self.entries = match &self.active_editor {
+            Some(active_editor) => flatten_undo_tree(&active_editor.buffer.read(cx).undo_tree()),
+            None => Vec::new(),
+        };
+        self.entries_version = version;
+        self.selected_ix = selected_transaction_id.and_then(|transaction_id| {
+            self.entries
+                .iter()
+                .position(|entry| entry.transaction_id == transaction_id)
+        });
+        self.update_preview(cx);
+        cx.notify();
+    }
+
+    fn update_preview(&mut self, cx: &mut ViewContext<Self>) {
+        let transaction_id = self
+            .selected_ix
+            .and_then(|ix| self.entries.get(ix))
+            .and_then(|entry| entry.transaction_id);
+        self.preview = self.active_editor.as_ref().zip(transaction_id).and_then(
+            |(active_editor, transaction_id)| {
+                active_editor
+                    .buffer
+                    .read(cx)
+                    .transaction_preview(transaction_id)
+            },
+        );
+    }
+
+    fn select_next(&mut self, _: &SelectNext, cx: &mut ViewContext<Self>) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let ix = self
+            .selected_ix
+            .map_or(0, |ix| (ix + 1).min(self.entries.len() - 1));
+        self.select_entry(ix, cx);
+    }
+
+    fn select_prev(&mut self, _: &SelectPrev, cx: &mut ViewContext<Self>) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let ix = self
+            .selected_ix
+            .map_or(self.entries.len() - 1, |ix| ix.saturating_sub(1));
+        self.select_entry(ix, cx);
+    }
+
+    fn confirm(&mut self, _: &Confirm, cx: &mut ViewContext<Self>) {
+        if let Some(ix) = self.selected_ix {
+            self.restore_entry(ix, cx);
+        }
+    }
+
+    fn select_entry(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        self.selected_ix = Some(ix);
+        self.scroll_handle.scroll_to_item(ix);
+        self.update_preview(cx);
+        cx.notify();
+    }
+
+    fn restore_entry(&mut self, ix: usize, cx: &mut ViewContext<Self>) {
+        let Some(entry) = self.entries.get(ix) else {
+            return;
+        };
+        let Some(active_editor) = self.active_editor.as_ref() else {
+            return;
+        };
+        let transaction_id = entry.transaction_id;
+        active_editor.editor.update(cx, |editor, cx| {
+            editor.navigate_to_transaction(transaction_id, cx)
+        });
+    }
+
+    fn render_entry(&self, ix: usize, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let entry = &self.entries[ix];
+        let label = if entry.transaction_id.is_some() {
+            format!("#{}", entry.sequence_number)
+        } else {
+            "Original".to_string()
+        };
+        let color = if entry.is_current {
+            Color::Accent
+        } else if entry.applied {
+            Color::Default
+        } else {
+            Color::Muted
+        };
+
+        ListItem::new(ix)
+            .indent_level(entry.depth)
+            .indent_step_size(px(12.))
+            .selected(self.selected_ix == Some(ix))
+            .child(
+                h_flex()
+                    .w_full()
+                    .justify_between()
+                    .child(Label::new(label).color(color))
+                    .children(entry.edited_at.map(|edited_at| {
+                        Label::new(format_elapsed(edited_at.elapsed()))
+                            .size(LabelSize::Small)
+                            .color(Color::Muted)
+                    })),
+            )
+            .on_click(cx.listener(move |this, event: &gpui::ClickEvent, cx| {
+                this.select_entry(ix, cx);
+                if event.up.click_count > 1 {
+                    this.restore_entry(ix, cx);
+                }
+            }))
+    }
+
+    fn render_preview(&self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        let lines = self.preview.iter().flat_map(|preview| {
+            preview.edits.iter().flat_map(|edit| {
+                let deleted = edit
+                    .old_text
+                    .lines()
+                    .map(|line| (format!("- {line}"), Color::Deleted));
+                let inserted = edit
+                    .new_text
+                    .lines()
+                    .map(|line| (format!("+ {line}"), Color::Created));
+                deleted
+                    .chain(inserted)
+                    .chain([(String::new(), Color::Default)])
+            })
+        });
+
+        v_flex()
+            .id("undo-tree-preview")
+            .h(px(200.))
+            .p_2()
+            .overflow_y_scroll()
+            .border_t_1()
+            .border_color(cx.theme().colors().border)
+            .children(
+                lines.map(|(line, color)| Label::new(line).size(LabelSize::Small).color(color)),
+            )
+    }
+}
+
+fn active_editor(workspace: &Workspace, cx: &AppContext) -> Option<View<Editor>> {
+    workspace
+        .active_item(cx)
+        .and_then(|item| item.act_as::<Editor>(cx))
+}
+
+/// Lists every state in the tree, starting from the initial state. The newest branch
+/// of a state continues at the same indentation, while older branches are nested and
+/// listed first.
+fn flatten_undo_tree(tree: &UndoTree) -> Vec<UndoTreeEntry> {
+    let sequence_numbers = tree
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(ix, node)| (node.transaction_id, ix + 1))
+        .collect::<HashMap<_, _>>();
+
+    let mut entries = vec![UndoTreeEntry {
+        transaction_id: None,
+        sequence_number: 0,
+        depth: 0,
+        applied: true,
+        is_current: tree.current.is_none(),
+        edited_at: None,
+    }];
+
+    let mut children = HashMap::<Option<TransactionId>, Vec<TransactionId>>::default();
+    for node in &tree.nodes {
+        children
+            .entry(node.parent)
+            .or_default()
+            .push(node.transaction_id);
+    }
+
+    let push_children = |stack: &mut Vec<(TransactionId, usize)>, parent, depth| {
+        let mut children = children.get(&parent).into_iter().flatten().copied();
+        if let Some(newest) = children.next_back() {
+            stack.push((newest, depth));
+            for older in children.rev() {
+                stack.push((older, depth + 1));
+            }
+        }
+    };
+
+    let mut stack = Vec::new();
+    push_children(&mut stack, None, 0);
+    while let Some((transaction_id, depth)) = stack.pop() {
+        let Some(node) = tree.node(transaction_id) else {
+            continue;
+        };
+        entries.push(UndoTreeEntry {
+            transaction_id: Some(transaction_id),
+            sequence_number: sequence_numbers[&transaction_id],
+            depth,
+            applied: node.applied,
+            is_current: tree.current == Some(transaction_id),
+            edited_at: Some(node.last_edit_at),
+        });
+        push_children(&mut stack, Some(transaction_id), depth);
+    }
+
+    entries
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    if seconds < 5 {
+        "just now".to_string()
+    } else if seconds < 60 {
+        format!("{seconds}s ago")
+    } else if seconds < 60 * 60 {
+        format!("{}m ago", seconds / 60)
+    } else if seconds < 24 * 60 * 60 {
+        format!("{}h ago", seconds / (60 * 60))
+    } else {
+        format!("{}d ago", seconds / (24 * 60 * 60))
+    }
+}
+
+impl Render for UndoTreePanel {
+    fn render(&mut self, cx: &mut ViewContext<Self>) -> impl IntoElement {
+        v_flex()
+            .key_context("UndoTreePanel")
+            .track_focus(&self.focus_handle)
+            .on_action(cx.listener(Self::select_next))
+            .on_action(cx.listener(Self::select_prev))
+            .on_action(cx.listener(Self::confirm))
+            .size_full()
+            .child(
+                h_flex()
+                    .px_2()
+                    .py_1()
+                    // Match the height of the tab bar so they line up.
+                    .h(rems(ui::Tab::CONTAINER_HEIGHT_IN_REMS))
+                    .border_b_1()
+                    .border_color(cx.theme().colors().border)
+                    .child(Label::new("Undo History")),
+            )
+            .map(|this| {
+                if self.active_editor.is_none() {
+                    this.child(div().p_4().child(
+                        Label::new("Open a file to browse its undo history.").color(Color::Muted),
+                    ))
+                } else {
+                    this.child(
+                        uniform_list(
+                            cx.view().clone(),
+                            "undo-tree-entries",
+                            self.entries.len(),
+                            |this, range, cx| range.map(|ix| this.render_entry(ix, cx)).collect(),
+                        )
+                        .flex_grow()
+                        .track_scroll(self.scroll_handle.clone()),
+                    )
+                    .when(self.preview.is_some(), |this| {
+                        this.child(self.render_preview(cx))
+                    })
+                }
+            })
+    }
+}
+
+impl FocusableView for UndoTreePanel {
+    fn focus_handle(&self, _: &AppContext) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl EventEmitter<PanelEvent> for UndoTreePanel {}
+
+impl Panel for UndoTreePanel {
+    fn persistent_name() -> &'static str {
+        "UndoTreePanel"
+    }
+
+    fn position(&self, cx: &WindowContext) -> DockPosition {
+        UndoTreePanelSettings::get_global(cx).dock
+    }
+
+    fn position_is_valid(&self, position: DockPosition) -> bool {
+        matches!(position, DockPosition::Left | DockPosition::Right)
+    }
+
+    fn set_position(&mut self, position: DockPosition, cx: &mut ViewContext<Self>) {
+        settings::update_settings_file::<UndoTreePanelSettings>(
+            self.fs.clone(),
+            cx,
+            move |settings| settings.dock = Some(position),
+        );
+    }
+
+    fn size(&self, cx: &WindowContext) -> Pixels {
+        self.width
+            .unwrap_or_else(|| UndoTreePanelSettings::get_global(cx).default_width)
+    }
+
+    fn set_size(&mut self, size: Option<Pixels>, cx: &mut ViewContext<Self>) {
+        self.width = size;
+        self.serialize(cx);
+        cx.notify();
+    }
+
+    fn set_active(&mut self, active: bool, cx: &mut ViewContext<Self>) {
+        self.active = active;
+        if active {
+            self.update_entries(cx);
+        }
+    }
+
+    fn icon(&self, cx: &WindowContext) -> Option<ui::IconName> {
+        Some(ui::IconName::ArrowCircle).filter(|_| UndoTreePanelSettings::get_global(cx).button)
+    }
+
+    fn icon_tooltip(&self, _: &WindowContext) -> Option<&'static str> {
+        Some("Undo History")
+    }
+
+    fn toggle_action(&self) -> Box<dyn Action> {
+        Box::new(ToggleFocus)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use language::UndoTreeNode;
+
+    #[test]
+    fn test_flatten_undo_tree() {
+        let id = |value| TransactionId {
+            replica_id: 0,
+            value,
+        };
+        let now = Instant::now();
+        let node = |value, parent: Option<u32>, applied| UndoTreeNode {
+            transaction_id: id(value),
+            parent: parent.map(id),
+            first_edit_at: now,
+            last_edit_at: now,
+            applied,
+        };
+        // 1 was edited twice after being applied, first by 2 and 4, which were undone,
+        // and then by 3.
+        let tree = UndoTree {
+            nodes: vec![
+                node(1, None, true),
+                node(2, Some(1), false),
+                node(3, Some(1), true),
+                node(4, Some(2), false),
+            ],
+            current: Some(id(3)),
+        };
+
+        let entries = flatten_undo_tree(&tree);
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (
+                    entry.transaction_id.map(|id| id.value),
+                    entry.sequence_number,
+                    entry.depth,
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (None, 0, 0),
+                (Some(1), 1, 0),
+                (Some(2), 2, 1),
+                (Some(4), 4, 1),
+                (Some(3), 3, 0),
+            ]
+        );
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.applied, entry.is_current))
+                .collect::<Vec<_>>(),
+            [
+                (true, false),
+                (true, false),
+                (false, false),
+                (false, false),
+                (true, true),
+            ]
+        );
+    }
+}
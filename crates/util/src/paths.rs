@@ -10,6 +10,7 @@ lazy_static::lazy_static! {
     pub static ref HOME: PathBuf = dirs::home_dir().expect("failed to determine home directory");
     pub static ref CONFIG_DIR: PathBuf = HOME.join(".config").join("zed");
     pub static ref CONVERSATIONS_DIR: PathBuf = HOME.join(".config/zed/conversations");
+    pub static ref PROMPTS_DIR: PathBuf = HOME.join(".config/zed/prompts");
     pub static ref EMBEDDINGS_DIR: PathBuf = HOME.join(".config/zed/embeddings");
     pub static ref THEMES_DIR: PathBuf = HOME.join(".config/zed/themes");
     pub static ref LOGS_DIR: PathBuf = HOME.join("Library/Logs/Zed");
@@ -19,6 +20,7 @@ lazy_static::lazy_static! {
     pub static ref COPILOT_DIR: PathBuf = HOME.join("Library/Application Support/Zed/copilot");
     pub static ref DEFAULT_PRETTIER_DIR: PathBuf = HOME.join("Library/Application Support/Zed/prettier");
     pub static ref DB_DIR: PathBuf = HOME.join("Library/Application Support/Zed/db");
+    pub static ref LOCAL_HISTORY_DIR: PathBuf = HOME.join("Library/Application Support/Zed/local_history");
     pub static ref CRASHES_DIR: PathBuf = HOME.join("Library/Logs/DiagnosticReports");
     pub static ref CRASHES_RETIRED_DIR: PathBuf = HOME.join("Library/Logs/DiagnosticReports/Retired");
     pub static ref SETTINGS: PathBuf = CONFIG_DIR.join("settings.json");
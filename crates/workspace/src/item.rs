@@ -801,6 +801,14 @@ pub mod test {
     }
 
     impl project::Item for TestProjectItem {
+        fn try_open(
+            _project: &Model<Project>,
+            _path: &ProjectPath,
+            _cx: &mut AppContext,
+        ) -> Option<Task<anyhow::Result<Model<Self>>>> {
+            None
+        }
+
         fn entry_id(&self, _: &AppContext) -> Option<ProjectEntryId> {
             self.entry_id
         }
@@ -3,14 +3,15 @@ use anyhow::{anyhow, Result};
 use call::{ActiveCall, ParticipantLocation};
 use collections::HashMap;
 use gpui::{
-    point, size, AnyView, AnyWeakView, Axis, Bounds, IntoElement, Model, MouseButton, Pixels,
-    Point, View, ViewContext,
+    point, size, AnyElement, AnyView, AnyWeakView, Axis, Bounds, IntoElement, Model, MouseButton,
+    Pixels, Point, View, ViewContext, WindowContext,
 };
 use parking_lot::Mutex;
 use project::Project;
 use serde::Deserialize;
-use std::sync::Arc;
+use std::{rc::Rc, sync::Arc};
 use ui::prelude::*;
+use util::ResultExt;
 
 pub const HANDLE_HITBOX_SIZE: f32 = 4.0;
 const HORIZONTAL_MIN_SIZE: f32 = 80.;
@@ -22,7 +23,7 @@ pub struct PaneGroup {
 }
 
 impl PaneGroup {
-    pub(crate) fn with_root(root: Member) -> Self {
+    pub fn with_root(root: Member) -> Self {
         Self { root }
     }
 
@@ -65,6 +66,42 @@ impl PaneGroup {
         }
     }
 
+    /// Finds the pane adjacent to `active_pane` in the given direction, based on the bounds
+    /// the panes were laid out in when last rendered.
+    pub fn find_pane_in_direction(
+        &self,
+        active_pane: &View<Pane>,
+        direction: SplitDirection,
+        cx: &WindowContext,
+    ) -> Option<&View<Pane>> {
+        let Some(bounding_box) = self.bounding_box_for_pane(active_pane) else {
+            return None;
+        };
+        let cursor = active_pane.read(cx).pixel_position_of_cursor(cx);
+        let center = match cursor {
+            Some(cursor) if bounding_box.contains(&cursor) => cursor,
+            _ => bounding_box.center(),
+        };
+
+        let distance_to_next = HANDLE_HITBOX_SIZE;
+
+        let target = match direction {
+            SplitDirection::Left => {
+                Point::new(bounding_box.left() - distance_to_next.into(), center.y)
+            }
+            SplitDirection::Right => {
+                Point::new(bounding_box.right() + distance_to_next.into(), center.y)
+            }
+            SplitDirection::Up => {
+                Point::new(center.x, bounding_box.top() - distance_to_next.into())
+            }
+            SplitDirection::Down => {
+                Point::new(center.x, bounding_box.bottom() + distance_to_next.into())
+            }
+        };
+        self.pane_at_pixel_position(target)
+    }
+
     /// Returns:
     /// - Ok(true) if it found and removed a pane
     /// - Ok(false) if it found but did not remove the pane
@@ -110,19 +147,36 @@ impl PaneGroup {
         )
     }
 
-    pub(crate) fn panes(&self) -> Vec<&View<Pane>> {
+    /// Renders the panes of a group that is hosted outside of the workspace center, such as
+    /// in a panel. Unlike the center, these panes don't reflect collaborators' locations.
+    pub fn render_panes(
+        &self,
+        active_pane: &View<Pane>,
+        zoomed: Option<&AnyWeakView>,
+        on_resize: Rc<dyn Fn(&mut WindowContext)>,
+        cx: &mut WindowContext,
+    ) -> impl IntoElement {
+        self.root
+            .render_panes(0, active_pane, zoomed, &on_resize, cx)
+    }
+
+    pub fn panes(&self) -> Vec<&View<Pane>> {
         let mut panes = Vec::new();
         self.root.collect_panes(&mut panes);
         panes
     }
 
-    pub(crate) fn first_pane(&self) -> View<Pane> {
+    pub fn first_pane(&self) -> View<Pane> {
         self.root.first_pane()
     }
+
+    pub fn root(&self) -> &Member {
+        &self.root
+    }
 }
 
 #[derive(Clone)]
-pub(crate) enum Member {
+pub enum Member {
     Axis(PaneAxis),
     Pane(View<Pane>),
 }
@@ -296,6 +350,31 @@ impl Member {
         }
     }
 
+    fn render_panes(
+        &self,
+        basis: usize,
+        active_pane: &View<Pane>,
+        zoomed: Option<&AnyWeakView>,
+        on_resize: &Rc<dyn Fn(&mut WindowContext)>,
+        cx: &mut WindowContext,
+    ) -> AnyElement {
+        match self {
+            Member::Pane(pane) => {
+                if zoomed == Some(&pane.downgrade().into()) {
+                    return div().into_any();
+                }
+
+                div()
+                    .relative()
+                    .flex_1()
+                    .size_full()
+                    .child(AnyView::from(pane.clone()).cached())
+                    .into_any()
+            }
+            Member::Axis(axis) => axis.render_panes(basis + 1, active_pane, zoomed, on_resize, cx),
+        }
+    }
+
     fn collect_panes<'a>(&'a self, panes: &mut Vec<&'a View<Pane>>) {
         match self {
             Member::Axis(axis) => {
@@ -309,7 +388,7 @@ impl Member {
 }
 
 #[derive(Clone)]
-pub(crate) struct PaneAxis {
+pub struct PaneAxis {
     pub axis: Axis,
     pub members: Vec<Member>,
     pub flexes: Arc<Mutex<Vec<f32>>>,
@@ -485,12 +564,17 @@ impl PaneAxis {
         debug_assert!(self.members.len() == self.flexes.lock().len());
         let mut active_pane_ix = None;
 
+        let workspace = cx.view().downgrade();
         pane_axis(
             self.axis,
             basis,
             self.flexes.clone(),
             self.bounding_boxes.clone(),
-            cx.view().downgrade(),
+            Rc::new(move |cx| {
+                workspace
+                    .update(cx, |workspace, cx| workspace.schedule_serialize(cx))
+                    .log_err();
+            }),
         )
         .children(self.members.iter().enumerate().map(|(ix, member)| {
             if member.contains(active_pane) {
@@ -512,6 +596,34 @@ impl PaneAxis {
         .with_active_pane(active_pane_ix)
         .into_any_element()
     }
+
+    fn render_panes(
+        &self,
+        basis: usize,
+        active_pane: &View<Pane>,
+        zoomed: Option<&AnyWeakView>,
+        on_resize: &Rc<dyn Fn(&mut WindowContext)>,
+        cx: &mut WindowContext,
+    ) -> AnyElement {
+        debug_assert!(self.members.len() == self.flexes.lock().len());
+        let mut active_pane_ix = None;
+
+        pane_axis(
+            self.axis,
+            basis,
+            self.flexes.clone(),
+            self.bounding_boxes.clone(),
+            on_resize.clone(),
+        )
+        .children(self.members.iter().enumerate().map(|(ix, member)| {
+            if member.contains(active_pane) {
+                active_pane_ix = Some(ix);
+            }
+            member.render_panes((basis + ix) * 10, active_pane, zoomed, on_resize, cx)
+        }))
+        .with_active_pane(active_pane_ix)
+        .into_any_element()
+    }
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
@@ -590,15 +702,12 @@ mod element {
     use gpui::{
         px, relative, Along, AnyElement, Axis, Bounds, CursorStyle, Element, InteractiveBounds,
         IntoElement, MouseDownEvent, MouseMoveEvent, MouseUpEvent, ParentElement, Pixels, Point,
-        Size, Style, WeakView, WindowContext,
+        Size, Style, WindowContext,
     };
     use parking_lot::Mutex;
     use settings::Settings;
     use smallvec::SmallVec;
     use ui::prelude::*;
-    use util::ResultExt;
-
-    use crate::Workspace;
 
     use crate::WorkspaceSettings;
 
@@ -611,7 +720,7 @@ mod element {
         basis: usize,
         flexes: Arc<Mutex<Vec<f32>>>,
         bounding_boxes: Arc<Mutex<Vec<Option<Bounds<Pixels>>>>>,
-        workspace: WeakView<Workspace>,
+        on_resize: Rc<dyn Fn(&mut WindowContext)>,
     ) -> PaneAxisElement {
         PaneAxisElement {
             axis,
@@ -620,7 +729,7 @@ mod element {
             bounding_boxes,
             children: SmallVec::new(),
             active_pane_ix: None,
-            workspace,
+            on_resize,
         }
     }
 
@@ -631,7 +740,8 @@ mod element {
         bounding_boxes: Arc<Mutex<Vec<Option<Bounds<Pixels>>>>>,
         children: SmallVec<[AnyElement; 2]>,
         active_pane_ix: Option<usize>,
-        workspace: WeakView<Workspace>,
+        /// Invoked whenever the user resizes the panes, so their owner can persist the layout.
+        on_resize: Rc<dyn Fn(&mut WindowContext)>,
     }
 
     impl PaneAxisElement {
@@ -647,7 +757,7 @@ mod element {
             axis: Axis,
             child_start: Point<Pixels>,
             container_size: Size<Pixels>,
-            workspace: WeakView<Workspace>,
+            on_resize: &Rc<dyn Fn(&mut WindowContext)>,
             cx: &mut WindowContext,
         ) {
             let min_size = match axis {
@@ -721,9 +831,7 @@ mod element {
                 proposed_current_pixel_change -= current_pixel_change;
             }
 
-            workspace
-                .update(cx, |this, cx| this.schedule_serialize(cx))
-                .log_err();
+            on_resize(cx);
             cx.stop_propagation();
             cx.refresh();
         }
@@ -735,7 +843,7 @@ mod element {
             ix: usize,
             pane_bounds: Bounds<Pixels>,
             axis_bounds: Bounds<Pixels>,
-            workspace: WeakView<Workspace>,
+            on_resize: Rc<dyn Fn(&mut WindowContext)>,
             cx: &mut ElementContext,
         ) {
             let handle_bounds = Bounds {
@@ -771,16 +879,15 @@ mod element {
                 cx.on_mouse_event({
                     let dragged_handle = dragged_handle.clone();
                     let flexes = flexes.clone();
-                    let workspace = workspace.clone();
+                    let on_resize = on_resize.clone();
                     move |e: &MouseDownEvent, phase, cx| {
                         if phase.bubble() && handle_bounds.contains(&e.position) {
                             dragged_handle.replace(Some(ix));
                             if e.click_count >= 2 {
                                 let mut borrow = flexes.lock();
                                 *borrow = vec![1.; borrow.len()];
-                                workspace
-                                    .update(cx, |this, cx| this.schedule_serialize(cx))
-                                    .log_err();
+                                drop(borrow);
+                                on_resize(cx);
 
                                 cx.refresh();
                             }
@@ -789,7 +896,6 @@ mod element {
                     }
                 });
                 cx.on_mouse_event({
-                    let workspace = workspace.clone();
                     move |e: &MouseMoveEvent, phase, cx| {
                         let dragged_handle = dragged_handle.borrow();
 
@@ -801,7 +907,7 @@ mod element {
                                 axis,
                                 pane_bounds.origin,
                                 axis_bounds.size,
-                                workspace.clone(),
+                                &on_resize,
                                 cx,
                             )
                         }
@@ -906,7 +1012,7 @@ mod element {
                                 ix,
                                 child_bounds,
                                 bounds,
-                                self.workspace.clone(),
+                                self.on_resize.clone(),
                                 cx,
                             );
                         }
@@ -26,13 +26,13 @@ use futures::{
     Future, FutureExt, StreamExt,
 };
 use gpui::{
-    actions, canvas, div, impl_actions, point, px, size, Action, AnyElement, AnyModel, AnyView,
-    AnyWeakView, AppContext, AsyncAppContext, AsyncWindowContext, Bounds, Context, Div,
-    DragMoveEvent, Element, ElementContext, Entity, EntityId, EventEmitter, FocusHandle,
-    FocusableView, Global, GlobalPixels, InteractiveElement, IntoElement, KeyContext, LayoutId,
-    ManagedView, Model, ModelContext, ParentElement, PathPromptOptions, Pixels, Point, PromptLevel,
-    Render, SharedString, Size, Styled, Subscription, Task, View, ViewContext, VisualContext,
-    WeakView, WindowBounds, WindowContext, WindowHandle, WindowOptions,
+    actions, canvas, div, impl_actions, point, px, size, Action, AnyElement, AnyView, AnyWeakView,
+    AppContext, AsyncAppContext, AsyncWindowContext, Bounds, Context, Div, DragMoveEvent, Element,
+    ElementContext, Entity, EntityId, EventEmitter, FocusHandle, FocusableView, Global,
+    GlobalPixels, InteractiveElement, IntoElement, KeyContext, LayoutId, ManagedView, Model,
+    ModelContext, ParentElement, PathPromptOptions, Pixels, Point, PromptLevel, Render,
+    SharedString, Size, Styled, Subscription, Task, View, ViewContext, VisualContext, WeakView,
+    WindowBounds, WindowContext, WindowHandle, WindowOptions,
 };
 use item::{FollowableItem, FollowableItemHandle, Item, ItemHandle, ItemSettings, ProjectItem};
 use itertools::Itertools;
@@ -258,19 +258,51 @@ pub fn init(app_state: Arc<AppState>, cx: &mut AppContext) {
     });
 }
 
-#[derive(Clone, Default, Deref, DerefMut)]
-struct ProjectItemBuilders(
-    HashMap<TypeId, fn(Model<Project>, AnyModel, &mut ViewContext<Pane>) -> Box<dyn ItemHandle>>,
-);
+type WorkspaceItemBuilder = Box<dyn FnOnce(&mut ViewContext<Pane>) -> Box<dyn ItemHandle>>;
 
-impl Global for ProjectItemBuilders {}
+type ProjectItemTask = Task<Result<(Option<ProjectEntryId>, WorkspaceItemBuilder)>>;
 
-pub fn register_project_item<I: ProjectItem>(cx: &mut AppContext) {
-    let builders = cx.default_global::<ProjectItemBuilders>();
-    builders.insert(TypeId::of::<I::Item>(), |project, model, cx| {
-        let item = model.downcast::<I::Item>().unwrap();
-        Box::new(cx.new_view(|cx| I::for_project_item(project, item, cx)))
-    });
+type ProjectItemOpener =
+    fn(&Model<Project>, &ProjectPath, &mut WindowContext) -> Option<ProjectItemTask>;
+
+/// Decides which of the items that accept a project path opens it. The path is
+/// offered to the items with the highest priority first, and items with the
+/// same priority must accept different paths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OpenPriority {
+    /// For items that open any file, like the editor.
+    Fallback,
+    /// For items that open files with certain contents, like binary files.
+    Content,
+    /// For items that open certain types of files, like images.
+    FileType,
+}
+
+/// The registered openers, from the highest priority to the lowest.
+#[derive(Clone, Default, Deref, DerefMut)]
+struct ProjectItemOpeners(Vec<(OpenPriority, ProjectItemOpener)>);
+
+impl Global for ProjectItemOpeners {}
+
+/// Registers an item that project paths can be opened in, with the priority
+/// it gets to claim the paths it accepts.
+pub fn register_project_item<I: ProjectItem>(priority: OpenPriority, cx: &mut AppContext) {
+    let open: ProjectItemOpener = |project, project_path, cx| {
+        let project_item = <I::Item as project::Item>::try_open(project, project_path, cx)?;
+        let project = project.clone();
+        Some(cx.spawn(|cx| async move {
+            let project_item = project_item.await?;
+            let project_entry_id =
+                project_item.read_with(&cx, |item, cx| project::Item::entry_id(item, cx))?;
+            let build_item: WorkspaceItemBuilder = Box::new(move |cx: &mut ViewContext<Pane>| {
+                Box::new(cx.new_view(|cx| I::for_project_item(project, project_item, cx)))
+            });
+            Ok((project_entry_id, build_item))
+        }))
+    };
+    let openers = cx.default_global::<ProjectItemOpeners>();
+    let ix = openers.partition_point(|(other_priority, _)| *other_priority >= priority);
+    openers.insert(ix, (priority, open));
 }
 
 type FollowableItemBuilder = fn(
@@ -1212,18 +1244,20 @@ impl Workspace {
 
             Ok(this
                 .update(&mut cx, |this, cx| {
-                    this.save_all_internal(SaveIntent::Close, cx)
+                    this.save_dirty_items(SaveIntent::Close, cx)
                 })?
                 .await?)
         })
     }
 
     fn save_all(&mut self, action: &SaveAll, cx: &mut ViewContext<Self>) {
-        self.save_all_internal(action.save_intent.unwrap_or(SaveIntent::SaveAll), cx)
+        self.save_dirty_items(action.save_intent.unwrap_or(SaveIntent::SaveAll), cx)
             .detach_and_log_err(cx);
     }
 
-    fn save_all_internal(
+    /// Saves every item with unsaved changes, prompting as the intent asks.
+    /// Resolves to false if saving was cancelled.
+    pub fn save_dirty_items(
         &mut self,
         mut save_intent: SaveIntent,
         cx: &mut ViewContext<Self>,
@@ -1947,29 +1981,22 @@ impl Workspace {
         })
     }
 
-    fn load_path(
-        &mut self,
-        path: ProjectPath,
-        cx: &mut WindowContext,
-    ) -> Task<
-        Result<(
-            Option<ProjectEntryId>,
-            impl 'static + Send + FnOnce(&mut ViewContext<Pane>) -> Box<dyn ItemHandle>,
-        )>,
-    > {
+    fn load_path(&mut self, path: ProjectPath, cx: &mut WindowContext) -> ProjectItemTask {
         let project = self.project().clone();
-        let project_item = project.update(cx, |project, cx| project.open_path(path, cx));
+        let openers = cx.default_global::<ProjectItemOpeners>().clone();
         cx.spawn(|mut cx| async move {
-            let (project_entry_id, project_item) = project_item.await?;
-            let build_item = cx.update(|cx| {
-                cx.default_global::<ProjectItemBuilders>()
-                    .get(&project_item.entity_type())
-                    .ok_or_else(|| anyhow!("no item builder for project item"))
-                    .cloned()
-            })??;
-            let build_item =
-                move |cx: &mut ViewContext<Pane>| build_item(project, project_item, cx);
-            Ok((project_entry_id, build_item))
+            // Items that only tell from a file's contents whether they can open it
+            // decline it once loaded, leaving it to the items with a lower priority.
+            for (_, open_project_item) in openers.iter() {
+                let Some(task) = cx.update(|cx| open_project_item(&project, &path, cx))? else {
+                    continue;
+                };
+                match task.await {
+                    Err(error) if error.is::<project::UnsupportedContent>() => continue,
+                    result => return result,
+                }
+            }
+            Err(anyhow!("no item builder for project path {path:?}"))
         })
     }
 
@@ -2168,32 +2195,9 @@ impl Workspace {
         direction: SplitDirection,
         cx: &WindowContext,
     ) -> Option<View<Pane>> {
-        let Some(bounding_box) = self.center.bounding_box_for_pane(&self.active_pane) else {
-            return None;
-        };
-        let cursor = self.active_pane.read(cx).pixel_position_of_cursor(cx);
-        let center = match cursor {
-            Some(cursor) if bounding_box.contains(&cursor) => cursor,
-            _ => bounding_box.center(),
-        };
-
-        let distance_to_next = pane_group::HANDLE_HITBOX_SIZE;
-
-        let target = match direction {
-            SplitDirection::Left => {
-                Point::new(bounding_box.left() - distance_to_next.into(), center.y)
-            }
-            SplitDirection::Right => {
-                Point::new(bounding_box.right() + distance_to_next.into(), center.y)
-            }
-            SplitDirection::Up => {
-                Point::new(center.x, bounding_box.top() - distance_to_next.into())
-            }
-            SplitDirection::Down => {
-                Point::new(center.x, bounding_box.bottom() + distance_to_next.into())
-            }
-        };
-        self.center.pane_at_pixel_position(target).cloned()
+        self.center
+            .find_pane_in_direction(&self.active_pane, direction, cx)
+            .cloned()
     }
 
     pub fn swap_pane_in_direction(
@@ -0,0 +1,308 @@
+use anyhow::{anyhow, Context as _, Result};
+use editor::Editor;
+use gpui::{AppContext, AsyncAppContext, Model, Task};
+use language::Buffer;
+use project::Project;
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use workspace::{AppState, SaveIntent};
+
+/// How often to check whether the language servers are still busy. They run
+/// out of process, so nothing wakes us when they're done.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The arguments of `zed --headless`, which opens a workspace without a
+/// display, runs actions on each of the files it was given, and saves them:
+///
+/// ```text
+/// zed --headless [--action <name>]... [--no-save] [--timeout <seconds>] <path>...
+/// ```
+#[derive(Debug, PartialEq)]
+pub struct HeadlessArgs {
+    pub paths: Vec<PathBuf>,
+    /// The names of the actions to dispatch to each file, in order.
+    pub actions: Vec<String>,
+    pub save: bool,
+    /// How long to wait for the language servers to finish their work
+    /// before carrying on regardless.
+    pub timeout: Duration,
+}
+
+impl HeadlessArgs {
+    /// Parses the arguments following the executable's name, returning `None`
+    /// unless they ask to run headless.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Option<Result<Self>> {
+        let args = args.into_iter().collect::<Vec<_>>();
+        if !args.iter().any(|arg| arg == "--headless") {
+            return None;
+        }
+
+        let mut parsed = HeadlessArgs {
+            paths: Vec::new(),
+            actions: Vec::new(),
+            save: true,
+            timeout: DEFAULT_TIMEOUT,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => {}
+                "--no-save" => parsed.save = false,
+                "--action" => match args.next() {
+                    Some(action) => parsed.actions.push(action),
+                    None => return Some(Err(anyhow!("--action expects an action name"))),
+                },
+                "--timeout" => match args.next().and_then(|seconds| seconds.parse().ok()) {
+                    Some(seconds) => parsed.timeout = Duration::from_secs(seconds),
+                    None => return Some(Err(anyhow!("--timeout expects a number of seconds"))),
+                },
+                _ if arg.starts_with("--") => {
+                    return Some(Err(anyhow!("unknown argument {arg:?}")));
+                }
+                _ => parsed.paths.push(PathBuf::from(arg)),
+            }
+        }
+
+        if parsed.paths.is_empty() {
+            return Some(Err(anyhow!("--headless expects at least one path")));
+        }
+        Some(Ok(parsed))
+    }
+}
+
+/// Opens the given paths in a workspace, dispatches the actions to every file
+/// among them, saves whatever they changed and closes the workspace. The task
+/// resolves once all of it is done, or fails on the first step that did.
+pub fn run(args: HeadlessArgs, app_state: Arc<AppState>, cx: &mut AppContext) -> Task<Result<()>> {
+    let actions = match args
+        .actions
+        .iter()
+        .map(|name| cx.build_action(name, None))
+        .collect::<Result<Vec<_>>>()
+    {
+        Ok(actions) => actions,
+        Err(error) => return Task::ready(Err(error)),
+    };
+
+    cx.spawn(|mut cx| async move {
+        let mut paths = Vec::new();
+        for path in &args.paths {
+            let path = app_state
+                .fs
+                .canonicalize(path)
+                .await
+                .with_context(|| format!("failed to find {path:?}"))?;
+            paths.push(path);
+        }
+
+        let (window, items) = cx
+            .update(|cx| workspace::open_paths(&paths, &app_state, None, cx))?
+            .await?;
+        let mut files = Vec::new();
+        for (path, item) in paths.iter().zip(items) {
+            match item {
+                Some(Ok(item)) => files.push((path, item)),
+                Some(Err(error)) => return Err(error.context(format!("failed to open {path:?}"))),
+                // Directories are added to the workspace without opening anything.
+                None => {}
+            }
+        }
+        let project = window.update(&mut cx, |workspace, _| workspace.project().clone())?;
+
+        for (path, item) in &files {
+            log::info!("running {:?} on {path:?}", args.actions);
+            let buffer = cx.update(|cx| {
+                item.act_as::<Editor>(cx)
+                    .and_then(|editor| editor.read(cx).buffer().read(cx).as_singleton())
+            })?;
+            if let Some(buffer) = &buffer {
+                wait_for_language_servers(&project, buffer, &app_state, args.timeout, &mut cx)
+                    .await?;
+            }
+
+            window.update(&mut cx, |workspace, cx| {
+                workspace.activate_item(item.as_ref(), cx);
+                let focus_handle = item.focus_handle(cx);
+                cx.focus(&focus_handle);
+            })?;
+            for action in &actions {
+                // Let the window lay out the changes of the previous step, so
+                // that the action reaches the file's editor.
+                wait_until_idle(&project, args.timeout, &mut cx).await?;
+                window.update(&mut cx, |_, cx| cx.dispatch_action(action.boxed_clone()))?;
+            }
+            wait_until_idle(&project, args.timeout, &mut cx).await?;
+        }
+
+        if args.save {
+            let saved = window
+                .update(&mut cx, |workspace, cx| {
+                    workspace.save_dirty_items(SaveIntent::SaveAll, cx)
+                })?
+                .await?;
+            if !saved {
+                return Err(anyhow!("saving was cancelled"));
+            }
+        }
+
+        window.update(&mut cx, |_, cx| cx.remove_window())?;
+        Ok(())
+    })
+}
+
+/// Waits until the servers for the buffer's language have started, so that
+/// actions relying on them don't run too early.
+async fn wait_for_language_servers(
+    project: &Model<Project>,
+    buffer: &Model<Buffer>,
+    app_state: &AppState,
+    timeout: Duration,
+    cx: &mut AsyncAppContext,
+) -> Result<()> {
+    let path = buffer.read_with(cx, |buffer, cx| {
+        buffer.file().map(|file| file.full_path(cx))
+    })?;
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let Some(language) = app_state
+        .languages
+        .language_for_file(&path, None)
+        .await
+        .ok()
+    else {
+        return Ok(());
+    };
+    if language.lsp_adapters().is_empty() {
+        return Ok(());
+    }
+
+    let start = Instant::now();
+    loop {
+        let started = project.read_with(cx, |project, cx| {
+            project
+                .language_servers_for_buffer(buffer.read(cx), cx)
+                .next()
+                .is_some()
+        })?;
+        if started {
+            return Ok(());
+        }
+        if start.elapsed() > timeout {
+            log::warn!("no language server started for {path:?} within {timeout:?}");
+            return Ok(());
+        }
+        cx.background_executor().timer(POLL_INTERVAL).await;
+    }
+}
+
+/// Waits until no task is left to run and the project's language servers have
+/// answered every request, so that the edits made in response to their
+/// answers have been applied. Carries on regardless once the timeout passes.
+async fn wait_until_idle(
+    project: &Model<Project>,
+    timeout: Duration,
+    cx: &mut AsyncAppContext,
+) -> Result<()> {
+    let start = Instant::now();
+    loop {
+        cx.background_executor().wait_until_idle().await;
+        if !project.read_with(cx, |project, _| project.has_pending_language_server_work())? {
+            return Ok(());
+        }
+        if start.elapsed() > timeout {
+            log::warn!("language servers are still busy after {timeout:?}, carrying on");
+            return Ok(());
+        }
+        cx.background_executor().timer(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gpui::TestAppContext;
+    use serde_json::json;
+
+    fn parse(args: &str) -> Option<Result<HeadlessArgs>> {
+        HeadlessArgs::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_headless_args() {
+        assert!(parse("src/main.rs").is_none());
+        assert_eq!(
+            parse("--headless --action editor::Format src/a.rs --no-save src/b.rs")
+                .unwrap()
+                .unwrap(),
+            HeadlessArgs {
+                paths: vec!["src/a.rs".into(), "src/b.rs".into()],
+                actions: vec!["editor::Format".into()],
+                save: false,
+                timeout: DEFAULT_TIMEOUT,
+            }
+        );
+        assert_eq!(
+            parse("--headless --timeout 5 .").unwrap().unwrap().timeout,
+            Duration::from_secs(5)
+        );
+        assert!(parse("--headless").unwrap().is_err());
+        assert!(parse("--headless --action").unwrap().is_err());
+        assert!(parse("--headless --timeout soon .").unwrap().is_err());
+        assert!(parse("--headless --verbose .").unwrap().is_err());
+    }
+
+    #[gpui::test]
+    async fn test_run(cx: &mut TestAppContext) {
+        let app_state = cx.update(|cx| {
+            let app_state = AppState::test(cx);
+            theme::init(theme::LoadThemes::JustBase, cx);
+            language::init(cx);
+            Project::init_settings(cx);
+            workspace::init(app_state.clone(), cx);
+            editor::init(cx);
+            app_state
+        });
+        app_state
+            .fs
+            .as_fake()
+            .insert_tree(
+                "/root",
+                json!({
+                    "a.txt": "one\ntwo\n",
+                    "b.txt": "three\n",
+                }),
+            )
+            .await;
+
+        let args = parse(
+            "--headless --action editor::SelectAll --action editor::ConvertToUpperCase /root/a.txt",
+        )
+        .unwrap()
+        .unwrap();
+        cx.update(|cx| run(args, app_state.clone(), cx))
+            .await
+            .unwrap();
+        assert_eq!(
+            app_state.fs.load("/root/a.txt".as_ref()).await.unwrap(),
+            "ONE\nTWO\n"
+        );
+        assert_eq!(
+            app_state.fs.load("/root/b.txt".as_ref()).await.unwrap(),
+            "three\n"
+        );
+        assert!(cx.update(|cx| cx.windows().is_empty()));
+
+        let args = parse("--headless --action editor::Nonexistent /root/a.txt")
+            .unwrap()
+            .unwrap();
+        assert!(cx
+            .update(|cx| run(args, app_state.clone(), cx))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use assistant::{AssistantCompletionProvider, InlineCompletionModel};
+use client::telemetry::Telemetry;
+use collections::HashMap;
+use copilot::Copilot;
+use editor::{CopilotCompletionProvider, Editor, EditorMode};
+use gpui::{AnyWindowHandle, AppContext, Context, Model, ViewContext, WeakView};
+use language::language_settings::{all_language_settings, InlineCompletionProvider};
+use settings::SettingsStore;
+use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+/// Assigns an inline completion provider to every full editor, according to the
+/// `features.inline_completion_provider` setting.
+pub fn init(telemetry: Arc<Telemetry>, cx: &mut AppContext) {
+    let editors: Rc<RefCell<HashMap<WeakView<Editor>, AnyWindowHandle>>> = Rc::default();
+    let assistant_model = cx.new_model(InlineCompletionModel::new);
+
+    cx.observe_new_views({
+        let editors = editors.clone();
+        let assistant_model = assistant_model.clone();
+        let telemetry = telemetry.clone();
+        move |editor: &mut Editor, cx: &mut ViewContext<Editor>| {
+            if editor.mode() != EditorMode::Full {
+                return;
+            }
+
+            editors
+                .borrow_mut()
+                .insert(cx.view().downgrade(), cx.window_handle());
+            assign_inline_completion_provider(editor, &assistant_model, &telemetry, cx);
+        }
+    })
+    .detach();
+
+    let mut provider = all_language_settings(None, cx).inline_completion_provider;
+    cx.observe_global::<SettingsStore>({
+        let editors = editors.clone();
+        let assistant_model = assistant_model.clone();
+        let telemetry = telemetry.clone();
+        move |cx| {
+            let new_provider = all_language_settings(None, cx).inline_completion_provider;
+            if new_provider != provider {
+                provider = new_provider;
+                assign_inline_completion_providers(&editors, &assistant_model, &telemetry, cx);
+            }
+        }
+    })
+    .detach();
+
+    // The assistant's completion provider is rebuilt when its settings change.
+    cx.observe(&assistant_model, move |assistant_model, cx| {
+        if all_language_settings(None, cx).inline_completion_provider
+            == InlineCompletionProvider::Assistant
+        {
+            assign_inline_completion_providers(&editors, &assistant_model, &telemetry, cx);
+        }
+    })
+    .detach();
+}
+
+fn assign_inline_completion_providers(
+    editors: &RefCell<HashMap<WeakView<Editor>, AnyWindowHandle>>,
+    assistant_model: &Model<InlineCompletionModel>,
+    telemetry: &Arc<Telemetry>,
+    cx: &mut AppContext,
+) {
+    let editors_to_update = editors
+        .borrow()
+        .iter()
+        .map(|(editor, window)| (editor.clone(), *window))
+        .collect::<Vec<_>>();
+    for (editor, window) in editors_to_update {
+        let updated = window.update(cx, |_, cx| {
+            editor.update(cx, |editor, cx| {
+                assign_inline_completion_provider(editor, assistant_model, telemetry, cx)
+            })
+        });
+        if !matches!(updated, Ok(Ok(()))) {
+            editors.borrow_mut().remove(&editor);
+        }
+    }
+}
+
+fn assign_inline_completion_provider(
+    editor: &mut Editor,
+    assistant_model: &Model<InlineCompletionModel>,
+    telemetry: &Arc<Telemetry>,
+    cx: &mut ViewContext<Editor>,
+) {
+    match all_language_settings(None, cx).inline_completion_provider {
+        InlineCompletionProvider::None => {
+            editor.set_inline_completion_provider::<CopilotCompletionProvider>(None, cx);
+        }
+        InlineCompletionProvider::Copilot => {
+            let provider = Copilot::global(cx).map(|copilot| {
+                cx.new_model(|_| {
+                    CopilotCompletionProvider::new(copilot).with_telemetry(telemetry.clone())
+                })
+            });
+            editor.set_inline_completion_provider(provider, cx);
+        }
+        InlineCompletionProvider::Assistant => {
+            let provider = assistant_model.read(cx).completion_provider().map(
+                |(completion_provider, model)| {
+                    cx.new_model(|_| AssistantCompletionProvider::new(completion_provider, model))
+                },
+            );
+            editor.set_inline_completion_provider(provider, cx);
+        }
+    }
+}
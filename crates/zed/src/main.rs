@@ -10,7 +10,7 @@ use collab_ui::channel_view::ChannelView;
 use db::kvp::KEY_VALUE_STORE;
 use editor::Editor;
 use env_logger::Builder;
-use fs::RealFs;
+use fs::{local_history::LocalHistory, RealFs};
 use fsevent::StreamFlags;
 use futures::StreamExt;
 use gpui::{App, AppContext, AsyncAppContext, Context, SemanticVersion, Task};
@@ -37,7 +37,7 @@ use std::{
     panic,
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicBool, AtomicU32, Ordering},
         Arc,
     },
     thread,
@@ -55,7 +55,9 @@ use welcome::{show_welcome_view, BaseKeymap, FIRST_OPEN};
 use workspace::{AppState, WorkspaceStore};
 use zed::{
     app_menus, build_window_options, ensure_only_instance, handle_cli_connection,
-    handle_keymap_file_changes, initialize_workspace, languages, IsOnlyInstance, OpenListener,
+    handle_keymap_file_changes,
+    headless::{self, HeadlessArgs},
+    initialize_workspace, inline_completion_registry, languages, IsOnlyInstance, OpenListener,
     OpenRequest,
 };
 
@@ -69,12 +71,33 @@ fn main() {
     init_paths();
     init_logger();
 
-    if ensure_only_instance() != IsOnlyInstance::Yes {
+    let headless_args = match HeadlessArgs::parse(env::args().skip(1)).transpose() {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
+
+    // Headless runs don't talk to other instances, so that scripts can run
+    // while Zed is open.
+    if headless_args.is_none() && ensure_only_instance() != IsOnlyInstance::Yes {
         return;
     }
 
     log::info!("========== starting zed ==========");
-    let app = App::new().with_assets(Assets);
+    let app = if headless_args.is_some() {
+        App::headless()
+    } else {
+        match App::try_new() {
+            Ok(app) => app,
+            Err(error) => {
+                eprintln!("{error:#}");
+                std::process::exit(1);
+            }
+        }
+    }
+    .with_assets(Assets);
 
     let (installation_id, existing_installation_id_found) = app
         .background_executor()
@@ -84,7 +107,10 @@ fn main() {
     let session_id = Uuid::new_v4().to_string();
     init_panic_hook(&app, installation_id.clone(), session_id.clone());
 
-    let fs = Arc::new(RealFs);
+    let fs = Arc::new(RealFs::with_local_history(LocalHistory::new(
+        paths::LOCAL_HISTORY_DIR.clone(),
+        Default::default(),
+    )));
     let user_settings_file_rx = watch_config_file(
         &app.background_executor(),
         fs.clone(),
@@ -120,6 +146,8 @@ fn main() {
         }
     });
 
+    let headless_failed = Arc::new(AtomicBool::new(false));
+    let headless_failed_in_app = headless_failed.clone();
     app.run(move |cx| {
         release_channel::init(env!("CARGO_PKG_VERSION"), cx);
         if let Some(build_sha) = option_env!("ZED_COMMIT_SHA") {
@@ -171,6 +199,7 @@ fn main() {
             cx,
         );
         assistant::init(cx);
+        inline_completion_registry::init(client.telemetry().clone(), cx);
 
         load_user_themes_in_background(fs.clone(), cx);
         watch_themes(fs.clone(), cx);
@@ -239,6 +268,8 @@ fn main() {
         semantic_index::init(fs.clone(), http.clone(), languages.clone(), cx);
         vim::init(cx);
         terminal_view::init(cx);
+        undo_tree_panel::init(cx);
+        local_history::init(fs.clone(), cx);
 
         journal::init(app_state.clone(), cx);
         language_selector::init(cx);
@@ -249,11 +280,27 @@ fn main() {
         collab_ui::init(&app_state, cx);
         feedback::init(cx);
         markdown_preview::init(cx);
+        image_viewer::init(cx);
+        hex_editor::init(cx);
+        log_viewer::init(cx);
         welcome::init(cx);
 
         cx.set_menus(app_menus());
         initialize_workspace(app_state.clone(), cx);
 
+        if let Some(args) = headless_args {
+            let run = headless::run(args, app_state.clone(), cx);
+            cx.spawn(|cx| async move {
+                if let Err(error) = run.await {
+                    eprintln!("{error:?}");
+                    headless_failed_in_app.store(true, Ordering::SeqCst);
+                }
+                cx.update(|cx| cx.quit()).log_err();
+            })
+            .detach();
+            return;
+        }
+
         if stdout_is_a_pty() {
             upload_panics_and_crashes(http.clone(), cx);
             cx.activate(true);
@@ -400,6 +447,10 @@ fn main() {
                 .detach_and_log_err(cx);
         }
     });
+
+    if headless_failed.load(Ordering::SeqCst) {
+        std::process::exit(1);
+    }
 }
 
 async fn authenticate(client: Arc<Client>, cx: &AsyncAppContext) -> Result<()> {
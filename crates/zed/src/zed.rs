@@ -1,4 +1,6 @@
 mod app_menus;
+pub mod headless;
+pub mod inline_completion_registry;
 pub mod languages;
 mod only_instance;
 mod open_listener;
@@ -6,7 +8,6 @@ mod open_listener;
 pub use app_menus::*;
 use assistant::AssistantPanel;
 use breadcrumbs::Breadcrumbs;
-use collections::VecDeque;
 use editor::{Editor, MultiBuffer};
 use gpui::{
     actions, point, px, AppContext, Context, FocusableView, PromptLevel, TitlebarOptions, View,
@@ -18,6 +19,8 @@ pub use open_listener::*;
 use anyhow::{anyhow, Context as _};
 use assets::Assets;
 use futures::{channel::mpsc, select_biased, StreamExt};
+use log_viewer::LogFileOptions;
+use project::project_settings::ProjectSettings;
 use project_panel::ProjectPanel;
 use quick_action_bar::QuickActionBar;
 use release_channel::{AppCommitSha, ReleaseChannel};
@@ -26,6 +29,7 @@ use search::project_search::ProjectSearchBar;
 use settings::{initial_local_settings_content, KeymapFile, Settings, SettingsStore};
 use std::{borrow::Cow, ops::Deref, path::Path, sync::Arc};
 use terminal_view::terminal_panel::{self, TerminalPanel};
+use undo_tree_panel::UndoTreePanel;
 use util::{
     asset_str,
     paths::{self, LOCAL_SETTINGS_RELATIVE_PATH},
@@ -162,6 +166,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 workspace_handle.clone(),
                 cx.clone(),
             );
+            let undo_tree_panel = UndoTreePanel::load(workspace_handle.clone(), cx.clone());
             let (
                 project_panel,
                 terminal_panel,
@@ -169,6 +174,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 channels_panel,
                 chat_panel,
                 notification_panel,
+                undo_tree_panel,
             ) = futures::try_join!(
                 project_panel,
                 terminal_panel,
@@ -176,6 +182,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 channels_panel,
                 chat_panel,
                 notification_panel,
+                undo_tree_panel,
             )?;
 
             workspace_handle.update(&mut cx, |workspace, cx| {
@@ -185,6 +192,7 @@ pub fn initialize_workspace(app_state: Arc<AppState>, cx: &mut AppContext) {
                 workspace.add_panel(channels_panel, cx);
                 workspace.add_panel(chat_panel, cx);
                 workspace.add_panel(notification_panel, cx);
+                workspace.add_panel(undo_tree_panel, cx);
                 cx.focus_self();
             })
         })
@@ -364,6 +372,9 @@ fn initialize_pane(workspace: &mut Workspace, pane: &View<Pane>, cx: &mut ViewCo
             let syntax_tree_item =
                 cx.new_view(|_| language_tools::SyntaxTreeToolbarItemView::new());
             toolbar.add_item(syntax_tree_item, cx);
+            // Added after the buffer search bar, which takes the secondary row while it's open.
+            let large_file_banner = cx.new_view(|_| editor::LargeFileBanner::new());
+            toolbar.add_item(large_file_banner, cx);
         })
     });
 }
@@ -442,50 +453,11 @@ fn open_log_file(workspace: &mut Workspace, cx: &mut ViewContext<Workspace>) {
     const MAX_LINES: usize = 1000;
     workspace
         .with_local_workspace(cx, move |workspace, cx| {
-            let fs = workspace.app_state().fs.clone();
-            cx.spawn(|workspace, mut cx| async move {
-                let (old_log, new_log) =
-                    futures::join!(fs.load(&paths::OLD_LOG), fs.load(&paths::LOG));
-
-                let mut lines = VecDeque::with_capacity(MAX_LINES);
-                for line in old_log
-                    .iter()
-                    .flat_map(|log| log.lines())
-                    .chain(new_log.iter().flat_map(|log| log.lines()))
-                {
-                    if lines.len() == MAX_LINES {
-                        lines.pop_front();
-                    }
-                    lines.push_back(line);
-                }
-                let log = lines
-                    .into_iter()
-                    .flat_map(|line| [line, "\n"])
-                    .collect::<String>();
-
-                workspace
-                    .update(&mut cx, |workspace, cx| {
-                        let project = workspace.project().clone();
-                        let buffer = project
-                            .update(cx, |project, cx| project.create_buffer("", None, cx))
-                            .expect("creating buffers on a local workspace always succeeds");
-                        buffer.update(cx, |buffer, cx| buffer.edit([(0..0, log)], None, cx));
-
-                        let buffer = cx.new_model(|cx| {
-                            MultiBuffer::singleton(buffer, cx).with_title("Log".into())
-                        });
-                        workspace.add_item(
-                            Box::new(
-                                cx.new_view(|cx| {
-                                    Editor::for_multibuffer(buffer, Some(project), cx)
-                                }),
-                            ),
-                            cx,
-                        );
-                    })
-                    .log_err();
-            })
-            .detach();
+            let options = LogFileOptions::default()
+                .with_previous_log(paths::OLD_LOG.clone())
+                .with_max_lines(MAX_LINES)
+                .with_max_len(ProjectSettings::get_global(cx).large_file_threshold());
+            log_viewer::open_log_file(workspace, paths::LOG.clone(), options, cx);
         })
         .detach();
 }
@@ -2734,6 +2706,7 @@ mod tests {
             collab_ui::init(&app_state, cx);
             project_panel::init((), cx);
             terminal_view::init(cx);
+            undo_tree_panel::init(cx);
             assistant::init(cx);
             initialize_workspace(app_state.clone(), cx);
             app_state